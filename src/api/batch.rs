@@ -0,0 +1,290 @@
+use axum::{
+    extract::rejection::JsonRejection,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use validator::Validate;
+
+use super::{generate_reply, ApiError, AppState, LLMProvider, TokenInfo};
+use crate::personality::PersonalityProfile;
+
+const MAX_BATCH_SIZE: usize = 50;
+const MAX_MESSAGE_LENGTH: usize = 1000;
+const BATCH_CONCURRENCY: usize = 4;
+const JOB_RETENTION_MINUTES: i64 = 60;
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Deserialize, Validate)]
+pub struct BatchChatRequest {
+    messages: Vec<String>,
+    /// Character to answer as; defaults to the active character
+    #[validate(length(min = 1, max = 100))]
+    character: Option<String>,
+    #[serde(default)]
+    provider: LLMProvider,
+    #[serde(default)]
+    use_knowledge: bool,
+    /// Return a job id immediately and run the batch in the background
+    #[serde(default, rename = "async")]
+    run_async: bool,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize, Clone)]
+pub struct BatchChatResult {
+    message: String,
+    response: Option<String>,
+    tokens: Option<TokenInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<BatchItemError>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize, Clone)]
+pub struct BatchItemError {
+    #[cfg_attr(feature = "openapi", schema(value_type = String))]
+    code: &'static str,
+    message: String,
+}
+
+impl BatchChatResult {
+    fn new(message: String, outcome: Result<super::ChatResponse, ApiError>) -> Self {
+        match outcome {
+            Ok(reply) => Self {
+                message,
+                response: Some(reply.response),
+                tokens: Some(reply.tokens),
+                error: None,
+            },
+            Err(e) => Self {
+                message,
+                response: None,
+                tokens: None,
+                error: Some(BatchItemError { code: e.code(), message: e.public_message() }),
+            },
+        }
+    }
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize, Clone)]
+pub struct BatchJob {
+    id: String,
+    status: JobStatus,
+    total: usize,
+    completed: usize,
+    created_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<Vec<BatchChatResult>>,
+}
+
+/// In-memory store for `async` batch jobs. Finished jobs are dropped after an hour.
+#[derive(Clone, Default)]
+pub struct JobStore {
+    jobs: Arc<RwLock<HashMap<String, BatchJob>>>,
+}
+
+impl JobStore {
+    async fn create(&self, total: usize) -> BatchJob {
+        let job = BatchJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            status: JobStatus::Running,
+            total,
+            completed: 0,
+            created_at: Utc::now(),
+            finished_at: None,
+            results: None,
+        };
+
+        let mut jobs = self.jobs.write().await;
+        let cutoff = Utc::now() - chrono::Duration::minutes(JOB_RETENTION_MINUTES);
+        jobs.retain(|_, job| job.finished_at.is_none_or(|finished| finished > cutoff));
+        jobs.insert(job.id.clone(), job.clone());
+        job
+    }
+
+    async fn get(&self, id: &str) -> Option<BatchJob> {
+        self.jobs.read().await.get(id).cloned()
+    }
+
+    async fn set_progress(&self, id: &str, completed: usize) {
+        if let Some(job) = self.jobs.write().await.get_mut(id) {
+            job.completed = completed;
+        }
+    }
+
+    async fn finish(&self, id: &str, results: Vec<BatchChatResult>) {
+        if let Some(job) = self.jobs.write().await.get_mut(id) {
+            job.status = JobStatus::Completed;
+            job.completed = results.len();
+            job.finished_at = Some(Utc::now());
+            job.results = Some(results);
+        }
+    }
+}
+
+fn validate_messages(messages: &[String]) -> Result<(), ApiError> {
+    if messages.is_empty() || messages.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::validation(format!(
+            "A batch must contain between 1 and {} messages, got {}", MAX_BATCH_SIZE, messages.len()
+        )));
+    }
+
+    let invalid: Vec<usize> = messages.iter()
+        .enumerate()
+        .filter(|(_, m)| m.trim().is_empty() || m.chars().count() > MAX_MESSAGE_LENGTH)
+        .map(|(i, _)| i)
+        .collect();
+    if !invalid.is_empty() {
+        return Err(ApiError::ValidationFailed(
+            format!("Messages must be 1-{} characters long", MAX_MESSAGE_LENGTH),
+            Some(serde_json::json!({ "invalid_indices": invalid })),
+        ));
+    }
+    Ok(())
+}
+
+/// Runs every message with bounded concurrency, returning results in input order.
+/// Batch exchanges are not written to the conversation log or memory.
+async fn run_batch(
+    state: AppState,
+    personality: PersonalityProfile,
+    messages: Vec<String>,
+    provider: LLMProvider,
+    use_knowledge: bool,
+    job_id: Option<String>,
+) -> Vec<BatchChatResult> {
+    let total = messages.len();
+    let (state_ref, personality, provider) = (&state, &personality, &provider);
+
+    let mut replies = futures::stream::iter(messages.into_iter().map(|message| async move {
+        let outcome = generate_reply(state_ref, personality, &message, provider, use_knowledge, false).await;
+        BatchChatResult::new(message, outcome)
+    }))
+    .buffered(BATCH_CONCURRENCY);
+
+    let mut results = Vec::with_capacity(total);
+    while let Some(result) = replies.next().await {
+        results.push(result);
+        if let Some(id) = &job_id {
+            state.jobs.set_progress(id, results.len()).await;
+        }
+    }
+    results
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/chat/batch",
+    request_body = BatchChatRequest,
+    responses(
+        (status = 200, description = "Results in input order", body = [BatchChatResult]),
+        (status = 202, description = "Batch accepted; poll /jobs/{id}", body = BatchJob),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 404, description = "Not found", body = ApiErrorBody)
+    )
+))]
+pub(super) async fn batch_chat_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<BatchChatRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    let Json(request) = payload?;
+    request.validate()?;
+    validate_messages(&request.messages)?;
+
+    let personality = match &request.character {
+        Some(name) => state.characters.load(name).await?,
+        None => state.personality.read().await.clone(),
+    };
+    println!("Running batch of {} messages as character: {}", request.messages.len(), personality.name);
+
+    if !request.run_async {
+        let results = run_batch(
+            state, personality, request.messages, request.provider, request.use_knowledge, None,
+        ).await;
+        return Ok(Json(results).into_response());
+    }
+
+    let job = state.jobs.create(request.messages.len()).await;
+    let job_id = job.id.clone();
+    tokio::spawn(async move {
+        let jobs = state.jobs.clone();
+        let results = run_batch(
+            state, personality, request.messages, request.provider, request.use_knowledge, Some(job_id.clone()),
+        ).await;
+        jobs.finish(&job_id, results).await;
+    });
+
+    Ok((StatusCode::ACCEPTED, Json(job)).into_response())
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    params(("id" = String, Path, description = "Job id returned by /chat/batch")),
+    responses(
+        (status = 200, description = "Job status, with results once completed", body = BatchJob),
+        (status = 404, description = "Not found", body = ApiErrorBody)
+    )
+))]
+pub(super) async fn get_job_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<BatchJob>, ApiError> {
+    state.jobs.get(&id).await
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Job not found: {}", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_messages_limits() {
+        assert!(validate_messages(&["hello".to_string()]).is_ok());
+        assert!(validate_messages(&[]).is_err());
+        assert!(validate_messages(&vec!["hi".to_string(); MAX_BATCH_SIZE + 1]).is_err());
+
+        match validate_messages(&["ok".to_string(), "  ".to_string()]) {
+            Err(ApiError::ValidationFailed(_, Some(details))) => {
+                assert_eq!(details["invalid_indices"], serde_json::json!([1]));
+            }
+            _ => panic!("expected validation failure with details"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_store_tracks_progress() {
+        let store = JobStore::default();
+        let job = store.create(2).await;
+        assert_eq!(job.status, JobStatus::Running);
+
+        store.set_progress(&job.id, 1).await;
+        assert_eq!(store.get(&job.id).await.unwrap().completed, 1);
+
+        store.finish(&job.id, Vec::new()).await;
+        let finished = store.get(&job.id).await.unwrap();
+        assert_eq!(finished.status, JobStatus::Completed);
+        assert!(finished.results.is_some());
+        assert!(store.get("missing").await.is_none());
+    }
+}
@@ -0,0 +1,215 @@
+use axum::{
+    extract::rejection::JsonRejection,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+/// Errors returned by API handlers. Each variant maps to a stable, machine-readable `code`.
+#[derive(Error, Debug)]
+pub enum ApiError {
+    #[error("{0}")]
+    ProviderUnavailable(String),
+
+    #[error("{0}")]
+    ValidationFailed(String, Option<serde_json::Value>),
+
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("{0}")]
+    RateLimited(String),
+
+    #[error("{0}")]
+    PayloadTooLarge(String),
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+/// JSON body sent for every error response.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Serialize)]
+pub struct ApiErrorBody {
+    /// One of `provider_unavailable` (503), `validation_failed` (400), `not_found` (404),
+    /// `conflict` (409), `rate_limited` (429), `payload_too_large` (413) or `internal` (500).
+    #[cfg_attr(feature = "openapi", schema(value_type = String, example = "validation_failed"))]
+    pub code: &'static str,
+    pub message: String,
+    /// Also written to the server log, for matching reports to log lines.
+    pub request_id: String,
+    /// Per-field errors for `validation_failed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
+    pub details: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn validation(message: impl Into<String>) -> Self {
+        ApiError::ValidationFailed(message.into(), None)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::ProviderUnavailable(_) => "provider_unavailable",
+            ApiError::ValidationFailed(..) => "validation_failed",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::RateLimited(_) => "rate_limited",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::Internal(_) => "internal",
+        }
+    }
+
+    /// The error message with any configured secrets masked.
+    pub fn public_message(&self) -> String {
+        redact_secrets(&self.to_string())
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::ProviderUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::ValidationFailed(..) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let message = self.public_message();
+        eprintln!("[{}] {}: {}", request_id, self.code(), message);
+
+        let status = self.status();
+        let code = self.code();
+        let details = match self {
+            ApiError::ValidationFailed(_, details) => details,
+            _ => None,
+        };
+
+        (status, Json(ApiErrorBody { code, message, request_id, details })).into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError::validation(format!("Invalid JSON body: {}", rejection.body_text()))
+    }
+}
+
+impl From<validator::ValidationErrors> for ApiError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        let details = serde_json::to_value(&errors).ok();
+        ApiError::ValidationFailed("Request validation failed".to_string(), details)
+    }
+}
+
+impl From<crate::database::DatabaseError> for ApiError {
+    fn from(err: crate::database::DatabaseError) -> Self {
+        ApiError::Internal(format!("Database error: {}", err))
+    }
+}
+
+impl From<crate::AppError> for ApiError {
+    fn from(err: crate::AppError) -> Self {
+        use crate::AppError;
+        match err {
+            AppError::CommandError(err) => err.into(),
+            AppError::DatabaseError(err) => err.into(),
+            AppError::ProviderError(message) => ApiError::ProviderUnavailable(message),
+            AppError::TimeoutError => ApiError::ProviderUnavailable(err.to_string()),
+            AppError::MemoryError(_) | AppError::ApiError(_) => ApiError::Internal(err.to_string()),
+        }
+    }
+}
+
+impl From<crate::llm::context::ContextError> for ApiError {
+    fn from(err: crate::llm::context::ContextError) -> Self {
+        crate::commands::CommandError::from(err).into()
+    }
+}
+
+impl From<crate::commands::CommandError> for ApiError {
+    fn from(err: crate::commands::CommandError) -> Self {
+        use crate::commands::CommandError;
+        match err {
+            CommandError::Usage(message) => ApiError::validation(message),
+            CommandError::Provider(message) => ApiError::ProviderUnavailable(message),
+            CommandError::NotFound(message) => ApiError::NotFound(message),
+            CommandError::Io(message) | CommandError::VectorDb(message) | CommandError::Interrupted(message) => ApiError::Internal(message),
+        }
+    }
+}
+
+/// Masks any configured API key or credential that leaked into an error message.
+fn redact_secrets(message: &str) -> String {
+    let mut redacted = message.to_string();
+    for (name, value) in std::env::vars() {
+        let is_secret = name.ends_with("_API_KEY")
+            || name.ends_with("_TOKEN")
+            || name.ends_with("_PASSWORD")
+            || name.ends_with("_SECRET")
+            || name.ends_with("_COOKIE_STRING");
+        if is_secret && value.len() >= 8 && redacted.contains(&value) {
+            redacted = redacted.replace(&value, "[REDACTED]");
+        }
+    }
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_configured_secrets() {
+        std::env::set_var("APIERROR_TEST_API_KEY", "sk-test-1234567890");
+        let message = redact_secrets("request failed: invalid key sk-test-1234567890");
+        assert_eq!(message, "request failed: invalid key [REDACTED]");
+        std::env::remove_var("APIERROR_TEST_API_KEY");
+    }
+
+    #[test]
+    fn test_codes_and_statuses() {
+        let err = ApiError::RateLimited("slow down".to_string());
+        assert_eq!(err.code(), "rate_limited");
+        assert_eq!(err.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(ApiError::validation("bad").status(), StatusCode::BAD_REQUEST);
+        let err = ApiError::Conflict("Character already exists: pirate".to_string());
+        assert_eq!(err.code(), "conflict");
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_command_errors_map_to_statuses() {
+        use crate::commands::CommandError;
+        let status = |err: CommandError| ApiError::from(err).status();
+        assert_eq!(status(CommandError::Usage("Usage: kb get <key>".to_string())), StatusCode::BAD_REQUEST);
+        assert_eq!(status(CommandError::Provider("no key".to_string())), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(CommandError::NotFound("no such entry".to_string())), StatusCode::NOT_FOUND);
+        assert_eq!(status(CommandError::VectorDb("qdrant down".to_string())), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(status(CommandError::Interrupted("Stopped".to_string())), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_app_errors_map_to_statuses() {
+        use crate::AppError;
+        let status = |err: AppError| ApiError::from(err).status();
+        assert_eq!(status(AppError::ProviderError("all providers failed".to_string())), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(AppError::TimeoutError), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(crate::commands::CommandError::Usage("bad".to_string()).into()), StatusCode::BAD_REQUEST);
+        let err = ApiError::from(AppError::from(crate::database::VectorDBError::Connection("refused".to_string())));
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(err.to_string().contains("refused"));
+    }
+}
@@ -0,0 +1,261 @@
+use axum::{
+    extract::rejection::JsonRejection,
+    extract::{FromRef, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use validator::Validate;
+
+use super::{ApiError, AppState};
+use crate::food::api::spoonacular::{Recipe, RecipeFilters, SpoonacularClient, QUOTA_EXCEEDED_MESSAGE};
+use crate::food::api::usda::{FoodNutrition, UsdaClient};
+
+/// Food API clients for the keys that are configured.
+#[derive(Clone, Default)]
+pub struct FoodClients {
+    spoonacular: Option<Arc<SpoonacularClient>>,
+    usda: Option<Arc<UsdaClient>>,
+}
+
+impl FoodClients {
+    /// Builds a client for each of `SPOONACULAR_API_KEY` and `USDA_API_KEY` that is set.
+    pub fn from_env() -> Self {
+        let key = |name: &str| std::env::var(name).ok().filter(|k| !k.is_empty());
+        Self {
+            spoonacular: key("SPOONACULAR_API_KEY").map(|k| Arc::new(SpoonacularClient::new(k))),
+            usda: key("USDA_API_KEY").map(|k| Arc::new(UsdaClient::with_api_key(k))),
+        }
+    }
+}
+
+impl FromRef<AppState> for FoodClients {
+    fn from_ref(state: &AppState) -> Self {
+        state.food.clone()
+    }
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Deserialize, Validate)]
+pub struct NutritionRequest {
+    #[validate(length(min = 1, max = 200))]
+    item: String,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Deserialize, Validate)]
+pub struct RecipeRequest {
+    #[validate(length(min = 1, max = 200))]
+    query: String,
+    /// A Spoonacular diet such as `vegan`, `ketogenic` or `gluten free`
+    diet: Option<String>,
+}
+
+/// Nutrition for an item, from a Spoonacular recipe or, failing that, USDA data.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum NutritionResponse {
+    Spoonacular { recipe: Recipe },
+    Usda { food: FoodNutrition },
+}
+
+fn upstream_error(error: String) -> ApiError {
+    if error == QUOTA_EXCEEDED_MESSAGE {
+        ApiError::RateLimited(error)
+    } else {
+        ApiError::ProviderUnavailable(format!("Food API error: {}", error))
+    }
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/food/nutrition",
+    request_body = NutritionRequest,
+    responses(
+        (status = 200, description = "Nutrition data", body = NutritionResponse),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 404, description = "No data for this item", body = ApiErrorBody),
+        (status = 429, description = "Food API quota exceeded", body = ApiErrorBody),
+        (status = 503, description = "Food API keys not configured", body = ApiErrorBody)
+    )
+))]
+pub(super) async fn nutrition_handler(
+    State(clients): State<FoodClients>,
+    payload: Result<Json<NutritionRequest>, JsonRejection>,
+) -> Result<Json<NutritionResponse>, ApiError> {
+    let Json(request) = payload?;
+    request.validate()?;
+
+    if clients.spoonacular.is_none() && clients.usda.is_none() {
+        return Err(ApiError::ProviderUnavailable(
+            "Food API keys are not configured. Set SPOONACULAR_API_KEY and/or USDA_API_KEY.".to_string(),
+        ));
+    }
+
+    // Same order as the CLI: recipe data first, USDA as the fallback
+    if let Some(spoonacular) = &clients.spoonacular {
+        match spoonacular.search_recipe(&request.item, &RecipeFilters::default()).await {
+            Ok(Some(recipe)) => return Ok(Json(NutritionResponse::Spoonacular { recipe })),
+            Ok(None) => {}
+            Err(e) if clients.usda.is_none() => return Err(upstream_error(e)),
+            Err(e) => log::warn!("Spoonacular lookup failed, falling back to USDA: {}", e),
+        }
+    }
+
+    if let Some(usda) = &clients.usda {
+        if let Some(food) = usda.search_food(&request.item).await.map_err(upstream_error)? {
+            return Ok(Json(NutritionResponse::Usda { food }));
+        }
+    }
+
+    Err(ApiError::NotFound(format!("No nutrition data found for '{}'", request.item)))
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/food/recipe",
+    request_body = RecipeRequest,
+    responses(
+        (status = 200, description = "Best matching recipe", body = Recipe),
+        (status = 400, description = "Invalid request or unknown diet", body = ApiErrorBody),
+        (status = 404, description = "No recipe found", body = ApiErrorBody),
+        (status = 429, description = "Food API quota exceeded", body = ApiErrorBody),
+        (status = 503, description = "Spoonacular key not configured", body = ApiErrorBody)
+    )
+))]
+pub(super) async fn recipe_handler(
+    State(clients): State<FoodClients>,
+    payload: Result<Json<RecipeRequest>, JsonRejection>,
+) -> Result<Json<Recipe>, ApiError> {
+    let Json(request) = payload?;
+    request.validate()?;
+
+    let spoonacular = clients.spoonacular.as_ref().ok_or_else(|| {
+        ApiError::ProviderUnavailable("Recipe search is not configured. Set SPOONACULAR_API_KEY.".to_string())
+    })?;
+    let filters = RecipeFilters::new(request.diet.as_deref(), &[]).map_err(ApiError::validation)?;
+
+    spoonacular.search_recipe(&request.query, &filters).await
+        .map_err(upstream_error)?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("No recipe found for '{}'", request.query)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::food::cache::ResponseCache;
+    use axum::{routing::{get, post}, Router};
+    use reqwest::StatusCode;
+    use std::time::Duration;
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn temp_cache() -> ResponseCache {
+        let dir = std::env::temp_dir().join(format!("food_api_cache_{}", uuid::Uuid::new_v4()));
+        ResponseCache::with_dir(dir, Duration::from_secs(60))
+    }
+
+    async fn food_api(clients: FoodClients) -> String {
+        serve(Router::new()
+            .route("/food/nutrition", post(nutrition_handler))
+            .route("/food/recipe", post(recipe_handler))
+            .with_state(clients)).await
+    }
+
+    async fn mock_upstreams() -> FoodClients {
+        let spoonacular = serve(Router::new().route("/recipes/complexSearch", get(|| async {
+            Json(serde_json::json!({
+                "results": [{
+                    "id": 1,
+                    "title": "Vegan Pad Thai",
+                    "servings": 2,
+                    "nutrition": {"nutrients": [{"name": "Calories", "amount": 480.0, "unit": "kcal"}]}
+                }]
+            }))
+        }))).await;
+        let usda = serve(Router::new().route("/foods/search", get(|| async {
+            Json(serde_json::json!({
+                "foods": [{
+                    "description": "Banana, raw",
+                    "foodNutrients": [{"nutrientName": "Energy", "value": 89.0, "unitName": "KCAL"}]
+                }]
+            }))
+        }))).await;
+
+        FoodClients {
+            spoonacular: Some(Arc::new(SpoonacularClient::new("key".to_string())
+                .with_base_url(spoonacular)
+                .with_cache(temp_cache()))),
+            usda: Some(Arc::new(UsdaClient::with_api_key("key".to_string())
+                .with_base_url(usda)
+                .with_cache(temp_cache()))),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recipe_and_nutrition_return_json() {
+        let base = food_api(mock_upstreams().await).await;
+        let client = reqwest::Client::new();
+
+        let response = client.post(format!("{}/food/recipe", base))
+            .json(&serde_json::json!({"query": "pad thai", "diet": "vegan"}))
+            .send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let recipe: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(recipe["title"], "Vegan Pad Thai");
+        assert_eq!(recipe["nutrition"]["nutrients"][0]["amount"], 480.0);
+
+        let response = client.post(format!("{}/food/nutrition", base))
+            .json(&serde_json::json!({"item": "pad thai"}))
+            .send().await.unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["source"], "spoonacular");
+
+        let response = client.post(format!("{}/food/recipe", base))
+            .json(&serde_json::json!({"query": "pad thai", "diet": "vegn"}))
+            .send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_nutrition_falls_back_to_usda() {
+        let mut clients = mock_upstreams().await;
+        clients.spoonacular = None;
+        let base = food_api(clients).await;
+
+        let body: serde_json::Value = reqwest::Client::new()
+            .post(format!("{}/food/nutrition", base))
+            .json(&serde_json::json!({"item": "banana"}))
+            .send().await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(body["source"], "usda");
+        assert_eq!(body["food"]["description"], "Banana, raw");
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/food/recipe", base))
+            .json(&serde_json::json!({"query": "banana bread"}))
+            .send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_missing_keys_return_503() {
+        let base = food_api(FoodClients::default()).await;
+        let response = reqwest::Client::new()
+            .post(format!("{}/food/nutrition", base))
+            .json(&serde_json::json!({"item": "banana"}))
+            .send().await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "provider_unavailable");
+        assert!(body["message"].as_str().unwrap().contains("USDA_API_KEY"));
+    }
+}
@@ -1,33 +1,59 @@
 use axum::{
-    routing::{get, post},
+    routing::{get, post, put},
     Router,
     Json,
-    extract::State,
+    extract::{State, Path, Query, DefaultBodyLimit},
+    extract::rejection::JsonRejection,
+    http::StatusCode,
     response::{IntoResponse, Response},
-    http::{Method, header, StatusCode},
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, Semaphore};
 use tower_http::cors::{CorsLayer, Any};
-use std::error::Error;
-use std::fmt;
 use tokio::fs;
 use tower::limit::RateLimitLayer;
 use validator::Validate;
-use anyhow;
 
-use crate::personality::PersonalityProfile;
+use crate::personality::{character_file_stem, PersonalityError, PersonalityProfile};
+use crate::commands::{web_chat_prompt, BUILTIN_CHARACTERS};
 use crate::providers::deepseek::deepseek::DeepSeekProvider;
 use crate::database::Database;
-use crate::providers::web_crawler::crawler_manager::WebCrawlerManager;
+use crate::providers::web_crawler::crawler_manager::{ResearchProgress, WebCrawlerManager};
 use crate::providers::traits::CompletionProvider;
-use crate::llm::memory::MemoryManager;
-use crate::llm::EmbeddingGenerator;
+use crate::llm::budget::count_tokens;
+use crate::llm::cost::{self, CostTotals};
+use crate::llm::memory::{ConversationSession, MemoryManager};
+use crate::llm::{ContextBuilder, EmbeddingGenerator};
+use crate::llm::untrusted::wrap_untrusted;
+use crate::knowledge_base::context::with_knowledge_context;
+use crate::learning::{recall_facts, with_known_facts, LEARNED_CONTEXT_LIMIT};
 use crate::providers::openai::openai::OpenAIProvider;
 use crate::providers::openrouter::openrouter::OpenRouterProvider;
 use crate::providers::mistral::mistral::MistralProvider;
 
+mod batch;
+mod error;
+#[cfg(feature = "food")]
+mod food;
+mod persona;
+mod research;
+#[cfg(feature = "upload")]
+mod upload;
+mod webhook;
+#[cfg(feature = "openapi")]
+mod openapi;
+
+pub use error::{ApiError, ApiErrorBody};
+#[cfg(feature = "openapi")]
+pub use openapi::ApiDoc;
+
+const CHARACTERS_DIR: &str = "characters";
+const MAX_CHARACTER_SIZE: usize = 64 * 1024; // 64KB per character profile
+const DEFAULT_PROVIDER_CONCURRENCY: usize = 4;
+const DEFAULT_SESSION_LIMIT: i64 = 20;
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Debug, Deserialize, Clone)]
 pub enum LLMProvider {
     DeepSeek,
@@ -44,73 +70,135 @@ impl Default for LLMProvider {
 
 #[derive(Clone)]
 pub struct AppState {
-    deepseek: Arc<DeepSeekProvider>,
+    /// Serves `LLMProvider::DeepSeek`, the default
+    deepseek: Arc<dyn CompletionProvider + Send + Sync>,
     openai: Arc<RwLock<Option<OpenAIProvider>>>,
     openrouter: Arc<RwLock<Option<OpenRouterProvider>>>,
     mistral: Arc<RwLock<Option<MistralProvider>>>,
     personality: Arc<RwLock<PersonalityProfile>>,
     db: Arc<Database>,
     crawler: Arc<RwLock<Option<WebCrawlerManager>>>,
-    memory: Arc<RwLock<MemoryManager>>,
+    /// `None` when the server runs without vector memory
+    memory: Arc<RwLock<Option<MemoryManager>>>,
     embedding_generator: Arc<EmbeddingGenerator>,
+    provider_limiter: Arc<Semaphore>,
+    jobs: batch::JobStore,
+    web_jobs: webhook::WebJobStore,
+    characters: persona::CharacterCache,
+    #[cfg(feature = "food")]
+    food: food::FoodClients,
 }
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Deserialize, Validate)]
 pub struct ChatRequest {
     #[validate(length(min = 1, max = 1000))]
     message: String,
+    /// Answer as this character for this request only; defaults to the active character
     #[validate(length(min = 1, max = 100))]
     character: Option<String>,
     #[serde(default)]
     provider: LLMProvider,
+    #[serde(default)]
+    use_knowledge: bool,
 }
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Deserialize)]
 pub struct CharacterRequest {
     character: String,
 }
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Deserialize)]
 pub struct WebRequest {
     command: String,
+    /// Run in the background and POST the signed result here instead of waiting
+    #[serde(default)]
+    callback_url: Option<String>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[derive(Deserialize)]
+pub struct KnowledgeQuery {
+    /// Only return entries whose key starts with this prefix
+    prefix: Option<String>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[derive(Deserialize)]
+pub struct SessionsQuery {
+    /// How many sessions to return, most recently active first (default 20)
+    limit: Option<i64>,
 }
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Serialize)]
+pub struct KnowledgeEntry {
+    key: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    value: serde_json::Value,
+}
+
+/// One provider's models, or why they couldn't be listed.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize)]
+pub struct ProviderModels {
+    provider: String,
+    models: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl ProviderModels {
+    fn from_result(provider: &str, models: anyhow::Result<Vec<String>>) -> Self {
+        match models {
+            Ok(models) => Self { provider: provider.to_string(), models, error: None },
+            Err(e) => Self { provider: provider.to_string(), models: Vec::new(), error: Some(e.to_string()) },
+        }
+    }
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize, Clone)]
 pub struct ChatResponse {
     response: String,
     tokens: TokenInfo,
 }
 
-#[derive(Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize, Clone)]
 pub struct TokenInfo {
     input: usize,
     response: usize,
     total: usize,
 }
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Serialize)]
 pub struct CharacterResponse {
     status: String,
 }
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Serialize)]
 struct ApiResponse {
     status: String,
 }
 
-type ApiResult<T> = Result<Json<T>, (StatusCode, Json<ApiResponse>)>;
+type ApiResult<T> = Result<Json<T>, ApiError>;
 
-#[derive(Debug)]
-struct ApiError(String);
-
-impl fmt::Display for ApiError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
+/// Maximum simultaneous provider calls, from `PROVIDER_CONCURRENCY`.
+fn provider_concurrency() -> usize {
+    std::env::var("PROVIDER_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_PROVIDER_CONCURRENCY)
 }
 
-impl Error for ApiError {}
-
 /// Create and configure the API router
 pub async fn create_api(
     deepseek: DeepSeekProvider,
@@ -118,13 +206,8 @@ pub async fn create_api(
     db: Database,
     crawler: Option<WebCrawlerManager>,
     memory: MemoryManager,
+    embedding_generator: EmbeddingGenerator,
 ) -> Router {
-    // Create embedding generator
-    let api_key = std::env::var("DEEPSEEK_API_KEY")
-        .expect("DEEPSEEK_API_KEY must be set");
-    let embedding_generator = EmbeddingGenerator::new(api_key).await
-        .expect("Failed to create embedding generator");
-
     // Initialize optional providers
     let openai = if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
         Some(OpenAIProvider::new(api_key, "You are a helpful assistant.".to_string()).await
@@ -147,18 +230,32 @@ pub async fn create_api(
         None
     };
 
+    let db = Arc::new(db);
+    let memory = memory.with_session_store(db.clone());
+
     let state = AppState {
         deepseek: Arc::new(deepseek),
         openai: Arc::new(RwLock::new(openai)),
         openrouter: Arc::new(RwLock::new(openrouter)),
         mistral: Arc::new(RwLock::new(mistral)),
         personality: Arc::new(RwLock::new(personality)),
-        db: Arc::new(db),
+        db,
         crawler: Arc::new(RwLock::new(crawler)),
-        memory: Arc::new(RwLock::new(memory)),
+        memory: Arc::new(RwLock::new(Some(memory))),
         embedding_generator: Arc::new(embedding_generator),
+        provider_limiter: Arc::new(Semaphore::new(provider_concurrency())),
+        jobs: batch::JobStore::default(),
+        web_jobs: webhook::WebJobStore::default(),
+        characters: persona::CharacterCache::default(),
+        #[cfg(feature = "food")]
+        food: food::FoodClients::from_env(),
     };
 
+    router(state)
+}
+
+/// The routes and middleware, over an already-built state.
+fn router(state: AppState) -> Router {
     println!("Setting up API server with CORS...");
 
     // Fully permissive CORS configuration
@@ -171,265 +268,630 @@ pub async fn create_api(
     println!("CORS configured with permissive settings");
 
     // Create the router with middleware
-    Router::new()
+    let router = Router::new()
         .route("/chat", post(chat_handler))
+        .route("/chat/batch", post(batch::batch_chat_handler))
+        .route("/jobs/:id", get(batch::get_job_handler))
         .route("/character", post(character_handler))
         .route("/health", get(health_check))
+        .route("/models", get(list_models_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/sessions", get(list_sessions_handler))
         .route("/web", post(web_handler))
+        .route("/web/jobs/:id", get(webhook::get_web_job_handler))
+        .route("/web/research/stream", post(research::research_stream_handler))
+        .route("/characters", post(create_character_handler)
+            .layer(DefaultBodyLimit::max(MAX_CHARACTER_SIZE)))
+        .route("/characters/:name", put(update_character_handler)
+            .delete(delete_character_handler)
+            .layer(DefaultBodyLimit::max(MAX_CHARACTER_SIZE)))
+        .route("/kb", get(list_knowledge_handler))
+        .route("/kb/:key", put(put_knowledge_handler)
+            .get(get_knowledge_handler)
+            .delete(delete_knowledge_handler));
+
+    #[cfg(feature = "food")]
+    let router = router
+        .route("/food/nutrition", post(food::nutrition_handler))
+        .route("/food/recipe", post(food::recipe_handler));
+
+    #[cfg(feature = "upload")]
+    let router = router
+        .route("/document/upload", post(upload::upload_handler)
+            .layer(DefaultBodyLimit::max(upload::max_upload_size())));
+
+    #[cfg(feature = "openapi")]
+    let router = router.route("/openapi.json", get(openapi::openapi_json));
+    #[cfg(feature = "swagger-ui")]
+    let router = router.merge(openapi::swagger_ui());
+
+    router
         .layer(cors)
         .with_state(state)
 }
 
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/chat",
+    request_body = ChatRequest,
+    responses(
+        (status = 200, description = "Model reply", body = ChatResponse),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 404, description = "Unknown character; the message lists available ones", body = ApiErrorBody),
+        (status = 503, description = "Provider unavailable", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
 async fn chat_handler(
     State(state): State<AppState>,
-    Json(request): Json<ChatRequest>,
-) -> Response {
-    let input_tokens = request.message.split_whitespace().count();
-    
-    // Get recent conversations from database
-    let recent_convos = match state.db.get_recent_conversations(5).await {
-        Ok(convos) => convos,
-        Err(e) => {
-            eprintln!("Database error: {}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse { status: "Database error".to_string() })
-            ).into_response();
-        }
+    payload: Result<Json<ChatRequest>, JsonRejection>,
+) -> ApiResult<ChatResponse> {
+    let Json(request) = payload?;
+    request.validate()?;
+
+    // A requested character applies to this call only; the active one is left alone
+    let personality = match &request.character {
+        Some(name) => state.characters.load(name).await?,
+        None => state.personality.read().await.clone(),
     };
-    
-    // Get current personality and build context
-    let personality = state.personality.read().await;
     println!("Generating response as character: {}", personality.name);
+
+    let response = generate_reply(
+        &state,
+        &personality,
+        &request.message,
+        &request.provider,
+        request.use_knowledge,
+        true,
+    ).await?;
+
+    Ok(Json(response))
+}
+
+/// Runs one message through the selected provider, holding a provider limiter permit for the call.
+/// When `persist` is set the exchange is saved to the conversation log and memory.
+async fn generate_reply(
+    state: &AppState,
+    personality: &PersonalityProfile,
+    message: &str,
+    provider: &LLMProvider,
+    use_knowledge: bool,
+    persist: bool,
+) -> Result<ChatResponse, ApiError> {
+    let input_tokens = message.split_whitespace().count();
     
     // Get system prompt
     let system_prompt = personality.generate_system_prompt();
 
+    // Optionally prepend knowledge base entries whose key appears in the message
+    let prompt = if use_knowledge {
+        match state.db.list_knowledge(None).await {
+            Ok(entries) => with_knowledge_context(message, &entries),
+            Err(e) => {
+                eprintln!("Warning: Failed to load knowledge base: {}", e);
+                message.to_string()
+            }
+        }
+    } else {
+        message.to_string()
+    };
+    let prompt = if crate::config::learned_context_enabled() {
+        match recall_facts(&state.db, message, None, LEARNED_CONTEXT_LIMIT).await {
+            Ok(facts) => with_known_facts(&prompt, &facts),
+            Err(e) => {
+                eprintln!("Warning: Failed to recall learned facts: {}", e);
+                prompt
+            }
+        }
+    } else {
+        prompt
+    };
+
+    let permit = state.provider_limiter.acquire().await
+        .map_err(|_| ApiError::Internal("Provider limiter closed".to_string()))?;
+
     // Select provider based on request
-    let response = match request.provider {
+    let (response, model) = match provider {
         LLMProvider::DeepSeek => {
-            match std::env::var("DEEPSEEK_API_KEY") {
-                Ok(api_key) => {
-                    match DeepSeekProvider::new(api_key, system_prompt).await {
-                        Ok(provider) => provider.complete(&request.message).await,
-                        Err(e) => Err(anyhow::Error::msg(format!("Failed to create DeepSeek provider: {}", e)))
-                    }
-                },
-                Err(_) => Err(anyhow::Error::msg("DEEPSEEK_API_KEY not set"))
-            }
+            let provider = state.deepseek.with_system_message(&system_prompt);
+            (provider.complete(&prompt).await, provider.get_model_info().await)
         },
         LLMProvider::OpenAI => {
             let provider = state.openai.read().await;
             if let Some(provider) = provider.as_ref() {
-                provider.complete(&request.message).await
+                (provider.complete(&prompt).await, provider.get_model_info().await)
             } else {
-                Err(anyhow::Error::msg("OpenAI provider not initialized"))
+                return Err(ApiError::ProviderUnavailable("OpenAI provider not initialized".to_string()));
             }
         },
         LLMProvider::OpenRouter => {
             let provider = state.openrouter.read().await;
             if let Some(provider) = provider.as_ref() {
-                provider.complete(&request.message).await
+                (provider.complete(&prompt).await, provider.get_model_info().await)
             } else {
-                Err(anyhow::Error::msg("OpenRouter provider not initialized"))
+                return Err(ApiError::ProviderUnavailable("OpenRouter provider not initialized".to_string()));
             }
         },
         LLMProvider::Mistral => {
             let provider = state.mistral.read().await;
             if let Some(provider) = provider.as_ref() {
-                provider.complete(&request.message).await
+                (provider.complete(&prompt).await, provider.get_model_info().await)
             } else {
-                Err(anyhow::Error::msg("Mistral provider not initialized"))
+                return Err(ApiError::ProviderUnavailable("Mistral provider not initialized".to_string()));
             }
         }
     };
+    drop(permit);
 
-    let response = match response {
-        Ok(text) => text,
-        Err(e) => {
-            eprintln!("AI error: {}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse { status: format!("AI error: {}", e) })
-            ).into_response();
-        }
-    };
+    let response = response
+        .map_err(|e| ApiError::ProviderUnavailable(format!("AI error: {}", e)))?;
 
     let response_tokens = response.split_whitespace().count();
-    
-    // Save conversation to database with current personality
-    if let Err(e) = state.db.save_conversation(
-        request.message.clone(),
-        response.clone(),
-        personality.name.clone(),
-    ).await {
-        eprintln!("Warning: Failed to save conversation to database: {}", e);
-    }
+    cost::record(&model.unwrap_or_default(), count_tokens(&prompt), count_tokens(&response));
 
-    // Store in memory with proper embeddings
-    let mut memory = state.memory.write().await;
-    let chat_text = format!("User: {}\nAI: {}", request.message, response);
-    
-    // Generate embedding for the chat
-    let embedding = match state.embedding_generator.generate_embedding(&chat_text).await {
-        Ok(emb) => emb,
-        Err(e) => {
-            eprintln!("Warning: Failed to generate embedding: {}", e);
-            vec![0.0; 1536] // Fallback to zero vector
+    if persist {
+        // Save conversation to database with current personality
+        if let Err(e) = state.db.save_conversation(
+            message.to_string(),
+            response.clone(),
+            personality.name.clone(),
+        ).await {
+            eprintln!("Warning: Failed to save conversation to database: {}", e);
         }
-    };
 
-    if let Err(e) = memory.store_memory(
-        &chat_text,
-        "chat",
-        embedding,
-        None
-    ).await {
-        eprintln!("Warning: Failed to store memory: {}", e);
+        // Store in memory with proper embeddings
+        let mut memory = state.memory.write().await;
+        if let Some(memory) = memory.as_mut() {
+            let chat_text = format!("User: {}\nAI: {}", message, response);
+        
+            // Generate embedding for the chat
+            let embedding = match state.embedding_generator.generate_embedding(&chat_text).await {
+                Ok(emb) => emb,
+                Err(e) => {
+                    eprintln!("Warning: Failed to generate embedding: {}", e);
+                    vec![0.0; crate::config::embedding_dim()] // Fallback to zero vector
+                }
+            };
+
+            if let Err(e) = memory.store_memory(
+                &chat_text,
+                "chat",
+                embedding,
+                None
+            ).await {
+                eprintln!("Warning: Failed to store memory: {}", e);
+            }
+        }
     }
 
-    Json(ChatResponse {
+    Ok(ChatResponse {
         response,
         tokens: TokenInfo {
             input: input_tokens,
             response: response_tokens,
             total: input_tokens + response_tokens,
         },
-    }).into_response()
+    })
+}
+
+fn knowledge_entry(key: String, value: String) -> KnowledgeEntry {
+    // Values are stored as JSON text; fall back to a plain string for legacy rows
+    let value = serde_json::from_str(&value)
+        .unwrap_or(serde_json::Value::String(value));
+    KnowledgeEntry { key, value }
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    put,
+    path = "/kb/{key}",
+    params(("key" = String, Path, description = "Knowledge key")),
+    request_body(content = Object, description = "Any JSON value"),
+    responses(
+        (status = 200, description = "Stored entry", body = KnowledgeEntry),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
+async fn put_knowledge_handler(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    payload: Result<Json<serde_json::Value>, JsonRejection>,
+) -> ApiResult<KnowledgeEntry> {
+    let Json(value) = payload?;
+    state.db.save_knowledge(key.clone(), value.to_string()).await?;
+    Ok(Json(KnowledgeEntry { key, value }))
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/kb/{key}",
+    params(("key" = String, Path, description = "Knowledge key")),
+    responses(
+        (status = 200, description = "Knowledge entry", body = KnowledgeEntry),
+        (status = 404, description = "Not found", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
+async fn get_knowledge_handler(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> ApiResult<KnowledgeEntry> {
+    match state.db.get_knowledge(key.clone()).await? {
+        Some(value) => Ok(Json(knowledge_entry(key, value))),
+        None => Err(ApiError::NotFound(format!("Knowledge entry not found: {}", key))),
+    }
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/kb",
+    params(KnowledgeQuery),
+    responses(
+        (status = 200, description = "Knowledge entries", body = [KnowledgeEntry]),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
+async fn list_knowledge_handler(
+    State(state): State<AppState>,
+    Query(query): Query<KnowledgeQuery>,
+) -> ApiResult<Vec<KnowledgeEntry>> {
+    let entries = state.db.list_knowledge(query.prefix).await?;
+    Ok(Json(entries.into_iter()
+        .map(|(key, value)| knowledge_entry(key, value))
+        .collect()))
 }
 
+#[cfg_attr(feature = "openapi", utoipa::path(
+    delete,
+    path = "/kb/{key}",
+    params(("key" = String, Path, description = "Knowledge key")),
+    responses(
+        (status = 200, description = "Entry deleted", body = ApiResponse),
+        (status = 404, description = "Not found", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
+async fn delete_knowledge_handler(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> ApiResult<ApiResponse> {
+    if state.db.delete_knowledge(key.clone()).await? {
+        Ok(Json(ApiResponse { status: format!("Deleted knowledge entry: {}", key) }))
+    } else {
+        Err(ApiError::NotFound(format!("Knowledge entry not found: {}", key)))
+    }
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/character",
+    request_body = CharacterRequest,
+    responses(
+        (status = 200, description = "Active character changed", body = CharacterResponse),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 404, description = "Not found", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
 async fn character_handler(
-    State(mut state): State<AppState>,
-    Json(request): Json<CharacterRequest>,
-) -> Response {
+    State(state): State<AppState>,
+    payload: Result<Json<CharacterRequest>, JsonRejection>,
+) -> ApiResult<CharacterResponse> {
+    let Json(request) = payload?;
     println!("Changing character to: {}", request.character);
     
     // Load character profile
-    let file_path = format!("/root/RUSTV3-MULTILLM/characters/{}.json", request.character);
-    let profile = match tokio::fs::read_to_string(&file_path).await {
-        Ok(content) => {
-            match serde_json::from_str::<PersonalityProfile>(&content) {
-                Ok(profile) => profile,
-                Err(e) => {
-                    eprintln!("Error parsing character profile: {}", e);
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        Json(ApiResponse { status: format!("Error parsing character profile: {}", e) })
-                    ).into_response();
-                }
-            }
-        },
+    let profile = match PersonalityProfile::load_from_dir(CHARACTERS_DIR, &request.character) {
+        Ok(profile) => profile,
+        Err(PersonalityError::NotFound(name)) => {
+            return Err(ApiError::NotFound(format!("Character file not found: {}", name)));
+        }
         Err(e) => {
-            eprintln!("Error reading character file: {}", e);
-            return (
-                StatusCode::NOT_FOUND,
-                Json(ApiResponse { status: format!("Character file not found: {}", e) })
-            ).into_response();
+            return Err(ApiError::Internal(format!("Error loading character profile: {}", e)));
         }
     };
 
     // Update the personality
     *state.personality.write().await = profile.clone();
 
-    // Create new provider with updated character
-    let api_key = match std::env::var("DEEPSEEK_API_KEY") {
-        Ok(key) => key,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse { status: "DEEPSEEK_API_KEY not set".to_string() })
-            ).into_response();
-        }
-    };
+    // Swap the shared provider's system message; the state here is a per-request clone,
+    // so replacing the provider itself would not outlive this request
+    state.deepseek.update_personality(profile.generate_system_prompt()).await
+        .map_err(|e| ApiError::Internal(format!("Failed to update provider: {}", e)))?;
 
-    let system_prompt = profile.generate_system_prompt();
-    let new_provider = match DeepSeekProvider::new(api_key, system_prompt).await {
-        Ok(provider) => provider,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiResponse { status: format!("Failed to create provider: {}", e) })
-            ).into_response();
-        }
-    };
+    Ok(Json(CharacterResponse {
+        status: "Character updated successfully".to_string(),
+    }))
+}
 
-    // Update the provider
-    state.deepseek = Arc::new(new_provider);
+fn validate_character(profile: &PersonalityProfile) -> Result<String, ApiError> {
+    if profile.name.trim().is_empty() {
+        return Err(ApiError::validation("Character name must not be empty"));
+    }
+    if !profile.attributes.is_object() {
+        return Err(ApiError::validation("Character attributes must be a JSON object"));
+    }
+    let stem = character_file_stem(&profile.name);
+    if stem.is_empty() {
+        return Err(ApiError::validation(format!("Character name '{}' has no usable characters", profile.name)));
+    }
+    if BUILTIN_CHARACTERS.contains(&stem.as_str()) {
+        return Err(ApiError::validation(format!("Character name '{}' collides with a built-in character", stem)));
+    }
+    Ok(stem)
+}
 
-    Json(CharacterResponse {
-        status: "Character updated successfully".to_string(),
-    }).into_response()
+async fn write_character(stem: &str, profile: &PersonalityProfile) -> Result<(), ApiError> {
+    let content = serde_json::to_string_pretty(profile)
+        .map_err(|e| ApiError::Internal(format!("Failed to serialize character: {}", e)))?;
+    fs::create_dir_all(CHARACTERS_DIR).await
+        .map_err(|e| ApiError::Internal(format!("Failed to create characters directory: {}", e)))?;
+    fs::write(std::path::Path::new(CHARACTERS_DIR).join(format!("{}.json", stem)), content).await
+        .map_err(|e| ApiError::Internal(format!("Failed to write character file: {}", e)))
 }
 
-async fn health_check() -> Response {
+/// Resolves the file for an existing, non-built-in character.
+async fn existing_character_path(name: &str, action: &str) -> Result<(String, std::path::PathBuf), ApiError> {
+    if name.contains("..") || name.contains('/') || name.contains('\\') {
+        return Err(ApiError::validation(format!("Invalid character name: {}", name)));
+    }
+    let stem = character_file_stem(name);
+    if BUILTIN_CHARACTERS.contains(&stem.as_str()) {
+        return Err(ApiError::validation(format!("Built-in character '{}' cannot be {}", stem, action)));
+    }
+
+    let path = std::path::Path::new(CHARACTERS_DIR).join(format!("{}.json", stem));
+    if stem.is_empty() || !fs::try_exists(&path).await.unwrap_or(false) {
+        return Err(ApiError::NotFound(format!("Character not found: {}", name)));
+    }
+    Ok((stem, path))
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/characters",
+    request_body(content = Object, description = "Character profile: a `name` plus free-form attributes"),
+    responses(
+        (status = 201, description = "Character created", body = ApiResponse),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 409, description = "Character already exists", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
+async fn create_character_handler(
+    payload: Result<Json<PersonalityProfile>, JsonRejection>,
+) -> Result<(StatusCode, Json<ApiResponse>), ApiError> {
+    let Json(profile) = payload?;
+    let stem = validate_character(&profile)?;
+
+    let path = std::path::Path::new(CHARACTERS_DIR).join(format!("{}.json", stem));
+    if fs::try_exists(&path).await.unwrap_or(false) {
+        return Err(ApiError::Conflict(format!(
+            "Character already exists: {}. Use PUT /characters/{} to update it.", stem, stem
+        )));
+    }
+
+    write_character(&stem, &profile).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(ApiResponse { status: format!("Character saved as {}", stem) })
+    ))
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    put,
+    path = "/characters/{name}",
+    params(("name" = String, Path, description = "Character name")),
+    request_body(content = Object, description = "Character profile: a `name` plus free-form attributes"),
+    responses(
+        (status = 200, description = "Character updated", body = ApiResponse),
+        (status = 400, description = "Invalid name, or a profile whose name doesn't match it", body = ApiErrorBody),
+        (status = 404, description = "Not found", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
+async fn update_character_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    payload: Result<Json<PersonalityProfile>, JsonRejection>,
+) -> ApiResult<ApiResponse> {
+    let Json(profile) = payload?;
+    let profile_stem = validate_character(&profile)?;
+    if profile_stem != character_file_stem(&name) {
+        return Err(ApiError::validation(format!(
+            "Character name '{}' does not match {}. Create it with POST /characters instead.", profile.name, name
+        )));
+    }
+    let (stem, _) = existing_character_path(&name, "modified").await?;
+
+    write_character(&stem, &profile).await?;
+    state.characters.invalidate().await;
+    Ok(Json(ApiResponse { status: format!("Character {} updated", stem) }))
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    delete,
+    path = "/characters/{name}",
+    params(("name" = String, Path, description = "Character name")),
+    responses(
+        (status = 200, description = "Character deleted", body = ApiResponse),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 404, description = "Not found", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
+async fn delete_character_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<ApiResponse> {
+    let (stem, path) = existing_character_path(&name, "deleted").await?;
+
+    fs::remove_file(&path).await
+        .map_err(|e| ApiError::Internal(format!("Failed to delete character: {}", e)))?;
+    state.characters.invalidate().await;
+    Ok(Json(ApiResponse { status: format!("Character {} deleted", stem) }))
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "Server is healthy", body = ApiResponse)
+    )
+))]
+async fn health_check() -> Json<ApiResponse> {
     println!("Health check requested");
     Json(ApiResponse { 
         status: "Server is running and healthy".to_string() 
-    }).into_response()
+    })
 } 
 
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/models",
+    responses(
+        (status = 200, description = "Models of each initialized provider", body = [ProviderModels])
+    )
+))]
+async fn list_models_handler(State(state): State<AppState>) -> Json<Vec<ProviderModels>> {
+    let mut groups = vec![ProviderModels::from_result("deepseek", state.deepseek.list_models().await)];
+    if let Some(provider) = state.openai.read().await.as_ref() {
+        groups.push(ProviderModels::from_result("openai", provider.list_models().await));
+    }
+    if let Some(provider) = state.openrouter.read().await.as_ref() {
+        groups.push(ProviderModels::from_result("openrouter", provider.list_models().await));
+    }
+    if let Some(provider) = state.mistral.read().await.as_ref() {
+        groups.push(ProviderModels::from_result("mistral", provider.list_models().await));
+    }
+    Json(groups)
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Requests, tokens and estimated spend since the server started", body = CostTotals)
+    )
+))]
+async fn metrics_handler() -> Json<CostTotals> {
+    Json(cost::totals())
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/sessions",
+    params(SessionsQuery),
+    responses(
+        (status = 200, description = "Recorded conversation sessions, most recently active first", body = [ConversationSession]),
+        (status = 400, description = "Invalid limit", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
+async fn list_sessions_handler(
+    State(state): State<AppState>,
+    Query(query): Query<SessionsQuery>,
+) -> ApiResult<Vec<ConversationSession>> {
+    let limit = query.limit.unwrap_or(DEFAULT_SESSION_LIMIT);
+    if limit < 1 {
+        return Err(ApiError::validation("limit must be at least 1"));
+    }
+    Ok(Json(state.db.list_sessions(limit).await?))
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/web",
+    request_body = WebRequest,
+    responses(
+        (status = 200, description = "Command result", body = ApiResponse),
+        (status = 202, description = "Started in the background; result is sent to callback_url", body = WebJob),
+        (status = 400, description = "Invalid request", body = ApiErrorBody),
+        (status = 503, description = "Provider unavailable", body = ApiErrorBody),
+        (status = 500, description = "Internal error", body = ApiErrorBody)
+    )
+))]
 async fn web_handler(
     State(state): State<AppState>,
-    Json(request): Json<WebRequest>,
-) -> Response {
-    let command = request.command.as_str();
-    
+    payload: Result<Json<WebRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    let Json(request) = payload?;
+
+    if let Some(callback_url) = request.callback_url {
+        webhook::validate_callback_url(&callback_url)?;
+        let secret = webhook::webhook_secret()
+            .ok_or_else(|| ApiError::ProviderUnavailable("Webhook callbacks require WEBHOOK_SECRET to be set".to_string()))?;
+
+        let job = webhook::spawn_web_job(state, request.command, callback_url, secret).await;
+        return Ok((StatusCode::ACCEPTED, Json(job)).into_response());
+    }
+
+    let result = run_web_command(&state, &request.command).await?;
+    Ok(Json(ApiResponse { status: result }).into_response())
+}
+
+async fn run_web_command(state: &AppState, command: &str) -> Result<String, ApiError> {
+    run_web_command_with_progress(state, command, &mut |_| {}).await
+}
+
+/// Like [`run_web_command`], reporting each source `research` visits to `on_progress`.
+async fn run_web_command_with_progress(
+    state: &AppState,
+    command: &str,
+    on_progress: &mut (dyn FnMut(&ResearchProgress) + Send),
+) -> Result<String, ApiError> {
     let mut crawler = state.crawler.write().await;
     let mut memory = state.memory.write().await;
+    let memory = memory.as_mut()
+        .ok_or_else(|| ApiError::ProviderUnavailable("Memory is unavailable".to_string()))?;
     let personality = state.personality.read().await;
     
-    match handle_web_command(
+    handle_web_command(
         command,
         &mut crawler,
-        &state.deepseek,
-        &mut memory,
+        state.deepseek.as_ref(),
+        memory,
         &personality,
-        &state.embedding_generator
-    ).await {
-        Ok(result) => Json(ApiResponse { 
-            status: result 
-        }).into_response(),
-        Err(e) => (
-            StatusCode::BAD_REQUEST,
-            Json(ApiResponse { status: e })
-        ).into_response()
-    }
+        &state.embedding_generator,
+        on_progress,
+    ).await
 }
 
 async fn handle_web_command(
     command: &str,
     crawler: &mut Option<WebCrawlerManager>,
-    provider: &DeepSeekProvider,
+    provider: &(dyn CompletionProvider + Send + Sync),
     memory: &mut MemoryManager,
     personality: &PersonalityProfile,
     embedding_generator: &EmbeddingGenerator,
-) -> Result<String, String> {
+    on_progress: &mut (dyn FnMut(&ResearchProgress) + Send),
+) -> Result<String, ApiError> {
     if let Some(crawler) = crawler {
         match command {
             s if s.starts_with("analyze ") => {
                 let url = s.trim_start_matches("analyze ").trim();
                 if url.is_empty() {
-                    return Err("Please provide a URL to analyze.".to_string());
+                    return Err(ApiError::validation("Please provide a URL to analyze."));
                 }
 
                 let content = crawler.analyze_url(url).await
-                    .map_err(|e| format!("Failed to analyze webpage: {}", e))?;
+                    .map_err(|e| ApiError::Internal(format!("Failed to analyze webpage: {}", e)))?;
 
                 // Store the webpage content in memory with embedding
                 let content_text = format!("Webpage being discussed: {}\nContent:\n{}", url, content);
                 let content_embedding = embedding_generator.generate_embedding(&content_text).await
-                    .map_err(|e| format!("Failed to generate embedding: {}", e))?;
+                    .map_err(|e| ApiError::Internal(format!("Failed to generate embedding: {}", e)))?;
 
                 memory.store_memory(
                     &content_text,
                     "system",
                     content_embedding,
                     None
-                ).await.map_err(|e| format!("Failed to store memory: {}", e))?;
+                ).await.map_err(|e| ApiError::Internal(format!("Failed to store memory: {}", e)))?;
 
                 // Create new provider with current personality
                 let system_prompt = personality.generate_system_prompt();
-                let new_provider = provider.clone_with_prompt(&system_prompt);
+                let new_provider = provider.with_system_message(&system_prompt);
 
                 let analysis_prompt = format!(
                     "{}\n\n\
@@ -437,46 +899,50 @@ async fn handle_web_command(
                     Consider your personality traits and expertise. \
                     Be creative and stay true to your character's style:\n\n{}",
                     new_provider.get_system_message(),
-                    content
+                    wrap_untrusted(url, &content)
                 );
 
                 let analysis = new_provider.complete(&analysis_prompt).await
-                    .map_err(|e| format!("Failed to analyze content: {}", e))?;
+                    .map_err(|e| ApiError::ProviderUnavailable(format!("Failed to analyze content: {}", e)))?;
 
                 // Store the analysis in memory with embedding
                 let analysis_text = format!("Analysis of webpage: {}\n{}", url, analysis);
                 let analysis_embedding = embedding_generator.generate_embedding(&analysis_text).await
-                    .map_err(|e| format!("Failed to generate embedding: {}", e))?;
+                    .map_err(|e| ApiError::Internal(format!("Failed to generate embedding: {}", e)))?;
 
                 memory.store_memory(
                     &analysis_text,
                     "assistant",
                     analysis_embedding,
                     None
-                ).await.map_err(|e| format!("Failed to store memory: {}", e))?;
+                ).await.map_err(|e| ApiError::Internal(format!("Failed to store memory: {}", e)))?;
 
                 Ok(analysis)
             },
             s if s.starts_with("research ") => {
                 let topic = s.trim_start_matches("research ").trim();
                 if topic.is_empty() {
-                    return Err("Please provide a topic to research.".to_string());
+                    return Err(ApiError::validation("Please provide a topic to research."));
                 }
 
-                let results = crawler.research_topic(topic).await
-                    .map_err(|e| format!("Failed to research topic: {}", e))?;
+                let results = crawler.research_topic(topic, 0, on_progress).await
+                    .map_err(|e| ApiError::Internal(format!("Failed to research topic: {}", e)))?;
+
+                // Store research request in memory with embedding
+                let topic_text = format!("Research topic: {}", topic);
+                let topic_embedding = embedding_generator.generate_embedding(&topic_text).await
+                    .map_err(|e| ApiError::Internal(format!("Failed to generate embedding: {}", e)))?;
 
-                // Store research request in memory
                 memory.store_memory(
-                    &format!("Research topic: {}", topic),
+                    &topic_text,
                     "user",
-                    vec![0.0; 1536],
+                    topic_embedding,
                     None
-                ).await.map_err(|e| format!("Failed to store memory: {}", e))?;
+                ).await.map_err(|e| ApiError::Internal(format!("Failed to store memory: {}", e)))?;
 
                 // Create new provider with current personality
                 let system_prompt = personality.generate_system_prompt();
-                let new_provider = provider.clone_with_prompt(&system_prompt);
+                let new_provider = provider.with_system_message(&system_prompt);
 
                 let research_prompt = format!(
                     "{}\n\n\
@@ -489,36 +955,172 @@ async fn handle_web_command(
                     3. Then make a quick summary of all of these, short and insightful with your own unique style:\n{}",  
                     new_provider.get_system_message(),
                     topic,
-                    results.join("\n")
+                    wrap_untrusted(&format!("web research on {}", topic), &results.join("\n"))
                 );
 
                 let analysis = new_provider.complete(&research_prompt).await
-                    .map_err(|e| format!("Failed to synthesize research: {}", e))?;
+                    .map_err(|e| ApiError::ProviderUnavailable(format!("Failed to synthesize research: {}", e)))?;
+
+                // Store research results in memory with embedding
+                let findings_text = format!("Research findings for {}: {}", topic, analysis);
+                let findings_embedding = embedding_generator.generate_embedding(&findings_text).await
+                    .map_err(|e| ApiError::Internal(format!("Failed to generate embedding: {}", e)))?;
 
-                // Store research results in memory
                 memory.store_memory(
-                    &format!("Research findings for {}: {}", topic, analysis),
+                    &findings_text,
                     "assistant",
-                    vec![0.0; 1536],
+                    findings_embedding,
                     None
-                ).await.map_err(|e| format!("Failed to store memory: {}", e))?;
+                ).await.map_err(|e| ApiError::Internal(format!("Failed to store memory: {}", e)))?;
 
                 Ok(analysis)
             },
             s if s.starts_with("links ") => {
-                let url = s.trim_start_matches("links ").trim();
-                if url.is_empty() {
-                    return Err("Please provide a URL to extract links from.".to_string());
-                }
+                let (url, options) = crate::providers::web_crawler::LinkOptions::parse(s.trim_start_matches("links "))
+                    .map_err(ApiError::validation)?;
 
-                let links = crawler.extract_links(url).await
-                    .map_err(|e| format!("Failed to extract links: {}", e))?;
+                let links = crawler.extract_links(&url, &options).await
+                    .map_err(|e| ApiError::Internal(format!("Failed to extract links: {}", e)))?;
 
                 Ok(format!("Links found:\n{}", links.join("\n")))
             },
-            _ => Err("Unknown web command. Available commands: analyze <url>, research <topic>, links <url>".to_string())
+            s if s.starts_with("chat ") => {
+                let query = s.trim_start_matches("chat ").trim();
+                if query.is_empty() {
+                    return Err(ApiError::validation("Please provide a question about the pages you've analyzed."));
+                }
+
+                let context = ContextBuilder::new(embedding_generator, memory).build(query).await?;
+
+                // Create new provider with current personality
+                let system_prompt = personality.generate_system_prompt();
+                let new_provider = provider.with_system_message(&system_prompt);
+
+                let chat_prompt = web_chat_prompt(new_provider.as_ref(), &context, query).await;
+
+                let response = new_provider.complete(&chat_prompt).await
+                    .map_err(|e| ApiError::ProviderUnavailable(format!("Failed to get response: {}", e)))?;
+
+                // Store the chat interaction
+                let interaction = format!("Q: {}\nA: {}", query, response);
+                let embedding = embedding_generator.generate_embedding(&interaction).await
+                    .map_err(|e| ApiError::Internal(format!("Failed to generate embedding: {}", e)))?;
+                memory.store_memory(&interaction, "chat", embedding, None).await
+                    .map_err(|e| ApiError::Internal(format!("Failed to store memory: {}", e)))?;
+
+                Ok(response)
+            },
+            _ => Err(ApiError::validation("Unknown web command. Available commands: analyze <url>, research <topic>, links <url>, chat <question>"))
         }
     } else {
-        Err("Web crawler not initialized. Use --crawler flag to enable web features.".to_string())
+        Err(ApiError::ProviderUnavailable("Web crawler not initialized. Use --crawler flag to enable web features.".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    async fn serve(provider: MockProvider, db: Arc<Database>) -> String {
+        let state = AppState {
+            deepseek: Arc::new(provider.clone()),
+            openai: Arc::new(RwLock::new(None)),
+            openrouter: Arc::new(RwLock::new(None)),
+            mistral: Arc::new(RwLock::new(None)),
+            personality: Arc::new(RwLock::new(PersonalityProfile {
+                name: "tester".to_string(),
+                attributes: serde_json::json!({ "description": "a test bot" }),
+            })),
+            db,
+            crawler: Arc::new(RwLock::new(None)),
+            memory: Arc::new(RwLock::new(None)),
+            embedding_generator: Arc::new(EmbeddingGenerator::from_provider(Box::new(provider))),
+            provider_limiter: Arc::new(Semaphore::new(1)),
+            jobs: batch::JobStore::default(),
+            web_jobs: webhook::WebJobStore::default(),
+            characters: persona::CharacterCache::default(),
+            #[cfg(feature = "food")]
+            food: food::FoodClients::from_env(),
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_chat_through_mock_provider() {
+        let mock = MockProvider::with_response("Ahoy from the mock");
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let url = serve(mock.clone(), db.clone()).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/chat", url))
+            .json(&serde_json::json!({ "message": "hello there" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["response"], "Ahoy from the mock");
+        assert_eq!(body["tokens"]["total"], 6);
+
+        assert_eq!(mock.prompts(), vec!["hello there"]);
+        let saved = db.get_conversations(None, 10).await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].personality, "tester");
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_listed_from_the_database() {
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let now = chrono::Utc::now();
+        for (id, topic, minutes_ago) in [("older", "rust", 10), ("newer", "soup", 1)] {
+            let time = now - chrono::Duration::minutes(minutes_ago);
+            db.save_session(&ConversationSession {
+                id: id.to_string(),
+                start_time: time,
+                topic: topic.to_string(),
+                summary: String::new(),
+                last_active: time,
+                personality: Some("tester".to_string()),
+            }).await.unwrap();
+        }
+        let url = serve(MockProvider::with_response("unused"), db).await;
+
+        let sessions: Vec<serde_json::Value> = reqwest::get(format!("{}/sessions", url)).await.unwrap()
+            .json().await.unwrap();
+        let ids: Vec<_> = sessions.iter().map(|s| s["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["newer", "older"]);
+        assert_eq!(sessions[0]["personality"], "tester");
+
+        let limited: Vec<serde_json::Value> = reqwest::get(format!("{}/sessions?limit=1", url)).await.unwrap()
+            .json().await.unwrap();
+        assert_eq!(limited.len(), 1);
+        let invalid = reqwest::get(format!("{}/sessions?limit=0", url)).await.unwrap();
+        assert_eq!(invalid.status(), reqwest::StatusCode::BAD_REQUEST);
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_character_updates_stay_in_the_characters_directory() {
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let url = serve(MockProvider::with_response("unused"), db).await;
+        let client = reqwest::Client::new();
+        let put = |name: &str, profile_name: &str| client
+            .put(format!("{}/characters/{}", url, name))
+            .json(&serde_json::json!({ "name": profile_name, "attributes": {} }))
+            .send();
+
+        let response = put("..%5C..%5Cpasswd", "passwd").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["message"].as_str().unwrap().starts_with("Invalid character name"));
+
+        let response = put("ghost", "pirate").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["message"].as_str().unwrap().contains("does not match ghost"));
+    }
+}
@@ -0,0 +1,135 @@
+use axum::Json;
+use utoipa::OpenApi;
+
+use super::batch::{BatchChatRequest, BatchChatResult, BatchItemError, BatchJob, JobStatus};
+use super::research::ResearchRequest;
+use super::webhook::WebJob;
+use crate::llm::cost::CostTotals;
+use crate::llm::memory::ConversationSession;
+use super::{
+    ApiErrorBody, ApiResponse, CharacterRequest, CharacterResponse, ChatRequest, ChatResponse,
+    KnowledgeEntry, LLMProvider, ProviderModels, TokenInfo, WebRequest,
+};
+
+/// OpenAPI document generated from the handler annotations in `api/mod.rs`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Rust AI Agent API"),
+    paths(
+        super::chat_handler,
+        super::batch::batch_chat_handler,
+        super::batch::get_job_handler,
+        super::character_handler,
+        super::health_check,
+        super::list_models_handler,
+        super::metrics_handler,
+        super::list_sessions_handler,
+        super::web_handler,
+        super::webhook::get_web_job_handler,
+        super::research::research_stream_handler,
+        super::create_character_handler,
+        super::update_character_handler,
+        super::delete_character_handler,
+        super::list_knowledge_handler,
+        super::put_knowledge_handler,
+        super::get_knowledge_handler,
+        super::delete_knowledge_handler,
+    ),
+    components(schemas(
+        ChatRequest,
+        ChatResponse,
+        TokenInfo,
+        BatchChatRequest,
+        BatchChatResult,
+        BatchItemError,
+        BatchJob,
+        JobStatus,
+        LLMProvider,
+        CharacterRequest,
+        CharacterResponse,
+        WebRequest,
+        WebJob,
+        ResearchRequest,
+        KnowledgeEntry,
+        ProviderModels,
+        CostTotals,
+        ConversationSession,
+        ApiResponse,
+        ApiErrorBody,
+    ))
+)]
+pub struct ApiDoc;
+
+/// Routes that only exist with the `food` feature.
+#[cfg(feature = "food")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(super::food::nutrition_handler, super::food::recipe_handler),
+    components(schemas(
+        super::food::NutritionRequest,
+        super::food::RecipeRequest,
+        super::food::NutritionResponse,
+        crate::food::api::spoonacular::Recipe,
+        crate::food::api::spoonacular::Ingredient,
+        crate::food::api::spoonacular::InstructionSection,
+        crate::food::api::spoonacular::InstructionStep,
+        crate::food::api::spoonacular::NutritionInfo,
+        crate::food::api::spoonacular::Nutrient,
+        crate::food::api::usda::FoodNutrition,
+        crate::food::api::usda::ComponentNutrition,
+    ))
+)]
+struct FoodApiDoc;
+
+/// Routes that only exist with the `upload` feature.
+#[cfg(feature = "upload")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(super::upload::upload_handler),
+    components(schemas(
+        super::upload::UploadForm,
+        super::upload::UploadResponse,
+        super::upload::UploadedInsight,
+    ))
+)]
+struct UploadApiDoc;
+
+/// The full spec, including feature-gated routes.
+pub fn spec() -> utoipa::openapi::OpenApi {
+    let gated: Vec<utoipa::openapi::OpenApi> = vec![
+        #[cfg(feature = "food")]
+        FoodApiDoc::openapi(),
+        #[cfg(feature = "upload")]
+        UploadApiDoc::openapi(),
+    ];
+    gated.into_iter().fold(ApiDoc::openapi(), |mut spec, routes| {
+        spec.merge(routes);
+        spec
+    })
+}
+
+pub(super) async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(spec())
+}
+
+#[cfg(feature = "swagger-ui")]
+pub(super) fn swagger_ui() -> utoipa_swagger_ui::SwaggerUi {
+    utoipa_swagger_ui::SwaggerUi::new("/docs").config(utoipa_swagger_ui::Config::from("/openapi.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_documents_routes_and_error_codes() {
+        let json = spec().to_json().unwrap();
+        let spec: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert!(spec["paths"]["/chat"]["post"].is_object());
+        assert!(spec["paths"]["/kb/{key}"]["get"].is_object());
+
+        let code = &spec["components"]["schemas"]["ApiErrorBody"]["properties"]["code"];
+        assert!(code["description"].as_str().unwrap().contains("rate_limited"));
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{ApiError, CHARACTERS_DIR};
+use crate::personality::{PersonalityError, PersonalityProfile};
+
+/// Profiles loaded for per-request `character` overrides, keyed by name.
+/// Cleared whenever a character file is written or deleted, since bases are shared.
+#[derive(Clone, Default)]
+pub struct CharacterCache {
+    profiles: Arc<RwLock<HashMap<String, PersonalityProfile>>>,
+}
+
+impl CharacterCache {
+    pub async fn load(&self, name: &str) -> Result<PersonalityProfile, ApiError> {
+        let key = name.trim_end_matches(".json").to_string();
+        if let Some(profile) = self.profiles.read().await.get(&key) {
+            return Ok(profile.clone());
+        }
+
+        let profile = match PersonalityProfile::load_from_dir(CHARACTERS_DIR, &key) {
+            Ok(profile) => profile,
+            Err(PersonalityError::NotFound(name)) => {
+                let available = PersonalityProfile::available_in_dir(CHARACTERS_DIR);
+                return Err(ApiError::NotFound(format!(
+                    "Character not found: {}. Available characters: {}",
+                    name,
+                    if available.is_empty() { "none".to_string() } else { available.join(", ") }
+                )));
+            }
+            Err(e) => return Err(ApiError::Internal(format!("Error loading character profile: {}", e))),
+        };
+
+        self.profiles.write().await.insert(key, profile.clone());
+        Ok(profile)
+    }
+
+    pub async fn invalidate(&self) {
+        self.profiles.write().await.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unknown_character_lists_available() {
+        let cache = CharacterCache::default();
+        match cache.load("no_such_character").await {
+            Err(ApiError::NotFound(message)) => {
+                assert!(message.contains("no_such_character"));
+                assert!(message.contains("Available characters:"));
+            }
+            _ => panic!("expected not found"),
+        }
+        assert!(cache.profiles.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_loaded_character_is_cached_until_invalidated() {
+        let cache = CharacterCache::default();
+        let profile = cache.load("simple_assistant.json").await.unwrap();
+        assert_eq!(cache.load("simple_assistant").await.unwrap().name, profile.name);
+        assert_eq!(cache.profiles.read().await.len(), 1);
+
+        cache.invalidate().await;
+        assert!(cache.profiles.read().await.is_empty());
+    }
+}
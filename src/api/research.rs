@@ -0,0 +1,64 @@
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::Stream;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use tokio::sync::mpsc;
+
+use super::{run_web_command_with_progress, ApiError, AppState};
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Deserialize)]
+pub struct ResearchRequest {
+    topic: String,
+}
+
+/// Researches a topic like `POST /web` with `research <topic>`, streaming a `progress`
+/// event per visited source and then one `result` (or `error`) event.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/web/research/stream",
+    request_body = ResearchRequest,
+    responses(
+        (status = 200, description = "Server-sent events: `progress` with {url, visited, total, found}, then `result` with {status} or `error` with {error}", content_type = "text/event-stream"),
+        (status = 400, description = "Invalid request", body = ApiErrorBody)
+    )
+))]
+pub(super) async fn research_stream_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<ResearchRequest>, JsonRejection>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let Json(request) = payload?;
+    let topic = request.topic.trim().to_string();
+    if topic.is_empty() {
+        return Err(ApiError::validation("Please provide a topic to research."));
+    }
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let progress = tx.clone();
+        let mut on_progress = move |update: &_| {
+            let _ = progress.send(event("progress", json!(update)));
+        };
+        let command = format!("research {}", topic);
+        let last = match run_web_command_with_progress(&state, &command, &mut on_progress).await {
+            Ok(analysis) => event("result", json!({ "status": analysis })),
+            Err(e) => event("error", json!({ "error": e.to_string() })),
+        };
+        let _ = tx.send(last);
+    });
+
+    // Ends once the task above finishes and drops the sender
+    let events = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|event| (Ok(event), rx))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn event(name: &str, data: serde_json::Value) -> Event {
+    Event::default().event(name).data(data.to_string())
+}
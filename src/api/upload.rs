@@ -0,0 +1,279 @@
+use axum::{
+    body::Bytes,
+    extract::multipart::{Multipart, MultipartError, MultipartRejection},
+    extract::{FromRef, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{ApiError, AppState};
+use crate::database::Database;
+use crate::personality::PersonalityProfile;
+use crate::providers::document::{self, DocumentError, DocumentProcessor, FileKind};
+
+const DEFAULT_MAX_UPLOAD_SIZE: usize = 10 * 1024 * 1024;
+
+/// Largest accepted upload in bytes, from `MAX_UPLOAD_SIZE`.
+pub(super) fn max_upload_size() -> usize {
+    std::env::var("MAX_UPLOAD_SIZE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_MAX_UPLOAD_SIZE)
+}
+
+/// What the upload handler needs from `AppState`.
+#[derive(Clone)]
+pub struct UploadState {
+    db: Arc<Database>,
+    personality: Arc<RwLock<PersonalityProfile>>,
+}
+
+impl FromRef<AppState> for UploadState {
+    fn from_ref(state: &AppState) -> Self {
+        Self {
+            db: state.db.clone(),
+            personality: state.personality.clone(),
+        }
+    }
+}
+
+/// The multipart form accepted by `POST /document/upload`.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UploadForm {
+    /// A PDF, spreadsheet, Word document, image or text file
+    #[cfg_attr(feature = "openapi", schema(value_type = String, format = Binary))]
+    file: Bytes,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize)]
+pub struct UploadedInsight {
+    text: String,
+    relevance: f32,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize)]
+pub struct UploadResponse {
+    /// The file name prefixed with a hash of its contents, which `doc ask` cites and
+    /// searches can be scoped to
+    document_id: String,
+    insights: Vec<UploadedInsight>,
+}
+
+/// The uploaded copy on disk, removed however the request ends.
+struct TempUpload(PathBuf);
+
+impl Drop for TempUpload {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn multipart_error(error: MultipartError) -> ApiError {
+    if error.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        ApiError::PayloadTooLarge(format!("Uploads are limited to {} bytes", max_upload_size()))
+    } else {
+        ApiError::validation(format!("Invalid multipart body: {}", error.body_text()))
+    }
+}
+
+fn document_error(error: DocumentError) -> ApiError {
+    match error {
+        DocumentError::InvalidExtension
+        | DocumentError::UnsupportedFileType(_)
+        | DocumentError::FileTooLarge(_) => ApiError::validation(error.to_string()),
+        _ => ApiError::Internal(format!("Failed to process document: {}", error)),
+    }
+}
+
+/// Names an upload by its contents as well as its file name, so two different files
+/// uploaded as `report.pdf` don't overwrite each other's chunks.
+fn document_id(file_name: &str, bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let hash: String = digest.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}", hash, file_name)
+}
+
+/// The text of an upload. Extractors work on paths, so it is written to a temporary file
+/// that keeps the extension for type detection, and read off the async runtime.
+async fn extract_upload(file_name: &str, bytes: &[u8]) -> Result<String, ApiError> {
+    let extension = Path::new(file_name).extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let temp = TempUpload(std::env::temp_dir().join(format!("upload-{}.{}", uuid::Uuid::new_v4(), extension)));
+    tokio::fs::write(&temp.0, bytes).await
+        .map_err(|e| ApiError::Internal(format!("Failed to store upload: {}", e)))?;
+
+    let temp_path = temp.0.to_string_lossy().to_string();
+    tokio::task::spawn_blocking(move || {
+        let _temp = temp;
+        document::extract_text(&temp_path)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Text extraction failed: {}", e)))?
+    .map_err(document_error)
+}
+
+/// The name of the uploaded file and the form holding its contents.
+async fn read_file_field(multipart: &mut Multipart) -> Result<(String, UploadForm), ApiError> {
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        // Only the final component, in case a client sends a path
+        let file_name = field.file_name()
+            .and_then(|name| Path::new(name).file_name())
+            .and_then(|name| name.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| ApiError::validation("The 'file' field needs a filename"))?;
+        let file = field.bytes().await.map_err(multipart_error)?;
+        return Ok((file_name, UploadForm { file }));
+    }
+    Err(ApiError::validation("Missing multipart field 'file'"))
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/document/upload",
+    request_body(content = UploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Insights extracted from the document", body = UploadResponse),
+        (status = 400, description = "Missing file or unsupported file type", body = ApiErrorBody),
+        (status = 413, description = "File larger than MAX_UPLOAD_SIZE", body = ApiErrorBody),
+        (status = 503, description = "DeepSeek provider not configured", body = ApiErrorBody)
+    )
+))]
+pub(super) async fn upload_handler(
+    State(state): State<UploadState>,
+    multipart: Result<Multipart, MultipartRejection>,
+) -> Result<Json<UploadResponse>, ApiError> {
+    let mut multipart = multipart.map_err(|e| ApiError::validation(e.body_text()))?;
+    let (file_name, form) = read_file_field(&mut multipart).await?;
+    FileKind::from_path(&file_name).map_err(|e| {
+        ApiError::validation(format!("{}. Supported: {}", e, FileKind::EXTENSIONS.join(", ")))
+    })?;
+
+    let api_key = std::env::var("DEEPSEEK_API_KEY")
+        .map_err(|_| ApiError::ProviderUnavailable("DeepSeek provider is not configured".to_string()))?;
+
+    let text = extract_upload(&file_name, &form.file).await?;
+    let document_id = document_id(&file_name, &form.file);
+
+    let system_prompt = state.personality.read().await.generate_system_prompt();
+    let processor = DocumentProcessor::new(api_key, system_prompt).await
+        .map_err(|e| ApiError::ProviderUnavailable(format!("Document processing is unavailable: {}", e)))?;
+    let insights = processor.process_text_as(&document_id, &text).await
+        .map_err(document_error)?;
+
+    for insight in &insights {
+        state.db.save_document_insight(
+            document_id.clone(),
+            insight.text.clone(),
+            insight.relevance,
+            "analysis".to_string(),
+        ).await?;
+    }
+
+    Ok(Json(UploadResponse {
+        document_id,
+        insights: insights.into_iter()
+            .map(|insight| UploadedInsight { text: insight.text, relevance: insight.relevance })
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::DefaultBodyLimit, routing::post, Router};
+    use reqwest::StatusCode;
+
+    const BOUNDARY: &str = "upload-test-boundary";
+
+    async fn serve(limit: usize) -> String {
+        let state = UploadState {
+            db: Arc::new(Database::new(":memory:").await.unwrap()),
+            personality: Arc::new(RwLock::new(PersonalityProfile {
+                name: "tester".to_string(),
+                attributes: serde_json::json!({ "description": "a test bot" }),
+            })),
+        };
+        let app = Router::new()
+            .route("/document/upload", post(upload_handler).layer(DefaultBodyLimit::max(limit)))
+            .with_state(state);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/document/upload", addr)
+    }
+
+    async fn upload(url: &str, file_name: &str, contents: &str) -> reqwest::Response {
+        let body = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{f}\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n{c}\r\n--{b}--\r\n",
+            b = BOUNDARY,
+            f = file_name,
+            c = contents
+        );
+        reqwest::Client::new()
+            .post(url)
+            .header("Content-Type", format!("multipart/form-data; boundary={}", BOUNDARY))
+            .body(body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unsupported_and_oversized_files() {
+        let url = serve(1024).await;
+
+        let response = upload(&url, "archive.zip", "PK").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["message"].as_str().unwrap().contains("zip"));
+
+        let response = upload(&url, "big.txt", &"x".repeat(4096)).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[test]
+    fn test_same_name_uploads_get_distinct_ids() {
+        let first = document_id("report.txt", b"Revenue grew 12%");
+        let second = document_id("report.txt", b"Revenue fell 3%");
+        assert_ne!(first, second);
+        assert!(first.ends_with("-report.txt"));
+        assert_eq!(first, document_id("report.txt", b"Revenue grew 12%"));
+    }
+
+    #[tokio::test]
+    async fn test_extracts_upload_text_off_the_runtime() {
+        let text = extract_upload("notes.md", b"# Notes\nShip on Friday.").await.unwrap();
+        assert!(text.contains("Ship on Friday."));
+
+        let error = extract_upload("scan.pdf", b"not a pdf").await.unwrap_err();
+        assert!(matches!(error, ApiError::Internal(_)));
+    }
+
+    // Needs Qdrant, DEEPSEEK_API_KEY and OPENAI_API_KEY: cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn test_upload_returns_insights() {
+        let url = serve(max_upload_size()).await;
+        let fixture = "Quarterly report. Revenue grew 12% year over year, driven by subscriptions. \
+            Operating costs fell 4% after the move to a smaller office.";
+
+        let response = upload(&url, "report.txt", fixture).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["document_id"].as_str().unwrap().ends_with("-report.txt"));
+        assert!(!body["insights"].as_array().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,258 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use super::batch::JobStatus;
+use super::{run_web_command, ApiError, AppState};
+
+pub(super) const SIGNATURE_HEADER: &str = "X-Signature-256";
+const CALLBACK_ATTEMPTS: u32 = 3;
+const CALLBACK_BASE_DELAY: Duration = Duration::from_secs(2);
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(10);
+const JOB_RETENTION_MINUTES: i64 = 60;
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Serialize, Clone)]
+pub struct WebJob {
+    id: String,
+    command: String,
+    status: JobStatus,
+    created_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// Whether the final result reached the callback URL
+    callback_delivered: bool,
+}
+
+/// In-memory store for `/web` jobs started with a `callback_url`.
+#[derive(Clone, Default)]
+pub struct WebJobStore {
+    jobs: Arc<RwLock<HashMap<String, WebJob>>>,
+}
+
+impl WebJobStore {
+    async fn create(&self, command: &str) -> WebJob {
+        let job = WebJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            command: command.to_string(),
+            status: JobStatus::Running,
+            created_at: Utc::now(),
+            finished_at: None,
+            result: None,
+            error: None,
+            callback_delivered: false,
+        };
+
+        let mut jobs = self.jobs.write().await;
+        let cutoff = Utc::now() - chrono::Duration::minutes(JOB_RETENTION_MINUTES);
+        jobs.retain(|_, job| job.finished_at.is_none_or(|finished| finished > cutoff));
+        jobs.insert(job.id.clone(), job.clone());
+        job
+    }
+
+    async fn get(&self, id: &str) -> Option<WebJob> {
+        self.jobs.read().await.get(id).cloned()
+    }
+
+    async fn finish(&self, id: &str, outcome: &Result<String, ApiError>) -> Option<WebJob> {
+        let mut jobs = self.jobs.write().await;
+        let job = jobs.get_mut(id)?;
+        job.finished_at = Some(Utc::now());
+        match outcome {
+            Ok(result) => {
+                job.status = JobStatus::Completed;
+                job.result = Some(result.clone());
+            }
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(e.public_message());
+            }
+        }
+        Some(job.clone())
+    }
+
+    async fn mark_delivered(&self, id: &str) {
+        if let Some(job) = self.jobs.write().await.get_mut(id) {
+            job.callback_delivered = true;
+        }
+    }
+}
+
+/// Secret used to sign callbacks, from `WEBHOOK_SECRET`.
+pub(super) fn webhook_secret() -> Option<String> {
+    std::env::var("WEBHOOK_SECRET").ok().filter(|s| !s.is_empty())
+}
+
+pub(super) fn validate_callback_url(callback_url: &str) -> Result<(), ApiError> {
+    match url::Url::parse(callback_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(()),
+        _ => Err(ApiError::validation(format!("Invalid callback_url: {}", callback_url))),
+    }
+}
+
+/// Hex-encoded HMAC-SHA256 of `body`, sent as `X-Signature-256: sha256=<hex>`.
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// POSTs `body` to the callback, retrying with exponential backoff. Returns the attempts used.
+async fn deliver_callback(
+    client: &reqwest::Client,
+    callback_url: &str,
+    secret: &str,
+    body: Vec<u8>,
+    base_delay: Duration,
+) -> Result<u32, String> {
+    let signature = format!("sha256={}", sign_payload(secret, &body));
+    let mut last_error = String::new();
+
+    for attempt in 1..=CALLBACK_ATTEMPTS {
+        let response = client.post(callback_url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, &signature)
+            .body(body.clone())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => return Ok(attempt),
+            Ok(response) => last_error = format!("callback returned {}", response.status()),
+            Err(e) => last_error = format!("callback request failed: {}", e),
+        }
+
+        if attempt < CALLBACK_ATTEMPTS {
+            tokio::time::sleep(base_delay * 2u32.pow(attempt - 1)).await;
+        }
+    }
+    Err(last_error)
+}
+
+/// Starts a web command in the background and reports the result to `callback_url`.
+pub(super) async fn spawn_web_job(state: AppState, command: String, callback_url: String, secret: String) -> WebJob {
+    let job = state.web_jobs.create(&command).await;
+    let job_id = job.id.clone();
+
+    tokio::spawn(async move {
+        let outcome = run_web_command(&state, &command).await;
+        let Some(finished) = state.web_jobs.finish(&job_id, &outcome).await else {
+            return;
+        };
+
+        let body = match serde_json::to_vec(&finished) {
+            Ok(body) => body,
+            Err(e) => {
+                eprintln!("Failed to serialize web job {}: {}", job_id, e);
+                return;
+            }
+        };
+        let client = match reqwest::Client::builder().timeout(CALLBACK_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("Failed to create callback client for job {}: {}", job_id, e);
+                return;
+            }
+        };
+
+        match deliver_callback(&client, &callback_url, &secret, body, CALLBACK_BASE_DELAY).await {
+            Ok(_) => state.web_jobs.mark_delivered(&job_id).await,
+            Err(e) => eprintln!("Giving up on callback for web job {}: {}", job_id, e),
+        }
+    });
+
+    job
+}
+
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/web/jobs/{id}",
+    params(("id" = String, Path, description = "Job id returned by /web")),
+    responses(
+        (status = 200, description = "Job status, with the result once finished", body = WebJob),
+        (status = 404, description = "Not found", body = ApiErrorBody)
+    )
+))]
+pub(super) async fn get_web_job_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<WebJob>, ApiError> {
+    state.web_jobs.get(&id).await
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("Web job not found: {}", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::{HeaderMap, StatusCode}, routing::post, Router};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_sign_payload_matches_rfc4231() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_callback_retries_until_delivered() {
+        let hits = Arc::new(AtomicU32::new(0));
+        let signatures = Arc::new(RwLock::new(Vec::new()));
+        let (hits_ref, signatures_ref) = (hits.clone(), signatures.clone());
+
+        let app = Router::new().route("/hook", post(move |headers: HeaderMap| async move {
+            if let Some(sig) = headers.get(SIGNATURE_HEADER) {
+                signatures_ref.write().await.push(sig.to_str().unwrap().to_string());
+            }
+            // Fail the first attempt so the retry path is exercised
+            if hits_ref.fetch_add(1, Ordering::SeqCst) == 0 {
+                StatusCode::INTERNAL_SERVER_ERROR
+            } else {
+                StatusCode::OK
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let body = br#"{"status":"completed"}"#.to_vec();
+        let attempts = deliver_callback(
+            &reqwest::Client::new(),
+            &format!("http://{}/hook", addr),
+            "secret",
+            body.clone(),
+            Duration::from_millis(10),
+        ).await.unwrap();
+
+        assert_eq!(attempts, 2);
+        let expected = format!("sha256={}", sign_payload("secret", &body));
+        assert_eq!(*signatures.read().await, vec![expected.clone(), expected]);
+    }
+
+    #[test]
+    fn test_callback_url_must_be_http() {
+        assert!(validate_callback_url("https://example.com/hook").is_ok());
+        assert!(validate_callback_url("ftp://example.com/hook").is_err());
+        assert!(validate_callback_url("not a url").is_err());
+    }
+}
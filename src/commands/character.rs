@@ -1,14 +1,64 @@
-use crate::personality::PersonalityProfile;
-use std::path::Path;
-use std::fs;
+use super::error::{CommandError, CommandResult};
+use super::keys;
+use crate::personality::{character_file_stem, PersonalityError, PersonalityProfile};
+use crate::providers::traits::CompletionProvider;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
 use colored::Colorize;
 
+/// Names handled in code rather than loaded from the characters directory.
+pub const BUILTIN_CHARACTERS: &[&str] = &["helpful", "friendly", "expert"];
+
+/// How many earlier characters `load back` can return to.
+const STACK_LIMIT: usize = 10;
+
+/// Characters switched away from, most recent last. Whole profiles are kept, so going
+/// back works even if a character's file has been deleted since.
+#[derive(Debug, Default)]
+pub struct CharacterStack {
+    previous: Vec<PersonalityProfile>,
+}
+
+impl CharacterStack {
+    /// Remembers `profile`, forgetting the oldest entry once there are [`STACK_LIMIT`].
+    fn push(&mut self, profile: PersonalityProfile) {
+        if self.previous.len() == STACK_LIMIT {
+            self.previous.remove(0);
+        }
+        self.previous.push(profile);
+    }
+
+    fn pop(&mut self) -> Option<PersonalityProfile> {
+        self.previous.pop()
+    }
+
+    fn print(&self) {
+        if self.previous.is_empty() {
+            return;
+        }
+        let names: Vec<&str> = self.previous.iter().rev().map(|p| p.name.as_str()).collect();
+        println!("\n  Previous (load back returns to the first): {}", names.join(" ← "));
+    }
+}
+
 pub fn handle_command(
     input: &str,
-    current_personality: &mut PersonalityProfile
-) -> Result<(), String> {
+    current_personality: &mut PersonalityProfile,
+    stack: &mut CharacterStack,
+) -> CommandResult {
     if input.eq_ignore_ascii_case("chars") || input.eq_ignore_ascii_case("characters") {
         list_available_characters();
+        println!("\n  Current: {}", current_personality.name.bright_yellow());
+        stack.print();
+        return Ok(());
+    }
+    else if input.eq_ignore_ascii_case("load back") || input.eq_ignore_ascii_case("char pop") {
+        let previous = stack.pop()
+            .ok_or_else(|| CommandError::NotFound("No previous character to go back to.".to_string()))?;
+        println!("\n↩️ Back to: {}", previous.name.bright_yellow());
+        *current_personality = previous;
         return Ok(());
     }
     else if input.eq_ignore_ascii_case("load") {
@@ -26,26 +76,175 @@ pub fn handle_command(
             return Ok(());
         } 
         
-        let profile = load_personality_from_filename(char_name)
-            .ok_or_else(|| format!("Failed to load character: {}. Type 'chars' to see available characters.", char_name))?;
+        let profile = load_personality_from_filename(char_name).map_err(|e| {
+            let message = format!("Failed to load character: {} ({}). Type 'chars' to see available characters.", char_name, e);
+            match e {
+                PersonalityError::NotFound(_) => CommandError::NotFound(message),
+                PersonalityError::Io(_) => CommandError::Io(message),
+                _ => CommandError::Usage(message),
+            }
+        })?;
             
         let name = profile.name.clone();
         let description = profile.get_str("description")
             .unwrap_or("an AI assistant")
             .to_string();
         println!("\n🔄 Successfully switched to: {} - {}", name.bright_yellow(), description);
-        *current_personality = profile;
+        if profile.name != current_personality.name {
+            stack.push(std::mem::replace(current_personality, profile));
+        }
         return Ok(());
     }
-    Err("Unknown character command".to_string())
+    Err(CommandError::Usage("Unknown character command".to_string()))
+}
+
+/// The answers collected by `char new`.
+#[derive(Debug, Default)]
+struct CharacterDraft {
+    name: String,
+    description: String,
+    style: String,
+    motto: String,
+    traits: Vec<String>,
+    interests: Vec<String>,
+    emoji: String,
+    examples: Vec<String>,
+}
+
+impl CharacterDraft {
+    /// A profile with the attributes `generate_system_prompt` reads; blank answers are left out.
+    fn into_profile(self) -> PersonalityProfile {
+        let mut attributes = Map::new();
+        for (key, value) in [
+            ("description", self.description),
+            ("style", self.style),
+            ("motto", self.motto),
+            ("emoji", self.emoji),
+        ] {
+            if !value.is_empty() {
+                attributes.insert(key.to_string(), Value::String(value));
+            }
+        }
+        for (key, values) in [("traits", self.traits), ("interests", self.interests), ("examples", self.examples)] {
+            if !values.is_empty() {
+                attributes.insert(key.to_string(), Value::from(values));
+            }
+        }
+        PersonalityProfile { name: self.name, attributes: Value::Object(attributes) }
+    }
+}
+
+/// Splits a comma-separated answer into its non-empty items.
+fn split_list(input: &str) -> Vec<String> {
+    input.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn suggestion_prompt(name: &str, description: &str) -> String {
+    format!(
+        "Suggest personality traits and interests for a chat character named {} who is {}. \
+        Reply with exactly two lines and nothing else:\n\
+        Traits: <five comma-separated traits>\n\
+        Interests: <five comma-separated interests>",
+        name, description
+    )
+}
+
+/// The traits and interests in a reply to [`suggestion_prompt`].
+fn parse_suggestions(reply: &str) -> (Vec<String>, Vec<String>) {
+    let field = |label: &str| {
+        reply.lines()
+            .find_map(|line| {
+                let line = line.trim().trim_start_matches(['*', '-']).trim();
+                line.get(..label.len())
+                    .filter(|prefix| prefix.eq_ignore_ascii_case(label))
+                    .map(|_| split_list(line[label.len()..].trim_start_matches([':', '*']).trim()))
+            })
+            .unwrap_or_default()
+    };
+    (field("Traits"), field("Interests"))
+}
+
+fn ask(editor: &mut DefaultEditor, prompt: &str, initial: &str) -> CommandResult<String> {
+    match editor.readline_with_initial(prompt, (initial, "")) {
+        Ok(line) => Ok(line.trim().to_string()),
+        Err(ReadlineError::Interrupted | ReadlineError::Eof) => Err(CommandError::Usage("Character creation cancelled".to_string())),
+        Err(e) => Err(CommandError::Io(format!("Failed to read input: {}", e))),
+    }
+}
+
+/// Writes `profile` to `<dir>/<file stem>.json`, creating `dir` if needed.
+fn save_character(dir: &Path, profile: &PersonalityProfile) -> CommandResult<PathBuf> {
+    std::fs::create_dir_all(dir).map_err(|e| CommandError::Io(format!("Failed to create {}: {}", dir.display(), e)))?;
+    let path = dir.join(format!("{}.json", character_file_stem(&profile.name)));
+    let json = serde_json::to_string_pretty(profile).map_err(|e| CommandError::Io(e.to_string()))?;
+    std::fs::write(&path, json).map_err(|e| CommandError::Io(format!("Failed to write {}: {}", path.display(), e)))?;
+    Ok(path)
+}
+
+/// `char new`: asks for each attribute, previews the system prompt and saves the character.
+pub async fn create_character(
+    provider: &Box<dyn CompletionProvider + Send + Sync>,
+    characters_dir: &str,
+) -> CommandResult {
+    let mut editor = DefaultEditor::new().map_err(|e| CommandError::Io(format!("Failed to start prompt: {}", e)))?;
+    println!("\n🎭 New character (Ctrl-C to cancel)");
+
+    let mut draft = CharacterDraft::default();
+    while character_file_stem(&draft.name).is_empty() {
+        draft.name = ask(&mut editor, "Name: ", "")?;
+    }
+    let path = Path::new(characters_dir).join(format!("{}.json", character_file_stem(&draft.name)));
+    if path.exists() && !keys::confirm(&format!("{} already exists. Overwrite?", path.display())) {
+        println!("Character creation cancelled.");
+        return Ok(());
+    }
+
+    draft.description = ask(&mut editor, "Description (e.g. a grumpy pirate chef): ", "")?;
+
+    let (mut suggested_traits, mut suggested_interests) = (Vec::new(), Vec::new());
+    if !draft.description.is_empty() && keys::confirm("Suggest traits and interests with the active provider?") {
+        match provider.complete(&suggestion_prompt(&draft.name, &draft.description)).await {
+            Ok(reply) => (suggested_traits, suggested_interests) = parse_suggestions(&reply),
+            Err(e) => eprintln!("{}", format!("Warning: No suggestions: {}", e).yellow()),
+        }
+    }
+
+    draft.style = ask(&mut editor, "Communication style: ", "")?;
+    draft.motto = ask(&mut editor, "Motto: ", "")?;
+    draft.traits = split_list(&ask(&mut editor, "Traits (comma-separated): ", &suggested_traits.join(", "))?);
+    draft.interests = split_list(&ask(&mut editor, "Interests (comma-separated): ", &suggested_interests.join(", "))?);
+    draft.emoji = ask(&mut editor, "Emoji: ", "")?;
+    println!("Example responses, one per line (empty line to finish):");
+    loop {
+        let example = ask(&mut editor, "> ", "")?;
+        if example.is_empty() {
+            break;
+        }
+        draft.examples.push(example);
+    }
+
+    let profile = draft.into_profile();
+    println!("\n📝 System prompt preview:\n{}", profile.generate_system_prompt().dimmed());
+    if !keys::confirm("\nSave this character?") {
+        println!("Character creation cancelled.");
+        return Ok(());
+    }
+
+    let path = save_character(Path::new(characters_dir), &profile)?;
+    println!("✅ Saved {}. Switch to it with: load {}", path.display().to_string().cyan(), character_file_stem(&profile.name));
+    Ok(())
 }
 
 fn list_available_characters() {
     println!("\nAvailable Characters:");
     println!("  Built-in:");
-    println!("    - helpful");
-    println!("    - friendly");
-    println!("    - expert");
+    for name in BUILTIN_CHARACTERS {
+        println!("    - {}", name);
+    }
     
     let characters_dir = Path::new("characters");
     if characters_dir.exists() {
@@ -62,10 +261,10 @@ fn list_available_characters() {
     }
 }
 
-fn load_personality_from_filename(filename: &str) -> Option<PersonalityProfile> {
+fn load_personality_from_filename(filename: &str) -> Result<PersonalityProfile, PersonalityError> {
     // Handle built-in characters
     match filename.to_lowercase().as_str() {
-        "helpful" => return Some(PersonalityProfile {
+        "helpful" => return Ok(PersonalityProfile {
             name: "Helpful Assistant".to_string(),
             attributes: serde_json::json!({
                 "description": "a helpful AI assistant",
@@ -84,7 +283,7 @@ fn load_personality_from_filename(filename: &str) -> Option<PersonalityProfile>
                 ]
             }),
         }),
-        "friendly" => return Some(PersonalityProfile {
+        "friendly" => return Ok(PersonalityProfile {
             name: "Friendly Companion".to_string(),
             attributes: serde_json::json!({
                 "description": "a friendly and casual companion",
@@ -103,7 +302,7 @@ fn load_personality_from_filename(filename: &str) -> Option<PersonalityProfile>
                 ]
             }),
         }),
-        "expert" => return Some(PersonalityProfile {
+        "expert" => return Ok(PersonalityProfile {
             name: "Expert Advisor".to_string(),
             attributes: serde_json::json!({
                 "description": "a knowledgeable expert advisor",
@@ -125,18 +324,65 @@ fn load_personality_from_filename(filename: &str) -> Option<PersonalityProfile>
         _ => {}
     }
 
-    // Handle custom characters from JSON files
-    let mut path = Path::new("characters").join(filename);
-    if !path.exists() && !filename.ends_with(".json") {
-        path = Path::new("characters").join(format!("{}.json", filename));
+    // Handle custom characters from JSON files, resolving any "base" inheritance
+    PersonalityProfile::load_from_dir("characters", filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str) -> PersonalityProfile {
+        PersonalityProfile { name: name.to_string(), attributes: serde_json::json!({}) }
     }
 
-    if path.exists() {
-        if let Ok(file) = fs::File::open(path) {
-            if let Ok(profile) = serde_json::from_reader::<_, PersonalityProfile>(file) {
-                return Some(profile);
-            }
+    #[test]
+    fn test_load_back_restores_previous_characters() {
+        let mut current = profile("Deleted Since");
+        let mut stack = CharacterStack::default();
+
+        handle_command("load helpful", &mut current, &mut stack).unwrap();
+        handle_command("load expert", &mut current, &mut stack).unwrap();
+        assert_eq!(current.name, "Expert Advisor");
+
+        handle_command("load back", &mut current, &mut stack).unwrap();
+        assert_eq!(current.name, "Helpful Assistant");
+        handle_command("char pop", &mut current, &mut stack).unwrap();
+        assert_eq!(current.name, "Deleted Since");
+        assert!(handle_command("load back", &mut current, &mut stack).is_err());
+
+        for i in 0..=STACK_LIMIT {
+            stack.push(profile(&i.to_string()));
         }
+        assert_eq!(stack.previous.len(), STACK_LIMIT);
+        assert_eq!(stack.previous[0].name, "1");
+    }
+
+    #[test]
+    fn test_draft_saves_a_loadable_character() {
+        let (traits, interests) = parse_suggestions("Traits: gruff, loyal, inventive\n**Interests:** knots, stews");
+        assert_eq!(traits, vec!["gruff", "loyal", "inventive"]);
+        assert_eq!(interests, vec!["knots", "stews"]);
+
+        let draft = CharacterDraft {
+            name: "Captain Stew".to_string(),
+            description: "a grumpy pirate chef".to_string(),
+            style: "salty".to_string(),
+            traits,
+            interests: split_list("knots, , stews"),
+            examples: vec!["Arr, more salt!".to_string()],
+            ..Default::default()
+        };
+        let dir = std::env::temp_dir().join(format!("characters_{}", uuid::Uuid::new_v4()));
+        let path = save_character(&dir, &draft.into_profile()).unwrap();
+        assert_eq!(path.file_name().unwrap(), "captain_stew.json");
+
+        let loaded = PersonalityProfile::load_from_dir(&dir, "captain_stew").unwrap();
+        let prompt = loaded.generate_system_prompt();
+        assert!(prompt.starts_with("You are Captain Stew, a grumpy pirate chef."));
+        assert!(prompt.contains("Your key traits are: gruff, loyal, inventive"));
+        assert!(prompt.contains("Your interests include: knots, stews"));
+        assert!(loaded.get_str("motto").is_none());
+        std::fs::remove_dir_all(dir).unwrap();
     }
-    None
-}
\ No newline at end of file
+}
@@ -0,0 +1,130 @@
+use crate::llm::memory::MemoryManager;
+use crate::llm::EmbeddingGenerator;
+use crate::database::{ConversationRecord, Database};
+use crate::providers::traits::CompletionProvider;
+
+/// Memories from the current session replayed into each prompt (user and assistant count
+/// separately), unless the `context_window` setting says otherwise.
+pub const RECENT_TURNS: usize = 10;
+
+/// Embeds `text`, or returns `None` if there is no real embedding model configured.
+pub async fn embed(embedder: &EmbeddingGenerator, text: &str) -> Option<Vec<f32>> {
+    if !embedder.is_semantic() {
+        return None;
+    }
+    match embedder.generate_embedding(text).await {
+        Ok(embedding) if embedding.iter().any(|&x| x != 0.0) => Some(embedding),
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("Warning: Failed to generate embedding: {}", e);
+            None
+        }
+    }
+}
+
+/// Where [`remember_exchange`] put an exchange, so a regenerated reply can replace it.
+#[derive(Debug, Clone, Default)]
+pub struct StoredExchange {
+    pub conversation_id: Option<i64>,
+    pub user_memory_id: Option<String>,
+    pub response_memory_id: Option<String>,
+}
+
+/// Saves the exchange to SQLite and stores both sides in memory under the active session,
+/// tagged with `provider` when `MEMORY_TAGGING=llm`. Failures are reported as warnings so
+/// they never break the chat itself.
+pub async fn remember_exchange(
+    mut record: ConversationRecord,
+    user_embedding: Option<Vec<f32>>,
+    embedder: &EmbeddingGenerator,
+    memory_manager: Option<&MemoryManager>,
+    db: &Database,
+    provider: &dyn CompletionProvider,
+) -> StoredExchange {
+    let mut stored = StoredExchange::default();
+    let input = record.user_input.clone();
+    let response = record.ai_response.clone();
+    record.session_id = memory_manager.and_then(|m| m.current_session()).map(|s| s.id.clone());
+    match db.save_exchange(record).await {
+        Ok(id) => stored.conversation_id = Some(id),
+        Err(e) => eprintln!("Warning: Failed to save conversation: {}", e),
+    }
+
+    let (Some(memory_manager), Some(user_embedding)) = (memory_manager, user_embedding) else {
+        return stored;
+    };
+    match memory_manager.store_memory_analyzed(&input, "user", user_embedding, None, Some(provider)).await {
+        Ok(id) => stored.user_memory_id = Some(id),
+        Err(e) => {
+            eprintln!("Warning: {}", e);
+            return stored;
+        }
+    }
+    stored.response_memory_id = store_response(&response, embedder, memory_manager, provider).await;
+    stored
+}
+
+async fn store_response(
+    response: &str,
+    embedder: &EmbeddingGenerator,
+    memory_manager: &MemoryManager,
+    provider: &dyn CompletionProvider,
+) -> Option<String> {
+    let response_embedding = embed(embedder, response).await?;
+    memory_manager.store_memory_analyzed(response, "assistant", response_embedding, None, Some(provider)).await
+        .map_err(|e| eprintln!("Warning: {}", e))
+        .ok()
+}
+
+/// Puts a regenerated reply in place of the one [`remember_exchange`] stored, instead of
+/// saving a second exchange.
+pub async fn replace_response(
+    stored: &StoredExchange,
+    record: &ConversationRecord,
+    embedder: &EmbeddingGenerator,
+    memory_manager: Option<&MemoryManager>,
+    db: &Database,
+    provider: &dyn CompletionProvider,
+) -> StoredExchange {
+    let mut replaced = stored.clone();
+    if let Some(id) = stored.conversation_id {
+        if let Err(e) = db.replace_response(id, record.ai_response.clone(), record.provider.clone(), record.response_tokens).await {
+            eprintln!("Warning: Failed to update conversation: {}", e);
+        }
+    }
+
+    // Only exchanges whose message made it into memory get their reply swapped there
+    let Some(memory_manager) = memory_manager.filter(|_| stored.user_memory_id.is_some()) else {
+        return replaced;
+    };
+    if let Some(old) = &stored.response_memory_id {
+        if let Err(e) = memory_manager.forget(vec![old.clone()]).await {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    replaced.response_memory_id = store_response(&record.ai_response, embedder, memory_manager, provider).await;
+    replaced
+}
+
+/// `prompt` with an extra instruction for `again but ...`, placed before the trailing
+/// `Assistant:` cue when the prompt carries conversation context.
+pub fn with_instruction(prompt: &str, instruction: &str) -> String {
+    match prompt.strip_suffix("\nAssistant:") {
+        Some(body) => format!("{}\n{}\nAssistant:", body, instruction),
+        None => format!("{}\n\n{}", prompt, instruction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_instruction() {
+        assert_eq!(with_instruction("tell a joke", "make it shorter"), "tell a joke\n\nmake it shorter");
+        assert_eq!(
+            with_instruction("Recent Conversation:\nuser: hi\n\nUser: tell a joke\nAssistant:", "make it shorter"),
+            "Recent Conversation:\nuser: hi\n\nUser: tell a joke\nmake it shorter\nAssistant:"
+        );
+    }
+}
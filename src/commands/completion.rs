@@ -0,0 +1,193 @@
+use colored::Colorize;
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use crate::personality::PersonalityProfile;
+use super::registry;
+
+const PROVIDERS: &[&str] = &["deepseek", "openai", "openrouter", "mistral", "gemini"];
+
+/// Commands whose argument is a path on disk.
+const PATH_COMMANDS: &[&str] = &["doc analyze ", "doc summary ", "doc extract ", "doc ocr ", "doc batch ", "doc info ", "doc delete ", "export md ", "export json "];
+
+const HISTORY_FILE: &str = ".airysz_history";
+
+/// Opens and closes a multi-line message in the REPL.
+const BLOCK_DELIMITER: &str = "\"\"\"";
+
+/// Where REPL history is kept between runs: `~/.airysz_history`.
+pub fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+/// Tab completion and argument hints for the interactive prompt.
+pub struct ReplHelper {
+    characters: Vec<String>,
+    files: FilenameCompleter,
+}
+
+impl ReplHelper {
+    pub fn new() -> Self {
+        Self {
+            characters: PersonalityProfile::available_in_dir("characters"),
+            files: FilenameCompleter::new(),
+        }
+    }
+}
+
+impl Default for ReplHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The text a usage completes to: the command words, plus a space if it takes arguments.
+fn completion_of(usage: &str) -> String {
+    match usage.find(" <").into_iter().chain(usage.find(" [")).min() {
+        Some(args) => format!("{} ", &usage[..args]),
+        None => usage.to_string(),
+    }
+}
+
+fn complete_words(line: &str, characters: &[String]) -> (usize, Vec<String>) {
+    if let Some(name) = line.strip_prefix("load ") {
+        return (5, characters.iter().filter(|c| c.starts_with(name)).cloned().collect());
+    }
+    if let Some(provider) = line.strip_prefix("retry with ") {
+        return (11, PROVIDERS.iter().filter(|p| p.starts_with(provider)).map(|p| p.to_string()).collect());
+    }
+    if let Some(provider) = line.strip_prefix("keys set ") {
+        return (9, PROVIDERS.iter().filter(|p| p.starts_with(provider)).map(|p| p.to_string()).collect());
+    }
+    if let Some(provider) = line.strip_prefix("use ") {
+        return (4, PROVIDERS.iter().filter(|p| p.starts_with(provider)).map(|p| p.to_string()).collect());
+    }
+
+    let mut commands: Vec<String> = Vec::new();
+    for command in registry::commands() {
+        let completion = completion_of(command.usage);
+        if completion.starts_with(line) && !commands.contains(&completion) {
+            commands.push(completion);
+        }
+    }
+    (0, commands)
+}
+
+fn hint_for(line: &str) -> Option<String> {
+    if line.is_empty() {
+        return None;
+    }
+    registry::commands()
+        .map(|command| command.usage)
+        .find(|usage| usage.starts_with(line) && usage.len() > line.len())
+        .map(|usage| usage[line.len()..].to_string())
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        if PATH_COMMANDS.iter().any(|command| before.starts_with(command)) {
+            return self.files.complete(line, pos, ctx);
+        }
+
+        let (start, candidates) = complete_words(before, &self.characters);
+        Ok((start, candidates.into_iter()
+            .map(|c| Pair { display: c.clone(), replacement: c })
+            .collect()))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        hint_for(line)
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(hint.dimmed().to_string())
+    }
+}
+
+/// Whether `input` opened a `"""` block that hasn't been closed yet.
+fn is_open_block(input: &str) -> bool {
+    input.trim_start()
+        .strip_prefix(BLOCK_DELIMITER)
+        .is_some_and(|rest| !rest.contains(BLOCK_DELIMITER))
+}
+
+/// The chat message in a multi-line entry, which must skip command matching: the body of a
+/// `"""` block, or a bracketed paste (any entry spanning several lines) verbatim.
+pub fn multiline_message(input: &str) -> Option<String> {
+    if let Some(body) = input.trim_start().strip_prefix(BLOCK_DELIMITER) {
+        let body = body.trim_end();
+        let body = body.strip_suffix(BLOCK_DELIMITER).unwrap_or(body);
+        return Some(body.trim_matches(|c| c == '\n' || c == '\r').to_string());
+    }
+    input.contains('\n').then(|| input.to_string())
+}
+
+impl Validator for ReplHelper {
+    // Enter inside an open block adds a line instead of submitting
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(if is_open_block(ctx.input()) {
+            ValidationResult::Incomplete
+        } else {
+            ValidationResult::Valid(None)
+        })
+    }
+}
+
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completes_commands_characters_and_providers() {
+        let (start, candidates) = complete_words("sess", &[]);
+        assert_eq!(start, 0);
+        assert_eq!(candidates, vec!["session new ", "session list", "session resume ", "session summary"]);
+
+        let (_, candidates) = complete_words("memory c", &[]);
+        assert_eq!(candidates, vec!["memory compact ", "memory clear --session ", "memory clear --all"]);
+
+        let characters = vec!["friendly".to_string(), "funny".to_string(), "helpful".to_string()];
+        assert_eq!(complete_words("load f", &characters), (5, vec!["friendly".to_string(), "funny".to_string()]));
+        assert_eq!(complete_words("use open", &[]), (4, vec!["openai".to_string(), "openrouter".to_string()]));
+    }
+
+    #[test]
+    fn test_multiline_blocks() {
+        assert!(is_open_block("\"\"\"fn main() {"));
+        assert!(!is_open_block("\"\"\"fn main() {}\n\"\"\""));
+        assert!(!is_open_block("doc analyze notes.txt"));
+
+        let block = "\"\"\"\ndoc analyze is broken:\n    web links x\n\"\"\"";
+        assert_eq!(multiline_message(block).unwrap(), "doc analyze is broken:\n    web links x");
+
+        let pasted = "web links are odd\nfn main() {}";
+        assert_eq!(multiline_message(pasted).as_deref(), Some(pasted));
+        assert_eq!(multiline_message("web links https://example.com"), None);
+    }
+
+    #[test]
+    fn test_hints_remaining_usage() {
+        assert_eq!(hint_for("session res").as_deref(), Some("ume <id>"));
+        assert_eq!(hint_for("load ").as_deref(), Some("<name>"));
+        assert_eq!(hint_for("load friendly"), None);
+        assert_eq!(hint_for(""), None);
+    }
+}
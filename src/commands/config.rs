@@ -0,0 +1,80 @@
+use crate::config::{mask_key, ProviderConfig, COMPILED_FEATURES, TUNABLE_SETTINGS};
+use crate::database::VectorBackend;
+use colored::Colorize;
+
+const CONFIG_PROVIDERS: &[&str] = &["openai", "openrouter", "mistral", "gemini", "deepseek"];
+
+const API_KEYS: &[&str] = &[
+    "API_KEY",
+    "OPENAI_API_KEY",
+    "OPENROUTER_API_KEY",
+    "MISTRAL_API_KEY",
+    "GEMINI_API_KEY",
+    "DEEPSEEK_API_KEY",
+    "SPOONACULAR_API_KEY",
+    "USDA_API_KEY",
+];
+
+/// Where things live, as resolved by the command handler.
+pub struct Locations<'a> {
+    pub database: &'a str,
+    pub qdrant_url: &'a str,
+    pub characters_dir: &'a str,
+}
+
+/// Prints the effective configuration for `config`.
+pub fn print_config(
+    active_provider: &str,
+    embeddings: &str,
+    locations: &Locations,
+    settings: &[(String, String)],
+) {
+    println!("\n⚙️ Configuration");
+
+    println!("\n🤖 Providers:");
+    for name in CONFIG_PROVIDERS {
+        let config = ProviderConfig::from_env(name);
+        let marker = if name.eq_ignore_ascii_case(active_provider) { "▶" } else { " " };
+        println!("{} {}", marker, name.cyan());
+        if !config.models.is_empty() {
+            println!("    models:      {}", config.models.join(", "));
+        }
+        if !config.api_url.is_empty() {
+            println!("    api_url:     {}", config.api_url);
+        }
+        println!("    temperature: {}", config.temperature);
+    }
+
+    println!("\n🔑 API keys:");
+    for name in API_KEYS {
+        let status = match std::env::var(name) {
+            Ok(key) if !key.is_empty() => mask_key(&key).green(),
+            _ => "not set".red(),
+        };
+        println!("  {:<20} {}", name, status);
+    }
+
+    println!("\n💾 Storage:");
+    println!("  database:   {}", locations.database);
+    match VectorBackend::from_env() {
+        VectorBackend::Qdrant => println!("  vectors:    qdrant at {}", locations.qdrant_url),
+        VectorBackend::Sqlite => println!("  vectors:    sqlite, in {}", locations.database),
+    }
+    println!("  characters: {}", locations.characters_dir);
+
+    println!("\n🧮 Embeddings: {}", embeddings);
+
+    println!("\n🧩 Features:");
+    for (feature, enabled) in COMPILED_FEATURES {
+        println!("  {:<16} {}", feature, if *enabled { "on".green() } else { "off".dimmed() });
+    }
+
+    println!("\n🎛️ Settings (change with: config set <key> <value>):");
+    for (key, description) in TUNABLE_SETTINGS {
+        let value = settings.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.cyan().to_string())
+            .unwrap_or_else(|| "default".dimmed().to_string());
+        println!("  {:<15} {:<10} {}", key, value, description.dimmed());
+    }
+}
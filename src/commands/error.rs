@@ -0,0 +1,57 @@
+use thiserror::Error;
+
+/// Why a command failed. The REPL shows usage mistakes differently from real failures,
+/// and the API maps each kind to a status code.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum CommandError {
+    /// The command was typed wrong; the message says how to use it
+    #[error("{0}")]
+    Usage(String),
+
+    /// The model provider failed, refused or isn't configured
+    #[error("{0}")]
+    Provider(String),
+
+    /// Reading or writing a file, the terminal, the database or a network service failed
+    #[error("{0}")]
+    Io(String),
+
+    /// What the command refers to doesn't exist
+    #[error("{0}")]
+    NotFound(String),
+
+    /// The vector store failed or is unavailable
+    #[error("{0}")]
+    VectorDb(String),
+
+    /// The user stopped the command with Ctrl-C
+    #[error("{0}")]
+    Interrupted(String),
+}
+
+pub type CommandResult<T = ()> = Result<T, CommandError>;
+
+impl CommandError {
+    /// Whether the user can fix this by typing the command differently.
+    pub fn is_usage(&self) -> bool {
+        matches!(self, CommandError::Usage(_))
+    }
+}
+
+impl From<crate::llm::context::ContextError> for CommandError {
+    fn from(err: crate::llm::context::ContextError) -> Self {
+        match err {
+            crate::llm::context::ContextError::Embedding(_) => CommandError::Provider(err.to_string()),
+            crate::llm::context::ContextError::Search(_) => CommandError::VectorDb(err.to_string()),
+        }
+    }
+}
+
+impl From<crate::database::DatabaseError> for CommandError {
+    fn from(err: crate::database::DatabaseError) -> Self {
+        match err {
+            crate::database::DatabaseError::VectorDB(_) => CommandError::VectorDb(err.to_string()),
+            _ => CommandError::Io(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use super::error::{CommandError, CommandResult};
+use super::keys;
+use crate::database::{Archive, ConversationRecord, Database};
+use crate::llm::memory::{MemoryManager, MemoryRecord};
+use crate::llm::EmbeddingGenerator;
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Exchanges exported when there is no session to scope to.
+const RECENT_FALLBACK_LIMIT: i64 = 50;
+const SESSION_LIMIT: i64 = 10_000;
+const EXPORT_USAGE: &str = "Usage: export <file> [--memories] | export md <path> | export json <path>";
+const IMPORT_USAGE: &str = "Usage: import <file> [--reembed]";
+
+/// Whether `arg` can name a backup file, rather than being a flag or an export format.
+fn is_backup_path(arg: &str) -> bool {
+    !arg.starts_with("--") && !matches!(arg, "md" | "json")
+}
+
+/// What `export <file>` writes: everything in SQLite, plus memory vectors with `--memories`.
+#[derive(Serialize, Deserialize)]
+struct Backup {
+    #[serde(flatten)]
+    archive: Archive,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    memories: Vec<MemoryRecord>,
+}
+
+pub async fn handle_command(
+    input: &str,
+    memory_manager: Option<&MemoryManager>,
+    db: &Arc<Database>,
+) -> CommandResult {
+    let args: Vec<&str> = input.trim_start_matches("export").split_whitespace().collect();
+    let (format, path) = match args.as_slice() {
+        [format @ ("md" | "json"), path] if !path.starts_with("--") => (*format, *path),
+        [path] if is_backup_path(path) => return export_backup(path, false, memory_manager, db).await,
+        [path, "--memories"] | ["--memories", path] if is_backup_path(path) => return export_backup(path, true, memory_manager, db).await,
+        _ => return Err(CommandError::Usage(EXPORT_USAGE.to_string())),
+    };
+
+    let session = memory_manager.and_then(|m| m.current_session());
+    let mut records = match session {
+        Some(session) => db.get_conversations(Some(session.id.clone()), SESSION_LIMIT).await?,
+        None => Vec::new(),
+    };
+    let title = match session {
+        Some(session) if !records.is_empty() => format!("Session {} ({})", session.id, session.topic),
+        _ => {
+            records = db.get_conversations(None, RECENT_FALLBACK_LIMIT).await?;
+            "Recent conversations".to_string()
+        }
+    };
+    if records.is_empty() {
+        println!("Nothing to export yet.");
+        return Ok(());
+    }
+
+    if !confirm_overwrite(path) {
+        println!("Export cancelled.");
+        return Ok(());
+    }
+
+    let contents = match format {
+        "md" => to_markdown(&title, &records),
+        _ => serde_json::to_string_pretty(&records).map_err(|e| CommandError::Io(e.to_string()))?,
+    };
+    std::fs::write(path, contents).map_err(|e| CommandError::Io(format!("Failed to write {}: {}", path, e)))?;
+
+    println!("💾 Wrote {} exchanges to {}", records.len(), path.cyan());
+    Ok(())
+}
+
+/// `export <file>`: conversations, knowledge and document insights, and with `memories`
+/// every memory and its vector, as one JSON file `import` can restore.
+async fn export_backup(path: &str, memories: bool, memory_manager: Option<&MemoryManager>, db: &Database) -> CommandResult {
+    let archive = db.export_all().await?;
+    let memories = match (memories, memory_manager.filter(|m| m.is_available())) {
+        (false, _) => Vec::new(),
+        (true, Some(memory_manager)) => memory_manager.records().await.map_err(|e| CommandError::VectorDb(e.to_string()))?,
+        (true, None) => return Err(CommandError::VectorDb("Memory is unavailable, so --memories can't be exported".to_string())),
+    };
+    if !confirm_overwrite(path) {
+        println!("Export cancelled.");
+        return Ok(());
+    }
+
+    let backup = Backup { archive, memories };
+    let contents = serde_json::to_string(&backup).map_err(|e| CommandError::Io(e.to_string()))?;
+    std::fs::write(path, contents).map_err(|e| CommandError::Io(format!("Failed to write {}: {}", path, e)))?;
+    println!(
+        "💾 Wrote {} exchanges, {} knowledge entries, {} document insights and {} memories to {}",
+        backup.archive.conversations.len(),
+        backup.archive.knowledge.len(),
+        backup.archive.document_insights.len(),
+        backup.memories.len(),
+        path.cyan()
+    );
+    Ok(())
+}
+
+/// `import <file> [--reembed]`: restores an `export <file>` backup, skipping anything
+/// already here, so importing the same file twice adds nothing.
+pub async fn handle_import(
+    input: &str,
+    memory_manager: Option<&MemoryManager>,
+    embedder: &EmbeddingGenerator,
+    db: &Database,
+) -> CommandResult {
+    let args: Vec<&str> = input.trim_start_matches("import").split_whitespace().collect();
+    let (path, reembed) = match args.as_slice() {
+        [path] if !path.starts_with("--") => (*path, false),
+        [path, "--reembed"] | ["--reembed", path] => (*path, true),
+        _ => return Err(CommandError::Usage(IMPORT_USAGE.to_string())),
+    };
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| CommandError::Io(format!("Failed to read {}: {}", path, e)))?;
+    let backup: Backup = serde_json::from_str(&contents)
+        .map_err(|e| CommandError::Usage(format!("{} is not an export backup: {}", path, e)))?;
+
+    let counts = db.import_all(backup.archive).await?;
+    println!("📥 Restored {} rows from {} ({} already here)", counts.added, path.cyan(), counts.skipped);
+
+    if backup.memories.is_empty() {
+        return Ok(());
+    }
+    let Some(memory_manager) = memory_manager.filter(|m| m.is_available()) else {
+        println!("{}", format!("⚠️  Skipped {} memories: memory is unavailable", backup.memories.len()).yellow());
+        return Ok(());
+    };
+    if reembed && !embedder.is_semantic() {
+        return Err(CommandError::Provider("No embedding model is configured. Set OPENAI_API_KEY to re-embed memories.".to_string()));
+    }
+    let total = backup.memories.len();
+    let stored = memory_manager.import_records(backup.memories, reembed.then_some(embedder), &mut |_, _| {}).await
+        .map_err(|e| CommandError::VectorDb(e.to_string()))?;
+    println!("📥 Restored {} memories ({} already here)", stored, total - stored);
+    Ok(())
+}
+
+/// Whether `path` may be written: it doesn't exist yet, or the user agrees to replace it.
+fn confirm_overwrite(path: &str) -> bool {
+    !Path::new(path).exists() || keys::confirm(&format!("{} already exists. Overwrite?", path))
+}
+
+fn to_markdown(title: &str, records: &[ConversationRecord]) -> String {
+    let mut markdown = format!("# {}\n", title);
+    for record in records {
+        let provider = record.provider.as_deref().unwrap_or("unknown provider");
+        markdown.push_str(&format!("\n---\n\n_{} · {} · {}_\n\n", record.timestamp, record.personality, provider));
+        markdown.push_str(&format!("**You:** {}\n\n", record.user_input));
+        markdown.push_str(&format!("**{}:** {}\n", record.personality, record.ai_response));
+        if let (Some(input), Some(response)) = (record.input_tokens, record.response_tokens) {
+            markdown.push_str(&format!("\n_Tokens: {} in, {} out_\n", input, response));
+        }
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_format_without_a_path_shows_usage() {
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        for input in ["export md", "export json", "export json --memories", "export --memories md"] {
+            let err = handle_command(input, None, &db).await.unwrap_err();
+            assert!(matches!(&err, CommandError::Usage(message) if message == EXPORT_USAGE), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_markdown_dialogue() {
+        let mut record = ConversationRecord::new("What is Rust?", "A systems language.", "helpful");
+        record.timestamp = "2024-05-01 10:00:00".to_string();
+        record.provider = Some("OpenAI".to_string());
+        record.input_tokens = Some(3);
+        record.response_tokens = Some(3);
+        let legacy = ConversationRecord::new("hi", "hello", "funny");
+
+        let markdown = to_markdown("Session s1 (rust)", &[record, legacy]);
+        assert!(markdown.starts_with("# Session s1 (rust)\n"));
+        assert!(markdown.contains("_2024-05-01 10:00:00 · helpful · OpenAI_\n\n**You:** What is Rust?\n\n**helpful:** A systems language.\n"));
+        assert!(markdown.contains("_Tokens: 3 in, 3 out_"));
+        assert!(markdown.contains("· funny · unknown provider_"));
+        assert_eq!(markdown.matches("_Tokens:").count(), 1);
+    }
+}
@@ -1,37 +1,56 @@
 use crate::food::analysis::nutrition::analyze_nutrition;
-use crate::food::api::spoonacular::SpoonacularClient;
+use crate::food::api::spoonacular::{MealPlan, RecipeFilters, SpoonacularClient, QUOTA_EXCEEDED_MESSAGE};
 use crate::food::config::FoodConfig;
 use crate::providers::traits::CompletionProvider;
+use super::error::{CommandError, CommandResult};
+use super::OutputMode;
+use serde_json::json;
 
-pub async fn handle_command(input: &str, provider: &Box<dyn CompletionProvider + Send + Sync>) -> Result<(), String> {
+pub async fn handle_command(input: &str, provider: &Box<dyn CompletionProvider + Send + Sync>, output: OutputMode) -> CommandResult {
     let input = input.trim();
     
     let response = match input.split_whitespace().next() {
         Some("nutrition") => {
             let food_item = input.trim_start_matches("nutrition").trim();
             if food_item.is_empty() {
-                return Ok(println!("Please specify a food item to analyze."));
+                output.status("Please specify a food item to analyze.");
+                return Ok(());
             }
-            let result = analyze_nutrition(food_item).await?;
-            println!("{}", result);
-            Ok::<(), String>(())
+            let result = analyze_nutrition(food_item).await.map_err(CommandError::Io)?;
+            output.result(&result);
+            output.json(json!({"command": "nutrition", "food": food_item, "result": result}));
+            Ok::<(), CommandError>(())
         }
         Some("recipe") => {
-            let recipe_name = input.trim_start_matches("recipe").trim();
+            let (recipe_name, filters) = parse_recipe_args(input.trim_start_matches("recipe"))?;
+            let recipe_name = recipe_name.as_str();
             if recipe_name.is_empty() {
-                return Ok(println!("Please specify a recipe name to search."));
+                output.status("Please specify a recipe name to search.");
+                return Ok(());
             }
             
             // Initialize Spoonacular client
-            let config = FoodConfig::from_env()?;
+            let config = FoodConfig::from_env().map_err(CommandError::Usage)?;
             let spoonacular = SpoonacularClient::new(config.spoonacular_api_key);
             
+            if !filters.is_empty() {
+                let mut applied = Vec::new();
+                if let Some(diet) = &filters.diet {
+                    applied.push(format!("diet: {}", diet));
+                }
+                if !filters.intolerances.is_empty() {
+                    applied.push(format!("no {}", filters.intolerances.join(", ")));
+                }
+                output.status(format!("🔎 Filters: {}", applied.join(" | ")));
+            }
+
             // Get recipe details
-            let recipe_info = spoonacular.search_recipe(recipe_name).await?;
+            let recipe_info = spoonacular.search_recipe_formatted(recipe_name, &filters).await.map_err(CommandError::Io)?;
             
             if recipe_info.starts_with("No recipe found") {
-                println!("❌ Recipe not found. Try:\n1. Check your spelling\n2. Use a more common name (e.g., 'pasta carbonara' instead of 'spaghetti carbonara')\n3. Simplify the search (e.g., 'carbonara' instead of 'authentic Italian carbonara')");
-                return Ok::<(), String>(());
+                output.json(json!({"command": "recipe", "recipe": recipe_name, "found": false}));
+                output.status("❌ Recipe not found. Try:\n1. Check your spelling\n2. Use a more common name (e.g., 'pasta carbonara' instead of 'spaghetti carbonara')\n3. Simplify the search (e.g., 'carbonara' instead of 'authentic Italian carbonara')");
+                return Ok::<(), CommandError>(());
             }
             
             // Use LLM to enhance recipe information with cooking tips
@@ -44,19 +63,157 @@ pub async fn handle_command(input: &str, provider: &Box<dyn CompletionProvider +
                 recipe_info
             );
             
-            let output = match provider.complete(&prompt).await {
-                Ok(cooking_tips) => {
-                    format!("🔍 Recipe Information:\n{}\n\n👨‍🍳 Cooking Analysis:\n{}", recipe_info, cooking_tips)
+            // Fall back to just the recipe info if the LLM fails
+            let cooking_tips = provider.complete(&prompt).await.ok();
+            match &cooking_tips {
+                Some(tips) => output.result(format!("🔍 Recipe Information:\n{}\n\n👨‍🍳 Cooking Analysis:\n{}", recipe_info, tips)),
+                None => output.result(&recipe_info),
+            }
+            output.json(json!({
+                "command": "recipe",
+                "recipe": recipe_name,
+                "found": true,
+                "info": recipe_info,
+                "analysis": cooking_tips,
+            }));
+            Ok::<(), CommandError>(())
+        }
+        Some("mealplan") => {
+            let args: Vec<&str> = input.split_whitespace().skip(1).collect();
+            let (days, calories) = match (
+                args.first().and_then(|d| d.parse::<usize>().ok()),
+                args.get(1).and_then(|c| c.parse::<u32>().ok()),
+            ) {
+                (Some(days), Some(calories)) => (days, calories),
+                _ => {
+                    output.status("Usage: mealplan <days> <calories/day> [diet]");
+                    return Ok(());
                 }
-                Err(_) => recipe_info // Fallback to just recipe info if LLM fails
             };
-            println!("{}", output);
-            Ok::<(), String>(())
+            let diet = args.get(2).copied();
+
+            let config = FoodConfig::from_env().map_err(CommandError::Usage)?;
+            let spoonacular = SpoonacularClient::new(config.spoonacular_api_key);
+
+            match spoonacular.generate_meal_plan(days, calories, diet).await {
+                Ok(plan) => {
+                    output.result(format_meal_plan(&plan));
+                    output.json(meal_plan_json(&plan));
+                }
+                Err(e) if e == QUOTA_EXCEEDED_MESSAGE => {
+                    output.status(format!("⚠️ {}\nIn the meantime, try `recipe <name>` for individual dishes.", e));
+                }
+                Err(e) => return Err(CommandError::Io(e)),
+            }
+            Ok::<(), CommandError>(())
         }
         _ => {
-            println!("Available commands:\n- nutrition <food_item> (Get nutrition facts)\n- recipe <name> (Get detailed recipe with cooking tips)");
-            Ok::<(), String>(())
+            output.status("Available commands:\n- nutrition <food_item> (Get nutrition facts)\n- recipe <name> [--diet <diet>] [--no <intolerance>] (Get detailed recipe with cooking tips)\n- mealplan <days> <calories/day> [diet] (Plan meals for up to 7 days)");
+            Ok::<(), CommandError>(())
         }
     }?;
-    Ok::<(), String>(())
+    Ok::<(), CommandError>(())
+}
+
+/// What `mealplan` reports in JSON mode.
+fn meal_plan_json(plan: &MealPlan) -> serde_json::Value {
+    json!({
+        "command": "mealplan",
+        "days": plan.days.len(),
+        "target_calories": plan.target_calories,
+        "diet": plan.diet,
+        "plan": format_meal_plan(plan),
+    })
+}
+
+fn format_meal_plan(plan: &MealPlan) -> String {
+    let mut output = format!("🗓️ Meal plan: {} day(s) at {} kcal/day", plan.days.len(), plan.target_calories);
+    if let Some(diet) = &plan.diet {
+        output.push_str(&format!(" ({})", diet));
+    }
+    output.push_str("\n\n");
+
+    for day in &plan.days {
+        output.push_str(&format!("📅 {}\n", day.day));
+        for meal in &day.meals {
+            match meal.ready_in_minutes {
+                Some(minutes) => output.push_str(&format!("• {} ({} min)\n", meal.title, minutes)),
+                None => output.push_str(&format!("• {}\n", meal.title)),
+            }
+        }
+        output.push_str(&format!(
+            "🥗 {:.0} kcal | protein {:.1} g | fat {:.1} g | carbs {:.1} g\n\n",
+            day.nutrients.calories, day.nutrients.protein, day.nutrients.fat, day.nutrients.carbohydrates
+        ));
+    }
+
+    output.push_str(&format!(
+        "📊 Total: {:.0} kcal (avg {:.0} kcal/day) | protein {:.1} g | fat {:.1} g | carbs {:.1} g",
+        plan.totals.calories, plan.average_calories(), plan.totals.protein, plan.totals.fat, plan.totals.carbohydrates
+    ));
+    output
+}
+
+/// Splits `recipe` arguments into the dish name and `--diet <diet>` / `--no <intolerance>` filters.
+/// `--no` may be repeated or take a comma-separated list.
+fn parse_recipe_args(args: &str) -> CommandResult<(String, RecipeFilters)> {
+    let usage = |message: &str| CommandError::Usage(message.to_string());
+    let mut name = Vec::new();
+    let mut diet = None;
+    let mut intolerances = Vec::new();
+
+    let mut tokens = args.split_whitespace();
+    while let Some(token) = tokens.next() {
+        match token {
+            "--diet" => {
+                diet = Some(tokens.next().ok_or_else(|| usage("--diet needs a value, e.g. --diet vegan"))?);
+            }
+            "--no" => {
+                let value = tokens.next().ok_or_else(|| usage("--no needs a value, e.g. --no gluten"))?;
+                intolerances.extend(value.split(',').filter(|v| !v.is_empty()));
+            }
+            _ => name.push(token),
+        }
+    }
+
+    Ok((name.join(" "), RecipeFilters::new(diet, &intolerances).map_err(CommandError::Usage)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipe_args_with_filters() {
+        let (name, filters) = parse_recipe_args(" pad thai --diet vegan --no gluten --no peanut,soy").unwrap();
+        assert_eq!(name, "pad thai");
+        assert_eq!(filters.diet.as_deref(), Some("vegan"));
+        assert_eq!(filters.intolerances, vec!["gluten", "peanut", "soy"]);
+
+        let (name, filters) = parse_recipe_args("carbonara").unwrap();
+        assert_eq!(name, "carbonara");
+        assert!(filters.is_empty());
+
+        assert!(parse_recipe_args("salad --diet").is_err());
+        assert!(parse_recipe_args("salad --diet vegn").is_err());
+    }
+
+    #[test]
+    fn test_meal_plan_json() {
+        use crate::food::api::spoonacular::{DailyNutrients, DayPlan};
+        let totals = DailyNutrients { calories: 1980.0, ..DailyNutrients::default() };
+        let plan = MealPlan {
+            target_calories: 2000,
+            diet: Some("vegan".to_string()),
+            days: vec![DayPlan { day: "Monday".to_string(), meals: Vec::new(), nutrients: totals.clone() }],
+            totals,
+        };
+
+        let value = meal_plan_json(&plan);
+        assert_eq!(value["command"], "mealplan");
+        assert_eq!(value["days"], 1);
+        assert_eq!(value["target_calories"], 2000);
+        assert_eq!(value["diet"], "vegan");
+        assert_eq!(value["plan"], format_meal_plan(&plan));
+    }
 }
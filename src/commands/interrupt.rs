@@ -0,0 +1,62 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Lets Ctrl-C stop a reply while it is being generated, without cancelling the rest of
+/// the command: the partial reply is kept and the REPL returns to the prompt.
+#[derive(Clone, Default)]
+pub struct Interrupt {
+    /// The generation in flight, with its number
+    current: Arc<Mutex<Option<(u64, CancellationToken)>>>,
+    started: Arc<AtomicU64>,
+}
+
+/// A generation in flight; it stops being interruptible when this is dropped.
+pub struct Generation {
+    id: u64,
+    token: CancellationToken,
+    interrupt: Interrupt,
+}
+
+impl Interrupt {
+    /// Marks a generation as started.
+    pub fn start(&self) -> Generation {
+        let id = self.started.fetch_add(1, Ordering::Relaxed);
+        let token = CancellationToken::new();
+        *self.current.lock().unwrap() = Some((id, token.clone()));
+        Generation { id, token, interrupt: self.clone() }
+    }
+
+    /// Stops the generation in flight, returning false if there was none.
+    pub fn cancel_generation(&self) -> bool {
+        match self.current.lock().unwrap().take() {
+            Some((_, token)) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Generation {
+    /// Runs `future` until it finishes, or returns `None` as soon as the generation is
+    /// interrupted, dropping it so a streamed request stops consuming tokens.
+    pub async fn run<T>(&self, future: impl Future<Output = T>) -> Option<T> {
+        tokio::select! {
+            biased;
+            _ = self.token.cancelled() => None,
+            result = future => Some(result),
+        }
+    }
+}
+
+impl Drop for Generation {
+    fn drop(&mut self) {
+        let mut current = self.interrupt.current.lock().unwrap();
+        if current.as_ref().is_some_and(|(id, _)| *id == self.id) {
+            *current = None;
+        }
+    }
+}
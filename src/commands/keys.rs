@@ -0,0 +1,96 @@
+use super::error::{CommandError, CommandResult};
+use crate::config::mask_key;
+use colored::Colorize;
+use console::Term;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Where `keys set` saves keys when asked to, the file `dotenv` loads at startup.
+pub const ENV_FILE: &str = ".env";
+
+/// The environment variable holding `provider`'s key, e.g. `OPENAI_API_KEY`.
+pub fn key_var(provider: &str) -> String {
+    format!("{}_API_KEY", provider.to_uppercase())
+}
+
+/// `keys`: every provider with its masked key, or that it has none.
+pub fn list(providers: &[(&str, &str)], keys: &HashMap<String, String>) {
+    println!("\n🔑 Provider API keys:");
+    for (name, _) in providers {
+        let status = match keys.get(*name) {
+            Some(key) => mask_key(key).green(),
+            None => "not set".red(),
+        };
+        println!("  {:<11} {}", name, status);
+    }
+    println!("\nAdd one with: keys set <provider>");
+}
+
+/// Asks for `provider`'s key without echoing it.
+pub fn read_key(provider: &str) -> CommandResult<String> {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Err(CommandError::Usage(format!("keys set needs a terminal; set {} in {} instead", key_var(provider), ENV_FILE)));
+    }
+    term.write_str(&format!("Paste the {} API key (hidden): ", provider))
+        .and_then(|_| term.read_secure_line())
+        .map(|key| key.trim().to_string())
+        .map_err(|e| CommandError::Io(format!("Failed to read key: {}", e)))
+        .and_then(|key| if key.is_empty() { Err(CommandError::Usage("No key entered.".to_string())) } else { Ok(key) })
+}
+
+/// Asks a yes/no question, defaulting to no.
+pub fn confirm(question: &str) -> bool {
+    let term = Term::stderr();
+    term.write_str(&format!("{} [y/N] ", question))
+        .and_then(|_| term.read_line())
+        .map(|answer| matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Sets `var` in the env file at `path`, replacing an existing line or appending one.
+pub fn save_to_env(path: &Path, var: &str, value: &str) -> std::io::Result<()> {
+    let existing = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let line = format!("{}={}", var, value);
+    let mut replaced = false;
+    let mut lines: Vec<String> = existing.lines()
+        .map(|l| {
+            let is_var = l.trim_start().strip_prefix("export ").unwrap_or(l.trim_start())
+                .split_once('=')
+                .is_some_and(|(name, _)| name.trim() == var);
+            if is_var && !replaced {
+                replaced = true;
+                line.clone()
+            } else {
+                l.to_string()
+            }
+        })
+        .collect();
+    if !replaced {
+        lines.push(line);
+    }
+    std::fs::write(path, lines.join("\n") + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_to_env_replaces_or_appends() {
+        let path = std::env::temp_dir().join(format!("keys_test_{}.env", uuid::Uuid::new_v4().simple()));
+        std::fs::write(&path, "# keys\nOPENAI_API_KEY=old\nQDRANT_URL=http://localhost:6334").unwrap();
+
+        save_to_env(&path, "OPENAI_API_KEY", "sk-new").unwrap();
+        save_to_env(&path, "MISTRAL_API_KEY", "m-key").unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents, "# keys\nOPENAI_API_KEY=sk-new\nQDRANT_URL=http://localhost:6334\nMISTRAL_API_KEY=m-key\n");
+        assert_eq!(key_var("openrouter"), "OPENROUTER_API_KEY");
+    }
+}
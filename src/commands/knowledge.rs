@@ -0,0 +1,173 @@
+use super::error::{CommandError, CommandResult};
+use crate::database::Database;
+use crate::knowledge_base::context::display_value;
+use crate::learning::{store_fact_vector, Fact, FACT_PREFIX};
+use crate::llm::EmbeddingGenerator;
+use super::chat::embed;
+use colored::Colorize;
+use std::sync::Arc;
+
+const USAGE: &str = "Usage: kb set <key> <value> | kb get <key> | kb list [prefix] | kb search <text> | kb del <key> (or: knowledge ...)";
+
+/// A learned fact's text, or any other entry's value as `display_value` shows it.
+fn shown_value(key: &str, value: &str) -> String {
+    if key.starts_with(FACT_PREFIX) {
+        if let Ok(fact) = serde_json::from_str::<Fact>(value) {
+            return fact.text;
+        }
+    }
+    display_value(value)
+}
+
+/// The value to store for `key`. A learned fact keeps its source and timestamp, so
+/// correcting one doesn't turn it into a plain entry `learned` can no longer read.
+fn stored_value(key: &str, value: &str, existing: Option<&str>) -> String {
+    if key.starts_with(FACT_PREFIX) {
+        if let Some(mut fact) = existing.and_then(|v| serde_json::from_str::<Fact>(v).ok()) {
+            fact.text = value.to_string();
+            return serde_json::to_string(&fact).unwrap_or_else(|_| serde_json::Value::String(value.to_string()).to_string());
+        }
+    }
+    // Stored as JSON text, like values written through `PUT /kb/:key`
+    serde_json::Value::String(value.to_string()).to_string()
+}
+
+/// Entries whose key or shown value contains `needle`, ignoring case.
+fn search<'a>(entries: &'a [(String, String)], needle: &str) -> Vec<&'a (String, String)> {
+    let needle = needle.to_lowercase();
+    entries.iter()
+        .filter(|(key, value)| {
+            key.to_lowercase().contains(&needle) || shown_value(key, value).to_lowercase().contains(&needle)
+        })
+        .collect()
+}
+
+/// `kb set|get|list|search|del`, also spelled `knowledge`, backed by the `knowledge_base`
+/// table the API's `/kb` routes use. Learned facts live there too, under `fact:` keys,
+/// and are embedded again with `embedder` when corrected.
+pub async fn handle_command(input: &str, db: &Arc<Database>, embedder: &EmbeddingGenerator) -> CommandResult {
+    let args = input.strip_prefix("knowledge").or_else(|| input.strip_prefix("kb")).unwrap_or(input).trim();
+    let (subcommand, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let rest = rest.trim();
+
+    match subcommand {
+        "set" => {
+            let (key, value) = rest.split_once(char::is_whitespace).ok_or_else(usage)?;
+            let existing = db.get_knowledge(key.to_string()).await
+                .map_err(|e| CommandError::Io(format!("Failed to read knowledge: {}", e)))?;
+            let stored = stored_value(key, value.trim(), existing.as_deref());
+            db.save_knowledge(key.to_string(), stored.clone()).await
+                .map_err(|e| CommandError::Io(format!("Failed to save knowledge: {}", e)))?;
+            if let Ok(fact) = serde_json::from_str::<Fact>(&stored) {
+                if let Some(embedding) = embed(embedder, &fact.text).await {
+                    if let Err(e) = store_fact_vector(db, key, &fact, embedding).await {
+                        println!("{}", format!("⚠️  Saved without updating its embedding: {}", e).yellow());
+                    }
+                }
+            }
+            println!("✅ Saved {}", key.cyan());
+            Ok(())
+        }
+        "get" if !rest.is_empty() => {
+            let value = db.get_knowledge(rest.to_string()).await
+                .map_err(|e| CommandError::Io(format!("Failed to read knowledge: {}", e)))?
+                .ok_or_else(|| CommandError::NotFound(format!("No knowledge entry for '{}'", rest)))?;
+            println!("{}: {}", rest.cyan(), shown_value(rest, &value));
+            Ok(())
+        }
+        "list" => {
+            let prefix = (!rest.is_empty()).then(|| rest.to_string());
+            let entries = db.list_knowledge(prefix).await
+                .map_err(|e| CommandError::Io(format!("Failed to list knowledge: {}", e)))?;
+            if entries.is_empty() {
+                println!("No knowledge entries yet. Add one with: kb set <key> <value>");
+                return Ok(());
+            }
+            println!("\n📚 Knowledge base ({} entries):", entries.len());
+            for (key, value) in entries {
+                println!("  {} - {}", key.cyan(), shown_value(&key, &value));
+            }
+            Ok(())
+        }
+        "search" if !rest.is_empty() => {
+            let entries = db.list_knowledge(None).await
+                .map_err(|e| CommandError::Io(format!("Failed to search knowledge: {}", e)))?;
+            let found = search(&entries, rest);
+            if found.is_empty() {
+                println!("No knowledge entries mention '{}'", rest);
+                return Ok(());
+            }
+            println!("\n📚 {} entries mention '{}':", found.len(), rest);
+            for (key, value) in found {
+                println!("  {} - {}", key.cyan(), shown_value(key, value));
+            }
+            Ok(())
+        }
+        "del" | "delete" if !rest.is_empty() => {
+            if !db.delete_knowledge(rest.to_string()).await
+                .map_err(|e| CommandError::Io(format!("Failed to delete knowledge: {}", e)))? {
+                return Err(CommandError::NotFound(format!("No knowledge entry for '{}'", rest)));
+            }
+            println!("🗑️ Deleted {}", rest.cyan());
+            Ok(())
+        }
+        _ => Err(usage()),
+    }
+}
+
+fn usage() -> CommandError {
+    CommandError::Usage(USAGE.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::learning::{recall_facts, FactSource};
+    use crate::providers::mock::MockProvider;
+
+    fn embedder() -> EmbeddingGenerator {
+        EmbeddingGenerator::from_provider(Box::new(MockProvider::with_response("unused")))
+    }
+
+    #[tokio::test]
+    async fn test_knowledge_commands() {
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let embedder = embedder();
+
+        handle_command("knowledge set deploy_process merge, then ./deploy.sh", &db, &embedder).await.unwrap();
+        assert_eq!(db.get_knowledge("deploy_process".to_string()).await.unwrap().as_deref(), Some("\"merge, then ./deploy.sh\""));
+        handle_command("knowledge get deploy_process", &db, &embedder).await.unwrap();
+        assert!(handle_command("knowledge get missing", &db, &embedder).await.is_err());
+        handle_command("knowledge list", &db, &embedder).await.unwrap();
+        handle_command("knowledge search DEPLOY.SH", &db, &embedder).await.unwrap();
+
+        handle_command("knowledge delete deploy_process", &db, &embedder).await.unwrap();
+        assert!(db.get_knowledge("deploy_process".to_string()).await.unwrap().is_none());
+        assert!(handle_command("knowledge delete deploy_process", &db, &embedder).await.is_err());
+        assert!(handle_command("knowledge search", &db, &embedder).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_correcting_a_learned_fact_keeps_it_a_fact() {
+        let db = Arc::new(Database::in_memory_with_vectors().await);
+        let embedder = embedder();
+        let fact = Fact { text: "The user's dog is called Pixle".to_string(), source: FactSource::Extracted, timestamp: chrono::Utc::now() };
+        db.save_knowledge("fact:1".to_string(), serde_json::to_string(&fact).unwrap()).await.unwrap();
+        store_fact_vector(&db, "fact:1", &fact, embedder.generate_embedding(&fact.text).await.unwrap()).await.unwrap();
+        db.save_knowledge("pets".to_string(), "\"no cats\"".to_string()).await.unwrap();
+
+        let entries = db.list_knowledge(None).await.unwrap();
+        let found: Vec<&str> = search(&entries, "pixle").into_iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(found, vec!["fact:1"]);
+
+        handle_command("kb set fact:1 The user's dog is called Pixel", &db, &embedder).await.unwrap();
+        let stored: Fact = serde_json::from_str(&db.get_knowledge("fact:1".to_string()).await.unwrap().unwrap()).unwrap();
+        assert_eq!(stored.text, "The user's dog is called Pixel");
+        assert_eq!((stored.source, stored.timestamp), (fact.source, fact.timestamp));
+
+        // Found by the meaning of its new text, with the old vector gone
+        let query = embedder.generate_embedding("The user's dog is called Pixel").await.unwrap();
+        assert_eq!(recall_facts(&db, "unrelated", Some(query), 5).await.unwrap(), vec![stored]);
+        assert_eq!(db.get_vector_db().await.unwrap().count_vectors("learned_facts").await.unwrap(), 1);
+    }
+}
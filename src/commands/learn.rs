@@ -0,0 +1,74 @@
+use crate::learning::{fact_extraction_prompt, parse_facts, FactSource, LearningManager};
+use crate::llm::EmbeddingGenerator;
+use crate::providers::traits::CompletionProvider;
+use super::chat::embed;
+use super::error::{CommandError, CommandResult};
+use colored::Colorize;
+
+const USAGE: &str = "Usage: learn <fact> | learn from last | learned <topic>";
+/// Facts printed by `learned`.
+const RECALL_LIMIT: usize = 10;
+
+/// `learn <fact>` and `learn from last`.
+pub async fn handle_learn(
+    input: &str,
+    learning: &LearningManager,
+    provider: &Box<dyn CompletionProvider + Send + Sync>,
+    embedder: &EmbeddingGenerator,
+    last_exchange: Option<(&str, &str)>,
+) -> CommandResult {
+    let fact = input.trim_start_matches("learn").trim();
+    if fact.is_empty() {
+        return Err(CommandError::Usage(USAGE.to_string()));
+    }
+
+    if fact != "from last" {
+        learning.learn_fact(fact, FactSource::User, embed(embedder, fact).await).await
+            .map_err(|e| CommandError::Io(format!("Failed to save fact: {}", e)))?;
+        println!("🧠 Learned: {}", fact.cyan());
+        return Ok(());
+    }
+
+    let (user_input, ai_response) = last_exchange
+        .ok_or_else(|| CommandError::NotFound("Nothing to learn from yet; chat first, then run: learn from last".to_string()))?;
+    let reply = provider.complete(&fact_extraction_prompt(user_input, ai_response)).await
+        .map_err(|e| CommandError::Provider(format!("Failed to extract facts: {}", e)))?;
+    let facts = parse_facts(&reply);
+    if facts.is_empty() {
+        println!("No facts worth keeping in the last exchange.");
+        return Ok(());
+    }
+
+    println!("🧠 Learned {} facts:", facts.len());
+    for fact in facts {
+        learning.learn_fact(&fact, FactSource::Extracted, embed(embedder, &fact).await).await
+            .map_err(|e| CommandError::Io(format!("Failed to save fact: {}", e)))?;
+        println!("  • {}", fact.cyan());
+    }
+    Ok(())
+}
+
+/// `learned <topic>`.
+pub async fn handle_learned(
+    input: &str,
+    learning: &LearningManager,
+    embedder: &EmbeddingGenerator,
+) -> CommandResult {
+    let topic = input.trim_start_matches("learned").trim();
+    if topic.is_empty() {
+        return Err(CommandError::Usage(USAGE.to_string()));
+    }
+
+    let facts = learning.recall(topic, embed(embedder, topic).await, RECALL_LIMIT).await
+        .map_err(|e| CommandError::Io(format!("Failed to recall facts: {}", e)))?;
+    if facts.is_empty() {
+        println!("Nothing learned about '{}' yet. Teach me with: learn <fact>", topic);
+        return Ok(());
+    }
+
+    println!("\n🧠 Learned about {}:", topic.cyan());
+    for fact in facts {
+        println!("  • {} {}", fact.text, format!("({}, {})", fact.source, fact.timestamp.format("%Y-%m-%d %H:%M")).dimmed());
+    }
+    Ok(())
+}
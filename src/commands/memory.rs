@@ -0,0 +1,265 @@
+use super::error::{CommandError, CommandResult};
+use super::keys;
+use crate::llm::memory::{Memory, MemoryManager, RetrievalWeights};
+use crate::llm::EmbeddingGenerator;
+use crate::providers::traits::CompletionProvider;
+use colored::Colorize;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::Path;
+
+const DEFAULT_SEARCH_LIMIT: u64 = 5;
+const DEFAULT_RECENT_LIMIT: usize = 10;
+const PREVIEW_CHARS: usize = 120;
+
+pub async fn handle_command(
+    input: &str,
+    embedder: &EmbeddingGenerator,
+    memory_manager: &MemoryManager,
+    provider: &dyn CompletionProvider,
+) -> CommandResult {
+    let args = input.trim_start_matches("memory").trim();
+    let (command, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let rest = rest.trim();
+
+    match command {
+        "search" => {
+            if rest.is_empty() {
+                return Err(CommandError::Usage("Usage: memory search <query>".to_string()));
+            }
+
+            if !embedder.is_semantic() {
+                return Err(CommandError::Provider("No embedding model is configured. Set OPENAI_API_KEY to search memory.".to_string()));
+            }
+            let embedding = embedder.generate_embedding(rest).await
+                .map_err(|e| CommandError::Provider(format!("Failed to embed query: {}", e)))?;
+
+            let hits = memory_manager.retrieve(embedding, DEFAULT_SEARCH_LIMIT, &RetrievalWeights::from_env()).await
+                .map_err(vector_db_error)?;
+            if hits.is_empty() {
+                println!("No memories found.");
+                return Ok(());
+            }
+
+            println!("\n🔍 Memories matching \"{}\":", rest.bright_yellow());
+            for hit in &hits {
+                println!("  {} {}", format!("[{:.3}]", hit.score).cyan(), format_memory(&hit.memory));
+                println!("          {}", format!(
+                    "similarity {:.3} · recency {:.3} · importance {:.2}",
+                    hit.similarity, hit.recency, hit.memory.importance
+                ).dimmed());
+            }
+            Ok(())
+        }
+        "recent" => {
+            let limit = if rest.is_empty() {
+                DEFAULT_RECENT_LIMIT
+            } else {
+                rest.parse::<usize>().map_err(|_| CommandError::Usage("Usage: memory recent [n]".to_string()))?
+            };
+
+            let memories = memory_manager.list_memories(None).await.map_err(vector_db_error)?;
+            if memories.is_empty() {
+                println!("No memories stored yet.");
+                return Ok(());
+            }
+
+            println!("\n🕒 {} most recent memories:", limit.min(memories.len()));
+            for memory in memories.iter().take(limit) {
+                println!("  {}", format_memory(memory));
+            }
+            Ok(())
+        }
+        "stats" => {
+            let stats = memory_manager.stats().await.map_err(vector_db_error)?;
+            let format_time = |time: Option<chrono::DateTime<chrono::Utc>>| {
+                time.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "-".to_string())
+            };
+
+            println!("\n🧠 Memory Stats:");
+            println!("  Points:   {}", stats.points.to_string().cyan());
+            println!("  Sessions: {}", stats.sessions.to_string().cyan());
+            println!("  Oldest:   {}", format_time(stats.oldest));
+            println!("  Newest:   {}", format_time(stats.newest));
+            Ok(())
+        }
+        "tag" => {
+            let (mut all, mut use_provider) = (false, false);
+            for flag in rest.split_whitespace() {
+                match flag {
+                    "--all" => all = true,
+                    "--llm" => use_provider = true,
+                    _ => return Err(CommandError::Usage("Usage: memory tag [--all] [--llm]".to_string())),
+                }
+            }
+            if use_provider {
+                println!("Tagging with the provider, one request per memory...");
+            }
+            let updated = memory_manager.retag_memories(use_provider.then_some(provider), all).await
+                .map_err(vector_db_error)?;
+            println!("🏷️ Tagged {} memories.", updated);
+            Ok(())
+        }
+        "compact" => {
+            let usage = || CommandError::Usage("Usage: memory compact [--days <n>] [--dry-run]".to_string());
+            let (mut age, mut dry_run) = (MemoryManager::retention(), false);
+            let mut args = rest.split_whitespace();
+            while let Some(arg) = args.next() {
+                match arg {
+                    "--dry-run" => dry_run = true,
+                    "--days" => {
+                        let days: i64 = args.next().and_then(|d| d.parse().ok()).filter(|d| *d >= 0).ok_or_else(usage)?;
+                        age = chrono::Duration::days(days);
+                    }
+                    _ => return Err(usage()),
+                }
+            }
+            if !dry_run {
+                println!("Summarizing sessions idle for {} days or more...", age.num_days());
+            }
+            let sessions = memory_manager.compact_older_than(age, provider, dry_run).await
+                .map_err(vector_db_error)?;
+            if sessions.is_empty() {
+                println!("No sessions idle for {} days or more to compact.", age.num_days());
+                return Ok(());
+            }
+
+            let memories: usize = sessions.iter().map(|s| s.memories).sum();
+            if dry_run {
+                println!("\n🗜️ Would compact {} memories from {} sessions:", memories, sessions.len());
+            } else {
+                println!("\n🗜️ Compacted {} memories from {} sessions:", memories, sessions.len());
+            }
+            for session in &sessions {
+                println!("  {} {} memories [{}]", session.session_id.cyan(), session.memories, session.topic_tags.join(", "));
+                if let Some(summary) = &session.summary {
+                    println!("      {}", summary.dimmed());
+                }
+            }
+            Ok(())
+        }
+        "prune" => {
+            let usage = || CommandError::Usage("Usage: memory prune [--days <n>]".to_string());
+            let age = match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [] => MemoryManager::retention(),
+                ["--days", days] => days.parse().ok().filter(|d: &i64| *d >= 0).map(chrono::Duration::days).ok_or_else(usage)?,
+                _ => return Err(usage()),
+            };
+            let prompt = format!("Delete memories older than {} days without summarizing them?", age.num_days());
+            if !keys::confirm(&prompt) {
+                println!("Prune cancelled.");
+                return Ok(());
+            }
+
+            let removed = memory_manager.delete_older_than(chrono::Utc::now() - age, MemoryManager::keep_importance()).await
+                .map_err(vector_db_error)?;
+            println!("🗑️ Removed {} memories.", removed);
+            Ok(())
+        }
+        "export" => {
+            if rest.is_empty() {
+                return Err(CommandError::Usage("Usage: memory export <file>".to_string()));
+            }
+            let pb = progress_bar();
+            let exported = memory_manager.export_all(Path::new(rest), &mut |done, total| {
+                pb.set_length(total as u64);
+                pb.set_position(done as u64);
+            }).await.map_err(|e| CommandError::Io(e.to_string()));
+            pb.finish_and_clear();
+            println!("📤 Exported {} memories to {}", exported?, rest.bright_yellow());
+            Ok(())
+        }
+        "import" => {
+            let (mut file, mut reembed, mut replace) = (None, false, false);
+            for arg in rest.split_whitespace() {
+                match arg {
+                    "--reembed" => reembed = true,
+                    "--replace" => replace = true,
+                    _ if file.is_none() && !arg.starts_with("--") => file = Some(arg),
+                    _ => return Err(CommandError::Usage("Usage: memory import <file> [--reembed] [--replace]".to_string())),
+                }
+            }
+            let file = file.ok_or_else(|| CommandError::Usage("Usage: memory import <file> [--reembed] [--replace]".to_string()))?;
+            if reembed && !embedder.is_semantic() {
+                return Err(CommandError::Provider("No embedding model is configured. Set OPENAI_API_KEY to re-embed memories.".to_string()));
+            }
+            if replace && !keys::confirm("Replace ALL stored memories with the imported ones? This cannot be undone.") {
+                println!("Import cancelled.");
+                return Ok(());
+            }
+
+            let pb = progress_bar();
+            let imported = memory_manager.import(Path::new(file), replace, reembed.then_some(embedder), &mut |done, total| {
+                pb.set_length(total as u64);
+                pb.set_position(done as u64);
+            }).await.map_err(|e| CommandError::Io(e.to_string()));
+            pb.finish_and_clear();
+            println!("📥 Imported {} memories from {}", imported?, file.bright_yellow());
+            Ok(())
+        }
+        "clear" => {
+            let target = match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["--all"] => None,
+                ["--session", id] => Some(*id),
+                _ => return Err(CommandError::Usage("Usage: memory clear --session <id> | --all".to_string())),
+            };
+
+            let prompt = match target {
+                Some(id) => format!("Delete all memories from session {}?", id),
+                None => "Delete ALL stored memories? This cannot be undone.".to_string(),
+            };
+            if !keys::confirm(&prompt) {
+                println!("Clear cancelled.");
+                return Ok(());
+            }
+
+            let removed = match target {
+                Some(id) => memory_manager.clear_session(id).await.map_err(vector_db_error)? as u64,
+                None => memory_manager.clear_all().await.map_err(vector_db_error)?,
+            };
+            println!("🗑️ Removed {} memories.", removed);
+            Ok(())
+        }
+        _ => {
+            println!("🧠 Memory Commands:");
+            println!("  memory search <query>              - Semantic search over stored memories");
+            println!("  memory recent [n]                  - Show the n most recent memories");
+            println!("  memory stats                       - Point count, sessions and time range");
+            println!("  memory tag [--all] [--llm]         - Tag untagged (or all) memories, by keywords or the provider");
+            println!("  memory compact [--days <n>] [--dry-run]");
+            println!("                                     - Summarize idle sessions into one memory each");
+            println!("  memory prune [--days <n>]          - Delete old memories outright, keeping important ones (asks first)");
+            println!("  memory export <file>               - Write all memories to a JSON lines file");
+            println!("  memory import <file> [--reembed] [--replace]");
+            println!("                                     - Load an export, re-embedding or replacing existing memories");
+            println!("  memory clear --session <id>|--all  - Delete memories (asks first)");
+            Ok(())
+        }
+    }
+}
+
+fn vector_db_error(e: anyhow::Error) -> CommandError {
+    CommandError::VectorDb(e.to_string())
+}
+
+fn progress_bar() -> ProgressBar {
+    let pb = ProgressBar::new(0);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] {bar:30.cyan/blue} {pos}/{len} ({percent}%)")
+        .unwrap());
+    pb
+}
+
+fn format_memory(memory: &Memory) -> String {
+    let text = memory.text.replace('\n', " ");
+    let preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    let ellipsis = if text.chars().count() > PREVIEW_CHARS { "…" } else { "" };
+    format!(
+        "{} [{}] ({}) {}{}",
+        memory.timestamp.format("%Y-%m-%d %H:%M").to_string().dimmed(),
+        memory.role,
+        memory.session_id.dimmed(),
+        preview,
+        ellipsis
+    )
+}
@@ -1,40 +1,225 @@
 use colored::Colorize;
-use crate::providers::traits::CompletionProvider;
+use crate::providers::traits::{CompletionOptions, CompletionProvider};
 use crate::providers::openai::openai::OpenAIProvider;
 use crate::providers::openrouter::openrouter::OpenRouterProvider;
 use crate::providers::mistral::mistral::MistralProvider;
 use crate::providers::gemini::gemini::GeminiProvider;
+use crate::providers::deepseek::deepseek::DeepSeekProvider;
 use crate::personality::PersonalityProfile;
 use crate::providers::twitter::manager::ConversationManager;
 use crate::providers::web_crawler::crawler_manager::WebCrawlerManager;
+use crate::providers::tools::{RecipeSearchTool, ToolRegistry, WebPageTool};
+use crate::food::api::spoonacular::SpoonacularClient;
+use crate::llm::budget::count_tokens;
+use crate::llm::cost;
+use crate::llm::cache::{CacheRequest, CompletionCache};
+use crate::llm::chat::SharedProvider;
 use crate::llm::memory::MemoryManager;
-use crate::database::Database;
-use crate::database::vector_db::VectorDB;
+use crate::llm::ChatManager;
+use crate::llm::MemoryMonitor;
+use crate::llm::EmbeddingGenerator;
+use crate::database::{ConversationRecord, Database, PromptTemplate};
+use crate::knowledge_base::context::{mentioned, with_knowledge_context};
+use crate::learning::{with_known_facts, LearningManager, LEARNED_CONTEXT_LIMIT};
 use std::sync::Arc;
 use std::collections::HashMap;
 use std::env;
-use std::any::Any;
-use std::any::TypeId;
+use std::fmt::Display;
+use std::io::Write;
+use tokio::sync::OwnedMutexGuard;
+use serde::Serialize;
 
 mod character;
+mod chat;
+mod config;
+pub mod completion;
 mod twitter;
 mod web;
 mod system;
 mod document;
+mod error;
+mod export;
+mod interrupt;
+mod keys;
+mod knowledge;
+mod learn;
+mod memory;
+mod prompts;
+pub mod registry;
+mod script;
+mod session;
+mod shell;
+mod spinner;
+mod status;
 
 #[cfg(feature = "food")]
 pub mod food_cmd;
 
+/// How command output is presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// Streaming, colors, spinners and token stats
+    #[default]
+    Interactive,
+    /// Just the response text, for scripts and `--quiet`
+    Plain,
+    /// One JSON object per command on stdout, e.g. `{response, tokens, provider}` for chat;
+    /// everything else goes to stderr
+    Json,
+}
+
+impl OutputMode {
+    /// Prints a banner, progress line or tip: to stdout when interactive, to stderr in
+    /// JSON mode so stdout stays parseable, and not at all in plain mode.
+    pub fn status(self, line: impl Display) {
+        match self {
+            OutputMode::Interactive => println!("{}", line),
+            OutputMode::Plain => {}
+            OutputMode::Json => eprintln!("{}", line),
+        }
+    }
+
+    /// Prints part of a command's result. JSON mode reports it through [`OutputMode::json`].
+    pub fn result(self, text: impl Display) {
+        if self != OutputMode::Json {
+            println!("{}", text);
+        }
+    }
+
+    /// Prints a command's result as its single JSON object, in JSON mode only.
+    pub fn json(self, value: serde_json::Value) {
+        if self == OutputMode::Json {
+            println!("{}", value);
+            #[cfg(test)]
+            PRINTED_JSON.with(|printed| printed.borrow_mut().push(value));
+        }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Every object [`OutputMode::json`] printed on this thread, so tests can check it.
+    static PRINTED_JSON: std::cell::RefCell<Vec<serde_json::Value>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// The JSON objects printed on this thread since the last call.
+#[cfg(test)]
+pub(crate) fn take_printed_json() -> Vec<serde_json::Value> {
+    PRINTED_JSON.with(|printed| printed.take())
+}
+
+#[derive(Debug, Serialize)]
+struct TokenUsage {
+    input: usize,
+    response: usize,
+    total: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatOutput<'a> {
+    response: &'a str,
+    tokens: TokenUsage,
+    /// Estimated; absent when the model has no price
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost_usd: Option<f64>,
+    provider: String,
+    /// Knowledge base keys and past messages the prompt drew on
+    sources: &'a [String],
+}
+
+/// The input of a single non-interactive run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OneShot {
+    /// `-c` or piped stdin: a command, or chat if it isn't one
+    Command(String),
+    /// `--prompt`: always a chat message
+    Prompt(String),
+}
+
+/// Whether `input` is exactly `clear` or `reset`, optionally followed by `--hard`,
+/// so a chat message that merely starts with "clear" isn't taken for one.
+pub fn is_clear_command(input: &str) -> bool {
+    matches!(
+        input.split_whitespace().collect::<Vec<_>>().as_slice(),
+        ["clear" | "reset"] | ["clear" | "reset", "--hard"]
+    )
+}
+
+pub const DATABASE_PATH: &str = "data/agent.db";
+const CHARACTERS_DIR: &str = "characters";
+/// How long `status` waits for the provider to answer its access check.
+const STATUS_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Providers `use` can switch to, by command name and display name.
+const PROVIDERS: &[(&str, &str)] = &[
+    ("deepseek", "DeepSeek"),
+    ("openai", "OpenAI"),
+    ("openrouter", "OpenRouter"),
+    ("mistral", "Mistral"),
+    ("gemini", "Gemini"),
+];
+
+pub fn qdrant_url() -> String {
+    env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string())
+}
+
+/// The error for commands that need vector memory when Qdrant is unreachable.
+fn memory_unavailable() -> CommandError {
+    CommandError::VectorDb(format!("Memory is unavailable: could not connect to Qdrant at {}", qdrant_url()))
+}
+
 pub struct CommandHandler {
     twitter_manager: Option<ConversationManager>,
     web_crawler: Option<WebCrawlerManager>,
-    provider: Box<dyn CompletionProvider + Send + Sync>,
+    /// Shared so background work such as `auto_learn` can use it after the turn
+    provider: SharedProvider,
+    /// Command name of the active provider, e.g. `deepseek`
+    current_provider: String,
     personality: PersonalityProfile,
-    memory_manager: MemoryManager,
+    /// Earlier characters, for `load back`
+    character_stack: character::CharacterStack,
+    /// Chat context and vector memory; `None` when Qdrant is unreachable, which leaves
+    /// plain chat working
+    chat_manager: Option<ChatManager>,
+    embedder: Arc<EmbeddingGenerator>,
+    /// The dedicated embedding model, if not the chat provider's (for `config`)
+    embedding_model: Option<String>,
     db: Arc<Database>,
-    crawler: WebCrawlerManager,
     // Store API keys for different providers
     provider_keys: HashMap<String, String>,
+    output_mode: OutputMode,
+    /// Session turns replayed into chat prompts (the `context_window` setting)
+    context_window: usize,
+    /// Prepend knowledge base entries the message mentions (the `use_knowledge` setting)
+    use_knowledge: bool,
+    /// Extract facts from every chat turn in the background (the `auto_learn` setting)
+    auto_learn: bool,
+    learning_manager: LearningManager,
+    /// The previous message and reply, for `learn from last` and `retry`
+    last_exchange: Option<LastExchange>,
+    /// Input just rejected as a likely command typo
+    suggested: Option<String>,
+    /// A `prompt save` template waiting for its text, which is the next message
+    pending_template: Option<PromptTemplate>,
+    /// Stops the reply being generated, for Ctrl-C in the REPL
+    interrupt: Interrupt,
+    /// Tokens and recent turns of this conversation, for cleanup and `stats`
+    monitor: Arc<MemoryMonitor>,
+    /// Replies to repeated prompts (`COMPLETION_CACHE`); `None` when off or bypassed
+    completion_cache: Option<CompletionCache>,
+}
+
+/// The last chat exchange, kept so it can be learned from or answered again.
+struct LastExchange {
+    input: String,
+    /// The prompt as first sent, with its memory and knowledge context
+    prompt: String,
+    response: String,
+    input_tokens: usize,
+    sources: Vec<String>,
+    stored: chat::StoredExchange,
+    /// Settings it was sent with, e.g. a saved prompt's temperature
+    options: CompletionOptions,
 }
 
 impl CommandHandler {
@@ -43,64 +228,211 @@ impl CommandHandler {
         twitter_manager: Option<ConversationManager>,
         web_crawler: Option<WebCrawlerManager>,
         provider: Box<dyn CompletionProvider + Send + Sync>,
-    ) -> Result<Self, String> {
-        let db = Database::new("agent.db")
-            .await
-            .map_err(|e| format!("Failed to initialize database: {}", e))?;
-
-        // Initialize vector database
-        let vector_db = VectorDB::new("http://localhost:6333")
-            .await
-            .map_err(|e| format!("Failed to initialize vector database: {}", e))?;
-
-        // Initialize memory manager with vector database
-        let vector_db = Arc::new(vector_db);
-        let memory_manager = MemoryManager::new(vector_db)
-            .await
-            .map_err(|e| format!("Failed to initialize memory manager: {}", e))?;
+        provider_name: &str,
+        db: Arc<Database>,
+        memory_manager: Option<MemoryManager>,
+        embedder: EmbeddingGenerator,
+        learning_manager: LearningManager,
+    ) -> CommandResult<Self> {
+        let embedding_model = embedder.description().map(str::to_string);
 
         // Load API keys from environment
         let mut provider_keys = HashMap::new();
-        for provider_name in ["openai", "openrouter", "mistral", "gemini"] {
+        for (provider_name, _) in PROVIDERS {
             let key_var = format!("{}_API_KEY", provider_name.to_uppercase());
             if let Ok(api_key) = env::var(&key_var) {
                 provider_keys.insert(provider_name.to_string(), api_key);
             }
         }
 
-        Ok(Self {
+        // Sessions are recorded alongside the conversation history
+        let memory_manager = memory_manager.map(|manager| {
+            let mut manager = manager.with_session_store(db.clone());
+            manager.set_personality(&personality.name);
+            manager
+        });
+
+        let provider: SharedProvider = Arc::new(provider);
+        let chat_manager = memory_manager.map(|manager| ChatManager::with_memory(manager, chat::RECENT_TURNS));
+
+        let completion_cache = CompletionCache::from_env(db.clone());
+
+        let mut handler = Self {
             twitter_manager,
             web_crawler,
             provider,
-            personality: personality.clone(),
-            memory_manager,
-            db: Arc::new(db),
-            crawler: WebCrawlerManager::new(personality)
-                .await
-                .map_err(|e| format!("Failed to initialize web crawler: {}", e))?,
+            current_provider: provider_name.to_lowercase(),
+            personality,
+            character_stack: character::CharacterStack::default(),
+            chat_manager,
+            embedder: Arc::new(embedder),
+            embedding_model,
+            db,
             provider_keys,
-        })
+            output_mode: OutputMode::default(),
+            context_window: chat::RECENT_TURNS,
+            use_knowledge: false,
+            auto_learn: false,
+            learning_manager,
+            last_exchange: None,
+            suggested: None,
+            pending_template: None,
+            interrupt: Interrupt::default(),
+            monitor: Arc::new(MemoryMonitor::from_env()),
+            completion_cache,
+        };
+
+        // Settings saved with `config set` in earlier runs
+        let settings = handler.db.get_settings().await
+            .map_err(|e| CommandError::Io(format!("Failed to load settings: {}", e)))?;
+        for (key, value) in settings {
+            if let Err(e) = handler.apply_setting(&key, &value) {
+                eprintln!("Warning: Ignoring saved setting: {}", e);
+            }
+        }
+
+        Ok(handler)
+    }
+
+    /// Validates a setting and makes it take effect for this run.
+    fn apply_setting(&mut self, key: &str, value: &str) -> CommandResult<String> {
+        let value = crate::config::parse_setting(key, value).map_err(CommandError::Usage)?;
+        match key {
+            "temperature" => crate::config::set_default_temperature(value.parse().ok()),
+            "context_window" => {
+                self.context_window = value.parse().unwrap_or(chat::RECENT_TURNS);
+                if let Some(chat_manager) = self.chat_manager.as_mut() {
+                    chat_manager.set_context_window(self.context_window);
+                }
+            }
+            "use_knowledge" => self.use_knowledge = value == "on",
+            "auto_learn" => self.auto_learn = value == "on",
+            _ => {}
+        }
+        Ok(value)
+    }
+
+    async fn handle_config_command(&mut self, input: &str) -> CommandResult {
+        let args: Vec<&str> = input.split_whitespace().skip(1).collect();
+        match args.as_slice() {
+            [] => {
+                let settings = self.db.get_settings().await?;
+                let embeddings = self.embedding_model.clone()
+                    .unwrap_or_else(|| format!("{} (chat provider)", self.get_current_provider_name()));
+                let qdrant_url = qdrant_url();
+                config::print_config(
+                    &self.get_current_provider_name(),
+                    &embeddings,
+                    &config::Locations {
+                        database: DATABASE_PATH,
+                        qdrant_url: &qdrant_url,
+                        characters_dir: CHARACTERS_DIR,
+                    },
+                    &settings,
+                );
+                Ok(())
+            }
+            ["set", key, value] => {
+                let value = self.apply_setting(key, value)?;
+                self.db.save_setting(key.to_string(), value.clone()).await
+                    .map_err(|e| CommandError::Io(format!("Failed to save setting: {}", e)))?;
+                println!("✅ {} = {}", key, value.cyan());
+                Ok(())
+            }
+            _ => Err(CommandError::Usage("Usage: config | config set <key> <value>".to_string())),
+        }
     }
 
-    pub async fn handle_command(&mut self, input: &str) -> Result<(), String> {
+    /// Prints the status panel, checking the provider and vector store live.
+    pub async fn show_status(&self) -> CommandResult {
+        // A real request, so a bad key or an outage shows as unreachable
+        let model = tokio::time::timeout(STATUS_CHECK_TIMEOUT, self.provider.check_access()).await
+            .ok()
+            .and_then(|checked| checked.ok());
+        let vectors_connected = match self.db.get_vector_db().await {
+            Some(store) => store.is_available().await,
+            None => false,
+        };
+        let vectors = match crate::database::VectorBackend::from_env() {
+            crate::database::VectorBackend::Qdrant => format!("qdrant at {}", qdrant_url()),
+            crate::database::VectorBackend::Sqlite => "sqlite".to_string(),
+        };
+        status::print_status(&status::Status {
+            provider: &self.get_current_provider_name(),
+            model,
+            character: &self.personality.name,
+            database: DATABASE_PATH,
+            vectors,
+            vectors_connected,
+            twitter: self.twitter_manager.is_some(),
+            crawler: self.web_crawler.is_some(),
+        });
+        Ok(())
+    }
+
+    pub fn set_output_mode(&mut self, mode: OutputMode) {
+        self.output_mode = mode;
+    }
+
+    /// Reports turns and token use to `monitor`, shared with the REPL's cleanup loop, which
+    /// summarizes compacted sessions with the active provider.
+    pub fn set_memory_monitor(&mut self, monitor: Arc<MemoryMonitor>) {
+        monitor.set_summarizer(self.provider.clone());
+        self.monitor = monitor;
+    }
+
+    /// `stats`: token use, message count and context window fill since the last cleanup.
+    async fn show_stats(&self) -> CommandResult {
+        let stats = self.monitor.stats().await;
+        match self.output_mode {
+            OutputMode::Json => println!("{}", serde_json::json!({
+                "tokens": stats.tokens,
+                "messages": stats.messages,
+                "context": stats.context_len,
+                "context_window": stats.context_window,
+                "seconds_since_cleanup": stats.since_cleanup.as_secs(),
+            })),
+            _ => status::print_stats(&stats),
+        }
+        Ok(())
+    }
+
+    pub async fn handle_command(&mut self, input: &str) -> CommandResult {
         if input.is_empty() {
             return Ok(());
         }
 
         let input = input.trim();
+        if let Some(template) = self.pending_template.take() {
+            return self.save_template(template, input).await;
+        }
+        // The input a typo warning was last shown for; repeating it sends it as chat
+        let suggested = self.suggested.take();
 
         // Handle single-word commands first
         match input.to_lowercase().as_str() {
             "help" | "exit" | "quit" => return self.handle_system_command(input).await,
-            "chars" | "characters" | "load" => return self.handle_character_command(input).await,
+            // `help doc`, but not a message like "help me debug this"
+            s if s.strip_prefix("help ").is_some_and(|topic| !topic.trim().contains(' ')) => {
+                return self.handle_system_command(input).await;
+            }
+            "chars" | "characters" | "load" | "char pop" => return self.handle_character_command(input).await,
+            "char new" => return character::create_character(&self.provider, CHARACTERS_DIR).await,
             "providers" => return self.list_providers(),
+            "models" => return self.list_models().await,
+            "keys" => {
+                keys::list(PROVIDERS, &self.provider_keys);
+                return Ok(());
+            }
+            "status" => return self.show_status().await,
+            "stats" => return self.show_stats().await,
             _ => {}
         }
 
         // Handle food commands if the feature is enabled
         #[cfg(feature = "food")]
-        if input.starts_with("nutrition ") || input.starts_with("recipe ") {
-            return food_cmd::handle_command(input, &self.provider).await;
+        if input.starts_with("nutrition ") || input.starts_with("recipe ") || input.starts_with("mealplan ") {
+            return food_cmd::handle_command(input, &self.provider, self.output_mode).await;
         }
 
         // Handle command prefixes
@@ -108,43 +440,164 @@ impl CommandHandler {
             return self.handle_character_command(input).await;
         }
 
+        if let Some(provider_name) = input.strip_prefix("keys set ") {
+            return self.set_key(provider_name).await;
+        }
+
+        if let Some(command_line) = input.strip_prefix("sh! ") {
+            return self.run_shell(command_line.trim(), true).await;
+        }
+        if let Some(command_line) = input.strip_prefix("sh ") {
+            return self.run_shell(command_line.trim(), false).await;
+        }
+
         if input.starts_with("use ") {
             return self.switch_provider(input.trim_start_matches("use ").trim()).await;
         }
 
         // Document commands
+        if input.starts_with("doc ") && document::is_library_command(input) {
+            return document::handle_library_command(input, &self.db, self.output_mode).await;
+        }
         if input.starts_with("doc ") {
+            let mut memory_manager = self.available_memory().await?;
             return document::handle_command(
                 input,
                 &self.provider,
-                &mut self.memory_manager,
-                &self.db
+                &mut memory_manager,
+                &self.embedder,
+                &self.db,
+                self.output_mode,
             ).await;
         }
 
+        if input == "config" || input.starts_with("config ") {
+            return self.handle_config_command(input).await;
+        }
+
+        // Start over with a fresh context
+        if is_clear_command(input) {
+            self.monitor.clear_context().await;
+            let Some(mut memory_manager) = self.memory().await else {
+                println!("Nothing to clear; no session is active.");
+                return Ok(());
+            };
+            return session::clear(input, &mut memory_manager).await;
+        }
+
+        // Session commands
+        if input == "session" || input.starts_with("session ") {
+            let mut memory_manager = self.available_memory().await?;
+            return session::handle_command(input, &self.provider, &mut memory_manager, &self.db).await;
+        }
+
+        // Export commands
+        if input == "export" || input.starts_with("export ") {
+            return export::handle_command(input, self.memory().await.as_deref(), &self.db).await;
+        }
+        if input == "import" || input.starts_with("import ") {
+            return export::handle_import(input, self.memory().await.as_deref(), &self.embedder, &self.db).await;
+        }
+
+        // Saved prompts
+        if let Some(args) = input.strip_prefix("prompt save ") {
+            let template = prompts::parse_save_args(args)?;
+            println!(
+                "📝 Type the prompt for {}, with {} where the input goes. Wrap several lines in \"\"\" or type paste.",
+                template.name.cyan(),
+                prompts::PLACEHOLDER
+            );
+            self.pending_template = Some(template);
+            return Ok(());
+        }
+        if input == "prompt" || input.starts_with("prompt ") {
+            return prompts::handle_command(input, &self.db).await;
+        }
+        if let Some(args) = input.strip_prefix("run ") {
+            let (name, text) = args.trim().split_once(char::is_whitespace).unwrap_or((args.trim(), ""));
+            return self.run_template(name, text.trim()).await;
+        }
+
+        // Knowledge base commands
+        if input == "kb" || input.starts_with("kb ") || input == "knowledge" || input.starts_with("knowledge ") {
+            return knowledge::handle_command(input, &self.db, &self.embedder).await;
+        }
+
+        // A prompt sent as typed, for when conversation context would get in the way
+        if let Some(prompt) = input.strip_prefix("raw ") {
+            return self.send_raw(prompt.trim()).await;
+        }
+
+        // A question the model may answer by calling tools of its choosing
+        if input == "agent" || input.starts_with("agent ") {
+            return self.run_agent(input.trim_start_matches("agent").trim()).await;
+        }
+
+        // Answering the last message again
+        if input == "retry" {
+            return self.regenerate(None, None).await;
+        }
+        if let Some(provider_name) = input.strip_prefix("retry with ") {
+            return self.regenerate(Some(provider_name.trim()), None).await;
+        }
+        if let Some(instruction) = input.strip_prefix("again but ") {
+            return self.regenerate(None, Some(instruction.trim())).await;
+        }
+
+        // Learning commands
+        if input == "learn" || input.starts_with("learn ") {
+            return learn::handle_learn(input, &self.learning_manager, &self.provider, &self.embedder, self.last_exchange.as_ref().map(|last| (last.input.as_str(), last.response.as_str()))).await;
+        }
+        if input == "learned" || input.starts_with("learned ") {
+            return learn::handle_learned(input, &self.learning_manager, &self.embedder).await;
+        }
+
+        // Memory commands
+        if input == "memory" || input.starts_with("memory ") {
+            let memory_manager = self.available_memory().await?;
+            return memory::handle_command(input, &self.embedder, &memory_manager, &**self.provider).await;
+        }
+
         // Twitter commands
         if input.starts_with("tweet ") ||
            input.starts_with("autopost ") ||
            input.eq_ignore_ascii_case("tweet") ||
            input.eq_ignore_ascii_case("autopost") ||
            input.starts_with("reply ") ||
-           input.starts_with("dm @") {
+           input.starts_with("dm @") ||
+           input == "logs" ||
+           input.starts_with("logs ") {
             return self.handle_twitter_command(input).await;
         }
 
         // Web commands
         if input.starts_with("web ") {
             if let Some(ref crawler) = self.web_crawler {
+                let mut memory_manager = self.available_memory().await?;
                 let result = web::handle_command(
                     input.trim_start_matches("web ").trim(),
                     crawler,
                     &self.provider,
-                    &mut self.memory_manager,
+                    &mut memory_manager,
+                    &self.embedder,
+                    self.output_mode,
                 ).await?;
-                println!("{}", result);
+                self.output_mode.status(result);
                 return Ok(());
             } else {
-                return Err("Web crawler not initialized. Use --crawler flag to enable web features.".to_string());
+                return Err(CommandError::Usage("Web crawler not initialized. Use --crawler flag to enable web features.".to_string()));
+            }
+        }
+
+        // A near miss of a command word is probably a typo, unless it is sent again
+        if let Some(command) = input.split_whitespace().next().and_then(registry::suggest) {
+            if suggested.as_deref() != Some(input) {
+                self.suggested = Some(input.to_string());
+                return Err(CommandError::Usage(format!(
+                    "Unknown command '{}', did you mean '{}'? Type it again to send as chat.",
+                    input.split_whitespace().next().unwrap_or_default(),
+                    command
+                )));
             }
         }
 
@@ -152,7 +605,82 @@ impl CommandHandler {
         self.handle_chat(input).await
     }
 
-    async fn handle_twitter_command(&mut self, input: &str) -> Result<(), String> {
+    /// Runs the input of a one-shot invocation.
+    pub async fn run_once(&mut self, input: &OneShot) -> CommandResult {
+        match input {
+            OneShot::Command(command) => self.handle_command(command).await,
+            OneShot::Prompt(prompt) => self.handle_message(prompt).await,
+        }
+    }
+
+    /// Runs each line of `script` in order, echoing it first. Stops at the first failing
+    /// command unless `keep_going`; a malformed script fails before anything runs.
+    pub async fn run_script(&mut self, script: &str, keep_going: bool) -> CommandResult<ScriptReport> {
+        let steps = script::parse(script)?;
+        let commands = steps.iter().filter(|(_, step)| matches!(step, script::Step::Command(_))).count();
+        let mut report = ScriptReport::default();
+        for (line, step) in steps {
+            let command = match step {
+                script::Step::Sleep(duration) => {
+                    tokio::time::sleep(duration).await;
+                    continue;
+                }
+                script::Step::Command(command) => command,
+            };
+            // JSON mode keeps stdout for results
+            let echo = format!("> {}", command);
+            match self.output_mode {
+                OutputMode::Json => eprintln!("{}", echo),
+                _ => println!("{}", echo.bold()),
+            }
+
+            report.ran += 1;
+            if let Err(error) = self.handle_command(&command).await {
+                eprintln!("{}", format!("line {}: {}", line, error).red());
+                report.failures.push(ScriptFailure { line, command, error });
+                if !keep_going {
+                    report.skipped = commands - report.ran;
+                    break;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Sends `text` to the chat as-is, without treating its first word as a command.
+    /// Used for multi-line input, where a pasted line may start with e.g. "doc ".
+    pub async fn handle_message(&mut self, text: &str) -> CommandResult {
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        if let Some(template) = self.pending_template.take() {
+            return self.save_template(template, text).await;
+        }
+        self.handle_chat(text).await
+    }
+
+    async fn save_template(&mut self, mut template: PromptTemplate, text: &str) -> CommandResult {
+        if text.trim().is_empty() {
+            return Err(CommandError::Usage("Prompt not saved; it was empty.".to_string()));
+        }
+        template.template = text.trim().to_string();
+        let name = template.name.clone();
+        self.db.save_prompt_template(template).await
+            .map_err(|e| CommandError::Io(format!("Failed to save prompt: {}", e)))?;
+        println!("✅ Saved prompt {}. Use it with: run {} <input>", name.cyan(), name);
+        Ok(())
+    }
+
+    /// `run <name> <input>`: sends the saved template with `input` filled in, using its
+    /// provider and temperature for this one message.
+    async fn run_template(&mut self, name: &str, input: &str) -> CommandResult {
+        let template = prompts::find(&self.db, name).await?;
+        let message = prompts::render(&template.template, input);
+        let options = CompletionOptions { temperature: template.temperature };
+        self.chat_with(&message, template.provider.as_deref(), &options).await
+    }
+
+    async fn handle_twitter_command(&mut self, input: &str) -> CommandResult {
         if input.eq_ignore_ascii_case("tweet") {
             println!("Please provide a message to tweet.");
             println!("Usage: tweet <message>");
@@ -166,62 +694,487 @@ impl CommandHandler {
         twitter::handle_command(input, &mut self.twitter_manager).await
     }
 
-    async fn handle_character_command(&mut self, input: &str) -> Result<(), String> {
-        let result = character::handle_command(input, &mut self.personality);
+    async fn handle_character_command(&mut self, input: &str) -> CommandResult {
+        let result = character::handle_command(input, &mut self.personality, &mut self.character_stack);
         if result.is_ok() {
             // Update provider with new personality
             if let Err(e) = self.provider.update_personality(
                 self.personality.generate_system_prompt()
             ).await {
-                return Err(format!("Failed to update personality: {}", e));
+                return Err(CommandError::Provider(format!("Failed to update personality: {}", e)));
+            }
+            if let Some(mut memory_manager) = self.memory().await {
+                memory_manager.set_personality(&self.personality.name);
+            }
+            // Keep tweets in the new character's voice
+            if let Some(manager) = self.twitter_manager.as_mut() {
+                manager.update_personality(self.personality.clone()).await;
             }
         }
         result
     }
 
-    async fn handle_system_command(&mut self, input: &str) -> Result<(), String> {
+    async fn handle_system_command(&mut self, input: &str) -> CommandResult {
         system::handle_command(input)
     }
 
-    async fn handle_chat(&mut self, input: &str) -> Result<(), String> {
+    async fn handle_chat(&mut self, input: &str) -> CommandResult {
+        self.chat_with(input, None, &CompletionOptions::default()).await
+    }
+
+    /// Chats with the active provider, or just this once with the one named, sending `options`
+    /// with the request.
+    async fn chat_with(&mut self, input: &str, provider_name: Option<&str>, options: &CompletionOptions) -> CommandResult {
+        let temporary = match provider_name {
+            Some(name) => Some((self.create_provider(name).await?, provider_display_name(name))),
+            None => None,
+        };
+        let interactive = self.output_mode == OutputMode::Interactive;
+
         // Count input tokens
         let input_tokens = input.split_whitespace().count();
-        println!("📥 Input tokens: {}", input_tokens.to_string().cyan());
+        if interactive {
+            println!("📥 Input tokens: {}", input_tokens.to_string().cyan());
+        }
 
-        // Get response from AI
-        match self.provider.complete(input).await {
-            Ok(response) => {
-                let response_tokens = response.split_whitespace().count();
-                self.print_response("", &response, input_tokens, response_tokens);
-                Ok(())
+        self.start_session().await;
+
+        // Without memory there is nothing to retrieve or store, so skip embedding
+        let user_embedding = match self.memory().await {
+            Some(memory_manager) if memory_manager.is_available() => chat::embed(&self.embedder, input).await,
+            _ => None,
+        };
+        let mut sources = Vec::new();
+        let message = if self.use_knowledge {
+            match self.db.list_knowledge(None).await {
+                Ok(entries) => {
+                    sources.extend(mentioned(input, &entries).into_iter().map(|(key, _)| format!("kb:{}", key)));
+                    with_knowledge_context(input, &entries)
+                }
+                Err(e) => {
+                    eprintln!("Warning: Failed to load knowledge base: {}", e);
+                    input.to_string()
+                }
             }
-            Err(e) => Err(format!("Failed to get AI response: {}", e))
+        } else {
+            input.to_string()
+        };
+        let message = if crate::config::learned_context_enabled() {
+            match self.learning_manager.recall(input, user_embedding.clone(), LEARNED_CONTEXT_LIMIT).await {
+                Ok(facts) => {
+                    sources.extend(facts.iter().map(|fact| format!("fact:{}", fact.text)));
+                    with_known_facts(&message, &facts)
+                }
+                Err(e) => {
+                    eprintln!("Warning: Failed to recall learned facts: {}", e);
+                    message
+                }
+            }
+        } else {
+            message
+        };
+        let (provider, provider_name) = match &temporary {
+            Some((provider, display_name)) => (&**provider, display_name.clone()),
+            None => (&**self.provider, self.get_current_provider_name()),
+        };
+        let (prompt, recalled) = match &self.chat_manager {
+            Some(chat_manager) => chat_manager.prepare_prompt(provider, &message, user_embedding.as_deref()).await.unwrap_or_else(|e| {
+                eprintln!("Warning: Failed to load conversation context: {}", e);
+                (message.clone(), Vec::new())
+            }),
+            None => (message.clone(), Vec::new()),
+        };
+        sources.extend(recalled.into_iter().map(|text| format!("memory:{}", text)));
+
+        let response = self.complete_and_print(provider, &provider_name, &prompt, options, input_tokens, &sources).await?;
+        let response_tokens = response.split_whitespace().count();
+
+        let mut record = ConversationRecord::new(input, &response, &self.personality.name);
+        record.provider = Some(provider_name);
+        record.input_tokens = Some(input_tokens as i64);
+        record.response_tokens = Some(response_tokens as i64);
+        let stored = chat::remember_exchange(
+            record,
+            user_embedding,
+            &self.embedder,
+            self.memory().await.as_deref(),
+            &self.db,
+            provider,
+        ).await;
+        self.monitor.record_turn(input, &response).await;
+        if self.auto_learn {
+            self.learn_in_background(input, &response);
         }
+        self.last_exchange = Some(LastExchange {
+            input: input.to_string(),
+            prompt,
+            response,
+            input_tokens,
+            sources,
+            stored,
+            options: options.clone(),
+        });
+        Ok(())
     }
 
-    fn print_response(&self, _character_name: &str, response: &str, input_tokens: usize, response_tokens: usize) {
-        println!("{}", response.truecolor(255, 236, 179));
+    /// `raw <prompt>`: sends the prompt exactly as typed, without conversation, knowledge
+    /// or memory context, and keeps the exchange out of memory. It is still saved to the
+    /// history and can be retried.
+    async fn send_raw(&mut self, prompt: &str) -> CommandResult {
+        if prompt.is_empty() {
+            return Err(CommandError::Usage("Usage: raw <prompt>".to_string()));
+        }
+        let input_tokens = prompt.split_whitespace().count();
+        let provider_name = self.get_current_provider_name();
+        let response = self.complete_and_print(&**self.provider, &provider_name, prompt, &CompletionOptions::default(), input_tokens, &[]).await?;
 
+        let mut record = ConversationRecord::new(prompt, &response, &self.personality.name);
+        record.provider = Some(provider_name);
+        record.input_tokens = Some(input_tokens as i64);
+        record.response_tokens = Some(response.split_whitespace().count() as i64);
+        let stored = chat::remember_exchange(record, None, &self.embedder, None, &self.db, &**self.provider).await;
+        self.last_exchange = Some(LastExchange {
+            input: prompt.to_string(),
+            prompt: prompt.to_string(),
+            response,
+            input_tokens,
+            sources: Vec::new(),
+            stored,
+            options: CompletionOptions::default(),
+        });
+        Ok(())
+    }
+
+    /// `agent <question>`: answers with the active provider, which may read web pages and,
+    /// with a Spoonacular key, look up recipes whenever it decides it needs to. Like `raw`,
+    /// no conversation context goes with the question.
+    async fn run_agent(&mut self, question: &str) -> CommandResult {
+        if question.is_empty() {
+            return Err(CommandError::Usage("Usage: agent <question>".to_string()));
+        }
+        let tools = self.agent_tools().await?;
+        let provider_name = self.get_current_provider_name();
+        let input_tokens = question.split_whitespace().count();
+
+        let spinner = (self.output_mode == OutputMode::Interactive).then(|| spinner::Spinner::start("Thinking..."));
+        let generation = self.interrupt.start();
+        let result = generation.run(self.provider.complete_with_tools(question, &tools)).await;
+        drop(generation);
+        drop(spinner);
+        let response = match result {
+            Some(result) => result.map_err(|e| CommandError::Provider(format!("Failed to get AI response: {}", e)))?,
+            None => return Err(CommandError::Interrupted("\n⏹️ Stopped before the reply began".to_string())),
+        };
+        self.monitor.record_completion(question, &response);
+
+        let response_tokens = response.split_whitespace().count();
+        match self.output_mode {
+            OutputMode::Interactive => println!("{}", response.truecolor(255, 236, 179)),
+            OutputMode::Plain => println!("{}", response),
+            OutputMode::Json => println!("{}", chat_json(&provider_name, &response, input_tokens, response_tokens, None, &[])),
+        }
+        Ok(())
+    }
+
+    /// The tools `agent` offers: web pages always, recipes when `SPOONACULAR_API_KEY` is set.
+    async fn agent_tools(&self) -> CommandResult<ToolRegistry> {
+        let crawler = WebCrawlerManager::new(self.personality.clone()).await
+            .map_err(|e| CommandError::Io(format!("Failed to start the web crawler: {}", e)))?;
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(WebPageTool::new(Arc::new(crawler))));
+        if let Ok(api_key) = env::var("SPOONACULAR_API_KEY") {
+            tools.register(Arc::new(RecipeSearchTool::new(Arc::new(SpoonacularClient::new(api_key)))));
+        }
+        Ok(tools)
+    }
+
+    /// Extracts facts from one exchange with the active provider without holding up the
+    /// next prompt. Failures are only logged, since nobody is waiting on them.
+    fn learn_in_background(&self, input: &str, response: &str) {
+        let learning = self.learning_manager.clone();
+        let provider = self.provider.clone();
+        let (input, response) = (input.to_string(), response.to_string());
+        tokio::spawn(async move {
+            match learning.learn_from_exchange(&input, &response, &**provider).await {
+                Ok(facts) if !facts.is_empty() => log::info!("Learned {} facts from the last exchange", facts.len()),
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to learn from the last exchange: {}", e),
+            }
+        });
+    }
+
+    /// The conversation memory, locked for one command; `None` without a vector store.
+    async fn memory(&self) -> Option<OwnedMutexGuard<MemoryManager>> {
+        match &self.chat_manager {
+            Some(chat_manager) => Some(chat_manager.memory().lock_owned().await),
+            None => None,
+        }
+    }
+
+    /// The conversation memory, for commands that need Qdrant reachable.
+    async fn available_memory(&self) -> CommandResult<OwnedMutexGuard<MemoryManager>> {
+        self.memory().await.filter(|m| m.is_available()).ok_or_else(memory_unavailable)
+    }
+
+    /// Keeps memories from the next exchange grouped under the active session.
+    async fn start_session(&mut self) {
+        if let Some(mut memory_manager) = self.memory().await {
+            if let Err(e) = memory_manager.get_or_create_session(None).await {
+                eprintln!("Warning: Failed to start session: {}", e);
+            }
+        }
+    }
+
+    /// `sh <command>` runs a shell command and shows its output; `sh! <command>` also asks
+    /// the model to explain it. Commands off the allowlist need a yes first. The output,
+    /// and the explanation, are remembered like a chat exchange so follow-ups can use them.
+    async fn run_shell(&mut self, command_line: &str, interpret: bool) -> CommandResult {
+        let policy = shell::ShellPolicy::from_env();
+        match policy.check(command_line) {
+            shell::Verdict::Allowed => {}
+            shell::Verdict::NeedsConfirmation => {
+                if !keys::confirm(&format!("Run `{}`? It is not on the allowlist (SHELL_ALLOW).", command_line)) {
+                    return Err(CommandError::Usage("Command not run.".to_string()));
+                }
+            }
+            shell::Verdict::Denied(reason) => return Err(CommandError::Usage(reason)),
+        }
+
+        let result = policy.run(command_line).await?;
+        let status = shell::exit_status(&result);
+        match self.output_mode {
+            OutputMode::Json if !interpret => println!("{}", serde_json::json!({
+                "command": command_line,
+                "exit_code": result.exit_code,
+                "output": result.output,
+                "truncated": result.truncated,
+            })),
+            OutputMode::Json => {}
+            _ => {
+                print!("{}", result.output);
+                if !result.output.is_empty() && !result.output.ends_with('\n') {
+                    println!();
+                }
+                let cut = if result.truncated { format!(", output cut at {} bytes", policy.max_output) } else { String::new() };
+                println!("{}", format!("[{}{}]", status, cut).dimmed());
+            }
+        }
+
+        self.start_session().await;
+        let input = format!("$ {}", command_line);
+        let (prompt, response) = if interpret {
+            let prompt = shell::interpretation_prompt(command_line, &result);
+            let provider_name = self.get_current_provider_name();
+            let input_tokens = prompt.split_whitespace().count();
+            let response = self.complete_and_print(&**self.provider, &provider_name, &prompt, &CompletionOptions::default(), input_tokens, &[]).await?;
+            (prompt, format!("{}\n\n{}", result.output.trim_end(), response))
+        } else {
+            (input.clone(), result.output.trim_end().to_string())
+        };
+
+        let mut record = ConversationRecord::new(&input, &response, &self.personality.name);
+        record.provider = interpret.then(|| self.get_current_provider_name());
+        let memory_manager = self.memory().await;
+        let user_embedding = match memory_manager.as_deref() {
+            Some(memory_manager) if memory_manager.is_available() => chat::embed(&self.embedder, &input).await,
+            _ => None,
+        };
+        let stored = chat::remember_exchange(record, user_embedding, &self.embedder, memory_manager.as_deref(), &self.db, &**self.provider).await;
+        self.monitor.record_turn(&input, &response).await;
+        if interpret {
+            let input_tokens = prompt.split_whitespace().count();
+            self.last_exchange = Some(LastExchange {
+                input,
+                prompt,
+                response,
+                input_tokens,
+                sources: Vec::new(),
+                stored,
+                options: CompletionOptions::default(),
+            });
+        }
+        Ok(())
+    }
+
+    /// `retry`, `retry with <provider>` and `again but <instruction>`: answers the last
+    /// message again and puts the new reply in place of the old one, in the database and
+    /// in memory. A provider named here is only used for this one request.
+    async fn regenerate(&mut self, provider_name: Option<&str>, instruction: Option<&str>) -> CommandResult {
+        let last = self.last_exchange.as_ref()
+            .ok_or_else(|| CommandError::NotFound("Nothing to retry yet; send a message first.".to_string()))?;
+        let prompt = match instruction {
+            Some(instruction) => chat::with_instruction(&last.prompt, instruction),
+            None => last.prompt.clone(),
+        };
+
+        let temporary = match provider_name {
+            Some(name) => Some((self.create_provider(name).await?, provider_display_name(name))),
+            None => None,
+        };
+        let (provider, display_name) = match &temporary {
+            Some((provider, display_name)) => (&**provider, display_name.clone()),
+            None => (&**self.provider, self.get_current_provider_name()),
+        };
+        let response = self.complete_and_print(provider, &display_name, &prompt, &last.options, last.input_tokens, &last.sources).await?;
+
+        let mut record = ConversationRecord::new(&last.input, &response, &self.personality.name);
+        record.provider = Some(display_name);
+        record.input_tokens = Some(last.input_tokens as i64);
+        record.response_tokens = Some(response.split_whitespace().count() as i64);
+        let stored = chat::replace_response(&last.stored, &record, &self.embedder, self.memory().await.as_deref(), &self.db, provider).await;
+
+        // Keep the original prompt, so a later `again but` starts from it rather than stacking
+        if let Some(last) = self.last_exchange.as_mut() {
+            last.response = response;
+            last.stored = stored;
+        }
+        Ok(())
+    }
+
+    /// Streams `prompt` through `provider` in the current output mode, returning the reply.
+    async fn complete_and_print(
+        &self,
+        provider: &(dyn CompletionProvider + Send + Sync),
+        provider_name: &str,
+        prompt: &str,
+        options: &CompletionOptions,
+        input_tokens: usize,
+        sources: &[String],
+    ) -> CommandResult<String> {
+        // The same request answered recently is replayed rather than paid for again
+        let cached = match &self.completion_cache {
+            Some(cache) => {
+                let request = CacheRequest::new(provider, provider_name, prompt, options).await;
+                Some((cache, request))
+            }
+            None => None,
+        };
+        if let Some((cache, request)) = &cached {
+            if let Some(response) = cache.get(request).await {
+                self.print_cached(provider_name, &response, input_tokens, sources);
+                return Ok(response);
+            }
+        }
+
+        // Stream the response, with a spinner until the first chunk arrives.
+        // JSON mode needs the whole reply before printing anything.
+        let spinner = (self.output_mode == OutputMode::Interactive).then(|| spinner::Spinner::start("Thinking..."));
+        let output_mode = self.output_mode;
+        let mut partial = String::new();
+        let generation = self.interrupt.start();
+        let result = generation.run(provider.complete_stream_with_options(prompt, options, &mut |token| {
+            partial.push_str(token);
+            match output_mode {
+                OutputMode::Interactive => {
+                    if let Some(spinner) = &spinner {
+                        spinner.stop();
+                    }
+                    print!("{}", token.truecolor(255, 236, 179));
+                }
+                OutputMode::Plain => print!("{}", token),
+                OutputMode::Json => return,
+            }
+            let _ = std::io::stdout().flush();
+        })).await;
+        drop(generation);
+        drop(spinner);
+
+        let response = match result {
+            Some(result) => {
+                let response = result.map_err(|e| CommandError::Provider(format!("Failed to get AI response: {}", e)))?;
+                if let Some((cache, request)) = &cached {
+                    cache.put(request, &response).await;
+                }
+                response
+            }
+            // Interrupted: keep what arrived, so it can still be retried or learned from
+            None if partial.trim().is_empty() => return Err(CommandError::Interrupted("\n⏹️ Stopped before the reply began".to_string())),
+            None => {
+                self.output_mode.status(format!("\n{}", "⏹️ Stopped".yellow()));
+                partial
+            }
+        };
+        let response_tokens = response.split_whitespace().count();
+        self.monitor.record_completion(prompt, &response);
+        // Billed on the whole prompt, not just the typed message
+        let model = provider.get_model_info().await.unwrap_or_default();
+        let cost = cost::record(&model, count_tokens(prompt), count_tokens(&response));
+        match self.output_mode {
+            OutputMode::Interactive => {
+                println!();
+                self.print_token_stats(input_tokens, response_tokens, &model, cost);
+            }
+            OutputMode::Plain => println!(),
+            OutputMode::Json => println!("{}", chat_json(provider_name, &response, input_tokens, response_tokens, cost, sources)),
+        }
+        Ok(response)
+    }
+
+    /// Shows a reply from the completion cache as a streamed one would end up, at no cost.
+    fn print_cached(&self, provider_name: &str, response: &str, input_tokens: usize, sources: &[String]) {
+        let response_tokens = response.split_whitespace().count();
+        match self.output_mode {
+            OutputMode::Interactive => {
+                println!("{}", response.truecolor(255, 236, 179));
+                println!("\n📦 {}", "Cached reply; the provider wasn't asked again".dimmed());
+            }
+            OutputMode::Plain => println!("{}", response),
+            OutputMode::Json => println!("{}", chat_json(provider_name, response, input_tokens, response_tokens, Some(0.0), sources)),
+        }
+    }
+
+    fn print_token_stats(&self, input_tokens: usize, response_tokens: usize, model: &str, cost: Option<f64>) {
         println!("\n📊 Tokens: 📥 Input: {} | 📤 Response: {} | 📈 Total: {}",
             input_tokens.to_string().cyan(),
             response_tokens.to_string().cyan(),
             (input_tokens + response_tokens).to_string().cyan()
         );
+        let session = cost::format_usd(cost::totals().cost_usd);
+        match cost {
+            Some(cost) => println!("💵 Cost: ~{} | Session: {}", cost::format_usd(cost).cyan(), session.cyan()),
+            None => println!("💵 Cost: no price for {} (set MODEL_PRICES) | Session: {}", model, session.cyan()),
+        }
         println!();
     }
 
-    fn list_providers(&self) -> Result<(), String> {
+    /// `keys set <provider>`: asks for a key, checks it with the provider, and keeps it
+    /// for this run and, if the user agrees, in `.env`.
+    async fn set_key(&mut self, provider_name: &str) -> CommandResult {
+        let provider_name = known_provider(provider_name)?;
+        let api_key = keys::read_key(&provider_name)?;
+
+        println!("Checking the key with {}...", provider_display_name(&provider_name));
+        let provider = self.build_provider(&provider_name, api_key.clone()).await?;
+        let model = provider.check_access().await
+            .map_err(|e| CommandError::Provider(format!("❌ {} rejected the key: {}", provider_display_name(&provider_name), e)))?;
+        println!("✅ Key works ({})", model.cyan());
+
+        let var = keys::key_var(&provider_name);
+        env::set_var(&var, &api_key);
+        self.provider_keys.insert(provider_name.clone(), api_key.clone());
+
+        if keys::confirm(&format!("Save {} to {}?", var, keys::ENV_FILE)) {
+            keys::save_to_env(std::path::Path::new(keys::ENV_FILE), &var, &api_key)
+                .map_err(|e| CommandError::Io(format!("Failed to update {}: {}", keys::ENV_FILE, e)))?;
+            println!("💾 Saved to {}", keys::ENV_FILE);
+        }
+        println!("Switch to it with: use {}", provider_name);
+        Ok(())
+    }
+
+    fn list_providers(&self) -> CommandResult {
         println!("\n🤖 Available AI Providers:");
         println!("  Currently using: {}", self.get_current_provider_name().cyan());
         println!("\n  Available providers:");
         
-        for provider in ["openai", "openrouter", "mistral", "gemini"] {
-            let status = if self.provider_keys.contains_key(provider) {
+        for (provider, _) in PROVIDERS {
+            let status = if self.provider_keys.contains_key(*provider) {
                 "✅ Ready".green()
             } else {
                 "❌ No API key".red()
             };
-            println!("  • {} - {}", provider, status);
+            let marker = if *provider == self.current_provider { " (active)" } else { "" };
+            println!("  • {} - {}{}", provider, status, marker);
         }
         
         println!("\nTo switch providers, use: use <provider>");
@@ -230,50 +1183,478 @@ impl CommandHandler {
         Ok(())
     }
 
-    fn get_current_provider_name(&self) -> String {
-        let type_id = self.provider.type_id();
-        
-        if type_id == TypeId::of::<OpenAIProvider>() {
-            "OpenAI"
-        } else if type_id == TypeId::of::<OpenRouterProvider>() {
-            "OpenRouter"
-        } else if type_id == TypeId::of::<MistralProvider>() {
-            "Mistral"
-        } else if type_id == TypeId::of::<GeminiProvider>() {
-            "Gemini"
-        } else {
-            "Unknown"
-        }.to_string()
+    /// The models of every provider with a key, the active provider first. A provider whose
+    /// list can't be fetched is reported without failing the command.
+    async fn list_models(&self) -> CommandResult {
+        let mut groups = Vec::new();
+        for (provider_name, _) in PROVIDERS {
+            let Some(api_key) = self.provider_keys.get(*provider_name) else { continue };
+            let models = if *provider_name == self.current_provider {
+                self.provider.list_models().await.map_err(|e| e.to_string())
+            } else {
+                match self.build_provider(provider_name, api_key.clone()).await {
+                    Ok(provider) => provider.list_models().await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                }
+            };
+            let group = (provider_name.to_string(), models);
+            if *provider_name == self.current_provider {
+                groups.insert(0, group);
+            } else {
+                groups.push(group);
+            }
+        }
+
+        let current_model = self.provider.get_model_info().await.unwrap_or_default();
+        self.output_mode.json(serde_json::json!({
+            "current": { "provider": self.current_provider, "model": current_model },
+            "providers": groups.iter().map(|(provider, models)| match models {
+                Ok(models) => serde_json::json!({ "provider": provider, "models": models }),
+                Err(e) => serde_json::json!({ "provider": provider, "error": e }),
+            }).collect::<Vec<_>>(),
+        }));
+        if self.output_mode == OutputMode::Json {
+            return Ok(());
+        }
+
+        for (provider, models) in &groups {
+            let active = *provider == self.current_provider;
+            println!("\n{}{}", provider_display_name(provider).bold(), if active { " (active)" } else { "" });
+            match models {
+                Ok(models) if models.is_empty() => println!("  (none listed)"),
+                Ok(models) => {
+                    for model in models {
+                        if active && *model == current_model {
+                            println!("  • {} {}", model.cyan(), "← current".green());
+                        } else {
+                            println!("  • {}", model);
+                        }
+                    }
+                }
+                Err(e) => println!("  {}", format!("⚠️  Couldn't list models: {}", e).yellow()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends every prompt to the provider even with `COMPLETION_CACHE` on, for `--no-cache`.
+    pub fn bypass_completion_cache(&mut self) {
+        self.completion_cache = None;
     }
 
-    async fn switch_provider(&mut self, provider_name: &str) -> Result<(), String> {
+    /// A handle that stops the reply being generated, for a Ctrl-C handler to hold while
+    /// the handler itself is busy.
+    pub fn interrupt(&self) -> Interrupt {
+        self.interrupt.clone()
+    }
+
+    /// Display name of the active provider, e.g. `DeepSeek`.
+    pub fn get_current_provider_name(&self) -> String {
+        provider_display_name(&self.current_provider)
+    }
+
+    async fn switch_provider(&mut self, provider_name: &str) -> CommandResult {
         let provider_name = provider_name.to_lowercase();
+        if provider_name == self.current_provider {
+            println!("👍 Already using {}", self.get_current_provider_name().cyan());
+            return Ok(());
+        }
+        let new_provider = self.create_provider(&provider_name).await?;
+
+        // Switch to the new provider
+        self.provider = Arc::new(new_provider);
+        self.monitor.set_summarizer(self.provider.clone());
+        self.current_provider = provider_name.clone();
+        println!("🔄 Switched to {} provider", provider_name.cyan());
+        
+        Ok(())
+    }
+
+    /// A provider by command name, e.g. `openai`, speaking as the current character.
+    async fn create_provider(&self, provider_name: &str) -> CommandResult<Box<dyn CompletionProvider + Send + Sync>> {
+        let provider_name = known_provider(provider_name)?;
         
         // Get API key for the requested provider
         let api_key = self.provider_keys.get(&provider_name)
-            .ok_or_else(|| format!("No API key found for {}. Set {}_API_KEY in your environment.", 
-                provider_name, provider_name.to_uppercase()))?
+            .ok_or_else(|| CommandError::Provider(format!("No API key found for {}. Add it with: keys set {}",
+                provider_name, provider_name)))?
             .clone();
+        self.build_provider(&provider_name, api_key).await
+    }
 
-        // Create the new provider
-        let new_provider: Box<dyn CompletionProvider + Send + Sync> = match provider_name.as_str() {
+    /// `provider_name`'s provider with `api_key`, speaking as the current character.
+    async fn build_provider(&self, provider_name: &str, api_key: String) -> CommandResult<Box<dyn CompletionProvider + Send + Sync>> {
+        let provider: Box<dyn CompletionProvider + Send + Sync> = match provider_name {
             "openai" => Box::new(OpenAIProvider::new(api_key, self.personality.generate_system_prompt()).await
-                .map_err(|e| format!("Failed to initialize OpenAI provider: {}", e))?),
+                .map_err(|e| CommandError::Provider(format!("Failed to initialize OpenAI provider: {}", e)))?),
             "openrouter" => Box::new(OpenRouterProvider::new(api_key, self.personality.generate_system_prompt()).await
-                .map_err(|e| format!("Failed to initialize OpenRouter provider: {}", e))?),
+                .map_err(|e| CommandError::Provider(format!("Failed to initialize OpenRouter provider: {}", e)))?),
             "mistral" => Box::new(MistralProvider::new(api_key, self.personality.generate_system_prompt()).await
-                .map_err(|e| format!("Failed to initialize Mistral provider: {}", e))?),
+                .map_err(|e| CommandError::Provider(format!("Failed to initialize Mistral provider: {}", e)))?),
             "gemini" => Box::new(GeminiProvider::new(api_key, self.personality.generate_system_prompt()).await
-                .map_err(|e| format!("Failed to initialize Gemini provider: {}", e))?),
-            _ => return Err(format!("Unknown provider: {}. Available providers: openai, openrouter, mistral, gemini", provider_name))
+                .map_err(|e| CommandError::Provider(format!("Failed to initialize Gemini provider: {}", e)))?),
+            "deepseek" => Box::new(DeepSeekProvider::new(api_key, self.personality.generate_system_prompt()).await
+                .map_err(|e| CommandError::Provider(format!("Failed to initialize DeepSeek provider: {}", e)))?),
+            _ => unreachable!("provider names are checked against PROVIDERS"),
         };
+        Ok(provider)
+    }
+}
 
-        // Switch to the new provider
-        self.provider = new_provider;
-        println!("🔄 Switched to {} provider", provider_name.cyan());
-        
-        Ok(())
+/// `name` in lower case, if it is one of [`PROVIDERS`].
+fn known_provider(name: &str) -> CommandResult<String> {
+    let name = name.trim().to_lowercase();
+    if !PROVIDERS.iter().any(|(provider, _)| *provider == name) {
+        return Err(CommandError::Usage(format!("Unknown provider: {}. Available providers: {}", name,
+            PROVIDERS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", "))));
     }
+    Ok(name)
+}
+
+/// Display name of a provider by command name, e.g. `DeepSeek` for `deepseek`.
+fn provider_display_name(provider_name: &str) -> String {
+    PROVIDERS.iter()
+        .find(|(name, _)| *name == provider_name)
+        .map(|(_, display)| display.to_string())
+        .unwrap_or_else(|| provider_name.to_string())
+}
+
+fn chat_json(provider_name: &str, response: &str, input_tokens: usize, response_tokens: usize, cost_usd: Option<f64>, sources: &[String]) -> String {
+    let output = ChatOutput {
+        response,
+        tokens: TokenUsage {
+            input: input_tokens,
+            response: response_tokens,
+            total: input_tokens + response_tokens,
+        },
+        cost_usd,
+        provider: provider_name.to_string(),
+        sources,
+    };
+    serde_json::to_string(&output).unwrap_or_default()
 }
 
 pub use document::handle_command as handle_document_command;
+pub use character::BUILTIN_CHARACTERS;
+pub use error::{CommandError, CommandResult};
+pub use interrupt::Interrupt;
+pub use script::{ScriptFailure, ScriptReport};
+pub use web::chat_prompt as web_chat_prompt;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge_base::knowledge_base::KnowledgeBaseHandler;
+    use crate::database::{VectorDB, VectorStore};
+    use crate::providers::mock::MockProvider;
+
+    /// A handler chatting and embedding through `mock`, over an in-memory database.
+    async fn test_handler(mock: &MockProvider) -> CommandHandler {
+        build_test_handler(mock, None).await
+    }
+
+    /// Like [`test_handler`], with `memory` as the conversation memory.
+    async fn test_handler_with_memory(mock: &MockProvider, memory: MemoryManager) -> CommandHandler {
+        build_test_handler(mock, Some(memory)).await
+    }
+
+    async fn build_test_handler(mock: &MockProvider, memory: Option<MemoryManager>) -> CommandHandler {
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let personality = PersonalityProfile {
+            name: "tester".to_string(),
+            attributes: serde_json::json!({ "description": "a test bot" }),
+        };
+        let learning = LearningManager::new(db.clone(), KnowledgeBaseHandler::new("data/knowledge_base.json"));
+        let embedder = EmbeddingGenerator::from_provider(Box::new(mock.clone()));
+        CommandHandler::new(personality, None, None, Box::new(mock.clone()), "deepseek", db, memory, embedder, learning)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_chat_json_is_valid() {
+        let sources = vec!["kb:deploy".to_string(), "memory:I like \"tea\"".to_string()];
+        let json: serde_json::Value = serde_json::from_str(&chat_json("Mock", "line one\nline two", 3, 4, Some(0.0015), &sources)).unwrap();
+        assert_eq!(json["response"], "line one\nline two");
+        assert_eq!(json["tokens"]["total"], 7);
+        assert_eq!(json["cost_usd"], 0.0015);
+        assert_eq!(json["provider"], "Mock");
+        assert_eq!(json["sources"][1], "memory:I like \"tea\"");
+    }
+
+    #[tokio::test]
+    async fn test_one_shot_prompt_is_one_completion() {
+        let mock = MockProvider::with_responder(|_| "42".to_string());
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Json);
+
+        // A prompt that looks like a command is still sent as chat
+        handler.run_once(&OneShot::Prompt("providers".to_string())).await.unwrap();
+        assert_eq!(mock.prompts(), vec!["providers".to_string()]);
+        assert_eq!(handler.last_exchange.as_ref().map(|last| last.response.as_str()), Some("42"));
+    }
+
+    #[tokio::test]
+    async fn test_script_stops_at_first_failure_unless_keep_going() {
+        let mock = MockProvider::with_responder(|prompt| format!("re: {}", prompt));
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Plain);
+        let script = "# smoke test
+providers
+sleep 0
+run missing_template hi
+hello there
+";
+
+        let report = handler.run_script(script, false).await.unwrap();
+        assert_eq!((report.ran, report.skipped), (2, 1));
+        assert_eq!(report.failures[0].line, 4);
+        assert!(matches!(report.failures[0].error, CommandError::NotFound(_)));
+        assert!(mock.prompts().is_empty());
+
+        let report = handler.run_script(script, true).await.unwrap();
+        assert_eq!(report.summary(), "3 commands run, 1 failed");
+        assert_eq!(mock.prompts(), vec!["hello there".to_string()]);
+
+        assert!(handler.run_script("sleep later", true).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_learned_facts_are_added_to_related_prompts() {
+        let mock = MockProvider::with_responder(|_| "ok".to_string());
+        let mut handler = test_handler(&mock).await;
+        handler.learning_manager.learn_fact("The user prefers dark roast coffee", crate::learning::FactSource::User, None).await.unwrap();
+        handler.set_output_mode(OutputMode::Plain);
+
+        handler.handle_message("Which coffee beans should I buy?").await.unwrap();
+        assert_eq!(
+            mock.prompts()[0],
+            "Known facts:\n- The user prefers dark roast coffee\n\nWhich coffee beans should I buy?"
+        );
+        assert_eq!(handler.last_exchange.as_ref().unwrap().sources, vec!["fact:The user prefers dark roast coffee"]);
+
+        handler.handle_message("What is the capital of France?").await.unwrap();
+        assert_eq!(mock.prompts()[1], "What is the capital of France?");
+
+        // `raw` leaves the known facts out
+        handler.handle_command("raw Which coffee beans should I buy?").await.unwrap();
+        assert_eq!(mock.prompts()[2], "Which coffee beans should I buy?");
+        assert!(handler.last_exchange.as_ref().unwrap().sources.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_interrupt_stops_a_slow_reply() {
+        // The clock only moves while everything waits, so stops are timed from when the reply begins
+        let began = Arc::new(tokio::sync::Notify::new());
+        let notify = began.clone();
+        let mock = MockProvider::with_responder(move |_| {
+            notify.notify_one();
+            "one two three four five".to_string()
+        })
+            .with_delay(std::time::Duration::from_millis(200));
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Plain);
+        let interrupt = handler.interrupt();
+        assert!(!interrupt.cancel_generation());
+
+        // Stopped after the first word: control returns with the partial reply kept
+        let (stopper, waiter) = (interrupt.clone(), began.clone());
+        let stop = tokio::spawn(async move {
+            waiter.notified().await;
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            stopper.cancel_generation()
+        });
+        handler.handle_message("count to five").await.unwrap();
+        assert!(stop.await.unwrap());
+        assert_eq!(handler.last_exchange.as_ref().unwrap().response, "one ");
+        assert!(!interrupt.cancel_generation());
+
+        // Stopped before anything arrived
+        let (stopper, waiter) = (interrupt.clone(), began.clone());
+        tokio::spawn(async move {
+            waiter.notified().await;
+            stopper.cancel_generation()
+        });
+        let err = handler.handle_message("count again").await.unwrap_err();
+        assert!(matches!(&err, CommandError::Interrupted(message) if message.contains("Stopped")));
+    }
+
+
+    #[tokio::test]
+    async fn test_saved_prompt_runs_through_chat() {
+        let mock = MockProvider::with_responder(|_| "looks fine".to_string());
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Plain);
+
+        handler.handle_command("prompt save review").await.unwrap();
+        handler.handle_message("Review for security issues:\n{{input}}").await.unwrap();
+        assert!(mock.prompts().is_empty());
+
+        handler.handle_command("run review fn main() {}").await.unwrap();
+        assert_eq!(mock.prompts().last().unwrap(), "Review for security issues:\nfn main() {}");
+        assert!(handler.handle_command("run nope text").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_saved_prompt_temperature_goes_with_its_request() {
+        let mock = MockProvider::with_responder(|_| "looks fine".to_string());
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Plain);
+
+        handler.handle_command("prompt save review --temperature 0.2").await.unwrap();
+        handler.handle_message("Review:\n{{input}}").await.unwrap();
+
+        handler.handle_command("run review fn main() {}").await.unwrap();
+        assert_eq!(mock.temperatures().last(), Some(&Some(0.2)));
+
+        handler.handle_chat("and now?").await.unwrap();
+        assert_eq!(mock.temperatures().last(), Some(&None));
+    }
+
+    #[tokio::test]
+    async fn test_chat_through_mock_provider() {
+        let mock = MockProvider::with_responder(|prompt| format!("echo: {}", prompt.lines().last().unwrap_or_default()));
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Plain);
+
+        handler.handle_chat("what is a borrow checker?").await.unwrap();
+
+        assert_eq!(mock.prompts().len(), 1);
+        assert!(mock.prompts()[0].contains("what is a borrow checker?"));
+        let saved = handler.db.get_conversations(None, 10).await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert!(saved[0].ai_response.starts_with("echo: "));
+        assert_eq!(saved[0].provider.as_deref(), Some("DeepSeek"));
+        assert_eq!(handler.last_exchange.as_ref().map(|last| last.input.as_str()), Some("what is a borrow checker?"));
+
+        // The monitor sees the real turn, not a clock
+        let stats = handler.monitor.stats().await;
+        assert_eq!(stats.messages, 2);
+        assert_eq!(stats.tokens, count_tokens(&mock.prompts()[0]) + count_tokens(&saved[0].ai_response));
+        assert_eq!(handler.monitor.get_recent_context().await[0], "User: what is a borrow checker?");
+        handler.handle_command("stats").await.unwrap();
+        handler.handle_command("clear").await.unwrap();
+        assert_eq!(handler.monitor.stats().await.context_len, 0);
+    }
+
+    #[test]
+    fn test_clear_matches_only_its_command_forms() {
+        for input in ["clear", "reset", "clear --hard", "  reset   --hard "] {
+            assert!(is_clear_command(input), "{input}");
+        }
+        for input in ["clear the table please", "reset my password", "clear --soft", "clearly"] {
+            assert!(!is_clear_command(input), "{input}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_replaces_the_last_reply() {
+        let replies = std::sync::atomic::AtomicUsize::new(0);
+        let mock = MockProvider::with_responder(move |_| format!("reply {}", replies.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1));
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Plain);
+
+        assert!(handler.handle_command("retry").await.is_err());
+        handler.handle_chat("tell me a joke").await.unwrap();
+        handler.handle_command("retry").await.unwrap();
+
+        let saved = handler.db.get_conversations(None, 10).await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].ai_response, "reply 2");
+
+        handler.handle_command("again but shorter").await.unwrap();
+        let prompt = mock.prompts().last().unwrap().clone();
+        assert!(prompt.contains("tell me a joke") && prompt.ends_with("shorter"));
+        let saved = handler.db.get_conversations(None, 10).await.unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].ai_response, "reply 3");
+        assert_eq!(handler.last_exchange.as_ref().map(|last| last.prompt.contains("shorter")), Some(false));
+
+        assert!(handler.handle_command("retry with nope").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_typo_is_caught_once_then_sent_as_chat() {
+        let mock = MockProvider::with_response("hi");
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Plain);
+
+        let error = handler.handle_command("tweeet hello").await.unwrap_err();
+        assert!(matches!(&error, CommandError::Usage(message) if message.contains("did you mean 'tweet'")));
+        assert!(mock.prompts().is_empty());
+
+        handler.handle_command("tweeet hello").await.unwrap();
+        assert_eq!(mock.prompts().len(), 1);
+        handler.handle_command("help me debug this").await.unwrap();
+        assert_eq!(mock.prompts().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_chat_without_qdrant() {
+        let vector_db = VectorDB::connect_lazy("http://127.0.0.1:1").unwrap();
+        assert!(!vector_db.is_available().await);
+        let memory = MemoryManager::new(Arc::new(vector_db)).await.unwrap();
+        assert!(!memory.is_available());
+        assert_eq!(memory.store_memory("hi", "user", vec![0.1; 1536], None).await.unwrap(), "");
+        assert!(memory.search_similar(vec![0.1; 1536], 5, None).await.unwrap().is_empty());
+
+        let mock = MockProvider::with_response("still here");
+        let mut handler = test_handler_with_memory(&mock, memory).await;
+        handler.set_output_mode(OutputMode::Plain);
+
+        handler.handle_chat("are you there?").await.unwrap();
+        assert_eq!(handler.db.get_conversations(None, 10).await.unwrap()[0].ai_response, "still here");
+        let err = handler.handle_command("memory search anything").await.unwrap_err();
+        assert!(matches!(err, CommandError::VectorDb(_)));
+    }
+
+    #[tokio::test]
+    async fn test_agent_lets_the_model_read_a_page() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 2048];
+                let _ = socket.read(&mut request).await;
+                let body = "<p>The office opens at nine.</p>";
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(), body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        // Reads the page the first time, then answers from what it observed
+        let page = url.clone();
+        let mock = MockProvider::with_responder(move |prompt| match prompt.rsplit_once("Observation: ") {
+            Some((_, observation)) => format!("Final Answer: {}", observation.trim()),
+            None => format!("Action: read_web_page\nAction Input: {{\"url\": \"{}\"}}", page),
+        });
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Plain);
+
+        handler.handle_command("agent When does the office open?").await.unwrap();
+        let prompts = mock.prompts();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[0].contains("read_web_page") && prompts[0].contains("Question: When does the office open?"));
+        assert!(prompts[1].contains("Observation: - The office opens at nine."));
+        assert!(matches!(handler.handle_command("agent").await, Err(CommandError::Usage(_))));
+    }
+
+    #[tokio::test]
+    async fn test_command_errors_say_what_went_wrong() {
+        let mock = MockProvider::with_response("unused");
+        let mut handler = test_handler(&mock).await;
+        handler.set_output_mode(OutputMode::Plain);
+
+        let err = handler.handle_command("config set temperature hot").await.unwrap_err();
+        assert!(err.is_usage());
+        assert!(matches!(handler.handle_command("use nosuch").await, Err(CommandError::Usage(_))));
+        assert!(matches!(handler.handle_command("kb get missing").await, Err(CommandError::NotFound(_))));
+        assert!(matches!(handler.handle_command("retry").await, Err(CommandError::NotFound(_))));
+        assert!(matches!(handler.handle_command("memory search anything").await, Err(CommandError::VectorDb(_))));
+        assert!(mock.prompts().is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use super::error::{CommandError, CommandResult};
+use crate::database::{Database, PromptTemplate};
+use colored::Colorize;
+use std::sync::Arc;
+
+const USAGE: &str = "Usage: prompt save <name> [--provider <provider>] [--temperature <t>] | prompt list | prompt show <name> | prompt del <name>";
+
+/// Where `run` puts its arguments.
+pub const PLACEHOLDER: &str = "{{input}}";
+
+/// The template for `prompt save <name> [--provider p] [--temperature t]`, still without
+/// its text, which is the next message the user sends.
+pub fn parse_save_args(args: &str) -> CommandResult<PromptTemplate> {
+    let usage = |message: &str| CommandError::Usage(message.to_string());
+    let mut tokens = args.split_whitespace();
+    let name = tokens.next().ok_or_else(|| usage(USAGE))?;
+    let mut template = PromptTemplate {
+        name: name.to_string(),
+        template: String::new(),
+        provider: None,
+        temperature: None,
+    };
+    while let Some(token) = tokens.next() {
+        match token {
+            "--provider" => {
+                let provider = tokens.next().ok_or_else(|| usage("--provider needs a value, e.g. --provider openai"))?;
+                template.provider = Some(provider.to_lowercase());
+            }
+            "--temperature" => {
+                let value = tokens.next().ok_or_else(|| usage("--temperature needs a value, e.g. --temperature 0.2"))?;
+                let value = crate::config::parse_setting("temperature", value).map_err(CommandError::Usage)?;
+                template.temperature = value.parse().ok();
+            }
+            other => return Err(usage(&format!("Unexpected '{}'. {}", other, USAGE))),
+        }
+    }
+    Ok(template)
+}
+
+/// `template` with `input` in place of each `{{input}}`, or after it if there are none.
+pub fn render(template: &str, input: &str) -> String {
+    if template.contains(PLACEHOLDER) {
+        template.replace(PLACEHOLDER, input)
+    } else if input.is_empty() {
+        template.to_string()
+    } else {
+        format!("{}\n\n{}", template, input)
+    }
+}
+
+/// `prompt list|show|del`; `prompt save` needs the next message, so the handler does it.
+pub async fn handle_command(input: &str, db: &Arc<Database>) -> CommandResult {
+    let args = input.trim_start_matches("prompt").trim();
+    let (subcommand, name) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let name = name.trim();
+
+    match subcommand {
+        "list" => {
+            let templates = db.list_prompt_templates().await
+                .map_err(|e| CommandError::Io(format!("Failed to list prompts: {}", e)))?;
+            if templates.is_empty() {
+                println!("No saved prompts yet. Add one with: prompt save <name>");
+                return Ok(());
+            }
+            println!("\n📝 Saved prompts ({}):", templates.len());
+            for template in templates {
+                let first_line = template.template.lines().next().unwrap_or_default();
+                println!("  {}{} - {}", template.name.cyan(), overrides(&template).dimmed(), first_line);
+            }
+            Ok(())
+        }
+        "show" if !name.is_empty() => {
+            let template = find(db, name).await?;
+            println!("{}{}\n{}", template.name.cyan(), overrides(&template).dimmed(), template.template);
+            Ok(())
+        }
+        "del" if !name.is_empty() => {
+            if !db.delete_prompt_template(name.to_string()).await
+                .map_err(|e| CommandError::Io(format!("Failed to delete prompt: {}", e)))? {
+                return Err(CommandError::NotFound(format!("No saved prompt called '{}'", name)));
+            }
+            println!("🗑️ Deleted {}", name.cyan());
+            Ok(())
+        }
+        _ => Err(CommandError::Usage(USAGE.to_string())),
+    }
+}
+
+/// The saved template called `name`.
+pub async fn find(db: &Database, name: &str) -> CommandResult<PromptTemplate> {
+    db.get_prompt_template(name.to_string()).await
+        .map_err(|e| CommandError::Io(format!("Failed to read prompt: {}", e)))?
+        .ok_or_else(|| CommandError::NotFound(format!("No saved prompt called '{}'. See: prompt list", name)))
+}
+
+/// ` (openai, temperature 0.2)` for templates with overrides, otherwise nothing.
+fn overrides(template: &PromptTemplate) -> String {
+    let parts: Vec<String> = template.provider.iter().cloned()
+        .chain(template.temperature.map(|t| format!("temperature {}", t)))
+        .collect();
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render() {
+        let template = parse_save_args("review --provider OpenAI --temperature 0.2").unwrap();
+        assert_eq!(template.name, "review");
+        assert_eq!(template.provider.as_deref(), Some("openai"));
+        assert_eq!(template.temperature, Some(0.2));
+        assert!(parse_save_args("").is_err());
+        assert!(parse_save_args("review --temperature 9").is_err());
+
+        assert_eq!(render("Review for security issues:\n{{input}}", "fn main() {}"), "Review for security issues:\nfn main() {}");
+        assert_eq!(render("Translate to French", "hello"), "Translate to French\n\nhello");
+    }
+}
@@ -0,0 +1,289 @@
+/// One command, as shown by `help` and completed at the prompt.
+pub struct Command {
+    /// The command words, then `<required>` and `[optional]` arguments
+    pub usage: &'static str,
+    pub description: &'static str,
+}
+
+/// A group of commands, shown by `help <name>`.
+pub struct HelpTopic {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub commands: &'static [Command],
+    pub examples: &'static [&'static str],
+}
+
+const fn command(usage: &'static str, description: &'static str) -> Command {
+    Command { usage, description }
+}
+
+/// Every command the REPL understands. `help`, tab completion and typo suggestions all
+/// read this, so add new commands here.
+pub const TOPICS: &[HelpTopic] = &[
+    HelpTopic {
+        name: "system",
+        title: "⚙️ System Commands",
+        commands: &[
+            command("help", "Show the help topics"),
+            command("help <topic>", "Show the commands for one topic"),
+            command("paste", "Send several lines as one message (end with '.', or wrap them in \"\"\")"),
+            command("raw <prompt>", "Send a prompt as typed, without conversation context or memory"),
+            command("agent <question>", "Answer with the model free to read web pages and look up recipes"),
+            command("status", "Show the provider, character, storage and feature status"),
+            command("stats", "Show tokens used, messages and context window fill since the last cleanup"),
+            command("config", "Show providers, keys, storage and settings"),
+            command("config set <key> <value>", "Change temperature, context_window, use_knowledge or auto_learn (saved)"),
+            command("exit", "Exit the program"),
+            command("quit", "Exit the program"),
+        ],
+        examples: &["help doc", "config set temperature 0.3"],
+    },
+    HelpTopic {
+        name: "character",
+        title: "👤 Character Commands",
+        commands: &[
+            command("chars", "List available characters"),
+            command("char new", "Create a character step by step"),
+            command("load <name>", "Switch to a different character"),
+            command("load back", "Return to the previous character"),
+            command("char pop", "Same as load back"),
+        ],
+        examples: &["load helpful", "load friendly"],
+    },
+    HelpTopic {
+        name: "provider",
+        title: "🔄 Provider Commands",
+        commands: &[
+            command("providers", "List available AI providers"),
+            command("models", "List the models of each provider with a key"),
+            command("use <provider>", "Switch to a different provider"),
+            command("keys", "Show which providers have API keys"),
+            command("keys set <provider>", "Add a provider's API key, checked before it is kept"),
+        ],
+        examples: &["use openai", "keys set mistral"],
+    },
+    HelpTopic {
+        name: "retry",
+        title: "🔁 Retry Commands",
+        commands: &[
+            command("retry", "Answer the last message again"),
+            command("retry with <provider>", "Answer it again with another provider, just this once"),
+            command("again but <instruction>", "Answer it again with an extra instruction"),
+        ],
+        examples: &["again but shorter", "retry with openai"],
+    },
+    HelpTopic {
+        name: "twitter",
+        title: "🐦 Twitter Commands",
+        commands: &[
+            command("tweet", "Generate and post a tweet in the character's voice"),
+            command("tweet <message>", "Post a tweet"),
+            command("tweet thread <text>", "Post long text as a thread"),
+            command("reply <id> <message>", "Reply to a tweet"),
+            command("dm @user: <message>", "Send a direct message"),
+            command("autopost start <minutes>", "Start auto-posting"),
+            command("autopost stop", "Stop auto-posting"),
+            command("logs [n]", "Show recent Twitter activity and the rate limit"),
+        ],
+        examples: &["autopost start 60", "logs 20"],
+    },
+    HelpTopic {
+        name: "web",
+        title: "🕷️ Web Commands",
+        commands: &[
+            command("web analyze <url>", "Analyze webpage content"),
+            command("web research <topic> [--depth <n>]", "Research a topic, following each source's links n levels deep"),
+            command("web crawl <url> [--depth <n>] [--pages <n>]", "Crawl a site breadth first and analyze its pages"),
+            command("web links <url> [--same-domain] [--dedupe] [--exclude <regex>]", "Extract links from a webpage"),
+            command("web chat <question>", "Ask about pages analyzed so far"),
+        ],
+        examples: &["web analyze https://www.rust-lang.org", "web chat what are the main points?"],
+    },
+    HelpTopic {
+        name: "doc",
+        title: "📄 Document Commands",
+        commands: &[
+            command("doc analyze <file>", "Analyze a document"),
+            command("doc summary <file>", "Get a quick summary"),
+            command("doc extract <file>", "Extract text from a document"),
+            command("doc ocr <image>", "Extract text from an image"),
+            command("doc batch <folder>", "Process multiple files"),
+            command("doc info <file>", "Show file information"),
+            command("doc search <query>", "Search through document insights"),
+            command("doc ask <question>", "Answer from analyzed documents, citing pages"),
+            command("doc list", "List analyzed documents"),
+            command("doc delete <file>", "Forget a document's insights"),
+        ],
+        examples: &["doc analyze report.pdf", "doc ask what was the revenue in Q3?"],
+    },
+    HelpTopic {
+        name: "session",
+        title: "🗂️ Session Commands",
+        commands: &[
+            command("session new [topic]", "Start a new session"),
+            command("session list", "List saved sessions"),
+            command("session resume <id>", "Continue an earlier session"),
+            command("session summary", "Summarize the current session"),
+            command("clear", "Forget this conversation"),
+            command("clear --hard", "Forget this conversation and delete its memories"),
+            command("reset", "Same as clear"),
+        ],
+        examples: &["session new rust lifetimes"],
+    },
+    HelpTopic {
+        name: "memory",
+        title: "🧠 Memory Commands",
+        commands: &[
+            command("memory search <query>", "Search stored memories"),
+            command("memory recent [n]", "Show recent memories"),
+            command("memory stats", "Show memory statistics"),
+            command("memory tag [--all] [--llm]", "Add topic tags and importance to stored memories"),
+            command("memory compact [--days <n>] [--dry-run]", "Summarize idle sessions into one memory each"),
+            command("memory prune [--days <n>]", "Delete old memories without summarizing them"),
+            command("memory export <file>", "Write all memories to a JSON lines file"),
+            command("memory import <file> [--reembed] [--replace]", "Load memories from an export"),
+            command("memory clear --session <id>", "Delete one session's memories"),
+            command("memory clear --all", "Delete all memories"),
+        ],
+        examples: &["memory search borrow checker", "memory recent 5"],
+    },
+    HelpTopic {
+        name: "kb",
+        title: "📚 Knowledge Commands",
+        commands: &[
+            command("kb set <key> <value>", "Store a fact"),
+            command("kb get <key>", "Show one entry"),
+            command("kb list [prefix]", "List entries, optionally by key prefix"),
+            command("kb search <text>", "Find entries whose key or value contains the text"),
+            command("kb del <key>", "Delete an entry"),
+            command("knowledge <subcommand>", "Same as kb, e.g. knowledge delete fact:<id> for a wrong fact"),
+            command("learn <fact>", "Remember something you tell me"),
+            command("learn from last", "Pick out facts from the last exchange"),
+            command("learned <topic>", "Show what I've learned about a topic"),
+        ],
+        examples: &["kb set deploy_process merge, then ./deploy.sh", "config set use_knowledge on"],
+    },
+    HelpTopic {
+        name: "prompt",
+        title: "📝 Saved Prompts",
+        commands: &[
+            command("prompt save <name> [--provider <p>] [--temperature <t>]", "Save the next message as a template"),
+            command("prompt list", "List saved prompts"),
+            command("prompt show <name>", "Show a saved prompt"),
+            command("prompt del <name>", "Delete a saved prompt"),
+            command("run <name> <input>", "Send a saved prompt with {{input}} filled in"),
+        ],
+        examples: &["prompt save review --provider openai", "run review src/main.rs"],
+    },
+    HelpTopic {
+        name: "shell",
+        title: "🖥️ Shell Commands",
+        commands: &[
+            command("sh <command>", "Run a shell command and show its output"),
+            command("sh! <command>", "Run a shell command and have the model explain the output"),
+        ],
+        examples: &["sh df -h", "sh! free -m"],
+    },
+    HelpTopic {
+        name: "export",
+        title: "💾 Export Commands",
+        commands: &[
+            command("export md <path>", "Save this session as a Markdown dialogue"),
+            command("export json <path>", "Save this session as a JSON array"),
+            command("export <file> [--memories]", "Back up all conversations, knowledge and document insights, and with --memories every memory"),
+            command("import <file> [--reembed]", "Restore a backup, skipping what is already here"),
+        ],
+        examples: &["export md notes/session.md", "export backup.json --memories", "import backup.json"],
+    },
+    #[cfg(feature = "food")]
+    HelpTopic {
+        name: "food",
+        title: "🍽️ Food Commands",
+        commands: &[
+            command("nutrition <food>", "Look up nutrition facts"),
+            command("recipe <dish>", "Find a recipe"),
+            command("mealplan <days> <calories> [diet]", "Plan meals"),
+        ],
+        examples: &["mealplan 3 2000 vegetarian"],
+    },
+];
+
+/// Every registered command, in help order.
+pub fn commands() -> impl Iterator<Item = &'static Command> {
+    TOPICS.iter().flat_map(|topic| topic.commands.iter())
+}
+
+/// The topic called `name`, or the one holding a command that starts with that word,
+/// so `help tweet` works as well as `help twitter`.
+pub fn topic(name: &str) -> Option<&'static HelpTopic> {
+    let name = name.trim().to_lowercase();
+    TOPICS.iter().find(|topic| topic.name == name).or_else(|| {
+        TOPICS.iter().find(|topic| topic.commands.iter().any(|c| first_word(c.usage) == name))
+    })
+}
+
+fn first_word(usage: &str) -> &str {
+    usage.split_whitespace().next().unwrap_or_default()
+}
+
+/// Whether `word` starts a registered command.
+pub fn is_command_word(word: &str) -> bool {
+    commands().any(|c| first_word(c.usage) == word)
+}
+
+/// The command word `word` was probably meant to be, if it is a near miss: one edit away,
+/// or two for words of six letters or more. Short words are left alone, since they are
+/// more likely the start of a message than a typo.
+pub fn suggest(word: &str) -> Option<&'static str> {
+    let word = word.to_lowercase();
+    if word.chars().count() < 4 || is_command_word(&word) {
+        return None;
+    }
+    let max_distance = if word.chars().count() >= 6 { 2 } else { 1 };
+    commands()
+        .map(|c| first_word(c.usage))
+        .map(|candidate| (levenshtein(&word, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance between `a` and `b`, counting insertions, deletions and substitutions.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions_and_topics() {
+        assert_eq!(levenshtein("tweeet", "tweet"), 1);
+        assert_eq!(levenshtein("", "doc"), 3);
+        assert_eq!(suggest("tweeet"), Some("tweet"));
+        assert_eq!(suggest("Memroy"), Some("memory"));
+        assert_eq!(suggest("sesion"), Some("session"));
+        // Real commands, short words and ordinary openers are not typos
+        assert_eq!(suggest("tweet"), None);
+        assert_eq!(suggest("doc"), None);
+        assert_eq!(suggest("hello"), None);
+        assert_eq!(suggest("what"), None);
+
+        assert_eq!(topic("doc").map(|t| t.name), Some("doc"));
+        assert_eq!(topic("tweet").map(|t| t.name), Some("twitter"));
+        assert_eq!(topic("logs").map(|t| t.name), Some("twitter"));
+        assert_eq!(topic("learned").map(|t| t.name), Some("kb"));
+        assert!(topic("nonsense").is_none());
+    }
+}
@@ -0,0 +1,88 @@
+use super::error::{CommandError, CommandResult};
+use std::time::Duration;
+
+/// One line of a `--script` file that does something.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Run through the command handler, like a line typed at the prompt
+    Command(String),
+    /// `sleep <secs>`: wait before the next line, for pacing demos
+    Sleep(Duration),
+}
+
+/// A command that failed, with its 1-based line number in the script.
+#[derive(Debug, Clone)]
+pub struct ScriptFailure {
+    pub line: usize,
+    pub command: String,
+    pub error: CommandError,
+}
+
+/// What a script run did.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptReport {
+    /// Commands run, including the ones that failed
+    pub ran: usize,
+    pub failures: Vec<ScriptFailure>,
+    /// Commands left unrun after a failure without `--keep-going`
+    pub skipped: usize,
+}
+
+impl ScriptReport {
+    pub fn succeeded(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// `4 commands run, 1 failed, 2 skipped`.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} command{} run", self.ran, if self.ran == 1 { "" } else { "s" });
+        if !self.failures.is_empty() {
+            summary.push_str(&format!(", {} failed", self.failures.len()));
+        }
+        if self.skipped > 0 {
+            summary.push_str(&format!(", {} skipped", self.skipped));
+        }
+        summary
+    }
+}
+
+/// The steps in `script` with their line numbers, skipping blank lines and `#` comments.
+/// Fails on the first malformed `sleep`, before anything runs.
+pub fn parse(script: &str) -> CommandResult<Vec<(usize, Step)>> {
+    let mut steps = Vec::new();
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let step = match line.strip_prefix("sleep ") {
+            Some(secs) => {
+                let secs: f64 = secs.trim().parse().ok()
+                    .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+                    .ok_or_else(|| CommandError::Usage(format!("line {}: sleep needs a number of seconds, e.g. sleep 1.5", i + 1)))?;
+                Step::Sleep(Duration::from_secs_f64(secs))
+            }
+            None => Step::Command(line.to_string()),
+        };
+        steps.push((i + 1, step));
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_skips_comments_and_reads_sleep() {
+        let steps = parse("# demo\n\nproviders\n  sleep 0.5\nuse openai # not a comment\n").unwrap();
+        assert_eq!(steps, vec![
+            (3, Step::Command("providers".to_string())),
+            (4, Step::Sleep(Duration::from_millis(500))),
+            (5, Step::Command("use openai # not a comment".to_string())),
+        ]);
+
+        let err = parse("providers\nsleep soon").unwrap_err();
+        assert!(matches!(&err, CommandError::Usage(message) if message.starts_with("line 2:")));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::providers::traits::CompletionProvider;
+use crate::llm::memory::{ConversationSession, MemoryManager};
+use crate::database::Database;
+use super::error::{CommandError, CommandResult};
+use colored::Colorize;
+use std::sync::Arc;
+
+const SESSION_LIST_LIMIT: i64 = 20;
+
+pub async fn handle_command(
+    input: &str,
+    provider: &Box<dyn CompletionProvider + Send + Sync>,
+    memory_manager: &mut MemoryManager,
+    db: &Arc<Database>,
+) -> CommandResult {
+    let args = input.trim_start_matches("session").trim();
+    let (command, rest) = args.split_once(' ').unwrap_or((args, ""));
+    let rest = rest.trim();
+
+    match command {
+        "new" => {
+            let topic = if rest.is_empty() { "General Conversation" } else { rest };
+            memory_manager.start_new_session(topic).await.map_err(|e| CommandError::VectorDb(e.to_string()))?;
+            let session = current(memory_manager)?;
+            println!("🆕 Started session {} ({})", session.id.cyan(), session.topic);
+            Ok(())
+        }
+        "list" => {
+            let sessions = db.list_sessions(SESSION_LIST_LIMIT).await?;
+            if sessions.is_empty() {
+                println!("No sessions yet. Start one with: session new [topic]");
+                return Ok(());
+            }
+
+            let current_id = memory_manager.current_session().map(|s| s.id.clone());
+            println!("\n🗂️ Sessions:");
+            for session in &sessions {
+                let marker = if current_id.as_deref() == Some(session.id.as_str()) { "▶" } else { " " };
+                println!(
+                    "{} {}  {}  last active {}",
+                    marker,
+                    session.id[..8.min(session.id.len())].cyan(),
+                    session.topic,
+                    session.last_active.format("%Y-%m-%d %H:%M")
+                );
+                if !session.summary.is_empty() {
+                    println!("    {}", session.summary.dimmed());
+                }
+            }
+            Ok(())
+        }
+        "resume" => {
+            if rest.is_empty() {
+                return Err(CommandError::Usage("Usage: session resume <id>".to_string()));
+            }
+
+            let mut matches = db.find_session(rest.to_string()).await?;
+            let session = match matches.len() {
+                0 => return Err(CommandError::NotFound(format!("No session found matching '{}'", rest))),
+                1 => matches.remove(0),
+                _ if matches[0].id == rest => matches.remove(0),
+                n => return Err(CommandError::Usage(format!("'{}' matches {} sessions; use more of the id", rest, n))),
+            };
+
+            memory_manager.resume_session(session).await;
+            let session = current(memory_manager)?;
+            println!("▶️ Resumed session {} ({})", session.id.cyan(), session.topic);
+            Ok(())
+        }
+        "summary" => {
+            if memory_manager.current_session().is_none() {
+                return Err(CommandError::NotFound("No active session. Start one with: session new [topic]".to_string()));
+            }
+
+            memory_manager.update_session_summary(provider.as_ref()).await
+                .map_err(|e| CommandError::Provider(format!("Failed to summarize session: {}", e)))?;
+            let session = current(memory_manager)?;
+            println!("\n📝 Summary of {} ({}):", session.id.cyan(), session.topic);
+            println!("{}", session.summary.truecolor(255, 236, 179));
+            Ok(())
+        }
+        _ => {
+            println!("🗂️ Session Commands:");
+            println!("  session new [topic]   - Start a new conversation session");
+            println!("  session list          - List saved sessions");
+            println!("  session resume <id>   - Continue an earlier session (id prefix is enough)");
+            println!("  session summary       - Summarize the current session");
+            Ok(())
+        }
+    }
+}
+
+/// `clear [--hard]`: ends the active session so the next message starts fresh.
+/// `--hard` also deletes the session's memories from the vector store.
+pub async fn clear(input: &str, memory_manager: &mut MemoryManager) -> CommandResult {
+    let hard = match input.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [_] => false,
+        [_, "--hard"] => true,
+        _ => return Err(CommandError::Usage("Usage: clear [--hard]".to_string())),
+    };
+
+    let Some(session) = memory_manager.clear_current_session() else {
+        println!("Nothing to clear; no session is active.");
+        return Ok(());
+    };
+
+    if hard {
+        let removed = memory_manager.clear_session(&session.id).await
+            .map_err(|e| CommandError::VectorDb(format!("Failed to delete session memories: {}", e)))?;
+        println!("🧹 Cleared session {} ({}) and deleted {} memories.", session.id.cyan(), session.topic, removed);
+    } else {
+        println!("🧹 Cleared session {} ({}). Its memories won't be used again in this run.", session.id.cyan(), session.topic);
+    }
+    println!("Your next message starts a fresh conversation.");
+    Ok(())
+}
+
+fn current(memory_manager: &MemoryManager) -> CommandResult<ConversationSession> {
+    memory_manager.current_session()
+        .cloned()
+        .ok_or_else(|| CommandError::NotFound("No active session".to_string()))
+}
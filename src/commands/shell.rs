@@ -0,0 +1,268 @@
+use super::error::{CommandError, CommandResult};
+use std::env;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+
+/// Read-only commands `sh` runs without asking, unless `SHELL_ALLOW` replaces them. Nothing
+/// here can run another program, as `env`, `find -exec` or `xargs` could.
+const DEFAULT_ALLOW: &[&str] = &[
+    "df", "du", "free", "uptime", "uname", "whoami", "hostname", "date", "pwd", "ls",
+    "ps", "cat", "head", "tail", "wc", "grep", "which", "echo",
+];
+/// Commands `sh` refuses outright, on top of any in `SHELL_DENY`, wherever they appear in
+/// the command line.
+const DEFAULT_DENY: &[&str] = &[
+    "rm", "rmdir", "dd", "mkfs", "shutdown", "reboot", "halt", "poweroff", "sudo", "su",
+    "kill", "killall", "chmod", "chown",
+];
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_MAX_OUTPUT: usize = 16 * 1024;
+
+/// What `sh` may run, and for how long.
+#[derive(Debug, Clone)]
+pub struct ShellPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub timeout: Duration,
+    /// Bytes of output kept; the rest is cut off
+    pub max_output: usize,
+}
+
+/// Whether a command line may run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Allowed,
+    /// Not on the allowlist, or uses redirection or substitution: ask first
+    NeedsConfirmation,
+    Denied(String),
+}
+
+/// A finished command.
+#[derive(Debug, Clone)]
+pub struct ShellOutput {
+    /// `None` if the command was killed by a signal
+    pub exit_code: Option<i32>,
+    /// Stdout followed by stderr
+    pub output: String,
+    pub truncated: bool,
+}
+
+fn list_from_env(var: &str) -> Option<Vec<String>> {
+    env::var(var).ok().map(|value| {
+        value.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect()
+    })
+}
+
+impl ShellPolicy {
+    /// The policy from `SHELL_ALLOW` (replaces the default allowlist), `SHELL_DENY` (adds to
+    /// the denylist), `SHELL_TIMEOUT_SECS` and `SHELL_MAX_OUTPUT`.
+    pub fn from_env() -> Self {
+        let mut deny: Vec<String> = DEFAULT_DENY.iter().map(|s| s.to_string()).collect();
+        deny.extend(list_from_env("SHELL_DENY").unwrap_or_default());
+        Self {
+            allow: list_from_env("SHELL_ALLOW")
+                .unwrap_or_else(|| DEFAULT_ALLOW.iter().map(|s| s.to_string()).collect()),
+            deny,
+            timeout: Duration::from_secs(
+                env::var("SHELL_TIMEOUT_SECS").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_TIMEOUT_SECS),
+            ),
+            max_output: env::var("SHELL_MAX_OUTPUT").ok().and_then(|s| s.parse().ok()).unwrap_or(DEFAULT_MAX_OUTPUT),
+        }
+    }
+
+    /// Checks every program in a pipeline or command list, e.g. both `ps` and `grep` in
+    /// `ps aux | grep nginx`. Denied commands are looked for in every word, since one
+    /// program can run another, as in `env rm` or `find -exec rm`.
+    pub fn check(&self, command_line: &str) -> Verdict {
+        let programs = programs(command_line);
+        if programs.is_empty() {
+            return Verdict::Denied("Nothing to run.".to_string());
+        }
+        if let Some(word) = words(command_line).find(|w| self.deny.iter().any(|d| d == w)) {
+            return Verdict::Denied(format!("'{}' is not allowed (SHELL_DENY)", word));
+        }
+        // Redirects, and anything in `$( )`, `<( )` or a `( )` subshell, which runs unchecked
+        let rewrites = command_line.contains(['>', '`', '(']);
+        if rewrites || !programs.iter().all(|p| self.allow.iter().any(|a| a == p)) {
+            return Verdict::NeedsConfirmation;
+        }
+        Verdict::Allowed
+    }
+
+    /// Runs `command_line` with `sh -c` in its own process group, keeping at most
+    /// `max_output` bytes of each stream and killing the whole group after the timeout.
+    pub async fn run(&self, command_line: &str) -> CommandResult<ShellOutput> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command_line)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| CommandError::Io(format!("Failed to start command: {}", e)))?;
+        let group = child.id();
+        let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+
+        let finished = async {
+            let (stdout, stderr) = tokio::join!(read_capped(stdout, self.max_output), read_capped(stderr, self.max_output));
+            let status = child.wait().await?;
+            std::io::Result::Ok((stdout?, stderr?, status))
+        };
+        let result = tokio::time::timeout(self.timeout, finished).await;
+        // Background jobs the command started would otherwise outlive it
+        if let Some(group) = group {
+            kill_group(group);
+        }
+        let ((stdout, stdout_cut), (stderr, stderr_cut), status) = result
+            .map_err(|_| CommandError::Io(format!("Command timed out after {}s and was stopped", self.timeout.as_secs_f32())))?
+            .map_err(|e| CommandError::Io(format!("Command failed: {}", e)))?;
+
+        let mut output = String::from_utf8_lossy(&stdout).into_owned();
+        output.push_str(&String::from_utf8_lossy(&stderr));
+        let truncated = stdout_cut || stderr_cut || output.len() > self.max_output;
+        if output.len() > self.max_output {
+            let mut end = self.max_output;
+            while !output.is_char_boundary(end) {
+                end -= 1;
+            }
+            output.truncate(end);
+        }
+        Ok(ShellOutput { exit_code: status.code(), output, truncated })
+    }
+}
+
+/// Up to `limit` bytes of `stream`, and whether there was more. The rest is read and
+/// dropped so the command doesn't block on a full pipe.
+async fn read_capped(stream: Option<impl AsyncRead + Unpin>, limit: usize) -> std::io::Result<(Vec<u8>, bool)> {
+    let Some(mut stream) = stream else {
+        return Ok((Vec::new(), false));
+    };
+    let mut kept = Vec::new();
+    (&mut stream).take(limit as u64).read_to_end(&mut kept).await?;
+    let dropped = tokio::io::copy(&mut stream, &mut tokio::io::sink()).await?;
+    Ok((kept, dropped > 0))
+}
+
+/// Kills every process in group `group`; there is nothing to do once they have all exited.
+fn kill_group(group: u32) {
+    // SAFETY: killpg only sends a signal; a stale group id fails with ESRCH
+    unsafe {
+        libc::killpg(group as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+/// The program names in a command line: the first word of each `|`, `;`, `&&` or `||` part,
+/// skipping leading `VAR=value` assignments.
+fn programs(command_line: &str) -> Vec<&str> {
+    command_line
+        .split(['|', ';', '&', '\n'])
+        .filter_map(|segment| {
+            segment.split_whitespace().find(|word| !word.contains('='))
+        })
+        .map(|program| program.rsplit('/').next().unwrap_or(program))
+        .collect()
+}
+
+/// Every word of a command line as a program name, without quotes or a leading path.
+fn words(command_line: &str) -> impl Iterator<Item = &str> {
+    command_line
+        .split(|c: char| c.is_whitespace() || "|;&()`'\"".contains(c))
+        .filter(|word| !word.is_empty())
+        .map(|word| word.rsplit('/').next().unwrap_or(word))
+}
+
+/// The prompt asking the model to explain a command's output.
+pub fn interpretation_prompt(command_line: &str, result: &ShellOutput) -> String {
+    format!(
+        "I ran `{}` ({}). Output{}:\n```\n{}\n```\n\
+        Explain what this shows, and point out anything that looks wrong or needs attention.",
+        command_line,
+        exit_status(result),
+        if result.truncated { " (cut short)" } else { "" },
+        result.output.trim_end()
+    )
+}
+
+/// `exit 0`, `exit 2` or `killed`.
+pub fn exit_status(result: &ShellOutput) -> String {
+    match result.exit_code {
+        Some(code) => format!("exit {}", code),
+        None => "killed".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ShellPolicy {
+        ShellPolicy {
+            allow: vec!["df".to_string(), "echo".to_string(), "grep".to_string(), "ps".to_string()],
+            deny: vec!["rm".to_string()],
+            timeout: Duration::from_millis(300),
+            max_output: 8,
+        }
+    }
+
+    #[test]
+    fn test_policy_checks_every_program() {
+        let policy = policy();
+        assert_eq!(policy.check("df -h"), Verdict::Allowed);
+        assert_eq!(policy.check("ps aux | grep nginx"), Verdict::Allowed);
+        assert_eq!(policy.check("LANG=C df"), Verdict::Allowed);
+        assert_eq!(policy.check("apt update"), Verdict::NeedsConfirmation);
+        assert_eq!(policy.check("echo hi > notes.txt"), Verdict::NeedsConfirmation);
+        assert_eq!(policy.check("echo $(whoami)"), Verdict::NeedsConfirmation);
+        assert_eq!(policy.check("echo <(touch x)"), Verdict::NeedsConfirmation);
+        assert_eq!(policy.check("(touch x)"), Verdict::NeedsConfirmation);
+        assert!(matches!(policy.check("df && /bin/rm -rf /tmp/x"), Verdict::Denied(_)));
+        assert!(matches!(policy.check("env rm -rf ~"), Verdict::Denied(_)));
+        assert!(matches!(policy.check("find . -exec 'rm' {} +"), Verdict::Denied(_)));
+        assert!(matches!(policy.check("  "), Verdict::Denied(_)));
+    }
+
+    #[tokio::test]
+    async fn test_run_caps_output_and_times_out() {
+        let policy = policy();
+        let result = policy.run("echo hello world; exit 3").await.unwrap();
+        assert_eq!(result.output, "hello wo");
+        assert!(result.truncated);
+        assert_eq!(exit_status(&result), "exit 3");
+
+        let err = policy.run("sleep 5").await.unwrap_err();
+        assert!(matches!(&err, CommandError::Io(message) if message.contains("timed out")));
+    }
+
+    #[test]
+    fn test_default_allowlist_has_no_program_runners() {
+        let policy = ShellPolicy { allow: DEFAULT_ALLOW.iter().map(|s| s.to_string()).collect(), ..policy() };
+        assert_eq!(policy.check("find . -delete"), Verdict::NeedsConfirmation);
+        assert_eq!(policy.check("env"), Verdict::NeedsConfirmation);
+        assert_eq!(policy.check("ls | xargs cat"), Verdict::NeedsConfirmation);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_stops_background_jobs() {
+        let pid_file = std::env::temp_dir().join(format!("sh-{}.pid", uuid::Uuid::new_v4()));
+        let command = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        assert!(policy().run(&command).await.is_err());
+
+        let pid: u32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+        std::fs::remove_file(&pid_file).ok();
+        // A killed job lingers as a zombie until something reaps it
+        let mut alive = true;
+        for _ in 0..50 {
+            alive = std::fs::read_to_string(format!("/proc/{}/stat", pid))
+                .is_ok_and(|stat| stat.rsplit(')').next().is_some_and(|rest| !rest.trim_start().starts_with('Z')));
+            if !alive {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!alive, "background sleep {} survived the timeout", pid);
+    }
+}
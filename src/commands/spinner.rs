@@ -0,0 +1,32 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Duration;
+
+/// A spinner with elapsed time, shown while waiting on a slow call.
+/// It clears itself when stopped or dropped, including when the call is cancelled.
+pub struct Spinner(ProgressBar);
+
+impl Spinner {
+    pub fn start(message: &str) -> Self {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(ProgressStyle::default_spinner()
+            .template("{spinner:.green} [{elapsed}] {msg}")
+            .unwrap());
+        pb.set_message(message.to_string());
+        pb.enable_steady_tick(Duration::from_millis(100));
+        Self(pb)
+    }
+
+    pub fn set_message(&self, message: &str) {
+        self.0.set_message(message.to_string());
+    }
+
+    pub fn stop(&self) {
+        self.0.finish_and_clear();
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,64 @@
+use colored::{ColoredString, Colorize};
+use std::time::Duration;
+
+use crate::llm::MonitorStats;
+
+/// What the startup panel and `status` report, gathered by the command handler.
+pub struct Status<'a> {
+    pub provider: &'a str,
+    /// The active model, or `None` when the provider failed its health check
+    pub model: Option<String>,
+    pub character: &'a str,
+    pub database: &'a str,
+    /// Where vectors are kept, e.g. `qdrant at http://localhost:6334`
+    pub vectors: String,
+    pub vectors_connected: bool,
+    pub twitter: bool,
+    pub crawler: bool,
+}
+
+fn up_down(ok: bool, up: &str, down: &str) -> ColoredString {
+    if ok { up.green() } else { down.red() }
+}
+
+fn on_off(enabled: bool) -> ColoredString {
+    if enabled { "on".green() } else { "off".dimmed() }
+}
+
+/// Prints the compact status panel.
+pub fn print_status(status: &Status) {
+    let model = match &status.model {
+        Some(model) => format!("{} {}", model.cyan(), "●".green()),
+        None => format!("{}", "● unreachable".red()),
+    };
+    println!("\n🤖 {} {}", status.provider.cyan(), model);
+    println!("  character  {}", status.character);
+    println!("  database   {}", status.database);
+    println!("  vectors    {} {}", status.vectors, up_down(status.vectors_connected, "● connected", "● offline, memory is off"));
+    println!(
+        "  features   twitter {} · crawler {} · food {}",
+        on_off(status.twitter),
+        on_off(status.crawler),
+        on_off(cfg!(feature = "food")),
+    );
+    println!("\n{}\n", "Type 'help' for commands, or just start chatting.".dimmed());
+}
+
+/// Prints `stats`: the conversation's token use and context window since the last cleanup.
+pub fn print_stats(stats: &MonitorStats) {
+    println!("\n📊 {}", "Conversation".cyan());
+    println!("  tokens     {}", stats.tokens.to_string().cyan());
+    println!("  messages   {}", stats.messages);
+    println!("  context    {}/{} messages", stats.context_len, stats.context_window);
+    println!("  cleanup    {} ago\n", format_elapsed(stats.since_cleanup));
+}
+
+/// `42s`, `5m` or `2h 10m`.
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
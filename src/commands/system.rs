@@ -1,7 +1,20 @@
 use colored::Colorize;
+use super::error::{CommandError, CommandResult};
+use super::registry::{self, HelpTopic, TOPICS};
 
-pub fn handle_command(input: &str) -> Result<(), String> {
-    match input.to_lowercase().as_str() {
+pub fn handle_command(input: &str) -> CommandResult {
+    let input = input.to_lowercase();
+    if let Some(name) = input.strip_prefix("help ") {
+        let topic = registry::topic(name).ok_or_else(|| CommandError::NotFound(format!(
+            "Unknown help topic '{}'. Topics: {}",
+            name.trim(),
+            TOPICS.iter().map(|t| t.name).collect::<Vec<_>>().join(", ")
+        )))?;
+        print_topic(topic);
+        return Ok(());
+    }
+
+    match input.as_str() {
         "help" => {
             println!("\n🤖 AI Assistant Commands:");
             println!("  Just type your question or request");
@@ -11,52 +24,37 @@ pub fn handle_command(input: &str) -> Result<(), String> {
             println!("    - help me debug this code: [your code]");
             println!();
 
-            println!("👤 Character Commands:");
-            println!("  chars         - List available characters");
-            println!("  load <name>   - Switch to a different character");
-            println!("  Example: load helpful, load friendly");
-            println!();
-
-            println!("🔄 Provider Commands:");
-            println!("  providers     - List available AI providers");
-            println!("  use <name>    - Switch to a different provider");
-            println!("  Example: use openai, use openrouter");
-            println!();
-
-            println!("🐦 Twitter Commands:");
-            println!("  tweet <message>           - Post a tweet");
-            println!("  tweet                     - Generate AI tweet");
-            println!("  reply <id> <message>      - Reply to a tweet");
-            println!("  dm @user: <message>       - Send a direct message");
-            println!("  autopost start <minutes>  - Start auto-posting");
-            println!("  autopost stop             - Stop auto-posting");
-            println!("  logs                      - Show recent activity");
-            println!();
-
-            println!("🕷️ Web Commands:");
-            println!("  analyze <url>    - Analyze webpage content");
-            println!("  research <topic> - Research a topic");
-            println!("  links <url>      - Extract links from webpage");
-            println!();
-
-            println!("⚙️ System Commands:");
-            println!("  help  - Show this help menu");
-            println!("  exit  - Exit the program");
+            println!("📖 Help Topics (type help <topic>):");
+            for topic in TOPICS {
+                let words: Vec<&str> = topic.commands.iter()
+                    .filter_map(|c| c.usage.split_whitespace().next())
+                    .fold(Vec::new(), |mut words, word| {
+                        if !words.contains(&word) {
+                            words.push(word);
+                        }
+                        words
+                    });
+                println!("  {:<10} {} ({})", topic.name.cyan(), topic.title, words.join(", "));
+            }
             println!();
-
-            println!("📄 Document Commands:");
-            println!("  doc analyze <file>   - Analyze a document");
-            println!("  doc summary <file>   - Get a quick summary");
-            println!("  doc extract <file>   - Extract text from document");
-            println!("  doc ocr <image>      - Extract text from image");
-            println!("  doc batch <folder>   - Process multiple files");
-            println!("  doc info <file>      - Show file information");
             Ok(())
         },
         "exit" | "quit" => {
             println!("👋 Goodbye!");
             std::process::exit(0);
         },
-        _ => Err("Unknown system command. Type 'help' for available commands.".to_string())
+        _ => Err(CommandError::Usage("Unknown system command. Type 'help' for available commands.".to_string()))
+    }
+}
+
+fn print_topic(topic: &HelpTopic) {
+    println!("\n{}:", topic.title);
+    let width = topic.commands.iter().map(|c| c.usage.len()).max().unwrap_or(0);
+    for command in topic.commands {
+        println!("  {:<width$}  - {}", command.usage, command.description, width = width);
+    }
+    if !topic.examples.is_empty() {
+        println!("  Example: {}", topic.examples.join(", "));
     }
-}
\ No newline at end of file
+    println!();
+}
@@ -1,9 +1,10 @@
+use super::error::{CommandError, CommandResult};
 use crate::providers::twitter::manager::ConversationManager;
 
 pub async fn handle_command(
     input: &str,
     manager: &mut Option<ConversationManager>
-) -> Result<(), String> {
+) -> CommandResult {
     if let Some(ref mut manager) = manager {
         if input.trim() == "tweet" {
             println!("🤖 Generating AI tweet...");
@@ -21,9 +22,9 @@ pub async fn handle_command(
             }
         } else {
             manager.handle_command(input).await
-                .map_err(|e| format!("Twitter error: {}", e))
+                .map_err(|e| CommandError::Io(format!("Twitter error: {}", e)))
         }
     } else {
-        Err("Twitter functionality not enabled. Run with --twitter flag to enable.".to_string())
+        Err(CommandError::Usage("Twitter functionality not enabled. Run with --twitter flag to enable.".to_string()))
     }
 }
\ No newline at end of file
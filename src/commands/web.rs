@@ -1,173 +1,322 @@
-use crate::providers::web_crawler::crawler_manager::WebCrawlerManager;
+use crate::providers::web_crawler::crawler_manager::{WebCrawlerManager, DEFAULT_MAX_PAGES};
+use crate::providers::web_crawler::LinkOptions;
 use crate::providers::traits::CompletionProvider;
 use crate::llm::memory::MemoryManager;
+use crate::llm::{ContextBuilder, EmbeddingGenerator};
+use crate::llm::untrusted::fit_untrusted_to_provider;
 use colored::Colorize;
+use super::error::{CommandError, CommandResult};
+use super::spinner::Spinner;
+use super::OutputMode;
+use serde_json::json;
 
 pub async fn handle_command(
     input: &str,
     crawler: &WebCrawlerManager,
     provider: &Box<dyn CompletionProvider + Send + Sync>,
     memory_manager: &mut MemoryManager,
-) -> Result<String, String> {
+    embedder: &EmbeddingGenerator,
+    output: OutputMode,
+) -> CommandResult<String> {
     match input {
         s if s.starts_with("analyze ") => {
             let url = s.trim_start_matches("analyze ").trim();
             if url.is_empty() {
-                println!("Please provide a URL to analyze.");
-                println!("Usage: analyze <url>");
+                output.status("Please provide a URL to analyze.");
+                output.status("Usage: analyze <url>");
                 return Ok("Please provide a URL to analyze.".to_string());
             }
 
+            let spinner = Spinner::start(&format!("Fetching {}...", url));
             let content = crawler.analyze_url(url).await
-                .map_err(|e| format!("Failed to analyze webpage: {}", e))?;
+                .map_err(|e| CommandError::Io(format!("Failed to analyze webpage: {}", e)))?;
 
             // Store webpage content in memory
             let context = format!("Webpage being discussed: {}\nContent:\n{}", url, content);
-            let embedding = generate_embedding(&context).await?;
-            memory_manager.store_memory(&context, "webpage", embedding, None)
-                .await
-                .map_err(|e| format!("Failed to store memory: {}", e))?;
-
-            // Create personality-aware analysis prompt
-            let analysis_prompt = format!(
-                "{}\n\nAs this character, analyze and synthesize this webpage content and provide your unique perspective. \
+            remember(memory_manager, embedder, &context, "webpage").await?;
+
+            // Create personality-aware analysis prompt, trimming the page to fit the model
+            let instructions = "As this character, analyze and synthesize this webpage content and provide your unique perspective. \
                 find the key point , Consider your personality traits and expertise when providing this analysis. \
-                Be creative and stay true to your character's style:\n\n{}",
-                provider.get_system_message(),
-                content
-            );
+                Be creative and stay true to your character's style:";
+            let sections = fit_untrusted_to_provider(provider.as_ref(), &provider.get_system_message(), url, &content, instructions).await;
+            let analysis_prompt = format!("{}\n\n{}\n\n{}", sections.system, sections.user, sections.context);
 
+            spinner.set_message("Analyzing...");
             let analysis = provider.complete(&analysis_prompt).await
-                .map_err(|e| format!("Failed to analyze content: {}", e))?;
+                .map_err(|e| CommandError::Provider(format!("Failed to analyze content: {}", e)))?;
+            spinner.stop();
 
             // Store analysis in memory
             let analysis_context = format!("Analysis of webpage: {}\n{}", url, analysis);
-            let embedding = generate_embedding(&analysis_context).await?;
-            memory_manager.store_memory(&analysis_context, "analysis", embedding, None)
-                .await
-                .map_err(|e| format!("Failed to store memory: {}", e))?;
-
-            println!("\n📊 Analysis Results for {}:", url.bright_yellow());
-            println!("{}", analysis.truecolor(255, 236, 179));
-            println!("\n💭 You can now ask questions about this webpage. Try:");
-            println!("  web chat what are the main points?");
-            println!("  web chat can you explain [specific topic] in more detail?");
+            remember(memory_manager, embedder, &analysis_context, "analysis").await?;
+
+            output.status(format!("\n📊 Analysis Results for {}:", url.bright_yellow()));
+            output.result(analysis.truecolor(255, 236, 179));
+            output.status("\n💭 You can now ask questions about this webpage. Try:");
+            output.status("  web chat what are the main points?");
+            output.status("  web chat can you explain [specific topic] in more detail?");
+            output.json(json!({ "command": "web analyze", "url": url, "response": analysis }));
             Ok("Analysis complete.".to_string())
         },
         s if s.starts_with("research ") => {
-            let topic = s.trim_start_matches("research ").trim();
+            let (topic, depth) = numeric_option(s.trim_start_matches("research "), "--depth")
+                .map_err(|e| CommandError::Usage(format!("{}\nUsage: research <topic> [--depth <n>]", e)))?;
+            let topic = topic.as_str();
             if topic.is_empty() {
-                println!("Please provide a topic to research.");
-                println!("Usage: research <topic>");
+                output.status("Please provide a topic to research.");
+                output.status("Usage: research <topic> [--depth <n>]");
                 return Ok("Please provide a topic to research.".to_string());
             }
 
-            let results = crawler.research_topic(topic).await
-                .map_err(|e| format!("Failed to research topic: {}", e))?;
+            let spinner = Spinner::start(&format!("Researching '{}'...", topic));
+            let results = crawler.research_topic(topic, depth.unwrap_or(0), &mut |progress| {
+                spinner.set_message(&format!(
+                    "Researching '{}' ({}/{}, {} found): {}",
+                    topic, progress.visited, progress.total, progress.found, progress.url
+                ));
+            }).await
+                .map_err(|e| CommandError::Io(format!("Failed to research topic: {}", e)))?;
 
             // Store research results in memory
             let context = format!("Research topic: {}\nResearch findings:\n{}", topic, results.join("\n"));
-            let embedding = generate_embedding(&context).await?;
-            memory_manager.store_memory(&context, "research", embedding, None)
-                .await
-                .map_err(|e| format!("Failed to store memory: {}", e))?;
+            remember(memory_manager, embedder, &context, "research").await?;
 
             // Create personality-aware research prompt with better structure
-            let research_prompt = format!(
-                "{}\n\n\
-                As this character, analyze and synthesize the research about '{}'in your unique style. \
+            let instructions = format!(
+                "As this character, analyze and synthesize the research about '{}'in your unique style. \
                 Structure your response in these sections:\n\
                 1. Key Findings (3-10 main points)\n\
                 2. Analysis with (your unique perspective)\n\
                 Keep each section focused and insightfull \
                 Stay true to your character's expertise and communication style.\n\n\
-                3.then make quick summarize all of these , short and insightfull and adviceswith your own unique style:\n{}", 
-                provider.get_system_message(),
-                topic,
-                results.join("\n")
+                3.then make quick summarize all of these , short and insightfull and adviceswith your own unique style:",
+                topic
             );
+            let source = format!("web research on {}", topic);
+            let sections = fit_untrusted_to_provider(provider.as_ref(), &provider.get_system_message(), &source, &results.join("\n"), &instructions).await;
+            let research_prompt = format!("{}\n\n{}\n{}", sections.system, sections.user, sections.context);
 
+            spinner.set_message("Synthesizing findings...");
             let analysis = provider.complete(&research_prompt).await
-                .map_err(|e| format!("Failed to synthesize research: {}", e))?;
+                .map_err(|e| CommandError::Provider(format!("Failed to synthesize research: {}", e)))?;
+            spinner.stop();
 
             // Store analysis in memory
             let analysis_context = format!("Research analysis: {}\n{}", topic, analysis);
-            let embedding = generate_embedding(&analysis_context).await?;
-            memory_manager.store_memory(&analysis_context, "analysis", embedding, None)
-                .await
-                .map_err(|e| format!("Failed to store memory: {}", e))?;
-
-            println!("\n📚 Research Results for '{}':", topic.bright_yellow());
-            println!("{}", analysis.truecolor(255, 236, 179));
-            println!("\n💭 You can now ask questions about this research. Try:");
-            println!("  web chat tell me more about [specific finding]");
-            println!("  web chat what are the implications of [topic]?");
+            remember(memory_manager, embedder, &analysis_context, "analysis").await?;
+
+            output.status(format!("\n📚 Research Results for '{}':", topic.bright_yellow()));
+            output.result(analysis.truecolor(255, 236, 179));
+            output.status("\n💭 You can now ask questions about this research. Try:");
+            output.status("  web chat tell me more about [specific finding]");
+            output.status("  web chat what are the implications of [topic]?");
+            output.json(json!({ "command": "web research", "topic": topic, "findings": results, "response": analysis }));
             Ok("Research complete.".to_string())
         },
-        s if s.starts_with("links ") => {
-            let url = s.trim_start_matches("links ").trim();
-            if url.is_empty() {
-                println!("Please provide a URL to extract links from.");
-                println!("Usage: links <url>");
-                return Ok("Please provide a URL to extract links from.".to_string());
+        s if s.starts_with("crawl ") => {
+            let usage = "Usage: crawl <url> [--depth <n>] [--pages <n>]";
+            let ((url, pages), depth) = numeric_option(s.trim_start_matches("crawl "), "--depth")
+                .and_then(|(rest, depth)| Ok((numeric_option(&rest, "--pages")?, depth.unwrap_or(1))))
+                .map_err(|e| CommandError::Usage(format!("{}\n{}", e, usage)))?;
+            let max_pages = pages.unwrap_or(DEFAULT_MAX_PAGES).max(1);
+            if url.is_empty() || url.contains(' ') {
+                return Err(CommandError::Usage(usage.to_string()));
             }
 
-            let links = crawler.extract_links(url).await
-                .map_err(|e| format!("Failed to extract links: {}", e))?;
+            let spinner = Spinner::start(&format!("Crawling {} (depth {}, up to {} pages)...", url, depth, max_pages));
+            let pages = crawler.crawl_recursive(&url, depth, max_pages).await
+                .map_err(|e| CommandError::Io(format!("Failed to crawl {}: {}", url, e)))?;
 
-            println!("\n🔗 Links from {}:", url.bright_yellow());
-            let link_count = links.len();
-            for link in links {
-                println!("• {}", link);
+            // Each page is remembered on its own so `web chat` can find the one that matters
+            for page in &pages {
+                let context = format!("Webpage being discussed: {}\nContent:\n{}", page.url, page.text);
+                remember(memory_manager, embedder, &context, "webpage").await?;
             }
-            println!("\n📊 Total links found: {}", link_count);
+
+            let site = pages.iter()
+                .map(|page| format!("Page: {}\n{}", page.url, page.text))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let instructions = "As this character, analyze these pages from one website together: what the site covers, \
+                its key points and how the pages relate. Stay true to your character's style:";
+            let sections = fit_untrusted_to_provider(provider.as_ref(), &provider.get_system_message(), &url, &site, instructions).await;
+            let crawl_prompt = format!("{}\n\n{}\n\n{}", sections.system, sections.user, sections.context);
+
+            spinner.set_message(&format!("Analyzing {} pages...", pages.len()));
+            let analysis = provider.complete(&crawl_prompt).await
+                .map_err(|e| CommandError::Provider(format!("Failed to analyze site: {}", e)))?;
+            spinner.stop();
+            remember(memory_manager, embedder, &format!("Analysis of website: {}\n{}", url, analysis), "analysis").await?;
+
+            output.status(format!("\n🕸️ Crawled {} pages from {}:", pages.len(), url.bright_yellow()));
+            for page in &pages {
+                output.status(format!("• {}", page.url));
+            }
+            output.result(analysis.truecolor(255, 236, 179));
+            let urls: Vec<&str> = pages.iter().map(|page| page.url.as_str()).collect();
+            output.json(json!({ "command": "web crawl", "url": url, "depth": depth, "pages": urls, "response": analysis }));
+            Ok("Crawl complete.".to_string())
+        },
+        s if s.starts_with("links ") => {
+            let (url, options) = match LinkOptions::parse(s.trim_start_matches("links ")) {
+                Ok(parsed) => parsed,
+                Err(message) => {
+                    output.status(&message);
+                    output.status("Usage: links <url> [--same-domain] [--dedupe] [--exclude <regex>]...");
+                    return Ok(message);
+                }
+            };
+            let url = url.as_str();
+
+            let links = crawler.extract_links(url, &options).await
+                .map_err(|e| CommandError::Io(format!("Failed to extract links: {}", e)))?;
+
+            output.status(format!("\n🔗 Links from {}:", url.bright_yellow()));
+            for link in &links {
+                output.result(format!("• {}", link));
+            }
+            output.status(format!("\n📊 Total links found: {}", links.len()));
+            output.json(json!({ "command": "web links", "url": url, "links": links }));
             Ok("Links extracted.".to_string())
         },
         s if s.starts_with("chat ") => {
             let query = s.trim_start_matches("chat ").trim();
 
-            // Generate embedding for the query
-            let query_embedding = generate_embedding(query).await?;
-            
-            // Search for relevant memories
-            let memories = memory_manager.search_similar(query_embedding, 5).await
-                .map_err(|e| format!("Failed to search memories: {}", e))?;
-            
-            // Build context from memories
-            let context = memory_manager.summarize_memories(&memories).await;
-
-            // Create chat prompt with context
-            let chat_prompt = format!(
-                "{}\n\n\
-                Previous context:\n{}\n\n\
-                User question: {}\n\n\
-                Answer the question based on the previous context while maintaining your character's personality. \
-                Keep your response focused and relevant to the topic being discussed.",
-                provider.get_system_message(),
-                context,
-                query
-            );
+            let context = ContextBuilder::new(embedder, memory_manager).build(query).await?;
+
+            let chat_prompt = chat_prompt(provider.as_ref(), &context, query).await;
 
+            let spinner = Spinner::start("Thinking...");
             let response = provider.complete(&chat_prompt).await
-                .map_err(|e| format!("Failed to get response: {}", e))?;
+                .map_err(|e| CommandError::Provider(format!("Failed to get response: {}", e)))?;
+            spinner.stop();
 
             // Store the chat interaction
             let interaction = format!("Q: {}\nA: {}", query, response);
-            let embedding = generate_embedding(&interaction).await?;
-            memory_manager.store_memory(&interaction, "chat", embedding, None)
-                .await
-                .map_err(|e| format!("Failed to store memory: {}", e))?;
+            remember(memory_manager, embedder, &interaction, "chat").await?;
 
-            println!("\n💬 Response:");
-            println!("{}", response.bright_green());
+            output.status("\n💬 Response:");
+            output.result(response.bright_green());
+            output.json(json!({ "command": "web chat", "query": query, "response": response }));
             Ok("Chat completed.".to_string())
         },
-        _ => Err("Unknown web command. Available commands:\n  analyze <url> - Analyze webpage content\n  research <topic> - Research a topic\n  links <url> - Extract links from webpage".to_string())
+        _ => Err(CommandError::Usage("Unknown web command. Available commands:\n  analyze <url> - Analyze webpage content\n  research <topic> - Research a topic\n  crawl <url> - Crawl a site\n  links <url> - Extract links from webpage".to_string()))
     }
 }
 
-async fn generate_embedding(text: &str) -> Result<Vec<f32>, String> {
-    // This is a placeholder - you should implement actual embedding generation
-    // For now, return a dummy embedding of size 1536 (OpenAI's embedding size)
-    Ok(vec![0.0; 1536])
+/// `args` without `flag <n>`, and `n` if the flag was given.
+fn numeric_option(args: &str, flag: &str) -> Result<(String, Option<usize>), String> {
+    let mut rest = Vec::new();
+    let mut value = None;
+    let mut words = args.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == flag {
+            let n = words.next().and_then(|n| n.parse().ok())
+                .ok_or_else(|| format!("{} needs a number", flag))?;
+            value = Some(n);
+        } else {
+            rest.push(word);
+        }
+    }
+    Ok((rest.join(" "), value))
+}
+
+/// The `web chat` prompt answering `query` from the remembered pages in `context`, fitted to
+/// `provider`'s budget and shared with the API.
+pub async fn chat_prompt<P>(provider: &P, context: &str, query: &str) -> String
+where
+    P: CompletionProvider + ?Sized,
+{
+    // Remembered pages are still someone else's words
+    let sections = fit_untrusted_to_provider(provider, &provider.get_system_message(), "remembered web pages", context, query).await;
+    format!(
+        "{}\n\n\
+        Previous context:\n{}\n\n\
+        User question: {}\n\n\
+        Answer the question based on the previous context while maintaining your character's personality. \
+        Keep your response focused and relevant to the topic being discussed.",
+        sections.system,
+        sections.context,
+        sections.user
+    )
+}
+
+/// Embeds `text` and stores it in memory so later `web chat` questions can find it.
+async fn remember(memory_manager: &MemoryManager, embedder: &EmbeddingGenerator, text: &str, role: &str) -> CommandResult {
+    let embedding = embedder.generate_embedding(text).await
+        .map_err(|e| CommandError::Provider(format!("Failed to generate embedding: {}", e)))?;
+    memory_manager.store_memory(text, role, embedding, None)
+        .await
+        .map_err(|e| CommandError::VectorDb(format!("Failed to store memory: {}", e)))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::vector_db::VectorDB;
+    use crate::providers::openai::openai::OpenAIProvider;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_links_prints_json() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        use crate::personality::PersonalityProfile;
+        use crate::providers::mock::MockProvider;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = vec![0; 2048];
+                let _ = socket.read(&mut request).await;
+                let body = r#"<a href="/a">A</a><a href="https://elsewhere.example/">Out</a>"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(), body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let profile = PersonalityProfile { name: "Links Test Bot".to_string(), attributes: json!({}) };
+        let crawler = WebCrawlerManager::new(profile).await.unwrap();
+        let mock = MockProvider::with_response("unused");
+        let provider: Box<dyn CompletionProvider + Send + Sync> = Box::new(mock.clone());
+        let embedder = EmbeddingGenerator::from_provider(Box::new(mock));
+        let store = Arc::new(SqliteVectorStore::in_memory().await);
+        let mut memory_manager = MemoryManager::new(store).await.unwrap();
+
+        let input = format!("links {} --same-domain", base);
+        handle_command(&input, &crawler, &provider, &mut memory_manager, &embedder, OutputMode::Json).await.unwrap();
+        assert_eq!(crate::commands::take_printed_json(), vec![json!({
+            "command": "web links",
+            "url": base,
+            "links": [format!("{}a", base)],
+        })]);
+    }
+
+    // Needs Qdrant and OPENAI_API_KEY: cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn test_web_analysis_is_retrievable_by_related_query() {
+        let api_key = std::env::var("OPENAI_API_KEY")
+            .expect("OPENAI_API_KEY environment variable not set");
+        let openai = OpenAIProvider::new(api_key, String::new()).await.unwrap();
+        let embedder = EmbeddingGenerator::from_provider(Box::new(openai));
+        let vector_db = VectorDB::new("http://localhost:6333").await.unwrap();
+        let memory_manager = MemoryManager::new(Arc::new(vector_db)).await.unwrap();
+
+        let analysis = format!(
+            "Analysis of webpage: https://example.com/{}\nThe article explains how sourdough starter ferments flour with wild yeast.",
+            uuid::Uuid::new_v4()
+        );
+        remember(&memory_manager, &embedder, &analysis, "analysis").await.unwrap();
+
+        let query = embedder.generate_embedding("how do I bake bread with natural yeast?").await.unwrap();
+        let memories = memory_manager.search_similar(query, 5, Some(crate::config::context_min_score())).await.unwrap();
+        assert!(memories.iter().any(|m| m.text == analysis));
+    }
 }
@@ -1,5 +1,320 @@
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::sync::{OnceLock, RwLock};
+use crate::providers::openai::openai::DEFAULT_EMBEDDING_MODEL;
+use crate::llm::EmbeddingGenerator;
+
+/// Values `config set` can change at runtime, with what they control.
+pub const TUNABLE_SETTINGS: &[(&str, &str)] = &[
+    ("temperature", "sampling temperature for every provider, 0.0 to 2.0"),
+    ("context_window", "recent turns replayed into each chat prompt, 0 to 100"),
+    ("use_knowledge", "add knowledge base entries a message mentions to its prompt, on or off"),
+    ("auto_learn", "extract facts from each chat turn into the knowledge base, on or off"),
+];
+
+/// Every Cargo feature other than `default`, with whether this build has it on.
+pub const COMPILED_FEATURES: &[(&str, bool)] = &[
+    ("food", cfg!(feature = "food")),
+    ("local-embeddings", cfg!(feature = "local-embeddings")),
+    ("mock", cfg!(feature = "mock")),
+    ("openapi", cfg!(feature = "openapi")),
+    ("swagger-ui", cfg!(feature = "swagger-ui")),
+    ("tls", cfg!(feature = "tls")),
+    ("upload", cfg!(feature = "upload")),
+];
+
+/// Set from the `temperature` setting; takes precedence over `<PROVIDER>_TEMPERATURE`.
+/// Requests that need a particular temperature pass it in their `CompletionOptions`
+/// instead of changing this.
+static TEMPERATURE_SETTING: RwLock<Option<f32>> = RwLock::new(None);
+
+/// Makes `temperature` the default for every provider, or clears it with `None`.
+pub fn set_default_temperature(temperature: Option<f32>) {
+    if let Ok(mut guard) = TEMPERATURE_SETTING.write() {
+        *guard = temperature;
+    }
+}
+
+/// Default sampling temperature for `provider`: the runtime setting, then `<PROVIDER>_TEMPERATURE`, then 0.7.
+pub fn temperature(provider: &str) -> f32 {
+    if let Some(temperature) = TEMPERATURE_SETTING.read().ok().and_then(|t| *t) {
+        return temperature;
+    }
+    env::var(format!("{}_TEMPERATURE", provider.to_uppercase()))
+        .ok()
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(0.7)
+}
+
+/// Checks a `config set` value and returns it in canonical form.
+pub fn parse_setting(key: &str, value: &str) -> Result<String, String> {
+    match key {
+        "temperature" => match value.parse::<f32>() {
+            Ok(t) if (0.0..=2.0).contains(&t) => Ok(t.to_string()),
+            _ => Err(format!("temperature must be a number from 0.0 to 2.0, got '{}'", value)),
+        },
+        "context_window" => match value.parse::<usize>() {
+            Ok(n) if n <= 100 => Ok(n.to_string()),
+            _ => Err(format!("context_window must be a whole number from 0 to 100, got '{}'", value)),
+        },
+        "use_knowledge" | "auto_learn" => match value.to_lowercase().as_str() {
+            "on" | "true" | "yes" => Ok("on".to_string()),
+            "off" | "false" | "no" => Ok("off".to_string()),
+            _ => Err(format!("{} must be on or off, got '{}'", key, value)),
+        },
+        _ => Err(format!(
+            "Unknown setting '{}'. Settable: {}",
+            key,
+            TUNABLE_SETTINGS.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(", ")
+        )),
+    }
+}
+
+/// Vector size when neither `EMBEDDING_DIMENSION` nor the embedding model says otherwise.
+pub const DEFAULT_EMBEDDING_DIM: usize = 1536;
+
+/// Output sizes of known embedding models, matched by name substring.
+const EMBEDDING_DIMS: &[(&str, usize)] = &[
+    ("text-embedding-3-large", 3072),
+    ("text-embedding-3-small", 1536),
+    ("text-embedding-ada-002", 1536),
+    ("mistral-embed", 1024),
+    ("nomic-embed-text", 768),
+    ("all-minilm", 384),
+    ("bge-small", 384),
+    ("bge-base", 768),
+    ("bge-large", 1024),
+];
+
+/// Vector size produced by `model`, if it is a known embedding model.
+pub fn model_embedding_dim(model: &str) -> Option<usize> {
+    let model = model.to_lowercase();
+    EMBEDDING_DIMS.iter()
+        .find(|(name, _)| model.contains(name))
+        .map(|(_, dim)| *dim)
+}
+
+/// The embedding model and the size of its vectors, settled once at startup so that every
+/// collection and placeholder vector agrees on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingConfig {
+    pub model: String,
+    pub dimension: usize,
+}
+
+/// Set by [`EmbeddingConfig::install`].
+static EMBEDDING_CONFIG: OnceLock<EmbeddingConfig> = OnceLock::new();
+
+impl EmbeddingConfig {
+    /// The model `embedder` embeds with and the size of its vectors: `EMBEDDING_DIMENSION`,
+    /// then the known size of the model, then the length of a vector it returns, then 1536.
+    pub async fn resolve(embedder: &EmbeddingGenerator) -> Self {
+        let model = embedder.model_name().await;
+        let mut dimension = configured_embedding_dim().or_else(|| model_embedding_dim(&model));
+        // Placeholder and prompted vectors are sized by this setting, so asking for one says nothing
+        if dimension.is_none() && embedder.reports_dimension() {
+            match embedder.generate_embedding("dimension probe").await {
+                Ok(vector) if !vector.is_empty() => dimension = Some(vector.len()),
+                Ok(_) => {}
+                Err(e) => log::warn!("Could not ask {} for its embedding size: {}", model, e),
+            }
+        }
+        Self { model, dimension: dimension.unwrap_or(DEFAULT_EMBEDDING_DIM) }
+    }
+
+    /// Makes this what [`embedding_dim`] reports for the rest of the run, returning the
+    /// configuration in effect; only the first call takes effect.
+    pub fn install(self) -> &'static EmbeddingConfig {
+        EMBEDDING_CONFIG.get_or_init(|| self)
+    }
+}
+
+/// `EMBEDDING_DIMENSION`, or its older name `EMBEDDING_DIM`.
+fn configured_embedding_dim() -> Option<usize> {
+    ["EMBEDDING_DIMENSION", "EMBEDDING_DIM"].iter()
+        .filter_map(|var| env::var(var).ok())
+        .find_map(|dim| dim.trim().parse().ok().filter(|dim| *dim > 0))
+}
+
+/// Model run on this machine when `EMBEDDING_BACKEND=local` and none is named.
+pub const DEFAULT_LOCAL_EMBEDDING_MODEL: &str = "all-MiniLM-L6-v2";
+
+/// `LOCAL_EMBEDDING_MODEL` or all-MiniLM-L6-v2, when `EMBEDDING_BACKEND=local` asks for
+/// embeddings computed on this machine rather than by a provider.
+pub fn local_embedding_model() -> Option<String> {
+    env::var("EMBEDDING_BACKEND").ok()
+        .filter(|backend| backend.trim().eq_ignore_ascii_case("local"))
+        .map(|_| env::var("LOCAL_EMBEDDING_MODEL").unwrap_or_else(|_| DEFAULT_LOCAL_EMBEDDING_MODEL.to_string()))
+}
+
+/// Where local embedding models are downloaded to: `EMBEDDING_CACHE_DIR`, or `data/models`.
+pub fn embedding_cache_dir() -> std::path::PathBuf {
+    env::var("EMBEDDING_CACHE_DIR").unwrap_or_else(|_| "data/models".to_string()).into()
+}
+
+/// Provider that embeds text unless `EMBEDDING_PROVIDER` names another.
+pub const DEFAULT_EMBEDDING_PROVIDER: &str = "openai";
+
+/// Which provider embeds text, chosen apart from the chat provider so that chat can run on
+/// Gemini while memories are embedded by OpenAI.
+#[derive(Clone, PartialEq)]
+pub struct EmbeddingProviderConfig {
+    /// Provider command name, e.g. `openai`
+    pub provider: String,
+    /// `EMBEDDING_MODEL`, if set
+    pub model: Option<String>,
+    /// The provider's `<PROVIDER>_API_KEY`
+    pub api_key: Option<String>,
+}
+
+impl EmbeddingProviderConfig {
+    /// `EMBEDDING_PROVIDER` (default openai), `EMBEDDING_MODEL` and that provider's API key.
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let provider = var("EMBEDDING_PROVIDER")
+            .map(|provider| provider.trim().to_lowercase())
+            .filter(|provider| !provider.is_empty())
+            .unwrap_or_else(|| DEFAULT_EMBEDDING_PROVIDER.to_string());
+        let model = var("EMBEDDING_MODEL")
+            .map(|model| model.trim().to_string())
+            .filter(|model| !model.is_empty());
+        let api_key = var(&format!("{}_API_KEY", provider.to_uppercase()));
+        Self { provider, model, api_key }
+    }
+
+    /// The embedding model: `EMBEDDING_MODEL`, or for OpenAI `OPENAI_EMBEDDING_MODEL` or
+    /// its default. Other providers have no model to name unless one is set.
+    pub fn model_name(&self) -> Option<String> {
+        self.model.clone().or_else(|| (self.provider == "openai").then(|| {
+            env::var("OPENAI_EMBEDDING_MODEL").unwrap_or_else(|_| DEFAULT_EMBEDDING_MODEL.to_string())
+        }))
+    }
+}
+
+/// The configured embedding provider's model, when it has an API key to embed with.
+fn provider_embedding_model() -> Option<String> {
+    let config = EmbeddingProviderConfig::from_env();
+    config.api_key.as_ref().and_then(|_| config.model_name())
+}
+
+/// Size of every stored and fallback vector: the installed [`EmbeddingConfig`]'s, or before
+/// startup has settled one, `EMBEDDING_DIMENSION`, then the size of the local or configured
+/// provider's embedding model in use, then 1536.
+pub fn embedding_dim() -> usize {
+    if let Some(config) = EMBEDDING_CONFIG.get() {
+        return config.dimension;
+    }
+    configured_embedding_dim()
+        .or_else(|| local_embedding_model().or_else(provider_embedding_model).and_then(|model| model_embedding_dim(&model)))
+        .unwrap_or(DEFAULT_EMBEDDING_DIM)
+}
+
+/// Texts sent in one request to providers with a batch embedding endpoint.
+pub const DEFAULT_EMBEDDING_BATCH_SIZE: usize = 100;
+
+/// Texts per batch embedding request: `EMBEDDING_BATCH_SIZE`, or 100.
+pub fn embedding_batch_size() -> usize {
+    env::var("EMBEDDING_BATCH_SIZE").ok()
+        .and_then(|n| n.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_EMBEDDING_BATCH_SIZE)
+}
+
+/// Single embedding requests in flight at once for providers without a batch endpoint.
+pub const EMBEDDING_CONCURRENCY: usize = 8;
+
+/// Whether phrases like "ignore previous instructions" are cut from web and document text
+/// before it reaches a prompt (`STRIP_PROMPT_INJECTIONS`, on unless set to false, off or 0).
+pub fn strip_prompt_injections() -> bool {
+    !matches!(
+        env::var("STRIP_PROMPT_INJECTIONS").map(|v| v.trim().to_lowercase()).as_deref(),
+        Ok("false" | "off" | "0")
+    )
+}
+
+/// Whether Twitter commands only print what they would post (`TWITTER_DRY_RUN`, off
+/// unless set to true, on or 1).
+pub fn twitter_dry_run() -> bool {
+    matches!(
+        env::var("TWITTER_DRY_RUN").map(|v| v.trim().to_lowercase()).as_deref(),
+        Ok("true" | "on" | "1")
+    )
+}
+
+/// Characters of text kept from one crawled page.
+pub const DEFAULT_CRAWLER_MAX_CONTENT: usize = 20_000;
+
+/// Most text the crawler keeps from a page: `CRAWLER_MAX_CONTENT` characters, or 20,000.
+pub fn crawler_max_content() -> usize {
+    env::var("CRAWLER_MAX_CONTENT").ok()
+        .and_then(|n| n.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_CRAWLER_MAX_CONTENT)
+}
+
+/// Twitter's limit, in its weighted characters (see `tweet_length`).
+pub const DEFAULT_TWEET_MAX_LENGTH: usize = 280;
+
+/// Longest tweet the composer writes: `TWEET_MAX_LENGTH`, or 280.
+pub fn tweet_max_length() -> usize {
+    env::var("TWEET_MAX_LENGTH").ok()
+        .and_then(|l| l.trim().parse().ok())
+        .filter(|l| *l > 0)
+        .unwrap_or(DEFAULT_TWEET_MAX_LENGTH)
+}
+
+/// Normalized score (see `normalize_score`) a retrieved memory needs to reach a chat
+/// prompt: 0.65 is a cosine similarity of 0.3.
+pub const DEFAULT_CONTEXT_MIN_SCORE: f32 = 0.65;
+
+/// Relevance floor for prompt context: `CONTEXT_MIN_SCORE`, or 0.65.
+pub fn context_min_score() -> f32 {
+    env::var("CONTEXT_MIN_SCORE").ok()
+        .and_then(|s| s.trim().parse().ok())
+        .filter(|s: &f32| (0.0..=1.0).contains(s))
+        .unwrap_or(DEFAULT_CONTEXT_MIN_SCORE)
+}
+
+/// Recent messages the REPL's memory monitor keeps.
+pub const DEFAULT_CONTEXT_WINDOW: usize = 20;
+
+/// Messages the memory monitor keeps: `CONTEXT_WINDOW`, or 20.
+pub fn context_window() -> usize {
+    env::var("CONTEXT_WINDOW").ok()
+        .and_then(|w| w.trim().parse().ok())
+        .filter(|w| *w > 0)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+/// Whether chat prompts start with the learned facts relevant to the message
+/// (`ENABLE_LEARNED_CONTEXT`, on unless set to false, off or 0).
+pub fn learned_context_enabled() -> bool {
+    !matches!(
+        env::var("ENABLE_LEARNED_CONTEXT").map(|v| v.trim().to_lowercase()).as_deref(),
+        Ok("false" | "off" | "0" | "no")
+    )
+}
+
+/// `provider`'s models from `<PROVIDER>_MODELS` or the defaults, with `active` first, for
+/// providers whose model list can't be fetched.
+pub fn configured_models(provider: &str, active: &str) -> Vec<String> {
+    let mut models = ProviderConfig::from_env(provider).models;
+    models.retain(|model| model != active);
+    models.insert(0, active.to_string());
+    models
+}
+
+/// Shows only the last 4 characters of a secret.
+pub fn mask_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 4 {
+        return "****".to_string();
+    }
+    format!("****{}", chars[chars.len() - 4..].iter().collect::<String>())
+}
 
 #[derive(Debug, Clone)]
 pub struct ProviderConfig {
@@ -34,6 +349,10 @@ impl ProviderConfig {
                     "gemini-2.0-flash-exp".to_string(),
                     "gemini-1.5-flash-8b".to_string(),
                 ],
+                "deepseek" => vec![
+                    "deepseek-chat".to_string(),
+                    "deepseek-reasoner".to_string(),
+                ],
                 _ => vec![]
             });
 
@@ -47,11 +366,7 @@ impl ProviderConfig {
                 _ => String::new()
             });
 
-        // Get temperature from env or use default
-        let temperature = env::var(format!("{}_TEMPERATURE", prefix))
-            .ok()
-            .and_then(|t| t.parse().ok())
-            .unwrap_or(0.7);
+        let temperature = temperature(provider);
 
         Self {
             models,
@@ -59,4 +374,82 @@ impl ProviderConfig {
             temperature,
         }
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compiled_features_match_the_manifest() {
+        let manifest = include_str!("../Cargo.toml");
+        let features = manifest.split("[features]").nth(1).unwrap()
+            .lines()
+            .take_while(|line| !line.starts_with('['))
+            .filter_map(|line| line.split_once('=').map(|(name, _)| name.trim()))
+            .filter(|name| *name != "default");
+        let listed: Vec<&str> = COMPILED_FEATURES.iter().map(|(name, _)| *name).collect();
+        for feature in features {
+            assert!(listed.contains(&feature), "{} is missing from COMPILED_FEATURES", feature);
+        }
+    }
+
+    #[test]
+    fn test_parse_setting_validates() {
+        assert_eq!(parse_setting("temperature", "0.30").unwrap(), "0.3");
+        assert!(parse_setting("temperature", "3").is_err());
+        assert_eq!(parse_setting("context_window", "12").unwrap(), "12");
+        assert!(parse_setting("context_window", "-1").is_err());
+        assert_eq!(parse_setting("use_knowledge", "TRUE").unwrap(), "on");
+        assert!(parse_setting("use_knowledge", "maybe").is_err());
+        assert!(parse_setting("model", "gpt-4").unwrap_err().contains("temperature, context_window, use_knowledge"));
+    }
+
+    #[test]
+    fn test_model_embedding_dims() {
+        assert_eq!(model_embedding_dim("text-embedding-3-large"), Some(3072));
+        assert_eq!(model_embedding_dim("mistral-embed"), Some(1024));
+        assert_eq!(model_embedding_dim("BAAI/bge-small-en-v1.5"), Some(384));
+        assert_eq!(model_embedding_dim(DEFAULT_LOCAL_EMBEDDING_MODEL), Some(384));
+        assert_eq!(model_embedding_dim("nomic-embed-text-v1.5"), Some(768));
+        assert_eq!(model_embedding_dim("gpt-4o"), None);
+    }
+
+    #[test]
+    fn test_embedding_provider_is_read_apart_from_chat() {
+        let vars = |set: &'static [(&'static str, &'static str)]| {
+            move |name: &str| set.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string())
+        };
+
+        let config = EmbeddingProviderConfig::from_vars(vars(&[("OPENAI_API_KEY", "sk-open"), ("GEMINI_API_KEY", "gm-key")]));
+        assert_eq!(config.provider, "openai");
+        assert_eq!(config.api_key.as_deref(), Some("sk-open"));
+
+        let config = EmbeddingProviderConfig::from_vars(vars(&[
+            ("EMBEDDING_PROVIDER", " Mistral "),
+            ("EMBEDDING_MODEL", "mistral-embed"),
+            ("MISTRAL_API_KEY", "ms-key"),
+            ("OPENAI_API_KEY", "sk-open"),
+        ]));
+        assert_eq!(config.provider, "mistral");
+        assert_eq!(config.model_name().as_deref(), Some("mistral-embed"));
+        assert_eq!(config.api_key.as_deref(), Some("ms-key"));
+    }
+
+    #[tokio::test]
+    async fn test_embedding_size_is_asked_of_the_provider() {
+        for var in ["EMBEDDING_DIMENSION", "EMBEDDING_DIM", "OPENAI_API_KEY"] {
+            env::remove_var(var);
+        }
+        let mock = crate::providers::mock::MockProvider::with_response("unused").with_embedding_size(384);
+        let config = EmbeddingConfig::resolve(&EmbeddingGenerator::from_provider(Box::new(mock))).await;
+        assert_eq!(config.dimension, 384);
+        assert_eq!(config.model, "mock");
+    }
+
+    #[test]
+    fn test_mask_key_keeps_last_four() {
+        assert_eq!(mask_key("sk-abcdef123456"), "****3456");
+        assert_eq!(mask_key("abc"), "****");
+    }
+}
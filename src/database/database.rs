@@ -1,10 +1,85 @@
 use tokio_rusqlite::Connection;
 use std::path::Path;
-use log::{info, error};
+use log::{info, error, warn};
 use thiserror::Error;
 use std::sync::Arc;
-use super::vector_db::{VectorDB, VectorDBError};
+use super::sqlite_vector_store::SqliteVectorStore;
+use super::vector_db::{require_vector_db, VectorDB, VectorDBError};
+use super::vector_store::{VectorBackend, VectorStore};
 use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use crate::llm::memory::ConversationSession;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Columns added to tables after the first release, created on startup when missing.
+const ADDED_COLUMNS: &[(&str, &[(&str, &str)])] = &[
+    ("conversations", &[
+        ("session_id", "TEXT"),
+        ("provider", "TEXT"),
+        ("input_tokens", "INTEGER"),
+        ("response_tokens", "INTEGER"),
+    ]),
+    ("sessions", &[("personality", "TEXT")]),
+];
+
+/// One saved exchange between the user and the agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationRecord {
+    pub timestamp: String,
+    pub session_id: Option<String>,
+    pub personality: String,
+    pub provider: Option<String>,
+    pub user_input: String,
+    pub ai_response: String,
+    pub input_tokens: Option<i64>,
+    pub response_tokens: Option<i64>,
+}
+
+/// A keyword index entry matching a `search_keywords` query.
+#[derive(Debug, Clone)]
+pub struct KeywordHit {
+    pub point_id: String,
+    pub text: String,
+    pub source: String,
+    pub metadata: Option<String>,
+}
+
+/// A reusable prompt saved with `prompt save`, rendered by `run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptTemplate {
+    pub name: String,
+    /// The prompt text, with `{{input}}` where the `run` arguments go
+    pub template: String,
+    /// Provider to run it with instead of the active one
+    pub provider: Option<String>,
+    pub temperature: Option<f32>,
+}
+
+/// An FTS5 query matching any of the whitespace-separated terms in `query`, each quoted
+/// so punctuation in ids and error codes is matched literally instead of parsed as syntax.
+fn fts_query(query: &str) -> String {
+    query.split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+impl ConversationRecord {
+    /// A record timestamped now, in the same format SQLite's `CURRENT_TIMESTAMP` uses.
+    pub fn new(user_input: &str, ai_response: &str, personality: &str) -> Self {
+        Self {
+            timestamp: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            session_id: None,
+            personality: personality.to_string(),
+            provider: None,
+            user_input: user_input.to_string(),
+            ai_response: ai_response.to_string(),
+            input_tokens: None,
+            response_tokens: None,
+        }
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum DatabaseError {
@@ -13,13 +88,88 @@ pub enum DatabaseError {
     #[error("Database connection error: {0}")]
     Connection(String),
     #[error("Vector database error: {0}")]
-    VectorDB(String),
+    VectorDB(#[from] VectorDBError),
+    #[error("The archive is version {found}, newer than this build reads ({supported})")]
+    UnsupportedArchiveVersion { found: u32, supported: u32 },
+}
+
+fn not_initialized() -> DatabaseError {
+    VectorDBError::Connection("Vector database not initialized".to_string()).into()
+}
+
+/// Version of the [`Archive`] layout written by `export_all`.
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// A knowledge base entry, as archived.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeRecord {
+    pub key: String,
+    pub value: String,
+    pub timestamp: String,
+}
+
+/// A document insight, as archived.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InsightRecord {
+    pub document_path: String,
+    pub insight_text: String,
+    pub relevance: f64,
+    pub insight_type: String,
+    pub timestamp: String,
+}
+
+/// Everything `export_all` reads from SQLite, for backups and moving to another machine.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Archive {
+    pub version: u32,
+    #[serde(default)]
+    pub conversations: Vec<ConversationRecord>,
+    #[serde(default)]
+    pub knowledge: Vec<KnowledgeRecord>,
+    #[serde(default)]
+    pub document_insights: Vec<InsightRecord>,
+}
+
+/// Rows `import_all` added, and those it skipped because they were already there.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ImportCounts {
+    pub added: usize,
+    pub skipped: usize,
+}
+
+/// Cache timestamps at a fixed precision, so they compare correctly as text.
+fn cache_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// Identifies a row by its content, so importing the same archive twice adds nothing.
+fn content_hash(fields: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update(field.as_bytes());
+        hasher.update([0x1f]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn conversation_hash(record: &ConversationRecord) -> String {
+    content_hash(&[
+        &record.timestamp,
+        record.session_id.as_deref().unwrap_or(""),
+        &record.personality,
+        &record.user_input,
+        &record.ai_response,
+    ])
+}
+
+fn insight_hash(record: &InsightRecord) -> String {
+    content_hash(&[&record.document_path, &record.insight_text, &record.insight_type])
 }
 
 #[derive(Clone)]
 pub struct Database {
     conn: Arc<Connection>,
-    vector_db: Option<Arc<VectorDB>>,
+    vector_db: Option<Arc<dyn VectorStore>>,
 }
 
 impl Database {
@@ -36,15 +186,33 @@ impl Database {
         Ok(db)
     }
 
+    /// Attaches the vector store picked by `VECTOR_BACKEND`: Qdrant at `url`, or tables in
+    /// this database for `sqlite`. When Qdrant is unreachable the database is returned
+    /// without vector storage, unless `REQUIRE_VECTOR_DB` is set, which makes that an error.
     pub async fn with_vector_db(mut self, url: &str) -> Result<Self, DatabaseError> {
-        let vector_db = VectorDB::new(url)
-            .await
-            .map_err(|e| DatabaseError::VectorDB(e.to_string()))?;
-        self.vector_db = Some(Arc::new(vector_db));
+        if VectorBackend::from_env() == VectorBackend::Sqlite {
+            let store = SqliteVectorStore::new(self.conn.clone()).await?;
+            self.vector_db = Some(Arc::new(store));
+            return Ok(self);
+        }
+
+        match VectorDB::new(url).await {
+            Ok(vector_db) => self.vector_db = Some(Arc::new(vector_db)),
+            Err(e) if require_vector_db() => return Err(VectorDBError::Connection(e.to_string()).into()),
+            Err(e) => warn!("Running without vector storage: {}", e),
+        }
         Ok(self)
     }
 
-    pub async fn get_vector_db(&self) -> Option<Arc<VectorDB>> {
+    /// An in-memory database that keeps its vectors in its own tables.
+    #[cfg(test)]
+    pub async fn in_memory_with_vectors() -> Self {
+        let mut db = Self::new(":memory:").await.unwrap();
+        db.vector_db = Some(Arc::new(SqliteVectorStore::new(db.conn.clone()).await.unwrap()));
+        db
+    }
+
+    pub async fn get_vector_db(&self) -> Option<Arc<dyn VectorStore>> {
         self.vector_db.clone()
     }
 
@@ -60,10 +228,10 @@ impl Database {
                     info!("Collection {} already exists, skipping creation", name);
                     Ok(())
                 }
-                Err(e) => Err(DatabaseError::VectorDB(e.to_string())),
+                Err(e) => Err(e.into()),
             }
         } else {
-            Err(DatabaseError::VectorDB("Vector database not initialized".to_string()))
+            Err(not_initialized())
         }
     }
 
@@ -91,8 +259,57 @@ impl Database {
                     insight_text TEXT NOT NULL,
                     relevance REAL NOT NULL,
                     insight_type TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS sessions (
+                    id TEXT PRIMARY KEY,
+                    topic TEXT NOT NULL,
+                    start_time TEXT NOT NULL,
+                    last_active TEXT NOT NULL,
+                    summary TEXT NOT NULL DEFAULT '',
+                    personality TEXT
+                );
+                CREATE TABLE IF NOT EXISTS settings (
+                    key TEXT PRIMARY KEY,
+                    value TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS prompt_templates (
+                    name TEXT PRIMARY KEY,
+                    template TEXT NOT NULL,
+                    provider TEXT,
+                    temperature TEXT
+                );
+                CREATE TABLE IF NOT EXISTS completion_cache (
+                    key TEXT PRIMARY KEY,
+                    response TEXT NOT NULL,
+                    created_at TEXT NOT NULL
+                );"
+            )?;
+
+            for (table, columns) in ADDED_COLUMNS {
+                let existing = conn
+                    .prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?
+                    .query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                for (column, kind) in *columns {
+                    if !existing.iter().any(|name| name == column) {
+                        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, kind))?;
+                    }
+                }
+            }
+
+            // Keyword search is optional; everything else works on SQLite builds without FTS5
+            if let Err(e) = conn.execute_batch(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS keyword_index USING fts5(
+                    point_id UNINDEXED,
+                    collection UNINDEXED,
+                    source UNINDEXED,
+                    metadata UNINDEXED,
+                    text
                 );"
-            )
+            ) {
+                warn!("Keyword index unavailable, hybrid search will use vectors only: {}", e);
+            }
+            Ok(())
         })
         .await?;
 
@@ -118,6 +335,196 @@ impl Database {
         Ok(())
     }
 
+    /// Saves an exchange along with its session, provider and token counts, returning its row id.
+    pub async fn save_exchange(&self, record: ConversationRecord) -> Result<i64, DatabaseError> {
+        let values = [
+            Some(record.timestamp),
+            Some(record.user_input),
+            Some(record.ai_response),
+            Some(record.personality),
+            record.session_id,
+            record.provider,
+            record.input_tokens.map(|n| n.to_string()),
+            record.response_tokens.map(|n| n.to_string()),
+        ];
+        let id = self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO conversations
+                        (timestamp, user_input, ai_response, personality, session_id, provider, input_tokens, response_tokens)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    values,
+                )?;
+                Ok(conn.last_insert_rowid())
+            })
+            .await?;
+
+        Ok(id)
+    }
+
+    /// Swaps the reply of a saved exchange, e.g. after `retry`.
+    pub async fn replace_response(
+        &self,
+        id: i64,
+        ai_response: String,
+        provider: Option<String>,
+        response_tokens: Option<i64>,
+    ) -> Result<(), DatabaseError> {
+        let values = [
+            Some(ai_response),
+            provider,
+            response_tokens.map(|n| n.to_string()),
+            Some(id.to_string()),
+        ];
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "UPDATE conversations SET ai_response = ?1, provider = ?2, response_tokens = ?3 WHERE id = ?4",
+                    values,
+                )
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Exchanges oldest first, from one session or, with `None`, the most recent `limit` overall.
+    pub async fn get_conversations(&self, session_id: Option<String>, limit: i64) -> Result<Vec<ConversationRecord>, DatabaseError> {
+        let mut records = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT timestamp, session_id, personality, provider, user_input, ai_response, input_tokens, response_tokens
+                     FROM conversations
+                     WHERE ?1 IS NULL OR session_id = ?1
+                     ORDER BY timestamp DESC, id DESC
+                     LIMIT ?2"
+                )?;
+
+                let rows = stmt.query_map([session_id, Some(limit.to_string())], |row| {
+                    Ok(ConversationRecord {
+                        timestamp: row.get(0)?,
+                        session_id: row.get(1)?,
+                        personality: row.get(2)?,
+                        provider: row.get(3)?,
+                        user_input: row.get(4)?,
+                        ai_response: row.get(5)?,
+                        input_tokens: row.get(6)?,
+                        response_tokens: row.get(7)?,
+                    })
+                })?;
+
+                rows.collect::<Result<Vec<_>, _>>()
+            })
+            .await?;
+
+        records.reverse();
+        Ok(records)
+    }
+
+    /// Inserts the session, or updates its topic, activity time, summary and personality.
+    pub async fn save_session(&self, session: &ConversationSession) -> Result<(), DatabaseError> {
+        let values = [
+            Some(session.id.clone()),
+            Some(session.topic.clone()),
+            Some(session.start_time.to_rfc3339()),
+            Some(session.last_active.to_rfc3339()),
+            Some(session.summary.clone()),
+            session.personality.clone(),
+        ];
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO sessions (id, topic, start_time, last_active, summary, personality)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT(id) DO UPDATE SET
+                        topic = excluded.topic,
+                        last_active = excluded.last_active,
+                        summary = excluded.summary,
+                        personality = COALESCE(excluded.personality, personality)",
+                    values,
+                )
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Marks a saved session as active at `time`.
+    pub async fn touch_session(&self, id: &str, time: DateTime<Utc>) -> Result<(), DatabaseError> {
+        let values = [id.to_string(), time.to_rfc3339()];
+        self.conn
+            .call(move |conn| conn.execute("UPDATE sessions SET last_active = ?2 WHERE id = ?1", values))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Most recently active sessions first.
+    pub async fn list_sessions(&self, limit: i64) -> Result<Vec<ConversationSession>, DatabaseError> {
+        let rows = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, topic, start_time, last_active, summary, personality
+                     FROM sessions
+                     ORDER BY last_active DESC
+                     LIMIT ?"
+                )?;
+
+                let rows = stmt.query_map([limit], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                    ))
+                })?;
+                let mut sessions: Vec<SessionRow> = Vec::new();
+                for row in rows {
+                    sessions.push(row?);
+                }
+
+                Ok(sessions)
+            })
+            .await?;
+
+        Ok(rows.into_iter().map(session_from_row).collect())
+    }
+
+    /// Finds a session by id or unique id prefix.
+    pub async fn find_session(&self, id_prefix: String) -> Result<Vec<ConversationSession>, DatabaseError> {
+        let rows = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, topic, start_time, last_active, summary, personality
+                     FROM sessions
+                     WHERE id = ?1 OR id LIKE ?1 || '%'
+                     ORDER BY id = ?1 DESC, last_active DESC"
+                )?;
+
+                let rows = stmt.query_map([id_prefix], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, Option<String>>(5)?,
+                    ))
+                })?;
+                let mut sessions: Vec<SessionRow> = Vec::new();
+                for row in rows {
+                    sessions.push(row?);
+                }
+
+                Ok(sessions)
+            })
+            .await?;
+
+        Ok(rows.into_iter().map(session_from_row).collect())
+    }
+
     pub async fn save_knowledge(
         &self,
         key: String,
@@ -135,6 +542,185 @@ impl Database {
         Ok(())
     }
 
+    /// Adds text to the keyword index under the id of its vector point.
+    pub async fn index_keywords(
+        &self,
+        collection: String,
+        point_id: String,
+        text: String,
+        source: String,
+        metadata: Option<String>,
+    ) -> Result<(), DatabaseError> {
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO keyword_index (point_id, collection, source, metadata, text)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    [Some(point_id), Some(collection), Some(source), metadata, Some(text)],
+                )
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Texts in `collection` containing any term of `query`, best BM25 match first.
+    pub async fn search_keywords(&self, collection: String, query: &str, limit: i64) -> Result<Vec<KeywordHit>, DatabaseError> {
+        let query = fts_query(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let hits = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT point_id, text, source, metadata
+                     FROM keyword_index
+                     WHERE keyword_index MATCH ?1 AND collection = ?2
+                     ORDER BY bm25(keyword_index)
+                     LIMIT ?3"
+                )?;
+
+                let rows = stmt.query_map([query, collection, limit.to_string()], |row| {
+                    Ok(KeywordHit {
+                        point_id: row.get(0)?,
+                        text: row.get(1)?,
+                        source: row.get(2)?,
+                        metadata: row.get(3)?,
+                    })
+                })?;
+
+                rows.collect::<Result<Vec<_>, _>>()
+            })
+            .await?;
+
+        Ok(hits)
+    }
+
+    pub async fn save_setting(&self, key: String, value: String) -> Result<(), DatabaseError> {
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT INTO settings (key, value) VALUES (?1, ?2)
+                     ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                    [&key, &value],
+                )
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// All stored settings, sorted by key.
+    pub async fn get_settings(&self) -> Result<Vec<(String, String)>, DatabaseError> {
+        let settings = self.conn
+            .call(|conn| {
+                let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+                let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+                rows.collect::<Result<Vec<_>, _>>()
+            })
+            .await?;
+
+        Ok(settings)
+    }
+
+    /// Saves `template`, replacing any with the same name.
+    pub async fn save_prompt_template(&self, template: PromptTemplate) -> Result<(), DatabaseError> {
+        let values = [
+            Some(template.name),
+            Some(template.template),
+            template.provider,
+            template.temperature.map(|t| t.to_string()),
+        ];
+        self.conn
+            .call(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO prompt_templates (name, template, provider, temperature)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    values,
+                )
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// The reply cached under `key`, if it was stored within `max_age`.
+    pub async fn get_cached_completion(&self, key: String, max_age: chrono::Duration) -> Result<Option<String>, DatabaseError> {
+        let values = [key, cache_time(Utc::now() - max_age)];
+        let response = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare("SELECT response FROM completion_cache WHERE key = ?1 AND created_at >= ?2")?;
+                let mut rows = stmt.query(values)?;
+                match rows.next()? {
+                    Some(row) => Ok(Some(row.get::<_, String>(0)?)),
+                    None => Ok(None),
+                }
+            })
+            .await?;
+
+        Ok(response)
+    }
+
+    /// Caches `response` under `key`, dropping entries older than `max_age` on the way.
+    pub async fn cache_completion(&self, key: String, response: String, max_age: chrono::Duration) -> Result<(), DatabaseError> {
+        let now = Utc::now();
+        let values = [key, response, cache_time(now)];
+        let cutoff = cache_time(now - max_age);
+        self.conn
+            .call(move |conn| {
+                conn.execute("DELETE FROM completion_cache WHERE created_at < ?1", [cutoff])?;
+                conn.execute(
+                    "INSERT OR REPLACE INTO completion_cache (key, response, created_at) VALUES (?1, ?2, ?3)",
+                    values,
+                )
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_prompt_template(&self, name: String) -> Result<Option<PromptTemplate>, DatabaseError> {
+        let mut templates = self.query_prompt_templates(Some(name)).await?;
+        Ok(templates.pop())
+    }
+
+    /// All saved templates, sorted by name.
+    pub async fn list_prompt_templates(&self) -> Result<Vec<PromptTemplate>, DatabaseError> {
+        self.query_prompt_templates(None).await
+    }
+
+    async fn query_prompt_templates(&self, name: Option<String>) -> Result<Vec<PromptTemplate>, DatabaseError> {
+        let templates = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT name, template, provider, temperature FROM prompt_templates
+                     WHERE ?1 IS NULL OR name = ?1
+                     ORDER BY name"
+                )?;
+                let rows = stmt.query_map([name], |row| {
+                    Ok(PromptTemplate {
+                        name: row.get(0)?,
+                        template: row.get(1)?,
+                        provider: row.get(2)?,
+                        temperature: row.get::<_, Option<String>>(3)?.and_then(|t| t.parse().ok()),
+                    })
+                })?;
+                rows.collect::<Result<Vec<_>, _>>()
+            })
+            .await?;
+
+        Ok(templates)
+    }
+
+    pub async fn delete_prompt_template(&self, name: String) -> Result<bool, DatabaseError> {
+        let deleted = self.conn
+            .call(move |conn| conn.execute("DELETE FROM prompt_templates WHERE name = ?1", [&name]))
+            .await?;
+
+        Ok(deleted > 0)
+    }
+
     pub async fn get_recent_conversations(&self, limit: i64) -> Result<Vec<(String, String, String, String)>, DatabaseError> {
         let result = self.conn
             .call(move |conn| {
@@ -183,6 +769,52 @@ impl Database {
         Ok(result)
     }
 
+    pub async fn list_knowledge(&self, prefix: Option<String>) -> Result<Vec<(String, String)>, DatabaseError> {
+        let result = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT key, value 
+                     FROM knowledge_base 
+                     WHERE key LIKE ?1 ESCAPE '\\' 
+                     ORDER BY key"
+                )?;
+
+                let pattern = match prefix {
+                    Some(prefix) => format!(
+                        "{}%",
+                        prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+                    ),
+                    None => "%".to_string(),
+                };
+                let rows = stmt.query_map([pattern], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                    ))
+                })?;
+
+                let mut entries = Vec::new();
+                for row in rows {
+                    entries.push(row?);
+                }
+                
+                Ok(entries)
+            })
+            .await?;
+            
+        Ok(result)
+    }
+
+    pub async fn delete_knowledge(&self, key: String) -> Result<bool, DatabaseError> {
+        let deleted = self.conn
+            .call(move |conn| {
+                conn.execute("DELETE FROM knowledge_base WHERE key = ?1", [&key])
+            })
+            .await?;
+        
+        Ok(deleted > 0)
+    }
+
     pub async fn save_document_insight(
         &self,
         document_path: String,
@@ -302,6 +934,49 @@ impl Database {
         Ok(result)
     }
 
+    /// Each analyzed document with its insight count and when it was last processed,
+    /// most recent first.
+    pub async fn list_documents(&self) -> Result<Vec<(String, usize, String)>, DatabaseError> {
+        let result = self.conn
+            .call(|conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT document_path, COUNT(*), MAX(timestamp)
+                     FROM document_insights
+                     GROUP BY document_path
+                     ORDER BY MAX(timestamp) DESC, document_path"
+                )?;
+
+                let rows = stmt.query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)? as usize,
+                        row.get::<_, String>(2)?,
+                    ))
+                })?;
+
+                let mut documents = Vec::new();
+                for row in rows {
+                    documents.push(row?);
+                }
+
+                Ok(documents)
+            })
+            .await?;
+
+        Ok(result)
+    }
+
+    /// Removes a document's insights, returning how many rows were deleted.
+    pub async fn delete_document_insights(&self, document_path: String) -> Result<usize, DatabaseError> {
+        let deleted = self.conn
+            .call(move |conn| {
+                conn.execute("DELETE FROM document_insights WHERE document_path = ?1", [&document_path])
+            })
+            .await?;
+
+        Ok(deleted)
+    }
+
     pub async fn store_vector(
         &self,
         collection: &str,
@@ -309,11 +984,11 @@ impl Database {
         payload: HashMap<String, serde_json::Value>,
     ) -> Result<String, DatabaseError> {
         let vector_db = self.vector_db.as_ref()
-            .ok_or_else(|| DatabaseError::VectorDB("Vector database not initialized".to_string()))?;
+            .ok_or_else(not_initialized)?;
         
         vector_db.store_vector(collection, vector, payload)
             .await
-            .map_err(|e| DatabaseError::VectorDB(e.to_string()))
+            .map_err(DatabaseError::from)
     }
 
     pub async fn search_vectors(
@@ -323,11 +998,11 @@ impl Database {
         limit: u64,
     ) -> Result<Vec<(String, f32, HashMap<String, serde_json::Value>)>, DatabaseError> {
         let vector_db = self.vector_db.as_ref()
-            .ok_or_else(|| DatabaseError::VectorDB("Vector database not initialized".to_string()))?;
+            .ok_or_else(not_initialized)?;
         
         vector_db.search_vectors(collection, query_vector, limit)
             .await
-            .map_err(|e| DatabaseError::VectorDB(e.to_string()))
+            .map_err(DatabaseError::from)
     }
 
     pub async fn delete_vectors(
@@ -336,10 +1011,376 @@ impl Database {
         ids: Vec<String>,
     ) -> Result<(), DatabaseError> {
         let vector_db = self.vector_db.as_ref()
-            .ok_or_else(|| DatabaseError::VectorDB("Vector database not initialized".to_string()))?;
+            .ok_or_else(not_initialized)?;
         
         vector_db.delete_vectors(collection, ids)
             .await
-            .map_err(|e| DatabaseError::VectorDB(e.to_string()))
+            .map_err(DatabaseError::from)
+    }
+}
+
+impl Database {
+    /// Every conversation, knowledge entry and document insight, oldest first.
+    pub async fn export_all(&self) -> Result<Archive, DatabaseError> {
+        let archive = self.conn
+            .call(|conn| {
+                let conversations = conn
+                    .prepare(
+                        "SELECT timestamp, session_id, personality, provider, user_input, ai_response, input_tokens, response_tokens
+                         FROM conversations ORDER BY timestamp, id"
+                    )?
+                    .query_map([], |row| {
+                        Ok(ConversationRecord {
+                            timestamp: row.get(0)?,
+                            session_id: row.get(1)?,
+                            personality: row.get(2)?,
+                            provider: row.get(3)?,
+                            user_input: row.get(4)?,
+                            ai_response: row.get(5)?,
+                            input_tokens: row.get(6)?,
+                            response_tokens: row.get(7)?,
+                        })
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                let knowledge = conn
+                    .prepare("SELECT key, value, timestamp FROM knowledge_base ORDER BY timestamp, id")?
+                    .query_map([], |row| {
+                        Ok(KnowledgeRecord { key: row.get(0)?, value: row.get(1)?, timestamp: row.get(2)? })
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                let document_insights = conn
+                    .prepare(
+                        "SELECT document_path, insight_text, relevance, insight_type, timestamp
+                         FROM document_insights ORDER BY timestamp, id"
+                    )?
+                    .query_map([], |row| {
+                        Ok(InsightRecord {
+                            document_path: row.get(0)?,
+                            insight_text: row.get(1)?,
+                            relevance: row.get(2)?,
+                            insight_type: row.get(3)?,
+                            timestamp: row.get(4)?,
+                        })
+                    })?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Archive { version: ARCHIVE_VERSION, conversations, knowledge, document_insights })
+            })
+            .await?;
+
+        Ok(archive)
+    }
+
+    /// Restores an `export_all` archive in one transaction. Conversations and insights
+    /// already here (by content hash) are skipped, as are knowledge keys that exist, so
+    /// importing the same archive again changes nothing.
+    pub async fn import_all(&self, archive: Archive) -> Result<ImportCounts, DatabaseError> {
+        if archive.version > ARCHIVE_VERSION {
+            return Err(DatabaseError::UnsupportedArchiveVersion { found: archive.version, supported: ARCHIVE_VERSION });
+        }
+        let counts = self.conn
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                let mut counts = ImportCounts::default();
+
+                let mut existing = std::collections::HashSet::new();
+                {
+                    let mut stmt = tx.prepare(
+                        "SELECT timestamp, session_id, personality, user_input, ai_response FROM conversations"
+                    )?;
+                    let rows = stmt.query_map([], |row| {
+                        Ok(ConversationRecord {
+                            timestamp: row.get(0)?,
+                            session_id: row.get(1)?,
+                            personality: row.get(2)?,
+                            user_input: row.get(3)?,
+                            ai_response: row.get(4)?,
+                            provider: None,
+                            input_tokens: None,
+                            response_tokens: None,
+                        })
+                    })?;
+                    for row in rows {
+                        existing.insert(conversation_hash(&row?));
+                    }
+                }
+                for record in &archive.conversations {
+                    if !existing.insert(conversation_hash(record)) {
+                        counts.skipped += 1;
+                        continue;
+                    }
+                    tx.execute(
+                        "INSERT INTO conversations
+                            (timestamp, user_input, ai_response, personality, session_id, provider, input_tokens, response_tokens)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                        [
+                            Some(record.timestamp.clone()),
+                            Some(record.user_input.clone()),
+                            Some(record.ai_response.clone()),
+                            Some(record.personality.clone()),
+                            record.session_id.clone(),
+                            record.provider.clone(),
+                            record.input_tokens.map(|n| n.to_string()),
+                            record.response_tokens.map(|n| n.to_string()),
+                        ],
+                    )?;
+                    counts.added += 1;
+                }
+
+                for record in &archive.knowledge {
+                    let added = tx.execute(
+                        "INSERT OR IGNORE INTO knowledge_base (key, value, timestamp) VALUES (?1, ?2, ?3)",
+                        [&record.key, &record.value, &record.timestamp],
+                    )?;
+                    if added > 0 { counts.added += 1 } else { counts.skipped += 1 }
+                }
+
+                let mut existing = std::collections::HashSet::new();
+                {
+                    let mut stmt = tx.prepare("SELECT document_path, insight_text, insight_type FROM document_insights")?;
+                    let rows = stmt.query_map([], |row| {
+                        Ok(content_hash(&[&row.get::<_, String>(0)?, &row.get::<_, String>(1)?, &row.get::<_, String>(2)?]))
+                    })?;
+                    for row in rows {
+                        existing.insert(row?);
+                    }
+                }
+                for record in &archive.document_insights {
+                    if !existing.insert(insight_hash(record)) {
+                        counts.skipped += 1;
+                        continue;
+                    }
+                    tx.execute(
+                        "INSERT INTO document_insights (document_path, insight_text, relevance, insight_type, timestamp)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        [
+                            &record.document_path,
+                            &record.insight_text,
+                            &record.relevance.to_string(),
+                            &record.insight_type,
+                            &record.timestamp,
+                        ],
+                    )?;
+                    counts.added += 1;
+                }
+
+                tx.commit()?;
+                Ok(counts)
+            })
+            .await?;
+
+        Ok(counts)
+    }
+}
+
+type SessionRow = (String, String, String, String, String, Option<String>);
+
+fn session_from_row((id, topic, start_time, last_active, summary, personality): SessionRow) -> ConversationSession {
+    let parse_time = |value: &str| {
+        DateTime::parse_from_rfc3339(value)
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now())
+    };
+
+    ConversationSession {
+        id,
+        start_time: parse_time(&start_time),
+        topic,
+        summary,
+        last_active: parse_time(&last_active),
+        personality,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, topic: &str, minutes_ago: i64) -> ConversationSession {
+        let time = Utc::now() - chrono::Duration::minutes(minutes_ago);
+        ConversationSession {
+            id: id.to_string(),
+            start_time: time,
+            topic: topic.to_string(),
+            summary: String::new(),
+            last_active: time,
+            personality: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sessions_round_trip() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.save_session(&session("aaaa-1", "rust", 10)).await.unwrap();
+        db.save_session(&session("bbbb-2", "cooking", 5)).await.unwrap();
+
+        let mut updated = session("aaaa-1", "rust", 0);
+        updated.summary = "Talked about lifetimes".to_string();
+        db.save_session(&updated).await.unwrap();
+
+        let sessions = db.list_sessions(10).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].id, "aaaa-1");
+        assert_eq!(sessions[0].summary, "Talked about lifetimes");
+
+        let found = db.find_session("bbbb".to_string()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].topic, "cooking");
+        assert!(db.find_session("zzzz".to_string()).await.unwrap().is_empty());
+
+        // A save without a personality keeps the recorded one
+        let mut named = session("bbbb-2", "cooking", 0);
+        named.personality = Some("chef".to_string());
+        db.save_session(&named).await.unwrap();
+        db.save_session(&session("bbbb-2", "cooking", 0)).await.unwrap();
+        let later = Utc::now() + chrono::Duration::minutes(1);
+        db.touch_session("bbbb-2", later).await.unwrap();
+        let sessions = db.list_sessions(10).await.unwrap();
+        assert_eq!((sessions[0].id.as_str(), sessions[0].personality.as_deref()), ("bbbb-2", Some("chef")));
+        assert_eq!(sessions[0].last_active.timestamp(), later.timestamp());
+    }
+
+    #[tokio::test]
+    async fn test_archive_round_trips_into_a_fresh_database() {
+        let db = Database::new(":memory:").await.unwrap();
+        let mut record = ConversationRecord::new("What is Rust?", "A systems language.", "helpful");
+        record.session_id = Some("s1".to_string());
+        record.provider = Some("OpenAI".to_string());
+        record.input_tokens = Some(3);
+        db.save_exchange(record).await.unwrap();
+        db.save_exchange(ConversationRecord::new("hi", "hello", "funny")).await.unwrap();
+        db.save_knowledge("deploy".to_string(), "cargo build --release".to_string()).await.unwrap();
+        db.save_document_insight("notes.pdf".to_string(), "Ownership prevents data races".to_string(), 0.75, "key_point".to_string()).await.unwrap();
+
+        let archive = db.export_all().await.unwrap();
+        assert_eq!((archive.conversations.len(), archive.knowledge.len(), archive.document_insights.len()), (2, 1, 1));
+        let json = serde_json::to_string(&archive).unwrap();
+
+        let fresh = Database::new(":memory:").await.unwrap();
+        let counts = fresh.import_all(serde_json::from_str(&json).unwrap()).await.unwrap();
+        assert_eq!(counts, ImportCounts { added: 4, skipped: 0 });
+        assert_eq!(fresh.export_all().await.unwrap(), archive);
+
+        // Importing again, or into the original, adds nothing
+        assert_eq!(fresh.import_all(archive.clone()).await.unwrap(), ImportCounts { added: 0, skipped: 4 });
+        assert_eq!(db.import_all(archive.clone()).await.unwrap(), ImportCounts { added: 0, skipped: 4 });
+        assert_eq!(fresh.get_conversations(None, 10).await.unwrap().len(), 2);
+
+        let newer = Archive { version: ARCHIVE_VERSION + 1, ..Archive::default() };
+        assert!(matches!(
+            fresh.import_all(newer).await,
+            Err(DatabaseError::UnsupportedArchiveVersion { found, supported: ARCHIVE_VERSION }) if found == ARCHIVE_VERSION + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_keyword_search_matches_exact_tokens() {
+        let db = Database::new(":memory:").await.unwrap();
+        for (id, text) in [
+            ("p1", "The build failed with a borrow checker complaint"),
+            ("p2", "error[E0502]: cannot borrow `v` as mutable because it is also borrowed"),
+            ("p3", "Notes about gardening and tomatoes"),
+        ] {
+            db.index_keywords("docs".to_string(), id.to_string(), text.to_string(), "test".to_string(), None).await.unwrap();
+        }
+        db.index_keywords("other".to_string(), "p4".to_string(), "E0502 elsewhere".to_string(), "test".to_string(), None).await.unwrap();
+
+        let hits = db.search_keywords("docs".to_string(), "E0502", 10).await.unwrap();
+        assert_eq!(hits.iter().map(|h| h.point_id.as_str()).collect::<Vec<_>>(), vec!["p2"]);
+        assert!(db.search_keywords("docs".to_string(), "  ", 10).await.unwrap().is_empty());
+        assert_eq!(fts_query("say \"hi\" ERR-42"), "\"say\" OR \"\"\"hi\"\"\" OR \"ERR-42\"");
+    }
+
+    #[tokio::test]
+    async fn test_prompt_templates_round_trip() {
+        let db = Database::new(":memory:").await.unwrap();
+        let review = PromptTemplate {
+            name: "review".to_string(),
+            template: "Review for security issues:\n{{input}}".to_string(),
+            provider: Some("openai".to_string()),
+            temperature: Some(0.2),
+        };
+        let summary = PromptTemplate {
+            name: "summary".to_string(),
+            template: "Summarize: {{input}}".to_string(),
+            provider: None,
+            temperature: None,
+        };
+        db.save_prompt_template(summary.clone()).await.unwrap();
+        db.save_prompt_template(review.clone()).await.unwrap();
+
+        assert_eq!(db.get_prompt_template("review".to_string()).await.unwrap(), Some(review.clone()));
+        assert_eq!(db.list_prompt_templates().await.unwrap(), vec![review, summary]);
+        assert!(db.delete_prompt_template("review".to_string()).await.unwrap());
+        assert_eq!(db.get_prompt_template("review".to_string()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_settings_upsert() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.save_setting("temperature".to_string(), "0.7".to_string()).await.unwrap();
+        db.save_setting("context_window".to_string(), "4".to_string()).await.unwrap();
+        db.save_setting("temperature".to_string(), "0.2".to_string()).await.unwrap();
+
+        assert_eq!(db.get_settings().await.unwrap(), vec![
+            ("context_window".to_string(), "4".to_string()),
+            ("temperature".to_string(), "0.2".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_exchanges_filter_by_session() {
+        let db = Database::new(":memory:").await.unwrap();
+        db.save_conversation("old".to_string(), "reply".to_string(), "helpful".to_string()).await.unwrap();
+
+        for (input, tokens) in [("first", 3), ("second", 5)] {
+            let mut record = ConversationRecord::new(input, "ok", "helpful");
+            record.session_id = Some("s1".to_string());
+            record.provider = Some("OpenAI".to_string());
+            record.input_tokens = Some(tokens);
+            record.response_tokens = Some(1);
+            db.save_exchange(record).await.unwrap();
+        }
+
+        let session = db.get_conversations(Some("s1".to_string()), 100).await.unwrap();
+        assert_eq!(session.iter().map(|r| r.user_input.as_str()).collect::<Vec<_>>(), vec!["first", "second"]);
+        assert_eq!(session[1].input_tokens, Some(5));
+        assert_eq!(session[0].provider.as_deref(), Some("OpenAI"));
+
+        let all = db.get_conversations(None, 100).await.unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].provider, None);
+    }
+
+    #[tokio::test]
+    async fn test_replace_response_keeps_one_row() {
+        let db = Database::new(":memory:").await.unwrap();
+        let id = db.save_exchange(ConversationRecord::new("tell a joke", "a weak joke", "funny")).await.unwrap();
+        db.save_exchange(ConversationRecord::new("another", "reply", "funny")).await.unwrap();
+
+        db.replace_response(id, "a better joke".to_string(), Some("Mistral".to_string()), Some(3)).await.unwrap();
+
+        let all = db.get_conversations(None, 10).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].ai_response, "a better joke");
+        assert_eq!(all[0].provider.as_deref(), Some("Mistral"));
+        assert_eq!(all[0].response_tokens, Some(3));
+        assert_eq!(all[1].ai_response, "reply");
+    }
+
+    #[tokio::test]
+    async fn test_list_and_delete_documents() {
+        let db = Database::new(":memory:").await.unwrap();
+        for (path, text) in [("report.pdf", "Revenue grew"), ("report.pdf", "Costs fell"), ("notes.txt", "Buy milk")] {
+            db.save_document_insight(path.to_string(), text.to_string(), 0.8, "analysis".to_string()).await.unwrap();
+        }
+
+        let mut documents = db.list_documents().await.unwrap();
+        documents.sort();
+        assert_eq!(documents.iter().map(|(p, n, _)| (p.as_str(), *n)).collect::<Vec<_>>(), vec![("notes.txt", 1), ("report.pdf", 2)]);
+        assert!(!documents[0].2.is_empty());
+
+        assert_eq!(db.delete_document_insights("report.pdf".to_string()).await.unwrap(), 2);
+        assert_eq!(db.delete_document_insights("report.pdf".to_string()).await.unwrap(), 0);
+        assert_eq!(db.list_documents().await.unwrap().len(), 1);
     }
 }
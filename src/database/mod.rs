@@ -1,7 +1,12 @@
 pub mod vector_db;
+pub mod vector_store;
+pub mod sqlite_vector_store;
 pub mod database;
 pub mod qdrant_config;
 
 pub use database::Database;
 pub use database::DatabaseError;
+pub use database::{Archive, ConversationRecord, ImportCounts, InsightRecord, KeywordHit, KnowledgeRecord, PromptTemplate};
 pub use vector_db::{VectorDB, VectorDBError};
+pub use vector_store::{VectorBackend, VectorStore};
+pub use sqlite_vector_store::SqliteVectorStore;
@@ -2,6 +2,23 @@ use qdrant_client::{Qdrant, config::QdrantConfig};
 use std::time::Duration;
 
 pub async fn create_qdrant_client(url: &str) -> Result<Qdrant, Box<dyn std::error::Error>> {
+    let client = build_qdrant_client(url)?;
+
+    // Test the connection
+    match client.list_collections().await {
+        Ok(_) => {
+            log::info!("Successfully connected to Qdrant");
+            Ok(client)
+        }
+        Err(e) => {
+            log::error!("Connection test failed: {}", e);
+            Err(format!("Failed to connect to Qdrant: {}", e).into())
+        }
+    }
+}
+
+/// A client for `url` that connects on first use, so it can be built while Qdrant is down.
+pub fn build_qdrant_client(url: &str) -> Result<Qdrant, Box<dyn std::error::Error>> {
     // Clean the URL
     let clean_url = if url.contains("://") {
         url.split("://").nth(1).unwrap_or(url).to_string()
@@ -24,18 +41,6 @@ pub async fn create_qdrant_client(url: &str) -> Result<Qdrant, Box<dyn std::erro
     config.check_compatibility = false;
     config.timeout = Duration::from_secs(30);
     config.connect_timeout = Duration::from_secs(10);
-    
-    let client = Qdrant::new(config)?;
-    
-    // Test the connection
-    match client.list_collections().await {
-        Ok(_) => {
-            log::info!("Successfully connected to Qdrant");
-            Ok(client)
-        }
-        Err(e) => {
-            log::error!("Connection test failed: {}", e);
-            Err(format!("Failed to connect to Qdrant: {}", e).into())
-        }
-    }
-} 
\ No newline at end of file
+
+    Ok(Qdrant::new(config)?)
+}
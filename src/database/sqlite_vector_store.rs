@@ -0,0 +1,369 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio_rusqlite::Connection;
+use uuid::Uuid;
+use super::vector_db::{normalize_score, VectorDBError};
+use super::vector_store::{ScoredPoint, StoredPoint, VectorPoint, VectorStore};
+
+/// A [`VectorStore`] in SQLite tables, searched by comparing the query with every vector
+/// in the collection. Needs no server, and stays quick up to a few thousand vectors.
+#[derive(Clone)]
+pub struct SqliteVectorStore {
+    conn: Arc<Connection>,
+}
+
+impl SqliteVectorStore {
+    /// Uses `conn`, creating the vector tables if needed.
+    pub async fn new(conn: Arc<Connection>) -> Result<Self, VectorDBError> {
+        conn.call(|conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS vector_collections (
+                    name TEXT PRIMARY KEY,
+                    vector_size INTEGER NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS vector_points (
+                    collection TEXT NOT NULL,
+                    id TEXT NOT NULL,
+                    vector TEXT NOT NULL,
+                    payload TEXT NOT NULL,
+                    PRIMARY KEY (collection, id)
+                );"
+            )
+        })
+        .await
+        .map_err(operation)?;
+
+        Ok(Self { conn })
+    }
+
+    /// A store in a fresh in-memory database, for tests.
+    #[cfg(test)]
+    pub async fn in_memory() -> Self {
+        let conn = Connection::open_in_memory().await.unwrap();
+        Self::new(Arc::new(conn)).await.unwrap()
+    }
+
+    /// The dimension `collection` was created with, if it exists.
+    async fn vector_size(&self, collection: &str) -> Result<Option<usize>, VectorDBError> {
+        let collection = collection.to_string();
+        let sizes = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare("SELECT vector_size FROM vector_collections WHERE name = ?")?;
+                let sizes = stmt.query_map([&collection], |row| row.get::<_, i64>(0))?
+                    .collect::<Result<Vec<_>, _>>();
+                sizes
+            })
+            .await
+            .map_err(operation)?;
+        Ok(sizes.first().map(|size| *size as usize))
+    }
+
+    /// Every point in `collection` as (id, vector, payload) JSON.
+    async fn points(&self, collection: &str) -> Result<Vec<(String, String, String)>, VectorDBError> {
+        let collection = collection.to_string();
+        self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare("SELECT id, vector, payload FROM vector_points WHERE collection = ? ORDER BY rowid")?;
+                let points = stmt.query_map([&collection], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                    .collect::<Result<Vec<_>, _>>();
+                points
+            })
+            .await
+            .map_err(operation)
+    }
+
+    /// The `limit` points closest to `query_vector`, only those whose `field` equals `value`
+    /// if `field_match` is given.
+    async fn search_where(
+        &self,
+        collection: &str,
+        query_vector: &[f32],
+        limit: u64,
+        field_match: Option<(&str, &str)>,
+    ) -> Result<Vec<ScoredPoint>, VectorDBError> {
+        let mut scored: Vec<ScoredPoint> = self.points(collection).await?
+            .into_iter()
+            .filter_map(|(id, vector, payload)| {
+                let payload = parse_payload(&payload);
+                if let Some((field, value)) = field_match {
+                    if payload.get(field).and_then(|v| v.as_str()) != Some(value) {
+                        return None;
+                    }
+                }
+                let vector: Vec<f32> = serde_json::from_str(&vector).ok()?;
+                Some((id, normalize_score(cosine(query_vector, &vector)), payload))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit as usize);
+        Ok(scored)
+    }
+}
+
+fn operation(e: impl std::fmt::Display) -> VectorDBError {
+    VectorDBError::Operation(e.to_string())
+}
+
+fn parse_payload(payload: &str) -> HashMap<String, serde_json::Value> {
+    serde_json::from_str(payload).unwrap_or_default()
+}
+
+/// Cosine similarity, 0.0 when either vector is all zeros.
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
+#[async_trait]
+impl VectorStore for SqliteVectorStore {
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn create_collection(&self, name: &str, vector_size: u64) -> Result<(), VectorDBError> {
+        let params = [name.to_string(), vector_size.to_string()];
+        self.conn
+            .call(move |conn| conn.execute("INSERT OR IGNORE INTO vector_collections (name, vector_size) VALUES (?1, ?2)", params))
+            .await
+            .map_err(operation)?;
+        Ok(())
+    }
+
+    async fn collection_dimension(&self, name: &str) -> Result<Option<u64>, VectorDBError> {
+        Ok(self.vector_size(name).await?.map(|size| size as u64))
+    }
+
+    async fn store_vector(
+        &self,
+        collection: &str,
+        vector: Vec<f32>,
+        payload: HashMap<String, serde_json::Value>,
+    ) -> Result<String, VectorDBError> {
+        // Match Qdrant, which rejects points for missing collections or of the wrong size
+        match self.vector_size(collection).await? {
+            None => return Err(VectorDBError::Operation(format!("Collection {} not found", collection))),
+            Some(size) if size != vector.len() => {
+                return Err(VectorDBError::Operation(format!(
+                    "Vector has {} dimensions, but {} expects {}", vector.len(), collection, size
+                )));
+            }
+            Some(_) => {}
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let params = [
+            collection.to_string(),
+            id.clone(),
+            serde_json::to_string(&vector).map_err(operation)?,
+            serde_json::to_string(&payload).map_err(operation)?,
+        ];
+        self.conn
+            .call(move |conn| conn.execute("INSERT INTO vector_points (collection, id, vector, payload) VALUES (?1, ?2, ?3, ?4)", params))
+            .await
+            .map_err(operation)?;
+        Ok(id)
+    }
+
+    async fn search_vectors(
+        &self,
+        collection: &str,
+        query_vector: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<ScoredPoint>, VectorDBError> {
+        self.search_where(collection, &query_vector, limit, None).await
+    }
+
+    async fn search_vectors_matching(
+        &self,
+        collection: &str,
+        query_vector: Vec<f32>,
+        limit: u64,
+        field_match: (&str, &str),
+    ) -> Result<Vec<ScoredPoint>, VectorDBError> {
+        self.search_where(collection, &query_vector, limit, Some(field_match)).await
+    }
+
+    async fn delete_vectors(&self, collection: &str, ids: Vec<String>) -> Result<(), VectorDBError> {
+        let collection = collection.to_string();
+        self.conn
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                for id in ids {
+                    tx.execute("DELETE FROM vector_points WHERE collection = ?1 AND id = ?2", [&collection, &id])?;
+                }
+                tx.commit()
+            })
+            .await
+            .map_err(operation)
+    }
+
+    async fn update_payload(
+        &self,
+        collection: &str,
+        id: &str,
+        fields: HashMap<String, serde_json::Value>,
+    ) -> Result<(), VectorDBError> {
+        let params = [collection.to_string(), id.to_string(), serde_json::to_string(&fields).map_err(operation)?];
+        let updated = self.conn
+            .call(move |conn| conn.execute(
+                "UPDATE vector_points SET payload = json_patch(payload, ?3) WHERE collection = ?1 AND id = ?2",
+                params,
+            ))
+            .await
+            .map_err(operation)?;
+        if updated == 0 {
+            return Err(VectorDBError::Operation(format!("Point {} not found", id)));
+        }
+        Ok(())
+    }
+
+    async fn scroll_vectors(
+        &self,
+        collection: &str,
+        field_match: Option<(&str, &str)>,
+    ) -> Result<Vec<StoredPoint>, VectorDBError> {
+        Ok(self.points(collection).await?
+            .into_iter()
+            .map(|(id, _, payload)| (id, parse_payload(&payload)))
+            .filter(|(_, payload)| match field_match {
+                Some((field, value)) => payload.get(field).and_then(|v| v.as_str()) == Some(value),
+                None => true,
+            })
+            .collect())
+    }
+
+    async fn scroll_with_vectors(&self, collection: &str) -> Result<Vec<VectorPoint>, VectorDBError> {
+        Ok(self.points(collection).await?
+            .into_iter()
+            .filter_map(|(id, vector, payload)| Some((id, serde_json::from_str(&vector).ok()?, parse_payload(&payload))))
+            .collect())
+    }
+
+    /// Pages are in insertion order; an offset is the rowid the next page starts after.
+    async fn scroll_page(
+        &self,
+        collection: &str,
+        offset: Option<String>,
+        limit: usize,
+    ) -> Result<(Vec<VectorPoint>, Option<String>), VectorDBError> {
+        let after: i64 = offset.and_then(|o| o.parse().ok()).unwrap_or(0);
+        let params = [collection.to_string(), after.to_string(), limit.to_string()];
+        let rows: Vec<(i64, String, String, String)> = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT rowid, id, vector, payload FROM vector_points
+                     WHERE collection = ?1 AND rowid > CAST(?2 AS INTEGER) ORDER BY rowid LIMIT CAST(?3 AS INTEGER)"
+                )?;
+                let rows = stmt.query_map(params, |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })?
+                .collect::<Result<Vec<_>, _>>();
+                rows
+            })
+            .await
+            .map_err(operation)?;
+
+        let next = (rows.len() == limit).then(|| rows.last().map(|(rowid, ..)| rowid.to_string())).flatten();
+        let points = rows.into_iter()
+            .filter_map(|(_, id, vector, payload)| Some((id, serde_json::from_str(&vector).ok()?, parse_payload(&payload))))
+            .collect();
+        Ok((points, next))
+    }
+
+    async fn scroll_older_than(
+        &self,
+        collection: &str,
+        field: &str,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<StoredPoint>, VectorDBError> {
+        Ok(self.scroll_vectors(collection, None).await?
+            .into_iter()
+            .filter(|(_, payload)| {
+                payload.get(field)
+                    .and_then(|v| v.as_str())
+                    .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+                    .is_some_and(|ts| ts < before)
+            })
+            .collect())
+    }
+
+    async fn scroll_latest(
+        &self,
+        collection: &str,
+        field: &str,
+        limit: usize,
+        excluded: (&str, &[String]),
+    ) -> Result<Vec<StoredPoint>, VectorDBError> {
+        let excluded_values = serde_json::to_string(excluded.1).map_err(|e| VectorDBError::Operation(e.to_string()))?;
+        let params = [
+            collection.to_string(),
+            format!("$.{}", field),
+            format!("$.{}", excluded.0),
+            excluded_values,
+            limit.to_string(),
+        ];
+        let rows: Vec<(String, String)> = self.conn
+            .call(move |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, payload FROM vector_points
+                     WHERE collection = ?1
+                       AND coalesce(json_extract(payload, ?3), '') NOT IN (SELECT value FROM json_each(?4))
+                     ORDER BY julianday(json_extract(payload, ?2)) DESC LIMIT CAST(?5 AS INTEGER)"
+                )?;
+                let rows = stmt.query_map(params, |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<Result<Vec<_>, _>>();
+                rows
+            })
+            .await
+            .map_err(operation)?;
+        Ok(rows.into_iter().map(|(id, payload)| (id, parse_payload(&payload))).collect())
+    }
+
+    async fn count_vectors(&self, collection: &str) -> Result<u64, VectorDBError> {
+        let collection = collection.to_string();
+        let count = self.conn
+            .call(move |conn| conn.query_row("SELECT COUNT(*) FROM vector_points WHERE collection = ?", [&collection], |row| row.get::<_, i64>(0)))
+            .await
+            .map_err(operation)?;
+        Ok(count as u64)
+    }
+
+    async fn delete_by_payload(&self, collection: &str, field: &str, value: &str) -> Result<(), VectorDBError> {
+        let ids = self.scroll_vectors(collection, Some((field, value))).await?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        self.delete_vectors(collection, ids).await
+    }
+
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorDBError> {
+        let name = name.to_string();
+        self.conn
+            .call(move |conn| {
+                let tx = conn.transaction()?;
+                tx.execute("DELETE FROM vector_points WHERE collection = ?", [&name])?;
+                tx.execute("DELETE FROM vector_collections WHERE name = ?", [&name])?;
+                tx.commit()
+            })
+            .await
+            .map_err(operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::vector_store::check_vector_store;
+
+    #[tokio::test]
+    async fn test_sqlite_vector_store() {
+        let store = SqliteVectorStore::in_memory().await;
+        check_vector_store(&store).await;
+
+        assert!(store.store_vector("missing", vec![1.0], HashMap::new()).await.is_err());
+        store.create_collection("small", 2).await.unwrap();
+        assert!(store.store_vector("small", vec![1.0, 0.0, 0.0], HashMap::new()).await.is_err());
+    }
+}
@@ -7,7 +7,11 @@ use qdrant_client::{
         point_id::PointIdOptions,
         PointId, PointsSelector,
         CreateCollection, VectorsConfig,
-        UpsertPoints, DeletePoints,
+        UpsertPoints, DeletePoints, SetPayloadPoints,
+        ScrollPoints, CountPoints, Condition, Filter,
+        DatetimeRange, Timestamp, RetrievedPoint, VectorsOutput,
+        CreateFieldIndexCollection, FieldType, OrderBy, Direction,
+        vectors_output::VectorsOptions, vector_output,
     },
     Qdrant,
     config::QdrantConfig,
@@ -17,7 +21,28 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 use log;
-use crate::database::qdrant_config::create_qdrant_client;
+use crate::database::qdrant_config::{build_qdrant_client, create_qdrant_client};
+use crate::database::vector_store::{VectorPoint, VectorStore};
+use async_trait::async_trait;
+
+/// Maps a Qdrant cosine score from [-1, 1] to [0, 1], which is the range every search in
+/// this crate reports: 1 is an identical vector, 0.5 is unrelated and 0 is opposite.
+pub fn normalize_score(cosine: f32) -> f32 {
+    ((cosine + 1.0) / 2.0).clamp(0.0, 1.0)
+}
+
+/// Whether a normalized score clears an optional `min_score` floor.
+pub fn meets_threshold(score: f32, min_score: Option<f32>) -> bool {
+    min_score.is_none_or(|min| score >= min)
+}
+
+/// Whether `REQUIRE_VECTOR_DB` asks for a hard failure when Qdrant is unreachable,
+/// instead of running without vector memory.
+pub fn require_vector_db() -> bool {
+    std::env::var("REQUIRE_VECTOR_DB")
+        .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(false)
+}
 
 #[derive(Error, Debug)]
 pub enum VectorDBError {
@@ -27,6 +52,28 @@ pub enum VectorDBError {
     Operation(String),
     #[error("Collection exists: {0}")]
     CollectionExists(String),
+    #[error("The {collection} collection holds {existing}-dimensional vectors, but embeddings have {expected}. Set EMBEDDING_DIMENSION={existing} or empty the collection")]
+    DimensionMismatch { collection: String, existing: u64, expected: u64 },
+}
+
+/// The vector size Qdrant collection `name` was created with, or `None` if it doesn't exist.
+pub(crate) async fn collection_dimension(client: &Qdrant, name: &str) -> Result<Option<u64>, VectorDBError> {
+    if !client.collection_exists(name).await.map_err(|e| VectorDBError::Operation(e.to_string()))? {
+        return Ok(None);
+    }
+    let info = client.collection_info(name).await
+        .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+    let size = info.result
+        .and_then(|info| info.config)
+        .and_then(|config| config.params)
+        .and_then(|params| params.vectors_config)
+        .and_then(|vectors| vectors.config)
+        .and_then(|config| match config {
+            qdrant_client::qdrant::vectors_config::Config::Params(params) => Some(params.size),
+            // Named vectors: report the first one's size
+            qdrant_client::qdrant::vectors_config::Config::ParamsMap(map) => map.map.values().next().map(|p| p.size),
+        });
+    Ok(size)
 }
 
 #[derive(Clone)]
@@ -42,7 +89,142 @@ impl VectorDB {
         })
     }
 
-    pub async fn create_collection(
+    /// Like [`VectorDB::new`], but without checking that Qdrant is reachable.
+    pub fn connect_lazy(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = build_qdrant_client(url)?;
+        Ok(Self {
+            client: Arc::new(client),
+        })
+    }
+
+    /// The `limit` points matching `filter` closest to `query_vector`, filtered by Qdrant
+    /// before ranking.
+    async fn search_filtered(
+        &self,
+        collection: &str,
+        query_vector: Vec<f32>,
+        limit: u64,
+        filter: Option<Filter>,
+    ) -> Result<Vec<(String, f32, HashMap<String, serde_json::Value>)>, VectorDBError> {
+        let request = SearchPoints {
+            collection_name: collection.to_string(),
+            vector: query_vector,
+            limit: limit as u64,
+            filter,
+            with_payload: Some(WithPayloadSelector {
+                selector_options: Some(SelectorOptions::Enable(true)),
+            }),
+            ..Default::default()
+        };
+
+        let results = self.client.search_points(request)
+            .await
+            .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+
+        let points = results.result
+            .into_iter()
+            .map(|point| {
+                let id = match point.id.and_then(|id| id.point_id_options) {
+                    Some(PointIdOptions::Uuid(uuid)) => uuid,
+                    _ => String::new(),
+                };
+                let score = normalize_score(point.score);
+                let payload = point.payload
+                    .into_iter()
+                    .map(|(k, v)| (k, serde_json::Value::from(v)))
+                    .collect();
+                (id, score, payload)
+            })
+            .collect();
+
+        Ok(points)
+    }
+
+    /// Every point matching `filter`, a page at a time.
+    async fn scroll_filtered(
+        &self,
+        collection: &str,
+        filter: Option<Filter>,
+    ) -> Result<Vec<(String, HashMap<String, serde_json::Value>)>, VectorDBError> {
+        Ok(self.scroll_points(collection, filter, false).await?
+            .into_iter()
+            .map(|point| (point_id(point.id), payload_json(point.payload)))
+            .collect())
+    }
+
+    /// Every point matching `filter`, a page at a time, with vectors if `with_vectors`.
+    async fn scroll_points(
+        &self,
+        collection: &str,
+        filter: Option<Filter>,
+        with_vectors: bool,
+    ) -> Result<Vec<RetrievedPoint>, VectorDBError> {
+        let mut points = Vec::new();
+        let mut offset = None;
+        loop {
+            let request = ScrollPoints {
+                collection_name: collection.to_string(),
+                filter: filter.clone(),
+                offset,
+                limit: Some(256),
+                with_payload: Some(WithPayloadSelector {
+                    selector_options: Some(SelectorOptions::Enable(true)),
+                }),
+                with_vectors: Some(with_vectors.into()),
+                ..Default::default()
+            };
+
+            let response = self.client.scroll(request)
+                .await
+                .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+
+            points.extend(response.result);
+
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(points)
+    }
+}
+
+fn point_id(id: Option<PointId>) -> String {
+    match id.and_then(|id| id.point_id_options) {
+        Some(PointIdOptions::Uuid(uuid)) => uuid,
+        Some(PointIdOptions::Num(num)) => num.to_string(),
+        None => String::new(),
+    }
+}
+
+fn payload_json(payload: HashMap<String, Value>) -> HashMap<String, serde_json::Value> {
+    payload.into_iter()
+        .map(|(k, v)| (k, serde_json::Value::from(v)))
+        .collect()
+}
+
+/// The dense vector of a retrieved point; empty for named or sparse vectors, which this
+/// crate never stores.
+fn dense_vector(vectors: Option<VectorsOutput>) -> Vec<f32> {
+    match vectors.and_then(|v| v.vectors_options) {
+        Some(VectorsOptions::Vector(output)) => match output.vector {
+            Some(vector_output::Vector::Dense(dense)) => dense.data,
+            #[allow(deprecated)]
+            _ => output.data,
+        },
+        _ => Vec::new(),
+    }
+}
+
+#[async_trait]
+impl VectorStore for VectorDB {
+    /// Whether Qdrant answers a health check right now.
+    async fn is_available(&self) -> bool {
+        self.client.health_check().await.is_ok()
+    }
+
+    async fn create_collection(
         &self,
         name: &str,
         vector_size: u64,
@@ -73,7 +255,11 @@ impl VectorDB {
         }
     }
 
-    pub async fn store_vector(
+    async fn collection_dimension(&self, name: &str) -> Result<Option<u64>, VectorDBError> {
+        collection_dimension(&self.client, name).await
+    }
+
+    async fn store_vector(
         &self,
         collection: &str,
         vector: Vec<f32>,
@@ -107,46 +293,59 @@ impl VectorDB {
         Ok(point_id)
     }
 
-    pub async fn search_vectors(
+    async fn store_vectors(
         &self,
         collection: &str,
-        query_vector: Vec<f32>,
-        limit: u64,
-    ) -> Result<Vec<(String, f32, HashMap<String, serde_json::Value>)>, VectorDBError> {
-        let request = SearchPoints {
+        points: Vec<(Vec<f32>, HashMap<String, serde_json::Value>)>,
+    ) -> Result<Vec<String>, VectorDBError> {
+        let ids: Vec<String> = points.iter().map(|_| Uuid::new_v4().to_string()).collect();
+        let points: Vec<PointStruct> = points.into_iter().zip(&ids)
+            .map(|((vector, payload), id)| PointStruct {
+                id: Some(PointId {
+                    point_id_options: Some(PointIdOptions::Uuid(id.clone()))
+                }),
+                vectors: Some(vector.into()),
+                payload: payload.into_iter().map(|(k, v)| (k, Value::from(v))).collect(),
+            })
+            .collect();
+        if points.is_empty() {
+            return Ok(ids);
+        }
+
+        let upsert_points = UpsertPoints {
             collection_name: collection.to_string(),
-            vector: query_vector,
-            limit: limit as u64,
-            with_payload: Some(WithPayloadSelector {
-                selector_options: Some(SelectorOptions::Enable(true)),
-            }),
+            points,
             ..Default::default()
         };
 
-        let results = self.client.search_points(request)
+        self.client.upsert_points(upsert_points)
             .await
             .map_err(|e| VectorDBError::Operation(e.to_string()))?;
 
-        let points = results.result
-            .into_iter()
-            .map(|point| {
-                let id = match point.id.and_then(|id| id.point_id_options) {
-                    Some(PointIdOptions::Uuid(uuid)) => uuid,
-                    _ => String::new(),
-                };
-                let score = point.score;
-                let payload = point.payload
-                    .into_iter()
-                    .map(|(k, v)| (k, serde_json::Value::try_from(v).unwrap_or(serde_json::Value::Null)))
-                    .collect();
-                (id, score, payload)
-            })
-            .collect();
+        Ok(ids)
+    }
 
-        Ok(points)
+    async fn search_vectors(
+        &self,
+        collection: &str,
+        query_vector: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<(String, f32, HashMap<String, serde_json::Value>)>, VectorDBError> {
+        self.search_filtered(collection, query_vector, limit, None).await
+    }
+
+    async fn search_vectors_matching(
+        &self,
+        collection: &str,
+        query_vector: Vec<f32>,
+        limit: u64,
+        (field, value): (&str, &str),
+    ) -> Result<Vec<(String, f32, HashMap<String, serde_json::Value>)>, VectorDBError> {
+        let filter = Filter::must([Condition::matches(field, value.to_string())]);
+        self.search_filtered(collection, query_vector, limit, Some(filter)).await
     }
 
-    pub async fn delete_vectors(
+    async fn delete_vectors(
         &self,
         collection: &str,
         ids: Vec<String>,
@@ -174,4 +373,200 @@ impl VectorDB {
 
         Ok(())
     }
-} 
\ No newline at end of file
+
+    async fn update_payload(
+        &self,
+        collection: &str,
+        id: &str,
+        fields: HashMap<String, serde_json::Value>,
+    ) -> Result<(), VectorDBError> {
+        let point = PointId {
+            point_id_options: Some(PointIdOptions::Uuid(id.to_string()))
+        };
+        let set_payload = SetPayloadPoints {
+            collection_name: collection.to_string(),
+            payload: fields.into_iter().map(|(k, v)| (k, Value::from(v))).collect(),
+            points_selector: Some(PointsSelector {
+                points_selector_one_of: Some(vec![point].into()),
+            }),
+            ..Default::default()
+        };
+
+        self.client.set_payload(set_payload)
+            .await
+            .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Returns every point's id and payload, optionally only those whose `field` equals `value`.
+    async fn scroll_vectors(
+        &self,
+        collection: &str,
+        field_match: Option<(&str, &str)>,
+    ) -> Result<Vec<(String, HashMap<String, serde_json::Value>)>, VectorDBError> {
+        let filter = field_match.map(|(field, value)| {
+            Filter::must([Condition::matches(field, value.to_string())])
+        });
+        self.scroll_filtered(collection, filter).await
+    }
+
+    async fn scroll_with_vectors(&self, collection: &str) -> Result<Vec<VectorPoint>, VectorDBError> {
+        Ok(self.scroll_points(collection, None, true).await?
+            .into_iter()
+            .map(|point| (point_id(point.id), dense_vector(point.vectors), payload_json(point.payload)))
+            .collect())
+    }
+
+    async fn scroll_page(
+        &self,
+        collection: &str,
+        offset: Option<String>,
+        limit: usize,
+    ) -> Result<(Vec<VectorPoint>, Option<String>), VectorDBError> {
+        let request = ScrollPoints {
+            collection_name: collection.to_string(),
+            offset: offset.map(|id| PointId { point_id_options: Some(PointIdOptions::Uuid(id)) }),
+            limit: Some(limit as u32),
+            with_payload: Some(WithPayloadSelector {
+                selector_options: Some(SelectorOptions::Enable(true)),
+            }),
+            with_vectors: Some(true.into()),
+            ..Default::default()
+        };
+
+        let response = self.client.scroll(request)
+            .await
+            .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+
+        let points = response.result.into_iter()
+            .map(|point| (point_id(point.id), dense_vector(point.vectors), payload_json(point.payload)))
+            .collect();
+        Ok((points, response.next_page_offset.map(|next| point_id(Some(next)))))
+    }
+
+    async fn scroll_older_than(
+        &self,
+        collection: &str,
+        field: &str,
+        before: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<(String, HashMap<String, serde_json::Value>)>, VectorDBError> {
+        let range = DatetimeRange {
+            lt: Some(Timestamp { seconds: before.timestamp(), nanos: before.timestamp_subsec_nanos() as i32 }),
+            ..Default::default()
+        };
+        self.scroll_filtered(collection, Some(Filter::must([Condition::datetime_range(field, range)]))).await
+    }
+
+    async fn scroll_latest(
+        &self,
+        collection: &str,
+        field: &str,
+        limit: usize,
+        excluded: (&str, &[String]),
+    ) -> Result<Vec<(String, HashMap<String, serde_json::Value>)>, VectorDBError> {
+        // Ordering by a payload field needs an index on it; creating an existing one is a no-op
+        let index = CreateFieldIndexCollection {
+            collection_name: collection.to_string(),
+            wait: Some(true),
+            field_name: field.to_string(),
+            field_type: Some(FieldType::Datetime as i32),
+            ..Default::default()
+        };
+        self.client.create_field_index(index)
+            .await
+            .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+
+        let (excluded_field, excluded_values) = excluded;
+        let request = ScrollPoints {
+            collection_name: collection.to_string(),
+            filter: (!excluded_values.is_empty())
+                .then(|| Filter::must_not([Condition::matches(excluded_field, excluded_values.to_vec())])),
+            limit: Some(limit as u32),
+            with_payload: Some(WithPayloadSelector {
+                selector_options: Some(SelectorOptions::Enable(true)),
+            }),
+            order_by: Some(OrderBy {
+                key: field.to_string(),
+                direction: Some(Direction::Desc as i32),
+                start_from: None,
+            }),
+            ..Default::default()
+        };
+
+        let response = self.client.scroll(request)
+            .await
+            .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+        Ok(response.result.into_iter()
+            .map(|point| (point_id(point.id), payload_json(point.payload)))
+            .collect())
+    }
+
+    async fn count_vectors(&self, collection: &str) -> Result<u64, VectorDBError> {
+        let request = CountPoints {
+            collection_name: collection.to_string(),
+            exact: Some(true),
+            ..Default::default()
+        };
+
+        let response = self.client.count(request)
+            .await
+            .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+
+        Ok(response.result.map(|r| r.count).unwrap_or(0))
+    }
+
+    /// Deletes every point whose `field` payload equals `value`.
+    async fn delete_by_payload(
+        &self,
+        collection: &str,
+        field: &str,
+        value: &str,
+    ) -> Result<(), VectorDBError> {
+        let delete_points = DeletePoints {
+            collection_name: collection.to_string(),
+            points: Some(Filter::must([Condition::matches(field, value.to_string())]).into()),
+            ..Default::default()
+        };
+
+        self.client.delete_points(delete_points)
+            .await
+            .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorDBError> {
+        self.client.delete_collection(name)
+            .await
+            .map_err(|e| VectorDBError::Operation(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores_normalize_and_threshold() {
+        assert_eq!(normalize_score(1.0), 1.0);
+        assert_eq!(normalize_score(0.0), 0.5);
+        assert_eq!(normalize_score(-1.0), 0.0);
+        assert_eq!(normalize_score(1.0001), 1.0);
+
+        let scores: Vec<f32> = [0.9, 0.1, -0.4].into_iter().map(normalize_score).collect();
+        let kept: Vec<f32> = scores.iter().copied().filter(|&s| meets_threshold(s, Some(0.6))).collect();
+        assert_eq!(kept, vec![0.95]);
+        assert!(scores.iter().all(|&s| meets_threshold(s, None)));
+    }
+
+    // Needs Qdrant: cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn test_qdrant_vector_store() {
+        let vector_db = VectorDB::new("http://localhost:6333").await.unwrap();
+        crate::database::vector_store::check_vector_store(&vector_db).await;
+    }
+}
@@ -0,0 +1,256 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use super::vector_db::VectorDBError;
+
+/// A point's id, normalized score and payload, as returned by searches.
+pub type ScoredPoint = (String, f32, HashMap<String, serde_json::Value>);
+
+/// A point's id and payload.
+pub type StoredPoint = (String, HashMap<String, serde_json::Value>);
+
+/// A point's id, vector and payload, as exported.
+pub type VectorPoint = (String, Vec<f32>, HashMap<String, serde_json::Value>);
+
+/// Embeddings with JSON payloads, grouped into named collections.
+///
+/// Every backend reports search scores normalized to 0.0..=1.0 (see `normalize_score`),
+/// so thresholds mean the same thing whichever one is configured.
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Whether the backend can be used right now.
+    async fn is_available(&self) -> bool;
+
+    /// Creates `name` for vectors of `vector_size` dimensions; an existing one is kept.
+    async fn create_collection(&self, name: &str, vector_size: u64) -> Result<(), VectorDBError>;
+
+    /// The vector size `name` was created with, or `None` if it doesn't exist.
+    async fn collection_dimension(&self, name: &str) -> Result<Option<u64>, VectorDBError>;
+
+    /// Creates `name` for vectors of `vector_size` dimensions, failing if it already
+    /// exists with another size, since every store and search on it would fail.
+    async fn ensure_collection(&self, name: &str, vector_size: u64) -> Result<(), VectorDBError> {
+        match self.collection_dimension(name).await? {
+            Some(existing) if existing != vector_size => Err(VectorDBError::DimensionMismatch {
+                collection: name.to_string(),
+                existing,
+                expected: vector_size,
+            }),
+            Some(_) => Ok(()),
+            None => self.create_collection(name, vector_size).await,
+        }
+    }
+
+    /// Stores a point, returning its new id.
+    async fn store_vector(
+        &self,
+        collection: &str,
+        vector: Vec<f32>,
+        payload: HashMap<String, serde_json::Value>,
+    ) -> Result<String, VectorDBError>;
+
+    /// Stores many points, returning their new ids in order. Backends that can write a
+    /// batch in one request override this.
+    async fn store_vectors(
+        &self,
+        collection: &str,
+        points: Vec<(Vec<f32>, HashMap<String, serde_json::Value>)>,
+    ) -> Result<Vec<String>, VectorDBError> {
+        let mut ids = Vec::with_capacity(points.len());
+        for (vector, payload) in points {
+            ids.push(self.store_vector(collection, vector, payload).await?);
+        }
+        Ok(ids)
+    }
+
+    /// The `limit` points closest to `query_vector`, best first.
+    async fn search_vectors(
+        &self,
+        collection: &str,
+        query_vector: Vec<f32>,
+        limit: u64,
+    ) -> Result<Vec<ScoredPoint>, VectorDBError>;
+
+    /// Like `search_vectors`, over only the points whose `field` equals `value`.
+    async fn search_vectors_matching(
+        &self,
+        collection: &str,
+        query_vector: Vec<f32>,
+        limit: u64,
+        field_match: (&str, &str),
+    ) -> Result<Vec<ScoredPoint>, VectorDBError>;
+
+    async fn delete_vectors(&self, collection: &str, ids: Vec<String>) -> Result<(), VectorDBError>;
+
+    /// Sets `fields` in the payload of point `id`, keeping its other fields and its vector.
+    async fn update_payload(
+        &self,
+        collection: &str,
+        id: &str,
+        fields: HashMap<String, serde_json::Value>,
+    ) -> Result<(), VectorDBError>;
+
+    /// Returns every point's id and payload, optionally only those whose `field` equals `value`.
+    async fn scroll_vectors(
+        &self,
+        collection: &str,
+        field_match: Option<(&str, &str)>,
+    ) -> Result<Vec<StoredPoint>, VectorDBError>;
+
+    /// Returns every point with its vector, for exporting a collection.
+    async fn scroll_with_vectors(&self, collection: &str) -> Result<Vec<VectorPoint>, VectorDBError>;
+
+    /// Up to `limit` points with their vectors, starting at `offset` (`None` for the first
+    /// page), and the offset of the next page, `None` after the last one.
+    async fn scroll_page(
+        &self,
+        collection: &str,
+        offset: Option<String>,
+        limit: usize,
+    ) -> Result<(Vec<VectorPoint>, Option<String>), VectorDBError>;
+
+    /// Returns every point whose `field` holds an RFC 3339 time before `before`.
+    async fn scroll_older_than(
+        &self,
+        collection: &str,
+        field: &str,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<StoredPoint>, VectorDBError>;
+
+    /// The `limit` points whose `field` holds the latest RFC 3339 times, newest first,
+    /// leaving out those whose `excluded.0` field holds one of `excluded.1`.
+    async fn scroll_latest(
+        &self,
+        collection: &str,
+        field: &str,
+        limit: usize,
+        excluded: (&str, &[String]),
+    ) -> Result<Vec<StoredPoint>, VectorDBError>;
+
+    async fn count_vectors(&self, collection: &str) -> Result<u64, VectorDBError>;
+
+    /// Deletes every point whose `field` payload equals `value`.
+    async fn delete_by_payload(&self, collection: &str, field: &str, value: &str) -> Result<(), VectorDBError>;
+
+    async fn delete_collection(&self, name: &str) -> Result<(), VectorDBError>;
+}
+
+/// Where vectors are kept, picked with `VECTOR_BACKEND`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorBackend {
+    /// A Qdrant server at `QDRANT_URL` (the default)
+    Qdrant,
+    /// Brute-force cosine search over a table in the main SQLite database, which is fine
+    /// for the few thousand vectors of a single-user CLI
+    Sqlite,
+}
+
+impl VectorBackend {
+    /// The backend named by `VECTOR_BACKEND`, or Qdrant when it is unset or unknown.
+    pub fn from_env() -> Self {
+        match std::env::var("VECTOR_BACKEND").map(|value| value.trim().to_lowercase()).as_deref() {
+            Ok("sqlite") => VectorBackend::Sqlite,
+            Ok("qdrant") | Err(_) => VectorBackend::Qdrant,
+            Ok(other) => {
+                log::warn!("Unknown VECTOR_BACKEND '{}', using qdrant", other);
+                VectorBackend::Qdrant
+            }
+        }
+    }
+}
+
+/// The behaviour every backend must share, run against each of them.
+#[cfg(test)]
+pub(crate) async fn check_vector_store(store: &dyn VectorStore) {
+    let collection = format!("test_{}", uuid::Uuid::new_v4().simple());
+    assert_eq!(store.collection_dimension(&collection).await.unwrap(), None);
+    store.create_collection(&collection, 3).await.unwrap();
+    store.create_collection(&collection, 3).await.unwrap();
+    assert_eq!(store.collection_dimension(&collection).await.unwrap(), Some(3));
+
+    let point = |text: &str, session: &str, timestamp: &str| {
+        HashMap::from([
+            ("text".to_string(), serde_json::json!(text)),
+            ("session_id".to_string(), serde_json::json!(session)),
+            ("timestamp".to_string(), serde_json::json!(timestamp)),
+        ])
+    };
+    let rust = store.store_vector(&collection, vec![1.0, 0.0, 0.0], point("rust", "a", "2020-01-01T00:00:00Z")).await.unwrap();
+    let go = store.store_vector(&collection, vec![0.8, 0.6, 0.0], point("go", "a", &(Utc::now() - chrono::Duration::hours(1)).to_rfc3339())).await.unwrap();
+    store.store_vector(&collection, vec![-1.0, 0.0, 0.0], point("soup", "b", &Utc::now().to_rfc3339())).await.unwrap();
+    assert_eq!(store.count_vectors(&collection).await.unwrap(), 3);
+
+    let old = store.scroll_older_than(&collection, "timestamp", Utc::now() - chrono::Duration::days(1)).await.unwrap();
+    assert_eq!(old.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>(), vec![rust.clone()]);
+    let latest = store.scroll_latest(&collection, "timestamp", 2, ("session_id", &["b".to_string()])).await.unwrap();
+    assert_eq!(latest.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>(), vec![go.clone(), rust.clone()]);
+    let latest = store.scroll_latest(&collection, "timestamp", 1, ("session_id", &[])).await.unwrap();
+    assert_eq!(latest[0].1["text"], "soup");
+
+    let hits = store.search_vectors(&collection, vec![1.0, 0.0, 0.0], 2).await.unwrap();
+    assert_eq!(hits.iter().map(|(id, _, _)| id.clone()).collect::<Vec<_>>(), vec![rust.clone(), go.clone()]);
+    assert!((hits[0].1 - 1.0).abs() < 1e-4);
+    assert!((hits[1].1 - 0.9).abs() < 1e-4);
+    assert_eq!(hits[0].2["text"], "rust");
+
+    assert_eq!(store.scroll_vectors(&collection, Some(("session_id", "a"))).await.unwrap().len(), 2);
+    // Filtered before ranking, so the closest point outside the session can't crowd it out
+    let in_b = store.search_vectors_matching(&collection, vec![1.0, 0.0, 0.0], 1, ("session_id", "b")).await.unwrap();
+    assert_eq!(in_b.iter().map(|(_, _, payload)| payload["text"].clone()).collect::<Vec<_>>(), vec![serde_json::json!("soup")]);
+    store.update_payload(&collection, &rust, HashMap::from([("importance".to_string(), serde_json::json!(0.25))])).await.unwrap();
+    let updated = store.search_vectors(&collection, vec![1.0, 0.0, 0.0], 1).await.unwrap();
+    assert_eq!(updated[0].2["importance"], 0.25);
+    assert_eq!(updated[0].2["text"], "rust");
+    let exported = store.scroll_with_vectors(&collection).await.unwrap();
+    assert_eq!(exported.len(), 3);
+    let (_, vector, payload) = exported.iter().find(|(id, _, _)| *id == go).unwrap();
+    assert_eq!((vector.clone(), payload["text"].clone()), (vec![0.8, 0.6, 0.0], serde_json::json!("go")));
+
+    // Paging visits every point exactly once
+    let (mut paged, mut offset) = (Vec::new(), None);
+    loop {
+        let (page, next) = store.scroll_page(&collection, offset, 2).await.unwrap();
+        assert!(page.len() <= 2);
+        paged.extend(page.into_iter().map(|(id, _, _)| id));
+        match next {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+    let mut all: Vec<String> = exported.iter().map(|(id, _, _)| id.clone()).collect();
+    all.sort();
+    paged.sort();
+    assert_eq!(paged, all);
+
+    let copied = store.store_vectors(&collection, vec![
+        (vec![0.0, 0.0, 1.0], point("copy one", "c", "2020-01-01T00:00:00Z")),
+        (vec![0.0, 1.0, 0.0], point("copy two", "c", "2020-01-01T00:00:00Z")),
+    ]).await.unwrap();
+    assert_eq!(copied.len(), 2);
+    assert_eq!(store.scroll_vectors(&collection, Some(("session_id", "c"))).await.unwrap().len(), 2);
+    store.delete_by_payload(&collection, "session_id", "c").await.unwrap();
+
+    store.delete_vectors(&collection, vec![go]).await.unwrap();
+    store.delete_by_payload(&collection, "session_id", "b").await.unwrap();
+    let left = store.scroll_vectors(&collection, None).await.unwrap();
+    assert_eq!(left.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>(), vec![rust]);
+
+    store.delete_collection(&collection).await.unwrap();
+
+    // MiniLM- and OpenAI-sized collections side by side, neither resizable afterwards
+    for size in [384, 1536] {
+        let sized = format!("{}_{}", collection, size);
+        store.ensure_collection(&sized, size).await.unwrap();
+        store.ensure_collection(&sized, size).await.unwrap();
+        assert_eq!(store.collection_dimension(&sized).await.unwrap(), Some(size));
+        let mut vector = vec![0.0; size as usize];
+        vector[0] = 1.0;
+        store.store_vector(&sized, vector.clone(), point("sized", "d", "2020-01-01T00:00:00Z")).await.unwrap();
+        assert_eq!(store.search_vectors(&sized, vector, 1).await.unwrap()[0].2["text"], "sized");
+
+        let other = if size == 384 { 1536 } else { 384 };
+        let err = store.ensure_collection(&sized, other).await.unwrap_err();
+        assert!(matches!(err, VectorDBError::DimensionMismatch { existing, expected, .. } if existing == size && expected == other));
+        store.delete_collection(&sized).await.unwrap();
+    }
+}
@@ -0,0 +1,54 @@
+use thiserror::Error;
+use crate::commands::CommandError;
+use crate::database::{DatabaseError, VectorDBError};
+
+/// Any error the crate's layers return, keeping the original as its `source()` so `?`
+/// can cross from the vector store up to the binary without flattening it to a string.
+#[derive(Error, Debug)]
+pub enum AppError {
+    #[error("Provider error: {0}")]
+    ProviderError(String),
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] DatabaseError),
+    #[error("Memory error: {0}")]
+    MemoryError(#[source] anyhow::Error),
+    #[error(transparent)]
+    CommandError(#[from] CommandError),
+    #[error("API error: {0}")]
+    ApiError(String),
+    #[error("Timeout error")]
+    TimeoutError,
+}
+
+/// Vector store failures reach the app through the database layer.
+impl From<VectorDBError> for AppError {
+    fn from(err: VectorDBError) -> Self {
+        AppError::DatabaseError(err.into())
+    }
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_conversions_keep_the_cause() {
+        let err: AppError = VectorDBError::Connection("qdrant refused".to_string()).into();
+        assert!(matches!(
+            &err,
+            AppError::DatabaseError(DatabaseError::VectorDB(VectorDBError::Connection(message))) if message == "qdrant refused"
+        ));
+        let cause = err.source().and_then(|database| database.source()).unwrap();
+        assert_eq!(cause.to_string(), "Connection error: qdrant refused");
+
+        let err: AppError = CommandError::NotFound("No knowledge entry 'x'".to_string()).into();
+        assert!(matches!(err, AppError::CommandError(CommandError::NotFound(_))));
+        assert_eq!(err.to_string(), "No knowledge entry 'x'");
+
+        let err = AppError::MemoryError(anyhow::Error::msg("cleanup failed"));
+        assert_eq!(err.source().unwrap().to_string(), "cleanup failed");
+    }
+}
@@ -1,5 +1,5 @@
-use crate::food::api::usda::UsdaClient;
-use crate::food::api::spoonacular::SpoonacularClient;
+use crate::food::api::usda::{format_food_nutrition, UsdaClient};
+use crate::food::api::spoonacular::{format_recipe, RecipeFilters, SpoonacularClient};
 use crate::food::config::FoodConfig;
 
 pub async fn analyze_nutrition(food_item: &str) -> Result<String, String> {
@@ -11,15 +11,14 @@ pub async fn analyze_nutrition(food_item: &str) -> Result<String, String> {
     let spoonacular_client = SpoonacularClient::new(config.spoonacular_api_key);
     
     // Try Spoonacular first for recipe data
-    match spoonacular_client.search_recipe(food_item).await {
-        Ok(recipe_info) => {
-            if !recipe_info.contains("No recipe found") {
-                return Ok(recipe_info);
-            }
-        }
-        Err(_) => {} // Fall back to USDA if Spoonacular fails
+    match spoonacular_client.search_recipe(food_item, &RecipeFilters::default()).await {
+        Ok(Some(recipe)) => return Ok(format_recipe(&recipe)),
+        Ok(None) | Err(_) => {} // Fall back to USDA if Spoonacular fails
     }
 
     // Fall back to USDA for basic ingredient data
-    usda_client.search_food(food_item).await
+    match usda_client.search_food(food_item).await? {
+        Some(nutrition) => Ok(format_food_nutrition(&nutrition)),
+        None => Ok(format!("No nutrition data found for '{}'", food_item)),
+    }
 }
@@ -1,6 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::food::cache::ResponseCache;
+
+const CACHE_CLIENT: &str = "spoonacular_recipe";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Recipe {
     pub id: i64,
     pub title: String,
@@ -10,38 +16,240 @@ pub struct Recipe {
     pub image: Option<String>,
     pub summary: Option<String>,
     pub instructions: Option<String>,
+    #[serde(default)]
+    pub cuisines: Vec<String>,
+    #[serde(default)]
+    pub dish_types: Vec<String>,
     pub extended_ingredients: Option<Vec<Ingredient>>,
+    #[serde(default)]
+    pub analyzed_instructions: Vec<InstructionSection>,
     pub nutrition: Option<NutritionInfo>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ingredient {
     pub id: Option<i64>,
     pub name: String,
     pub amount: f64,
     pub unit: String,
+    pub original: Option<String>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstructionSection {
+    #[serde(default)]
+    pub steps: Vec<InstructionStep>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstructionStep {
+    pub number: Option<i32>,
+    pub step: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NutritionInfo {
+    #[serde(default)]
     pub nutrients: Vec<Nutrient>,
-    pub calories: String,
-    pub fat: String,
-    pub protein: String,
-    pub carbs: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Nutrient {
     pub name: String,
     pub amount: f64,
     pub unit: String,
 }
 
+impl Recipe {
+    /// Looks up a per-serving nutrient such as "Calories" or "Protein".
+    pub fn nutrient(&self, name: &str) -> Option<&Nutrient> {
+        self.nutrition.as_ref()?
+            .nutrients.iter()
+            .find(|n| n.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Message shown when the Spoonacular daily quota is used up.
+pub const QUOTA_EXCEEDED_MESSAGE: &str = "Spoonacular API quota exceeded for today. Try again tomorrow or upgrade your plan.";
+
+/// Diets accepted by Spoonacular's `diet` parameter.
+pub const SUPPORTED_DIETS: &[&str] = &[
+    "gluten free", "ketogenic", "vegetarian", "lacto-vegetarian", "ovo-vegetarian",
+    "vegan", "pescetarian", "paleo", "primal", "low fodmap", "whole30",
+];
+
+/// Intolerances accepted by Spoonacular's `intolerances` parameter.
+pub const SUPPORTED_INTOLERANCES: &[&str] = &[
+    "dairy", "egg", "gluten", "grain", "peanut", "seafood",
+    "sesame", "shellfish", "soy", "sulfite", "tree nut", "wheat",
+];
+
+const WEEK_DAYS: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedMeal {
+    pub id: i64,
+    pub title: String,
+    #[serde(rename = "readyInMinutes")]
+    pub ready_in_minutes: Option<i32>,
+    pub servings: Option<i32>,
+    #[serde(rename = "sourceUrl")]
+    pub source_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyNutrients {
+    #[serde(default)]
+    pub calories: f64,
+    #[serde(default)]
+    pub protein: f64,
+    #[serde(default)]
+    pub fat: f64,
+    #[serde(default)]
+    pub carbohydrates: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayPlan {
+    pub day: String,
+    pub meals: Vec<PlannedMeal>,
+    pub nutrients: DailyNutrients,
+}
+
+/// A generated meal plan with per-day and overall nutrition totals.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MealPlan {
+    pub target_calories: u32,
+    pub diet: Option<String>,
+    pub days: Vec<DayPlan>,
+    pub totals: DailyNutrients,
+}
+
+#[derive(Deserialize)]
+struct DayResponse {
+    meals: Vec<PlannedMeal>,
+    #[serde(default)]
+    nutrients: DailyNutrients,
+}
+
+impl MealPlan {
+    /// Builds a plan from a `/mealplanner/generate` response, keeping at most `days` days.
+    /// Accepts both the `timeFrame=day` and `timeFrame=week` shapes.
+    pub fn from_response(data: serde_json::Value, days: usize, target_calories: u32, diet: Option<&str>) -> Result<Self, String> {
+        let parse_day = |value: serde_json::Value| {
+            serde_json::from_value::<DayResponse>(value)
+                .map_err(|e| format!("Failed to parse meal plan: {}", e))
+        };
+
+        let mut plan_days = Vec::new();
+        if let Some(week) = data.get("week") {
+            for day in WEEK_DAYS.iter().take(days) {
+                if let Some(value) = week.get(*day) {
+                    let parsed = parse_day(value.clone())?;
+                    plan_days.push(DayPlan { day: day.to_string(), meals: parsed.meals, nutrients: parsed.nutrients });
+                }
+            }
+        } else {
+            let parsed = parse_day(data)?;
+            plan_days.push(DayPlan { day: "day 1".to_string(), meals: parsed.meals, nutrients: parsed.nutrients });
+        }
+
+        if plan_days.is_empty() {
+            return Err("Meal plan response contained no days".to_string());
+        }
+
+        let totals = plan_days.iter().fold(DailyNutrients::default(), |acc, day| DailyNutrients {
+            calories: acc.calories + day.nutrients.calories,
+            protein: acc.protein + day.nutrients.protein,
+            fat: acc.fat + day.nutrients.fat,
+            carbohydrates: acc.carbohydrates + day.nutrients.carbohydrates,
+        });
+
+        Ok(Self {
+            target_calories,
+            diet: diet.map(str::to_string),
+            days: plan_days,
+            totals,
+        })
+    }
+
+    pub fn average_calories(&self) -> f64 {
+        self.totals.calories / self.days.len() as f64
+    }
+}
+
+/// Dietary restrictions applied to recipe searches.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecipeFilters {
+    pub diet: Option<String>,
+    pub intolerances: Vec<String>,
+}
+
+impl RecipeFilters {
+    /// Builds filters, normalizing names (`keto`, `gluten-free`, `tree_nut`) to Spoonacular's spelling.
+    pub fn new(diet: Option<&str>, intolerances: &[&str]) -> Result<Self, String> {
+        let diet = diet.map(parse_diet).transpose()?;
+        let mut parsed = Vec::new();
+        for intolerance in intolerances {
+            let intolerance = parse_intolerance(intolerance)?;
+            if !parsed.contains(&intolerance) {
+                parsed.push(intolerance);
+            }
+        }
+        Ok(Self { diet, intolerances: parsed })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diet.is_none() && self.intolerances.is_empty()
+    }
+}
+
+fn normalize_filter_name(name: &str) -> String {
+    name.trim().to_lowercase().replace(['_', '-'], " ")
+}
+
+/// Validates a diet name against [`SUPPORTED_DIETS`].
+pub fn parse_diet(diet: &str) -> Result<String, String> {
+    let normalized = match normalize_filter_name(diet).as_str() {
+        "keto" => "ketogenic".to_string(),
+        "pescatarian" => "pescetarian".to_string(),
+        other => other.to_string(),
+    };
+    SUPPORTED_DIETS.iter()
+        .find(|allowed| normalize_filter_name(allowed) == normalized)
+        .map(|allowed| allowed.to_string())
+        .ok_or_else(|| format!("Unknown diet '{}'. Supported diets: {}", diet, SUPPORTED_DIETS.join(", ")))
+}
+
+/// Validates an intolerance against [`SUPPORTED_INTOLERANCES`].
+pub fn parse_intolerance(intolerance: &str) -> Result<String, String> {
+    let normalized = match normalize_filter_name(intolerance).as_str() {
+        "eggs" => "egg".to_string(),
+        "peanuts" => "peanut".to_string(),
+        "nuts" | "tree nuts" => "tree nut".to_string(),
+        "milk" | "lactose" => "dairy".to_string(),
+        other => other.to_string(),
+    };
+    SUPPORTED_INTOLERANCES.iter()
+        .find(|allowed| **allowed == normalized)
+        .map(|allowed| allowed.to_string())
+        .ok_or_else(|| format!(
+            "Unknown intolerance '{}'. Supported intolerances: {}",
+            intolerance,
+            SUPPORTED_INTOLERANCES.join(", ")
+        ))
+}
+
 #[derive(Debug)]
 pub struct SpoonacularClient {
     api_key: String,
     base_url: String,
+    cache: ResponseCache,
 }
 
 impl SpoonacularClient {
@@ -49,13 +257,83 @@ impl SpoonacularClient {
         Self {
             api_key,
             base_url: "https://api.spoonacular.com".to_string(),
+            cache: ResponseCache::new(),
+        }
+    }
+
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// Finds the most popular recipe matching `query`, or `None` if Spoonacular has no match.
+    pub async fn search_recipe(&self, query: &str, filters: &RecipeFilters) -> Result<Option<Recipe>, String> {
+        let query = &crate::food::matching::canonical_query(query);
+        let cache_key = format!(
+            "{} diet:{} no:{}",
+            query,
+            filters.diet.as_deref().unwrap_or(""),
+            filters.intolerances.join(",")
+        );
+        if let Some(cached) = self.cache.get(CACHE_CLIENT, &cache_key) {
+            if let Ok(recipe) = serde_json::from_str::<Recipe>(&cached) {
+                return Ok(Some(recipe));
+            }
+        }
+
+        let recipe = self.fetch_recipe(query, filters).await?;
+        if let Some(recipe) = &recipe {
+            if let Ok(serialized) = serde_json::to_string(recipe) {
+                self.cache.put(CACHE_CLIENT, &cache_key, &serialized);
+            }
+        }
+        Ok(recipe)
+    }
+
+    /// `search_recipe` rendered for the CLI, with search tips when nothing matches.
+    pub async fn search_recipe_formatted(&self, query: &str, filters: &RecipeFilters) -> Result<String, String> {
+        match self.search_recipe(query, filters).await? {
+            Some(recipe) => Ok(format_recipe(&recipe)),
+            None => Ok(format!("No recipe found for '{}'. Try:\n1. Check your spelling\n2. Use a more common name\n3. Try a different variation (e.g., 'nasi goreng' for 'indonesian fried rice')\n4. Specify the cuisine type (e.g., 'japanese ramen')", query)),
         }
     }
 
-    pub async fn search_recipe(&self, query: &str) -> Result<String, String> {
+    async fn fetch_recipe(&self, query: &str, filters: &RecipeFilters) -> Result<Option<Recipe>, String> {
         let client = reqwest::Client::new();
         let url = format!("{}/recipes/complexSearch", self.base_url);
-        
+        let params = self.recipe_search_params(query, filters);
+
+        let response = client
+            .get(&url)
+            .query(&params)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to send request: {}", e))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::PAYMENT_REQUIRED || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(QUOTA_EXCEEDED_MESSAGE.to_string());
+        }
+        if !status.is_success() {
+            return Err(format!("API request failed with status: {}", status));
+        }
+
+        let data: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+        parse_recipe_results(data)
+    }
+
+    /// Query parameters for `/recipes/complexSearch`, including any dietary filters.
+    fn recipe_search_params(&self, query: &str, filters: &RecipeFilters) -> Vec<(&'static str, String)> {
         // Convert parameters to String
         let true_str = "true".to_string();
         let one_str = "1".to_string();
@@ -102,9 +380,39 @@ impl SpoonacularClient {
             params.push(("query", query.to_string()));
         }
 
+        if let Some(diet) = &filters.diet {
+            params.push(("diet", diet.clone()));
+        }
+        if !filters.intolerances.is_empty() {
+            params.push(("intolerances", filters.intolerances.join(",")));
+        }
+
         // Add sorting by relevance and popularity
         params.push(("sort", "popularity".to_string()));
         params.push(("sortDirection", "desc".to_string()));
+        params
+    }
+
+    /// Generates a plan of 1-7 days around `target_calories` per day.
+    pub async fn generate_meal_plan(&self, days: usize, target_calories: u32, diet: Option<&str>) -> Result<MealPlan, String> {
+        if !(1..=WEEK_DAYS.len()).contains(&days) {
+            return Err(format!("Meal plans can cover 1 to {} days", WEEK_DAYS.len()));
+        }
+
+        let client = reqwest::Client::new();
+        let url = format!("{}/mealplanner/generate", self.base_url);
+        let time_frame = if days == 1 { "day" } else { "week" };
+
+        let diet = diet.map(parse_diet).transpose()?;
+
+        let mut params = vec![
+            ("apiKey", self.api_key.clone()),
+            ("timeFrame", time_frame.to_string()),
+            ("targetCalories", target_calories.to_string()),
+        ];
+        if let Some(diet) = &diet {
+            params.push(("diet", diet.clone()));
+        }
 
         let response = client
             .get(&url)
@@ -113,8 +421,13 @@ impl SpoonacularClient {
             .await
             .map_err(|e| format!("Failed to send request: {}", e))?;
 
-        if !response.status().is_success() {
-            return Err(format!("API request failed with status: {}", response.status()));
+        // Spoonacular answers 402 once the daily points are spent and 429 when throttled
+        let status = response.status();
+        if status == reqwest::StatusCode::PAYMENT_REQUIRED || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(QUOTA_EXCEEDED_MESSAGE.to_string());
+        }
+        if !status.is_success() {
+            return Err(format!("API request failed with status: {}", status));
         }
 
         let data: serde_json::Value = response
@@ -122,105 +435,257 @@ impl SpoonacularClient {
             .await
             .map_err(|e| format!("Failed to parse response: {}", e))?;
 
-        if let Some(results) = data.get("results").and_then(|r| r.as_array()) {
-            if let Some(recipe) = results.first() {
-                let mut info = String::new();
-                
-                // Basic recipe information
-                if let Some(title) = recipe.get("title").and_then(|t| t.as_str()) {
-                    info.push_str(&format!("🍳 Recipe: {}\n\n", title));
-                }
+        MealPlan::from_response(data, days, target_calories, diet.as_deref())
+    }
+}
 
-                // Ready time and servings
-                if let (Some(time), Some(servings)) = (
-                    recipe.get("readyInMinutes").and_then(|t| t.as_i64()),
-                    recipe.get("servings").and_then(|s| s.as_i64())
-                ) {
-                    info.push_str(&format!("⏱️ Ready in: {} minutes\n👥 Servings: {}\n\n", time, servings));
-                }
+/// Takes the first hit from a `complexSearch` response.
+fn parse_recipe_results(data: serde_json::Value) -> Result<Option<Recipe>, String> {
+    let first = match data.get("results").and_then(|r| r.as_array()).and_then(|r| r.first()) {
+        Some(first) => first.clone(),
+        None => return Ok(None),
+    };
+    serde_json::from_value(first)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse recipe: {}", e))
+}
 
-                // Cuisine and dish type information
-                if let Some(cuisines) = recipe.get("cuisines").and_then(|c| c.as_array()) {
-                    if !cuisines.is_empty() {
-                        info.push_str("🌍 Cuisine: ");
-                        info.push_str(&cuisines.iter()
-                            .filter_map(|c| c.as_str())
-                            .collect::<Vec<_>>()
-                            .join(", "));
-                        info.push_str("\n");
-                    }
-                }
+/// Renders a recipe the way the CLI shows it.
+pub fn format_recipe(recipe: &Recipe) -> String {
+    let mut info = String::new();
 
-                if let Some(dish_types) = recipe.get("dishTypes").and_then(|d| d.as_array()) {
-                    if !dish_types.is_empty() {
-                        info.push_str("🍽️ Type: ");
-                        info.push_str(&dish_types.iter()
-                            .filter_map(|d| d.as_str())
-                            .collect::<Vec<_>>()
-                            .join(", "));
-                        info.push_str("\n\n");
-                    }
-                }
+    // Basic recipe information
+    info.push_str(&format!("🍳 Recipe: {}\n\n", recipe.title));
 
-                // Main ingredients with amounts
-                if let Some(ingredients) = recipe.get("extendedIngredients").and_then(|i| i.as_array()) {
-                    info.push_str("📝 Ingredients:\n");
-                    for ingredient in ingredients {
-                        if let (Some(amount), Some(unit), Some(name), Some(original)) = (
-                            ingredient.get("amount").and_then(|a| a.as_f64()),
-                            ingredient.get("unit").and_then(|u| u.as_str()),
-                            ingredient.get("name").and_then(|n| n.as_str()),
-                            ingredient.get("original").and_then(|o| o.as_str()),
-                        ) {
-                            info.push_str(&format!("• {:.1} {} {} ({})\n", amount, unit, name, original));
-                        }
-                    }
-                    info.push_str("\n");
-                }
+    // Ready time and servings
+    if let (Some(time), Some(servings)) = (recipe.ready_in_minutes, recipe.servings) {
+        info.push_str(&format!("⏱️ Ready in: {} minutes\n👥 Servings: {}\n\n", time, servings));
+    }
 
-                // Cooking instructions with steps
-                if let Some(instructions) = recipe.get("analyzedInstructions").and_then(|i| i.as_array()) {
-                    info.push_str("📋 Instructions:\n");
-                    for section in instructions {
-                        if let Some(steps) = section.get("steps").and_then(|s| s.as_array()) {
-                            for (i, step) in steps.iter().enumerate() {
-                                if let Some(step_text) = step.get("step").and_then(|s| s.as_str()) {
-                                    info.push_str(&format!("{}. {}\n", i + 1, step_text));
-                                }
-                            }
-                        }
-                    }
-                    info.push_str("\n");
-                }
+    // Cuisine and dish type information
+    if !recipe.cuisines.is_empty() {
+        info.push_str(&format!("🌍 Cuisine: {}\n", recipe.cuisines.join(", ")));
+    }
+    if !recipe.dish_types.is_empty() {
+        info.push_str(&format!("🍽️ Type: {}\n\n", recipe.dish_types.join(", ")));
+    }
 
-                // Nutrition information per serving
-                if let Some(nutrition) = recipe.get("nutrition") {
-                    info.push_str("🥗 Nutrition Facts (per serving):\n");
-                    let important_nutrients = [
-                        "Calories", "Protein", "Fat", "Carbohydrates",
-                        "Fiber", "Sugar", "Sodium", "Cholesterol"
-                    ];
-                    
-                    if let Some(nutrients) = nutrition.get("nutrients").and_then(|n| n.as_array()) {
-                        for &nutrient_name in &important_nutrients {
-                            if let Some(nutrient) = nutrients.iter().find(|n| {
-                                n.get("name").and_then(|name| name.as_str()) == Some(nutrient_name)
-                            }) {
-                                if let (Some(amount), Some(unit)) = (
-                                    nutrient.get("amount").and_then(|a| a.as_f64()),
-                                    nutrient.get("unit").and_then(|u| u.as_str()),
-                                ) {
-                                    info.push_str(&format!("• {}: {:.1} {}\n", nutrient_name, amount, unit));
-                                }
-                            }
-                        }
-                    }
-                }
+    // Main ingredients with amounts
+    if let Some(ingredients) = &recipe.extended_ingredients {
+        info.push_str("📝 Ingredients:\n");
+        for ingredient in ingredients {
+            match &ingredient.original {
+                Some(original) => info.push_str(&format!(
+                    "• {:.1} {} {} ({})\n", ingredient.amount, ingredient.unit, ingredient.name, original
+                )),
+                None => info.push_str(&format!(
+                    "• {:.1} {} {}\n", ingredient.amount, ingredient.unit, ingredient.name
+                )),
+            }
+        }
+        info.push('\n');
+    }
+
+    // Cooking instructions with steps
+    if !recipe.analyzed_instructions.is_empty() {
+        info.push_str("📋 Instructions:\n");
+        for section in &recipe.analyzed_instructions {
+            for (i, step) in section.steps.iter().enumerate() {
+                info.push_str(&format!("{}. {}\n", i + 1, step.step));
+            }
+        }
+        info.push('\n');
+    }
 
-                return Ok(info);
+    // Nutrition information per serving
+    if recipe.nutrition.is_some() {
+        info.push_str("🥗 Nutrition Facts (per serving):\n");
+        let important_nutrients = [
+            "Calories", "Protein", "Fat", "Carbohydrates",
+            "Fiber", "Sugar", "Sodium", "Cholesterol"
+        ];
+        for nutrient_name in important_nutrients {
+            if let Some(nutrient) = recipe.nutrient(nutrient_name) {
+                info.push_str(&format!("• {}: {:.1} {}\n", nutrient_name, nutrient.amount, nutrient.unit));
             }
         }
+    }
+
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param<'a>(params: &'a [(&'static str, String)], name: &str) -> Option<&'a str> {
+        params.iter().find(|(key, _)| *key == name).map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn test_recipe_params_without_filters() {
+        let client = SpoonacularClient::new("key".to_string());
+        let params = client.recipe_search_params("pasta", &RecipeFilters::default());
+        assert_eq!(param(&params, "query"), Some("pasta"));
+        assert_eq!(param(&params, "diet"), None);
+        assert_eq!(param(&params, "intolerances"), None);
+    }
+
+    #[test]
+    fn test_recipe_params_with_filters() {
+        let client = SpoonacularClient::new("key".to_string());
+
+        let filters = RecipeFilters::new(Some("Keto"), &[]).unwrap();
+        let params = client.recipe_search_params("thai curry", &filters);
+        assert_eq!(param(&params, "diet"), Some("ketogenic"));
+        assert_eq!(param(&params, "cuisine"), Some("thai"));
+        assert_eq!(param(&params, "intolerances"), None);
+
+        let filters = RecipeFilters::new(None, &["gluten", "peanuts", "tree_nut", "gluten"]).unwrap();
+        let params = client.recipe_search_params("cake", &filters);
+        assert_eq!(param(&params, "diet"), None);
+        assert_eq!(param(&params, "intolerances"), Some("gluten,peanut,tree nut"));
+
+        let filters = RecipeFilters::new(Some("gluten-free"), &["dairy"]).unwrap();
+        let params = client.recipe_search_params("bread", &filters);
+        assert_eq!(param(&params, "diet"), Some("gluten free"));
+        assert_eq!(param(&params, "intolerances"), Some("dairy"));
+    }
+
+    #[test]
+    fn test_rejects_unknown_filters() {
+        let err = RecipeFilters::new(Some("vegn"), &[]).unwrap_err();
+        assert!(err.contains("Unknown diet 'vegn'"));
+        assert!(err.contains("vegan"));
+        assert!(RecipeFilters::new(None, &["chocolate"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_repeated_search_is_served_from_cache() {
+        use axum::{routing::get, Json, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let hits_ref = hits.clone();
+        let app = Router::new().route("/recipes/complexSearch", get(move || async move {
+            hits_ref.fetch_add(1, Ordering::SeqCst);
+            Json(serde_json::json!({
+                "results": [{"id": 1, "title": "Pad Thai", "readyInMinutes": 30, "servings": 2}]
+            }))
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let cache_dir = std::env::temp_dir().join(format!("food_cache_{}", uuid::Uuid::new_v4()));
+        let client = SpoonacularClient::new("key".to_string())
+            .with_base_url(format!("http://{}", addr))
+            .with_cache(ResponseCache::with_dir(&cache_dir, std::time::Duration::from_secs(60)));
+
+        let first = client.search_recipe("Pad Thai", &RecipeFilters::default()).await.unwrap();
+        let second = client.search_recipe("  pad   thai ", &RecipeFilters::default()).await.unwrap();
+        assert_eq!(first.unwrap().title, "Pad Thai");
+        assert_eq!(second.unwrap().title, "Pad Thai");
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // Different filters are a different cache entry
+        let vegan = RecipeFilters::new(Some("vegan"), &[]).unwrap();
+        client.search_recipe("pad thai", &vegan).await.unwrap();
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        let _ = std::fs::remove_dir_all(cache_dir);
+    }
+
+    #[test]
+    fn test_parses_recipe_search_response() {
+        let sample = serde_json::json!({
+            "results": [{
+                "id": 715538,
+                "title": "Pad Thai",
+                "readyInMinutes": 30,
+                "servings": 2,
+                "sourceUrl": "https://example.com/pad-thai",
+                "cuisines": ["Thai", "Asian"],
+                "dishTypes": ["main course"],
+                "extendedIngredients": [
+                    {"id": 1, "name": "rice noodles", "amount": 200.0, "unit": "g", "original": "200g rice noodles"},
+                    {"id": 2, "name": "peanuts", "amount": 0.25, "unit": "cup"}
+                ],
+                "analyzedInstructions": [{"name": "", "steps": [
+                    {"number": 1, "step": "Soak the noodles."},
+                    {"number": 2, "step": "Stir-fry everything."}
+                ]}],
+                "nutrition": {"nutrients": [
+                    {"name": "Calories", "amount": 512.3, "unit": "kcal", "percentOfDailyNeeds": 25.6},
+                    {"name": "Protein", "amount": 18.0, "unit": "g"}
+                ]}
+            }],
+            "offset": 0,
+            "number": 1,
+            "totalResults": 42
+        });
+
+        let recipe = parse_recipe_results(sample).unwrap().unwrap();
+        assert_eq!(recipe.title, "Pad Thai");
+        assert_eq!(recipe.ready_in_minutes, Some(30));
+        assert_eq!(recipe.cuisines, vec!["Thai", "Asian"]);
+        assert_eq!(recipe.extended_ingredients.as_ref().unwrap()[1].original, None);
+        assert_eq!(recipe.analyzed_instructions[0].steps[1].step, "Stir-fry everything.");
+        assert_eq!(recipe.nutrient("calories").unwrap().amount, 512.3);
 
-        Ok(format!("No recipe found for '{}'. Try:\n1. Check your spelling\n2. Use a more common name\n3. Try a different variation (e.g., 'nasi goreng' for 'indonesian fried rice')\n4. Specify the cuisine type (e.g., 'japanese ramen')", query))
+        let formatted = format_recipe(&recipe);
+        assert!(formatted.contains("🍳 Recipe: Pad Thai"));
+        assert!(formatted.contains("• Calories: 512.3 kcal"));
+
+        assert!(parse_recipe_results(serde_json::json!({"results": []})).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parses_week_meal_plan() {
+        let sample = serde_json::json!({
+            "week": {
+                "monday": {
+                    "meals": [
+                        {"id": 1, "imageType": "jpg", "title": "Oatmeal", "readyInMinutes": 10, "servings": 1, "sourceUrl": "https://example.com/oatmeal"},
+                        {"id": 2, "imageType": "jpg", "title": "Chicken Salad", "readyInMinutes": 20, "servings": 2, "sourceUrl": "https://example.com/salad"}
+                    ],
+                    "nutrients": {"calories": 1980.5, "protein": 110.0, "fat": 70.2, "carbohydrates": 220.0}
+                },
+                "tuesday": {
+                    "meals": [
+                        {"id": 3, "imageType": "jpg", "title": "Lentil Soup", "readyInMinutes": 45, "servings": 4, "sourceUrl": "https://example.com/soup"}
+                    ],
+                    "nutrients": {"calories": 2019.5, "protein": 90.0, "fat": 60.0, "carbohydrates": 250.0}
+                },
+                "wednesday": {
+                    "meals": [],
+                    "nutrients": {"calories": 2000.0, "protein": 100.0, "fat": 65.0, "carbohydrates": 230.0}
+                }
+            }
+        });
+
+        let plan = MealPlan::from_response(sample, 2, 2000, Some("vegetarian")).unwrap();
+        assert_eq!(plan.days.len(), 2);
+        assert_eq!(plan.days[0].day, "monday");
+        assert_eq!(plan.days[0].meals[1].title, "Chicken Salad");
+        assert_eq!(plan.days[1].meals[0].ready_in_minutes, Some(45));
+        assert_eq!(plan.totals.calories, 4000.0);
+        assert_eq!(plan.totals.protein, 200.0);
+        assert_eq!(plan.average_calories(), 2000.0);
+        assert_eq!(plan.diet.as_deref(), Some("vegetarian"));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_parses_single_day_meal_plan() {
+        let sample = serde_json::json!({
+            "meals": [{"id": 7, "title": "Pancakes", "readyInMinutes": 15, "servings": 2, "sourceUrl": null}],
+            "nutrients": {"calories": 1800.0, "protein": 60.0, "fat": 50.0, "carbohydrates": 260.0}
+        });
+
+        let plan = MealPlan::from_response(sample, 1, 1800, None).unwrap();
+        assert_eq!(plan.days.len(), 1);
+        assert_eq!(plan.totals.carbohydrates, 260.0);
+        assert!(MealPlan::from_response(serde_json::json!({"week": {}}), 3, 1800, None).is_err());
+    }
+}
@@ -1,7 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+use super::spoonacular::Nutrient;
+use crate::food::cache::ResponseCache;
+use crate::food::matching;
+
+const CACHE_CLIENT: &str = "usda_food";
+
+/// Nutrition for one food, or for a dish broken down into `components`.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FoodNutrition {
+    pub name: String,
+    /// The USDA description of the matched food
+    pub description: Option<String>,
+    pub nutrients: Vec<Nutrient>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<ComponentNutrition>,
+}
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentNutrition {
+    pub ingredient: String,
+    pub nutrition: Option<FoodNutrition>,
+    pub error: Option<String>,
+}
+
+impl FoodNutrition {
+    pub fn nutrient(&self, name: &str) -> Option<&Nutrient> {
+        self.nutrients.iter().find(|n| n.name.eq_ignore_ascii_case(name))
+    }
+}
+
 #[derive(Debug)]
 pub struct UsdaClient {
     api_key: String,
     base_url: String,
+    cache: ResponseCache,
 }
 
 // Common recipe ingredients mapping
@@ -13,53 +48,103 @@ const RECIPE_COMPONENTS: &[(&str, &[&str])] = &[
 
 impl UsdaClient {
     pub fn new(config: crate::food::config::FoodConfig) -> Self {
+        Self::with_api_key(config.usda_api_key)
+    }
+
+    pub fn with_api_key(api_key: String) -> Self {
         Self {
-            api_key: config.usda_api_key,
+            api_key,
             base_url: "https://api.nal.usda.gov/fdc/v1".to_string(),
+            cache: ResponseCache::new(),
         }
     }
 
-    pub async fn search_food(&self, query: &str) -> Result<String, String> {
-        // Check if this is a complex dish that needs to be broken down
-        if let Some(components) = RECIPE_COMPONENTS.iter().find(|(dish, _)| query.to_lowercase().contains(&dish.to_lowercase())) {
-            return self.analyze_recipe_components(components.0, components.1).await;
-        }
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = cache;
+        self
+    }
 
-        // Original single ingredient search
-        self.search_single_food(query).await
+    #[cfg(test)]
+    pub(crate) fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
     }
 
-    async fn analyze_recipe_components(&self, dish_name: &str, components: &[&str]) -> Result<String, String> {
-        let mut combined_info = format!("Food: {} (Recipe Breakdown)\n\nIngredient Analysis:\n", dish_name);
-        
-        for ingredient in components {
-            match self.search_single_food(ingredient).await {
-                Ok(info) => {
-                    combined_info.push_str(&format!("\n=== {} ===\n{}\n", ingredient, info));
-                }
-                Err(e) => {
-                    combined_info.push_str(&format!("\n=== {} ===\nCould not find data: {}\n", ingredient, e));
-                }
+    /// Looks up nutrition for a food, breaking known dishes into their ingredients.
+    /// Returns `None` when USDA has no match.
+    pub async fn search_food(&self, query: &str) -> Result<Option<FoodNutrition>, String> {
+        if let Some(cached) = self.cache.get(CACHE_CLIENT, query) {
+            if let Ok(nutrition) = serde_json::from_str::<FoodNutrition>(&cached) {
+                return Ok(Some(nutrition));
+            }
+        }
+
+        // Check if this is a complex dish that needs to be broken down
+        let nutrition = if let Some(components) = RECIPE_COMPONENTS.iter().find(|(dish, _)| query.to_lowercase().contains(&dish.to_lowercase())) {
+            Some(self.analyze_recipe_components(components.0, components.1).await)
+        } else {
+            // Original single ingredient search
+            self.search_single_food(query).await?
+        };
+
+        if let Some(nutrition) = &nutrition {
+            if let Ok(serialized) = serde_json::to_string(nutrition) {
+                self.cache.put(CACHE_CLIENT, query, &serialized);
             }
         }
+        Ok(nutrition)
+    }
+
+    async fn analyze_recipe_components(&self, dish_name: &str, components: &[&str]) -> FoodNutrition {
+        // Look the ingredients up concurrently; results keep the component order
+        let lookups = futures::future::join_all(
+            components.iter().map(|ingredient| self.search_single_food(ingredient))
+        ).await;
+
+        let components = components.iter()
+            .zip(lookups)
+            .map(|(ingredient, lookup)| match lookup {
+                Ok(Some(nutrition)) => ComponentNutrition {
+                    ingredient: ingredient.to_string(),
+                    nutrition: Some(nutrition),
+                    error: None,
+                },
+                Ok(None) => ComponentNutrition {
+                    ingredient: ingredient.to_string(),
+                    nutrition: None,
+                    error: Some(format!("No nutrition data found for '{}'", ingredient)),
+                },
+                Err(e) => ComponentNutrition {
+                    ingredient: ingredient.to_string(),
+                    nutrition: None,
+                    error: Some(e),
+                },
+            })
+            .collect();
 
-        Ok(combined_info)
+        FoodNutrition {
+            name: dish_name.to_string(),
+            description: None,
+            nutrients: Vec::new(),
+            components,
+        }
     }
 
-    async fn search_single_food(&self, query: &str) -> Result<String, String> {
+    async fn search_single_food(&self, query: &str) -> Result<Option<FoodNutrition>, String> {
         let client = reqwest::Client::new();
         let url = format!("{}/foods/search", self.base_url);
+        let search_query = matching::canonical_query(query);
         
         // Try different data types to get better results
         let data_types = ["Survey (FNDDS)", "Foundation", "SR Legacy"];
-        let mut best_match: Option<(String, serde_json::Value)> = None;
+        let mut foods = Vec::new();
         
         for data_type in data_types.iter() {
             let response = client
                 .get(&url)
                 .query(&[
                     ("api_key", &self.api_key),
-                    ("query", &query.to_string()),
+                    ("query", &search_query),
                     ("dataType", &data_type.to_string()),
                     ("pageSize", &"10".to_string()),
                 ])
@@ -76,58 +161,149 @@ impl UsdaClient {
                 .await
                 .map_err(|e| format!("Failed to parse response: {}", e))?;
 
-            if let Some(foods) = data.get("foods").and_then(|f| f.as_array()) {
-                for food in foods {
-                    if let Some(description) = food.get("description").and_then(|d| d.as_str()) {
-                        let is_better_match = match &best_match {
-                            None => true,
-                            Some((curr_desc, _)) => {
-                                let curr_score = string_similarity(curr_desc, query);
-                                let new_score = string_similarity(description, query);
-                                new_score > curr_score
-                            }
-                        };
-
-                        if is_better_match {
-                            best_match = Some((description.to_string(), food.clone()));
-                        }
-                    }
-                }
+            if let Some(results) = data.get("foods").and_then(|f| f.as_array()) {
+                foods.extend(results.iter().cloned());
             }
         }
 
-        if let Some((_description, food)) = best_match {
-            let mut nutrition_info = String::new();
-            
-            if let Some(nutrients) = food.get("foodNutrients").and_then(|n| n.as_array()) {
-                for nutrient in nutrients {
-                    if let (Some(name), Some(amount), Some(unit)) = (
-                        nutrient.get("nutrientName").and_then(|n| n.as_str()),
-                        nutrient.get("value").and_then(|v| v.as_f64()),
-                        nutrient.get("unitName").and_then(|u| u.as_str()),
-                    ) {
-                        nutrition_info.push_str(&format!("- {}: {:.1} {}\n", name, amount, unit));
-                    }
+        Ok(best_food_match(query, &foods))
+    }
+}
+
+/// Picks the search result whose description best matches `query` and reads its nutrients.
+fn best_food_match(query: &str, foods: &[serde_json::Value]) -> Option<FoodNutrition> {
+    let mut best_match: Option<(&str, &serde_json::Value)> = None;
+
+    for food in foods {
+        if let Some(description) = food.get("description").and_then(|d| d.as_str()) {
+            let is_better_match = match &best_match {
+                None => true,
+                Some((curr_desc, _)) => {
+                    let curr_score = matching::similarity(curr_desc, query);
+                    let new_score = matching::similarity(description, query);
+                    new_score > curr_score
                 }
-            }
+            };
 
-            return Ok(nutrition_info);
+            if is_better_match {
+                best_match = Some((description, food));
+            }
         }
+    }
+
+    let (description, food) = best_match?;
+    let nutrients = food.get("foodNutrients")
+        .and_then(|n| n.as_array())
+        .map(|nutrients| {
+            nutrients.iter()
+                .filter_map(|nutrient| Some(Nutrient {
+                    name: nutrient.get("nutrientName")?.as_str()?.to_string(),
+                    amount: nutrient.get("value")?.as_f64()?,
+                    unit: nutrient.get("unitName")?.as_str()?.to_string(),
+                }))
+                .collect()
+        })
+        .unwrap_or_default();
 
-        Ok(format!("No nutrition data found for '{}'", query))
+    Some(FoodNutrition {
+        name: query.to_string(),
+        description: Some(description.to_string()),
+        nutrients,
+        components: Vec::new(),
+    })
+}
+
+/// Renders nutrition the way the CLI shows it.
+pub fn format_food_nutrition(nutrition: &FoodNutrition) -> String {
+    let list_nutrients = |nutrients: &[Nutrient]| {
+        nutrients.iter()
+            .map(|n| format!("- {}: {:.1} {}\n", n.name, n.amount, n.unit))
+            .collect::<String>()
+    };
+
+    if nutrition.components.is_empty() {
+        return list_nutrients(&nutrition.nutrients);
+    }
+
+    let mut combined_info = format!("Food: {} (Recipe Breakdown)\n\nIngredient Analysis:\n", nutrition.name);
+    for component in &nutrition.components {
+        match (&component.nutrition, &component.error) {
+            (Some(info), _) => {
+                combined_info.push_str(&format!("\n=== {} ===\n{}\n", component.ingredient, list_nutrients(&info.nutrients)));
+            }
+            (None, error) => {
+                combined_info.push_str(&format!(
+                    "\n=== {} ===\nCould not find data: {}\n",
+                    component.ingredient,
+                    error.as_deref().unwrap_or("unknown error")
+                ));
+            }
+        }
     }
+    combined_info
 }
 
-fn string_similarity(s1: &str, s2: &str) -> f64 {
-    let s1_lower = s1.to_lowercase();
-    let s2_lower = s2.to_lowercase();
-    
-    let s1_words: Vec<&str> = s1_lower.split_whitespace().collect();
-    let s2_words: Vec<&str> = s2_lower.split_whitespace().collect();
-    
-    let matches = s1_words.iter()
-        .filter(|w| s2_words.contains(w))
-        .count();
-    
-    matches as f64 / s1_words.len().max(s2_words.len()) as f64
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_best_food_match_parses_nutrients() {
+        let foods = serde_json::json!([
+            {
+                "description": "Bread, white, commercially prepared",
+                "foodNutrients": [{"nutrientName": "Energy", "value": 266.0, "unitName": "KCAL"}]
+            },
+            {
+                "description": "Banana, raw",
+                "foodNutrients": [
+                    {"nutrientName": "Energy", "value": 89.0, "unitName": "KCAL"},
+                    {"nutrientName": "Protein", "value": 1.09, "unitName": "G"},
+                    {"nutrientName": "Potassium, K", "unitName": "MG"}
+                ]
+            }
+        ]);
+
+        let nutrition = best_food_match("raw banana", foods.as_array().unwrap()).unwrap();
+        assert_eq!(nutrition.description.as_deref(), Some("Banana, raw"));
+        assert_eq!(nutrition.nutrients.len(), 2);
+        assert_eq!(nutrition.nutrient("energy").unwrap().amount, 89.0);
+        assert!(format_food_nutrition(&nutrition).contains("- Protein: 1.1 G"));
+        assert!(best_food_match("banana", &[]).is_none());
+    }
+
+    #[test]
+    fn test_best_food_match_tolerates_typos_and_synonyms() {
+        let foods = serde_json::json!([
+            {"description": "Squash, summer, raw"},
+            {"description": "Eggplant, raw"},
+            {"description": "Eggplant, pickled"}
+        ]);
+        let foods = foods.as_array().unwrap();
+
+        let nutrition = best_food_match("aubergine", foods).unwrap();
+        assert_eq!(nutrition.description.as_deref(), Some("Eggplant, raw"));
+        assert_eq!(nutrition.name, "aubergine");
+
+        let nutrition = best_food_match("eggplnt pickeld", foods).unwrap();
+        assert_eq!(nutrition.description.as_deref(), Some("Eggplant, pickled"));
+    }
+
+    #[tokio::test]
+    async fn test_single_food_search_uses_canonical_name() {
+        use axum::{extract::Query, routing::get, Json, Router};
+        use std::collections::HashMap;
+
+        let app = Router::new().route("/foods/search", get(|Query(params): Query<HashMap<String, String>>| async move {
+            let description = if params.get("query").map(String::as_str) == Some("eggplant") { "Eggplant, raw" } else { "Unrelated" };
+            Json(serde_json::json!({"foods": [{"description": description}]}))
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = UsdaClient::with_api_key("key".to_string()).with_base_url(format!("http://{}", addr));
+        let nutrition = client.search_single_food("Aubergine").await.unwrap().unwrap();
+        assert_eq!(nutrition.description.as_deref(), Some("Eggplant, raw"));
+    }
+}
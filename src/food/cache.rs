@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_CACHE_DIR: &str = "data/food_cache";
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    query: String,
+    created_at: DateTime<Utc>,
+    value: String,
+}
+
+/// On-disk cache of food API responses, one JSON file per client and normalized query.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    /// Uses `data/food_cache` with a TTL from `FOOD_CACHE_TTL_SECS` (default 24h).
+    pub fn new() -> Self {
+        let ttl = std::env::var("FOOD_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TTL);
+        Self::with_dir(DEFAULT_CACHE_DIR, ttl)
+    }
+
+    pub fn with_dir(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self { dir: dir.into(), ttl }
+    }
+
+    /// Returns the cached response if it is younger than the TTL.
+    pub fn get(&self, client: &str, query: &str) -> Option<String> {
+        let query = normalize_query(query);
+        let entry = std::fs::read_to_string(self.entry_path(client, &query))
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheEntry>(&content).ok())
+            .filter(|entry| entry.query == query);
+
+        let fresh = entry.filter(|entry| {
+            Utc::now().signed_duration_since(entry.created_at)
+                .to_std()
+                .ok()
+                .is_none_or(|age| age < self.ttl)
+        });
+
+        match fresh {
+            Some(entry) => {
+                println!("📦 Cache hit ({}): {}", client, query);
+                Some(entry.value)
+            }
+            None => {
+                println!("🌐 Cache miss ({}): {}", client, query);
+                None
+            }
+        }
+    }
+
+    pub fn put(&self, client: &str, query: &str, value: &str) {
+        let query = normalize_query(query);
+        let entry = CacheEntry {
+            query: query.clone(),
+            created_at: Utc::now(),
+            value: value.to_string(),
+        };
+
+        let result = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| serde_json::to_string(&entry).map_err(std::io::Error::from))
+            .and_then(|content| std::fs::write(self.entry_path(client, &query), content));
+        if let Err(e) = result {
+            eprintln!("Warning: Failed to write food cache entry: {}", e);
+        }
+    }
+
+    fn entry_path(&self, client: &str, normalized_query: &str) -> PathBuf {
+        let digest = Sha256::digest(normalized_query.as_bytes());
+        let hash: String = digest.iter().take(16).map(|b| format!("{:02x}", b)).collect();
+        self.dir.join(format!("{}_{}.json", client, hash))
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lowercases and collapses whitespace so "Pad  Thai" and "pad thai" share an entry.
+fn normalize_query(query: &str) -> String {
+    query.split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
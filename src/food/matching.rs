@@ -0,0 +1,103 @@
+use strsim::{jaro_winkler, normalized_levenshtein};
+
+/// Regional food names mapped to the names USDA and Spoonacular index them under.
+const SYNONYMS: &[(&str, &str)] = &[
+    ("aubergine", "eggplant"),
+    ("courgette", "zucchini"),
+    ("coriander leaves", "cilantro"),
+    ("rocket", "arugula"),
+    ("capsicum", "bell pepper"),
+    ("spring onion", "green onion"),
+    ("scallion", "green onion"),
+    ("prawn", "shrimp"),
+    ("prawns", "shrimp"),
+    ("beetroot", "beets"),
+    ("swede", "rutabaga"),
+    ("mangetout", "snow peas"),
+    ("garbanzo", "chickpeas"),
+    ("maize", "corn"),
+    ("cornflour", "cornstarch"),
+    ("icing sugar", "powdered sugar"),
+    ("double cream", "heavy cream"),
+    ("minced beef", "ground beef"),
+    ("beef mince", "ground beef"),
+    ("porridge", "oatmeal"),
+    ("crisps", "potato chips"),
+    ("biscuit", "cookie"),
+];
+
+fn tokens(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Lowercases `query` and swaps regional names for their canonical ones,
+/// e.g. "Aubergine parmigiana" becomes "eggplant parmigiana".
+pub fn canonical_query(query: &str) -> String {
+    let mut words = tokens(query);
+
+    // Longer phrases first so "beef mince" wins over any single-word entry
+    let mut synonyms: Vec<_> = SYNONYMS.iter().collect();
+    synonyms.sort_by_key(|(alias, _)| std::cmp::Reverse(alias.split(' ').count()));
+
+    for (alias, canonical) in synonyms {
+        let alias: Vec<&str> = alias.split(' ').collect();
+        let mut i = 0;
+        while i + alias.len() <= words.len() {
+            if words[i..i + alias.len()].iter().zip(&alias).all(|(w, a)| w == a) {
+                words.splice(i..i + alias.len(), [canonical.to_string()]);
+            }
+            i += 1;
+        }
+    }
+    words.join(" ")
+}
+
+/// How well a food description matches a query, from 0.0 to 1.0.
+///
+/// Each query word is matched against its closest description word with Jaro-Winkler,
+/// so typos and word order matter little; the whole-string Levenshtein term breaks ties
+/// in favour of descriptions without extra words.
+pub fn similarity(description: &str, query: &str) -> f64 {
+    let query = canonical_query(query);
+    let description = canonical_query(description);
+    let query_tokens: Vec<&str> = query.split(' ').filter(|t| !t.is_empty()).collect();
+    let description_tokens: Vec<&str> = description.split(' ').filter(|t| !t.is_empty()).collect();
+    if query_tokens.is_empty() || description_tokens.is_empty() {
+        return 0.0;
+    }
+
+    let token_score = query_tokens.iter()
+        .map(|q| description_tokens.iter().map(|d| jaro_winkler(q, d)).fold(0.0, f64::max))
+        .sum::<f64>() / query_tokens.len() as f64;
+
+    0.8 * token_score + 0.2 * normalized_levenshtein(&query, &description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_query_applies_synonyms() {
+        assert_eq!(canonical_query("Aubergine"), "eggplant");
+        assert_eq!(canonical_query("grilled  Aubergine, sliced"), "grilled eggplant sliced");
+        assert_eq!(canonical_query("beef mince pie"), "ground beef pie");
+        assert_eq!(canonical_query("spring onion pancake"), "green onion pancake");
+        assert_eq!(canonical_query("banana"), "banana");
+    }
+
+    #[test]
+    fn test_similarity_ranks_near_misses() {
+        let query = "bananna raw";
+        let best = similarity("Bananas, raw", query);
+        assert!(best > similarity("Bread, banana", query));
+        assert!(best > similarity("Apples, raw, with skin", query));
+
+        assert!(similarity("Zucchini, raw", "courgette") > similarity("Cucumber, raw", "courgette"));
+        assert!(similarity("Eggplant, raw", "aubergine") > 0.9);
+    }
+}
@@ -1,4 +1,6 @@
 pub mod config;
 pub mod api;
+pub mod cache;
+pub mod matching;
 pub mod analysis;
 pub mod data;
@@ -0,0 +1,71 @@
+/// Stored values are JSON text (see `PUT /kb/:key`); strings read back without their quotes.
+pub fn display_value(value: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(value) {
+        Ok(serde_json::Value::String(text)) => text,
+        Ok(other) => other.to_string(),
+        Err(_) => value.to_string(),
+    }
+}
+
+/// Lowercases and treats `_` and `-` as spaces, so `deploy_process` matches "deploy process".
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .replace(['_', '-'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Entries whose key the message mentions.
+pub fn mentioned<'a>(message: &str, entries: &'a [(String, String)]) -> Vec<&'a (String, String)> {
+    let message = normalize(message);
+    entries.iter()
+        .filter(|(key, _)| {
+            let key = normalize(key);
+            !key.is_empty() && message.contains(&key)
+        })
+        .collect()
+}
+
+/// Prepends the entries the message mentions, or returns it unchanged when there are none.
+pub fn with_knowledge_context(message: &str, entries: &[(String, String)]) -> String {
+    let matching: Vec<String> = mentioned(message, entries).into_iter()
+        .map(|(key, value)| format!("- {}: {}", key, display_value(value)))
+        .collect();
+
+    if matching.is_empty() {
+        return message.to_string();
+    }
+
+    format!(
+        "Relevant knowledge:\n{}\n\nUser message: {}",
+        matching.join("\n"),
+        message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mentioned_keys_are_injected() {
+        let entries = vec![
+            ("deploy_process".to_string(), "\"Merge to main, then run ./deploy.sh\"".to_string()),
+            ("on-call".to_string(), "{\"week\":12}".to_string()),
+            ("billing".to_string(), "legacy plain text".to_string()),
+        ];
+
+        let prompt = with_knowledge_context("What's our Deploy Process?", &entries);
+        assert_eq!(
+            prompt,
+            "Relevant knowledge:\n- deploy_process: Merge to main, then run ./deploy.sh\n\nUser message: What's our Deploy Process?"
+        );
+
+        let prompt = with_knowledge_context("who is on call, and billing?", &entries);
+        assert!(prompt.contains("- on-call: {\"week\":12}"));
+        assert!(prompt.contains("- billing: legacy plain text"));
+
+        assert_eq!(with_knowledge_context("hello", &entries), "hello");
+    }
+}
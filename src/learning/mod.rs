@@ -1,11 +1,21 @@
 use crate::database::Database;
 use crate::knowledge_base::knowledge_base::KnowledgeBaseHandler;
-use log::info;
+use crate::providers::traits::CompletionProvider;
+use log::{info, warn};
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Knowledge base keys of learned facts start with this.
+pub const FACT_PREFIX: &str = "fact:";
+/// Qdrant collection holding fact embeddings, when Qdrant is available.
+const FACTS_COLLECTION: &str = "learned_facts";
+/// Below this normalized similarity a fact is not considered about the topic.
+const FACT_MIN_SCORE: f32 = 0.75;
+/// Facts prepended to a chat prompt when `ENABLE_LEARNED_CONTEXT` is on.
+pub const LEARNED_CONTEXT_LIMIT: usize = 5;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Insight {
     pub topic: String,
@@ -99,6 +109,89 @@ impl LearningContext {
     }
 }
 
+/// Where a learned fact came from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FactSource {
+    /// Stated with `learn <fact>`
+    User,
+    /// Pulled out of a conversation by the provider
+    Extracted,
+}
+
+impl std::fmt::Display for FactSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FactSource::User => write!(f, "user"),
+            FactSource::Extracted => write!(f, "extracted"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Fact {
+    pub text: String,
+    pub source: FactSource,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Asks the provider to list the lasting facts in one exchange.
+pub fn fact_extraction_prompt(user_input: &str, ai_response: &str) -> String {
+    format!(
+        "List the standalone facts worth remembering from this exchange, such as the user's \
+        preferences, details about them, or things they asked you to remember. Write one short, \
+        self-contained fact per line with no numbering or commentary. If there are none, reply NONE.\n\n\
+        User: {}\nAssistant: {}",
+        user_input, ai_response
+    )
+}
+
+/// The facts in a reply to [`fact_extraction_prompt`].
+pub fn parse_facts(reply: &str) -> Vec<String> {
+    reply.lines()
+        .map(|line| line.trim().trim_start_matches(['-', '*', '•']).trim())
+        .map(|line| line.trim_start_matches(|c: char| c.is_ascii_digit()).trim_start_matches(['.', ')']).trim())
+        .filter(|line| !line.is_empty() && !line.eq_ignore_ascii_case("none"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Prepends the facts as "Known facts", or returns the message unchanged when there are none.
+pub fn with_known_facts(message: &str, facts: &[Fact]) -> String {
+    if facts.is_empty() {
+        return message.to_string();
+    }
+    let lines: Vec<String> = facts.iter().map(|fact| format!("- {}", fact.text)).collect();
+    format!("Known facts:\n{}\n\n{}", lines.join("\n"), message)
+}
+
+/// `fact` in lowercase with punctuation and repeated spaces removed, for spotting duplicates.
+fn normalize_fact(fact: &str) -> String {
+    fact.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Words too common to tie a fact to a topic, since `recall` is also given whole messages.
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "are", "was", "you", "your", "user", "user's", "what", "which", "who",
+    "how", "why", "when", "where", "with", "this", "that", "have", "has", "does", "did", "should",
+    "could", "would", "can", "about", "from", "they", "their", "them", "there", "not", "but",
+    "any", "all", "some", "into", "just", "like", "tell", "know", "please",
+];
+
+/// Whether `fact` shares a word of three or more letters with `topic`.
+fn mentions_topic(fact: &str, topic: &str) -> bool {
+    let fact = fact.to_lowercase();
+    topic.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|word| word.len() >= 3 && !STOPWORDS.contains(word))
+        .any(|word| fact.contains(word))
+}
+
+#[derive(Clone)]
 pub struct LearningManager {
     db: Arc<Database>,
     knowledge_base: Arc<KnowledgeBaseHandler>,
@@ -106,9 +199,9 @@ pub struct LearningManager {
 }
 
 impl LearningManager {
-    pub fn new(db: Database, knowledge_base: KnowledgeBaseHandler) -> Self {
+    pub fn new(db: Arc<Database>, knowledge_base: KnowledgeBaseHandler) -> Self {
         Self {
-            db: Arc::new(db),
+            db,
             knowledge_base: Arc::new(knowledge_base),
             context_cache: Arc::new(Mutex::new(HashMap::new())),
         }
@@ -217,4 +310,201 @@ impl LearningManager {
 
         Ok(summary)
     }
+
+    /// Stores a fact in the knowledge base, and its embedding in Qdrant when both are
+    /// available so [`Self::recall`] can find it by meaning.
+    pub async fn learn_fact(
+        &self,
+        text: &str,
+        source: FactSource,
+        embedding: Option<Vec<f32>>,
+    ) -> Result<Fact, Box<dyn std::error::Error>> {
+        let fact = Fact {
+            text: text.trim().to_string(),
+            source,
+            timestamp: chrono::Utc::now(),
+        };
+        let key = format!("{}{}", FACT_PREFIX, uuid::Uuid::new_v4());
+        self.db.save_knowledge(key.clone(), serde_json::to_string(&fact)?).await?;
+
+        if let Some(embedding) = embedding.filter(|e| !e.is_empty()) {
+            if let Err(e) = store_fact_vector(&self.db, &key, &fact, embedding).await {
+                warn!("Saved fact without an embedding: {}", e);
+            }
+        }
+
+        Ok(fact)
+    }
+
+    /// Asks `provider` for the lasting facts in one exchange and stores those not already
+    /// known, returning them. Run after each chat turn when `auto_learn` is on.
+    pub async fn learn_from_exchange(
+        &self,
+        user_input: &str,
+        ai_response: &str,
+        provider: &(dyn CompletionProvider + Send + Sync),
+    ) -> Result<Vec<Fact>, Box<dyn std::error::Error + Send + Sync>> {
+        let reply = provider.complete(&fact_extraction_prompt(user_input, ai_response)).await
+            .map_err(|e| format!("Failed to extract facts: {}", e))?;
+        let candidates = parse_facts(&reply);
+        if candidates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut known: Vec<String> = self.facts().await
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|fact| normalize_fact(&fact.text))
+            .collect();
+        let mut learned = Vec::new();
+        for candidate in candidates {
+            let normalized = normalize_fact(&candidate);
+            if normalized.is_empty() || known.contains(&normalized) {
+                continue;
+            }
+            let fact = self.learn_fact(&candidate, FactSource::Extracted, None).await
+                .map_err(|e| e.to_string())?;
+            known.push(normalized);
+            learned.push(fact);
+        }
+        Ok(learned)
+    }
+
+    /// Every learned fact, oldest first.
+    pub async fn facts(&self) -> Result<Vec<Fact>, Box<dyn std::error::Error>> {
+        facts(&self.db).await
+    }
+
+    /// Facts about `topic`; see [`recall_facts`].
+    pub async fn recall(
+        &self,
+        topic: &str,
+        embedding: Option<Vec<f32>>,
+        limit: usize,
+    ) -> Result<Vec<Fact>, Box<dyn std::error::Error>> {
+        recall_facts(&self.db, topic, embedding, limit).await
+    }
+}
+
+/// Stores the embedding of the fact under `key`, replacing the one it had, so a corrected
+/// fact is found by its new text. Does nothing without vector storage.
+pub async fn store_fact_vector(db: &Database, key: &str, fact: &Fact, embedding: Vec<f32>) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(vector_db) = db.get_vector_db().await else {
+        return Ok(());
+    };
+    db.create_vector_collection(FACTS_COLLECTION, embedding.len() as u64).await?;
+    vector_db.delete_by_payload(FACTS_COLLECTION, "key", key).await?;
+
+    let mut payload = HashMap::new();
+    payload.insert("key".to_string(), serde_json::json!(key));
+    payload.insert("text".to_string(), serde_json::json!(fact.text));
+    payload.insert("source".to_string(), serde_json::json!(fact.source));
+    payload.insert("timestamp".to_string(), serde_json::json!(fact.timestamp.to_rfc3339()));
+    vector_db.store_vector(FACTS_COLLECTION, embedding, payload).await?;
+    Ok(())
+}
+
+/// Every fact learned into `db`, oldest first.
+async fn facts(db: &Database) -> Result<Vec<Fact>, Box<dyn std::error::Error>> {
+    let entries = db.list_knowledge(Some(FACT_PREFIX.to_string())).await?;
+    let mut facts: Vec<Fact> = entries.iter()
+        .filter_map(|(_, value)| serde_json::from_str(value).ok())
+        .collect();
+    facts.sort_by_key(|fact| fact.timestamp);
+    Ok(facts)
+}
+
+/// Facts about `topic`: those whose embedding is close to `embedding`, then any
+/// that mention one of its words. Used by the API, which has no `LearningManager`.
+pub async fn recall_facts(
+    db: &Database,
+    topic: &str,
+    embedding: Option<Vec<f32>>,
+    limit: usize,
+) -> Result<Vec<Fact>, Box<dyn std::error::Error>> {
+    let facts = facts(db).await?;
+    let mut recalled: Vec<Fact> = Vec::new();
+
+    if let (Some(embedding), Some(vector_db)) = (embedding.filter(|e| !e.is_empty()), db.get_vector_db().await) {
+        match vector_db.search_vectors(FACTS_COLLECTION, embedding, limit as u64).await {
+            Ok(hits) => {
+                for (_, score, payload) in hits {
+                    let text = payload.get("text").and_then(|v| v.as_str()).unwrap_or_default();
+                    if score < FACT_MIN_SCORE {
+                        continue;
+                    }
+                    if let Some(fact) = facts.iter().find(|f| f.text == text) {
+                        recalled.push(fact.clone());
+                    }
+                }
+            }
+            // A missing collection just means nothing has been embedded yet
+            Err(e) => info!("Semantic fact search unavailable: {}", e),
+        }
+    }
+
+    for fact in facts.iter().rev().filter(|f| mentions_topic(&f.text, topic)) {
+        if !recalled.contains(fact) {
+            recalled.push(fact.clone());
+        }
+    }
+
+    recalled.truncate(limit);
+    Ok(recalled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_facts() {
+        let reply = "- The user is vegetarian\n2. Their dog is called Pixel\n\n• They live in Lisbon";
+        assert_eq!(parse_facts(reply), vec![
+            "The user is vegetarian",
+            "Their dog is called Pixel",
+            "They live in Lisbon",
+        ]);
+        assert!(parse_facts("NONE").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_learn_from_exchange_skips_known_facts() {
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let learning = LearningManager::new(db.clone(), KnowledgeBaseHandler::new("data/knowledge_base.json"));
+        learning.learn_fact("The user is vegetarian.", FactSource::User, None).await.unwrap();
+        let provider = crate::providers::mock::MockProvider::with_response(
+            "- The user is vegetarian\n- Their dog is called Pixel\n- their dog is called pixel!",
+        );
+
+        let learned = learning.learn_from_exchange("I'm vegetarian, and Pixel is my dog", "Noted!", &provider).await.unwrap();
+        assert_eq!(learned.len(), 1);
+        assert_eq!(learned[0].text, "Their dog is called Pixel");
+        assert_eq!(learned[0].source, FactSource::Extracted);
+
+        // Stored in the knowledge base next to the fact that was already there
+        let texts: Vec<String> = learning.facts().await.unwrap().into_iter().map(|f| f.text).collect();
+        assert_eq!(texts, vec!["The user is vegetarian.", "Their dog is called Pixel"]);
+        assert!(learning.learn_from_exchange("again", "again", &provider).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_facts_are_recalled_by_topic() {
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let learning = LearningManager::new(db.clone(), KnowledgeBaseHandler::new("data/knowledge_base.json"));
+
+        learning.learn_fact("The user's dog is called Pixel", FactSource::User, None).await.unwrap();
+        learning.learn_fact("The user prefers dark roast coffee", FactSource::Extracted, None).await.unwrap();
+
+        let recalled = learning.recall("coffee", None, 5).await.unwrap();
+        assert_eq!(recalled.len(), 1);
+        assert_eq!(recalled[0].source, FactSource::Extracted);
+        assert!(learning.recall("gardening", None, 5).await.unwrap().is_empty());
+
+        // Stored as JSON in the knowledge base, with source and timestamp
+        let (_, value) = &db.list_knowledge(Some(FACT_PREFIX.to_string())).await.unwrap()[0];
+        let stored: serde_json::Value = serde_json::from_str(value).unwrap();
+        assert!(stored["source"] == "user" || stored["source"] == "extracted");
+        assert!(stored["timestamp"].is_string());
+    }
 }
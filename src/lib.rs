@@ -1,6 +1,7 @@
 pub mod api;
 pub mod config;
 pub mod database;
+pub mod error;
 pub mod knowledge_base;
 pub mod learning;
 pub mod llm;
@@ -12,6 +13,7 @@ pub mod food;
 pub mod completion;
 
 // Re-export commonly used items
+pub use error::{AppError, AppError as Error, AppResult};
 pub use personality::PersonalityProfile;
 pub use providers::web_crawler::crawler_manager::WebCrawlerManager;
 pub use providers::document::DocumentProcessor; 
\ No newline at end of file
@@ -0,0 +1,166 @@
+use crate::providers::traits::CompletionProvider;
+use std::sync::OnceLock;
+use tiktoken_rs::{cl100k_base, CoreBPE};
+
+/// Tokens kept free for the model's reply.
+pub const RESPONSE_RESERVE: usize = 2048;
+
+/// Context window for unrecognized models.
+const DEFAULT_CONTEXT_LIMIT: usize = 8_192;
+
+/// Context windows by model-name prefix or substring; more specific names come first.
+const CONTEXT_LIMITS: &[(&str, usize)] = &[
+    ("deepseek", 64_000),
+    ("gpt-4o", 128_000),
+    ("gpt-4-turbo", 128_000),
+    ("gpt-4.1", 1_000_000),
+    ("gpt-4", 8_192),
+    ("gpt-3.5", 16_385),
+    ("mistral-large", 128_000),
+    ("mistral", 32_000),
+    ("gemini-1.5", 1_000_000),
+    ("gemini", 30_720),
+    ("claude", 200_000),
+];
+
+/// Context window of `model`, in tokens.
+pub fn context_limit(model: &str) -> usize {
+    let model = model.to_lowercase();
+    CONTEXT_LIMITS.iter()
+        .find(|(name, _)| model.contains(name))
+        .map(|(_, limit)| *limit)
+        .unwrap_or(DEFAULT_CONTEXT_LIMIT)
+}
+
+/// Tokens a prompt for `model` may use: `PROMPT_TOKEN_BUDGET`, or its context window less
+/// room for the reply.
+pub fn prompt_budget(model: &str) -> usize {
+    std::env::var("PROMPT_TOKEN_BUDGET").ok()
+        .and_then(|budget| budget.trim().parse().ok())
+        .filter(|budget| *budget > 0)
+        .unwrap_or_else(|| context_limit(model).saturating_sub(RESPONSE_RESERVE))
+}
+
+/// The cl100k_base tokenizer, loaded on first use.
+static TOKENIZER: OnceLock<CoreBPE> = OnceLock::new();
+
+/// Tokens in `text` under cl100k_base, the encoding GPT-4 and GPT-3.5 use. Other
+/// providers split text a little differently, but near enough to budget by.
+pub fn count_tokens(text: &str) -> usize {
+    TOKENIZER.get_or_init(|| cl100k_base().expect("cl100k_base is bundled with tiktoken-rs"))
+        .encode_ordinary(text)
+        .len()
+}
+
+/// The parts of a prompt, after fitting them to a budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptSections {
+    pub system: String,
+    pub context: String,
+    pub user: String,
+}
+
+impl PromptSections {
+    pub fn total_tokens(&self) -> usize {
+        count_tokens(&self.system) + count_tokens(&self.context) + count_tokens(&self.user)
+    }
+}
+
+/// The longest prefix of `text` that fits in `max_tokens`.
+fn truncate_tokens(text: &str, max_tokens: usize) -> &str {
+    if count_tokens(text) <= max_tokens {
+        return text;
+    }
+    // Byte offset where each char starts; the whole text is known not to fit
+    let boundaries: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    // Largest prefix length (in chars) that fits; the count only grows with length
+    let (mut low, mut high) = (0, boundaries.len() - 1);
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if count_tokens(&text[..boundaries[mid]]) <= max_tokens {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    &text[..boundaries[low]]
+}
+
+/// Fits a prompt into `max_tokens`, trimming the end of the lowest-priority section
+/// first: context, then the user text, then the system message.
+pub fn truncate_to_budget(system: &str, context: &str, user: &str, max_tokens: usize, model: &str) -> PromptSections {
+    let (system_tokens, context_tokens, user_tokens) = (count_tokens(system), count_tokens(context), count_tokens(user));
+    if system_tokens + context_tokens + user_tokens <= max_tokens {
+        return PromptSections { system: system.to_string(), context: context.to_string(), user: user.to_string() };
+    }
+
+    let system = truncate_tokens(system, max_tokens);
+    let user = truncate_tokens(user, max_tokens.saturating_sub(count_tokens(system)));
+    let context = truncate_tokens(context, max_tokens.saturating_sub(count_tokens(system) + count_tokens(user)));
+
+    log::warn!(
+        "Prompt for {} exceeded its {} token budget; dropped {} context, {} user and {} system tokens",
+        model,
+        max_tokens,
+        context_tokens - count_tokens(context),
+        user_tokens - count_tokens(user),
+        system_tokens - count_tokens(system),
+    );
+
+    PromptSections { system: system.to_string(), context: context.to_string(), user: user.to_string() }
+}
+
+/// [`truncate_to_budget`] against the budget of `provider`'s model.
+pub async fn fit_to_provider<P>(provider: &P, system: &str, context: &str, user: &str) -> PromptSections
+where
+    P: CompletionProvider + ?Sized,
+{
+    let model = provider.get_model_info().await.unwrap_or_default();
+    truncate_to_budget(system, context, user, prompt_budget(&model), &model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fits_budget_and_drops_context_first() {
+        let system = "You are a helpful pirate.";
+        let user = "Summarize the page in three bullet points.";
+        let context = "Lorem ipsum dolor sit amet. ".repeat(500);
+
+        let sections = truncate_to_budget(system, &context, user, 200, "deepseek-chat");
+        assert!(sections.total_tokens() <= 200);
+        assert_eq!(sections.system, system);
+        assert_eq!(sections.user, user);
+        assert!(context.starts_with(&sections.context));
+        assert!(!sections.context.is_empty());
+
+        // Small prompts pass through untouched
+        let sections = truncate_to_budget(system, "short", user, 200, "deepseek-chat");
+        assert_eq!(sections.context, "short");
+
+        // When even system + user are over, the user text is cut before the system message
+        let long_user = "word ".repeat(300);
+        let sections = truncate_to_budget(system, &context, &long_user, 100, "deepseek-chat");
+        assert!(sections.total_tokens() <= 100);
+        assert_eq!(sections.system, system);
+        assert!(sections.context.is_empty());
+    }
+
+    #[test]
+    fn test_counts_tokenizer_tokens() {
+        assert_eq!(count_tokens(""), 0);
+        assert_eq!(count_tokens("hello world"), 2);
+        assert_eq!(count_tokens("tokenization"), 2);
+    }
+
+    #[test]
+    fn test_context_limits() {
+        assert_eq!(context_limit("gpt-4o-mini"), 128_000);
+        assert_eq!(context_limit("gpt-4"), 8_192);
+        assert_eq!(context_limit("deepseek-chat"), 64_000);
+        assert_eq!(context_limit("something-new"), DEFAULT_CONTEXT_LIMIT);
+        assert_eq!(prompt_budget("deepseek-chat"), 64_000 - RESPONSE_RESERVE);
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::database::Database;
+use crate::providers::traits::{CompletionOptions, CompletionProvider};
+
+/// How long a cached completion is served unless `COMPLETION_CACHE_TTL_SECS` says otherwise.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Which completions the cache keeps (`COMPLETION_CACHE`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Only temperature 0 requests, whose replies shouldn't change (`on`)
+    Deterministic,
+    /// Every request, even ones whose replies would otherwise vary (`always`)
+    Always,
+}
+
+impl CacheMode {
+    /// `COMPLETION_CACHE`: `on` for deterministic requests, `always` for all of them, and
+    /// off unless set.
+    pub fn from_env() -> Option<Self> {
+        match env::var("COMPLETION_CACHE").map(|v| v.trim().to_lowercase()).as_deref() {
+            Ok("on" | "true" | "1") => Some(Self::Deterministic),
+            Ok("always") => Some(Self::Always),
+            _ => None,
+        }
+    }
+}
+
+/// A completion request, as far as the cache can tell one from another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheRequest {
+    /// Command name, e.g. `deepseek`
+    pub provider: String,
+    pub model: String,
+    pub system_message: String,
+    pub prompt: String,
+    pub temperature: f32,
+}
+
+impl CacheRequest {
+    /// `prompt` as `provider` would send it now with `options`.
+    pub async fn new(provider: &dyn CompletionProvider, provider_name: &str, prompt: &str, options: &CompletionOptions) -> Self {
+        let provider_name = provider_name.to_lowercase();
+        Self {
+            model: provider.get_model_info().await.unwrap_or_default(),
+            system_message: provider.get_system_message(),
+            prompt: prompt.to_string(),
+            temperature: options.temperature_for(&provider_name),
+            provider: provider_name,
+        }
+    }
+
+    /// The options that send this request at its temperature.
+    pub fn options(&self) -> CompletionOptions {
+        CompletionOptions { temperature: Some(self.temperature) }
+    }
+
+    /// Hash of every field, so changing any of them misses the cache.
+    fn key(&self) -> String {
+        let temperature = self.temperature.to_string();
+        let mut hasher = Sha256::new();
+        for field in [&self.provider, &self.model, &self.system_message, &self.prompt, &temperature] {
+            hasher.update(field.as_bytes());
+            hasher.update([0x1f]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Replies to repeated prompts, kept in SQLite for a while so asking again doesn't go back
+/// to the provider. Failures to read or write it are logged and treated as misses.
+#[derive(Clone)]
+pub struct CompletionCache {
+    db: Arc<Database>,
+    mode: CacheMode,
+    ttl: chrono::Duration,
+}
+
+impl CompletionCache {
+    pub fn new(db: Arc<Database>, mode: CacheMode, ttl: Duration) -> Self {
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::days(365));
+        Self { db, mode, ttl }
+    }
+
+    /// The cache `COMPLETION_CACHE` asks for, keeping replies `COMPLETION_CACHE_TTL_SECS`
+    /// (default 24h), or `None` when it is off.
+    pub fn from_env(db: Arc<Database>) -> Option<Self> {
+        let ttl = env::var("COMPLETION_CACHE_TTL_SECS").ok()
+            .and_then(|secs| secs.trim().parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_CACHE_TTL);
+        CacheMode::from_env().map(|mode| Self::new(db, mode, ttl))
+    }
+
+    /// Whether replies to `request` are kept: temperature 0 ones, or any with `always`.
+    pub fn caches(&self, request: &CacheRequest) -> bool {
+        self.mode == CacheMode::Always || request.temperature == 0.0
+    }
+
+    /// The cached reply to `request`, if there is one young enough.
+    pub async fn get(&self, request: &CacheRequest) -> Option<String> {
+        if !self.caches(request) {
+            return None;
+        }
+        self.db.get_cached_completion(request.key(), self.ttl).await.unwrap_or_else(|e| {
+            log::warn!("Failed to read the completion cache: {}", e);
+            None
+        })
+    }
+
+    /// Keeps `response` as the reply to `request`, if requests like it are cached.
+    pub async fn put(&self, request: &CacheRequest, response: &str) {
+        if !self.caches(request) {
+            return;
+        }
+        if let Err(e) = self.db.cache_completion(request.key(), response.to_string(), self.ttl).await {
+            log::warn!("Failed to write the completion cache: {}", e);
+        }
+    }
+
+    /// `provider.complete_with_options`, answered from the cache when the same request was
+    /// seen before.
+    pub async fn complete(&self, provider: &dyn CompletionProvider, request: &CacheRequest) -> Result<String> {
+        if let Some(response) = self.get(request).await {
+            return Ok(response);
+        }
+        let response = provider.complete_with_options(&request.prompt, &request.options()).await?;
+        self.put(request, &response).await;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    async fn cache(mode: CacheMode) -> CompletionCache {
+        CompletionCache::new(Arc::new(Database::new(":memory:").await.unwrap()), mode, DEFAULT_CACHE_TTL)
+    }
+
+    fn request(prompt: &str, temperature: f32) -> CacheRequest {
+        CacheRequest {
+            provider: "mock".to_string(),
+            model: "mock".to_string(),
+            system_message: String::new(),
+            prompt: prompt.to_string(),
+            temperature,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_skips_the_provider() {
+        let cache = cache(CacheMode::Deterministic).await;
+        let mock = MockProvider::with_response("Paris");
+
+        assert_eq!(cache.complete(&mock, &request("Capital of France?", 0.0)).await.unwrap(), "Paris");
+        assert_eq!(cache.complete(&mock, &request("Capital of France?", 0.0)).await.unwrap(), "Paris");
+        assert_eq!(mock.prompts().len(), 1);
+
+        // Any other setting is a different request
+        let mut other_model = request("Capital of France?", 0.0);
+        other_model.model = "bigger".to_string();
+        cache.complete(&mock, &other_model).await.unwrap();
+        cache.complete(&mock, &request("Capital of Spain?", 0.0)).await.unwrap();
+        assert_eq!(mock.prompts().len(), 3);
+        assert_eq!(mock.temperatures(), vec![Some(0.0); 3]);
+    }
+
+    #[tokio::test]
+    async fn test_warm_requests_are_only_cached_when_forced() {
+        let cache = cache(CacheMode::Deterministic).await;
+        let mock = MockProvider::with_response("Once upon a time");
+        cache.complete(&mock, &request("Tell me a story", 0.7)).await.unwrap();
+        cache.complete(&mock, &request("Tell me a story", 0.7)).await.unwrap();
+        assert_eq!(mock.prompts().len(), 2);
+
+        let forced = CompletionCache { mode: CacheMode::Always, ..cache };
+        forced.complete(&mock, &request("Tell me a story", 0.7)).await.unwrap();
+        forced.complete(&mock, &request("Tell me a story", 0.7)).await.unwrap();
+        assert_eq!(mock.prompts().len(), 3);
+    }
+}
@@ -1,28 +1,44 @@
 use anyhow::Result;
-use crate::llm::memory::{Memory, MemoryManager};
+use crate::llm::budget::{count_tokens, prompt_budget, truncate_to_budget};
+use crate::llm::memory::{Memory, MemoryManager, RetrievalWeights, NO_RELEVANT_CONTEXT};
 use crate::providers::traits::CompletionProvider;
-use crate::database::vector_db::VectorDB;
+use crate::database::vector_store::VectorStore;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-pub struct ChatManager<T: CompletionProvider> {
-    provider: Arc<T>,
+/// The active completion provider, shared so a provider switch reaches every holder.
+pub type SharedProvider = Arc<Box<dyn CompletionProvider + Send + Sync>>;
+
+pub struct ChatManager {
     memory: Arc<Mutex<MemoryManager>>,
+    /// Turns of the current session replayed into each prompt
     context_window: usize,
-    max_context_length: usize,
+    /// How past messages are ranked for the context
+    retrieval_weights: RetrievalWeights,
 }
 
-impl<T: CompletionProvider> ChatManager<T> {
-    pub async fn new(provider: T, vector_db: VectorDB, context_window: usize) -> Result<Self> {
-        let vector_db = Arc::new(vector_db);
-        let memory = Arc::new(Mutex::new(MemoryManager::new(vector_db).await?));
-        
-        Ok(Self {
-            provider: Arc::new(provider),
-            memory,
+impl ChatManager {
+    pub async fn new(vector_db: Arc<dyn VectorStore>, context_window: usize) -> Result<Self> {
+        let memory = MemoryManager::new(vector_db).await?;
+        Ok(Self::with_memory(memory, context_window))
+    }
+
+    /// A chat manager over memory that is already set up.
+    pub fn with_memory(memory: MemoryManager, context_window: usize) -> Self {
+        Self {
+            memory: Arc::new(Mutex::new(memory)),
             context_window,
-            max_context_length: 4000, // Adjust based on your model's limits
-        })
+            retrieval_weights: RetrievalWeights::from_env(),
+        }
+    }
+
+    pub fn set_context_window(&mut self, context_window: usize) {
+        self.context_window = context_window;
+    }
+
+    /// The conversation memory, for commands that manage it directly.
+    pub fn memory(&self) -> Arc<Mutex<MemoryManager>> {
+        self.memory.clone()
     }
 
     pub async fn start_conversation(&self, topic: Option<&str>) -> Result<String> {
@@ -30,88 +46,73 @@ impl<T: CompletionProvider> ChatManager<T> {
         memory.start_new_session(topic.unwrap_or("General Conversation")).await
     }
 
-    pub async fn chat(&self, user_message: &str) -> Result<String> {
-        // Generate embedding for user message
-        let user_embedding = self.provider.generate_embedding(user_message).await?;
-        
-        // Get or create session
-        let session_id = {
-            let mut memory = self.memory.lock().await;
-            memory.get_or_create_session(None).await?
-        };
-
-        // Store user message in memory
-        {
-            let memory = self.memory.lock().await;
-            memory.store_memory(
-                user_message,
-                "user",
-                user_embedding.clone(),
-                None
-            ).await?;
+    /// The prompt for `user_message` in the current session, with recent turns and, given
+    /// the message's embedding, relevant past messages that fit the budget of `provider`,
+    /// which will answer it. With nothing to add the prompt is the message itself. Also
+    /// returns the texts of the past messages it recalled.
+    pub async fn prepare_prompt(
+        &self,
+        provider: &dyn CompletionProvider,
+        user_message: &str,
+        user_embedding: Option<&[f32]>,
+    ) -> Result<(String, Vec<String>)> {
+        let session_id = self.memory.lock().await.get_or_create_session(None).await?;
+        let (recent, similar) = self.conversation_memories(&session_id, user_embedding).await?;
+        if recent.is_empty() && similar.is_empty() {
+            return Ok((user_message.to_string(), Vec::new()));
         }
 
-        // Build context from various sources
-        let context = self.build_conversation_context(user_message, &user_embedding).await?;
-        
-        // Generate response with rich context
+        // The system message isn't in the prompt text, but the provider sends it, so it counts
+        let model = provider.get_model_info().await.unwrap_or_default();
+        let budget = prompt_budget(&model);
+        let frame = format!("Current Session ID: {}", session_id);
+        let fixed = format!("{}\n{}", provider.get_system_message(), frame);
+        let fixed_tokens = count_tokens(&fixed) + count_tokens(user_message);
+        let (context, usage) = assemble_context(budget.saturating_sub(fixed_tokens), &recent, &similar);
+        log::debug!(
+            "Context within {} tokens: {} fixed, {} for {}/{} recent turns, {} for {}/{} past messages",
+            budget,
+            fixed_tokens,
+            usage.recent_tokens,
+            usage.recent_kept,
+            recent.len(),
+            usage.memory_tokens,
+            usage.memories_kept,
+            similar.len(),
+        );
+
+        // A message too long for the budget on its own is cut, leaving no context
+        let sections = truncate_to_budget(&fixed, &context, user_message, budget, &model);
         let prompt = format!(
             "Conversation Context:\n{}\n\n\
-             Current Session ID: {}\n\n\
+             {}\n\n\
              User: {}\nAssistant:",
-            context,
-            session_id,
-            user_message
+            sections.context,
+            frame,
+            sections.user
         );
-
-        let response = self.provider.complete(&prompt).await?;
-
-        // Store assistant's response
-        let response_embedding = self.provider.generate_embedding(&response).await?;
-        {
-            let memory = self.memory.lock().await;
-            memory.store_memory(
-                &response,
-                "assistant",
-                response_embedding,
-                None
-            ).await?;
-        }
-
-        Ok(response)
+        let recalled = similar.into_iter().take(usage.memories_kept).map(|mem| mem.text).collect();
+        Ok((prompt, recalled))
     }
 
-    async fn build_conversation_context(&self, user_message: &str, user_embedding: &[f32]) -> Result<String> {
+    /// The latest turns of session `session_id`, newest first, and past messages similar
+    /// enough to the embedding, best first and not among those turns.
+    async fn conversation_memories(&self, session_id: &str, user_embedding: Option<&[f32]>) -> Result<(Vec<Memory>, Vec<Memory>)> {
         let memory = self.memory.lock().await;
-        
-        // Get recent and similar messages
-        let similar_memories = memory.search_similar(user_embedding.to_vec(), 10).await?;
-        let recent_memories = memory.get_recent_memories(5).await?;
-        
-        // Build context sections
-        let mut context = String::new();
-        
-        // Add recent conversation
-        context.push_str("Recent Conversation:\n");
-        for mem in recent_memories.iter().rev() {
-            context.push_str(&format!("{}: {}\n", mem.role, mem.text));
-        }
-        
-        // Add relevant past messages
-        context.push_str("\nRelevant Past Messages:\n");
-        for mem in similar_memories.iter() {
-            if !recent_memories.iter().any(|m| m.text == mem.text) {
-                context.push_str(&format!("[Previous] {}: {}\n", mem.role, mem.text));
-            }
-        }
-        
-        // Truncate if too long while preserving recent messages
-        if context.len() > self.max_context_length {
-            let recent_part = context.split("\nRelevant Past Messages:\n").next().unwrap_or("");
-            context = format!("{}\nRelevant Past Messages: [Truncated for length]", recent_part);
-        }
-        
-        Ok(context)
+        let mut recent = memory.list_memories(Some(session_id)).await?;
+        recent.truncate(self.context_window);
+
+        let similar = match user_embedding {
+            Some(embedding) => memory.retrieve(embedding.to_vec(), 10, &self.retrieval_weights).await?,
+            None => Vec::new(),
+        };
+        let min_score = crate::config::context_min_score();
+        let similar = similar.into_iter()
+            .filter(|hit| hit.similarity >= min_score)
+            .map(|hit| hit.memory)
+            .filter(|mem| !recent.iter().any(|m| m.text == mem.text))
+            .collect();
+        Ok((recent, similar))
     }
 
     pub async fn get_conversation_summary(&self) -> Result<String> {
@@ -119,4 +120,125 @@ impl<T: CompletionProvider> ChatManager<T> {
         let memories = memory.get_recent_memories(10).await?;
         Ok(memory.summarize_memories(&memories).await)
     }
-} 
\ No newline at end of file
+}
+
+/// What [`assemble_context`] fit into its budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ContextUsage {
+    pub recent_tokens: usize,
+    pub recent_kept: usize,
+    pub memory_tokens: usize,
+    pub memories_kept: usize,
+}
+
+const RECENT_HEADER: &str = "Recent Conversation:\n";
+const MEMORIES_HEADER: &str = "\nRelevant Past Messages:\n";
+
+/// Context of at most `budget` tokens from `recent` turns, newest first, and `similar`
+/// past messages, best first. Recent turns are filled first and the past messages after,
+/// so when space runs out the weakest past messages go, then the oldest turns. With no past
+/// messages kept, the context says there is no relevant prior context.
+pub fn assemble_context(budget: usize, recent: &[Memory], similar: &[Memory]) -> (String, ContextUsage) {
+    let mut remaining = budget.saturating_sub(header_tokens());
+    let mut usage = ContextUsage::default();
+    let mut take = |lines: Vec<String>, tokens: &mut usize| -> Vec<String> {
+        lines.into_iter()
+            .map_while(|line| {
+                let cost = count_tokens(&line);
+                (cost <= remaining).then(|| {
+                    remaining -= cost;
+                    *tokens += cost;
+                    line
+                })
+            })
+            .collect()
+    };
+
+    let mut recent_lines = take(recent.iter().map(|mem| format!("{}: {}\n", mem.role, mem.text)).collect(), &mut usage.recent_tokens);
+    let memory_lines = take(similar.iter().map(|mem| format!("[Previous] {}: {}\n", mem.role, mem.text)).collect(), &mut usage.memory_tokens);
+    usage.recent_kept = recent_lines.len();
+    usage.memories_kept = memory_lines.len();
+
+    // Oldest turn first, as the conversation happened
+    recent_lines.reverse();
+    let memories = if memory_lines.is_empty() {
+        format!("\n{}\n", NO_RELEVANT_CONTEXT)
+    } else {
+        format!("{}{}", MEMORIES_HEADER, memory_lines.concat())
+    };
+    let context = format!("{}{}{}", RECENT_HEADER, recent_lines.concat(), memories);
+    (context, usage)
+}
+
+/// Tokens the section headers take, whichever way the past messages section turns out.
+fn header_tokens() -> usize {
+    count_tokens(RECENT_HEADER) + count_tokens(MEMORIES_HEADER).max(count_tokens(NO_RELEVANT_CONTEXT) + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn memory(role: &str, text: &str) -> Memory {
+        Memory {
+            text: text.to_string(),
+            timestamp: Utc::now(),
+            role: role.to_string(),
+            session_id: "s".to_string(),
+            importance: 0.5,
+            topic_tags: Vec::new(),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_context_drops_weak_memories_before_recent_turns() {
+        let recent = vec![memory("assistant", "newest reply"), memory("user", "older question")];
+        let similar = vec![memory("user", "best match"), memory("user", "weakest match ".repeat(20).trim())];
+
+        let (context, usage) = assemble_context(1_000, &recent, &similar);
+        assert_eq!(usage.recent_kept + usage.memories_kept, 4);
+        assert!(context.find("older question").unwrap() < context.find("newest reply").unwrap());
+
+        // Just enough for both turns and the best match
+        let headers = header_tokens();
+        let tight = headers + usage.recent_tokens + count_tokens("[Previous] user: best match\n");
+        let (context, usage) = assemble_context(tight, &recent, &similar);
+        assert_eq!((usage.recent_kept, usage.memories_kept), (2, 1));
+        assert!(!context.contains("weakest match"));
+
+        // Less still: the past messages go entirely, then the oldest turn
+        let (_, usage) = assemble_context(tight - 1, &recent, &similar);
+        assert_eq!((usage.recent_kept, usage.memories_kept), (2, 0));
+        let (context, usage) = assemble_context(headers + count_tokens("assistant: newest reply\n"), &recent, &similar);
+        assert_eq!((usage.recent_kept, usage.memories_kept), (1, 0));
+        assert!(context.contains("newest reply") && !context.contains("older question"));
+        assert!(context.contains(NO_RELEVANT_CONTEXT) && !context.contains(MEMORIES_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_prompt_replays_the_session() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        use crate::providers::mock::MockProvider;
+
+        let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+        let chat = ChatManager::new(store, 10).await.unwrap();
+        let mock = MockProvider::with_response("unused");
+
+        let (prompt, _) = chat.prepare_prompt(&mock, "My cat is called Miso", None).await.unwrap();
+        assert_eq!(prompt, "My cat is called Miso");
+        {
+            let memory = chat.memory();
+            let memory = memory.lock().await;
+            for (text, role) in [("My cat is called Miso", "user"), ("Noted!", "assistant")] {
+                let embedding = mock.generate_embedding(text).await.unwrap();
+                memory.store_memory(text, role, embedding, None).await.unwrap();
+            }
+        }
+
+        let (prompt, _) = chat.prepare_prompt(&mock, "What is my cat called?", None).await.unwrap();
+        assert!(prompt.starts_with("Conversation Context:\nRecent Conversation:\nuser: My cat is called Miso\nassistant: Noted!\n"));
+        assert!(prompt.ends_with("User: What is my cat called?\nAssistant:"));
+    }
+}
@@ -0,0 +1,124 @@
+use thiserror::Error;
+use crate::llm::budget::count_tokens;
+use crate::llm::memory::{Memory, MemoryManager, RetrievalWeights, NO_RELEVANT_CONTEXT};
+use crate::llm::EmbeddingGenerator;
+
+/// Why [`ContextBuilder::build`] couldn't look up context.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ContextError {
+    #[error("Failed to generate embedding: {0}")]
+    Embedding(String),
+    #[error("Failed to search memories: {0}")]
+    Search(String),
+}
+
+/// How much remembered context goes into a prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextOptions {
+    /// Memories retrieved for the query
+    pub limit: u64,
+    /// Normalized similarity a memory needs to be included
+    pub min_score: f32,
+    /// Tokens the context may take; the weakest memories are dropped to fit
+    pub max_tokens: Option<usize>,
+    /// How retrieved memories are ranked
+    pub weights: RetrievalWeights,
+}
+
+impl Default for ContextOptions {
+    /// Five memories at `CONTEXT_MIN_SCORE`, ranked by `RetrievalWeights::from_env`, with no token cap.
+    fn default() -> Self {
+        Self {
+            limit: 5,
+            min_score: crate::config::context_min_score(),
+            max_tokens: None,
+            weights: RetrievalWeights::from_env(),
+        }
+    }
+}
+
+/// Remembered context for a query: embeds it, retrieves similar memories and formats
+/// those that are relevant enough, as doc chat, web chat and the API's web chat share.
+pub struct ContextBuilder<'a> {
+    embedder: &'a EmbeddingGenerator,
+    memory: &'a MemoryManager,
+    options: ContextOptions,
+}
+
+impl<'a> ContextBuilder<'a> {
+    pub fn new(embedder: &'a EmbeddingGenerator, memory: &'a MemoryManager) -> Self {
+        Self { embedder, memory, options: ContextOptions::default() }
+    }
+
+    pub fn with_options(mut self, options: ContextOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The context block for `query`, or [`NO_RELEVANT_CONTEXT`] rather than weak matches.
+    pub async fn build(&self, query: &str) -> Result<String, ContextError> {
+        let embedding = self.embedder.generate_embedding(query).await
+            .map_err(|e| ContextError::Embedding(e.to_string()))?;
+        let hits = self.memory.retrieve(embedding, self.options.limit, &self.options.weights).await
+            .map_err(|e| ContextError::Search(e.to_string()))?;
+        let memories: Vec<Memory> = hits.into_iter()
+            .filter(|hit| hit.similarity >= self.options.min_score)
+            .map(|hit| hit.memory)
+            .collect();
+        let memories = match self.options.max_tokens {
+            Some(max_tokens) => self.within_budget(memories, max_tokens).await,
+            None => memories,
+        };
+
+        if memories.is_empty() {
+            Ok(NO_RELEVANT_CONTEXT.to_string())
+        } else {
+            Ok(self.memory.summarize_memories(&memories).await)
+        }
+    }
+
+    /// The best-ranked of `memories` whose summary lines fit in `max_tokens`.
+    async fn within_budget(&self, memories: Vec<Memory>, max_tokens: usize) -> Vec<Memory> {
+        let mut remaining = max_tokens;
+        let mut kept = Vec::new();
+        for memory in memories {
+            let cost = count_tokens(&self.memory.summarize_memories(std::slice::from_ref(&memory)).await);
+            if cost > remaining {
+                break;
+            }
+            remaining -= cost;
+            kept.push(memory);
+        }
+        kept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::sqlite_vector_store::SqliteVectorStore;
+    use crate::database::vector_store::VectorStore;
+    use crate::providers::mock::MockProvider;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_context_keeps_relevant_memories_within_budget() {
+        let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+        let memory = MemoryManager::new(store).await.unwrap();
+        let embedder = EmbeddingGenerator::from_provider(Box::new(MockProvider::with_response("unused")));
+
+        for text in ["My cat is called Miso", "The soup needs more salt"] {
+            let embedding = embedder.generate_embedding(text).await.unwrap();
+            memory.store_memory(text, "user", embedding, None).await.unwrap();
+        }
+
+        let builder = ContextBuilder::new(&embedder, &memory);
+        let context = builder.build("My cat is called Miso").await.unwrap();
+        assert!(context.contains("user: My cat is called Miso"));
+        assert!(!context.contains("soup"));
+
+        let tight = ContextBuilder::new(&embedder, &memory)
+            .with_options(ContextOptions { max_tokens: Some(1), ..ContextOptions::default() });
+        assert_eq!(tight.build("My cat is called Miso").await.unwrap(), NO_RELEVANT_CONTEXT);
+    }
+}
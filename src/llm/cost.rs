@@ -0,0 +1,181 @@
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+
+/// Built-in prices in USD per million tokens, as (input, output). `MODEL_PRICES` adds to
+/// and overrides these, since providers change prices more often than this list is updated.
+const DEFAULT_PRICES: &[(&str, f64, f64)] = &[
+    ("deepseek-chat", 0.27, 1.10),
+    ("deepseek-reasoner", 0.55, 2.19),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("mistral-small", 0.20, 0.60),
+    ("mistral-large", 2.00, 6.00),
+    ("gemini-1.5-flash", 0.075, 0.30),
+    ("gemini-2.0-flash", 0.10, 0.40),
+];
+
+/// What a model costs, in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+/// Prices by model name.
+#[derive(Debug, Clone, Default)]
+pub struct PriceTable {
+    prices: Vec<(String, ModelPrice)>,
+}
+
+impl PriceTable {
+    /// The built-in prices with `MODEL_PRICES` applied on top.
+    pub fn from_env() -> Self {
+        let mut table = Self::default();
+        for (model, input, output) in DEFAULT_PRICES {
+            table.set(model, ModelPrice { input_per_million: *input, output_per_million: *output });
+        }
+        if let Ok(spec) = std::env::var("MODEL_PRICES") {
+            for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                match parse_price(entry) {
+                    Some((model, price)) => table.set(&model, price),
+                    None => eprintln!("Warning: Ignoring MODEL_PRICES entry '{}' (expected model=input/output)", entry),
+                }
+            }
+        }
+        table
+    }
+
+    pub fn set(&mut self, model: &str, price: ModelPrice) {
+        let model = model.to_lowercase();
+        self.prices.retain(|(name, _)| *name != model);
+        self.prices.push((model, price));
+    }
+
+    /// The price of `model`, or of the longest listed name it starts with, so a dated
+    /// release such as `gpt-4o-2024-08-06` is priced as `gpt-4o`.
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        let model = model.to_lowercase();
+        self.prices.iter()
+            .filter(|(name, _)| model.starts_with(name.as_str()))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, price)| *price)
+    }
+
+    /// The cost in USD of one request, or `None` for a model with no listed price.
+    pub fn estimate(&self, model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+        let price = self.price(model)?;
+        Some((input_tokens as f64 * price.input_per_million + output_tokens as f64 * price.output_per_million) / 1_000_000.0)
+    }
+}
+
+/// `gpt-4o=2.5/10`: a model and its input and output price per million tokens.
+fn parse_price(entry: &str) -> Option<(String, ModelPrice)> {
+    let (model, prices) = entry.split_once('=')?;
+    let (input, output) = prices.split_once('/')?;
+    let parse = |p: &str| p.trim().trim_start_matches('$').parse::<f64>().ok().filter(|p| p.is_finite() && *p >= 0.0);
+    let model = model.trim();
+    if model.is_empty() {
+        return None;
+    }
+    Some((model.to_string(), ModelPrice { input_per_million: parse(input)?, output_per_million: parse(output)? }))
+}
+
+fn prices() -> &'static PriceTable {
+    static PRICES: OnceLock<PriceTable> = OnceLock::new();
+    PRICES.get_or_init(PriceTable::from_env)
+}
+
+/// The estimated cost in USD of a request to `model`, or `None` if its price is unknown.
+pub fn cost_estimate(model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+    prices().estimate(model, input_tokens, output_tokens)
+}
+
+/// Usage and estimated spend since the process started.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct CostTotals {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Spend on requests to models with a known price
+    pub cost_usd: f64,
+    /// Requests left out of `cost_usd` because their model has no price
+    pub unpriced_requests: u64,
+}
+
+impl CostTotals {
+    fn add(&mut self, input_tokens: usize, output_tokens: usize, cost: Option<f64>) {
+        self.requests += 1;
+        self.input_tokens += input_tokens as u64;
+        self.output_tokens += output_tokens as u64;
+        match cost {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+}
+
+static TOTALS: Mutex<CostTotals> = Mutex::new(CostTotals {
+    requests: 0,
+    input_tokens: 0,
+    output_tokens: 0,
+    cost_usd: 0.0,
+    unpriced_requests: 0,
+});
+
+/// Adds a request to the running totals and returns its estimated cost.
+pub fn record(model: &str, input_tokens: usize, output_tokens: usize) -> Option<f64> {
+    let cost = cost_estimate(model, input_tokens, output_tokens);
+    TOTALS.lock().unwrap().add(input_tokens, output_tokens, cost);
+    cost
+}
+
+pub fn totals() -> CostTotals {
+    TOTALS.lock().unwrap().clone()
+}
+
+/// `$0.0042`, with more digits for the fractions of a cent most chat turns cost.
+pub fn format_usd(cost: f64) -> String {
+    if cost >= 0.01 {
+        format!("${:.2}", cost)
+    } else {
+        format!("${:.4}", cost)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cost_math() {
+        let mut table = PriceTable::default();
+        table.set("gpt-4o", ModelPrice { input_per_million: 2.5, output_per_million: 10.0 });
+        table.set("gpt-4o-mini", ModelPrice { input_per_million: 0.15, output_per_million: 0.6 });
+
+        let cost = table.estimate("gpt-4o", 1_000, 500).unwrap();
+        assert!((cost - 0.0075).abs() < 1e-12);
+        assert_eq!(table.estimate("gpt-4o", 0, 0), Some(0.0));
+        // The most specific name wins, and dated releases match their family
+        assert_eq!(table.price("GPT-4o-mini-2024-07-18").unwrap().input_per_million, 0.15);
+        assert_eq!(table.price("gpt-4o-2024-08-06").unwrap().input_per_million, 2.5);
+
+        let mut totals = CostTotals::default();
+        totals.add(1_000, 500, Some(cost));
+        totals.add(10, 10, None);
+        assert_eq!((totals.requests, totals.input_tokens, totals.unpriced_requests), (2, 1_010, 1));
+        assert_eq!(totals.cost_usd, cost);
+    }
+
+    #[test]
+    fn test_unknown_models_have_no_price() {
+        let table = PriceTable::default();
+        assert_eq!(table.estimate("my-local-llama", 100, 100), None);
+
+        assert_eq!(parse_price("llama3 = 0.1/$0.2"), Some(("llama3".to_string(), ModelPrice { input_per_million: 0.1, output_per_million: 0.2 })));
+        assert_eq!(parse_price("llama3=cheap"), None);
+        assert_eq!(parse_price("=1/2"), None);
+    }
+}
@@ -1,26 +1,172 @@
 use anyhow::{Result, Error};
 use serde_json::Value;
+use futures::stream::{self, StreamExt};
 use crate::providers::deepseek::deepseek::DeepSeekProvider;
+use crate::providers::gemini::gemini::GeminiProvider;
+use crate::providers::mistral::mistral::MistralProvider;
+use crate::providers::openai::openai::OpenAIProvider;
+use crate::providers::openrouter::openrouter::OpenRouterProvider;
 use crate::providers::traits::CompletionProvider;
+use crate::config::{embedding_dim, EmbeddingProviderConfig};
+
+/// Where embedding vectors come from.
+pub enum EmbeddingBackend {
+    /// Asks DeepSeek to write the vector out as a completion
+    Prompted(DeepSeekProvider),
+    /// The provider's own embedding endpoint
+    Provider(Box<dyn CompletionProvider + Send + Sync>),
+    /// A fastembed model run on this machine
+    #[cfg(feature = "local-embeddings")]
+    Local(crate::llm::local_embeddings::LocalEmbedder),
+}
 
 pub struct EmbeddingGenerator {
-    provider: DeepSeekProvider,
+    backend: EmbeddingBackend,
+    /// What embeds, e.g. `OpenAI (text-embedding-3-small)`, unless it is the chat provider
+    description: Option<String>,
+    /// The embedding model, when it is chosen here rather than by the provider
+    model: Option<String>,
 }
 
 impl EmbeddingGenerator {
     pub async fn new(api_key: String) -> Result<Self> {
         let provider = DeepSeekProvider::new(api_key, "You are a helpful assistant.".to_string()).await?;
-        Ok(Self { provider })
+        Ok(Self { backend: EmbeddingBackend::Prompted(provider), description: Some("DeepSeek".to_string()), model: None })
+    }
+
+    /// Embeds through `provider.generate_embedding`.
+    pub fn from_provider(provider: Box<dyn CompletionProvider + Send + Sync>) -> Self {
+        Self { backend: EmbeddingBackend::Provider(provider), description: None, model: None }
+    }
+
+    /// Embeds with the provider `config` names, whichever provider chat uses: the local model
+    /// when `EMBEDDING_BACKEND=local`, otherwise `config.provider` with its API key. Without
+    /// a key for it, `chat_provider` embeds instead.
+    pub async fn from_config(config: &EmbeddingProviderConfig, chat_provider: Box<dyn CompletionProvider + Send + Sync>) -> Result<Self> {
+        match Self::local_from_env().await? {
+            Some(local) => Ok(local),
+            None => Self::from_provider_config(config, chat_provider).await,
+        }
+    }
+
+    async fn from_provider_config(config: &EmbeddingProviderConfig, chat_provider: Box<dyn CompletionProvider + Send + Sync>) -> Result<Self> {
+        // Only OpenAI has an embedding model to choose; the others would silently ignore it
+        if config.model.is_some() && config.provider != "openai" {
+            return Err(Error::msg(format!(
+                "EMBEDDING_MODEL only applies to EMBEDDING_PROVIDER=openai; {} has no embedding model to choose",
+                config.provider
+            )));
+        }
+        let Some(api_key) = config.api_key.clone() else {
+            log::warn!("No API key for {} embeddings, embedding with the chat provider", config.provider);
+            return Ok(Self::from_provider(chat_provider));
+        };
+
+        let model = config.model_name();
+        let (provider, name): (Box<dyn CompletionProvider + Send + Sync>, &str) = match config.provider.as_str() {
+            "openai" => {
+                let openai = OpenAIProvider::new(api_key, String::new()).await?;
+                let openai = match &model {
+                    Some(model) => openai.with_embedding_model(model),
+                    None => openai,
+                };
+                (Box::new(openai), "OpenAI")
+            }
+            "deepseek" => return Self::new(api_key).await,
+            "openrouter" => (Box::new(OpenRouterProvider::new(api_key, String::new()).await?), "OpenRouter"),
+            "mistral" => (Box::new(MistralProvider::new(api_key, String::new()).await?), "Mistral"),
+            "gemini" => (Box::new(GeminiProvider::new(api_key, String::new()).await?), "Gemini"),
+            other => return Err(Error::msg(format!(
+                "Unknown EMBEDDING_PROVIDER: {}. Available providers: openai, deepseek, openrouter, mistral, gemini",
+                other
+            ))),
+        };
+        let description = match &model {
+            Some(model) => format!("{} ({})", name, model),
+            None => name.to_string(),
+        };
+        Ok(Self { backend: EmbeddingBackend::Provider(provider), description: Some(description), model })
+    }
+
+    /// The local model when `EMBEDDING_BACKEND=local`, or `None` to embed through a provider.
+    /// Fails if the model can't be loaded, or wasn't built in.
+    pub async fn local_from_env() -> Result<Option<Self>> {
+        let Some(model) = crate::config::local_embedding_model() else {
+            return Ok(None);
+        };
+        #[cfg(feature = "local-embeddings")]
+        {
+            let embedder = crate::llm::local_embeddings::LocalEmbedder::new(&model, crate::config::embedding_cache_dir()).await?;
+            Ok(Some(Self { backend: EmbeddingBackend::Local(embedder), description: Some(format!("local ({})", model)), model: Some(model) }))
+        }
+        #[cfg(not(feature = "local-embeddings"))]
+        Err(Error::msg(format!(
+            "EMBEDDING_BACKEND=local needs local embedding support to run {}. Rebuild with `--features local-embeddings`.",
+            model
+        )))
+    }
+
+    /// What embeds, e.g. `OpenAI (text-embedding-3-small)`, or `None` for the chat provider.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The embedding model, e.g. `text-embedding-3-small`, or the model the provider reports
+    /// when none is chosen here.
+    pub async fn model_name(&self) -> String {
+        if let Some(model) = &self.model {
+            return model.clone();
+        }
+        match &self.backend {
+            EmbeddingBackend::Prompted(provider) => provider.get_model_info().await.unwrap_or_default(),
+            EmbeddingBackend::Provider(provider) => provider.get_model_info().await.unwrap_or_default(),
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingBackend::Local(_) => String::new(),
+        }
+    }
+
+    /// Whether the length of a vector it returns is the model's size; prompted and
+    /// placeholder vectors are sized by [`embedding_dim`] itself.
+    pub fn reports_dimension(&self) -> bool {
+        match &self.backend {
+            EmbeddingBackend::Prompted(_) => false,
+            EmbeddingBackend::Provider(provider) => provider.has_semantic_embeddings(),
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingBackend::Local(_) => true,
+        }
+    }
+
+    /// Whether similar texts get similar vectors, rather than placeholders.
+    pub fn is_semantic(&self) -> bool {
+        match &self.backend {
+            EmbeddingBackend::Prompted(_) => true,
+            EmbeddingBackend::Provider(provider) => provider.has_semantic_embeddings(),
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingBackend::Local(_) => true,
+        }
     }
 
     pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        match &self.backend {
+            EmbeddingBackend::Prompted(provider) => Self::prompted_embedding(provider, text).await,
+            EmbeddingBackend::Provider(provider) => provider.generate_embedding(text).await,
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingBackend::Local(embedder) => embedder.embed(vec![text.to_string()]).await?
+                .pop()
+                .ok_or_else(|| Error::msg("The local model returned no embedding")),
+        }
+    }
+
+    async fn prompted_embedding(provider: &DeepSeekProvider, text: &str) -> Result<Vec<f32>> {
+        let dim = embedding_dim();
         let prompt = format!(
             "Convert this text into a numerical embedding vector that captures its semantic meaning. \
-            Return ONLY a JSON array of 1536 float numbers:\n\n{}", 
+            Return ONLY a JSON array of {} float numbers:\n\n{}", 
+            dim,
             text
         );
 
-        let response = self.provider.complete(&prompt).await?;
+        let response = provider.complete(&prompt).await?;
         
         // Clean the response to get just the JSON array
         let clean_response = response
@@ -36,22 +182,94 @@ impl EmbeddingGenerator {
             .map_err(|e| Error::msg(format!("Failed to parse embedding numbers: {}", e)))?;
 
         // Validate vector size
-        if numbers.len() != 1536 {
+        if numbers.len() != dim {
             return Err(Error::msg(format!(
-                "Generated embedding has wrong size: {} (expected 1536)",
-                numbers.len()
+                "Generated embedding has wrong size: {} (expected {})",
+                numbers.len(),
+                dim
             )));
         }
 
         Ok(numbers)
     }
 
-    pub async fn generate_batch_embeddings(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
-        let mut embeddings = Vec::with_capacity(texts.len());
-        for text in texts {
-            let embedding = self.generate_embedding(text).await?;
-            embeddings.push(embedding);
+    /// One result per text, in order. Batches go out in as few requests as the backend
+    /// allows, and a text that fails to embed doesn't fail the others.
+    pub async fn generate_batch_embeddings(&self, texts: &[String]) -> Vec<Result<Vec<f32>>> {
+        match &self.backend {
+            EmbeddingBackend::Prompted(provider) => stream::iter(texts)
+                .map(|text| Self::prompted_embedding(provider, text))
+                .buffered(crate::config::EMBEDDING_CONCURRENCY)
+                .collect()
+                .await,
+            EmbeddingBackend::Provider(provider) => provider.generate_embeddings(texts).await,
+            // The local model embeds a batch in one pass, so a failure is the whole batch's
+            #[cfg(feature = "local-embeddings")]
+            EmbeddingBackend::Local(embedder) => match embedder.embed(texts.to_vec()).await {
+                Ok(embeddings) => embeddings.into_iter().map(Ok).collect(),
+                Err(e) => texts.iter().map(|_| Err(Error::msg(e.to_string()))).collect(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    #[tokio::test]
+    async fn test_batch_embeddings_beat_one_request_per_chunk() {
+        let chunks: Vec<String> = (0..100).map(|i| format!("chunk {} of a long document", i)).collect();
+
+        let mock = MockProvider::with_response("");
+        let concurrent = EmbeddingGenerator::from_provider(Box::new(mock.clone()));
+        let results = concurrent.generate_batch_embeddings(&chunks).await;
+        assert_eq!(results.len(), 100);
+        assert_eq!(mock.embedding_requests(), 100);
+
+        let mock = MockProvider::with_response("").with_batch_embeddings();
+        let batched = EmbeddingGenerator::from_provider(Box::new(mock.clone()));
+        let results = batched.generate_batch_embeddings(&chunks).await;
+        assert_eq!(results.len(), 100);
+        assert_eq!(results[42].as_ref().unwrap(), &crate::providers::utils::fake_embedding(&chunks[42]));
+        assert_eq!(mock.embedding_requests(), chunks.len().div_ceil(crate::config::embedding_batch_size()));
+    }
+
+    #[tokio::test]
+    async fn test_one_bad_text_fails_only_itself() {
+        let texts = vec!["fine".to_string(), "poison".to_string(), "also fine".to_string()];
+        for mock in [MockProvider::with_response(""), MockProvider::with_response("").with_batch_embeddings()] {
+            let generator = EmbeddingGenerator::from_provider(Box::new(mock.with_unembeddable("poison")));
+            let results = generator.generate_batch_embeddings(&texts).await;
+            assert!(results[0].is_ok());
+            assert!(results[1].as_ref().unwrap_err().to_string().contains("poison"));
+            assert!(results[2].is_ok());
         }
-        Ok(embeddings)
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_embedding_provider_comes_from_config_not_chat() {
+        // Chat runs on something else entirely; the config alone picks OpenAI
+        let chat = MockProvider::with_response("unused");
+        let config = EmbeddingProviderConfig {
+            provider: "openai".to_string(),
+            model: Some("text-embedding-3-large".to_string()),
+            api_key: Some("sk-test".to_string()),
+        };
+        let generator = EmbeddingGenerator::from_provider_config(&config, Box::new(chat.clone())).await.unwrap();
+        assert_eq!(generator.description(), Some("OpenAI (text-embedding-3-large)"));
+        assert_eq!(generator.model_name().await, "text-embedding-3-large");
+        assert!(generator.is_semantic());
+
+        // Providers without an embedding model to choose refuse one rather than ignore it
+        let gemini = EmbeddingProviderConfig { provider: "gemini".to_string(), ..config.clone() };
+        assert!(EmbeddingGenerator::from_provider_config(&gemini, Box::new(chat.clone())).await.is_err());
+
+        // Without a key for it, the chat provider embeds
+        let keyless = EmbeddingProviderConfig { api_key: None, ..config };
+        let generator = EmbeddingGenerator::from_provider_config(&keyless, Box::new(chat.clone())).await.unwrap();
+        assert_eq!(generator.description(), None);
+        assert_eq!(generator.generate_embedding("hello").await.unwrap(), chat.generate_embedding("hello").await.unwrap());
+    }
+}
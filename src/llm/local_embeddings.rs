@@ -0,0 +1,48 @@
+use anyhow::{Error, Result};
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Models `LOCAL_EMBEDDING_MODEL` can name, matched case-insensitively.
+const LOCAL_MODELS: &[(&str, EmbeddingModel)] = &[
+    ("all-minilm-l6-v2", EmbeddingModel::AllMiniLML6V2),
+    ("bge-small-en-v1.5", EmbeddingModel::BGESmallENV15),
+    ("bge-base-en-v1.5", EmbeddingModel::BGEBaseENV15),
+    ("nomic-embed-text-v1.5", EmbeddingModel::NomicEmbedTextV15),
+];
+
+/// A fastembed ONNX model run on this machine, so memory works without an embeddings API.
+pub struct LocalEmbedder {
+    model: Arc<TextEmbedding>,
+}
+
+impl LocalEmbedder {
+    /// Loads `name` from `cache_dir`, downloading it there on first use.
+    pub async fn new(name: &str, cache_dir: PathBuf) -> Result<Self> {
+        let model = LOCAL_MODELS.iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, model)| model.clone())
+            .ok_or_else(|| Error::msg(format!(
+                "Unknown local embedding model '{}'. Available: {}",
+                name,
+                LOCAL_MODELS.iter().map(|(known, _)| *known).collect::<Vec<_>>().join(", ")
+            )))?;
+
+        let first_run = std::fs::read_dir(&cache_dir).map(|mut entries| entries.next().is_none()).unwrap_or(true);
+        if first_run {
+            eprintln!("⬇️  Downloading the {} embedding model to {} (first run only)...", name, cache_dir.display());
+        }
+        let options = InitOptions::new(model)
+            .with_cache_dir(cache_dir)
+            .with_show_download_progress(first_run);
+        // Loading reads (and maybe downloads) the model file, so keep it off the runtime
+        let model = tokio::task::spawn_blocking(move || TextEmbedding::try_new(options)).await??;
+        Ok(Self { model: Arc::new(model) })
+    }
+
+    /// One vector per text, in order.
+    pub async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let model = self.model.clone();
+        tokio::task::spawn_blocking(move || model.embed(texts, None)).await?
+    }
+}
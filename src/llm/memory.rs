@@ -1,11 +1,79 @@
 use anyhow::{Result, Error};
 use serde::{Deserialize, Serialize};
-use crate::database::vector_db::VectorDB;
-use std::collections::HashMap;
+use crate::database::vector_db::{meets_threshold, require_vector_db, VectorDBError};
+use crate::database::vector_store::VectorStore;
+use crate::database::Database;
+use crate::config::embedding_dim;
+use std::collections::{HashMap, HashSet};
 use chrono::{DateTime, Utc};
 use uuid;
 use crate::providers::traits::CompletionProvider;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::io::Write;
+use std::path::Path;
+use crate::llm::EmbeddingGenerator;
+
+/// Days a session stays as it is before compaction, unless `MEMORY_RETENTION_DAYS` says otherwise.
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+/// Points deleted per request when cleaning up.
+const DELETE_BATCH: usize = 256;
+/// Points read per request when compacting.
+const SCROLL_PAGE: usize = 256;
+/// Memories stored per request when importing.
+const IMPORT_BATCH: usize = 256;
+/// Exported memories between progress reports.
+const PROGRESS_EVERY: usize = 500;
+/// Nearest neighbours fetched per memory `retrieve` returns, so recency and importance
+/// can lift a hit that plain similarity ranks lower.
+const RETRIEVE_CANDIDATES: u64 = 4;
+/// Topic tags kept per memory.
+const MAX_TAGS: usize = 3;
+/// Turns summarized per request when compacting a session; longer sessions are summarized
+/// in parts and the parts summarized together.
+const COMPACT_TURNS: usize = 50;
+/// Topic tags a compacted session keeps from its turns.
+const COMPACTED_TAGS: usize = 6;
+/// Importance of a session summary, so it outranks the chatter it replaced.
+const COMPACTED_IMPORTANCE: f32 = 0.9;
+/// Role of the memory a session is compacted into.
+pub const SUMMARY_ROLE: &str = "summary";
+
+/// A session's turns with their point ids and vectors while compacting, or `None` once one
+/// of them rules the session out.
+type CompactableTurns = Option<Vec<(String, Vec<f32>, Memory)>>;
+/// Common words never used as heuristic tags.
+const TAG_STOPWORDS: &[&str] = &[
+    "about", "after", "again", "also", "because", "been", "before", "being", "could", "does",
+    "doing", "from", "have", "here", "into", "just", "know", "like", "make", "more", "much",
+    "need", "only", "other", "over", "please", "really", "should", "some", "such", "than",
+    "thank", "thanks", "that", "their", "them", "then", "there", "these", "they", "this",
+    "those", "very", "want", "were", "what", "when", "where", "which", "while", "will",
+    "with", "would", "your", "you're",
+];
+/// Words that mark a message worth keeping, for heuristic importance.
+const IMPORTANT_MARKERS: &[&str] = &[
+    "remember", "important", "decided", "decision", "always", "never", "deadline", "must",
+    "password", "birthday", "prefer", "agreed",
+];
+
+/// How `store_memory_analyzed` fills in topic tags and importance, from `MEMORY_TAGGING`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryTagging {
+    /// Keyword extraction and length-based importance; costs nothing (the default)
+    Heuristic,
+    /// Ask the provider with `analyze_and_tag`, one request per memory
+    Provider,
+}
+
+impl MemoryTagging {
+    pub fn from_env() -> Self {
+        match std::env::var("MEMORY_TAGGING").map(|v| v.trim().to_lowercase()).as_deref() {
+            Ok("llm") | Ok("provider") => MemoryTagging::Provider,
+            _ => MemoryTagging::Heuristic,
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Memory {
@@ -18,38 +86,255 @@ pub struct Memory {
     pub metadata: Option<HashMap<String, String>>,
 }
 
+/// How `retrieve` ranks memories: a weighted mean of similarity, recency and importance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetrievalWeights {
+    pub similarity: f32,
+    pub recency: f32,
+    pub importance: f32,
+    /// Age at which a memory's recency score has halved
+    pub half_life_hours: f32,
+}
+
+impl Default for RetrievalWeights {
+    fn default() -> Self {
+        Self { similarity: 0.6, recency: 0.2, importance: 0.2, half_life_hours: 72.0 }
+    }
+}
+
+impl RetrievalWeights {
+    /// The defaults, overridden by `MEMORY_WEIGHT_SIMILARITY`, `MEMORY_WEIGHT_RECENCY`,
+    /// `MEMORY_WEIGHT_IMPORTANCE` and `MEMORY_RECENCY_HALF_LIFE_HOURS`.
+    pub fn from_env() -> Self {
+        let read = |var: &str, default: f32| {
+            std::env::var(var).ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|v: &f32| v.is_finite() && *v >= 0.0)
+                .unwrap_or(default)
+        };
+        let defaults = Self::default();
+        Self {
+            similarity: read("MEMORY_WEIGHT_SIMILARITY", defaults.similarity),
+            recency: read("MEMORY_WEIGHT_RECENCY", defaults.recency),
+            importance: read("MEMORY_WEIGHT_IMPORTANCE", defaults.importance),
+            half_life_hours: read("MEMORY_RECENCY_HALF_LIFE_HOURS", defaults.half_life_hours).max(f32::EPSILON),
+        }
+    }
+
+    /// 1.0 for a memory stored at `now`, halving every `half_life_hours`.
+    pub fn recency(&self, timestamp: DateTime<Utc>, now: DateTime<Utc>) -> f32 {
+        let age_hours = (now - timestamp).num_seconds().max(0) as f32 / 3600.0;
+        0.5f32.powf(age_hours / self.half_life_hours)
+    }
+
+    /// The combined score of the three components, each in 0.0..=1.0.
+    pub fn combine(&self, similarity: f32, recency: f32, importance: f32) -> f32 {
+        let total = self.similarity + self.recency + self.importance;
+        if total <= 0.0 {
+            return similarity;
+        }
+        (self.similarity * similarity + self.recency * recency + self.importance * importance.clamp(0.0, 1.0)) / total
+    }
+}
+
+/// A memory found by `retrieve`, with the parts of its score.
+#[derive(Debug)]
+pub struct ScoredMemory {
+    pub memory: Memory,
+    /// Normalized similarity to the query, see `normalize_score`
+    pub similarity: f32,
+    pub recency: f32,
+    pub score: f32,
+}
+
+/// One session `compact_older_than` collapsed, or would collapse in a dry run.
+#[derive(Debug, Clone, Serialize)]
+pub struct CompactedSession {
+    pub session_id: String,
+    /// Memories replaced by the summary
+    pub memories: usize,
+    pub topic_tags: Vec<String>,
+    /// None in a dry run
+    pub summary: Option<String>,
+}
+
+/// Stands in for retrieved context when nothing cleared the relevance floor.
+pub const NO_RELEVANT_CONTEXT: &str = "No relevant prior context.";
+
+/// One line of a `memory export` file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryRecord {
+    pub text: String,
+    pub role: String,
+    pub timestamp: DateTime<Utc>,
+    pub session_id: String,
+    pub importance: f32,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub embedding: Vec<f32>,
+}
+
+impl MemoryRecord {
+    fn new(memory: Memory, embedding: Vec<f32>) -> Self {
+        Self {
+            text: memory.text,
+            role: memory.role,
+            timestamp: memory.timestamp,
+            session_id: memory.session_id,
+            importance: memory.importance,
+            tags: memory.topic_tags,
+            metadata: memory.metadata,
+            embedding,
+        }
+    }
+
+    fn to_memory(&self) -> Memory {
+        Memory {
+            text: self.text.clone(),
+            timestamp: self.timestamp,
+            role: self.role.clone(),
+            session_id: self.session_id.clone(),
+            importance: self.importance,
+            topic_tags: self.tags.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct ConversationSession {
     pub id: String,
     pub start_time: DateTime<Utc>,
     pub topic: String,
     pub summary: String,
     pub last_active: DateTime<Utc>,
+    /// The character the session was started with
+    #[serde(default)]
+    pub personality: Option<String>,
+}
+
+/// Overview of the stored memories, for `memory stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryStats {
+    pub points: u64,
+    pub sessions: usize,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
 }
 
 #[derive(Clone)]
 pub struct MemoryManager {
-    vector_db: Arc<VectorDB>,
+    vector_db: Arc<dyn VectorStore>,
     collection_name: String,
+    /// Size of the stored embeddings
+    dimension: u64,
     current_session: Option<ConversationSession>,
+    /// Where sessions are recorded so they outlive the process
+    session_store: Option<Arc<Database>>,
+    /// Recorded on sessions started from now on
+    personality: Option<String>,
+    /// Sessions ended with `clear`, kept out of searches for the rest of the run.
+    cleared_sessions: HashSet<String>,
+    /// False when Qdrant was unreachable at startup; vector operations are then no-ops
+    available: bool,
+    /// Whether the no-op warning has been logged
+    warned: Arc<AtomicBool>,
 }
 
 impl MemoryManager {
-    pub async fn new(vector_db: Arc<VectorDB>) -> Result<Self> {
+    /// A manager over `vector_db`. If the store is unreachable this fails when
+    /// `REQUIRE_VECTOR_DB` is set, and otherwise returns a manager that stores nothing
+    /// and finds nothing, so chat keeps working without memory.
+    pub async fn new(vector_db: Arc<dyn VectorStore>) -> Result<Self> {
+        Self::with_dimension(vector_db, embedding_dim()).await
+    }
+
+    /// Like [`MemoryManager::new`], for embeddings of `dimension` values. Fails if the
+    /// memory collection already exists with another size, since every store would fail.
+    pub async fn with_dimension(vector_db: Arc<dyn VectorStore>, dimension: usize) -> Result<Self> {
         let collection_name = "conversation_memory";
-        
+        let dimension = dimension as u64;
+
+        let available = vector_db.is_available().await;
+        if !available && require_vector_db() {
+            return Err(Error::msg("Qdrant is unavailable and REQUIRE_VECTOR_DB is set"));
+        }
+
         // Create collection if it doesn't exist
-        if let Err(e) = vector_db.create_collection(collection_name, 1536).await {
-            eprintln!("Note: Collection may already exist: {}", e);
+        if available {
+            match vector_db.ensure_collection(collection_name, dimension).await {
+                Err(e @ VectorDBError::DimensionMismatch { .. }) => {
+                    return Err(Error::msg(format!("{} (with 'memory clear --all')", e)));
+                }
+                Err(e) => log::warn!("Could not set up {}: {}", collection_name, e),
+                Ok(()) => {}
+            }
         }
 
         Ok(Self {
             vector_db,
             collection_name: collection_name.to_string(),
+            dimension,
             current_session: None,
+            session_store: None,
+            personality: None,
+            cleared_sessions: HashSet::new(),
+            available,
+            warned: Arc::new(AtomicBool::new(false)),
         })
     }
 
+    /// Records sessions, their activity and summaries in `db`'s `sessions` table.
+    pub fn with_session_store(mut self, db: Arc<Database>) -> Self {
+        self.session_store = Some(db);
+        self
+    }
+
+    /// Sets the character recorded on sessions started from now on.
+    pub fn set_personality(&mut self, personality: &str) {
+        self.personality = Some(personality.to_string());
+    }
+
+    /// Writes the active session to the session store. A failure only loses metadata,
+    /// so it is logged rather than failing the chat.
+    async fn save_session(&self) {
+        if let (Some(db), Some(session)) = (&self.session_store, &self.current_session) {
+            if let Err(e) = db.save_session(session).await {
+                log::warn!("Failed to save session {}: {}", session.id, e);
+            }
+        }
+    }
+
+    /// Size of the embeddings this manager stores and searches with.
+    pub fn dimension(&self) -> usize {
+        self.dimension as usize
+    }
+
+    /// Whether Qdrant was reachable when this manager was created.
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+
+    /// True when vector operations should be skipped, warning the first time.
+    fn degraded(&self) -> bool {
+        if !self.available && !self.warned.swap(true, Ordering::Relaxed) {
+            log::warn!("Qdrant is unavailable; memories are not being stored or searched");
+        }
+        !self.available
+    }
+
+    /// Ends the active session. Its memories stay stored but are no longer retrieved
+    /// by `search_similar` or `search_with_scores`; the next chat starts a new session.
+    pub fn clear_current_session(&mut self) -> Option<ConversationSession> {
+        let session = self.current_session.take()?;
+        self.cleared_sessions.insert(session.id.clone());
+        Some(session)
+    }
+
     pub async fn start_new_session(&mut self, topic: &str) -> Result<String> {
         let session = ConversationSession {
             id: uuid::Uuid::new_v4().to_string(),
@@ -57,9 +342,11 @@ impl MemoryManager {
             topic: topic.to_string(),
             summary: String::new(),
             last_active: Utc::now(),
+            personality: self.personality.clone(),
         };
         
         self.current_session = Some(session.clone());
+        self.save_session().await;
         Ok(session.id)
     }
 
@@ -67,14 +354,47 @@ impl MemoryManager {
         if let Some(session) = &mut self.current_session {
             if Utc::now().signed_duration_since(session.last_active).num_minutes() < 30 {
                 session.last_active = Utc::now();
-                return Ok(session.id.clone());
+                let id = session.id.clone();
+                self.save_session().await;
+                return Ok(id);
             }
         }
         
         self.start_new_session(topic.unwrap_or("General Conversation")).await
     }
 
+    pub fn current_session(&self) -> Option<&ConversationSession> {
+        self.current_session.as_ref()
+    }
+
+    /// Makes a previously started session the active one again.
+    pub async fn resume_session(&mut self, mut session: ConversationSession) {
+        session.last_active = Utc::now();
+        self.current_session = Some(session);
+        self.save_session().await;
+    }
+
+    /// Stores a memory with heuristic topic tags and importance.
     pub async fn store_memory(&self, text: &str, role: &str, embedding: Vec<f32>, metadata: Option<HashMap<String, String>>) -> Result<String> {
+        self.store_memory_analyzed(text, role, embedding, metadata, None).await
+    }
+
+    /// Stores a memory, tagging it with `provider` when `MEMORY_TAGGING=llm` and one is
+    /// given, and with the heuristic otherwise or if the provider fails.
+    pub async fn store_memory_analyzed(
+        &self,
+        text: &str,
+        role: &str,
+        embedding: Vec<f32>,
+        metadata: Option<HashMap<String, String>>,
+        provider: Option<&dyn CompletionProvider>,
+    ) -> Result<String> {
+        if self.degraded() {
+            return Ok(String::new());
+        }
+        let provider = provider.filter(|_| MemoryTagging::from_env() == MemoryTagging::Provider);
+        let (topic_tags, importance) = self.tag(text, provider).await;
+
         let session_id = if let Some(session) = &self.current_session {
             session.id.clone()
         } else {
@@ -86,68 +406,277 @@ impl MemoryManager {
             timestamp: Utc::now(),
             role: role.to_string(),
             session_id,
-            importance: 1.0, // Default importance
-            topic_tags: vec![], // Will be filled by analyze_and_tag
+            importance,
+            topic_tags,
             metadata,
         };
+        let (session_id, timestamp) = (memory.session_id.clone(), memory.timestamp);
 
-        let mut payload = HashMap::new();
-        payload.insert("text".to_string(), serde_json::Value::String(memory.text.clone()));
-        payload.insert("timestamp".to_string(), serde_json::Value::String(memory.timestamp.to_rfc3339()));
-        payload.insert("role".to_string(), serde_json::Value::String(memory.role.clone()));
-        payload.insert("session_id".to_string(), serde_json::Value::String(memory.session_id.clone()));
-        payload.insert("importance".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(memory.importance as f64).unwrap()));
-        payload.insert("topic_tags".to_string(), serde_json::to_value(memory.topic_tags.clone())?);
-        
-        if let Some(meta) = memory.metadata {
-            payload.insert("metadata".to_string(), serde_json::to_value(meta)?);
+        let id = self.vector_db.store_vector(&self.collection_name, embedding, memory_payload(memory)?).await
+            .map_err(|e| Error::msg(format!("Failed to store memory: {}", e)))?;
+        if let (Some(db), Some(_)) = (&self.session_store, &self.current_session) {
+            if let Err(e) = db.touch_session(&session_id, timestamp).await {
+                log::warn!("Failed to update session {}: {}", session_id, e);
+            }
+        }
+        Ok(id)
+    }
+
+    /// Writes every memory with its embedding to `path` as JSON lines, calling `progress`
+    /// with (written, total) as it goes. Returns how many were written.
+    pub async fn export_all(&self, path: &Path, progress: &mut (dyn FnMut(usize, usize) + Send)) -> Result<usize> {
+        let records = self.records().await?;
+        let file = std::fs::File::create(path)
+            .map_err(|e| Error::msg(format!("Failed to create {}: {}", path.display(), e)))?;
+        let mut writer = std::io::BufWriter::new(file);
+        let total = records.len();
+        let mut written = 0;
+        for record in records {
+            serde_json::to_writer(&mut writer, &record)?;
+            writer.write_all(b"\n")?;
+            written += 1;
+            if written % PROGRESS_EVERY == 0 {
+                progress(written, total);
+            }
+        }
+        writer.flush()?;
+        progress(written, total);
+        Ok(written)
+    }
+
+    /// Every memory with its embedding, oldest first so an import stores them in the order
+    /// they happened.
+    pub async fn records(&self) -> Result<Vec<MemoryRecord>> {
+        if self.degraded() {
+            return Err(Error::msg("Memory is unavailable, nothing to export"));
+        }
+
+        let mut points = self.vector_db.scroll_with_vectors(&self.collection_name).await
+            .map_err(|e| Error::msg(format!("Failed to read memories: {}", e)))?;
+        points.sort_by_key(|(_, _, payload)| payload_time(payload));
+        Ok(points.into_iter()
+            .filter_map(|(_, embedding, payload)| Some(MemoryRecord::new(memory_from_payload(&payload)?, embedding)))
+            .collect())
+    }
+
+    /// Stores the memories in an `export_all` file, calling `progress` with (stored, total).
+    /// `replace` empties the collection first. With `reembed`, embeddings are generated
+    /// again instead of trusting the file's, for when the embedding model has changed.
+    /// The whole file is checked before anything is stored. Returns how many were stored.
+    pub async fn import(
+        &self,
+        path: &Path,
+        replace: bool,
+        reembed: Option<&EmbeddingGenerator>,
+        progress: &mut (dyn FnMut(usize, usize) + Send),
+    ) -> Result<usize> {
+        if self.degraded() {
+            return Err(Error::msg("Memory is unavailable, nothing can be imported"));
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| Error::msg(format!("Failed to read {}: {}", path.display(), e)))?;
+        let mut records = Vec::new();
+        for (i, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let record: MemoryRecord = serde_json::from_str(line)
+                .map_err(|e| Error::msg(format!("line {}: {}", i + 1, e)))?;
+            if reembed.is_none() && record.embedding.len() as u64 != self.dimension {
+                return Err(Error::msg(format!(
+                    "line {}: the embedding has {} dimensions but memory uses {}; import with --reembed",
+                    i + 1, record.embedding.len(), self.dimension
+                )));
+            }
+            records.push(record);
         }
 
-        self.vector_db.store_vector(&self.collection_name, embedding, payload).await
-            .map_err(|e| Error::msg(format!("Failed to store memory: {}", e)))
+        if replace {
+            self.clear_all().await?;
+        }
+        self.store_records(&records, reembed, progress).await
     }
 
-    pub async fn search_similar(&self, query_embedding: Vec<f32>, limit: u64) -> Result<Vec<Memory>> {
+    /// Stores memories from a backup, skipping any already stored (same text, session and
+    /// time) so restoring the same backup twice adds nothing. Returns how many were stored.
+    pub async fn import_records(
+        &self,
+        records: Vec<MemoryRecord>,
+        reembed: Option<&EmbeddingGenerator>,
+        progress: &mut (dyn FnMut(usize, usize) + Send),
+    ) -> Result<usize> {
+        if self.degraded() {
+            return Err(Error::msg("Memory is unavailable, nothing can be imported"));
+        }
+        if let Some((i, record)) = records.iter().enumerate()
+            .find(|(_, record)| reembed.is_none() && record.embedding.len() as u64 != self.dimension)
+        {
+            return Err(Error::msg(format!(
+                "memory {}: the embedding has {} dimensions but memory uses {}; import with --reembed",
+                i + 1, record.embedding.len(), self.dimension
+            )));
+        }
+
+        let existing: HashSet<(String, String, DateTime<Utc>)> = self.list_memories(None).await?
+            .into_iter()
+            .map(|memory| (memory.text, memory.session_id, memory.timestamp))
+            .collect();
+        let records: Vec<MemoryRecord> = records.into_iter()
+            .filter(|record| !existing.contains(&(record.text.clone(), record.session_id.clone(), record.timestamp)))
+            .collect();
+        self.store_records(&records, reembed, progress).await
+    }
+
+    /// Stores `records` in batches, re-embedding them with `reembed` when given.
+    async fn store_records(
+        &self,
+        records: &[MemoryRecord],
+        reembed: Option<&EmbeddingGenerator>,
+        progress: &mut (dyn FnMut(usize, usize) + Send),
+    ) -> Result<usize> {
+        let total = records.len();
+        let mut stored = 0;
+        for batch in records.chunks(IMPORT_BATCH) {
+            let embeddings = match reembed {
+                Some(embedder) => {
+                    let texts: Vec<String> = batch.iter().map(|record| record.text.clone()).collect();
+                    embedder.generate_batch_embeddings(&texts).await.into_iter().collect::<Result<Vec<_>>>()?
+                }
+                None => batch.iter().map(|record| record.embedding.clone()).collect(),
+            };
+            let points = batch.iter().zip(embeddings)
+                .map(|(record, embedding)| Ok((embedding, memory_payload(record.to_memory())?)))
+                .collect::<Result<Vec<_>>>()?;
+            self.vector_db.store_vectors(&self.collection_name, points).await
+                .map_err(|e| Error::msg(format!("Failed to store memories: {}", e)))?;
+            stored += batch.len();
+            progress(stored, total);
+        }
+        Ok(stored)
+    }
+
+    /// Deletes memories by the ids `store_memory` returned.
+    pub async fn forget(&self, ids: Vec<String>) -> Result<()> {
+        if self.degraded() {
+            return Ok(());
+        }
+
+        self.vector_db.delete_vectors(&self.collection_name, ids).await
+            .map_err(|e| Error::msg(format!("Failed to delete memories: {}", e)))
+    }
+
+    /// The `limit` memories closest to `query_embedding`, dropping any that score below
+    /// `min_score` (scores are normalized to 0.0..=1.0, see `normalize_score`).
+    pub async fn search_similar(&self, query_embedding: Vec<f32>, limit: u64, min_score: Option<f32>) -> Result<Vec<Memory>> {
+        if self.degraded() {
+            return Ok(Vec::new());
+        }
+
         let results = self.vector_db.search_vectors(&self.collection_name, query_embedding, limit).await
             .map_err(|e| Error::msg(format!("Failed to search memories: {}", e)))?;
 
         let memories = results.into_iter()
-            .filter_map(|(_, _, payload)| {
-                let text = payload.get("text")?.as_str()?.to_string();
-                let timestamp = payload.get("timestamp")?.as_str()
-                    .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
-                    .map(|dt| dt.with_timezone(&Utc))?;
-                let role = payload.get("role")?.as_str()?.to_string();
-                let metadata = payload.get("metadata")
-                    .and_then(|m| serde_json::from_value(m.clone()).ok());
-
-                Some(Memory {
-                    text,
-                    timestamp,
-                    role,
-                    metadata,
-                    session_id: String::new(),
-                    importance: 1.0,
-                    topic_tags: vec![],
-                })
+            .filter(|(_, score, payload)| {
+                meets_threshold(*score, min_score) && !in_sessions(payload, &self.cleared_sessions)
             })
+            .filter_map(|(_, _, payload)| memory_from_payload(&payload))
             .collect();
 
         Ok(memories)
     }
 
+    /// The `limit` most recent memories by timestamp, newest first, leaving out cleared
+    /// sessions.
     pub async fn get_recent_memories(&self, limit: u64) -> Result<Vec<Memory>> {
-        // For recent memories, we'll use a zero vector to get all memories
-        // and sort by timestamp (this could be optimized with a proper database query)
-        let zero_vector = vec![0.0; 1536];
-        let mut memories = self.search_similar(zero_vector, limit).await?;
-        
-        // Sort by timestamp, most recent first
-        memories.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        
+        if self.degraded() {
+            return Ok(Vec::new());
+        }
+
+        let cleared: Vec<String> = self.cleared_sessions.iter().cloned().collect();
+        let points = self.vector_db
+            .scroll_latest(&self.collection_name, "timestamp", limit as usize, ("session_id", &cleared))
+            .await
+            .map_err(|e| Error::msg(format!("Failed to list memories: {}", e)))?;
+        Ok(points.iter().filter_map(|(_, payload)| memory_from_payload(payload)).collect())
+    }
+
+    /// The `limit` best memories for `query_embedding` by `weights`, best first. Ranks a
+    /// wider set of nearest neighbours, so an important or recent memory can beat a closer
+    /// but trivial one.
+    pub async fn retrieve(&self, query_embedding: Vec<f32>, limit: u64, weights: &RetrievalWeights) -> Result<Vec<ScoredMemory>> {
+        let candidates = self.search_with_scores(query_embedding, limit * RETRIEVE_CANDIDATES).await?;
+        Ok(rank(candidates, limit as usize, weights, Utc::now()))
+    }
+
+    /// Like `search_similar`, but keeps the similarity score of each hit.
+    pub async fn search_with_scores(&self, query_embedding: Vec<f32>, limit: u64) -> Result<Vec<(Memory, f32)>> {
+        if self.degraded() {
+            return Ok(Vec::new());
+        }
+
+        let results = self.vector_db.search_vectors(&self.collection_name, query_embedding, limit).await
+            .map_err(|e| Error::msg(format!("Failed to search memories: {}", e)))?;
+
+        Ok(results.into_iter()
+            .filter(|(_, _, payload)| !in_sessions(payload, &self.cleared_sessions))
+            .filter_map(|(_, score, payload)| Some((memory_from_payload(&payload)?, score)))
+            .collect())
+    }
+
+    /// All stored memories, optionally limited to one session, newest first.
+    pub async fn list_memories(&self, session_id: Option<&str>) -> Result<Vec<Memory>> {
+        if self.degraded() {
+            return Ok(Vec::new());
+        }
+
+        let points = self.vector_db
+            .scroll_vectors(&self.collection_name, session_id.map(|id| ("session_id", id)))
+            .await
+            .map_err(|e| Error::msg(format!("Failed to list memories: {}", e)))?;
+
+        let mut memories: Vec<Memory> = points.iter()
+            .filter_map(|(_, payload)| memory_from_payload(payload))
+            .collect();
+        memories.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
         Ok(memories)
     }
 
+    pub async fn stats(&self) -> Result<MemoryStats> {
+        if self.degraded() {
+            return Ok(memory_stats(0, &[]));
+        }
+
+        let points = self.vector_db.count_vectors(&self.collection_name).await
+            .map_err(|e| Error::msg(format!("Failed to count memories: {}", e)))?;
+        let memories = self.list_memories(None).await?;
+        Ok(memory_stats(points, &memories))
+    }
+
+    /// Deletes one session's memories, returning how many were removed.
+    pub async fn clear_session(&self, session_id: &str) -> Result<usize> {
+        if self.degraded() {
+            return Ok(0);
+        }
+
+        let count = self.list_memories(Some(session_id)).await?.len();
+        self.vector_db.delete_by_payload(&self.collection_name, "session_id", session_id).await
+            .map_err(|e| Error::msg(format!("Failed to delete session memories: {}", e)))?;
+        Ok(count)
+    }
+
+    /// Drops and recreates the collection, returning how many memories were removed.
+    pub async fn clear_all(&self) -> Result<u64> {
+        if self.degraded() {
+            return Ok(0);
+        }
+
+        let count = self.vector_db.count_vectors(&self.collection_name).await
+            .map_err(|e| Error::msg(format!("Failed to count memories: {}", e)))?;
+        self.vector_db.delete_collection(&self.collection_name).await
+            .map_err(|e| Error::msg(format!("Failed to delete memories: {}", e)))?;
+        self.vector_db.create_collection(&self.collection_name, self.dimension).await
+            .map_err(|e| Error::msg(format!("Failed to recreate memory collection: {}", e)))?;
+        Ok(count)
+    }
+
     pub async fn summarize_memories(&self, memories: &[Memory]) -> String {
         let mut summary = String::new();
         
@@ -162,6 +691,47 @@ impl MemoryManager {
         summary
     }
 
+    /// Tags and importance for `text`: from `provider` if given, falling back to
+    /// `heuristic_tags` when it fails or returns no tags.
+    async fn tag(&self, text: &str, provider: Option<&dyn CompletionProvider>) -> (Vec<String>, f32) {
+        if let Some(provider) = provider {
+            match self.analyze_and_tag(text, provider).await {
+                Ok((tags, importance)) if !tags.is_empty() => return (tags, importance),
+                Ok(_) => {}
+                Err(e) => log::warn!("Tagging memory with the provider failed, using keywords: {}", e),
+            }
+        }
+        heuristic_tags(text)
+    }
+
+    /// Fills in topic tags and importance for stored memories, with `provider` if given and
+    /// the heuristic otherwise. Only untagged memories are changed unless `all` is set.
+    /// Returns how many were updated.
+    pub async fn retag_memories(&self, provider: Option<&dyn CompletionProvider>, all: bool) -> Result<usize> {
+        if self.degraded() {
+            return Ok(0);
+        }
+
+        let points = self.vector_db.scroll_vectors(&self.collection_name, None).await
+            .map_err(|e| Error::msg(format!("Failed to list memories: {}", e)))?;
+        let mut updated = 0;
+        for (id, payload) in points {
+            let Some(memory) = memory_from_payload(&payload) else { continue };
+            if !all && !memory.topic_tags.is_empty() {
+                continue;
+            }
+            let (tags, importance) = self.tag(&memory.text, provider).await;
+            let fields = HashMap::from([
+                ("topic_tags".to_string(), serde_json::to_value(&tags)?),
+                ("importance".to_string(), serde_json::json!(importance)),
+            ]);
+            self.vector_db.update_payload(&self.collection_name, &id, fields).await
+                .map_err(|e| Error::msg(format!("Failed to update memory: {}", e)))?;
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
     pub async fn analyze_and_tag(&self, text: &str, provider: &dyn CompletionProvider) -> Result<(Vec<String>, f32)> {
         let prompt = format!(
             "Analyze the following message and:\n\
@@ -174,7 +744,9 @@ impl MemoryManager {
         );
 
         let response = provider.complete(&prompt).await?;
-        let parts: Vec<&str> = response.split('|').collect();
+        // Models often add a preamble; the answer is the last line in the requested format
+        let line = response.lines().rev().find(|line| line.contains('|')).unwrap_or_default();
+        let parts: Vec<&str> = line.split('|').collect();
         
         if parts.len() != 2 {
             return Ok((vec![], 1.0));
@@ -182,8 +754,9 @@ impl MemoryManager {
 
         let tags: Vec<String> = parts[0]
             .split(',')
-            .map(|s| s.trim().to_string())
+            .map(|s| s.trim().trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
             .filter(|s| !s.is_empty())
+            .take(MAX_TAGS)
             .collect();
             
         let importance = parts[1]
@@ -215,16 +788,11 @@ impl MemoryManager {
         provider.complete(&prompt).await
     }
 
+    /// A session's memories in chronological order.
     pub async fn search_by_session(&self, session_id: &str) -> Result<Vec<Memory>> {
-        // For now, we'll retrieve all memories and filter
-        // This could be optimized with proper database filtering
-        let zero_vector = vec![0.0; 1536];
-        let all_memories = self.search_similar(zero_vector, 100).await?;
-        
-        Ok(all_memories
-            .into_iter()
-            .filter(|m| m.session_id == session_id)
-            .collect())
+        let mut memories = self.list_memories(Some(session_id)).await?;
+        memories.reverse();
+        Ok(memories)
     }
 
     pub async fn update_session_summary(&mut self, provider: &dyn CompletionProvider) -> Result<()> {
@@ -241,13 +809,14 @@ impl MemoryManager {
         if let Some(session) = &mut self.current_session {
             session.summary = summary;
         }
+        self.save_session().await;
 
         Ok(())
     }
 
     pub async fn get_topic_context(&self, topic: &str, limit: u64) -> Result<Vec<Memory>> {
-        let zero_vector = vec![0.0; 1536];
-        let all_memories = self.search_similar(zero_vector, 100).await?;
+        let zero_vector = vec![0.0; self.dimension as usize];
+        let all_memories = self.search_similar(zero_vector, 100, None).await?;
         
         let mut topic_memories: Vec<Memory> = all_memories
             .into_iter()
@@ -260,12 +829,604 @@ impl MemoryManager {
         Ok(topic_memories)
     }
 
-    pub async fn cleanup_old_memories(&self) -> Result<()> {
-        // Delete memories older than 30 days
-        let thirty_days_ago = Utc::now() - chrono::Duration::days(30);
-        
-        // For now, just return Ok since we don't have direct timestamp filtering in VectorDB
-        // In a real implementation, you would want to filter and delete old vectors
-        Ok(())
+    /// How long memories are kept as they are: `MEMORY_RETENTION_DAYS`, or 30 days.
+    pub fn retention() -> chrono::Duration {
+        let days = std::env::var("MEMORY_RETENTION_DAYS").ok()
+            .and_then(|d| d.trim().parse().ok())
+            .filter(|d: &i64| *d > 0)
+            .unwrap_or(DEFAULT_RETENTION_DAYS);
+        chrono::Duration::days(days)
+    }
+
+    /// Importance above which a memory is kept by compacting and pruning:
+    /// `MEMORY_KEEP_IMPORTANCE`, if set.
+    pub fn keep_importance() -> Option<f32> {
+        std::env::var("MEMORY_KEEP_IMPORTANCE").ok().and_then(|i| i.trim().parse().ok())
+    }
+
+    /// Collapses every session with no memory newer than `age` into one summary memory
+    /// written by `provider`, then deletes the session's raw turns. The summary keeps the
+    /// turns' most common topic tags, the time of the last turn, a high importance and the
+    /// mean of their embeddings. Sessions already compacted, the current session and
+    /// sessions with a turn rated above `MEMORY_KEEP_IMPORTANCE` are left alone. With
+    /// `dry_run` nothing is asked or changed, only reported.
+    pub async fn compact_older_than(
+        &self,
+        age: chrono::Duration,
+        provider: &dyn CompletionProvider,
+        dry_run: bool,
+    ) -> Result<Vec<CompactedSession>> {
+        if self.degraded() {
+            return Ok(Vec::new());
+        }
+
+        let cutoff = Utc::now() - age;
+        let keep_above = Self::keep_importance();
+        let current = self.current_session.as_ref().map(|s| s.id.as_str());
+
+        // Only the turns that will be compacted are held
+        let mut sessions: HashMap<String, CompactableTurns> = HashMap::new();
+        let mut offset = None;
+        loop {
+            let (points, next) = self.vector_db.scroll_page(&self.collection_name, offset, SCROLL_PAGE).await
+                .map_err(|e| Error::msg(format!("Failed to list memories: {}", e)))?;
+            for (id, vector, payload) in points {
+                let Some(memory) = memory_from_payload(&payload) else { continue };
+                let keep = memory.timestamp >= cutoff
+                    || Some(memory.session_id.as_str()) == current
+                    || (memory.role != SUMMARY_ROLE && keep_above.is_some_and(|threshold| memory.importance > threshold));
+                let turns = sessions.entry(memory.session_id.clone()).or_insert_with(|| Some(Vec::new()));
+                if keep {
+                    *turns = None;
+                } else if let Some(turns) = turns {
+                    turns.push((id, vector, memory));
+                }
+            }
+            match next {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        let mut compacted = Vec::new();
+        for (session_id, turns) in sessions {
+            let Some(mut turns) = turns else { continue };
+            if turns.iter().all(|(_, _, m)| m.role == SUMMARY_ROLE) {
+                continue;
+            }
+            turns.sort_by_key(|(_, _, m)| m.timestamp);
+            let memories: Vec<&Memory> = turns.iter().map(|(_, _, m)| m).collect();
+            let topic_tags = common_tags(&memories);
+            if dry_run {
+                compacted.push(CompactedSession { session_id, memories: turns.len(), topic_tags, summary: None });
+                continue;
+            }
+
+            let summary = summarize_turns(&memories, provider).await?;
+            let topic_tags = if topic_tags.is_empty() { heuristic_tags(&summary).0 } else { topic_tags };
+            let last = turns.last().map(|(_, _, m)| m);
+            let memory = Memory {
+                text: summary.clone(),
+                timestamp: last.map_or_else(Utc::now, |m| m.timestamp),
+                role: SUMMARY_ROLE.to_string(),
+                session_id: session_id.clone(),
+                importance: memories.iter().map(|m| m.importance).fold(COMPACTED_IMPORTANCE, f32::max),
+                topic_tags: topic_tags.clone(),
+                metadata: Some(HashMap::from([("compacted_from".to_string(), turns.len().to_string())])),
+            };
+            let vectors: Vec<&[f32]> = turns.iter().map(|(_, v, _)| v.as_slice()).collect();
+            let embedding = centroid(&vectors, self.dimension as usize);
+
+            // The summary is in place before anything it replaces is removed
+            self.vector_db.store_vector(&self.collection_name, embedding, memory_payload(memory)?).await
+                .map_err(|e| Error::msg(format!("Failed to store session summary: {}", e)))?;
+            let ids: Vec<String> = turns.iter().map(|(id, _, _)| id.clone()).collect();
+            for batch in ids.chunks(DELETE_BATCH) {
+                self.vector_db.delete_vectors(&self.collection_name, batch.to_vec()).await
+                    .map_err(|e| Error::msg(format!("Failed to delete compacted memories: {}", e)))?;
+            }
+            compacted.push(CompactedSession { session_id, memories: turns.len(), topic_tags, summary: Some(summary) });
+        }
+        Ok(compacted)
+    }
+
+    /// Deletes memories stored before `cutoff`, except those whose importance is above
+    /// `keep_above`. Returns how many were removed.
+    pub async fn delete_older_than(&self, cutoff: DateTime<Utc>, keep_above: Option<f32>) -> Result<usize> {
+        if self.degraded() {
+            return Ok(0);
+        }
+
+        let points = self.vector_db.scroll_older_than(&self.collection_name, "timestamp", cutoff).await
+            .map_err(|e| Error::msg(format!("Failed to find old memories: {}", e)))?;
+        let ids: Vec<String> = points.into_iter()
+            .filter(|(_, payload)| {
+                let importance = payload.get("importance").and_then(|v| v.as_f64());
+                match (keep_above, importance) {
+                    (Some(threshold), Some(importance)) => importance as f32 <= threshold,
+                    _ => true,
+                }
+            })
+            .map(|(id, _)| id)
+            .collect();
+
+        for batch in ids.chunks(DELETE_BATCH) {
+            self.vector_db.delete_vectors(&self.collection_name, batch.to_vec()).await
+                .map_err(|e| Error::msg(format!("Failed to delete old memories: {}", e)))?;
+        }
+        Ok(ids.len())
+    }
+}
+
+/// The payload a memory is stored with; `memory_from_payload` reads it back.
+fn memory_payload(memory: Memory) -> Result<HashMap<String, serde_json::Value>> {
+    let mut payload = HashMap::new();
+    payload.insert("text".to_string(), serde_json::Value::String(memory.text));
+    payload.insert("timestamp".to_string(), serde_json::Value::String(memory.timestamp.to_rfc3339()));
+    payload.insert("role".to_string(), serde_json::Value::String(memory.role));
+    payload.insert("session_id".to_string(), serde_json::Value::String(memory.session_id));
+    payload.insert("importance".to_string(), serde_json::json!(memory.importance));
+    payload.insert("topic_tags".to_string(), serde_json::to_value(memory.topic_tags)?);
+
+    if let Some(meta) = memory.metadata {
+        payload.insert("metadata".to_string(), serde_json::to_value(meta)?);
+    }
+    Ok(payload)
+}
+
+fn payload_time(payload: &HashMap<String, serde_json::Value>) -> Option<DateTime<Utc>> {
+    payload.get("timestamp")?.as_str()
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Rebuilds a `Memory` from a stored point's payload.
+fn memory_from_payload(payload: &HashMap<String, serde_json::Value>) -> Option<Memory> {
+    let text = payload.get("text")?.as_str()?.to_string();
+    let timestamp = payload.get("timestamp")?.as_str()
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .map(|dt| dt.with_timezone(&Utc))?;
+    let role = payload.get("role")?.as_str()?.to_string();
+
+    Some(Memory {
+        text,
+        timestamp,
+        role,
+        session_id: payload.get("session_id")
+            .and_then(|s| s.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        importance: payload.get("importance")
+            .and_then(|i| i.as_f64())
+            .map(|i| i as f32)
+            .unwrap_or(1.0),
+        topic_tags: payload.get("topic_tags")
+            .and_then(|t| serde_json::from_value(t.clone()).ok())
+            .unwrap_or_default(),
+        metadata: payload.get("metadata")
+            .and_then(|m| serde_json::from_value(m.clone()).ok()),
+    })
+}
+
+/// The tags used most across `memories`, most common first and ties in the order seen.
+fn common_tags(memories: &[&Memory]) -> Vec<String> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for tag in memories.iter().flat_map(|m| &m.topic_tags) {
+        match counts.iter_mut().find(|(t, _)| t == tag) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((tag, 1)),
+        }
+    }
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts.into_iter().take(COMPACTED_TAGS).map(|(tag, _)| tag.to_string()).collect()
+}
+
+/// A few sentences covering `turns`, asked of `provider` `COMPACT_TURNS` at a time.
+async fn summarize_turns(turns: &[&Memory], provider: &dyn CompletionProvider) -> Result<String> {
+    let mut parts = Vec::new();
+    for batch in turns.chunks(COMPACT_TURNS) {
+        let conversation = batch.iter()
+            .map(|m| format!("{}: {}", m.role, m.text))
+            .collect::<Vec<_>>()
+            .join("\n");
+        parts.push(provider.complete(&format!(
+            "Summarize this conversation in 2-4 sentences, keeping names, decisions, preferences and facts worth remembering:\n\n{}",
+            conversation
+        )).await?.trim().to_string());
+    }
+    if parts.len() == 1 {
+        return Ok(parts.remove(0));
+    }
+    Ok(provider.complete(&format!(
+        "Combine these summaries of one conversation, in order, into a single summary of 3-5 sentences:\n\n{}",
+        parts.join("\n\n")
+    )).await?.trim().to_string())
+}
+
+/// The mean of `vectors`, or zeros of `dimension` if there are none.
+fn centroid(vectors: &[&[f32]], dimension: usize) -> Vec<f32> {
+    let mut sum = vec![0.0; vectors.first().map_or(dimension, |v| v.len())];
+    for vector in vectors {
+        for (total, value) in sum.iter_mut().zip(vector.iter()) {
+            *total += value;
+        }
+    }
+    let count = vectors.len().max(1) as f32;
+    sum.iter().map(|total| total / count).collect()
+}
+
+/// Up to three keywords of `text` by frequency, and an importance that grows with length
+/// and with words like "remember" or "decided", for tagging without a model.
+pub fn heuristic_tags(text: &str) -> (Vec<String>, f32) {
+    let words: Vec<String> = text.split(|c: char| !c.is_alphanumeric() && c != '\'' && c != '-')
+        .map(|w| w.trim_matches(|c: char| c == '\'' || c == '-').to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for word in words.iter().filter(|w| w.chars().count() >= 4 && !w.chars().all(|c| c.is_ascii_digit())) {
+        if TAG_STOPWORDS.contains(&word.as_str()) {
+            continue;
+        }
+        match counts.iter_mut().find(|(w, _)| *w == word) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((word, 1)),
+        }
+    }
+    // Stable, so equally frequent words keep the order they first appeared in
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    let tags = counts.into_iter().take(MAX_TAGS).map(|(w, _)| w.to_string()).collect();
+
+    let length = (words.len() as f32 / 60.0).min(1.0);
+    let marked = words.iter().any(|w| IMPORTANT_MARKERS.contains(&w.as_str()));
+    let importance = 0.2 + 0.5 * length + if marked { 0.3 } else { 0.0 };
+    (tags, importance.clamp(0.0, 1.0))
+}
+
+fn rank(candidates: Vec<(Memory, f32)>, limit: usize, weights: &RetrievalWeights, now: DateTime<Utc>) -> Vec<ScoredMemory> {
+    let mut scored: Vec<ScoredMemory> = candidates.into_iter()
+        .map(|(memory, similarity)| {
+            let recency = weights.recency(memory.timestamp, now);
+            let score = weights.combine(similarity, recency, memory.importance);
+            ScoredMemory { memory, similarity, recency, score }
+        })
+        .collect();
+    scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scored.truncate(limit);
+    scored
+}
+
+fn in_sessions(payload: &HashMap<String, serde_json::Value>, sessions: &HashSet<String>) -> bool {
+    payload.get("session_id")
+        .and_then(|s| s.as_str())
+        .is_some_and(|id| sessions.contains(id))
+}
+
+fn memory_stats(points: u64, memories: &[Memory]) -> MemoryStats {
+    let sessions: HashSet<&str> = memories.iter()
+        .map(|m| m.session_id.as_str())
+        .collect();
+
+    MemoryStats {
+        points,
+        sessions: sessions.len(),
+        oldest: memories.iter().map(|m| m.timestamp).min(),
+        newest: memories.iter().map(|m| m.timestamp).max(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(text: &str, session: &str, timestamp: &str) -> HashMap<String, serde_json::Value> {
+        let mut payload = HashMap::new();
+        payload.insert("text".to_string(), serde_json::json!(text));
+        payload.insert("timestamp".to_string(), serde_json::json!(timestamp));
+        payload.insert("role".to_string(), serde_json::json!("chat"));
+        payload.insert("session_id".to_string(), serde_json::json!(session));
+        payload.insert("importance".to_string(), serde_json::json!(0.5));
+        payload.insert("topic_tags".to_string(), serde_json::json!(["rust"]));
+        payload
+    }
+
+    #[tokio::test]
+    async fn test_non_default_embedding_dimension() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+
+        let mut memory = MemoryManager::with_dimension(store.clone(), 384).await.unwrap();
+        memory.start_new_session("dims").await.unwrap();
+        let mut embedding = vec![0.0; 384];
+        embedding[0] = 1.0;
+        memory.store_memory("small vectors", "user", embedding.clone(), None).await.unwrap();
+        assert_eq!(memory.search_similar(embedding, 1, None).await.unwrap()[0].text, "small vectors");
+        assert_eq!(memory.get_recent_memories(5).await.unwrap().len(), 1);
+
+        let err = MemoryManager::with_dimension(store, 1536).await.err().unwrap();
+        assert!(err.to_string().contains("384"));
+    }
+
+    #[tokio::test]
+    async fn test_old_memories_are_deleted_unless_important() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+        let memory = MemoryManager::with_dimension(store.clone(), 3).await.unwrap();
+
+        let old = (Utc::now() - chrono::Duration::days(40)).to_rfc3339();
+        let mut important = payload("remember my birthday", "s1", &old);
+        important.insert("importance".to_string(), serde_json::json!(0.9));
+        for point in [payload("old chatter", "s1", &old), important, payload("new chatter", "s2", &Utc::now().to_rfc3339())] {
+            store.store_vector(&memory.collection_name, vec![1.0, 0.0, 0.0], point).await.unwrap();
+        }
+
+        let cutoff = Utc::now() - chrono::Duration::days(30);
+        assert_eq!(memory.delete_older_than(cutoff, Some(0.8)).await.unwrap(), 1);
+        let left: Vec<String> = memory.list_memories(None).await.unwrap().into_iter().map(|m| m.text).collect();
+        assert_eq!(left, vec!["new chatter", "remember my birthday"]);
+
+        assert_eq!(memory.delete_older_than(cutoff, None).await.unwrap(), 1);
+        assert_eq!(memory.list_memories(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stale_sessions_are_compacted_into_tagged_summaries() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        use crate::providers::mock::MockProvider;
+        let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+        let memory = MemoryManager::with_dimension(store.clone(), 3).await.unwrap();
+
+        let old = Utc::now() - chrono::Duration::days(40);
+        let tagged = |text: &str, session: &str, time: DateTime<Utc>, tags: serde_json::Value| {
+            let mut point = payload(text, session, &time.to_rfc3339());
+            point.insert("topic_tags".to_string(), tags);
+            point
+        };
+        let points = vec![
+            (vec![1.0, 0.0, 0.0], tagged("how do lifetimes work?", "old", old, serde_json::json!(["rust", "lifetimes"]))),
+            (vec![0.0, 1.0, 0.0], tagged("they bound borrows", "old", old + chrono::Duration::minutes(1), serde_json::json!(["rust"]))),
+            (vec![1.0, 0.0, 0.0], tagged("last week's question", "mixed", old, serde_json::json!(["go"]))),
+            (vec![1.0, 0.0, 0.0], tagged("today's question", "mixed", Utc::now(), serde_json::json!(["go"]))),
+        ];
+        for (vector, point) in points {
+            store.store_vector(&memory.collection_name, vector, point).await.unwrap();
+        }
+        let mock = MockProvider::with_response("They talked about Rust lifetimes bounding borrows.");
+
+        let planned = memory.compact_older_than(chrono::Duration::days(30), &mock, true).await.unwrap();
+        assert_eq!(planned.len(), 1);
+        assert_eq!((planned[0].session_id.as_str(), planned[0].memories), ("old", 2));
+        assert_eq!(planned[0].topic_tags, vec!["rust", "lifetimes"]);
+        assert_eq!(memory.list_memories(None).await.unwrap().len(), 4);
+        assert!(mock.prompts().is_empty());
+
+        let done = memory.compact_older_than(chrono::Duration::days(30), &mock, false).await.unwrap();
+        assert_eq!(done[0].summary.as_deref(), Some("They talked about Rust lifetimes bounding borrows."));
+        let old_session = memory.list_memories(Some("old")).await.unwrap();
+        assert_eq!(old_session.len(), 1);
+        let summary = &old_session[0];
+        assert_eq!((summary.role.as_str(), summary.topic_tags.clone()), (SUMMARY_ROLE, vec!["rust".to_string(), "lifetimes".to_string()]));
+        assert!(summary.importance >= COMPACTED_IMPORTANCE);
+        assert_eq!(summary.timestamp.timestamp(), (old + chrono::Duration::minutes(1)).timestamp());
+        assert!(mock.prompts()[0].contains("how do lifetimes work?"));
+        // Topic retrieval still finds the session
+        let hits = memory.search_with_scores(vec![0.5, 0.5, 0.0], 5).await.unwrap();
+        assert!(hits.iter().any(|(m, _)| m.role == SUMMARY_ROLE && m.topic_tags.contains(&"rust".to_string())));
+
+        // A session with recent turns is kept whole, and a summary isn't compacted again
+        assert_eq!(memory.list_memories(Some("mixed")).await.unwrap().len(), 2);
+        assert!(memory.compact_older_than(chrono::Duration::days(30), &mock, false).await.unwrap().is_empty());
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_recent_memories_are_newest_by_timestamp() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+        let memory = MemoryManager::with_dimension(store.clone(), 3).await.unwrap();
+
+        // Stored out of order, with the oldest closest to every query vector
+        for (text, timestamp, vector) in [
+            ("tuesday", "2025-01-07T09:00:00Z", [0.0, 1.0, 0.0]),
+            ("sunday", "2025-01-05T09:00:00Z", [1.0, 0.0, 0.0]),
+            ("wednesday", "2025-01-08T09:00:00Z", [0.0, 0.0, 1.0]),
+            ("monday", "2025-01-06T09:00:00Z", [0.5, 0.5, 0.0]),
+        ] {
+            store.store_vector(&memory.collection_name, vector.to_vec(), payload(text, "s1", timestamp)).await.unwrap();
+        }
+
+        let recent: Vec<String> = memory.get_recent_memories(2).await.unwrap().into_iter().map(|m| m.text).collect();
+        assert_eq!(recent, vec!["wednesday", "tuesday"]);
+        assert_eq!(memory.get_recent_memories(10).await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_isolated() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+        let mut memory = MemoryManager::with_dimension(store, 3).await.unwrap();
+
+        let rust = memory.start_new_session("rust").await.unwrap();
+        memory.store_memory("borrowing rules", "user", vec![1.0, 0.0, 0.0], None).await.unwrap();
+        memory.store_memory("lifetimes", "assistant", vec![0.9, 0.1, 0.0], None).await.unwrap();
+        let soup = memory.start_new_session("soup").await.unwrap();
+        memory.store_memory("miso soup", "user", vec![0.0, 1.0, 0.0], None).await.unwrap();
+
+        let texts = |memories: Vec<Memory>| memories.into_iter().map(|m| m.text).collect::<Vec<_>>();
+        assert_eq!(texts(memory.search_by_session(&rust).await.unwrap()), vec!["borrowing rules", "lifetimes"]);
+        assert_eq!(texts(memory.search_by_session(&soup).await.unwrap()), vec!["miso soup"]);
+
+        let hits = memory.search_similar(vec![1.0, 0.0, 0.0], 3, None).await.unwrap();
+        assert_eq!(hits[0].session_id, rust);
+        assert_eq!(hits[2].session_id, soup);
+        assert_eq!(hits[0].role, "user");
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_recorded_in_the_database() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        use crate::providers::mock::MockProvider;
+        let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+        let db = Arc::new(Database::new(":memory:").await.unwrap());
+        let mut memory = MemoryManager::with_dimension(store, 3).await.unwrap().with_session_store(db.clone());
+        memory.set_personality("pirate");
+
+        let id = memory.get_or_create_session(Some("rust")).await.unwrap();
+        let started = db.list_sessions(10).await.unwrap();
+        assert_eq!((started[0].id.as_str(), started[0].topic.as_str()), (id.as_str(), "rust"));
+        assert_eq!(started[0].personality.as_deref(), Some("pirate"));
+
+        memory.store_memory("borrowing rules", "user", vec![1.0, 0.0, 0.0], None).await.unwrap();
+        assert!(db.list_sessions(10).await.unwrap()[0].last_active >= started[0].last_active);
+
+        memory.update_session_summary(&MockProvider::with_response("Talked about borrowing")).await.unwrap();
+        let saved = db.find_session(id.clone()).await.unwrap();
+        assert_eq!(saved[0].summary, "Talked about borrowing");
+    }
+
+    #[test]
+    fn test_rank_weighs_recency_and_importance() {
+        let now = Utc::now();
+        let memory = |text: &str, hours_ago: i64, importance: f32| {
+            let mut memory = memory_from_payload(&payload(text, "s1", &(now - chrono::Duration::hours(hours_ago)).to_rfc3339())).unwrap();
+            memory.importance = importance;
+            memory
+        };
+        let candidates = || vec![
+            (memory("ok thanks", 24 * 30, 0.1), 0.92),
+            (memory("we chose postgres over mysql", 24, 0.9), 0.85),
+        ];
+
+        let similarity_only = RetrievalWeights { similarity: 1.0, recency: 0.0, importance: 0.0, half_life_hours: 72.0 };
+        assert_eq!(rank(candidates(), 2, &similarity_only, now)[0].memory.text, "ok thanks");
+
+        let ranked = rank(candidates(), 1, &RetrievalWeights::default(), now);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].memory.text, "we chose postgres over mysql");
+        assert!((ranked[0].recency - 0.5f32.powf(24.0 / 72.0)).abs() < 1e-3);
+        assert_eq!(ranked[0].similarity, 0.85);
+    }
+
+    #[test]
+    fn test_heuristic_tags() {
+        let (tags, importance) = heuristic_tags("Remember: we decided to deploy the Postgres migration on Friday, after the Postgres backup.");
+        assert_eq!(tags, vec!["postgres", "remember", "decided"]);
+        assert!(importance >= 0.5);
+
+        let (tags, trivial) = heuristic_tags("ok thanks!");
+        assert!(tags.is_empty());
+        assert!(trivial < importance);
+    }
+
+    #[tokio::test]
+    async fn test_memories_are_tagged_and_backfilled() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        use crate::providers::mock::MockProvider;
+        let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+        let memory = MemoryManager::with_dimension(store.clone(), 3).await.unwrap();
+
+        memory.store_memory("the borrow checker rejects my borrow", "user", vec![1.0, 0.0, 0.0], None).await.unwrap();
+        let mut untagged = payload("kubernetes pods keep restarting", "s1", &Utc::now().to_rfc3339());
+        untagged.insert("topic_tags".to_string(), serde_json::json!([]));
+        store.store_vector(&memory.collection_name, vec![0.0, 1.0, 0.0], untagged).await.unwrap();
+
+        let tags = |memories: Vec<Memory>| memories.into_iter().map(|m| (m.text, m.topic_tags)).collect::<HashMap<_, _>>();
+        let stored = tags(memory.list_memories(None).await.unwrap());
+        assert_eq!(stored["the borrow checker rejects my borrow"], vec!["borrow", "checker", "rejects"]);
+        assert!(stored["kubernetes pods keep restarting"].is_empty());
+
+        // Only the untagged memory is backfilled, here by the provider
+        let provider = MockProvider::with_responder(|_| "Tags and importance:\nDevOps, Kubernetes|0.8".to_string());
+        assert_eq!(memory.retag_memories(Some(&provider), false).await.unwrap(), 1);
+        let memories = memory.list_memories(None).await.unwrap();
+        let pods = memories.iter().find(|m| m.text.starts_with("kubernetes")).unwrap();
+        assert_eq!(pods.topic_tags, vec!["devops", "kubernetes"]);
+        assert_eq!(pods.importance, 0.8);
+
+        assert_eq!(memory.retag_memories(None, true).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_round_trip() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        use crate::providers::mock::MockProvider;
+        async fn manager(dimension: usize) -> MemoryManager {
+            let store: Arc<dyn VectorStore> = Arc::new(SqliteVectorStore::in_memory().await);
+            MemoryManager::with_dimension(store, dimension).await.unwrap()
+        }
+        let texts = |memories: Vec<Memory>| memories.into_iter().map(|m| m.text).collect::<Vec<_>>();
+
+        let source = manager(3).await;
+        for (text, timestamp, vector) in [
+            ("second", "2025-01-02T00:00:00Z", [0.0, 1.0, 0.0]),
+            ("first", "2025-01-01T00:00:00Z", [1.0, 0.0, 0.0]),
+        ] {
+            source.vector_db.store_vector(&source.collection_name, vector.to_vec(), payload(text, "s1", timestamp)).await.unwrap();
+        }
+        let path = std::env::temp_dir().join(format!("memories-{}.jsonl", uuid::Uuid::new_v4().simple()));
+        let mut reports = Vec::new();
+        assert_eq!(source.export_all(&path, &mut |done, total| reports.push((done, total))).await.unwrap(), 2);
+        assert_eq!(reports.last(), Some(&(2, 2)));
+        let lines = std::fs::read_to_string(&path).unwrap();
+        let first: MemoryRecord = serde_json::from_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!((first.text.as_str(), first.embedding.as_slice(), first.tags.as_slice()), ("first", &[1.0, 0.0, 0.0][..], &["rust".to_string()][..]));
+
+        let copy = manager(3).await;
+        assert_eq!(copy.import(&path, false, None, &mut |_, _| {}).await.unwrap(), 2);
+        assert_eq!(copy.import(&path, true, None, &mut |_, _| {}).await.unwrap(), 2);
+        assert_eq!(texts(copy.list_memories(None).await.unwrap()), vec!["second", "first"]);
+        assert_eq!(copy.search_similar(vec![0.0, 1.0, 0.0], 1, None).await.unwrap()[0].text, "second");
+
+        // Restoring a backup skips the memories already stored
+        let records = source.records().await.unwrap();
+        assert_eq!(copy.import_records(records, None, &mut |_, _| {}).await.unwrap(), 0);
+        let fresh = manager(3).await;
+        assert_eq!(fresh.import_records(source.records().await.unwrap(), None, &mut |_, _| {}).await.unwrap(), 2);
+        assert_eq!(texts(fresh.list_memories(None).await.unwrap()), vec!["second", "first"]);
+
+        // Another embedding size needs the memories embedded again
+        let resized = manager(crate::config::embedding_dim()).await;
+        let err = resized.import(&path, false, None, &mut |_, _| {}).await.unwrap_err();
+        assert!(err.to_string().contains("--reembed"));
+        assert_eq!(resized.stats().await.unwrap().points, 0);
+        let mock = MockProvider::with_response("").with_batch_embeddings();
+        let embedder = EmbeddingGenerator::from_provider(Box::new(mock.clone()));
+        assert_eq!(resized.import(&path, false, Some(&embedder), &mut |_, _| {}).await.unwrap(), 2);
+        assert_eq!(mock.embedding_requests(), 1);
+        let query = embedder.generate_embedding("first").await.unwrap();
+        assert_eq!(resized.search_similar(query, 1, None).await.unwrap()[0].text, "first");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_memory_from_payload_keeps_session_fields() {
+        let memory = memory_from_payload(&payload("hello", "s1", "2025-01-02T03:04:05Z")).unwrap();
+        assert_eq!(memory.session_id, "s1");
+        assert_eq!(memory.importance, 0.5);
+        assert_eq!(memory.topic_tags, vec!["rust"]);
+        assert!(memory_from_payload(&HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_cleared_sessions_are_excluded() {
+        let cleared = HashSet::from(["s1".to_string()]);
+        assert!(in_sessions(&payload("a", "s1", "2025-01-01T00:00:00Z"), &cleared));
+        assert!(!in_sessions(&payload("b", "s2", "2025-01-01T00:00:00Z"), &cleared));
+        assert!(!in_sessions(&HashMap::new(), &cleared));
+    }
+
+    #[test]
+    fn test_memory_stats_counts_sessions_and_range() {
+        let memories: Vec<Memory> = [
+            payload("a", "s1", "2025-01-01T00:00:00Z"),
+            payload("b", "s1", "2025-01-03T00:00:00Z"),
+            payload("c", "s2", "2025-01-02T00:00:00Z"),
+        ].iter().filter_map(memory_from_payload).collect();
+
+        let stats = memory_stats(3, &memories);
+        assert_eq!(stats.points, 3);
+        assert_eq!(stats.sessions, 2);
+        assert_eq!(stats.oldest.unwrap().to_rfc3339(), "2025-01-01T00:00:00+00:00");
+        assert_eq!(stats.newest.unwrap().to_rfc3339(), "2025-01-03T00:00:00+00:00");
+    }
+}
@@ -1,9 +1,19 @@
+pub mod budget;
+pub mod cache;
 pub mod chat;
+pub mod context;
+pub mod cost;
 pub mod memory;
+pub mod monitor;
 pub mod semantic_search;
+pub mod untrusted;
 pub mod embeddings;
+#[cfg(feature = "local-embeddings")]
+pub mod local_embeddings;
 
 pub use embeddings::EmbeddingGenerator;
 pub use memory::MemoryManager;
+pub use monitor::{MemoryMonitor, MonitorStats};
 pub use semantic_search::{SearchResult, SemanticSearch};
-pub use chat::ChatManager;
\ No newline at end of file
+pub use chat::ChatManager;
+pub use context::{ContextBuilder, ContextOptions};
\ No newline at end of file
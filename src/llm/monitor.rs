@@ -0,0 +1,202 @@
+use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
+
+use crate::llm::budget::count_tokens;
+use crate::llm::chat::SharedProvider;
+use crate::llm::MemoryManager;
+
+/// Tokens after which cleanup runs early.
+pub const DEFAULT_MAX_TOKENS: usize = 1_000_000;
+
+/// Cleanup runs at least this often.
+pub const DEFAULT_CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Tracks what the conversation has cost in tokens and keeps its most recent messages,
+/// so memory cleanup runs when the conversation has grown rather than on a timer alone.
+/// The command handler reports every completion and chat turn here.
+#[derive(Clone)]
+pub struct MemoryMonitor {
+    total_tokens: Arc<AtomicUsize>,
+    messages: Arc<AtomicUsize>,
+    last_cleanup: Arc<RwLock<SystemTime>>,
+    max_tokens: usize,
+    cleanup_interval: Duration,
+    recent_context: Arc<RwLock<Vec<String>>>,
+    context_window: usize,
+    /// Writes the summaries of compacted sessions; the handler keeps it on its active provider
+    summarizer: Arc<std::sync::RwLock<Option<SharedProvider>>>,
+}
+
+/// A snapshot of the monitor, for `stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorStats {
+    /// Tokens sent and received since the last cleanup
+    pub tokens: usize,
+    /// Messages, from either side, this run
+    pub messages: usize,
+    /// Messages held in the context window, out of `context_window`
+    pub context_len: usize,
+    pub context_window: usize,
+    pub since_cleanup: Duration,
+}
+
+impl MemoryMonitor {
+    /// Keeps the last `context_window` messages; see `config::context_window`.
+    pub fn new(max_tokens: usize, cleanup_interval: Duration, context_window: usize) -> Self {
+        Self {
+            total_tokens: Arc::new(AtomicUsize::new(0)),
+            messages: Arc::new(AtomicUsize::new(0)),
+            last_cleanup: Arc::new(RwLock::new(SystemTime::now())),
+            max_tokens,
+            cleanup_interval,
+            recent_context: Arc::new(RwLock::new(Vec::new())),
+            context_window: context_window.max(1),
+            summarizer: Arc::new(std::sync::RwLock::new(None)),
+        }
+    }
+
+    /// The defaults, keeping `CONTEXT_WINDOW` messages.
+    pub fn from_env() -> Self {
+        Self::new(DEFAULT_MAX_TOKENS, DEFAULT_CLEANUP_INTERVAL, crate::config::context_window())
+    }
+
+    pub fn add_tokens(&self, tokens: usize) {
+        self.total_tokens.fetch_add(tokens, Ordering::SeqCst);
+    }
+
+    pub fn get_total_tokens(&self) -> usize {
+        self.total_tokens.load(Ordering::SeqCst)
+    }
+
+    /// Counts a completion: the whole prompt sent and the reply that came back.
+    pub fn record_completion(&self, prompt: &str, response: &str) {
+        self.add_tokens(count_tokens(prompt) + count_tokens(response));
+    }
+
+    /// Adds one turn of the conversation, the user's message and the reply, to the context.
+    pub async fn record_turn(&self, input: &str, response: &str) {
+        self.add_context(format!("User: {}", input)).await;
+        self.add_context(format!("Assistant: {}", response)).await;
+    }
+
+    /// Has `provider` write compaction summaries from now on.
+    pub fn set_summarizer(&self, provider: SharedProvider) {
+        *self.summarizer.write().unwrap() = Some(provider);
+    }
+
+    pub async fn needs_cleanup(&self) -> bool {
+        let last_cleanup = self.last_cleanup.read().await;
+        let elapsed = last_cleanup.elapsed().unwrap_or(Duration::from_secs(0));
+
+        elapsed >= self.cleanup_interval || self.get_total_tokens() >= self.max_tokens
+    }
+
+    pub async fn add_context(&self, message: String) {
+        self.messages.fetch_add(1, Ordering::SeqCst);
+        let mut context = self.recent_context.write().await;
+        context.push(message);
+
+        // Keep only the most recent messages within context window
+        if context.len() > self.context_window {
+            let excess = context.len() - self.context_window;
+            context.drain(..excess);
+        }
+    }
+
+    pub async fn clear_context(&self) {
+        self.recent_context.write().await.clear();
+    }
+
+    pub async fn get_recent_context(&self) -> Vec<String> {
+        self.recent_context.read().await.clone()
+    }
+
+    pub async fn stats(&self) -> MonitorStats {
+        MonitorStats {
+            tokens: self.get_total_tokens(),
+            messages: self.messages.load(Ordering::SeqCst),
+            context_len: self.recent_context.read().await.len(),
+            context_window: self.context_window,
+            since_cleanup: self.last_cleanup.read().await.elapsed().unwrap_or_default(),
+        }
+    }
+
+    /// When cleanup is due, resets the token count to what the context still holds and has
+    /// the summarizer compact the sessions idle past `MEMORY_RETENTION_DAYS`.
+    pub async fn perform_cleanup(&self, memory_manager: &MemoryManager) -> Result<()> {
+        if self.needs_cleanup().await {
+            let mut last_cleanup = self.last_cleanup.write().await;
+            *last_cleanup = SystemTime::now();
+
+            // What is still in the window is all that carries over
+            let context_tokens = self.get_recent_context().await.iter()
+                .map(|msg| count_tokens(msg))
+                .sum::<usize>();
+
+            self.total_tokens.store(context_tokens, Ordering::SeqCst);
+
+            let Some(provider) = self.summarizer.read().unwrap().clone() else {
+                log::debug!("No provider to summarize with; skipping compaction");
+                return Ok(());
+            };
+            let compacted = memory_manager.compact_older_than(MemoryManager::retention(), provider.as_ref().as_ref(), false).await?;
+            if !compacted.is_empty() {
+                let memories: usize = compacted.iter().map(|s| s.memories).sum();
+                log::info!("Compacted {} memories from {} idle sessions into summaries", memories, compacted.len());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_monitor_keeps_the_configured_window() {
+        let monitor = MemoryMonitor::new(1_000, Duration::from_secs(60), 3);
+        for message in ["one", "two", "three"] {
+            monitor.add_context(message.to_string()).await;
+        }
+        assert_eq!(monitor.get_recent_context().await, vec!["one", "two", "three"]);
+
+        monitor.add_context("four".to_string()).await;
+        assert_eq!(monitor.get_recent_context().await, vec!["two", "three", "four"]);
+        assert_eq!(monitor.stats().await.messages, 4);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_summarizes_with_the_latest_provider() {
+        use crate::database::sqlite_vector_store::SqliteVectorStore;
+        use crate::database::vector_store::VectorStore;
+        use crate::providers::mock::MockProvider;
+        use std::collections::HashMap;
+
+        let store = Arc::new(SqliteVectorStore::in_memory().await);
+        let memory = MemoryManager::with_dimension(store.clone(), 3).await.unwrap();
+        let old = (chrono::Utc::now() - chrono::Duration::days(400)).to_rfc3339();
+        let payload = HashMap::from([
+            ("text".to_string(), serde_json::json!("what is a borrow checker?")),
+            ("timestamp".to_string(), serde_json::json!(old)),
+            ("role".to_string(), serde_json::json!("user")),
+            ("session_id".to_string(), serde_json::json!("old")),
+        ]);
+        store.store_vector("conversation_memory", vec![1.0, 0.0, 0.0], payload).await.unwrap();
+
+        // Due at once, since any token count reaches the limit
+        let monitor = MemoryMonitor::new(0, Duration::from_secs(3600), 3);
+        let first = MockProvider::with_response("first");
+        let switched = MockProvider::with_response("They asked about the borrow checker.");
+        monitor.set_summarizer(Arc::new(Box::new(first.clone())));
+        monitor.set_summarizer(Arc::new(Box::new(switched.clone())));
+        monitor.perform_cleanup(&memory).await.unwrap();
+
+        assert!(first.prompts().is_empty());
+        assert_eq!(switched.prompts().len(), 1);
+        assert_eq!(memory.list_memories(Some("old")).await.unwrap()[0].text, "They asked about the borrow checker.");
+    }
+}
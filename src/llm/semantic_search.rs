@@ -1,12 +1,19 @@
 use anyhow::{Result, Error};
 use serde::{Deserialize, Serialize};
-use crate::database::vector_db::VectorDB;
+use crate::database::vector_db::{meets_threshold, VectorDBError};
+use crate::database::vector_store::VectorStore;
+use crate::database::{Database, KeywordHit};
 use std::collections::HashMap;
-use crate::llm::memory::{Memory, MemoryManager};
+use crate::llm::memory::{Memory, MemoryManager, NO_RELEVANT_CONTEXT};
 use crate::providers::traits::CompletionProvider;
 use std::sync::Arc;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Candidates fetched per requested result by `search_reranked`, for callers without a preference.
+pub const DEFAULT_CANDIDATE_MULTIPLIER: u64 = 3;
+/// Rank offset for reciprocal-rank fusion; 60 is the value from the original RRF paper.
+const RRF_K: f32 = 60.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub text: String,
     pub score: f32,
@@ -15,19 +22,23 @@ pub struct SearchResult {
 }
 
 pub struct SemanticSearch {
-    vector_db: VectorDB,
+    vector_db: Arc<dyn VectorStore>,
     collection_name: String,
     provider: Arc<dyn CompletionProvider>,
     memory: MemoryManager,
+    /// Holds the FTS5 keyword index used by `search_hybrid`
+    db: Arc<Database>,
 }
 
 impl SemanticSearch {
-    pub async fn new(vector_db: VectorDB, provider: Arc<dyn CompletionProvider>, memory: MemoryManager) -> Result<Self> {
+    pub async fn new(vector_db: Arc<dyn VectorStore>, provider: Arc<dyn CompletionProvider>, memory: MemoryManager, db: Arc<Database>) -> Result<Self> {
         let collection_name = "semantic_search";
         
-        // Create collection if it doesn't exist
-        if let Err(e) = vector_db.create_collection(collection_name, 1536).await {
-            eprintln!("Note: Collection may already exist: {}", e);
+        // Create collection if it doesn't exist, at the size memory embeds with
+        match vector_db.ensure_collection(collection_name, memory.dimension() as u64).await {
+            Err(e @ VectorDBError::DimensionMismatch { .. }) => return Err(e.into()),
+            Err(e) => eprintln!("Note: Collection may already exist: {}", e),
+            Ok(()) => {}
         }
 
         Ok(Self {
@@ -35,6 +46,7 @@ impl SemanticSearch {
             collection_name: collection_name.to_string(),
             provider,
             memory,
+            db,
         })
     }
 
@@ -43,45 +55,90 @@ impl SemanticSearch {
         payload.insert("text".to_string(), serde_json::Value::String(text.to_string()));
         payload.insert("source".to_string(), serde_json::Value::String(source.to_string()));
         
+        let metadata_json = match &metadata {
+            Some(meta) => Some(serde_json::to_string(meta)?),
+            None => None,
+        };
         if let Some(meta) = metadata {
             payload.insert("metadata".to_string(), serde_json::to_value(meta)?);
         }
 
-        self.vector_db.store_vector(&self.collection_name, embedding, payload).await
-            .map_err(|e| Error::msg(format!("Failed to index text: {}", e)))
+        let point_id = self.vector_db.store_vector(&self.collection_name, embedding, payload).await
+            .map_err(|e| Error::msg(format!("Failed to index text: {}", e)))?;
+
+        // The vector is what matters most; a missing keyword entry only costs hybrid recall
+        if let Err(e) = self.db.index_keywords(
+            self.collection_name.clone(),
+            point_id.clone(),
+            text.to_string(),
+            source.to_string(),
+            metadata_json,
+        ).await {
+            eprintln!("Warning: Failed to add text to the keyword index: {}", e);
+        }
+
+        Ok(point_id)
+    }
+
+    /// Indexed texts closest to `query_embedding`. Scores are normalized to 0.0..=1.0
+    /// (0.5 is unrelated); results below `min_score` are dropped.
+    pub async fn search(&self, query_embedding: Vec<f32>, limit: u64, min_score: Option<f32>) -> Result<Vec<SearchResult>> {
+        Ok(self.search_with_ids(query_embedding, limit, min_score).await?
+            .into_iter()
+            .map(|(_, result)| result)
+            .collect())
+    }
+
+    /// Combines keyword (BM25) and vector rankings with weighted reciprocal-rank fusion, so
+    /// exact terms such as names, ids and error codes are found even when embeddings blur them.
+    /// `alpha` weights the vector ranking: 1.0 is vector-only, 0.0 keyword-only. Scores are
+    /// the fused value scaled to 0.0..=1.0, where 1.0 means ranked first by both.
+    pub async fn search_hybrid(&self, query: &str, query_embedding: Vec<f32>, limit: u64, alpha: f32) -> Result<Vec<SearchResult>> {
+        let candidates = limit * 2;
+        let vector = self.search_with_ids(query_embedding, candidates, None).await?;
+        let keyword = self.db.search_keywords(self.collection_name.clone(), query, candidates as i64).await
+            .unwrap_or_else(|e| {
+                eprintln!("Warning: Keyword search failed, using vectors only: {}", e);
+                Vec::new()
+            })
+            .into_iter()
+            .map(keyword_result)
+            .collect();
+
+        Ok(reciprocal_rank_fusion(vector, keyword, alpha, limit as usize))
     }
 
-    pub async fn search(&self, query_embedding: Vec<f32>, limit: u64) -> Result<Vec<SearchResult>> {
+    async fn search_with_ids(&self, query_embedding: Vec<f32>, limit: u64, min_score: Option<f32>) -> Result<Vec<(String, SearchResult)>> {
         let results = self.vector_db.search_vectors(&self.collection_name, query_embedding, limit).await
             .map_err(|e| Error::msg(format!("Failed to search: {}", e)))?;
 
         let search_results = results.into_iter()
-            .filter_map(|(_, score, payload)| {
-                let text = payload.get("text")?.as_str()?.to_string();
-                let source = payload.get("source")?.as_str()?.to_string();
-                let metadata = payload.get("metadata")
-                    .and_then(|m| serde_json::from_value(m.clone()).ok());
-
-                Some(SearchResult {
-                    text,
-                    score,
-                    source,
-                    metadata,
-                })
-            })
+            .filter(|(_, score, _)| meets_threshold(*score, min_score))
+            .filter_map(|(id, score, payload)| Some((id, search_result(score, &payload)?)))
             .collect();
 
         Ok(search_results)
     }
 
-    pub async fn search_by_source(&self, query_embedding: Vec<f32>, source: &str, limit: u64) -> Result<Vec<SearchResult>> {
-        // This is a basic implementation - in a real system, you'd want to use Qdrant's filtering capabilities
-        let mut results = self.search(query_embedding, limit * 2).await?;
-        
-        results.retain(|r| r.source == source);
-        results.truncate(limit as usize);
-        
-        Ok(results)
+    /// Opt-in, slower variant of `search`: fetches `limit * candidate_multiplier` candidates
+    /// for `query`, has the provider rate each one's relevance and returns the best `limit`.
+    /// Scores are then the provider's ratings, still in 0.0..=1.0.
+    pub async fn search_reranked(&self, query: &str, limit: u64, candidate_multiplier: u64) -> Result<Vec<SearchResult>> {
+        let query_embedding = self.provider.as_ref().generate_embedding(query).await?;
+        let candidates = self.search(query_embedding, limit * candidate_multiplier.max(1), None).await?;
+        Ok(rerank(self.provider.as_ref(), query, candidates, limit as usize).await)
+    }
+
+    /// Like `search`, over only the texts indexed from `source`. The store filters on the
+    /// payload before ranking, so other sources can't crowd out its results.
+    pub async fn search_by_source(&self, query_embedding: Vec<f32>, source: &str, limit: u64, min_score: Option<f32>) -> Result<Vec<SearchResult>> {
+        let results = self.vector_db.search_vectors_matching(&self.collection_name, query_embedding, limit, ("source", source)).await
+            .map_err(|e| Error::msg(format!("Failed to search: {}", e)))?;
+
+        Ok(results.into_iter()
+            .filter(|(_, score, _)| meets_threshold(*score, min_score))
+            .filter_map(|(_, score, payload)| search_result(score, &payload))
+            .collect())
     }
 
     pub async fn format_results(&self, results: &[SearchResult]) -> String {
@@ -103,8 +160,12 @@ impl SemanticSearch {
         let user_embedding = self.provider.as_ref().generate_embedding(user_message).await?;
         
         // Get relevant search results
-        let search_results = self.search(user_embedding.clone(), 5).await?;
-        let formatted_results = self.format_results(&search_results).await;
+        let search_results = self.search(user_embedding.clone(), 5, Some(crate::config::context_min_score())).await?;
+        let formatted_results = if search_results.is_empty() {
+            NO_RELEVANT_CONTEXT.to_string()
+        } else {
+            self.format_results(&search_results).await
+        };
         
         // Build prompt with search results
         let prompt = format!(
@@ -149,4 +210,209 @@ impl SemanticSearch {
         let memories = self.memory.get_recent_memories(10).await?;
         Ok(self.memory.summarize_memories(&memories).await)
     }
-} 
\ No newline at end of file
+}
+
+/// The result stored in `payload` by `index_text`, if it has one.
+fn search_result(score: f32, payload: &HashMap<String, serde_json::Value>) -> Option<SearchResult> {
+    Some(SearchResult {
+        text: payload.get("text")?.as_str()?.to_string(),
+        score,
+        source: payload.get("source")?.as_str()?.to_string(),
+        metadata: payload.get("metadata").and_then(|m| serde_json::from_value(m.clone()).ok()),
+    })
+}
+
+fn keyword_result(hit: KeywordHit) -> (String, SearchResult) {
+    let metadata = hit.metadata.and_then(|m| serde_json::from_str(&m).ok());
+    (hit.point_id, SearchResult { text: hit.text, score: 0.0, source: hit.source, metadata })
+}
+
+/// Merges two rankings of `(id, result)`, best first, into one of at most `limit` results.
+fn reciprocal_rank_fusion(
+    vector: Vec<(String, SearchResult)>,
+    keyword: Vec<(String, SearchResult)>,
+    alpha: f32,
+    limit: usize,
+) -> Vec<SearchResult> {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let mut fused: Vec<(String, SearchResult)> = Vec::new();
+
+    for (ranking, weight) in [(vector, alpha), (keyword, 1.0 - alpha)] {
+        for (rank, (id, result)) in ranking.into_iter().enumerate() {
+            let contribution = weight / (RRF_K + rank as f32 + 1.0);
+            match fused.iter_mut().find(|(existing, _)| *existing == id) {
+                Some((_, existing)) => existing.score += contribution,
+                None => fused.push((id, SearchResult { score: contribution, ..result })),
+            }
+        }
+    }
+
+    let best = 1.0 / (RRF_K + 1.0);
+    let mut results: Vec<SearchResult> = fused.into_iter()
+        .map(|(_, mut result)| {
+            result.score = (result.score / best).min(1.0);
+            result
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(limit);
+    results
+}
+
+/// Reorders `candidates` by the provider's 0-10 relevance rating for `query` and keeps the
+/// top `limit`. If the ratings can't be used, the vector order is kept.
+async fn rerank(provider: &dyn CompletionProvider, query: &str, mut candidates: Vec<SearchResult>, limit: usize) -> Vec<SearchResult> {
+    if candidates.len() > 1 {
+        let passages = candidates.iter()
+            .enumerate()
+            .map(|(i, c)| format!("[{}] {}", i + 1, c.text))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let prompt = format!(
+            "Rate how relevant each passage is to the query, from 0 (unrelated) to 10 (answers it directly). \
+            Reply with only a JSON array of {} numbers, one per passage, in order.\n\n\
+            Query: {}\n\nPassages:\n{}",
+            candidates.len(),
+            query,
+            passages
+        );
+
+        match provider.complete(&prompt).await {
+            Ok(reply) => match parse_ratings(&reply, candidates.len()) {
+                Some(ratings) => {
+                    for (candidate, rating) in candidates.iter_mut().zip(ratings) {
+                        candidate.score = (rating / 10.0).clamp(0.0, 1.0);
+                    }
+                    // Stable, so equally rated candidates keep their vector order
+                    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+                }
+                None => eprintln!("Warning: Could not parse rerank scores, keeping vector order"),
+            },
+            Err(e) => eprintln!("Warning: Rerank failed, keeping vector order: {}", e),
+        }
+    }
+
+    candidates.truncate(limit);
+    candidates
+}
+
+fn parse_ratings(reply: &str, expected: usize) -> Option<Vec<f32>> {
+    let start = reply.find('[')?;
+    let end = reply.rfind(']')?;
+    let ratings: Vec<f32> = serde_json::from_str(reply.get(start..=end)?).ok()?;
+    (ratings.len() == expected).then_some(ratings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct RatingProvider {
+        reply: String,
+        api_key: String,
+    }
+
+    #[async_trait::async_trait]
+    impl CompletionProvider for RatingProvider {
+        async fn new(api_key: String, _system_message: String) -> Result<Self> {
+            Ok(Self { reply: String::new(), api_key })
+        }
+
+        async fn complete(&self, _prompt: &str) -> Result<String> {
+            Ok(self.reply.clone())
+        }
+
+        async fn generate_embedding(&self, _text: &str) -> Result<Vec<f32>> {
+            Ok(Vec::new())
+        }
+
+        async fn update_personality(&self, _system_message: String) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_model_info(&self) -> Result<String> {
+            Ok("rating mock".to_string())
+        }
+
+        fn get_system_message(&self) -> String {
+            String::new()
+        }
+
+        fn get_api_key(&self) -> &String {
+            &self.api_key
+        }
+
+        fn clone_box(&self) -> Box<dyn CompletionProvider + Send + Sync> {
+            Box::new(Self { reply: self.reply.clone(), api_key: self.api_key.clone() })
+        }
+
+        fn with_system_message(&self, _system_message: &str) -> Box<dyn CompletionProvider + Send + Sync> {
+            self.clone_box()
+        }
+    }
+
+    fn candidates() -> Vec<SearchResult> {
+        [("tomato soup", 0.9), ("rust lifetimes", 0.8), ("borrow checker", 0.7)]
+            .into_iter()
+            .map(|(text, score)| SearchResult { text: text.to_string(), score, source: "test".to_string(), metadata: None })
+            .collect()
+    }
+
+    fn texts(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.text.as_str()).collect()
+    }
+
+    fn ranked(items: &[(&str, f32)]) -> Vec<(String, SearchResult)> {
+        items.iter()
+            .map(|(text, score)| (text.to_string(), SearchResult {
+                text: text.to_string(),
+                score: *score,
+                source: "test".to_string(),
+                metadata: None,
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn test_hybrid_ranks_exact_token_match_first() {
+        // "E0502" is blurred by the embedding but matched exactly by the keyword index
+        let vector = ranked(&[
+            ("borrowing rules overview", 0.82),
+            ("mutable references explained", 0.80),
+            ("ownership chapter", 0.78),
+            ("error E0502 when pushing while iterating", 0.56),
+        ]);
+        let keyword = ranked(&[("error E0502 when pushing while iterating", 0.0)]);
+
+        let results = reciprocal_rank_fusion(vector.clone(), keyword.clone(), 0.5, 3);
+        assert_eq!(results[0].text, "error E0502 when pushing while iterating");
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| (0.0..=1.0).contains(&r.score)));
+
+        let vector_only = reciprocal_rank_fusion(vector, keyword, 1.0, 4);
+        assert_eq!(vector_only[0].text, "borrowing rules overview");
+        assert_eq!(vector_only[3].text, "error E0502 when pushing while iterating");
+    }
+
+    #[test]
+    fn test_hybrid_includes_keyword_only_hits() {
+        let results = reciprocal_rank_fusion(ranked(&[("a", 0.9)]), ranked(&[("b", 0.0)]), 0.3, 5);
+        assert_eq!(results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>(), vec!["b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_rerank_orders_by_provider_scores() {
+        let provider = RatingProvider { reply: "Scores: [1, 7.5, 9]".to_string(), api_key: String::new() };
+        let results = rerank(&provider, "how does borrowing work?", candidates(), 2).await;
+        assert_eq!(texts(&results), vec!["borrow checker", "rust lifetimes"]);
+        assert_eq!(results[0].score, 0.9);
+        assert_eq!(results[1].score, 0.75);
+    }
+
+    #[tokio::test]
+    async fn test_rerank_keeps_vector_order_on_bad_reply() {
+        let provider = RatingProvider { reply: "[3, 4]".to_string(), api_key: String::new() };
+        let results = rerank(&provider, "anything", candidates(), 2).await;
+        assert_eq!(texts(&results), vec!["tomato soup", "rust lifetimes"]);
+    }
+}
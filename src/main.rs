@@ -5,20 +5,23 @@ use rust_ai_agent::providers::mistral::mistral::MistralProvider;
 use rust_ai_agent::providers::gemini::gemini::GeminiProvider;
 use rust_ai_agent::providers::deepseek::deepseek::DeepSeekProvider;
 use rust_ai_agent::knowledge_base::knowledge_base::KnowledgeBaseHandler;
-use rust_ai_agent::database::Database;
+use rust_ai_agent::database::{Database, VectorDB};
 use rust_ai_agent::learning::LearningManager;
 use rust_ai_agent::personality::{Personality, PersonalityProfile};
 use rust_ai_agent::providers::twitter::manager::ConversationManager;
 use rust_ai_agent::providers::web_crawler::crawler_manager::WebCrawlerManager;
-use rust_ai_agent::commands::CommandHandler;
-use rust_ai_agent::llm::MemoryManager;
+use rust_ai_agent::commands::{qdrant_url, CommandError, CommandHandler, OneShot, OutputMode, DATABASE_PATH};
+use rust_ai_agent::commands::completion::{self, ReplHelper};
+use rust_ai_agent::llm::{EmbeddingGenerator, MemoryManager, MemoryMonitor};
 use rust_ai_agent::api;
+use rust_ai_agent::AppError;
+use rust_ai_agent::config::{EmbeddingConfig, EmbeddingProviderConfig};
 use std::env;
 use std::io::Write;
-use std::path::Path;
-use std::fs::File;
-use std::net::SocketAddr;
-use clap::Parser;
+use std::io::{IsTerminal, Read};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use clap::{Parser, ValueEnum};
 use colored::Colorize;
 use dotenv::dotenv;
 use rustyline::error::ReadlineError;
@@ -28,11 +31,8 @@ use axum::serve;
 use tokio::net::TcpListener;
 use std::time::Duration;
 use tokio::time::timeout;
-use thiserror::Error;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "food")]
 mod food;
@@ -67,30 +67,89 @@ struct Args {
     #[arg(long)]
     twitter_email: Option<String>,
 
+    /// Have Twitter commands and autopost print what they would send instead of posting
+    /// (or set TWITTER_DRY_RUN)
+    #[arg(long)]
+    dry_run: bool,
+
     #[arg(long)]
     api: bool,
 
     #[arg(long, default_value = "3000")]
     port: u16,
 
+    /// Address to bind the API server to (defaults to BIND_ADDR or 0.0.0.0)
+    #[arg(long)]
+    host: Option<String>,
+
+    /// PEM certificate for serving the API over HTTPS
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key for serving the API over HTTPS
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
     #[arg(long)]
     server: bool,
 
     #[cfg(feature = "food")]
     #[arg(long)]
     food_mode: bool,
+
+    /// Run one command or chat message and exit instead of starting the REPL.
+    /// Piped stdin is used the same way, appended to this if both are given.
+    #[arg(short = 'c', long, conflicts_with = "prompt")]
+    command: Option<String>,
+
+    /// Send one chat message and exit with the reply. Unlike `-c`, the text is never
+    /// read as a command; piped stdin is appended to it.
+    #[arg(short, long)]
+    prompt: Option<String>,
+
+    /// Run each line of a file as a command, then exit; non-zero if any failed.
+    /// `#` starts a comment and `sleep <secs>` pauses between lines.
+    #[arg(long, conflicts_with_all = ["command", "prompt"])]
+    script: Option<PathBuf>,
+
+    /// With `--script`, carry on after a failing command instead of stopping
+    #[arg(long, requires = "script")]
+    keep_going: bool,
+
+    /// Only print results: no status panel, token stats or other decorations
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// `json` prints one JSON object per command on stdout and everything else on stderr
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Same as `--output json`
+    #[arg(long)]
+    json: bool,
+
+    /// Ask the provider even when `COMPLETION_CACHE` has a reply to the same prompt
+    #[arg(long)]
+    no_cache: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
 }
 
-#[derive(Error, Debug)]
-pub enum AppError {
-    #[error("Provider error: {0}")]
-    ProviderError(String),
-    #[error("Database error: {0}")]
-    DatabaseError(String),
-    #[error("API error: {0}")]
-    ApiError(String),
-    #[error("Timeout error")]
-    TimeoutError,
+impl Args {
+    /// The output mode the flags ask for, or `default` when they ask for none.
+    fn output_mode(&self, default: OutputMode) -> OutputMode {
+        if self.json || self.output == OutputFormat::Json {
+            OutputMode::Json
+        } else if self.quiet {
+            OutputMode::Plain
+        } else {
+            default
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -98,7 +157,8 @@ struct ProviderFactory {
     api_key: String,
     system_prompt: String,
     active_provider: Arc<RwLock<Box<dyn CompletionProvider + Send + Sync>>>,
-    backup_providers: Vec<Box<dyn CompletionProvider + Send + Sync>>,
+    active_name: Arc<RwLock<&'static str>>,
+    backup_providers: Vec<(&'static str, Box<dyn CompletionProvider + Send + Sync>)>,
 }
 
 impl ProviderFactory {
@@ -107,20 +167,21 @@ impl ProviderFactory {
         let primary = Box::new(DeepSeekProvider::new(api_key.clone(), system_prompt.clone()).await
             .map_err(|e| AppError::ProviderError(e.to_string()))?);
             
-        let mut backup_providers: Vec<Box<dyn CompletionProvider + Send + Sync>> = Vec::new();
+        let mut backup_providers: Vec<(&'static str, Box<dyn CompletionProvider + Send + Sync>)> = Vec::new();
         
         // Initialize backup providers
         if let Ok(provider) = OpenAIProvider::new(api_key.clone(), system_prompt.clone()).await {
-            backup_providers.push(Box::new(provider) as Box<dyn CompletionProvider + Send + Sync>);
+            backup_providers.push(("openai", Box::new(provider)));
         }
         if let Ok(provider) = MistralProvider::new(api_key.clone(), system_prompt.clone()).await {
-            backup_providers.push(Box::new(provider) as Box<dyn CompletionProvider + Send + Sync>);
+            backup_providers.push(("mistral", Box::new(provider)));
         }
         
         Ok(Self {
             api_key,
             system_prompt,
             active_provider: Arc::new(RwLock::new(primary)),
+            active_name: Arc::new(RwLock::new("deepseek")),
             backup_providers,
         })
     }
@@ -128,105 +189,42 @@ impl ProviderFactory {
     async fn get_provider(&self) -> Box<dyn CompletionProvider + Send + Sync> {
         self.active_provider.read().await.as_ref().clone_box()
     }
+
+    /// The name `use` knows the active provider by.
+    async fn active_name(&self) -> &'static str {
+        *self.active_name.read().await
+    }
     
     async fn health_check(&self) -> bool {
         let provider = self.active_provider.read().await;
         provider.as_ref().get_model_info().await.is_ok()
     }
     
-    async fn fallback_if_needed(&self) -> Result<(), AppError> {
+    /// Switches to the first healthy backup if the active provider fails, returning the
+    /// name of the provider that is active afterwards.
+    async fn fallback_if_needed(&self) -> Result<&'static str, AppError> {
         if !self.health_check().await {
             let mut active = self.active_provider.write().await;
             
             // Try each backup provider
-            for backup in &self.backup_providers {
+            for (name, backup) in &self.backup_providers {
                 if backup.get_model_info().await.is_ok() {
                     *active = backup.clone_box();
-                    return Ok(());
+                    *self.active_name.write().await = name;
+                    return Ok(name);
                 }
             }
             
             return Err(AppError::ProviderError("All providers failed".to_string()));
         }
-        Ok(())
-    }
-}
-
-#[derive(Clone)]
-struct MemoryMonitor {
-    total_tokens: Arc<AtomicUsize>,
-    last_cleanup: Arc<RwLock<SystemTime>>,
-    max_tokens: usize,
-    cleanup_interval: Duration,
-    recent_context: Arc<RwLock<Vec<String>>>,
-    context_window: usize,
-}
-
-impl MemoryMonitor {
-    fn new(max_tokens: usize, cleanup_interval: Duration) -> Self {
-        Self {
-            total_tokens: Arc::new(AtomicUsize::new(0)),
-            last_cleanup: Arc::new(RwLock::new(SystemTime::now())),
-            max_tokens,
-            cleanup_interval,
-            recent_context: Arc::new(RwLock::new(Vec::new())),
-            context_window: 20,  // Keep last 20 messages by default
-        }
-    }
-    
-    fn add_tokens(&self, tokens: usize) {
-        self.total_tokens.fetch_add(tokens, Ordering::SeqCst);
-    }
-    
-    fn get_total_tokens(&self) -> usize {
-        self.total_tokens.load(Ordering::SeqCst)
-    }
-    
-    async fn needs_cleanup(&self) -> bool {
-        let last_cleanup = self.last_cleanup.read().await;
-        let elapsed = last_cleanup.elapsed().unwrap_or(Duration::from_secs(0));
-        
-        elapsed >= self.cleanup_interval || self.get_total_tokens() >= self.max_tokens
-    }
-    
-    async fn add_context(&self, message: String) {
-        let mut context = self.recent_context.write().await;
-        context.push(message);
-        
-        // Keep only the most recent messages within context window
-        if context.len() > self.context_window {
-            context.remove(0);
-        }
-    }
-    
-    async fn get_recent_context(&self) -> Vec<String> {
-        self.recent_context.read().await.clone()
-    }
-    
-    async fn perform_cleanup(&self, memory_manager: &MemoryManager) -> Result<(), AppError> {
-        if self.needs_cleanup().await {
-            let mut last_cleanup = self.last_cleanup.write().await;
-            *last_cleanup = SystemTime::now();
-            
-            let recent_context = self.get_recent_context().await;
-            let context_tokens = recent_context.iter()
-                .map(|msg| msg.split_whitespace().count())
-                .sum::<usize>();
-            
-            self.total_tokens.store(context_tokens, Ordering::SeqCst);
-            
-            // Use cleanup_old_memories instead of smart_cleanup
-            memory_manager.cleanup_old_memories().await
-                .map_err(|e| AppError::DatabaseError(format!("Memory cleanup failed: {}", e)))?;
-        }
-        Ok(())
+        Ok(self.active_name().await)
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Initialize colored output
-    colored::control::set_override(true);
+    // Colors only on a terminal, and never when NO_COLOR is set
+    colored::control::set_override(std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal());
 
     // Load environment variables
     dotenv().ok();
@@ -242,6 +240,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 }
 
 async fn run_cli_mode(args: &Args) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let script = match &args.script {
+        Some(path) => Some(std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read script {}: {}", path.display(), e))?),
+        None => None,
+    };
+    let one_shot = if script.is_some() { None } else { one_shot_input(args)? };
+
     // Get API key from command line or environment
     let api_key = match &args.api_key {
         Some(key) => key.clone(),
@@ -266,47 +271,41 @@ async fn run_cli_mode(args: &Args) -> Result<(), Box<dyn std::error::Error + Sen
     // Initialize provider factory instead of single provider
     let provider_factory = ProviderFactory::new(api_key, personality.generate_system_prompt()).await?;
     
-    // Initialize database
-    let db = Database::new("data/agent.db").await?
-        .with_vector_db(&env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string()))
+    // Initialize database. Memory needs Qdrant, but plain chat works without it.
+    let db = Database::new(DATABASE_PATH).await?
+        .with_vector_db(&qdrant_url())
         .await?;
+    if db.get_vector_db().await.is_none() {
+        eprintln!("{}", format!("Warning: Could not connect to Qdrant at {}. Memory, sessions, web and document commands are disabled.", qdrant_url()).yellow());
+    }
 
     // Initialize knowledge base handler
     let knowledge_base_handler = KnowledgeBaseHandler::new("data/knowledge_base.json");
 
     // Initialize learning manager
+    let db = Arc::new(db);
     let learning_manager = LearningManager::new(db.clone(), knowledge_base_handler.clone());
 
     // Initialize memory monitor with context handling
-    let memory_monitor = Arc::new(MemoryMonitor::new(
-        1_000_000, // 1M tokens max
-        Duration::from_secs(3600), // Cleanup every hour
-    ));
+    let memory_monitor = Arc::new(MemoryMonitor::from_env());
     
-    // Initialize memory manager with cloned VectorDB
-    let vector_db = db.get_vector_db().await.ok_or("Failed to get vector database")?;
-    let memory_manager = MemoryManager::new(Arc::new((*vector_db).clone())).await?;
-    let memory_manager_clone = memory_manager.clone();
-    
-    // Start memory monitoring loop
-    let memory_monitor_clone = memory_monitor.clone();
-    tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(Duration::from_secs(300)).await;
-            if let Err(e) = memory_monitor_clone.perform_cleanup(&memory_manager_clone).await {
-                eprintln!("Memory cleanup failed: {}", e);
-            }
-            
-            let total_tokens = memory_monitor_clone.get_total_tokens();
-            println!("Current memory usage: {} tokens", total_tokens);
-        }
-    });
+    // Memories are only retrievable with real vectors, so embed with the configured
+    // provider rather than the chat provider whenever there is a key for it
+    let embedder = EmbeddingGenerator::from_config(&EmbeddingProviderConfig::from_env(), provider_factory.get_provider().await).await?;
+
+    // Settle the embedding size before any collection is created at it
+    let embedding = EmbeddingConfig::resolve(&embedder).await.install();
+    log::info!("Embeddings: {} ({} dimensions)", embedding.model, embedding.dimension);
+    let memory_manager = match db.get_vector_db().await {
+        Some(vector_db) => Some(MemoryManager::new(vector_db).await?),
+        None => None,
+    };
     
     // Update command handler with provider
     let mut command_handler = CommandHandler::new(
         personality.clone(),
         if args.twitter {
-            Some(ConversationManager::new(personality.clone()).await?)
+            Some(ConversationManager::new(personality.clone(), args.dry_run || rust_ai_agent::config::twitter_dry_run()).await?)
         } else {
             None
         },
@@ -316,17 +315,66 @@ async fn run_cli_mode(args: &Args) -> Result<(), Box<dyn std::error::Error + Sen
             None
         },
         provider_factory.get_provider().await,
+        provider_factory.active_name().await,
+        db,
+        memory_manager.clone(),
+        embedder,
+        learning_manager,
     ).await?;
+    command_handler.set_memory_monitor(memory_monitor.clone());
+    if args.no_cache {
+        command_handler.bypass_completion_cache();
+    }
 
-    // Add message tracking (if CommandHandler supports it)
-    let memory_monitor_clone = memory_monitor.clone();
-    tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(Duration::from_secs(1)).await;
-            // Track messages through memory monitor
-            memory_monitor_clone.add_tokens(1); // Example token tracking
+    // One-shot and script runs finish and exit, so they need none of the background loops below
+    if let Some(script) = script {
+        command_handler.set_output_mode(args.output_mode(OutputMode::Plain));
+        match command_handler.run_script(&script, args.keep_going).await {
+            Ok(report) => {
+                eprintln!("{}", report.summary());
+                if !report.succeeded() {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
         }
+        return Ok(());
+    }
+    if let Some(input) = one_shot {
+        command_handler.set_output_mode(args.output_mode(OutputMode::Plain));
+        if let Err(e) = command_handler.run_once(&input).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Start memory monitoring loop
+    let memory_monitor_clone = memory_monitor.clone();
+    if let Some(memory_manager_clone) = memory_manager {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(Duration::from_secs(300)).await;
+                if let Err(e) = memory_monitor_clone.perform_cleanup(&memory_manager_clone).await {
+                    eprintln!("Memory cleanup failed: {}", e);
+                }
+                
+                let total_tokens = memory_monitor_clone.get_total_tokens();
+                log::debug!("Current memory usage: {} tokens", total_tokens);
+            }
+        });
+    }
+
+    // A REPL whose output is piped to another program speaks JSON unless told otherwise
+    let output_mode = args.output_mode(if std::io::stdout().is_terminal() {
+        OutputMode::Interactive
+    } else {
+        OutputMode::Json
     });
+    command_handler.set_output_mode(output_mode);
 
     // Start health check loop
     let provider_factory_clone = provider_factory.clone();
@@ -339,21 +387,65 @@ async fn run_cli_mode(args: &Args) -> Result<(), Box<dyn std::error::Error + Sen
         }
     });
 
-    // Show initial help menu
-    command_handler.handle_command("help").await?;
+    // Show what's running, rather than the full command list
+    if output_mode == OutputMode::Interactive {
+        command_handler.show_status().await?;
+    }
 
     // Initialize rustyline editor
-    let mut rl = Editor::<(), DefaultHistory>::new()?;
+    let mut rl = Editor::<ReplHelper, DefaultHistory>::new()?;
+    rl.set_helper(Some(ReplHelper::new()));
+    let history_path = completion::history_path();
+    if let Some(path) = &history_path {
+        // A missing file just means this is the first run
+        let _ = rl.load_history(path);
+    }
 
     // Main input loop
     loop {
-        match rl.readline("👤 ") {
+        let prompt = match output_mode {
+            OutputMode::Interactive => format!("👤 [{}] ", command_handler.get_current_provider_name()),
+            _ => "> ".to_string(),
+        };
+        match rl.readline(&prompt) {
             Ok(line) => {
                 let input = line.trim();
                 rl.add_history_entry(input);
+                if let Some(path) = &history_path {
+                    if let Err(e) = rl.append_history(path) {
+                        eprintln!("Warning: Failed to save history: {}", e);
+                    }
+                }
 
-                if let Err(e) = command_handler.handle_command(input).await {
-                    println!("{}", e.red());
+                // Multi-line messages go straight to the chat
+                let message = if input == "paste" {
+                    match read_paste(&mut rl) {
+                        Some(message) => Some(message),
+                        None => continue,
+                    }
+                } else {
+                    completion::multiline_message(&line)
+                };
+
+                // Ctrl-C while a reply is generated stops the reply and keeps what arrived;
+                // otherwise it cancels just the running command
+                let interrupt = command_handler.interrupt();
+                let result = tokio::select! {
+                    result = async {
+                        match &message {
+                            Some(message) => command_handler.handle_message(message).await,
+                            None => command_handler.handle_command(input).await,
+                        }
+                    } => result,
+                    _ = async {
+                        while tokio::signal::ctrl_c().await.is_ok() && interrupt.cancel_generation() {}
+                    } => Err(CommandError::Interrupted("\n⏹️ Cancelled".to_string())),
+                };
+                match result {
+                    Ok(()) => {}
+                    // A mistyped or stopped command only needs a nudge; anything else really failed
+                    Err(e) if e.is_usage() || matches!(e, CommandError::Interrupted(_)) => eprintln!("{}", e.to_string().yellow()),
+                    Err(e) => eprintln!("{}", e.to_string().red()),
                 }
             }
             Err(ReadlineError::Interrupted) => {
@@ -373,16 +465,51 @@ async fn run_cli_mode(args: &Args) -> Result<(), Box<dyn std::error::Error + Sen
     Ok(())
 }
 
-fn load_personality_from_filename(filename: &str) -> Option<Personality> {
-    let path = Path::new("characters").join(filename);
-    if path.exists() {
-        if let Ok(file) = File::open(path) {
-            if let Ok(profile) = serde_json::from_reader::<_, PersonalityProfile>(file) {
-                return Some(Personality::Dynamic(profile));
+/// Reads lines for `paste` until a lone `.`; `None` if cancelled with Ctrl-C or Ctrl-D.
+fn read_paste(rl: &mut Editor<ReplHelper, DefaultHistory>) -> Option<String> {
+    println!("📋 Paste your message, then a line with just '.' to send it.");
+    let mut lines = Vec::new();
+    loop {
+        match rl.readline("… ") {
+            Ok(line) if line.trim() == "." => return Some(lines.join("\n")),
+            Ok(line) => lines.push(line),
+            Err(_) => {
+                println!("Paste cancelled.");
+                return None;
             }
         }
     }
-    None
+}
+
+/// The input for a single non-interactive run: `-c` or `--prompt`, piped stdin, or both.
+fn one_shot_input(args: &Args) -> std::io::Result<Option<OneShot>> {
+    let piped = if std::io::stdin().is_terminal() {
+        None
+    } else {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        Some(input.trim().to_string()).filter(|input| !input.is_empty())
+    };
+
+    let join = |text: String| match &piped {
+        Some(piped) => format!("{}\n\n{}", text, piped),
+        None => text,
+    };
+    Ok(match (args.prompt.clone(), args.command.clone()) {
+        (Some(prompt), _) => Some(OneShot::Prompt(join(prompt))),
+        (None, Some(command)) => Some(OneShot::Command(join(command))),
+        (None, None) => piped.clone().map(OneShot::Command),
+    })
+}
+
+fn load_personality_from_filename(filename: &str) -> Option<Personality> {
+    match PersonalityProfile::load_from_dir("characters", filename) {
+        Ok(profile) => Some(Personality::Dynamic(profile)),
+        Err(e) => {
+            eprintln!("Failed to load character {}: {}", filename, e);
+            None
+        }
+    }
 }
 
 fn create_default_personality() -> Personality {
@@ -401,12 +528,45 @@ fn create_default_personality() -> Personality {
     })
 }
 
+fn resolve_bind_addr(host: Option<&str>, port: u16) -> Result<SocketAddr, String> {
+    let host = host.map(str::to_string)
+        .or_else(|| env::var("BIND_ADDR").ok())
+        .unwrap_or_else(|| "0.0.0.0".to_string());
+
+    (host.trim_start_matches('[').trim_end_matches(']'), port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Invalid bind address '{}': {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("Bind address '{}' did not resolve", host))
+}
+
+#[cfg(feature = "tls")]
+async fn load_tls_config(cert: &std::path::Path, key: &std::path::Path) -> Result<axum_server::tls_rustls::RustlsConfig, String> {
+    for (label, path) in [("certificate", cert), ("key", key)] {
+        if !path.is_file() {
+            return Err(format!("TLS {} not found: {}", label, path.display()));
+        }
+    }
+    axum_server::tls_rustls::RustlsConfig::from_pem_file(cert, key).await
+        .map_err(|e| format!("Failed to load TLS certificate {} / key {}: {}", cert.display(), key.display(), e))
+}
+
 async fn run_api_server(args: Args) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let addr: SocketAddr = format!("0.0.0.0:{}", args.port)
-        .parse()
-        .expect("Failed to parse address");
+    let addr = resolve_bind_addr(args.host.as_deref(), args.port)?;
 
-    println!("Starting API server on {}", addr);
+    // Check TLS settings before doing any expensive setup
+    #[cfg(feature = "tls")]
+    let tls_config = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(load_tls_config(cert, key).await?),
+        _ => None,
+    };
+    #[cfg(not(feature = "tls"))]
+    if args.tls_cert.is_some() {
+        return Err("TLS support is not compiled in. Rebuild with `--features tls` to use --tls-cert/--tls-key.".into());
+    }
+
+    let scheme = if args.tls_cert.is_some() { "https" } else { "http" };
+    println!("Starting API server on {}://{}", scheme, addr);
 
     // Get API key from command line or environment
     let api_key = match &args.api_key {
@@ -430,8 +590,8 @@ async fn run_api_server(args: Args) -> Result<(), Box<dyn std::error::Error + Se
     };
 
     // Initialize database
-    let db = Database::new("data/agent.db").await?
-        .with_vector_db(&env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string()))
+    let db = Database::new(DATABASE_PATH).await?
+        .with_vector_db(&qdrant_url())
         .await?;
 
     println!("Initializing API routes...");
@@ -443,23 +603,45 @@ async fn run_api_server(args: Args) -> Result<(), Box<dyn std::error::Error + Se
         None
     };
 
-    // Create memory manager with vector database
-    let vector_db = db.get_vector_db().await.expect("Failed to get vector database");
-    let memory_manager = MemoryManager::new(Arc::new((*vector_db).clone())).await?;
-
     // Create a new DeepSeek provider for the API
     let api_key = std::env::var("DEEPSEEK_API_KEY")
         .expect("DEEPSEEK_API_KEY environment variable not set");
     let deepseek_provider = DeepSeekProvider::new(api_key, personality.generate_system_prompt()).await?;
 
-    let app = api::create_api(deepseek_provider, personality, db, crawler, memory_manager).await;
+    // Embeddings come from their own provider, whichever one chat uses
+    let embedder = EmbeddingGenerator::from_config(&EmbeddingProviderConfig::from_env(), deepseek_provider.clone_box()).await?;
+
+    // Settle the embedding size before any collection is created at it
+    let embedding = EmbeddingConfig::resolve(&embedder).await.install();
+    log::info!("Embeddings: {} ({} dimensions)", embedding.model, embedding.dimension);
+
+    // Create memory manager with vector database; without Qdrant it stores and finds nothing
+    let vector_db = match db.get_vector_db().await {
+        Some(vector_db) => vector_db,
+        None => Arc::new(VectorDB::connect_lazy(&qdrant_url()).map_err(|e| format!("Invalid QDRANT_URL: {}", e))?),
+    };
+    let memory_manager = MemoryManager::new(vector_db).await?;
+
+    let app = api::create_api(deepseek_provider, personality, db, crawler, memory_manager, embedder).await;
 
     println!("API routes configured, attempting to bind to address...");
 
+    #[cfg(feature = "tls")]
+    if let Some(tls_config) = tls_config {
+        println!("Server listening on {}://{}", scheme, addr);
+        println!("Ready to accept connections!");
+
+        axum_server::bind_rustls(addr, tls_config)
+            .serve(app.into_make_service())
+            .await
+            .map_err(|e| format!("Server error: {}", e))?;
+        return Ok(());
+    }
+
     let listener = TcpListener::bind(&addr).await
         .map_err(|e| format!("Failed to bind to {}: {}", addr, e))?;
 
-    println!("Server successfully bound to {}", addr);
+    println!("Server listening on {}://{}", scheme, addr);
     println!("Ready to accept connections!");
 
     axum::serve(listener, app)
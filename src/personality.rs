@@ -1,7 +1,64 @@
 use serde::{Serialize, Deserialize};
 use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use chrono::Local;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PersonalityError {
+    #[error("Character not found: {0}")]
+    NotFound(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid character JSON: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Circular character inheritance: {0}")]
+    InheritanceCycle(String),
+}
+
+/// Builds the values available to `{{placeholder}}` tokens in character attributes.
+/// `user_name` is only present when the `USER_NAME` environment variable is set.
+pub fn render_context() -> HashMap<String, String> {
+    let now = Local::now();
+    let mut context = HashMap::new();
+    context.insert("date".to_string(), now.format("%Y-%m-%d").to_string());
+    context.insert("time".to_string(), now.format("%H:%M").to_string());
+    if let Ok(user_name) = env::var("USER_NAME") {
+        context.insert("user_name".to_string(), user_name);
+    }
+    context
+}
+
+/// Replaces `{{key}}` tokens with values from `context`.
+/// Unknown or unterminated placeholders are kept literally.
+pub fn render_template(template: &str, context: &HashMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        match after_open.find("}}") {
+            Some(end) => {
+                let key = after_open[..end].trim();
+                match context.get(key) {
+                    Some(value) => rendered.push_str(value),
+                    None => rendered.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after_open[end + 2..];
+            }
+            None => {
+                rendered.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PersonalityProfile {
@@ -31,6 +88,10 @@ impl PersonalityProfile {
     }
 
     pub fn generate_system_prompt(&self) -> String {
+        self.generate_system_prompt_with_context(&render_context())
+    }
+
+    pub fn generate_system_prompt_with_context(&self, context: &HashMap<String, String>) -> String {
         let description = self.get_str("description")
             .unwrap_or("an AI assistant");
         
@@ -102,7 +163,7 @@ impl PersonalityProfile {
             })
             .unwrap_or_default();
 
-        format!(
+        let prompt = format!(
             "You are {}{}, {}. Your communication style is {}.{}{}{}{}{}\n\
              Always stay in character and respond as this personality would. Use the provided emotes and emojis frequently to express yourself. \
              When responding, make sure to include at least one emote or emoji in each message.",
@@ -115,13 +176,107 @@ impl PersonalityProfile {
             interests,
             emotes,
             examples
-        )
+        );
+
+        render_template(&prompt, context)
     }
 
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
         Ok(Self::from_json(&content)?)
     }
+
+    /// Names of the `.json` characters in `dir`, sorted.
+    pub fn available_in_dir<P: AsRef<Path>>(dir: P) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .map(|entries| {
+                entries.filter_map(Result::ok)
+                    .filter_map(|entry| entry.file_name().to_str().map(String::from))
+                    .filter_map(|name| name.strip_suffix(".json").map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// Loads a character from `dir`, resolving its `"base"` chain.
+    /// Base attributes are deep-merged underneath the child's, so the child wins.
+    pub fn load_from_dir<P: AsRef<Path>>(dir: P, name: &str) -> Result<Self, PersonalityError> {
+        Self::load_with_chain(dir.as_ref(), name, &mut Vec::new())
+    }
+
+    fn load_with_chain(dir: &Path, name: &str, chain: &mut Vec<String>) -> Result<Self, PersonalityError> {
+        let key = name.trim_end_matches(".json").to_string();
+        if chain.contains(&key) {
+            chain.push(key);
+            return Err(PersonalityError::InheritanceCycle(chain.join(" -> ")));
+        }
+
+        let path = resolve_character_path(dir, name)
+            .ok_or_else(|| PersonalityError::NotFound(name.to_string()))?;
+        let mut profile = Self::from_json(&fs::read_to_string(path)?)?;
+
+        let base_name = profile.attributes.as_object_mut()
+            .and_then(|attrs| attrs.remove("base"))
+            .and_then(|base| base.as_str().map(String::from));
+
+        if let Some(base_name) = base_name {
+            chain.push(key);
+            let base = Self::load_with_chain(dir, &base_name, chain)?;
+            chain.pop();
+
+            let mut attributes = base.attributes;
+            merge_attributes(&mut attributes, profile.attributes);
+            profile.attributes = attributes;
+        }
+
+        Ok(profile)
+    }
+}
+
+/// Derives a safe file stem from a character name, e.g. "Dr. Rissa" -> "dr_rissa".
+pub fn character_file_stem(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn resolve_character_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let path = dir.join(name);
+    if path.is_file() {
+        return Some(path);
+    }
+    if !name.ends_with(".json") {
+        let path = dir.join(format!("{}.json", name));
+        if path.is_file() {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// Recursively merges `overlay` into `base`; objects merge key by key, anything else is replaced.
+fn merge_attributes(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_attributes(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -150,3 +305,119 @@ impl std::fmt::Display for Personality {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> HashMap<String, String> {
+        let mut context = HashMap::new();
+        context.insert("date".to_string(), "2025-01-28".to_string());
+        context.insert("user_name".to_string(), "Rysz".to_string());
+        context
+    }
+
+    #[test]
+    fn test_render_template_substitutes_known_placeholders() {
+        let rendered = render_template("Hello {{user_name}}, today is {{ date }}.", &context());
+        assert_eq!(rendered, "Hello Rysz, today is 2025-01-28.");
+    }
+
+    #[test]
+    fn test_render_template_keeps_unknown_placeholders() {
+        let rendered = render_template("{{weather}} on {{date}} {{unterminated", &context());
+        assert_eq!(rendered, "{{weather}} on 2025-01-28 {{unterminated");
+    }
+
+    fn write_characters(files: &[(&str, Value)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("characters_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            fs::write(dir.join(format!("{}.json", name)), content.to_string()).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_load_merges_base_profile() {
+        let dir = write_characters(&[
+            ("base_chef", serde_json::json!({
+                "name": "Base Chef",
+                "style": "warm and precise",
+                "expertise": "cooking",
+                "emotes": { "default": ["*stirs pot*"] }
+            })),
+            ("pastry_chef", serde_json::json!({
+                "name": "Pastry Chef",
+                "base": "base_chef",
+                "description": "a pastry specialist"
+            })),
+        ]);
+
+        let profile = PersonalityProfile::load_from_dir(&dir, "pastry_chef").unwrap();
+        assert_eq!(profile.name, "Pastry Chef");
+        assert_eq!(profile.get_str("style"), Some("warm and precise"));
+        assert_eq!(profile.get_str("description"), Some("a pastry specialist"));
+        assert!(profile.get_str("base").is_none());
+        assert!(profile.generate_system_prompt().contains("warm and precise"));
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_load_child_overrides_base() {
+        let dir = write_characters(&[
+            ("root", serde_json::json!({
+                "name": "Root",
+                "style": "formal",
+                "emotes": { "default": ["*nods*"], "teaching": ["*explains*"] }
+            })),
+            ("middle", serde_json::json!({
+                "name": "Middle",
+                "base": "root",
+                "style": "casual"
+            })),
+            ("leaf", serde_json::json!({
+                "name": "Leaf",
+                "base": "middle.json",
+                "emotes": { "default": ["*waves*"] }
+            })),
+        ]);
+
+        let profile = PersonalityProfile::load_from_dir(&dir, "leaf").unwrap();
+        assert_eq!(profile.get_str("style"), Some("casual"));
+        let emotes = profile.get_object("emotes").unwrap();
+        assert_eq!(emotes["default"], serde_json::json!(["*waves*"]));
+        assert_eq!(emotes["teaching"], serde_json::json!(["*explains*"]));
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_load_detects_inheritance_cycle() {
+        let dir = write_characters(&[
+            ("first", serde_json::json!({ "name": "First", "base": "second" })),
+            ("second", serde_json::json!({ "name": "Second", "base": "first" })),
+        ]);
+
+        let result = PersonalityProfile::load_from_dir(&dir, "first");
+        assert!(matches!(result, Err(PersonalityError::InheritanceCycle(_))));
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_system_prompt_renders_attributes() {
+        let profile = PersonalityProfile {
+            name: "Tester".to_string(),
+            attributes: serde_json::json!({
+                "description": "an assistant helping {{user_name}}",
+                "motto": "Stay sharp on {{date}} and {{unknown}}"
+            }),
+        };
+
+        let prompt = profile.generate_system_prompt_with_context(&context());
+        assert!(prompt.contains("an assistant helping Rysz"));
+        assert!(prompt.contains("Stay sharp on 2025-01-28 and {{unknown}}"));
+    }
+}
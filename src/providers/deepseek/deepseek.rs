@@ -1,21 +1,44 @@
 use async_trait::async_trait;
 use anyhow::{Result, anyhow};
-use crate::providers::traits::CompletionProvider;
+use crate::providers::traits::{CompletionOptions, CompletionProvider};
+use crate::config::configured_models;
+use crate::providers::http::{check_key, shared_client};
 use crate::providers::utils::get_placeholder_embedding;
 use reqwest::Client;
 use serde_json::{json, Value};
 use std::sync::{Arc, RwLock};
 use std::env;
 
+#[cfg(test)]
+thread_local! {
+    /// Providers built on this thread, so tests can check requests reuse one.
+    static CONSTRUCTED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 #[derive(Clone)]
 pub struct DeepSeekProvider {
     api_key: String,
     system_message: Arc<RwLock<String>>,
-    client: Client,
+    client: Arc<Client>,
     model: String,
 }
 
 impl DeepSeekProvider {
+    /// Like `new`, but sends requests through `client` instead of the shared one.
+    pub fn with_client(api_key: String, system_message: String, client: Arc<Client>) -> Self {
+        let model = env::var("DEEPSEEK_MODEL").unwrap_or_else(|_| "deepseek-chat".to_string());
+        #[cfg(test)]
+        CONSTRUCTED.with(|n| n.set(n.get() + 1));
+
+        Self {
+            api_key,
+            system_message: Arc::new(RwLock::new(system_message)),
+            client,
+            model,
+        }
+    }
+
+    /// A copy speaking with `system_prompt`, sharing the key, model and client.
     pub fn clone_with_prompt(&self, system_prompt: &str) -> Self {
         Self {
             api_key: self.api_key.clone(),
@@ -33,17 +56,14 @@ impl DeepSeekProvider {
 #[async_trait]
 impl CompletionProvider for DeepSeekProvider {
     async fn new(api_key: String, system_message: String) -> Result<Self> {
-        let model = env::var("DEEPSEEK_MODEL").unwrap_or_else(|_| "deepseek-chat".to_string());
-        
-        Ok(Self {
-            api_key,
-            system_message: Arc::new(RwLock::new(system_message)),
-            client: Client::new(),
-            model,
-        })
+        Ok(Self::with_client(api_key, system_message, shared_client()))
     }
 
     async fn complete(&self, prompt: &str) -> Result<String> {
+        self.complete_with_options(prompt, &CompletionOptions::default()).await
+    }
+
+    async fn complete_with_options(&self, prompt: &str, options: &CompletionOptions) -> Result<String> {
         let system_message = self.system_message.read().map_err(|e| anyhow!("Failed to read system message: {}", e))?.clone();
         
         let response = self.client
@@ -62,7 +82,7 @@ impl CompletionProvider for DeepSeekProvider {
                         "content": prompt
                     }
                 ],
-                "temperature": 0.7
+                "temperature": options.temperature_for("deepseek")
             }))
             .send()
             .await?;
@@ -99,6 +119,10 @@ impl CompletionProvider for DeepSeekProvider {
         get_placeholder_embedding(text).await
     }
 
+    fn has_semantic_embeddings(&self) -> bool {
+        false
+    }
+
     async fn update_personality(&self, system_message: String) -> Result<()> {
         let mut guard = self.system_message.write().map_err(|e| anyhow!("Lock error: {}", e))?;
         *guard = system_message;
@@ -117,7 +141,63 @@ impl CompletionProvider for DeepSeekProvider {
         Box::new(self.clone())
     }
 
+    fn with_system_message(&self, system_message: &str) -> Box<dyn CompletionProvider + Send + Sync> {
+        Box::new(self.clone_with_prompt(system_message))
+    }
+
     async fn get_model_info(&self) -> Result<String> {
         Ok(self.model.clone())
     }
+
+    async fn check_access(&self) -> Result<String> {
+        check_key(self.client.get("https://api.deepseek.com/models").bearer_auth(&self.api_key)).await?;
+        Ok(self.model.clone())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        Ok(configured_models("deepseek", &self.model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_providers_share_one_client() {
+        let first = DeepSeekProvider::new("key".to_string(), String::new()).await.unwrap();
+        let second = DeepSeekProvider::new("key".to_string(), String::new()).await.unwrap();
+        assert!(Arc::ptr_eq(&first.client, &second.client));
+        assert!(Arc::ptr_eq(&first.clone_with_prompt("other").client, &first.client));
+
+        let own = DeepSeekProvider::with_client("key".to_string(), String::new(), Arc::new(Client::new()));
+        assert!(!Arc::ptr_eq(&own.client, &first.client));
+    }
+
+    #[tokio::test]
+    async fn test_prompt_changes_reuse_the_provider() {
+        let shared = Arc::new(DeepSeekProvider::new("key".to_string(), "old prompt".to_string()).await.unwrap());
+        let built = CONSTRUCTED.with(|n| n.get());
+
+        // What each /chat request does with the current personality
+        for prompt in ["pirate", "poet", "pirate"] {
+            let provider = shared.clone_with_prompt(prompt);
+            assert_eq!(provider.get_system_message(), prompt);
+            assert!(Arc::ptr_eq(&provider.client, &shared.client));
+        }
+        // What /character does
+        shared.update_personality("new prompt".to_string()).await.unwrap();
+        assert_eq!(shared.get_system_message(), "new prompt");
+
+        assert_eq!(CONSTRUCTED.with(|n| n.get()), built);
+    }
+
+    #[tokio::test]
+    async fn test_list_models_falls_back_to_configured_list() {
+        let provider = DeepSeekProvider::new("key".to_string(), String::new()).await.unwrap();
+        let models = provider.list_models().await.unwrap();
+        assert_eq!(models[0], provider.model);
+        assert!(models.iter().any(|m| m == "deepseek-reasoner"));
+        assert_eq!(models.iter().filter(|m| **m == provider.model).count(), 1);
+    }
 }
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use crate::providers::deepseek::deepseek::DeepSeekProvider;
 use crate::providers::openai::openai::OpenAIProvider;
-use crate::providers::traits::CompletionProvider;
+use crate::providers::traits::{CompletionOptions, CompletionProvider};
 use std::fmt;
 use anyhow::{Result, Error};
 use qdrant_client::{
@@ -12,6 +12,8 @@ use qdrant_client::{
         Condition, Filter, MinShould,
         condition::{self, ConditionOneOf},
         Range,
+        CreateCollection, VectorParams, VectorsConfig, Distance,
+        ScrollPoints,
     },
     Qdrant,
 };
@@ -20,12 +22,20 @@ use std::sync::Arc;
 use uuid::Uuid;
 use log;
 use crate::database::qdrant_config::create_qdrant_client;
+use crate::database::vector_db::{meets_threshold, normalize_score};
+use crate::llm::untrusted::wrap_untrusted;
 use serde_json;
 use serde_json::json;
 use lru::LruCache;
 use std::sync::Mutex;
 use std::num::NonZeroUsize;
 use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+pub const INSIGHTS_COLLECTION: &str = "document_insights";
+pub const CHUNKS_COLLECTION: &str = "document_chunks";
+/// Times the model is asked to fix an unparseable insight list before falling back to lines
+const REPAIR_ATTEMPTS: usize = 2;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Insight {
@@ -70,6 +80,98 @@ pub struct SearchResult {
     pub score: f32,
     pub page_number: i32,
     pub chunk_index: i32,
+    pub document_id: Option<String>,
+}
+
+/// Restricts a search to the points of one document.
+fn document_filter(document_id: Option<&str>) -> Option<Filter> {
+    document_id.map(|id| Filter::must([Condition::matches("document_id", id.to_string())]))
+}
+
+/// Key of a processed chunk in the cache; pages and chunk numbers repeat across documents.
+fn chunk_cache_key(document_id: &str, page: i32, chunk: i32) -> String {
+    format!("{}_page_{}_chunk_{}", document_id, page, chunk)
+}
+
+/// Qdrant payload for a stored chunk, tagged with the document it came from.
+fn chunk_payload(chunk: &DocumentChunk, document_id: &str) -> HashMap<String, Value> {
+    let mut payload = HashMap::new();
+    payload.insert("text".to_string(), Value::from(chunk.text.clone()));
+    payload.insert("page".to_string(), Value::from(chunk.page_number as i64));
+    payload.insert("chunk".to_string(), Value::from(chunk.chunk_index as i64));
+    payload.insert("document_id".to_string(), Value::from(document_id.to_string()));
+    payload
+}
+
+/// The insight list in a model reply, tolerating code fences, single quotes and a
+/// missing surrounding array; `None` if it still isn't valid JSON.
+fn parse_insights_json(response: &str) -> Option<Vec<Insight>> {
+    let cleaned_response = response
+        .trim()
+        .trim_matches('`')
+        .trim_start_matches("json")
+        .trim_start_matches("JSON")
+        .replace('\'', "\"")
+        .trim()
+        .to_string();
+
+    if let Ok(insights) = serde_json::from_str(&cleaned_response) {
+        return Some(insights);
+    }
+    if cleaned_response.starts_with('[') {
+        return None;
+    }
+    serde_json::from_str(&format!("[{}]", cleaned_response)).ok()
+}
+
+/// One insight per non-empty line, for replies that can't be read as JSON.
+fn line_insights(response: &str) -> Vec<Insight> {
+    response
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Insight {
+            text: line.trim().to_string(),
+            relevance: 0.8,
+            embedding: None,
+            metadata: None,
+        })
+        .collect()
+}
+
+fn repair_prompt(invalid: &str) -> String {
+    format!(
+        r#"The following was meant to be a JSON array of insights but is not valid JSON:
+
+{}
+
+Return only valid JSON matching this schema, with no explanations or code fences:
+[{{"text": string, "relevance": number between 0 and 1}}]"#,
+        invalid
+    )
+}
+
+/// Parses the insights in `response`. Invalid JSON goes back to `provider` at temperature 0
+/// to be fixed, up to `REPAIR_ATTEMPTS` times, before falling back to one insight per line.
+async fn parse_insights_with_repair(provider: &dyn CompletionProvider, response: &str) -> Vec<Insight> {
+    if let Some(insights) = parse_insights_json(response) {
+        return insights;
+    }
+
+    let options = CompletionOptions { temperature: Some(0.0) };
+    let mut invalid = response.to_string();
+    for attempt in 1..=REPAIR_ATTEMPTS {
+        match provider.complete_with_options(&repair_prompt(&invalid), &options).await {
+            Ok(repaired) => match parse_insights_json(&repaired) {
+                Some(insights) => return insights,
+                None => invalid = repaired,
+            },
+            Err(e) => {
+                log::warn!("Insight JSON repair attempt {} failed: {}", attempt, e);
+                break;
+            }
+        }
+    }
+    line_insights(response)
 }
 
 impl InsightExtractor {
@@ -80,11 +182,17 @@ impl InsightExtractor {
         let deepseek_provider = DeepSeekProvider::new(api_key.clone(), system_message.clone()).await
             .map_err(|e| Error::msg(format!("Failed to create DeepSeek provider: {}", e)))?;
             
-        let embedding_provider = OpenAIProvider::new(api_key.clone(), system_message).await
+        // Embeddings come from OpenAI, so prefer its own key when one is configured
+        let embedding_key = std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| api_key.clone());
+        let embedding_provider = OpenAIProvider::new(embedding_key, system_message).await
             .map_err(|e| Error::msg(format!("Failed to create OpenAI provider: {}", e)))?;
 
         // Initialize cache with 100 item capacity
         let chunk_cache = Arc::new(Mutex::new(LruCache::new(NonZeroUsize::new(100).unwrap())));
+
+        for collection in [INSIGHTS_COLLECTION, CHUNKS_COLLECTION] {
+            Self::ensure_collection(&client, collection).await?;
+        }
         
         Ok(Self { 
             deepseek_provider,
@@ -94,6 +202,44 @@ impl InsightExtractor {
         })
     }
 
+    /// Creates `name` at the configured embedding size. Fails if it exists with another
+    /// size; Qdrant being unreachable is only logged.
+    async fn ensure_collection(client: &Qdrant, name: &str) -> Result<()> {
+        let size = crate::config::embedding_dim() as u64;
+        match crate::database::vector_db::collection_dimension(client, name).await {
+            Ok(Some(existing)) if existing != size => {
+                return Err(crate::database::vector_db::VectorDBError::DimensionMismatch {
+                    collection: name.to_string(),
+                    existing,
+                    expected: size,
+                }.into());
+            }
+            Ok(Some(_)) => return Ok(()),
+            Ok(None) => {}
+            Err(e) => log::warn!("Could not check the size of {}: {}", name, e),
+        }
+        let vectors_config = VectorsConfig {
+            config: Some(qdrant_client::qdrant::vectors_config::Config::Params(VectorParams {
+                size,
+                distance: Distance::Cosine.into(),
+                ..Default::default()
+            })),
+        };
+        let create_collection = CreateCollection {
+            collection_name: name.to_string(),
+            vectors_config: Some(vectors_config),
+            ..Default::default()
+        };
+
+        if let Err(e) = client.create_collection(create_collection).await {
+            // Another upload may have created it since it was checked
+            if !client.collection_exists(name).await.unwrap_or(false) {
+                log::warn!("Failed to create collection {}: {}", name, e);
+            }
+        }
+        Ok(())
+    }
+
     // Add cache helper methods
     fn cache_chunk(&self, key: String, chunk: ProcessedChunk) {
         if let Ok(mut cache) = self.chunk_cache.lock() {
@@ -106,6 +252,11 @@ impl InsightExtractor {
     }
 
     pub async fn extract_insights(&self, text: &str) -> Result<Vec<Insight>> {
+        self.extract_document_insights(text, None).await
+    }
+
+    /// Like `extract_insights`, but tags the stored insight points with `document_id`.
+    async fn extract_document_insights(&self, text: &str, document_id: Option<&str>) -> Result<Vec<Insight>> {
         let prompt = format!(
             r#"Extract key insights from the following text and format them as a JSON array.
 
@@ -123,14 +274,14 @@ Text to analyze:
 {}
 
 Respond ONLY with the JSON array. Do not add any explanations or additional text."#,
-            text
+            wrap_untrusted(document_id.unwrap_or("document"), text)
         );
 
         let response = self.deepseek_provider.complete(&prompt).await
             .map_err(|e| Error::msg(format!("Failed to get completion: {}", e)))?;
 
         // Parse insights
-        let mut insights: Vec<Insight> = self.parse_insights_response(&response)?;
+        let mut insights = parse_insights_with_repair(&self.deepseek_provider, &response).await;
 
         // Generate embeddings for each insight
         for insight in &mut insights {
@@ -138,7 +289,7 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
                 insight.embedding = Some(embedding.clone());
                 
                 // Store in Qdrant
-                if let Err(e) = self.store_insight_vector(self.client.as_ref(), insight, &embedding).await {
+                if let Err(e) = self.store_insight_vector(self.client.as_ref(), insight, &embedding, document_id).await {
                     eprintln!("Warning: Failed to store vector: {}", e);
                 }
             }
@@ -157,7 +308,8 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         &self,
         client: &Qdrant,
         insight: &Insight,
-        embedding: &[f32]
+        embedding: &[f32],
+        document_id: Option<&str>,
     ) -> Result<()> {
         let point_id = Uuid::new_v4().to_string();
 
@@ -168,6 +320,9 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         if let Some(metadata) = &insight.metadata {
             payload.insert("metadata".to_string(), Value::from(metadata.clone()));
         }
+        if let Some(document_id) = document_id {
+            payload.insert("document_id".to_string(), Value::from(document_id.to_string()));
+        }
 
         let point = PointStruct {
             id: Some(PointId {
@@ -178,7 +333,7 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         };
 
         let upsert_points = UpsertPoints {
-            collection_name: "document_insights".to_string(),
+            collection_name: INSIGHTS_COLLECTION.to_string(),
             points: vec![point],
             ..Default::default()
         };
@@ -188,52 +343,11 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         Ok(())
     }
 
-    // Helper method to parse insights from AI response
-    fn parse_insights_response(&self, response: &str) -> Result<Vec<Insight>> {
-        let cleaned_response = response
-            .trim()
-            .trim_matches('`')
-            .trim_start_matches("json")
-            .trim_start_matches("JSON")
-            .replace('\'', "\"")
-            .trim()
-            .to_string();
-
-        match serde_json::from_str(&cleaned_response) {
-            Ok(insights) => Ok(insights),
-            Err(_) => {
-                let fixed_response = if cleaned_response.starts_with("{") && cleaned_response.ends_with("}") {
-                    format!("[{}]", cleaned_response)
-                } else if !cleaned_response.starts_with("[") {
-                    format!("[{}]", cleaned_response)
-                } else {
-                    cleaned_response
-                };
-
-                match serde_json::from_str(&fixed_response) {
-                    Ok(insights) => Ok(insights),
-                    Err(_) => {
-                        Ok(response
-                            .lines()
-                            .filter(|line| !line.trim().is_empty())
-                            .map(|line| Insight {
-                                text: line.trim().to_string(),
-                                relevance: 0.8,
-                                embedding: None,
-                                metadata: None,
-                            })
-                            .collect())
-                    }
-                }
-            }
-        }
-    }
-
     // New method for quick, direct analysis without JSON
     pub async fn quick_analyze(&self, text: &str) -> Result<String> {
         let prompt = format!(
             "Please analyze this text and provide the key insights in a clear, concise way:\n\n{}",
-            text
+            wrap_untrusted("document", text)
         );
 
         let response = self.deepseek_provider.complete(&prompt).await
@@ -246,7 +360,7 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         let embedding = self.generate_embedding(query_text).await?;
 
         let request = SearchPoints {
-            collection_name: "document_insights".to_string(),
+            collection_name: INSIGHTS_COLLECTION.to_string(),
             vector: embedding,
             limit: 10,
             with_payload: Some(WithPayloadSelector {
@@ -261,7 +375,7 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         let insights = results.result
             .into_iter()
             .filter_map(|point| {
-                let score = point.score;
+                let score = normalize_score(point.score);
                 let payload = point.payload;
                 if let Some(Value { kind: Some(qdrant_client::qdrant::value::Kind::StringValue(text)) }) = payload.get("text") {
                     Some((text.clone(), score))
@@ -326,40 +440,40 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
                     Some(PointIdOptions::Uuid(uuid)) => uuid,
                     _ => String::new(),
                 };
-                let score = point.score;
+                let score = normalize_score(point.score);
                 (id, score, point.payload)
             })
             .collect())
     }
 
-    pub async fn process_document(&self, text: &str, metadata: Option<serde_json::Value>) -> Result<Vec<Insight>> {
+    /// Chunks, embeds and extracts insights from `text`, tagging every stored point
+    /// with `document_id` so searches can be scoped to this document.
+    pub async fn process_document(&self, document_id: &str, text: &str, metadata: Option<serde_json::Value>) -> Result<Vec<Insight>> {
         let chunks = self.create_chunks(text, 1000);
+        let mut metadata = metadata.unwrap_or_else(|| json!({}));
+        if let Some(obj) = metadata.as_object_mut() {
+            obj.insert("document_id".to_string(), json!(document_id));
+        }
+        let metadata = Some(metadata);
         
         // Collect all texts for batch embedding
         let texts: Vec<String> = chunks.iter()
             .map(|c| c.text.clone())
             .collect();
 
-        // Generate embeddings in batch
-        let embeddings = if !texts.is_empty() {
-            let mut batch_embeddings = Vec::new();
-            for chunk in texts.chunks(20) {
-                let chunk_embeddings = futures::future::join_all(
-                    chunk.iter().map(|text| self.generate_embedding(text))
-                ).await;
-                batch_embeddings.extend(chunk_embeddings.into_iter().filter_map(Result::ok));
-            }
-            Some(batch_embeddings)
-        } else {
-            None
-        };
+        // Embed in batches; a chunk that fails still gets mined for insights, just isn't stored
+        let embeddings = self.embedding_provider.generate_embeddings(&texts).await
+            .into_iter()
+            .enumerate()
+            .map(|(i, result)| result
+                .map_err(|e| log::warn!("Failed to embed chunk {} of {}: {}", i, document_id, e))
+                .ok());
 
         // Process chunks in parallel and cache them
         let mut tasks = Vec::new();
-        for (i, chunk) in chunks.into_iter().enumerate() {
-            let chunk_embedding = embeddings.as_ref().and_then(|e| e.get(i).cloned());
+        for (chunk, chunk_embedding) in chunks.into_iter().zip(embeddings) {
             let metadata = metadata.clone();
-            tasks.push(self.process_chunk(chunk, chunk_embedding, metadata));
+            tasks.push(self.process_chunk(document_id, chunk, chunk_embedding, metadata));
         }
 
         let chunk_results = futures::future::join_all(tasks).await;
@@ -370,8 +484,8 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         for result in chunk_results {
             if let Ok((chunk_insights, processed_chunk)) = result {
                 // Cache the processed chunk
-                let cache_key = format!(
-                    "page_{}_chunk_{}", 
+                let cache_key = chunk_cache_key(
+                    document_id,
                     processed_chunk.chunk.page_number,
                     processed_chunk.chunk.chunk_index
                 );
@@ -381,10 +495,7 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
                 
                 if let Some(embedding) = processed_chunk.embedding {
                     let point_id = Uuid::new_v4().to_string();
-                    let mut payload = HashMap::new();
-                    payload.insert("text".to_string(), Value::from(processed_chunk.chunk.text));
-                    payload.insert("page".to_string(), Value::from(processed_chunk.chunk.page_number as i64));
-                    payload.insert("chunk".to_string(), Value::from(processed_chunk.chunk.chunk_index as i64));
+                    let payload = chunk_payload(&processed_chunk.chunk, document_id);
                     
                     points_to_store.push(PointStruct {
                         id: Some(PointId {
@@ -400,7 +511,7 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         // Batch store vectors
         if !points_to_store.is_empty() {
             let upsert_points = UpsertPoints {
-                collection_name: "document_chunks".to_string(),
+                collection_name: CHUNKS_COLLECTION.to_string(),
                 points: points_to_store,
                 ..Default::default()
             };
@@ -415,11 +526,16 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
 
     async fn process_chunk(
         &self,
+        document_id: &str,
         chunk: DocumentChunk,
         embedding: Option<Vec<f32>>,
         metadata: Option<serde_json::Value>,
     ) -> Result<(Vec<Insight>, ProcessedChunk)> {
-        let mut chunk_insights = self.extract_insights(&chunk.text).await?;
+        // A chunk without insights is still worth storing for search
+        let mut chunk_insights = self.extract_document_insights(&chunk.text, Some(document_id)).await.unwrap_or_else(|e| {
+            log::warn!("Failed to extract insights from chunk {}: {}", chunk.chunk_index, e);
+            Vec::new()
+        });
         
         // Add metadata and embedding to insights
         for insight in &mut chunk_insights {
@@ -477,17 +593,19 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         chunks
     }
 
-    // Improved search with context
-    pub async fn search_document(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+    /// Searches stored chunks, optionally only those of one document. Scores are normalized
+    /// to 0.0..=1.0 (0.5 is unrelated) and chunks scoring below `min_score` are dropped.
+    pub async fn search_document(&self, query: &str, limit: usize, document_id: Option<String>, min_score: Option<f32>) -> Result<Vec<SearchResult>> {
         let embedding = self.generate_embedding(query).await?;
 
         let request = SearchPoints {
-            collection_name: "document_chunks".to_string(),
+            collection_name: CHUNKS_COLLECTION.to_string(),
             vector: embedding,
             limit: limit as u64,
             with_payload: Some(WithPayloadSelector {
                 selector_options: Some(SelectorOptions::Enable(true)),
             }),
+            filter: document_filter(document_id.as_deref()),
             ..Default::default()
         };
 
@@ -495,6 +613,11 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         
         let mut search_results = Vec::new();
         for point in results.result {
+            let score = normalize_score(point.score);
+            if !meets_threshold(score, min_score) {
+                continue;
+            }
+
             let text = point.payload.get("text")
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string())
@@ -514,8 +637,12 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
                 })
                 .unwrap_or(0) as i32;
 
+            let document_id = point.payload.get("document_id")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+
             // Try to get context from cache
-            let cache_key = format!("page_{}_chunk_{}", page, chunk_idx);
+            let cache_key = chunk_cache_key(document_id.as_deref().unwrap_or_default(), page, chunk_idx);
             let context = if let Some(cached) = self.get_cached_chunk(&cache_key) {
                 cached.chunk.text
             } else {
@@ -525,15 +652,45 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
             search_results.push(SearchResult {
                 text,
                 context,
-                score: point.score,
+                score,
                 page_number: page,
                 chunk_index: chunk_idx,
+                document_id,
             });
         }
 
         Ok(search_results)
     }
 
+    /// The distinct ids of all documents with stored chunks, sorted.
+    pub async fn list_documents(&self) -> Result<Vec<String>> {
+        let mut documents = BTreeSet::new();
+        let mut offset = None;
+        loop {
+            let request = ScrollPoints {
+                collection_name: CHUNKS_COLLECTION.to_string(),
+                offset,
+                limit: Some(256),
+                with_payload: Some(WithPayloadSelector {
+                    selector_options: Some(SelectorOptions::Enable(true)),
+                }),
+                ..Default::default()
+            };
+            let response = self.client.scroll(request).await?;
+
+            documents.extend(response.result.iter().filter_map(|point| {
+                point.payload.get("document_id").and_then(|v| v.as_str()).map(|s| s.to_string())
+            }));
+
+            match response.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(documents.into_iter().collect())
+    }
+
     pub async fn get_document_summary(&self, page_range: Option<(i32, i32)>) -> Result<String> {
         let mut filter = None;
         if let Some((start, end)) = page_range {
@@ -565,8 +722,8 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
         }
 
         let request = SearchPoints {
-            collection_name: "document_chunks".to_string(),
-            vector: vec![0.0; 1536], // Dummy vector for getting all chunks
+            collection_name: CHUNKS_COLLECTION.to_string(),
+            vector: vec![0.0; crate::config::embedding_dim()], // Dummy vector for getting all chunks
             limit: 100,
             with_payload: Some(WithPayloadSelector {
                 selector_options: Some(SelectorOptions::Enable(true)),
@@ -601,7 +758,7 @@ Respond ONLY with the JSON array. Do not add any explanations or additional text
             let page_text = chunks.join(" ");
             let prompt = format!(
                 "Summarize this text from page {} concisely:\n\n{}", 
-                page, page_text
+                page, wrap_untrusted(&format!("page {}", page), &page_text)
             );
             
             if let Ok(summary) = self.deepseek_provider.complete(&prompt).await {
@@ -632,7 +789,88 @@ mod tests {
             .await
             .expect("Failed to generate embedding");
             
-        assert_eq!(embedding.len(), 1536); // OpenAI ada-002 embeddings are 1536-dimensional
+        assert_eq!(embedding.len(), crate::config::embedding_dim());
         assert!(embedding.iter().any(|&x| x != 0.0)); // Ensure we're not getting zero vectors
     }
+
+    #[tokio::test]
+    async fn test_malformed_insights_are_repaired() {
+        use crate::providers::mock::MockProvider;
+
+        let malformed = r#"[{"text": "Revenue grew 12%", "relevance": 0.95}, {"text": "Costs fell" "relevance": 0.4}"#;
+        let provider = MockProvider::with_response(
+            r#"[{"text": "Revenue grew 12%", "relevance": 0.95}, {"text": "Costs fell", "relevance": 0.4}]"#,
+        );
+        let insights = parse_insights_with_repair(&provider, malformed).await;
+        let parsed: Vec<_> = insights.iter().map(|i| (i.text.as_str(), i.relevance)).collect();
+        assert_eq!(parsed, vec![("Revenue grew 12%", 0.95), ("Costs fell", 0.4)]);
+        let prompts = provider.prompts();
+        assert_eq!(prompts.len(), 1);
+        assert!(prompts[0].contains(malformed));
+        // Temperature 0 goes with the repair request, not into settings other requests read
+        assert_eq!(provider.temperatures(), vec![Some(0.0)]);
+
+        // Valid replies are used as they are, without a repair request
+        let provider = MockProvider::with_response("unused");
+        let insights = parse_insights_with_repair(&provider, "```json\n{'text': 'One', 'relevance': 0.7}\n```").await;
+        assert_eq!((insights[0].text.as_str(), insights[0].relevance), ("One", 0.7));
+        assert!(provider.prompts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unrepairable_insights_fall_back_to_lines() {
+        use crate::providers::mock::MockProvider;
+
+        let provider = MockProvider::with_response("still not json");
+        let insights = parse_insights_with_repair(&provider, "First point\n\nSecond point").await;
+        let texts: Vec<_> = insights.iter().map(|i| i.text.as_str()).collect();
+        assert_eq!(texts, vec!["First point", "Second point"]);
+        assert_eq!(provider.prompts().len(), REPAIR_ATTEMPTS);
+        // The second attempt asks to fix the first attempt's output
+        assert!(provider.prompts()[1].contains("still not json"));
+    }
+
+    #[test]
+    fn test_chunks_are_tagged_with_document_id() {
+        let chunk = DocumentChunk {
+            text: "Quarterly revenue grew 12%".to_string(),
+            page_number: 2,
+            chunk_index: 5,
+            metadata: None,
+        };
+        let payload = chunk_payload(&chunk, "report-2024");
+        assert_eq!(payload.get("document_id").and_then(|v| v.as_str()).map(|s| s.as_str()), Some("report-2024"));
+        assert_eq!(payload.get("text").and_then(|v| v.as_str()).map(|s| s.as_str()), Some("Quarterly revenue grew 12%"));
+        assert_ne!(chunk_cache_key("report-2024", 2, 5), chunk_cache_key("report-2023", 2, 5));
+
+        assert!(document_filter(None).is_none());
+        let filter = document_filter(Some("report-2024")).unwrap();
+        assert_eq!(filter, Filter::must([Condition::matches("document_id", "report-2024".to_string())]));
+    }
+
+    // Needs Qdrant and OPENAI_API_KEY: cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn test_search_is_scoped_to_one_document() {
+        let api_key = std::env::var("OPENAI_API_KEY")
+            .expect("OPENAI_API_KEY environment variable not set");
+        let extractor = InsightExtractor::new(api_key, "You are a helpful assistant.".to_string())
+            .await
+            .expect("Failed to create InsightExtractor");
+
+        let suffix = Uuid::new_v4().to_string();
+        let (rust_doc, garden_doc) = (format!("rust-{}", suffix), format!("garden-{}", suffix));
+        extractor.process_document(&rust_doc, "Rust's borrow checker enforces ownership rules at compile time.", None)
+            .await.unwrap();
+        extractor.process_document(&garden_doc, "Tomatoes need six hours of direct sunlight and regular watering.", None)
+            .await.unwrap();
+
+        let results = extractor.search_document("ownership and borrowing", 10, Some(garden_doc.clone()), None)
+            .await.unwrap();
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.document_id.as_deref() == Some(garden_doc.as_str())));
+
+        let documents = extractor.list_documents().await.unwrap();
+        assert!(documents.contains(&rust_doc) && documents.contains(&garden_doc));
+    }
 }
@@ -16,6 +16,60 @@ pub use text::TextExtractor;
 
 use indicatif::{ProgressBar, ProgressStyle};
 
+/// The kinds of file `DocumentProcessor` can read, by extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Pdf,
+    Spreadsheet,
+    Word,
+    Image,
+    Text,
+}
+
+impl FileKind {
+    pub const EXTENSIONS: &'static [&'static str] = &[
+        "pdf", "xlsx", "xls", "docx", "doc", "png", "jpg", "jpeg",
+        "txt", "md", "rs", "py", "js", "json", "yaml", "yml",
+    ];
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "pdf" => Some(Self::Pdf),
+            "xlsx" | "xls" => Some(Self::Spreadsheet),
+            "docx" | "doc" => Some(Self::Word),
+            "png" | "jpg" | "jpeg" => Some(Self::Image),
+            "txt" | "md" | "rs" | "py" | "js" | "json" | "yaml" | "yml" => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &str) -> Result<Self, DocumentError> {
+        let extension = std::path::Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .ok_or(DocumentError::InvalidExtension)?;
+        Self::from_extension(extension)
+            .ok_or_else(|| DocumentError::UnsupportedFileType(extension.to_string()))
+    }
+}
+
+/// The text of the file at `file_path`, read by the extractor for its kind. Extraction
+/// blocks, so async callers that don't hold a `DocumentProcessor` run it in `spawn_blocking`.
+pub fn extract_text(file_path: &str) -> Result<String, DocumentError> {
+    let text = match FileKind::from_path(file_path)? {
+        FileKind::Pdf => PdfExtractor::new().extract_text(file_path)
+            .map_err(|e| DocumentError::PdfError(e.to_string()))?,
+        FileKind::Spreadsheet => ExcelExtractor::new().extract_text(file_path)
+            .map_err(|e| DocumentError::ExcelError(e.to_string()))?,
+        FileKind::Word => WordExtractor::new().extract_text(file_path)
+            .map_err(|e| DocumentError::WordError(e.to_string()))?,
+        FileKind::Image => OcrExtractor::new()?.extract_text(file_path)?,
+        FileKind::Text => TextExtractor::new().extract_text(file_path)
+            .map_err(|e| DocumentError::TextError(e.to_string()))?,
+    };
+    Ok(text)
+}
+
 pub struct DocumentProcessor {
     pdf_extractor: PdfExtractor,
     excel_extractor: ExcelExtractor,
@@ -43,55 +97,47 @@ impl DocumentProcessor {
     }
 
     pub async fn process_document(&mut self, file_path: &str) -> Result<Vec<insights::Insight>, DocumentError> {
-        let extension = std::path::Path::new(file_path)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .ok_or(DocumentError::InvalidExtension)?;
+        self.process_document_as(file_path, file_path).await
+    }
 
-        let text = match extension.to_lowercase().as_str() {
-            "pdf" => self.pdf_extractor.extract_text(file_path)
-                .map_err(|e| DocumentError::PdfError(e.to_string()))?,
-            "xlsx" | "xls" => self.excel_extractor.extract_text(file_path)
-                .map_err(|e| DocumentError::ExcelError(e.to_string()))?,
-            "docx" | "doc" => self.word_extractor.extract_text(file_path)
-                .map_err(|e| DocumentError::WordError(e.to_string()))?,
-            "png" | "jpg" | "jpeg" => {
-                let extractor = std::mem::replace(&mut self.ocr_extractor, OcrExtractor::default());
-                extractor.extract_text(file_path)
-            }
-                .map_err(|e| DocumentError::OcrError(e.to_string()))?,
-            "txt" | "md" | "rs" | "py" | "js" | "json" | "yaml" | "yml" => self.text_extractor.extract_text(file_path)
-                .map_err(|e| DocumentError::TextError(e.to_string()))?,
-            _ => return Err(DocumentError::UnsupportedFileType(extension.to_string())),
-        };
+    /// Like `process_document`, but stores the chunks under `document_id` instead of the
+    /// path, e.g. the original name of an uploaded file saved to a temporary path.
+    pub async fn process_document_as(&mut self, file_path: &str, document_id: &str) -> Result<Vec<insights::Insight>, DocumentError> {
+        let text = self.extract_text(file_path)?;
+        self.process_text_as(document_id, &text).await
+    }
 
-        let insights = self.insight_extractor.extract_insights(&text).await
+    /// Insights from `text` already extracted from a document, stored under `document_id`.
+    pub async fn process_text_as(&self, document_id: &str, text: &str) -> Result<Vec<insights::Insight>, DocumentError> {
+        // Chunks are stored under the document id so `doc ask` can cite them later
+        let insights = self.insight_extractor
+            .process_document(document_id, text, Some(serde_json::json!({ "source": document_id })))
+            .await
             .map_err(|e| DocumentError::InsightError(e.to_string()))?;
         Ok(insights)
     }
 
-    pub async fn quick_analyze(&mut self, file_path: &str) -> Result<String, DocumentError> {
-        let extension = std::path::Path::new(file_path)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .ok_or(DocumentError::InvalidExtension)?;
-
-        let text = match extension.to_lowercase().as_str() {
-            "pdf" => self.pdf_extractor.extract_text(file_path)
+    fn extract_text(&mut self, file_path: &str) -> Result<String, DocumentError> {
+        let text = match FileKind::from_path(file_path)? {
+            FileKind::Pdf => self.pdf_extractor.extract_text(file_path)
                 .map_err(|e| DocumentError::PdfError(e.to_string()))?,
-            "xlsx" | "xls" => self.excel_extractor.extract_text(file_path)
+            FileKind::Spreadsheet => self.excel_extractor.extract_text(file_path)
                 .map_err(|e| DocumentError::ExcelError(e.to_string()))?,
-            "docx" | "doc" => self.word_extractor.extract_text(file_path)
+            FileKind::Word => self.word_extractor.extract_text(file_path)
                 .map_err(|e| DocumentError::WordError(e.to_string()))?,
-            "png" | "jpg" | "jpeg" => {
+            FileKind::Image => {
                 let extractor = std::mem::replace(&mut self.ocr_extractor, OcrExtractor::default());
                 extractor.extract_text(file_path)
             }
                 .map_err(|e| DocumentError::OcrError(e.to_string()))?,
-            "txt" | "md" | "rs" | "py" | "js" | "json" | "yaml" | "yml" => self.text_extractor.extract_text(file_path)
+            FileKind::Text => self.text_extractor.extract_text(file_path)
                 .map_err(|e| DocumentError::TextError(e.to_string()))?,
-            _ => return Err(DocumentError::UnsupportedFileType(extension.to_string())),
         };
+        Ok(text)
+    }
+
+    pub async fn quick_analyze(&mut self, file_path: &str) -> Result<String, DocumentError> {
+        let text = self.extract_text(file_path)?;
 
         self.insight_extractor.quick_analyze(&text).await
             .map_err(|e| DocumentError::InsightError(e.to_string()))
@@ -131,3 +177,17 @@ impl DocumentProcessor {
         Ok(insights)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_kind_from_path() {
+        assert_eq!(FileKind::from_path("report.PDF").unwrap(), FileKind::Pdf);
+        assert_eq!(FileKind::from_path("/tmp/notes.md").unwrap(), FileKind::Text);
+        assert!(matches!(FileKind::from_path("archive.zip"), Err(DocumentError::UnsupportedFileType(ext)) if ext == "zip"));
+        assert!(matches!(FileKind::from_path("Makefile"), Err(DocumentError::InvalidExtension)));
+        assert!(FileKind::EXTENSIONS.iter().all(|ext| FileKind::from_extension(ext).is_some()));
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::{anyhow, Result};
+use reqwest::{Client, RequestBuilder};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// Covers a whole completion, including a streamed body.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const POOL_MAX_IDLE_PER_HOST: usize = 8;
+
+static SHARED_CLIENT: OnceLock<Arc<Client>> = OnceLock::new();
+
+/// The HTTP client providers share, so every instance reuses one connection pool.
+pub fn shared_client() -> Arc<Client> {
+    SHARED_CLIENT.get_or_init(|| Arc::new(build_client())).clone()
+}
+
+/// Sends `request` and fails with the API's error message unless it succeeds, for
+/// checking a key against an endpoint like `/models`.
+pub async fn check_key(request: RequestBuilder) -> Result<()> {
+    let response = request.timeout(CONNECT_TIMEOUT * 3).send().await?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    let message = body["error"]["message"].as_str()
+        .or_else(|| body["error"].as_str())
+        .or_else(|| body["message"].as_str())
+        .unwrap_or("request rejected");
+    Err(anyhow!("{} ({})", message, status))
+}
+
+/// The sorted model ids in an OpenAI-style `/models` reply: `{"data": [{"id": ...}]}`.
+pub fn parse_model_ids(body: &serde_json::Value) -> Vec<String> {
+    let mut ids: Vec<String> = body["data"].as_array()
+        .map(|models| models.iter().filter_map(|m| m["id"].as_str()).map(str::to_string).collect())
+        .unwrap_or_default();
+    ids.sort();
+    ids
+}
+
+/// Fetches and parses an OpenAI-style `/models` endpoint.
+pub async fn fetch_model_ids(request: RequestBuilder) -> Result<Vec<String>> {
+    let response = request.timeout(CONNECT_TIMEOUT * 3).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(anyhow!("Model list request failed ({})", status));
+    }
+    Ok(parse_model_ids(&response.json().await?))
+}
+
+fn build_client() -> Client {
+    Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST)
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to configure HTTP client, using defaults: {}", e);
+            Client::new()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_model_ids() {
+        let body = serde_json::json!({
+            "object": "list",
+            "data": [
+                {"id": "mistral-small-latest", "object": "model", "owned_by": "mistralai"},
+                {"id": "codestral-latest", "object": "model"},
+                {"object": "model"}
+            ]
+        });
+        assert_eq!(parse_model_ids(&body), vec!["codestral-latest", "mistral-small-latest"]);
+        assert!(parse_model_ids(&serde_json::json!({"error": "nope"})).is_empty());
+    }
+}
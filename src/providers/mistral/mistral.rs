@@ -1,6 +1,8 @@
 use async_trait::async_trait;
 use anyhow::{Result, anyhow};
-use crate::providers::traits::CompletionProvider;
+use crate::providers::traits::{CompletionOptions, CompletionProvider};
+use crate::providers::http::{check_key, fetch_model_ids, shared_client};
+use crate::providers::tools::{parse_arguments, ToolRegistry, MAX_TOOL_ROUNDS};
 use crate::providers::utils::get_placeholder_embedding;
 use reqwest::Client;
 use serde_json::{json, Value};
@@ -11,24 +13,35 @@ use std::env;
 pub struct MistralProvider {
     api_key: String,
     system_message: Arc<RwLock<String>>,
-    client: Client,
+    client: Arc<Client>,
     model: String,
 }
 
-#[async_trait]
-impl CompletionProvider for MistralProvider {
-    async fn new(api_key: String, system_message: String) -> Result<Self> {
+impl MistralProvider {
+    /// Like `new`, but sends requests through `client` instead of the shared one.
+    pub fn with_client(api_key: String, system_message: String, client: Arc<Client>) -> Self {
         let model = env::var("MISTRAL_MODEL").unwrap_or_else(|_| "mistral-large-latest".to_string());
-        
-        Ok(Self {
+
+        Self {
             api_key,
             system_message: Arc::new(RwLock::new(system_message)),
-            client: Client::new(),
+            client,
             model,
-        })
+        }
+    }
+}
+
+#[async_trait]
+impl CompletionProvider for MistralProvider {
+    async fn new(api_key: String, system_message: String) -> Result<Self> {
+        Ok(Self::with_client(api_key, system_message, shared_client()))
     }
 
     async fn complete(&self, prompt: &str) -> Result<String> {
+        self.complete_with_options(prompt, &CompletionOptions::default()).await
+    }
+
+    async fn complete_with_options(&self, prompt: &str, options: &CompletionOptions) -> Result<String> {
         let system_message = self.system_message.read().map_err(|e| anyhow!("Failed to read system message: {}", e))?.clone();
         
         let response = self.client
@@ -45,7 +58,8 @@ impl CompletionProvider for MistralProvider {
                         "role": "user",
                         "content": prompt
                     }
-                ]
+                ],
+                "temperature": options.temperature_for("mistral")
             }))
             .send()
             .await?;
@@ -58,11 +72,78 @@ impl CompletionProvider for MistralProvider {
             .ok_or_else(|| anyhow!("Invalid response format"))
     }
 
+    async fn complete_with_tools(&self, prompt: &str, tools: &ToolRegistry) -> Result<String> {
+        if tools.is_empty() {
+            return self.complete(prompt).await;
+        }
+
+        let system_message = self.system_message.read().map_err(|e| anyhow!("Failed to read system message: {}", e))?.clone();
+        let mut messages = vec![
+            json!({ "role": "system", "content": system_message }),
+            json!({ "role": "user", "content": prompt }),
+        ];
+
+        for _ in 0..MAX_TOOL_ROUNDS {
+            let response = self.client
+                .post("https://api.mistral.ai/v1/chat/completions")
+                .header("Authorization", format!("Bearer {}", self.api_key))
+                .json(&json!({
+                    "model": self.model,
+                    "messages": messages,
+                    "tools": tools.function_specs(),
+                    "tool_choice": "auto",
+                    "temperature": crate::config::temperature("mistral")
+                }))
+                .send()
+                .await?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response.text().await?;
+                return Err(anyhow!("API request failed: Status {}, Body: {}", status, error_text));
+            }
+
+            let response_json: Value = response.json().await?;
+            let message = response_json["choices"][0]["message"].clone();
+            let calls = match message["tool_calls"].as_array() {
+                Some(calls) if !calls.is_empty() => calls.clone(),
+                _ => {
+                    return message["content"]
+                        .as_str()
+                        .map(|s| s.to_string())
+                        .ok_or_else(|| anyhow!("Invalid response format"));
+                }
+            };
+
+            messages.push(message);
+            for call in calls {
+                let name = call["function"]["name"].as_str().unwrap_or_default();
+                // Arguments arrive as a JSON string or, occasionally, an object
+                let args = match &call["function"]["arguments"] {
+                    Value::String(arguments) => parse_arguments(arguments),
+                    other => other.clone(),
+                };
+                let output = tools.invoke(name, args).await;
+                messages.push(json!({
+                    "role": "tool",
+                    "name": name,
+                    "tool_call_id": call["id"],
+                    "content": output
+                }));
+            }
+        }
+        Err(anyhow!("No answer after {} tool calls", MAX_TOOL_ROUNDS))
+    }
+
     async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
         // Use placeholder embeddings for now
         get_placeholder_embedding(text).await
     }
 
+    fn has_semantic_embeddings(&self) -> bool {
+        false
+    }
+
     async fn update_personality(&self, system_message: String) -> Result<()> {
         let mut guard = self.system_message.write().map_err(|e| anyhow!("Lock error: {}", e))?;
         *guard = system_message;
@@ -81,7 +162,22 @@ impl CompletionProvider for MistralProvider {
         Box::new(self.clone())
     }
 
+    fn with_system_message(&self, system_message: &str) -> Box<dyn CompletionProvider + Send + Sync> {
+        let mut provider = self.clone();
+        provider.system_message = Arc::new(RwLock::new(system_message.to_string()));
+        Box::new(provider)
+    }
+
     async fn get_model_info(&self) -> Result<String> {
         Ok(self.model.clone())
     }
+
+    async fn check_access(&self) -> Result<String> {
+        check_key(self.client.get("https://api.mistral.ai/v1/models").bearer_auth(&self.api_key)).await?;
+        Ok(self.model.clone())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        fetch_model_ids(self.client.get("https://api.mistral.ai/v1/models").bearer_auth(&self.api_key)).await
+    }
 }
\ No newline at end of file
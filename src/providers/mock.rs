@@ -0,0 +1,224 @@
+use async_trait::async_trait;
+use anyhow::Result;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use crate::providers::traits::{embed_each, CompletionOptions, CompletionProvider};
+use crate::providers::utils::fake_embedding;
+
+/// Reply used when a mock is built with `CompletionProvider::new`.
+pub const DEFAULT_MOCK_RESPONSE: &str = "This is a mock response.";
+
+type Responder = dyn Fn(&str) -> String + Send + Sync;
+
+/// A provider that answers without any network calls, for tests and offline runs.
+///
+/// Replies come from a fixed string or a closure over the prompt, and every prompt is
+/// recorded. Embeddings are [`fake_embedding`]s, so the same text always gets the same
+/// vector.
+#[derive(Clone)]
+pub struct MockProvider {
+    api_key: String,
+    system_message: Arc<RwLock<String>>,
+    respond: Arc<Responder>,
+    prompts: Arc<Mutex<Vec<String>>>,
+    /// The per-request temperature each prompt came with
+    temperatures: Arc<Mutex<Vec<Option<f32>>>>,
+    /// Wait before each streamed word, or before the whole reply for `complete`
+    delay: Option<Duration>,
+    /// Embedding requests made, a batch counting as one
+    embedding_requests: Arc<AtomicUsize>,
+    /// Whether `generate_embeddings` sends many texts per request
+    batch_embeddings: bool,
+    /// Texts whose embedding fails
+    unembeddable: Arc<Vec<String>>,
+    /// Length of every embedding, instead of the configured size
+    embedding_size: Option<usize>,
+}
+
+impl MockProvider {
+    /// Always replies with `response`.
+    pub fn with_response(response: impl Into<String>) -> Self {
+        let response = response.into();
+        Self::with_responder(move |_| response.clone())
+    }
+
+    /// Replies with whatever `respond` returns for the prompt.
+    pub fn with_responder(respond: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        Self {
+            api_key: "mock".to_string(),
+            system_message: Arc::new(RwLock::new(String::new())),
+            respond: Arc::new(respond),
+            prompts: Arc::new(Mutex::new(Vec::new())),
+            temperatures: Arc::new(Mutex::new(Vec::new())),
+            delay: None,
+            embedding_requests: Arc::new(AtomicUsize::new(0)),
+            batch_embeddings: false,
+            unembeddable: Arc::new(Vec::new()),
+            embedding_size: None,
+        }
+    }
+
+    /// Replies slowly, streaming one word per `delay`, like a model that takes its time.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Embeds like a provider with a batch endpoint, `embedding_batch_size` texts per request.
+    pub fn with_batch_embeddings(mut self) -> Self {
+        self.batch_embeddings = true;
+        self
+    }
+
+    /// Fails to embed `text`, as an API rejecting one input would.
+    pub fn with_unembeddable(mut self, text: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.unembeddable).push(text.into());
+        self
+    }
+
+    /// Embeds into `size` values whatever size is configured, like a model whose vectors
+    /// have their own length.
+    pub fn with_embedding_size(mut self, size: usize) -> Self {
+        self.embedding_size = Some(size);
+        self
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        if self.unembeddable.iter().any(|bad| bad == text) {
+            return Err(anyhow::anyhow!("Cannot embed '{}'", text));
+        }
+        let mut vector = fake_embedding(text);
+        if let Some(size) = self.embedding_size {
+            vector.resize(size, 0.0);
+        }
+        Ok(vector)
+    }
+
+    /// Embedding requests made so far, including by clones; a batch counts as one.
+    pub fn embedding_requests(&self) -> usize {
+        self.embedding_requests.load(Ordering::SeqCst)
+    }
+
+    /// Every prompt completed so far, including by clones.
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().unwrap().clone()
+    }
+
+    /// The temperature each of [`prompts`](Self::prompts) asked for, if it set one.
+    pub fn temperatures(&self) -> Vec<Option<f32>> {
+        self.temperatures.lock().unwrap().clone()
+    }
+
+    fn record(&self, prompt: &str, options: &CompletionOptions) {
+        self.prompts.lock().unwrap().push(prompt.to_string());
+        self.temperatures.lock().unwrap().push(options.temperature);
+    }
+}
+
+#[async_trait]
+impl CompletionProvider for MockProvider {
+    async fn new(api_key: String, system_message: String) -> Result<Self> {
+        let mut provider = Self::with_response(DEFAULT_MOCK_RESPONSE);
+        provider.api_key = api_key;
+        provider.system_message = Arc::new(RwLock::new(system_message));
+        Ok(provider)
+    }
+
+    async fn complete(&self, prompt: &str) -> Result<String> {
+        self.complete_with_options(prompt, &CompletionOptions::default()).await
+    }
+
+    async fn complete_with_options(&self, prompt: &str, options: &CompletionOptions) -> Result<String> {
+        self.record(prompt, options);
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        Ok((self.respond)(prompt))
+    }
+
+    async fn complete_stream_with_options(
+        &self,
+        prompt: &str,
+        options: &CompletionOptions,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<String> {
+        let Some(delay) = self.delay else {
+            let response = self.complete_with_options(prompt, options).await?;
+            on_token(&response);
+            return Ok(response);
+        };
+        self.record(prompt, options);
+        let response = (self.respond)(prompt);
+        for word in response.split_inclusive(' ') {
+            tokio::time::sleep(delay).await;
+            on_token(word);
+        }
+        Ok(response)
+    }
+
+    async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
+        self.embedding_requests.fetch_add(1, Ordering::SeqCst);
+        self.embed(text)
+    }
+
+    async fn generate_embeddings(&self, texts: &[String]) -> Vec<Result<Vec<f32>>> {
+        if !self.batch_embeddings {
+            return embed_each(self, texts).await;
+        }
+        let mut results = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(crate::config::embedding_batch_size()) {
+            self.embedding_requests.fetch_add(1, Ordering::SeqCst);
+            results.extend(batch.iter().map(|text| self.embed(text)));
+        }
+        results
+    }
+
+    async fn update_personality(&self, system_message: String) -> Result<()> {
+        *self.system_message.write().unwrap() = system_message;
+        Ok(())
+    }
+
+    async fn get_model_info(&self) -> Result<String> {
+        Ok("mock".to_string())
+    }
+
+    fn get_system_message(&self) -> String {
+        self.system_message.read().unwrap().clone()
+    }
+
+    fn get_api_key(&self) -> &String {
+        &self.api_key
+    }
+
+    fn clone_box(&self) -> Box<dyn CompletionProvider + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn with_system_message(&self, system_message: &str) -> Box<dyn CompletionProvider + Send + Sync> {
+        let mut provider = self.clone();
+        provider.system_message = Arc::new(RwLock::new(system_message.to_string()));
+        Box::new(provider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::embedding_dim;
+
+    #[tokio::test]
+    async fn test_mock_replies_and_embeds_deterministically() {
+        let mock = MockProvider::with_responder(|prompt| format!("you said: {}", prompt));
+        assert_eq!(mock.complete("hi").await.unwrap(), "you said: hi");
+        assert_eq!(mock.clone_box().complete("again").await.unwrap(), "you said: again");
+        assert_eq!(mock.prompts(), vec!["hi", "again"]);
+
+        let first = mock.generate_embedding("rust lifetimes").await.unwrap();
+        assert_eq!(first.len(), embedding_dim());
+        assert_eq!(first, mock.generate_embedding("rust lifetimes").await.unwrap());
+        assert_ne!(first, mock.generate_embedding("tomato soup").await.unwrap());
+        assert!((first.iter().map(|x| x * x).sum::<f32>() - 1.0).abs() < 1e-4);
+    }
+}
@@ -1,8 +1,12 @@
 pub mod deepseek;
 pub mod gemini;
+pub mod http;
 pub mod mistral;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 pub mod openai;
 pub mod openrouter;
+pub mod tools;
 pub mod traits;
 pub mod twitter;
 pub mod utils;
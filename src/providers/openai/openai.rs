@@ -1,23 +1,32 @@
 use async_trait::async_trait;
 use anyhow::{Result, anyhow};
-use crate::providers::traits::CompletionProvider;
+use crate::providers::traits::{embed_each, CompletionOptions, CompletionProvider};
+use crate::providers::tools::{parse_arguments, ToolRegistry, MAX_TOOL_ROUNDS};
 use async_openai::{
     types::{
         CreateEmbeddingRequestArgs, 
         EmbeddingInput, 
+        CreateChatCompletionRequest,
         CreateChatCompletionRequestArgs, 
         ChatCompletionRequestMessage,
         ChatCompletionRequestSystemMessage,
         ChatCompletionRequestUserMessage,
         ChatCompletionRequestUserMessageContent,
+        ChatCompletionRequestAssistantMessageArgs,
+        ChatCompletionRequestToolMessageArgs,
+        ChatCompletionToolArgs,
+        FunctionObjectArgs,
         Role,
     },
     Client, 
     config::OpenAIConfig,
 };
+use futures::StreamExt;
 use std::sync::{Arc, RwLock};
 use std::env;
 
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
 #[derive(Clone)]
 pub struct OpenAIProvider {
     api_key: String,
@@ -27,6 +36,61 @@ pub struct OpenAIProvider {
     embedding_model: String,
 }
 
+impl OpenAIProvider {
+    /// Embeds with `model` rather than `OPENAI_EMBEDDING_MODEL`.
+    pub fn with_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.embedding_model = model.into();
+        self
+    }
+
+    /// Embeds `texts` in one request, ordered as given.
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let request = CreateEmbeddingRequestArgs::default()
+            .model(&self.embedding_model)
+            .input(EmbeddingInput::StringArray(texts.to_vec()))
+            .build()?;
+
+        let mut data = self.client.embeddings().create(request).await?.data;
+        if data.len() != texts.len() {
+            return Err(anyhow!("OpenAI returned {} embeddings for {} texts", data.len(), texts.len()));
+        }
+        data.sort_by_key(|embedding| embedding.index);
+        Ok(data.into_iter().map(|embedding| embedding.embedding).collect())
+    }
+
+    fn messages(&self, prompt: &str) -> Result<Vec<ChatCompletionRequestMessage>> {
+        let system_message = self.system_message.read()
+            .map_err(|e| anyhow!("Failed to read system message: {}", e))?.clone();
+
+        Ok(vec![
+            ChatCompletionRequestMessage::System(
+                ChatCompletionRequestSystemMessage {
+                    role: Role::System,
+                    content: system_message,
+                    name: None,
+                }
+            ),
+            ChatCompletionRequestMessage::User(
+                ChatCompletionRequestUserMessage {
+                    role: Role::User,
+                    content: ChatCompletionRequestUserMessageContent::Text(prompt.to_string()),
+                    name: None,
+                }
+            ),
+        ])
+    }
+
+    fn chat_request(&self, prompt: &str, options: &CompletionOptions) -> Result<CreateChatCompletionRequest> {
+        let request = CreateChatCompletionRequestArgs::default()
+            .model(&self.chat_model)
+            .messages(self.messages(prompt)?)
+            .temperature(options.temperature_for("openai"))
+            .build()?;
+
+        Ok(request)
+    }
+}
+
 #[async_trait]
 impl CompletionProvider for OpenAIProvider {
     async fn new(api_key: String, system_message: String) -> Result<Self> {
@@ -34,7 +98,7 @@ impl CompletionProvider for OpenAIProvider {
         let client = Client::with_config(config);
         
         let chat_model = env::var("OPENAI_CHAT_MODEL").unwrap_or_else(|_| "gpt-4-turbo-preview".to_string());
-        let embedding_model = env::var("OPENAI_EMBEDDING_MODEL").unwrap_or_else(|_| "text-embedding-3-small".to_string());
+        let embedding_model = env::var("OPENAI_EMBEDDING_MODEL").unwrap_or_else(|_| DEFAULT_EMBEDDING_MODEL.to_string());
         
         Ok(Self {
             api_key,
@@ -46,29 +110,11 @@ impl CompletionProvider for OpenAIProvider {
     }
 
     async fn complete(&self, prompt: &str) -> Result<String> {
-        let system_message = self.system_message.read()
-            .map_err(|e| anyhow!("Failed to read system message: {}", e))?.clone();
-        
-        let request = CreateChatCompletionRequestArgs::default()
-            .model(&self.chat_model)
-            .messages(vec![
-                ChatCompletionRequestMessage::System(
-                    ChatCompletionRequestSystemMessage {
-                        role: Role::System,
-                        content: system_message,
-                        name: None,
-                    }
-                ),
-                ChatCompletionRequestMessage::User(
-                    ChatCompletionRequestUserMessage {
-                        role: Role::User,
-                        content: ChatCompletionRequestUserMessageContent::Text(prompt.to_string()),
-                        name: None,
-                    }
-                ),
-            ])
-            .build()?;
+        self.complete_with_options(prompt, &CompletionOptions::default()).await
+    }
 
+    async fn complete_with_options(&self, prompt: &str, options: &CompletionOptions) -> Result<String> {
+        let request = self.chat_request(prompt, options)?;
         let response = self.client.chat().create(request).await?;
         
         response.choices.first()
@@ -76,6 +122,82 @@ impl CompletionProvider for OpenAIProvider {
             .ok_or_else(|| anyhow!("No response content"))
     }
 
+    async fn complete_stream_with_options(
+        &self,
+        prompt: &str,
+        options: &CompletionOptions,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<String> {
+        let request = self.chat_request(prompt, options)?;
+        let mut stream = self.client.chat().create_stream(request).await?;
+
+        let mut response = String::new();
+        while let Some(chunk) = stream.next().await {
+            for choice in chunk?.choices {
+                if let Some(content) = choice.delta.content {
+                    on_token(&content);
+                    response.push_str(&content);
+                }
+            }
+        }
+
+        if response.is_empty() {
+            return Err(anyhow!("No response content"));
+        }
+        Ok(response)
+    }
+
+    async fn complete_with_tools(&self, prompt: &str, tools: &ToolRegistry) -> Result<String> {
+        if tools.is_empty() {
+            return self.complete(prompt).await;
+        }
+
+        let mut functions = Vec::new();
+        for tool in tools.iter() {
+            functions.push(ChatCompletionToolArgs::default()
+                .function(FunctionObjectArgs::default()
+                    .name(tool.name())
+                    .description(tool.description())
+                    .parameters(tool.json_schema())
+                    .build()?)
+                .build()?);
+        }
+
+        let mut messages = self.messages(prompt)?;
+        for _ in 0..MAX_TOOL_ROUNDS {
+            let request = CreateChatCompletionRequestArgs::default()
+                .model(&self.chat_model)
+                .messages(messages.clone())
+                .tools(functions.clone())
+                .temperature(crate::config::temperature("openai"))
+                .build()?;
+            let response = self.client.chat().create(request).await?;
+            let message = response.choices.into_iter().next()
+                .ok_or_else(|| anyhow!("No response content"))?
+                .message;
+
+            let calls = match message.tool_calls {
+                Some(calls) if !calls.is_empty() => calls,
+                _ => return message.content.ok_or_else(|| anyhow!("No response content")),
+            };
+
+            messages.push(ChatCompletionRequestAssistantMessageArgs::default()
+                .tool_calls(calls.clone())
+                .build()?
+                .into());
+            for call in calls {
+                let output = tools.invoke(&call.function.name, parse_arguments(&call.function.arguments)).await;
+                messages.push(ChatCompletionRequestMessage::Tool(
+                    ChatCompletionRequestToolMessageArgs::default()
+                        .tool_call_id(call.id)
+                        .content(output)
+                        .build()?
+                ));
+            }
+        }
+        Err(anyhow!("No answer after {} tool calls", MAX_TOOL_ROUNDS))
+    }
+
     async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
         let request = CreateEmbeddingRequestArgs::default()
             .model(&self.embedding_model)
@@ -91,6 +213,21 @@ impl CompletionProvider for OpenAIProvider {
         }
     }
 
+    async fn generate_embeddings(&self, texts: &[String]) -> Vec<Result<Vec<f32>>> {
+        let mut results = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(crate::config::embedding_batch_size()) {
+            match self.embed_batch(batch).await {
+                Ok(embeddings) => results.extend(embeddings.into_iter().map(Ok)),
+                // One bad text fails the whole request, so retry singly to pin the error on it
+                Err(e) => {
+                    log::warn!("Batch embedding failed, embedding {} texts one at a time: {}", batch.len(), e);
+                    results.extend(embed_each(self, batch).await);
+                }
+            }
+        }
+        results
+    }
+
     async fn update_personality(&self, system_message: String) -> Result<()> {
         let mut guard = self.system_message.write().map_err(|e| anyhow!("Lock error: {}", e))?;
         *guard = system_message;
@@ -109,7 +246,28 @@ impl CompletionProvider for OpenAIProvider {
         Box::new(self.clone())
     }
 
+    fn with_system_message(&self, system_message: &str) -> Box<dyn CompletionProvider + Send + Sync> {
+        let mut provider = self.clone();
+        provider.system_message = Arc::new(RwLock::new(system_message.to_string()));
+        Box::new(provider)
+    }
+
     async fn get_model_info(&self) -> Result<String> {
         Ok(self.chat_model.clone())
     }
+
+    async fn check_access(&self) -> Result<String> {
+        self.client.models().list().await?;
+        Ok(self.chat_model.clone())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let mut ids: Vec<String> = self.client.models().list().await?
+            .data
+            .into_iter()
+            .map(|model| model.id)
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use anyhow::{Result, anyhow};
-use crate::providers::traits::CompletionProvider;
+use crate::providers::traits::{CompletionOptions, CompletionProvider};
+use crate::providers::http::{check_key, fetch_model_ids, shared_client};
 use crate::providers::utils::get_placeholder_embedding;
 use reqwest::Client;
 use serde_json::{json, Value};
@@ -11,24 +12,35 @@ use std::env;
 pub struct OpenRouterProvider {
     api_key: String,
     system_message: Arc<RwLock<String>>,
-    client: Client,
+    client: Arc<Client>,
     model: String,
 }
 
-#[async_trait]
-impl CompletionProvider for OpenRouterProvider {
-    async fn new(api_key: String, system_message: String) -> Result<Self> {
+impl OpenRouterProvider {
+    /// Like `new`, but sends requests through `client` instead of the shared one.
+    pub fn with_client(api_key: String, system_message: String, client: Arc<Client>) -> Self {
         let model = env::var("OPENROUTER_MODEL").unwrap_or_else(|_| "anthropic/claude-3-opus".to_string());
-        
-        Ok(Self {
+
+        Self {
             api_key,
             system_message: Arc::new(RwLock::new(system_message)),
-            client: Client::new(),
+            client,
             model,
-        })
+        }
+    }
+}
+
+#[async_trait]
+impl CompletionProvider for OpenRouterProvider {
+    async fn new(api_key: String, system_message: String) -> Result<Self> {
+        Ok(Self::with_client(api_key, system_message, shared_client()))
     }
 
     async fn complete(&self, prompt: &str) -> Result<String> {
+        self.complete_with_options(prompt, &CompletionOptions::default()).await
+    }
+
+    async fn complete_with_options(&self, prompt: &str, options: &CompletionOptions) -> Result<String> {
         let system_message = self.system_message.read().map_err(|e| anyhow!("Failed to read system message: {}", e))?.clone();
         
         let response = self.client
@@ -47,7 +59,8 @@ impl CompletionProvider for OpenRouterProvider {
                         "role": "user",
                         "content": prompt
                     }
-                ]
+                ],
+                "temperature": options.temperature_for("openrouter")
             }))
             .send()
             .await?;
@@ -65,6 +78,10 @@ impl CompletionProvider for OpenRouterProvider {
         get_placeholder_embedding(text).await
     }
 
+    fn has_semantic_embeddings(&self) -> bool {
+        false
+    }
+
     async fn update_personality(&self, system_message: String) -> Result<()> {
         let mut guard = self.system_message.write().map_err(|e| anyhow!("Lock error: {}", e))?;
         *guard = system_message;
@@ -83,7 +100,22 @@ impl CompletionProvider for OpenRouterProvider {
         Box::new(self.clone())
     }
 
+    fn with_system_message(&self, system_message: &str) -> Box<dyn CompletionProvider + Send + Sync> {
+        let mut provider = self.clone();
+        provider.system_message = Arc::new(RwLock::new(system_message.to_string()));
+        Box::new(provider)
+    }
+
     async fn get_model_info(&self) -> Result<String> {
         Ok(self.model.clone())
     }
+
+    async fn check_access(&self) -> Result<String> {
+        check_key(self.client.get("https://openrouter.ai/api/v1/auth/key").bearer_auth(&self.api_key)).await?;
+        Ok(self.model.clone())
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        fetch_model_ids(self.client.get("https://openrouter.ai/api/v1/models").bearer_auth(&self.api_key)).await
+    }
 }
\ No newline at end of file
@@ -0,0 +1,329 @@
+use async_trait::async_trait;
+use anyhow::{Result, anyhow};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::food::api::spoonacular::{format_recipe, RecipeFilters, SpoonacularClient};
+use crate::providers::traits::CompletionProvider;
+use crate::providers::web_crawler::crawler_manager::WebCrawlerManager;
+
+/// Model turns allowed before a tool-calling completion gives up.
+pub const MAX_TOOL_ROUNDS: usize = 5;
+
+/// Page text handed back to the model is cut to this many characters.
+const MAX_TOOL_OUTPUT_CHARS: usize = 4000;
+
+/// Something the model can decide to call mid-completion.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    /// Identifier the model calls the tool by: letters, digits, `_` and `-`.
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// JSON Schema for the arguments object.
+    fn json_schema(&self) -> Value;
+
+    async fn invoke(&self, args: Value) -> Result<String>;
+}
+
+/// The tools offered to a completion, by name.
+#[derive(Default, Clone)]
+pub struct ToolRegistry {
+    tools: Vec<Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a tool, replacing any registered under the same name.
+    pub fn register(&mut self, tool: Arc<dyn Tool>) {
+        self.tools.retain(|t| t.name() != tool.name());
+        self.tools.push(tool);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Tool>> {
+        self.tools.iter().find(|t| t.name() == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Tool>> {
+        self.tools.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Runs a tool for the model. Failures come back as text so the model can recover.
+    pub async fn invoke(&self, name: &str, args: Value) -> String {
+        let Some(tool) = self.get(name) else {
+            return format!("Error: no tool named '{}'", name);
+        };
+        match tool.invoke(args).await {
+            Ok(output) => truncate(&output),
+            Err(e) => format!("Error: {}", e),
+        }
+    }
+
+    /// Tool definitions in the OpenAI `tools` format, which Mistral shares.
+    pub fn function_specs(&self) -> Vec<Value> {
+        self.tools.iter()
+            .map(|tool| json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "parameters": tool.json_schema(),
+                }
+            }))
+            .collect()
+    }
+}
+
+fn truncate(output: &str) -> String {
+    match output.char_indices().nth(MAX_TOOL_OUTPUT_CHARS) {
+        Some((end, _)) => format!("{}…", &output[..end]),
+        None => output.to_string(),
+    }
+}
+
+/// Parses a model's arguments string, treating empty as `{}`.
+pub fn parse_arguments(arguments: &str) -> Value {
+    if arguments.trim().is_empty() {
+        return json!({});
+    }
+    serde_json::from_str(arguments).unwrap_or_else(|_| json!({}))
+}
+
+fn react_instructions(tools: &ToolRegistry) -> String {
+    let mut instructions = String::from(
+        "You can use tools to answer. To call one, reply with exactly these two lines and nothing else:\n\
+        Action: <tool name>\n\
+        Action Input: <JSON arguments>\n\
+        You will get the result as an Observation. When you can answer, reply with:\n\
+        Final Answer: <your answer>\n\nTools:\n",
+    );
+    for tool in tools.iter() {
+        instructions.push_str(&format!("- {}: {} Arguments schema: {}\n", tool.name(), tool.description(), tool.json_schema()));
+    }
+    instructions
+}
+
+/// The tool call in a ReAct-style reply, if it names a registered tool.
+fn parse_action(response: &str, tools: &ToolRegistry) -> Option<(String, Value)> {
+    let name = response.lines()
+        .find_map(|line| line.trim().strip_prefix("Action:"))?
+        .trim()
+        .to_string();
+    tools.get(&name)?;
+    let args = response.lines()
+        .find_map(|line| line.trim().strip_prefix("Action Input:"))
+        .map(parse_arguments)
+        .unwrap_or_else(|| json!({}));
+    Some((name, args))
+}
+
+fn final_answer(response: &str) -> String {
+    match response.find("Final Answer:") {
+        Some(start) => response[start + "Final Answer:".len()..].trim().to_string(),
+        None => response.trim().to_string(),
+    }
+}
+
+/// Tool calling through the prompt, for providers without native function calling.
+pub async fn complete_with_react<P>(provider: &P, prompt: &str, tools: &ToolRegistry) -> Result<String>
+where
+    P: CompletionProvider + ?Sized,
+{
+    if tools.is_empty() {
+        return provider.complete(prompt).await;
+    }
+
+    let mut transcript = format!("{}\nQuestion: {}\n", react_instructions(tools), prompt);
+    for _ in 0..MAX_TOOL_ROUNDS {
+        let response = provider.complete(&transcript).await?;
+        let Some((name, args)) = parse_action(&response, tools) else {
+            return Ok(final_answer(&response));
+        };
+        let observation = tools.invoke(&name, args).await;
+        transcript.push_str(&format!("{}\nObservation: {}\n", response.trim(), observation));
+    }
+    Err(anyhow!("No answer after {} tool calls", MAX_TOOL_ROUNDS))
+}
+
+/// Fetches a page with [`WebCrawlerManager::analyze_url`].
+pub struct WebPageTool {
+    crawler: Arc<WebCrawlerManager>,
+}
+
+impl WebPageTool {
+    pub fn new(crawler: Arc<WebCrawlerManager>) -> Self {
+        Self { crawler }
+    }
+}
+
+#[async_trait]
+impl Tool for WebPageTool {
+    fn name(&self) -> &str {
+        "read_web_page"
+    }
+
+    fn description(&self) -> &str {
+        "Fetches a web page and returns its text. Use it when the answer depends on a specific URL."
+    }
+
+    fn json_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "description": "Absolute http(s) URL" }
+            },
+            "required": ["url"]
+        })
+    }
+
+    async fn invoke(&self, args: Value) -> Result<String> {
+        let url = args["url"].as_str().ok_or_else(|| anyhow!("Missing 'url'"))?;
+        self.crawler.analyze_url(url).await.map_err(|e| anyhow!("{}", e))
+    }
+}
+
+/// Looks up a recipe with [`SpoonacularClient::search_recipe`].
+pub struct RecipeSearchTool {
+    client: Arc<SpoonacularClient>,
+}
+
+impl RecipeSearchTool {
+    pub fn new(client: Arc<SpoonacularClient>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl Tool for RecipeSearchTool {
+    fn name(&self) -> &str {
+        "search_recipe"
+    }
+
+    fn description(&self) -> &str {
+        "Finds a recipe by dish name, optionally filtered by diet and intolerances."
+    }
+
+    fn json_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Dish name, e.g. 'pad thai'" },
+                "diet": { "type": "string", "description": "e.g. vegetarian, vegan, ketogenic" },
+                "intolerances": { "type": "array", "items": { "type": "string" }, "description": "e.g. gluten, dairy" }
+            },
+            "required": ["query"]
+        })
+    }
+
+    async fn invoke(&self, args: Value) -> Result<String> {
+        let query = args["query"].as_str().ok_or_else(|| anyhow!("Missing 'query'"))?;
+        let intolerances: Vec<&str> = args["intolerances"].as_array()
+            .map(|items| items.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let filters = RecipeFilters::new(args["diet"].as_str(), &intolerances).map_err(|e| anyhow!(e))?;
+
+        match self.client.search_recipe(query, &filters).await.map_err(|e| anyhow!(e))? {
+            Some(recipe) => Ok(format_recipe(&recipe)),
+            None => Ok(format!("No recipe found for '{}'", query)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Asks for the `echo` tool once, then answers with whatever it observed.
+    #[derive(Clone)]
+    struct ActionProvider {
+        api_key: String,
+    }
+
+    #[async_trait]
+    impl CompletionProvider for ActionProvider {
+        async fn new(api_key: String, _system_message: String) -> Result<Self> {
+            Ok(Self { api_key })
+        }
+
+        async fn complete(&self, prompt: &str) -> Result<String> {
+            match prompt.rsplit_once("Observation: ") {
+                Some((_, observation)) => Ok(format!("Final Answer: the tool said {}", observation.trim())),
+                None => Ok("Action: echo\nAction Input: {\"text\": \"hello\"}".to_string()),
+            }
+        }
+
+        async fn generate_embedding(&self, _text: &str) -> Result<Vec<f32>> {
+            Ok(Vec::new())
+        }
+
+        async fn update_personality(&self, _system_message: String) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_model_info(&self) -> Result<String> {
+            Ok("action".to_string())
+        }
+
+        fn get_system_message(&self) -> String {
+            String::new()
+        }
+
+        fn get_api_key(&self) -> &String {
+            &self.api_key
+        }
+
+        fn clone_box(&self) -> Box<dyn CompletionProvider + Send + Sync> {
+            Box::new(self.clone())
+        }
+
+        fn with_system_message(&self, _system_message: &str) -> Box<dyn CompletionProvider + Send + Sync> {
+            self.clone_box()
+        }
+    }
+
+    struct EchoTool {
+        calls: Mutex<Vec<Value>>,
+    }
+
+    #[async_trait]
+    impl Tool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Repeats its input."
+        }
+
+        fn json_schema(&self) -> Value {
+            json!({ "type": "object", "properties": { "text": { "type": "string" } } })
+        }
+
+        async fn invoke(&self, args: Value) -> Result<String> {
+            self.calls.lock().unwrap().push(args.clone());
+            Ok(format!("echo: {}", args["text"].as_str().unwrap_or_default()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_result_is_fed_back() {
+        let echo = Arc::new(EchoTool { calls: Mutex::new(Vec::new()) });
+        let mut tools = ToolRegistry::new();
+        tools.register(echo.clone());
+
+        let provider = ActionProvider::new(String::new(), String::new()).await.unwrap();
+        let answer = provider.complete_with_tools("say hello", &tools).await.unwrap();
+
+        assert_eq!(*echo.calls.lock().unwrap(), vec![json!({ "text": "hello" })]);
+        assert_eq!(answer, "the tool said echo: hello");
+    }
+}
@@ -2,6 +2,22 @@ use async_trait::async_trait;
 use std::any::Any;
 use anyhow::Result;
 use std::sync::{Arc, RwLock};
+use crate::providers::tools::{complete_with_react, ToolRegistry};
+use futures::stream::{self, StreamExt};
+
+/// Per-request settings for `complete_with_options`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompletionOptions {
+    /// Overrides the configured sampling temperature for this request
+    pub temperature: Option<f32>,
+}
+
+impl CompletionOptions {
+    /// Sampling temperature to send to `provider`: this request's, else the configured one.
+    pub fn temperature_for(&self, provider: &str) -> f32 {
+        self.temperature.unwrap_or_else(|| crate::config::temperature(provider))
+    }
+}
 
 #[async_trait]
 pub trait CompletionProvider: Any + Send + Sync {
@@ -11,21 +27,90 @@ pub trait CompletionProvider: Any + Send + Sync {
 
     async fn complete(&self, prompt: &str) -> Result<String>;
 
+    /// Like `complete`, with `options` in place of the configured settings for this request
+    /// only. Providers without per-request settings ignore them.
+    async fn complete_with_options(&self, prompt: &str, _options: &CompletionOptions) -> Result<String> {
+        self.complete(prompt).await
+    }
+
+    /// Like `complete`, but hands each chunk of the reply to `on_token` as it arrives.
+    /// Providers without streaming deliver the whole reply as one chunk.
+    async fn complete_stream(&self, prompt: &str, on_token: &mut (dyn for<'t> FnMut(&'t str) + Send)) -> Result<String> {
+        self.complete_stream_with_options(prompt, &CompletionOptions::default(), on_token).await
+    }
+
+    /// `complete_stream` with `options`, as `complete_with_options` is to `complete`.
+    async fn complete_stream_with_options(
+        &self,
+        prompt: &str,
+        options: &CompletionOptions,
+        on_token: &mut (dyn for<'t> FnMut(&'t str) + Send),
+    ) -> Result<String> {
+        let response = self.complete_with_options(prompt, options).await?;
+        on_token(&response);
+        Ok(response)
+    }
+
+    /// Like `complete`, but the model may call `tools` and see their results before answering.
+    /// Providers without native function calling fall back to a ReAct-style prompt.
+    async fn complete_with_tools(&self, prompt: &str, tools: &ToolRegistry) -> Result<String> {
+        complete_with_react(self, prompt, tools).await
+    }
+
     async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>>;
 
+    /// One embedding result per text, in order, so a text that fails doesn't lose the rest.
+    /// Providers with a batch endpoint send many texts per request; the rest embed
+    /// [`EMBEDDING_CONCURRENCY`](crate::config::EMBEDDING_CONCURRENCY) texts at a time.
+    async fn generate_embeddings(&self, texts: &[String]) -> Vec<Result<Vec<f32>>> {
+        embed_each(self, texts).await
+    }
+
+    /// Whether `generate_embedding` captures meaning. Providers without an embedding
+    /// endpoint return placeholder vectors that only match identical text.
+    fn has_semantic_embeddings(&self) -> bool {
+        true
+    }
+
     async fn update_personality(&self, system_message: String) -> Result<()>;
 
     async fn get_model_info(&self) -> Result<String>;
 
+    /// Checks the API key with a cheap authenticated request, returning the model name.
+    /// Providers that can't check without a completion just report `get_model_info`.
+    async fn check_access(&self) -> Result<String> {
+        self.get_model_info().await
+    }
+
+    /// Models this provider can answer with, for picking one. Providers without a models
+    /// endpoint report the configured list (see `configured_models`).
+    async fn list_models(&self) -> Result<Vec<String>> {
+        Ok(vec![self.get_model_info().await?])
+    }
+
     fn get_system_message(&self) -> String;
 
     fn get_api_key(&self) -> &String;
 
     fn clone_box(&self) -> Box<dyn CompletionProvider + Send + Sync>;
+
+    /// A copy that answers with `system_message`. Unlike `clone_box`, whose copies share
+    /// the system message, updating one leaves the other alone.
+    fn with_system_message(&self, system_message: &str) -> Box<dyn CompletionProvider + Send + Sync>;
 }
 
 impl Clone for Box<dyn CompletionProvider + Send + Sync> {
     fn clone(&self) -> Self {
         self.clone_box()
     }
-}
\ No newline at end of file
+}
+
+/// Embeds `texts` with one request each, a bounded number at a time, keeping their order.
+pub async fn embed_each<P: CompletionProvider + ?Sized>(provider: &P, texts: &[String]) -> Vec<Result<Vec<f32>>> {
+    // Futures don't run until polled, so building them all up front still bounds the requests
+    let requests: Vec<_> = texts.iter().map(|text| provider.generate_embedding(text)).collect();
+    stream::iter(requests)
+        .buffered(crate::config::EMBEDDING_CONCURRENCY)
+        .collect()
+        .await
+}
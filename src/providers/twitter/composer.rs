@@ -1,3 +1,4 @@
+use crate::config::tweet_max_length;
 use crate::personality::PersonalityProfile;
 use crate::providers::twitter::twitbrain::Mention;
 use crate::providers::deepseek::deepseek::DeepSeekProvider;
@@ -10,22 +11,21 @@ use crate::providers::traits::CompletionProvider as ProviderTrait;
 use anyhow::{Result, Error};
 use std::collections::HashSet;
 use std::sync::Mutex;
-use lazy_static::lazy_static;
 use chrono::{DateTime, Utc};
 use std::env;
 use std::error::Error as StdError;
 use std::sync::Arc;
 
-const MAX_TWEET_LENGTH: usize = 270;
 const DEFAULT_EMOJI: &str = "💭";
 const MAX_CACHE_SIZE: usize = 1000; // Maximum number of topics to remember
 
-lazy_static! {
-    static ref TOPIC_CACHE: Mutex<Vec<(String, DateTime<Utc>)>> = Mutex::new(Vec::new());
+/// Writes tweets, replies and DMs in a character's voice using one long-lived provider.
+pub struct TweetComposer {
+    provider: Arc<Box<dyn CompletionProvider + Send + Sync>>,
+    // Recent auto-post topics, used to avoid repeating ourselves
+    topic_cache: Mutex<Vec<(String, DateTime<Utc>)>>,
 }
 
-pub struct TweetComposer;
-
 #[derive(Debug)]
 enum TweetProvider {
     DeepSeek,
@@ -48,7 +48,24 @@ impl TweetProvider {
 }
 
 impl TweetComposer {
-    async fn get_provider(profile: &PersonalityProfile) -> Result<Arc<Box<dyn CompletionProvider + Send + Sync>>> {
+    /// Creates the provider selected by `TWEET_PROVIDER` once, primed with the profile's system message.
+    pub async fn new(profile: &PersonalityProfile) -> Result<Self> {
+        Ok(Self::with_provider(Self::create_provider(profile).await?))
+    }
+
+    pub fn with_provider(provider: Arc<Box<dyn CompletionProvider + Send + Sync>>) -> Self {
+        Self {
+            provider,
+            topic_cache: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Switches the tweeting voice to a new character without rebuilding the provider.
+    pub async fn update_personality(&self, profile: &PersonalityProfile) -> Result<()> {
+        self.provider.update_personality(Self::create_system_message(profile)).await
+    }
+
+    async fn create_provider(profile: &PersonalityProfile) -> Result<Arc<Box<dyn CompletionProvider + Send + Sync>>> {
         match TweetProvider::from_env() {
             TweetProvider::Mistral => {
                 let api_key = std::env::var("MISTRAL_API_KEY")
@@ -140,8 +157,8 @@ impl TweetComposer {
             .count()
     }
 
-    fn clean_old_topics() {
-        let mut cache = TOPIC_CACHE.lock().unwrap();
+    fn clean_old_topics(&self) {
+        let mut cache = self.topic_cache.lock().unwrap();
         let one_day_ago = Utc::now() - chrono::Duration::days(1);
         cache.retain(|(_, timestamp)| *timestamp > one_day_ago);
         
@@ -152,17 +169,17 @@ impl TweetComposer {
         }
     }
 
-    fn is_topic_unique(topic: &str) -> bool {
-        let mut cache = TOPIC_CACHE.lock().unwrap();
+    fn is_topic_unique(&self, topic: &str) -> bool {
+        let cache = self.topic_cache.lock().unwrap();
         !cache.iter().any(|(cached_topic, _)| 
             cached_topic.to_lowercase().contains(&topic.to_lowercase()) || 
             topic.to_lowercase().contains(&cached_topic.to_lowercase())
         )
     }
 
-    pub async fn generate_auto_post_topic(profile: &PersonalityProfile) -> Result<String> {
+    pub async fn generate_auto_post_topic(&self, profile: &PersonalityProfile) -> Result<String> {
         // Clean old topics first
-        Self::clean_old_topics();
+        self.clean_old_topics();
 
         for attempt in 0..3 {  // Try up to 3 times to get a unique topic
             let mut prompt_parts = vec![
@@ -207,8 +224,7 @@ Generate a unique topic for timestamp {}\n\nTopic:", Utc::now()));
 
             let prompt = prompt_parts.join("\n\n");
             
-            let provider = Self::get_provider(profile).await?;
-            let topic = provider.complete(&prompt).await
+            let topic = self.provider.complete(&prompt).await
                 .map_err(|e| Error::msg(format!("Failed to generate topic: {}", e)))?;
             
             let topic = topic.trim()
@@ -218,15 +234,15 @@ Generate a unique topic for timestamp {}\n\nTopic:", Utc::now()));
                 .trim()
                 .to_string();
             
-            if Self::is_topic_unique(&topic) {
-                let mut cache = TOPIC_CACHE.lock().unwrap();
+            if self.is_topic_unique(&topic) {
+                let mut cache = self.topic_cache.lock().unwrap();
                 cache.push((topic.clone(), Utc::now()));
                 return Ok(topic);
             }
 
             if attempt == 2 {
                 let timestamped_topic = format!("{} ({})", topic, Utc::now().timestamp());
-                let mut cache = TOPIC_CACHE.lock().unwrap();
+                let mut cache = self.topic_cache.lock().unwrap();
                 cache.push((timestamped_topic.clone(), Utc::now()));
                 return Ok(timestamped_topic);
             }
@@ -236,8 +252,8 @@ Generate a unique topic for timestamp {}\n\nTopic:", Utc::now()));
     }
 
     #[inline]
-    pub async fn generate_auto_tweet(profile: &PersonalityProfile) -> Result<String> {
-        let topic = Self::generate_auto_post_topic(profile).await?;
+    pub async fn generate_auto_tweet(&self, profile: &PersonalityProfile) -> Result<String> {
+        let topic = self.generate_auto_post_topic(profile).await?;
         
         let mut prompt_parts = vec![
             format!("You are {} - {}", 
@@ -253,17 +269,17 @@ Generate a unique topic for timestamp {}\n\nTopic:", Utc::now()));
             2. Draw from your expertise in {}\n\
             3. Use your characteristic communication style\n\
             4. Keep your personality traits consistent\n\
-            5. Stay within Twitter's character limit at 260 character \n\
+            5. Stay within {} characters\n\
             6. Make it engaging and true to your character\n\n\
             Tweet:", 
             topic,
             profile.name,
-            profile.get_str("expertise").unwrap_or("your field")
+            profile.get_str("expertise").unwrap_or("your field"),
+            tweet_max_length()
         ));
 
         let prompt = prompt_parts.join("\n\n");
-        let provider = Self::get_provider(profile).await?;
-        let tweet = provider.complete(&prompt).await
+        let tweet = self.provider.complete(&prompt).await
             .map_err(|e| Error::msg(format!("Failed to generate tweet: {}", e)))?;
         
         Ok(Self::truncate_content(tweet.trim()
@@ -274,46 +290,426 @@ Generate a unique topic for timestamp {}\n\nTopic:", Utc::now()));
             .to_string()))
     }
 
-    pub async fn generate_auto_reply(profile: &PersonalityProfile, original_tweet: &str) -> Result<String> {
-        let provider = Self::get_provider(profile).await?;
+    pub async fn generate_auto_reply(&self, profile: &PersonalityProfile, original_tweet: &str) -> Result<String> {
         let prompt = format!(
             "As {}, create a thoughtful reply to this tweet: '{}' \
              Maintain your unique voice while adding value to the conversation.",
             profile.name,
             original_tweet
         );
-        let reply = provider.complete(&prompt).await
+        let reply = self.provider.complete(&prompt).await
             .map_err(|e| Error::msg(format!("Failed to generate reply: {}", e)))?;
         Ok(Self::truncate_content(reply))
     }
 
-    pub async fn generate_dm(profile: &PersonalityProfile, recipient: &str) -> Result<String> {
-        let provider = Self::get_provider(profile).await?;
+    pub async fn generate_dm(&self, profile: &PersonalityProfile, recipient: &str) -> Result<String> {
         let prompt = format!(
             "As {}, write a professional direct message to @{}. \
              Keep it friendly yet professional, reflecting your personality.",
             profile.name,
             recipient
         );
-        let dm = provider.complete(&prompt).await
+        let dm = self.provider.complete(&prompt).await
             .map_err(|e| Error::msg(format!("Failed to generate DM: {}", e)))?;
         Ok(Self::truncate_content(dm))
     }
 
-    pub async fn generate_mention_response(profile: &PersonalityProfile, mention: &Mention) -> Result<String> {
-        let provider = Self::get_provider(profile).await?;
+    pub async fn generate_mention_response(&self, profile: &PersonalityProfile, mention: &Mention) -> Result<String> {
         let prompt = format!(
             "As {}, respond to this mention: '{}' \
              Keep your response engaging and authentic to your character.",
             profile.name,
             mention.text
         );
-        let response = provider.complete(&prompt).await
+        let response = self.provider.complete(&prompt).await
             .map_err(|e| Error::msg(format!("Failed to generate mention response: {}", e)))?;
         Ok(Self::truncate_content(response))
     }
 
     fn truncate_content(content: String) -> String {
-        content.chars().take(MAX_TWEET_LENGTH).collect()
+        truncate_to(&content, tweet_max_length())
+    }
+
+    /// Splits long content into a numbered thread (`1/n`, `2/n`, ...) at sentence boundaries.
+    /// Content that already fits in one tweet is returned unchanged.
+    pub fn compose_thread(content: &str) -> Vec<String> {
+        Self::compose_thread_within(content, tweet_max_length())
+    }
+
+    fn compose_thread_within(content: &str, max_length: usize) -> Vec<String> {
+        let content = content.trim();
+        if tweet_length(content) <= max_length {
+            return vec![content.to_string()];
+        }
+
+        // Reserve room for the " i/n" suffix; retry if the part count needs more digits
+        let mut digits = 1;
+        loop {
+            let suffix_len = 2 * digits + 2;
+            // A limit too short for the suffix splits down to single characters rather than underflowing
+            let parts = Self::pack_sentences(content, max_length.saturating_sub(suffix_len));
+            let total = parts.len();
+            if total.to_string().len() <= digits {
+                return parts.into_iter()
+                    .enumerate()
+                    .map(|(i, part)| format!("{} {}/{}", part, i + 1, total))
+                    .collect();
+            }
+            digits = total.to_string().len();
+        }
+    }
+
+    fn pack_sentences(content: &str, limit: usize) -> Vec<String> {
+        let mut parts = Vec::new();
+        let mut current = String::new();
+
+        for sentence in Self::split_sentences(content) {
+            // Sentences longer than a tweet fall back to word boundaries
+            let pieces = if tweet_length(&sentence) > limit {
+                Self::split_words(&sentence, limit)
+            } else {
+                vec![sentence]
+            };
+
+            for piece in pieces {
+                let joined_len = tweet_length(&current) + 1 + tweet_length(&piece);
+                if current.is_empty() {
+                    current = piece;
+                } else if joined_len <= limit {
+                    current.push(' ');
+                    current.push_str(&piece);
+                } else {
+                    parts.push(std::mem::replace(&mut current, piece));
+                }
+            }
+        }
+        if !current.is_empty() {
+            parts.push(current);
+        }
+        parts
+    }
+
+    /// Sentences end at `.`, `!` or `?` followed by whitespace, so dots inside URLs don't split.
+    fn split_sentences(content: &str) -> Vec<String> {
+        let mut sentences = Vec::new();
+        let mut current = String::new();
+        let mut chars = content.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            current.push(c);
+            let at_boundary = matches!(c, '.' | '!' | '?')
+                && chars.peek().is_none_or(|next| next.is_whitespace());
+            if at_boundary || c == '\n' {
+                let sentence = current.trim().to_string();
+                if !sentence.is_empty() {
+                    sentences.push(sentence);
+                }
+                current.clear();
+            }
+        }
+        let rest = current.trim();
+        if !rest.is_empty() {
+            sentences.push(rest.to_string());
+        }
+        sentences
+    }
+
+    fn split_words(sentence: &str, limit: usize) -> Vec<String> {
+        let mut pieces = Vec::new();
+        let mut current = String::new();
+
+        for word in sentence.split_whitespace() {
+            if current.is_empty() {
+                current = word.to_string();
+            } else if tweet_length(&current) + 1 + tweet_length(word) <= limit {
+                current.push(' ');
+                current.push_str(word);
+            } else {
+                pieces.push(std::mem::replace(&mut current, word.to_string()));
+            }
+
+            // A single word longer than a tweet has to be cut
+            while tweet_length(&current) > limit {
+                let first = current.chars().next().map_or(1, char::len_utf8);
+                let end = fit(&current, limit).max(first);
+                pieces.push(current[..end].to_string());
+                current = current[end..].to_string();
+            }
+        }
+        if !current.is_empty() {
+            pieces.push(current);
+        }
+        pieces
+    }
+}
+
+/// Weight Twitter gives every link, whatever its length, once it is shortened to t.co.
+const URL_LENGTH: usize = 23;
+
+/// Twitter counts Latin, Greek, Cyrillic and common punctuation as 1 and everything else,
+/// such as CJK and emoji, as 2.
+fn char_weight(c: char) -> usize {
+    match c as u32 {
+        0xFE00..=0xFE0F => 0,
+        0..=4351 | 8192..=8205 | 8208..=8223 | 8242..=8247 => 1,
+        _ => 2,
+    }
+}
+
+/// Length of `text` as Twitter counts it towards the limit, with links counted as 23.
+pub fn tweet_length(text: &str) -> usize {
+    let weighted: usize = text.chars().map(char_weight).sum();
+    text.split_whitespace()
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .fold(weighted, |length, url| length - url.chars().map(char_weight).sum::<usize>() + URL_LENGTH)
+}
+
+/// Byte length of the longest start of `text` within `limit` weighted characters.
+fn fit(text: &str, limit: usize) -> usize {
+    let mut length = 0;
+    for (i, c) in text.char_indices() {
+        length += char_weight(c);
+        if length > limit {
+            return i;
+        }
+    }
+    text.len()
+}
+
+/// `content` cut to `limit` at a word boundary, ending with an ellipsis when cut.
+fn truncate_to(content: &str, limit: usize) -> String {
+    const ELLIPSIS: &str = "…";
+    let content = content.trim();
+    if tweet_length(content) <= limit {
+        return content.to_string();
+    }
+    let budget = limit.saturating_sub(tweet_length(ELLIPSIS));
+
+    let mut end = 0;
+    for (i, c) in content.char_indices() {
+        if c.is_whitespace() {
+            if tweet_length(&content[..i]) > budget {
+                break;
+            }
+            end = i;
+        }
+    }
+    // A first word longer than the whole tweet has to be cut mid-word
+    if end == 0 {
+        end = fit(content, budget);
+    }
+    let kept = content[..end].trim_end().trim_end_matches([',', ';', ':', '-']);
+    format!("{}{}", kept, ELLIPSIS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_TWEET_MAX_LENGTH;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockProvider {
+        api_key: String,
+        fixed_output: Option<String>,
+        calls: Arc<AtomicUsize>,
+        /// Providers made from this one, shared with them so a test can check none were needed
+        copies: Arc<AtomicUsize>,
+        system_message: Arc<std::sync::RwLock<String>>,
+    }
+
+    impl MockProvider {
+        fn with_output(fixed_output: Option<&str>) -> Self {
+            Self {
+                api_key: String::new(),
+                fixed_output: fixed_output.map(str::to_string),
+                calls: Arc::new(AtomicUsize::new(0)),
+                copies: Arc::new(AtomicUsize::new(0)),
+                system_message: Arc::new(std::sync::RwLock::new(String::new())),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl CompletionProvider for MockProvider {
+        async fn new(api_key: String, system_message: String) -> Result<Self> {
+            Ok(Self {
+                api_key,
+                system_message: Arc::new(std::sync::RwLock::new(system_message)),
+                ..Self::with_output(None)
+            })
+        }
+
+        async fn complete(&self, _prompt: &str) -> Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            // Unique output by default so topic de-duplication accepts the first attempt
+            Ok(self.fixed_output.clone()
+                .unwrap_or_else(|| format!("mock output {}", uuid::Uuid::new_v4())))
+        }
+
+        async fn generate_embedding(&self, _text: &str) -> Result<Vec<f32>> {
+            Ok(Vec::new())
+        }
+
+        async fn update_personality(&self, system_message: String) -> Result<()> {
+            *self.system_message.write().unwrap() = system_message;
+            Ok(())
+        }
+
+        async fn get_model_info(&self) -> Result<String> {
+            Ok("mock".to_string())
+        }
+
+        fn get_system_message(&self) -> String {
+            self.system_message.read().unwrap().clone()
+        }
+
+        fn get_api_key(&self) -> &String {
+            &self.api_key
+        }
+
+        fn clone_box(&self) -> Box<dyn CompletionProvider + Send + Sync> {
+            self.copies.fetch_add(1, Ordering::SeqCst);
+            Box::new(Self {
+                api_key: self.api_key.clone(),
+                fixed_output: self.fixed_output.clone(),
+                calls: self.calls.clone(),
+                copies: self.copies.clone(),
+                system_message: self.system_message.clone(),
+            })
+        }
+
+        fn with_system_message(&self, system_message: &str) -> Box<dyn CompletionProvider + Send + Sync> {
+            self.copies.fetch_add(1, Ordering::SeqCst);
+            Box::new(Self {
+                api_key: self.api_key.clone(),
+                fixed_output: self.fixed_output.clone(),
+                calls: self.calls.clone(),
+                copies: self.copies.clone(),
+                system_message: Arc::new(std::sync::RwLock::new(system_message.to_string())),
+            })
+        }
+    }
+
+    fn fixed_output_composer(output: &str) -> TweetComposer {
+        let mock = MockProvider::with_output(Some(output));
+        let provider: Box<dyn CompletionProvider + Send + Sync> = Box::new(mock);
+        TweetComposer::with_provider(Arc::new(provider))
+    }
+
+    fn test_profile(name: &str) -> PersonalityProfile {
+        PersonalityProfile {
+            name: name.to_string(),
+            attributes: serde_json::json!({ "description": "a test bot" }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_composer_reuses_injected_provider() {
+        let mock = MockProvider::with_output(None);
+        let (calls, copies) = (mock.calls.clone(), mock.copies.clone());
+        let provider: Box<dyn CompletionProvider + Send + Sync> = Box::new(mock);
+        let composer = TweetComposer::with_provider(Arc::new(provider));
+        let profile = test_profile("Composer Test Bot");
+
+        composer.generate_auto_tweet(&profile).await.unwrap();
+        composer.generate_auto_reply(&profile, "hello").await.unwrap();
+        composer.generate_dm(&profile, "someone").await.unwrap();
+
+        assert_eq!(copies.load(Ordering::SeqCst), 0);
+        assert!(calls.load(Ordering::SeqCst) >= 4);
+
+        composer.update_personality(&test_profile("Renamed Bot")).await.unwrap();
+        assert!(composer.provider.get_system_message().contains("Renamed Bot"));
+        assert_eq!(copies.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_topic_caches_are_independent() {
+        let profile = test_profile("Topic Test Bot");
+        let first = fixed_output_composer("Rust async runtimes");
+        let second = fixed_output_composer("Rust async runtimes");
+
+        assert_eq!(first.generate_auto_post_topic(&profile).await.unwrap(), "Rust async runtimes");
+        // The other composer has never seen this topic
+        assert_eq!(second.generate_auto_post_topic(&profile).await.unwrap(), "Rust async runtimes");
+
+        // A repeat on the same composer is disambiguated with a timestamp
+        let repeat = first.generate_auto_post_topic(&profile).await.unwrap();
+        assert_ne!(repeat, "Rust async runtimes");
+        assert!(repeat.starts_with("Rust async runtimes ("));
+    }
+
+    fn strip_number(part: &str) -> &str {
+        part.rsplit_once(' ').map(|(text, _)| text).unwrap()
+    }
+
+    #[test]
+    fn test_truncate_at_word_boundary() {
+        let content = "Rust makes systems programming safer, faster and more fun";
+
+        let cut = truncate_to(content, 40);
+
+        assert_eq!(cut, "Rust makes systems programming safer…");
+        assert!(tweet_length(&cut) <= 40);
+        assert_eq!(truncate_to(content, 200), content);
+        assert_eq!(truncate_to("Supercalifragilistic", 10), "Supercal…");
+
+        let long = "word ".repeat(100);
+        let cut = truncate_to(&long, DEFAULT_TWEET_MAX_LENGTH);
+        assert!((DEFAULT_TWEET_MAX_LENGTH - 5..=DEFAULT_TWEET_MAX_LENGTH).contains(&tweet_length(&cut)));
+        assert!(cut.ends_with("word…"));
+        assert!(tweet_length(&truncate_to(&long, 100)) <= 100);
+    }
+
+    #[test]
+    fn test_tweet_length_is_weighted() {
+        assert_eq!(tweet_length("hello"), 5);
+        assert_eq!(tweet_length("こんにちは"), 10);
+        assert_eq!(tweet_length("see https://example.com/a/very/long/path/to/some/page"), 4 + URL_LENGTH);
+        assert_eq!(tweet_length(&"日".repeat(141)), 282);
+        assert!(truncate_to(&"日本 ".repeat(100), 280).chars().count() < 280);
+    }
+
+    #[test]
+    fn test_short_content_is_single_tweet() {
+        assert_eq!(TweetComposer::compose_thread("Just one thought."), vec!["Just one thought."]);
+    }
+
+    #[test]
+    fn test_thread_splits_at_sentence_boundaries() {
+        let sentence = "This sentence is about forty-five characters.";
+        let content = [sentence; 12].join(" ");
+
+        let thread = TweetComposer::compose_thread(&content);
+
+        assert!(thread.len() > 1);
+        for (i, part) in thread.iter().enumerate() {
+            assert!(tweet_length(part) <= tweet_max_length());
+            assert!(part.ends_with(&format!(" {}/{}", i + 1, thread.len())));
+            assert!(strip_number(part).ends_with('.'), "split mid-sentence: {}", part);
+        }
+        let rejoined: Vec<&str> = thread.iter().map(|p| strip_number(p)).collect();
+        assert_eq!(rejoined.join(" "), content);
+    }
+
+    #[test]
+    fn test_thread_keeps_urls_whole() {
+        let url = "https://example.com/research/2024/summary.html?ref=thread";
+        let content = format!("{} Read more at {} for the details. {}",
+            "word ".repeat(40).trim(), url, "more ".repeat(30).trim());
+
+        let thread = TweetComposer::compose_thread(&content);
+
+        assert!(thread.len() > 1);
+        assert!(thread.iter().any(|part| part.contains(url)));
+    }
+
+    #[test]
+    fn test_thread_survives_a_limit_shorter_than_its_numbering() {
+        // No room is left for text, so each part carries a single character
+        let thread = TweetComposer::compose_thread_within("One two.", 3);
+        assert_eq!(thread.len(), 7);
+        assert_eq!(thread[0], "O 1/7");
+        assert_eq!(thread[6], ". 7/7");
     }
 }
@@ -3,6 +3,7 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 use anyhow::{Result, Error as AnyhowError};
+use async_trait::async_trait;
 use colored::Colorize;
 use std::fs::{OpenOptions, File};
 use std::io::{Write, BufRead, BufReader};
@@ -10,31 +11,172 @@ use std::io::{Write, BufRead, BufReader};
 use crate::personality::PersonalityProfile;
 use crate::providers::twitter::twitbrain::{TwitterProvider, TweetStatus, Mention};
 use crate::providers::twitter::composer::TweetComposer;
+use crate::providers::twitter::scheduler::{spawn_autopost, AutopostSchedule, ComposerSource, TweetPoster, DRY_RUN_LABEL};
+use crate::providers::twitter::rate_limit::{RateLimitInfo, RateLimitedPoster, RateLimiter};
 
 // Constants
 const DEFAULT_EMOJI: &str = "💭";
 
+type SendError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The account the manager sends through; lets it run without a live Twitter session.
+#[async_trait]
+pub trait TwitterAccount: TweetPoster {
+    async fn reply(&self, tweet_id: &str, content: &str) -> Result<TweetStatus, SendError>;
+    async fn send_dm(&self, username: &str, content: &str) -> Result<(), SendError>;
+    fn get_logs(&self, count: usize) -> std::io::Result<Vec<String>>;
+}
+
+#[async_trait]
+impl TwitterAccount for TwitterProvider {
+    async fn reply(&self, tweet_id: &str, content: &str) -> Result<TweetStatus, SendError> {
+        self.reply_to_tweet(tweet_id, content).await
+    }
+
+    async fn send_dm(&self, username: &str, content: &str) -> Result<(), SendError> {
+        TwitterProvider::send_dm(self, username, content).await
+    }
+
+    fn get_logs(&self, count: usize) -> std::io::Result<Vec<String>> {
+        TwitterProvider::get_logs(self, count)
+    }
+}
+
+/// What a send did.
+#[derive(Debug)]
+pub enum Sent<T> {
+    Posted(T),
+    /// Only printed, in a dry run
+    DryRun,
+}
+
 pub struct ConversationManager {
     profile: Arc<RwLock<PersonalityProfile>>,
-    twitter: Arc<TwitterProvider>,
+    twitter: Arc<dyn TwitterAccount>,
     auto_post_enabled: Arc<AtomicBool>,
     auto_post_task: Option<JoinHandle<()>>,
+    rate_limiter: Arc<RateLimiter>,
+    // Kept as the construction error so manual tweeting still works without an LLM key
+    composer: std::result::Result<Arc<TweetComposer>, String>,
+    /// Print what would be posted instead of posting it
+    dry_run: bool,
 }
 
 impl ConversationManager {
-    pub async fn new(profile: PersonalityProfile) -> Result<Self> {
+    /// With `dry_run`, tweets, replies and DMs are still written but only printed, so a
+    /// character can be tuned without anything reaching Twitter.
+    pub async fn new(profile: PersonalityProfile, dry_run: bool) -> Result<Self> {
         let twitter = TwitterProvider::new().await
             .map_err(|e| AnyhowError::msg(e.to_string()))?;
-        
-        Ok(Self { 
+
+        // Shared with the provider, which updates it from every tweet's rate limit headers
+        let rate_limiter = twitter.rate_limiter();
+        let mut manager = Self::with_account(profile, twitter, rate_limiter, dry_run).await;
+
+        // Resume a schedule that was running before the last shutdown
+        if let Some(schedule) = AutopostSchedule::load() {
+            println!("🤖 Resuming auto-post every {} minutes...", schedule.interval_minutes);
+            if let Err(e) = manager.start_autopost(schedule.interval_minutes) {
+                println!("❌ Could not resume auto-posting: {}", e);
+            }
+        }
+
+        Ok(manager)
+    }
+
+    /// A manager sending through `account`, pacing itself with `rate_limiter`.
+    pub async fn with_account(profile: PersonalityProfile, account: Arc<dyn TwitterAccount>, rate_limiter: Arc<RateLimiter>, dry_run: bool) -> Self {
+        let composer = match TweetComposer::new(&profile).await {
+            Ok(composer) => Ok(Arc::new(composer)),
+            Err(e) => {
+                println!("⚠️ AI tweet generation unavailable: {}", e);
+                Err(e.to_string())
+            }
+        };
+
+        if dry_run {
+            println!("{} Twitter commands will print what they would send instead of posting it.", DRY_RUN_LABEL.yellow().bold());
+        }
+        Self {
             profile: Arc::new(RwLock::new(profile)),
-            twitter,
+            twitter: account,
             auto_post_enabled: Arc::new(AtomicBool::new(false)),
             auto_post_task: None,
-        })
+            rate_limiter,
+            composer,
+            dry_run,
+        }
+    }
+
+    /// Last-seen Twitter rate limit budget.
+    pub fn rate_limit_info(&self) -> RateLimitInfo {
+        self.rate_limiter.info()
+    }
+
+    /// In a dry run, prints what would have been sent and returns true, so the caller stops
+    /// before posting.
+    fn dry_run(&self, what: impl std::fmt::Display) -> bool {
+        if self.dry_run {
+            println!("{} Would {}", DRY_RUN_LABEL.yellow().bold(), what);
+        }
+        self.dry_run
+    }
+
+    fn composer(&self) -> Result<Arc<TweetComposer>> {
+        self.composer.clone().map_err(AnyhowError::msg)
+    }
+
+    pub fn is_autopost_running(&self) -> bool {
+        self.auto_post_task.as_ref().is_some_and(|task| !task.is_finished())
+    }
+
+    pub fn start_autopost(&mut self, interval_minutes: u64) -> Result<()> {
+        if interval_minutes == 0 {
+            return Err(AnyhowError::msg("Auto-post interval must be at least 1 minute"));
+        }
+        if self.is_autopost_running() {
+            return Err(AnyhowError::msg("Auto-posting is already running. Use 'autopost stop' first."));
+        }
+
+        let composer = self.composer()?;
+        let task = spawn_autopost(
+            std::time::Duration::from_secs(interval_minutes * 60),
+            Arc::new(ComposerSource::new(composer, self.profile.clone())),
+            Arc::new(RateLimitedPoster::new(self.twitter.clone(), self.rate_limiter.clone())),
+            self.dry_run,
+        );
+        self.auto_post_task = Some(task);
+        self.auto_post_enabled.store(true, Ordering::SeqCst);
+
+        // A dry run mustn't leave a schedule that a later, live run would resume
+        if self.dry_run {
+            return Ok(());
+        }
+        if let Err(e) = AutopostSchedule::new(interval_minutes).save() {
+            eprintln!("Warning: Failed to persist auto-post schedule: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Cancels the running autopost task. Returns false if none was running.
+    pub fn stop_autopost(&mut self) -> bool {
+        self.auto_post_enabled.store(false, Ordering::SeqCst);
+        AutopostSchedule::clear();
+        match self.auto_post_task.take() {
+            Some(task) => {
+                task.abort();
+                true
+            }
+            None => false,
+        }
     }
 
     pub async fn update_personality(&mut self, profile: PersonalityProfile) {
+        if let Ok(composer) = &self.composer {
+            if let Err(e) = composer.update_personality(&profile).await {
+                eprintln!("Warning: Failed to update tweet composer personality: {}", e);
+            }
+        }
         let mut current_profile = self.profile.write().await;
         *current_profile = profile;
     }
@@ -63,11 +205,12 @@ impl ConversationManager {
         println!("Character: {}", desc);
         
         // First generate a topic using the verified profile
-        let topic = TweetComposer::generate_auto_post_topic(profile).await?;
+        let composer = self.composer()?;
+        let topic = composer.generate_auto_post_topic(profile).await?;
         println!("📝 Generated topic: \"{}\"", topic);
         
         // Then generate a tweet about that topic using the same profile
-        let tweet = TweetComposer::generate_auto_tweet(profile).await?;
+        let tweet = composer.generate_auto_tweet(profile).await?;
         println!("✍️ Generated tweet in {}'s style", name);
         Ok(tweet)
     }
@@ -90,10 +233,11 @@ impl ConversationManager {
                         
                         if input.trim().to_lowercase() == "y" {
                             match self.direct_tweet(&tweet_content).await {
-                                Ok(status) => {
+                                Ok(Sent::Posted(status)) => {
                                     println!("✅ Tweet posted successfully!");
                                     println!("🔗 Tweet URL: {}", status.url);
                                 },
+                                Ok(Sent::DryRun) => {},
                                 Err(e) => println!("❌ Failed to post tweet: {}", e)
                             }
                         } else {
@@ -104,6 +248,39 @@ impl ConversationManager {
                 }
             },
             
+            s if s.starts_with("tweet thread ") => {
+                let content = s.trim_start_matches("tweet thread ").trim();
+                if content.is_empty() {
+                    println!("❌ Thread content cannot be empty");
+                    return Ok(());
+                }
+
+                let parts = TweetComposer::compose_thread(content);
+                println!("🧵 Thread preview ({} tweets):", parts.len());
+                for part in &parts {
+                    println!("  {}", part);
+                }
+                println!("\nWould you like to post this thread? (y/n)");
+
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input)?;
+
+                if input.trim().to_lowercase() == "y" {
+                    match self.post_thread(&parts).await {
+                        Ok(Sent::Posted(statuses)) => {
+                            println!("✅ Thread of {} tweets posted successfully!", statuses.len());
+                            if let Some(first) = statuses.first() {
+                                println!("🔗 Thread URL: {}", first.url);
+                            }
+                        },
+                        Ok(Sent::DryRun) => {},
+                        Err(e) => println!("❌ Failed to post thread: {}", e)
+                    }
+                } else {
+                    println!("Thread cancelled.");
+                }
+            },
+
             s if s.starts_with("tweet ") => {
                 let content = s.trim_start_matches("tweet ").trim();
                 if content.is_empty() {
@@ -113,10 +290,11 @@ impl ConversationManager {
 
                 println!("🐦 Posting tweet: \"{}\"", content);
                 match self.direct_tweet(content).await {
-                    Ok(status) => {
+                    Ok(Sent::Posted(status)) => {
                         println!("✅ Tweet posted successfully!");
                         println!("🔗 Tweet URL: {}", status.url);
                     },
+                    Ok(Sent::DryRun) => {},
                     Err(e) => println!("❌ Failed to post tweet: {}", e)
                 }
             },
@@ -124,42 +302,14 @@ impl ConversationManager {
             s if s.starts_with("autopost start ") => {
                 let minutes = s.trim_start_matches("autopost start ").trim();
                 if let Ok(mins) = minutes.parse::<u64>() {
-                    println!("🤖 Starting auto-post every {} minutes...", mins);
-                    println!("(Type 'autopost stop' to stop auto-posting)");
-                    
-                    let auto_post_enabled = self.auto_post_enabled.clone();
-                    let profile = self.profile.clone();
-                    let twitter = self.twitter.clone();
-
-                    let task = tokio::spawn(async move {
-                        while auto_post_enabled.load(Ordering::SeqCst) {
-                            // Get the current profile
-                            let profile_guard = profile.read().await;
-                            let current_profile = &*profile_guard;
-                            match TweetComposer::generate_auto_tweet(current_profile).await {
-                                Ok(tweet_content) => {
-                                    match twitter.post_tweet(&tweet_content, true).await {
-                                        Ok(status) => {
-                                            println!("✅ Auto-tweet posted successfully as {}!", current_profile.name);
-                                            println!("🔗 Tweet URL: {}", status.url);
-                                        },
-                                        Err(e) => println!("❌ Failed to post tweet: {}", e)
-                                    }
-                                },
-                                Err(e) => println!("❌ Failed to generate tweet: {}", e)
-                            }
-                            // Drop the read lock
-                            drop(profile_guard);
-
-                            println!("⏰ Next auto-tweet in {} minutes...", mins);
-                            tokio::time::sleep(tokio::time::Duration::from_secs(mins * 60)).await;
-                        }
-                        println!("Auto-posting stopped.");
-                    });
-
-                    self.auto_post_task = Some(task);
-                    self.auto_post_enabled.store(true, Ordering::SeqCst);
-                    println!("Auto-posting is running in the background. You can continue chatting!");
+                    match self.start_autopost(mins) {
+                        Ok(()) => {
+                            println!("🤖 Starting auto-post every {} minutes...", mins);
+                            println!("(Type 'autopost stop' to stop auto-posting)");
+                            println!("Auto-posting is running in the background. You can continue chatting!");
+                        },
+                        Err(e) => println!("❌ {}", e)
+                    }
                 } else {
                     println!("❌ Invalid minutes value. Please use a number.");
                     println!("Example: autopost start 30");
@@ -167,10 +317,8 @@ impl ConversationManager {
             },
 
             "autopost stop" => {
-                self.auto_post_enabled.store(false, Ordering::SeqCst);
                 println!("🛑 Stopping auto-post...");
-                if let Some(task) = self.auto_post_task.take() {
-                    task.abort();
+                if self.stop_autopost() {
                     println!("Auto-posting stopped successfully!");
                 } else {
                     println!("No auto-posting task was running.");
@@ -180,11 +328,12 @@ impl ConversationManager {
             s if s.starts_with("reply ") => {
                 if let Some((tweet_id, content)) = s.trim_start_matches("reply ").split_once(' ') {
                     println!("🔄 Posting reply to tweet {}...", tweet_id);
-                    match self.twitter.reply_to_tweet(tweet_id.trim(), content.trim()).await {
-                        Ok(status) => {
+                    match self.reply_to_tweet(tweet_id.trim(), content.trim()).await {
+                        Ok(Sent::Posted(status)) => {
                             println!("✅ Reply posted successfully!");
                             println!("🔗 Reply URL: {}", status.url);
                         },
+                        Ok(Sent::DryRun) => {},
                         Err(e) => println!("❌ Failed to post reply: {}", e)
                     }
                 } else {
@@ -195,8 +344,9 @@ impl ConversationManager {
             s if s.starts_with("dm @") => {
                 if let Some((username, message)) = s.trim_start_matches("dm @").split_once(": ") {
                     println!("📨 Sending DM to @{}...", username);
-                    match self.twitter.send_dm(username.trim(), message.trim()).await {
-                        Ok(_) => println!("✅ DM sent successfully!"),
+                    match self.send_dm(username.trim(), message.trim()).await {
+                        Ok(Sent::Posted(())) => println!("✅ DM sent successfully!"),
+                        Ok(Sent::DryRun) => {},
                         Err(e) => println!("❌ Failed to send DM: {}", e)
                     }
                 } else {
@@ -216,6 +366,7 @@ impl ConversationManager {
                     },
                     Err(e) => println!("❌ Error reading logs: {}", e)
                 }
+                println!("⏱️  Rate limit: {}", self.rate_limit_info().summary());
             },
 
             s if s.starts_with("logs ") => {
@@ -231,6 +382,7 @@ impl ConversationManager {
                         },
                         Err(e) => println!("❌ Error reading logs: {}", e)
                     }
+                    println!("⏱️  Rate limit: {}", self.rate_limit_info().summary());
                 } else {
                     println!("❌ Invalid number. Usage: logs <number>");
                     println!("Example: logs 20");
@@ -242,7 +394,7 @@ impl ConversationManager {
                     println!("🤖 Generating AI reply to tweet: \"{}\"", tweet_text);
                     let profile_guard = self.profile.read().await;
                     let profile = &*profile_guard;
-                    match TweetComposer::generate_auto_reply(profile, tweet_text).await {
+                    match self.composer()?.generate_auto_reply(profile, tweet_text).await {
                         Ok(reply) => {
                             println!("📝 Generated reply: \"{}\"", reply);
                             println!("\nWould you like to post this reply? (y/n)");
@@ -251,11 +403,12 @@ impl ConversationManager {
                             std::io::stdin().read_line(&mut input)?;
                             
                             if input.trim().to_lowercase() == "y" {
-                                match self.twitter.reply_to_tweet(tweet_id.trim(), &reply).await {
-                                    Ok(status) => {
+                                match self.reply_to_tweet(tweet_id.trim(), &reply).await {
+                                    Ok(Sent::Posted(status)) => {
                                         println!("✅ Reply posted successfully!");
                                         println!("🔗 Reply URL: {}", status.url);
                                     },
+                                    Ok(Sent::DryRun) => {},
                                     Err(e) => println!("❌ Failed to post reply: {}", e)
                                 }
                             } else {
@@ -274,7 +427,7 @@ impl ConversationManager {
                     println!("🤖 Generating AI DM for @{}...", username);
                     let profile_guard = self.profile.read().await;
                     let profile = &*profile_guard;
-                    match TweetComposer::generate_dm(profile, username).await {
+                    match self.composer()?.generate_dm(profile, username).await {
                         Ok(dm) => {
                             println!("📝 Generated DM: \"{}\"", dm);
                             println!("\nWould you like to send this DM? (y/n)");
@@ -283,8 +436,9 @@ impl ConversationManager {
                             std::io::stdin().read_line(&mut input)?;
                             
                             if input.trim().to_lowercase() == "y" {
-                                match self.twitter.send_dm(username.trim(), &dm).await {
-                                    Ok(_) => println!("✅ DM sent successfully!"),
+                                match self.send_dm(username.trim(), &dm).await {
+                                    Ok(Sent::Posted(())) => println!("✅ DM sent successfully!"),
+                                    Ok(Sent::DryRun) => {},
                                     Err(e) => println!("❌ Failed to send DM: {}", e)
                                 }
                             } else {
@@ -307,7 +461,7 @@ impl ConversationManager {
                         id: None,
                         text: mention_text.to_string()
                     };
-                    match TweetComposer::generate_mention_response(profile, &mention).await {
+                    match self.composer()?.generate_mention_response(profile, &mention).await {
                         Ok(response) => {
                             println!("📝 Generated response: \"{}\"", response);
                             println!("\nWould you like to post this response? (y/n)");
@@ -316,11 +470,12 @@ impl ConversationManager {
                             std::io::stdin().read_line(&mut input)?;
                             
                             if input.trim().to_lowercase() == "y" {
-                                match self.twitter.post_tweet(&response, false).await {
-                                    Ok(status) => {
+                                match self.direct_tweet(&response).await {
+                                    Ok(Sent::Posted(status)) => {
                                         println!("✅ Response posted successfully!");
                                         println!("🔗 Response URL: {}", status.url);
                                     },
+                                    Ok(Sent::DryRun) => {},
                                     Err(e) => println!("❌ Failed to post response: {}", e)
                                 }
                             } else {
@@ -338,7 +493,8 @@ impl ConversationManager {
                 println!("🤖 Generating tweet topic...");
                 let profile_guard = self.profile.read().await;
                 let profile = &*profile_guard;
-                match TweetComposer::generate_auto_post_topic(profile).await {
+                let composer = self.composer()?;
+                match composer.generate_auto_post_topic(profile).await {
                     Ok(topic) => {
                         println!("📝 Generated topic: \"{}\"", topic);
                         println!("\nWould you like to generate a tweet about this topic? (y/n)");
@@ -347,7 +503,7 @@ impl ConversationManager {
                         std::io::stdin().read_line(&mut input)?;
                         
                         if input.trim().to_lowercase() == "y" {
-                            match TweetComposer::generate_auto_tweet(profile).await {
+                            match composer.generate_auto_tweet(profile).await {
                                 Ok(tweet_content) => {
                                     println!("📝 Generated tweet: \"{}\"", tweet_content);
                                     println!("\nWould you like to post this tweet? (y/n)");
@@ -357,10 +513,11 @@ impl ConversationManager {
                                     
                                     if input.trim().to_lowercase() == "y" {
                                         match self.direct_tweet(&tweet_content).await {
-                                            Ok(status) => {
+                                            Ok(Sent::Posted(status)) => {
                                                 println!("✅ Tweet posted successfully!");
                                                 println!("🔗 Tweet URL: {}", status.url);
                                             },
+                                            Ok(Sent::DryRun) => {},
                                             Err(e) => println!("❌ Failed to post tweet: {}", e)
                                         }
                                     } else {
@@ -379,6 +536,7 @@ impl ConversationManager {
                 println!("Available Twitter commands:");
                 println!("  tweet                     - Generate and post an AI tweet");
                 println!("  tweet <message>           - Post a specific tweet");
+                println!("  tweet thread <text>       - Split long text into a numbered thread and post it");
                 println!("  topic                     - Generate a tweet topic");
                 println!("  autoreply <id> <text>     - Generate AI reply to a tweet");
                 println!("  autodm @user: <context>   - Generate AI DM to a user");
@@ -417,16 +575,116 @@ impl ConversationManager {
         Ok(())
     }
 
-    pub async fn direct_tweet(&self, content: &str) -> Result<TweetStatus> {
-        self.twitter.post_tweet(content, true).await
+    /// Posts `content` as a tweet, or in a dry run only prints it.
+    pub async fn direct_tweet(&self, content: &str) -> Result<Sent<TweetStatus>> {
+        if self.dry_run(format!("tweet: \"{}\"", content)) {
+            return Ok(Sent::DryRun);
+        }
+        self.rate_limiter.run(|| self.twitter.post(content)).await
+            .map(Sent::Posted)
             .map_err(|e| AnyhowError::msg(e.to_string()))
     }
 
-    async fn reply_to_tweet(&self, tweet_id: &str, content: &str) -> Result<TweetStatus, Box<dyn std::error::Error + Send + Sync>> {
-        self.twitter.reply_to_tweet(tweet_id, content).await
+    /// Posts the first part as a tweet and each following part as a reply to the previous one.
+    pub async fn post_thread(&self, parts: &[String]) -> Result<Sent<Vec<TweetStatus>>> {
+        if self.dry_run(format!("post this thread of {} tweets", parts.len())) {
+            return Ok(Sent::DryRun);
+        }
+        let mut statuses: Vec<TweetStatus> = Vec::with_capacity(parts.len());
+        for part in parts {
+            let status = match statuses.last() {
+                None => self.rate_limiter.run(|| self.twitter.post(part)).await,
+                Some(previous) => self.rate_limiter.run(|| self.twitter.reply(&previous.tweet_id, part)).await,
+            };
+            let status = status.map_err(|e| match statuses.len() {
+                0 => AnyhowError::msg(e.to_string()),
+                posted => AnyhowError::msg(format!("Posted {} of {} tweets, then failed: {}", posted, parts.len(), e)),
+            })?;
+            statuses.push(status);
+        }
+        Ok(Sent::Posted(statuses))
+    }
+
+    async fn reply_to_tweet(&self, tweet_id: &str, content: &str) -> Result<Sent<TweetStatus>, Box<dyn std::error::Error + Send + Sync>> {
+        if self.dry_run(format!("reply to tweet {}: \"{}\"", tweet_id, content)) {
+            return Ok(Sent::DryRun);
+        }
+        self.rate_limiter.run(|| self.twitter.reply(tweet_id, content)).await.map(Sent::Posted)
     }
 
-    async fn send_dm(&self, username: &str, content: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        self.twitter.send_dm(username, content).await
+    async fn send_dm(&self, username: &str, content: &str) -> Result<Sent<()>, Box<dyn std::error::Error + Send + Sync>> {
+        if self.dry_run(format!("DM @{}: \"{}\"", username, content)) {
+            return Ok(Sent::DryRun);
+        }
+        self.rate_limiter.run(|| self.twitter.send_dm(username, content)).await.map(Sent::Posted)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingAccount {
+        sent: AtomicUsize,
+    }
+
+    impl CountingAccount {
+        fn status(&self) -> TweetStatus {
+            let n = self.sent.fetch_add(1, Ordering::SeqCst);
+            TweetStatus { tweet_id: n.to_string(), url: format!("https://twitter.com/i/status/{}", n) }
+        }
+    }
+
+    #[async_trait]
+    impl TweetPoster for CountingAccount {
+        async fn post(&self, _content: &str) -> Result<TweetStatus, SendError> {
+            Ok(self.status())
+        }
+    }
+
+    #[async_trait]
+    impl TwitterAccount for CountingAccount {
+        async fn reply(&self, _tweet_id: &str, _content: &str) -> Result<TweetStatus, SendError> {
+            Ok(self.status())
+        }
+
+        async fn send_dm(&self, _username: &str, _content: &str) -> Result<(), SendError> {
+            self.status();
+            Ok(())
+        }
+
+        fn get_logs(&self, _count: usize) -> std::io::Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+    }
+
+    async fn manager(account: Arc<CountingAccount>, dry_run: bool) -> ConversationManager {
+        let profile = PersonalityProfile {
+            name: "tester".to_string(),
+            attributes: serde_json::json!({ "description": "a test bot" }),
+        };
+        ConversationManager::with_account(profile, account, Arc::new(RateLimiter::in_memory()), dry_run).await
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_sends_nothing() {
+        let commands = ["tweet hello world", "reply 123 nice post", "dm @someone: hi there"];
+        let thread = vec!["1/2 first".to_string(), "2/2 second".to_string()];
+
+        let account = Arc::new(CountingAccount::default());
+        let mut dry = manager(account.clone(), true).await;
+        for command in commands {
+            dry.handle_command(command).await.unwrap();
+        }
+        assert!(matches!(dry.post_thread(&thread).await.unwrap(), Sent::DryRun));
+        assert_eq!(account.sent.load(Ordering::SeqCst), 0);
+
+        let mut live = manager(account.clone(), false).await;
+        for command in commands {
+            live.handle_command(command).await.unwrap();
+        }
+        assert!(matches!(live.post_thread(&thread).await.unwrap(), Sent::Posted(statuses) if statuses.len() == 2));
+        assert_eq!(account.sent.load(Ordering::SeqCst), 5);
+    }
+}
@@ -1 +1 @@
-pub mod twitbrain; pub mod manager; pub mod conversation; pub mod scheduler; pub mod composer;
+pub mod twitbrain; pub mod manager; pub mod conversation; pub mod scheduler; pub mod composer; pub mod rate_limit;
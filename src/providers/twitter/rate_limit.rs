@@ -0,0 +1,298 @@
+use crate::providers::twitter::scheduler::TweetPoster;
+use crate::providers::twitter::twitbrain::TweetStatus;
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const RATE_LIMIT_STATE_PATH: &str = "data/twitter_rate_limit.json";
+
+// Twitter's rate limit windows are 15 minutes long
+const DEFAULT_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+// Start pacing requests once this many calls are left in the window
+const LOW_WATERMARK: u32 = 1;
+
+const MAX_RATE_LIMIT_RETRIES: usize = 3;
+
+/// Last-seen `x-rate-limit-*` values for the posting endpoints.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitInfo {
+    pub limit: Option<u32>,
+    pub remaining: Option<u32>,
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimitInfo {
+    /// Parses `x-rate-limit-limit`, `x-rate-limit-remaining` and `x-rate-limit-reset` (epoch seconds).
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let number = |name: &str| {
+            headers.get(name)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<i64>().ok())
+        };
+
+        let info = Self {
+            limit: number("x-rate-limit-limit").map(|v| v.max(0) as u32),
+            remaining: number("x-rate-limit-remaining").map(|v| v.max(0) as u32),
+            reset_at: number("x-rate-limit-reset").and_then(|v| Utc.timestamp_opt(v, 0).single()),
+        };
+
+        if info == Self::default() { None } else { Some(info) }
+    }
+
+    /// Time left until the window resets, if it hasn't already.
+    pub fn time_until_reset(&self) -> Option<Duration> {
+        self.reset_at?.signed_duration_since(Utc::now()).to_std().ok()
+    }
+
+    pub fn summary(&self) -> String {
+        let remaining = match (self.remaining, self.limit) {
+            (Some(remaining), Some(limit)) => format!("{}/{} requests left", remaining, limit),
+            (Some(remaining), None) => format!("{} requests left", remaining),
+            _ => "remaining requests unknown".to_string(),
+        };
+        match self.time_until_reset() {
+            Some(wait) => format!("{}, window resets in {}s", remaining, wait.as_secs()),
+            None => remaining,
+        }
+    }
+
+    pub fn load() -> Option<Self> {
+        let content = std::fs::read_to_string(RATE_LIMIT_STATE_PATH).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(parent) = std::path::Path::new(RATE_LIMIT_STATE_PATH).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(RATE_LIMIT_STATE_PATH, content)
+    }
+}
+
+/// Returned when Twitter answers with HTTP 429.
+#[derive(Debug)]
+pub struct RateLimitedError {
+    pub info: Option<RateLimitInfo>,
+}
+
+impl fmt::Display for RateLimitedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Twitter rate limit exceeded")
+    }
+}
+
+impl Error for RateLimitedError {}
+
+/// Recognizes rate-limit failures, including the scraper's textual "status: 429" errors.
+pub fn is_rate_limited(error: &(dyn Error + Send + Sync + 'static)) -> bool {
+    if error.downcast_ref::<RateLimitedError>().is_some() {
+        return true;
+    }
+    if let Some(agent_twitter_client::error::TwitterError::RateLimit) = error.downcast_ref() {
+        return true;
+    }
+    error.to_string().contains("status: 429")
+}
+
+/// Tracks the posting budget and delays calls instead of letting them fail on a 429.
+pub struct RateLimiter {
+    state: Mutex<RateLimitInfo>,
+    persist: bool,
+}
+
+impl RateLimiter {
+    /// Starts from the limits stored by the previous run so a restart doesn't burst.
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(RateLimitInfo::load().unwrap_or_default()),
+            persist: true,
+        }
+    }
+
+    pub fn in_memory() -> Self {
+        Self {
+            state: Mutex::new(RateLimitInfo::default()),
+            persist: false,
+        }
+    }
+
+    pub fn info(&self) -> RateLimitInfo {
+        self.state.lock().unwrap().clone()
+    }
+
+    pub fn update(&self, info: RateLimitInfo) {
+        let mut state = self.state.lock().unwrap();
+        *state = info;
+        self.persist_state(&state);
+    }
+
+    pub fn update_from_headers(&self, headers: &HeaderMap) {
+        if let Some(info) = RateLimitInfo::from_headers(headers) {
+            self.update(info);
+        }
+    }
+
+    /// How long to hold off before the next request, if the budget is nearly spent.
+    pub fn delay_before_request(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        match state.remaining {
+            Some(remaining) if remaining <= LOW_WATERMARK => state.time_until_reset(),
+            _ => None,
+        }
+    }
+
+    /// Runs `op`, pacing it against the known budget and waiting out 429s until the window resets.
+    pub async fn run<T, F, Fut>(&self, mut op: F) -> Result<T, Box<dyn Error + Send + Sync>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Box<dyn Error + Send + Sync>>>,
+    {
+        let mut attempts = 0;
+        loop {
+            if let Some(wait) = self.delay_before_request() {
+                println!("⏳ Near Twitter rate limit, waiting {}s before posting...", wait.as_secs());
+                tokio::time::sleep(wait).await;
+            }
+
+            match op().await {
+                Ok(value) => {
+                    self.record_success();
+                    return Ok(value);
+                }
+                Err(e) if is_rate_limited(e.as_ref()) && attempts < MAX_RATE_LIMIT_RETRIES => {
+                    attempts += 1;
+                    let wait = self.record_rate_limited(e.as_ref());
+                    println!("⏳ Twitter rate limit hit, retrying in {}s...", wait.as_secs());
+                    tokio::time::sleep(wait).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.reset_at.is_some_and(|reset| reset <= Utc::now()) {
+            *state = RateLimitInfo::default();
+        } else if let Some(remaining) = state.remaining.as_mut() {
+            *remaining = remaining.saturating_sub(1);
+        }
+        self.persist_state(&state);
+    }
+
+    fn record_rate_limited(&self, error: &(dyn Error + Send + Sync + 'static)) -> Duration {
+        let reported = error.downcast_ref::<RateLimitedError>()
+            .and_then(|e| e.info.clone())
+            .filter(|info| info.time_until_reset().is_some());
+
+        let info = reported.unwrap_or_else(|| RateLimitInfo {
+            limit: None,
+            remaining: Some(0),
+            reset_at: chrono::Duration::from_std(DEFAULT_WINDOW).ok().map(|d| Utc::now() + d),
+        });
+        let wait = info.time_until_reset().unwrap_or(DEFAULT_WINDOW);
+        self.update(RateLimitInfo { remaining: Some(0), ..info });
+        wait
+    }
+
+    fn persist_state(&self, state: &RateLimitInfo) {
+        if self.persist {
+            if let Err(e) = state.save() {
+                eprintln!("Warning: Failed to persist Twitter rate limit state: {}", e);
+            }
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wraps a poster so autoposts respect the shared rate limiter.
+pub struct RateLimitedPoster {
+    inner: Arc<dyn TweetPoster>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitedPoster {
+    pub fn new(inner: Arc<dyn TweetPoster>, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+#[async_trait]
+impl TweetPoster for RateLimitedPoster {
+    async fn post(&self, content: &str) -> Result<TweetStatus, Box<dyn Error + Send + Sync>> {
+        self.limiter.run(|| self.inner.post(content)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    struct RateLimitedOnce {
+        calls: AtomicUsize,
+        reset_at: i64,
+    }
+
+    #[async_trait]
+    impl TweetPoster for RateLimitedOnce {
+        async fn post(&self, _content: &str) -> Result<TweetStatus, Box<dyn Error + Send + Sync>> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                let mut headers = HeaderMap::new();
+                headers.insert("x-rate-limit-remaining", HeaderValue::from_static("0"));
+                headers.insert("x-rate-limit-reset", HeaderValue::from_str(&self.reset_at.to_string()).unwrap());
+                return Err(Box::new(RateLimitedError { info: RateLimitInfo::from_headers(&headers) }));
+            }
+            Ok(TweetStatus {
+                tweet_id: "1".to_string(),
+                url: "https://twitter.com/i/status/1".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_parses_rate_limit_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-rate-limit-limit", HeaderValue::from_static("300"));
+        headers.insert("x-rate-limit-remaining", HeaderValue::from_static("12"));
+        headers.insert("x-rate-limit-reset", HeaderValue::from_static("1700000000"));
+
+        let info = RateLimitInfo::from_headers(&headers).unwrap();
+        assert_eq!(info.limit, Some(300));
+        assert_eq!(info.remaining, Some(12));
+        assert_eq!(info.reset_at.unwrap().timestamp(), 1_700_000_000);
+        assert!(RateLimitInfo::from_headers(&HeaderMap::new()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_reset_after_429() {
+        let limiter = Arc::new(RateLimiter::in_memory());
+        let inner = Arc::new(RateLimitedOnce {
+            calls: AtomicUsize::new(0),
+            reset_at: Utc::now().timestamp() + 2,
+        });
+        let poster = RateLimitedPoster::new(inner.clone(), limiter.clone());
+
+        let started = Instant::now();
+        let status = poster.post("hello").await.expect("post should wait out the rate limit");
+
+        assert_eq!(status.tweet_id, "1");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+        assert!(started.elapsed() >= Duration::from_millis(900));
+    }
+}
@@ -1,5 +1,15 @@
+use crate::personality::PersonalityProfile;
+use crate::providers::twitter::composer::TweetComposer;
 use crate::providers::twitter::twitbrain::{TwitterProvider, TweetStatus};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+
+const AUTOPOST_SCHEDULE_PATH: &str = "data/autopost_schedule.json";
 
 pub struct Scheduler {
     twitter: TwitterProvider
@@ -16,3 +26,178 @@ impl Scheduler {
     }
 }
 
+/// Something that can publish a tweet; lets the autopost loop run without a live Twitter session.
+#[async_trait]
+pub trait TweetPoster: Send + Sync {
+    async fn post(&self, content: &str) -> Result<TweetStatus, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+#[async_trait]
+impl TweetPoster for TwitterProvider {
+    async fn post(&self, content: &str) -> Result<TweetStatus, Box<dyn std::error::Error + Send + Sync>> {
+        self.post_tweet(content, true).await
+    }
+}
+
+/// Produces the content for each autopost.
+#[async_trait]
+pub trait TweetSource: Send + Sync {
+    async fn next_tweet(&self) -> anyhow::Result<(String, String)>;
+}
+
+/// Generates tweets in the voice of the current (possibly swapped) character.
+pub struct ComposerSource {
+    composer: Arc<TweetComposer>,
+    profile: Arc<RwLock<PersonalityProfile>>,
+}
+
+impl ComposerSource {
+    pub fn new(composer: Arc<TweetComposer>, profile: Arc<RwLock<PersonalityProfile>>) -> Self {
+        Self { composer, profile }
+    }
+}
+
+#[async_trait]
+impl TweetSource for ComposerSource {
+    async fn next_tweet(&self) -> anyhow::Result<(String, String)> {
+        let profile = self.profile.read().await;
+        let tweet = self.composer.generate_auto_tweet(&profile).await?;
+        Ok((tweet, profile.name.clone()))
+    }
+}
+
+/// Label on everything a dry run would have sent.
+pub const DRY_RUN_LABEL: &str = "[DRY RUN]";
+
+/// Spawns a loop that posts immediately and then once per `interval` until the handle is aborted.
+/// With `dry_run`, each tweet is still generated but only printed, never handed to `poster`.
+pub fn spawn_autopost(
+    interval: Duration,
+    source: Arc<dyn TweetSource>,
+    poster: Arc<dyn TweetPoster>,
+    dry_run: bool,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match source.next_tweet().await {
+                Ok((tweet_content, author)) if dry_run => {
+                    println!("{} Would auto-tweet as {}: \"{}\"", DRY_RUN_LABEL, author, tweet_content);
+                },
+                Ok((tweet_content, author)) => {
+                    match poster.post(&tweet_content).await {
+                        Ok(status) => {
+                            println!("✅ Auto-tweet posted successfully as {}!", author);
+                            println!("🔗 Tweet URL: {}", status.url);
+                        },
+                        Err(e) => println!("❌ Failed to post tweet: {}", e)
+                    }
+                },
+                Err(e) => println!("❌ Failed to generate tweet: {}", e)
+            }
+            println!("⏰ Next auto-tweet in {} minutes...", interval.as_secs() / 60);
+        }
+    })
+}
+
+/// Persisted autopost settings so a restart with `--twitter` resumes posting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutopostSchedule {
+    pub interval_minutes: u64,
+    pub started_at: DateTime<Utc>,
+}
+
+impl AutopostSchedule {
+    pub fn new(interval_minutes: u64) -> Self {
+        Self {
+            interval_minutes,
+            started_at: Utc::now(),
+        }
+    }
+
+    pub fn load() -> Option<Self> {
+        let content = std::fs::read_to_string(AUTOPOST_SCHEDULE_PATH).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        if let Some(parent) = std::path::Path::new(AUTOPOST_SCHEDULE_PATH).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(AUTOPOST_SCHEDULE_PATH, content)
+    }
+
+    pub fn clear() {
+        std::fs::remove_file(AUTOPOST_SCHEDULE_PATH).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingSource {
+        composed: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TweetSource for CountingSource {
+        async fn next_tweet(&self) -> anyhow::Result<(String, String)> {
+            self.composed.fetch_add(1, Ordering::SeqCst);
+            Ok(("scheduled tweet".to_string(), "Test Bot".to_string()))
+        }
+    }
+
+    struct MockPoster {
+        posts: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TweetPoster for MockPoster {
+        async fn post(&self, _content: &str) -> Result<TweetStatus, Box<dyn std::error::Error + Send + Sync>> {
+            let n = self.posts.fetch_add(1, Ordering::SeqCst);
+            Ok(TweetStatus {
+                tweet_id: n.to_string(),
+                url: format!("https://twitter.com/i/status/{}", n),
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_autopost_posts_on_interval_until_stopped() {
+        let poster = Arc::new(MockPoster { posts: AtomicUsize::new(0) });
+        let task = spawn_autopost(
+            Duration::from_secs(60),
+            Arc::new(CountingSource::default()),
+            poster.clone(),
+            false,
+        );
+
+        // Posts at once, then at 1 through 5 minutes
+        tokio::time::sleep(Duration::from_secs(330)).await;
+        task.abort();
+        let _ = task.await;
+        assert_eq!(poster.posts.load(Ordering::SeqCst), 6);
+
+        tokio::time::sleep(Duration::from_secs(300)).await;
+        assert_eq!(poster.posts.load(Ordering::SeqCst), 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_dry_run_composes_but_never_posts() {
+        let source = Arc::new(CountingSource::default());
+        let poster = Arc::new(MockPoster { posts: AtomicUsize::new(0) });
+        let task = spawn_autopost(Duration::from_secs(60), source.clone(), poster.clone(), true);
+
+        tokio::time::sleep(Duration::from_secs(150)).await;
+        task.abort();
+        let _ = task.await;
+
+        assert_eq!(source.composed.load(Ordering::SeqCst), 3);
+        assert_eq!(poster.posts.load(Ordering::SeqCst), 0);
+    }
+}
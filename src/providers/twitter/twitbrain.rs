@@ -1,5 +1,10 @@
 use agent_twitter_client::scraper::Scraper;
 use agent_twitter_client::error::TwitterError;
+use agent_twitter_client::api::requests::request_api;
+use agent_twitter_client::api::Method;
+use crate::providers::twitter::rate_limit::RateLimiter;
+use reqwest::header::HeaderMap;
+use serde_json::{json, Value};
 use dotenv::dotenv;
 use std::env;
 use std::sync::Arc;
@@ -80,10 +85,14 @@ impl ToString for LogType {
     }
 }
 
+/// GraphQL endpoint that creates tweets and replies.
+const CREATE_TWEET_URL: &str = "https://twitter.com/i/api/graphql/a1p9RWpkYKBjWv_I3WzS-A/CreateTweet";
+
 #[derive(Clone)]
 pub struct TwitterProvider {
     scraper: Arc<Scraper>,
     log_path: String,
+    rate_limiter: Arc<RateLimiter>,
 }
 
 #[derive(Debug)]
@@ -140,9 +149,45 @@ impl TwitterProvider {
         Ok(Arc::new(Self {
             scraper: Arc::new(scraper),
             log_path,
+            rate_limiter: Arc::new(RateLimiter::new()),
         }))
     }
 
+    /// The posting budget, kept current from the `x-rate-limit-*` headers of each tweet sent.
+    pub fn rate_limiter(&self) -> Arc<RateLimiter> {
+        self.rate_limiter.clone()
+    }
+
+    /// Sends a tweet, or a reply to `reply_to`, the way the scraper's `send_tweet` does but
+    /// keeping the response headers so the rate limiter sees the budget Twitter reports.
+    async fn create_tweet(&self, text: &str, reply_to: Option<&str>) -> Result<Value, TwitterError> {
+        let client = &self.scraper.twitter_client;
+        let mut headers = HeaderMap::new();
+        client.auth.install_headers(&mut headers).await?;
+
+        let mut variables = json!({
+            "tweet_text": text,
+            "dark_request": false,
+            "media": {
+                "media_entities": [],
+                "possibly_sensitive": false
+            },
+            "semantic_annotation_ids": []
+        });
+        if let Some(reply_id) = reply_to {
+            variables["reply"] = json!({ "in_reply_to_tweet_id": reply_id });
+        }
+
+        let body = json!({
+            "variables": variables,
+            "features": create_tweet_features(),
+            "fieldToggles": {}
+        });
+        let (value, response_headers) = request_api::<Value>(&client.client, CREATE_TWEET_URL, headers, Method::POST, Some(body)).await?;
+        self.rate_limiter.update_from_headers(&response_headers);
+        Ok(value)
+    }
+
     pub fn log_activity(&self, log_type: LogType, message: &str) -> std::io::Result<()> {
         let mut file = OpenOptions::new()
             .append(true)
@@ -191,7 +236,7 @@ impl TwitterProvider {
         }
 
         println!("Sending tweet to Twitter...");
-        match self.scraper.send_tweet(content, None, None).await {
+        match self.create_tweet(content, None).await {
             Ok(response) => {
                 // Parse the response to extract the tweet ID
                 if let Ok(json) = serde_json::from_str::<serde_json::Value>(&response.to_string()) {
@@ -224,7 +269,7 @@ impl TwitterProvider {
         println!("Generated reply: {}", content.bright_white());
         
         println!("Sending reply to tweet {}...", tweet_id);
-        match self.scraper.send_tweet(content, Some(tweet_id), None).await {
+        match self.create_tweet(content, Some(tweet_id)).await {
             Ok(response) => {
                 // Parse the response to extract the tweet ID
                 if let Ok(json) = serde_json::from_str::<serde_json::Value>(&response.to_string()) {
@@ -296,3 +341,44 @@ impl TwitterProvider {
         self.log_activity(LogType::Info, message)
     }
 }
+
+/// Feature flags CreateTweet expects, as the scraper sends them.
+fn create_tweet_features() -> Value {
+    json!({
+        "interactive_text_enabled": true,
+        "longform_notetweets_inline_media_enabled": false,
+        "responsive_web_text_conversations_enabled": false,
+        "tweet_with_visibility_results_prefer_gql_limited_actions_policy_enabled": false,
+        "vibe_api_enabled": false,
+        "rweb_lists_timeline_redesign_enabled": true,
+        "responsive_web_graphql_exclude_directive_enabled": true,
+        "verified_phone_label_enabled": false,
+        "creator_subscriptions_tweet_preview_api_enabled": true,
+        "responsive_web_graphql_timeline_navigation_enabled": true,
+        "responsive_web_graphql_skip_user_profile_image_extensions_enabled": false,
+        "tweetypie_unmention_optimization_enabled": true,
+        "responsive_web_edit_tweet_api_enabled": true,
+        "graphql_is_translatable_rweb_tweet_is_translatable_enabled": true,
+        "view_counts_everywhere_api_enabled": true,
+        "longform_notetweets_consumption_enabled": true,
+        "tweet_awards_web_tipping_enabled": false,
+        "freedom_of_speech_not_reach_fetch_enabled": true,
+        "standardized_nudges_misinfo": true,
+        "longform_notetweets_rich_text_read_enabled": true,
+        "responsive_web_enhance_cards_enabled": false,
+        "subscriptions_verification_info_enabled": true,
+        "subscriptions_verification_info_reason_enabled": true,
+        "subscriptions_verification_info_verified_since_enabled": true,
+        "super_follow_badge_privacy_enabled": false,
+        "super_follow_exclusive_tweet_notifications_enabled": false,
+        "super_follow_tweet_api_enabled": false,
+        "super_follow_user_api_enabled": false,
+        "android_graphql_skip_api_media_color_palette": false,
+        "creator_subscriptions_subscription_count_enabled": false,
+        "blue_business_profile_image_shape_enabled": false,
+        "unified_cards_ad_metadata_container_dynamic_card_content_query_enabled": false,
+        "rweb_video_timestamps_enabled": false,
+        "c9s_tweet_anatomy_moderator_badge_enabled": false,
+        "responsive_web_twitter_article_tweet_consumption_enabled": false
+    })
+}
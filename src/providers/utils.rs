@@ -1,20 +1,65 @@
 use anyhow::Result;
+use crate::config::embedding_dim;
 
-/// Returns a placeholder embedding vector for testing purposes.
-/// This should be replaced with proper embeddings in production.
-pub async fn get_placeholder_embedding(_text: &str) -> Result<Vec<f32>> {
-    // Return a dummy embedding vector of size 1536 (standard embedding dimension)
-    Ok(vec![0.0; 1536])
+/// A reproducible unit vector of [`embedding_dim`] values derived from a hash of `text`.
+///
+/// The same text always maps to the same vector and different texts to nearly orthogonal
+/// ones, so similarity search can be exercised without an embedding model. The vectors
+/// carry no meaning: only identical text scores as similar.
+pub fn fake_embedding(text: &str) -> Vec<f32> {
+    // FNV-1a, which unlike `DefaultHasher` is stable across Rust releases
+    let mut seed = text.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+
+    // splitmix64, mapped to [-1, 1)
+    let mut vector: Vec<f32> = (0..embedding_dim())
+        .map(|_| {
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            (z >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+        })
+        .collect();
+
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    vector.iter_mut().for_each(|x| *x /= norm);
+    vector
+}
+
+/// Returns a placeholder embedding for providers without an embedding endpoint.
+/// See [`fake_embedding`]; use a real embedding model for meaningful retrieval.
+pub async fn get_placeholder_embedding(text: &str) -> Result<Vec<f32>> {
+    Ok(fake_embedding(text))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
     #[tokio::test]
     async fn test_placeholder_embedding() {
         let result = get_placeholder_embedding("test text").await.unwrap();
-        assert_eq!(result.len(), 1536);
-        assert!(result.iter().all(|&x| x == 0.0));
+        assert_eq!(result.len(), embedding_dim());
+        assert!(result.iter().any(|&x| x != 0.0));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_fake_embeddings_match_only_identical_text() {
+        let first = fake_embedding("the borrow checker rejected my code");
+        let again = fake_embedding("the borrow checker rejected my code");
+        let other = fake_embedding("tomato soup recipe");
+
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+        assert!((cosine(&first, &again) - 1.0).abs() < 1e-4);
+        assert!(cosine(&first, &other).abs() < 0.2);
+        assert_ne!(fake_embedding(""), fake_embedding(" "));
+    }
+}
@@ -1,9 +1,27 @@
-use super::WebCrawler;
+use super::{LinkOptions, PageContent, Robots, WebCrawler};
 use crate::personality::PersonalityProfile;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Pages `crawl_recursive` visits when the caller doesn't say.
+pub const DEFAULT_MAX_PAGES: usize = 20;
+/// Pages followed from each source when `research` goes deeper than the search results.
+const RESEARCH_PAGES_PER_SOURCE: usize = 5;
+
+/// Reported by [`WebCrawlerManager::research_topic`] after each source is visited.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResearchProgress {
+    pub url: String,
+    /// Sources visited so far, including this one
+    pub visited: usize,
+    pub total: usize,
+    /// Sources that returned content so far
+    pub found: usize,
+}
+
 pub struct WebCrawlerManager {
     crawler: Arc<Mutex<WebCrawler>>,
     profile: PersonalityProfile,
@@ -25,22 +43,203 @@ impl WebCrawlerManager {
         Ok(page.text)
     }
 
-    pub async fn research_topic(&self, topic: &str) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    /// Visits the search results for `topic`, calling `on_progress` after each one. With a
+    /// `depth` above 0, also follows each source's links on its own site that many levels.
+    pub async fn research_topic(
+        &self,
+        topic: &str,
+        depth: usize,
+        on_progress: &mut (dyn FnMut(&ResearchProgress) + Send),
+    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
         let crawler = self.crawler.lock().await;
         let search_results = crawler.search(topic).await?;
-        
-        let mut findings = Vec::new();
-        for url in search_results {
+        Ok(visit_sources(&crawler, search_results, depth, on_progress).await)
+    }
+
+    /// Pages reachable from `start_url` on the same site, breadth first: the start page,
+    /// then the pages it links to, and so on down to `max_depth` links away, stopping after
+    /// `max_pages` URLs have been tried. Each URL is visited once and pages robots.txt
+    /// disallows are skipped, though they count toward `max_pages`.
+    pub async fn crawl_recursive(
+        &self,
+        start_url: &str,
+        max_depth: usize,
+        max_pages: usize,
+    ) -> Result<Vec<PageContent>, Box<dyn std::error::Error + Send + Sync>> {
+        let crawler = self.crawler.lock().await;
+        crawl(&crawler, start_url, max_depth, max_pages).await
+    }
+
+    /// The absolute URLs `url` links to, filtered by `options`.
+    pub async fn extract_links(&self, url: &str, options: &LinkOptions) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        // A bad pattern should fail before the page is fetched
+        options.filter(url, &[])?;
+        let crawler = self.crawler.lock().await;
+        let page = crawler.visit_page(url).await?;
+        Ok(options.filter(&page.url, &page.links)?)
+    }
+}
+
+/// The text of each of `urls` that could be fetched, and of pages up to `depth` links from
+/// them, reporting progress after every source.
+async fn visit_sources(
+    crawler: &WebCrawler,
+    urls: Vec<String>,
+    depth: usize,
+    on_progress: &mut (dyn FnMut(&ResearchProgress) + Send),
+) -> Vec<String> {
+    let total = urls.len();
+    let mut findings = Vec::new();
+    for (i, url) in urls.into_iter().enumerate() {
+        if depth == 0 {
             if let Ok(page) = crawler.visit_page(&url).await {
                 findings.push(page.text);
             }
+        } else if let Ok(pages) = crawl(crawler, &url, depth, RESEARCH_PAGES_PER_SOURCE).await {
+            findings.extend(pages.into_iter().map(|page| page.text));
         }
-        Ok(findings)
+        on_progress(&ResearchProgress { url, visited: i + 1, total, found: findings.len() });
     }
+    findings
+}
 
-    pub async fn extract_links(&self, url: &str) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
-        let crawler = self.crawler.lock().await;
-        let page = crawler.visit_page(url).await?;
-        Ok(page.links)
+/// Breadth-first crawl behind [`WebCrawlerManager::crawl_recursive`]. Fails only if the
+/// start page can't be fetched; later pages that fail are skipped.
+async fn crawl(
+    crawler: &WebCrawler,
+    start_url: &str,
+    max_depth: usize,
+    max_pages: usize,
+) -> Result<Vec<PageContent>, Box<dyn Error + Send + Sync>> {
+    let follow = LinkOptions { same_domain_only: true, dedupe: true, ..Default::default() };
+    let start_url = url::Url::parse(start_url)?.to_string();
+    let mut robots: HashMap<String, Robots> = HashMap::new();
+    // Every URL ever queued, so cycles and pages linked from many places are visited once
+    let mut seen = HashSet::from([start_url.clone()]);
+    let mut queue = VecDeque::from([(start_url.clone(), 0)]);
+    let mut pages = Vec::new();
+    // URLs tried, including those that failed or robots.txt disallows, so a site full of
+    // broken or forbidden links can't keep the crawl going
+    let mut tried = 0;
+
+    while let Some((url, depth)) = queue.pop_front() {
+        if tried >= max_pages {
+            break;
+        }
+        tried += 1;
+        let host = url::Url::parse(&url).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default();
+        if !robots.contains_key(&host) {
+            robots.insert(host.clone(), crawler.robots(&url).await);
+        }
+        if !robots[&host].allows(&url) {
+            log::debug!("robots.txt disallows {}", url);
+            continue;
+        }
+
+        let page = match crawler.visit_page(&url).await {
+            Ok(page) => page,
+            Err(e) if url == start_url => return Err(e),
+            Err(e) => {
+                log::debug!("Skipping {}: {}", url, e);
+                continue;
+            }
+        };
+        // A redirect counts as a visit to where it led
+        seen.insert(page.url.clone());
+        if depth < max_depth {
+            for link in follow.filter(&page.url, &page.links)? {
+                if seen.insert(link.clone()) {
+                    queue.push_back((link, depth + 1));
+                }
+            }
+        }
+        pages.push(page);
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_progress_is_reported_per_source() {
+        // Nothing listens on port 1, so every visit fails fast but is still reported
+        let urls = vec!["http://127.0.0.1:1/a".to_string(), "http://127.0.0.1:1/b".to_string()];
+        let mut reported = Vec::new();
+        let findings = visit_sources(&WebCrawler::new().unwrap(), urls, 0, &mut |progress| reported.push(progress.clone())).await;
+
+        assert!(findings.is_empty());
+        assert_eq!(reported.iter().map(|p| (p.url.as_str(), p.visited, p.total, p.found)).collect::<Vec<_>>(), vec![
+            ("http://127.0.0.1:1/a", 1, 2, 0),
+            ("http://127.0.0.1:1/b", 2, 2, 0),
+        ]);
+    }
+
+    /// Serves a small site on localhost and returns its base URL and the paths requested:
+    /// / links to /a, /b and an outside page; /a to /a/deep and back to /; /a/deep to
+    /// /a/deeper; /b to /private, which robots.txt disallows.
+    async fn mock_site() -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requested = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = requested.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else { break };
+                let mut request = vec![0; 2048];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                log.lock().unwrap().push(path.clone());
+                let (content_type, body) = match path.as_str() {
+                    "/robots.txt" => ("text/plain", "User-agent: *\nDisallow: /private\n".to_string()),
+                    "/" => ("text/html", r#"<p>home</p><a href="/a">A</a><a href="b">B</a><a href="https://elsewhere.example/">Out</a>"#.to_string()),
+                    "/a" => ("text/html", r#"<p>page a</p><a href="/a/deep">Deep</a><a href="/">Home</a><a href="/a#top">Self</a>"#.to_string()),
+                    "/a/deep" => ("text/html", r#"<p>deep</p><a href="/a/deeper">Deeper</a>"#.to_string()),
+                    "/a/deeper" => ("text/html", "<p>deeper</p>".to_string()),
+                    "/b" => ("text/html", r#"<p>page b</p><a href="/private">Private</a>"#.to_string()),
+                    _ => ("text/html", "<p>private</p>".to_string()),
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    content_type, body.len(), body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        (base, requested)
+    }
+
+    #[tokio::test]
+    async fn test_crawl_honors_depth_cap_and_robots() {
+        let (base, requested) = mock_site().await;
+        let crawler = WebCrawler::new().unwrap().with_delay(std::time::Duration::ZERO);
+        let paths = |pages: &[PageContent]| pages.iter()
+            .map(|page| page.url.trim_start_matches(&base).to_string())
+            .collect::<Vec<_>>();
+
+        let pages = crawl(&crawler, &base, 1, 10).await.unwrap();
+        assert_eq!(paths(&pages), vec!["/", "/a", "/b"]);
+
+        // /private is disallowed and the link back to / is not followed again
+        let pages = crawl(&crawler, &base, 2, 10).await.unwrap();
+        assert_eq!(paths(&pages), vec!["/", "/a", "/b", "/a/deep"]);
+        assert!(!requested.lock().unwrap().contains(&"/private".to_string()));
+
+        let pages = crawl(&crawler, &base, 5, 3).await.unwrap();
+        assert_eq!(paths(&pages), vec!["/", "/a", "/b"]);
+        assert_eq!(pages[0].text, "- home\n");
+
+        // The disallowed /private uses up the last of five tries before /a/deeper
+        let pages = crawl(&crawler, &base, 3, 5).await.unwrap();
+        assert_eq!(paths(&pages), vec!["/", "/a", "/b", "/a/deep"]);
+
+        assert!(crawl(&crawler, "http://127.0.0.1:1/", 1, 5).await.is_err());
+
+        // Each page is fetched once per crawl
+        let requested = requested.lock().unwrap();
+        assert_eq!(requested.iter().filter(|path| *path == "/a").count(), 4);
     }
 }
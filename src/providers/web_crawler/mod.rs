@@ -1,4 +1,4 @@
 mod new_crawler;
 pub mod crawler_manager;
 
-pub use new_crawler::{WebCrawler, PageContent};
\ No newline at end of file
+pub use new_crawler::{LinkOptions, Robots, WebCrawler, PageContent};
\ No newline at end of file
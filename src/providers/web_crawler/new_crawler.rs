@@ -1,8 +1,11 @@
+use regex::Regex;
 use reqwest::Client;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::error::Error;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::time;
 use urlencoding;
 
@@ -10,11 +13,22 @@ const DEFAULT_TIMEOUT: u64 = 5;
 const MAX_REDIRECTS: usize = 2;
 const RATE_LIMIT_DELAY: u64 = 1;
 const USER_AGENT: &str = "Mozilla/5.0 (compatible; AIAgent/1.0)";
+/// The name robots.txt rules address this crawler by.
+const ROBOTS_AGENT: &str = "aiagent";
+/// Responses larger than this are refused, or cut off when they don't say their length.
+const MAX_RESPONSE_BYTES: usize = 5 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct WebCrawler {
     client: Client,
-    last_visit: std::time::Instant,
+    /// When the last request went out, or is scheduled to, shared by clones
+    last_visit: Arc<Mutex<Option<Instant>>>,
+    /// Least time between two requests
+    delay: Duration,
+    /// Most characters of text kept from a page
+    max_content_chars: usize,
+    /// Whether responses that aren't HTML or text (PDFs, images) are read anyway
+    allow_non_html: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -25,6 +39,170 @@ pub struct PageContent {
     pub links: Vec<String>,
 }
 
+/// Which of a page's links `extract_links` returns.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkOptions {
+    /// Only links to the page's own host (ignoring a leading `www.`)
+    pub same_domain_only: bool,
+    /// Each URL once, in the order first seen
+    pub dedupe: bool,
+    /// Regexes; links matching any of them are left out
+    pub exclude_patterns: Vec<String>,
+}
+
+impl LinkOptions {
+    /// Splits `links` arguments, `<url> [--same-domain] [--dedupe] [--exclude <regex>]...`,
+    /// into the URL and its options.
+    pub fn parse(args: &str) -> Result<(String, Self), String> {
+        let mut words = args.split_whitespace();
+        let url = words.next().filter(|w| !w.starts_with("--")).ok_or("Please provide a URL to extract links from.")?;
+        let mut options = Self::default();
+        while let Some(word) = words.next() {
+            match word {
+                "--same-domain" => options.same_domain_only = true,
+                "--dedupe" => options.dedupe = true,
+                "--exclude" => options.exclude_patterns.push(
+                    words.next().ok_or("--exclude needs a pattern")?.to_string(),
+                ),
+                other => return Err(format!("Unknown option for links: {}", other)),
+            }
+        }
+        Ok((url.to_string(), options))
+    }
+
+    /// `links` found on `base` that these options let through.
+    pub fn filter(&self, base: &str, links: &[String]) -> Result<Vec<String>, regex::Error> {
+        let excluded = self.exclude_patterns.iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        let base_host = url::Url::parse(base).ok().and_then(|u| u.host_str().map(site_host));
+        let mut seen = HashSet::new();
+
+        Ok(links.iter()
+            .filter(|link| {
+                !self.same_domain_only || url::Url::parse(link).ok()
+                    .and_then(|u| u.host_str().map(site_host))
+                    .is_some_and(|host| Some(&host) == base_host.as_ref())
+            })
+            .filter(|link| !excluded.iter().any(|pattern| pattern.is_match(link)))
+            .filter(|link| !self.dedupe || seen.insert(link.as_str()))
+            .cloned()
+            .collect())
+    }
+}
+
+/// The `Allow` and `Disallow` rules of a robots.txt that apply to this crawler: those for
+/// its own name if the file names it, otherwise those for `*`. Paths are matched as
+/// prefixes, with `*` standing for any characters and a trailing `$` anchoring the end,
+/// and the longest match decides, with `Allow` winning a tie.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Robots {
+    /// (allowed, path pattern)
+    rules: Vec<(bool, String)>,
+}
+
+impl Robots {
+    pub fn parse(text: &str) -> Self {
+        let (mut own, mut any) = (Vec::new(), Vec::new());
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((field, value)) = line.split_once(':') else { continue };
+            let value = value.trim();
+            match field.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    // A user-agent after rules starts the next group
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_lowercase());
+                }
+                field @ ("allow" | "disallow") => {
+                    in_rules = true;
+                    // An empty Disallow allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+                    let rule = (field == "allow", value.to_string());
+                    if agents.iter().any(|agent| agent.contains(ROBOTS_AGENT)) {
+                        own.push(rule.clone());
+                    }
+                    if agents.iter().any(|agent| agent == "*") {
+                        any.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let named = text.to_lowercase().lines()
+            .any(|line| line.trim_start().starts_with("user-agent") && line.contains(ROBOTS_AGENT));
+        Self { rules: if named { own } else { any } }
+    }
+
+    /// Whether `url`'s path (and query) may be crawled.
+    pub fn allows(&self, url: &str) -> bool {
+        let Ok(url) = url::Url::parse(url) else { return false };
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        self.rules.iter()
+            .filter(|(_, pattern)| robots_match(pattern, &path))
+            .max_by_key(|(allowed, pattern)| (pattern.len(), *allowed))
+            .is_none_or(|(allowed, _)| *allowed)
+    }
+}
+
+/// Whether robots.txt `pattern` matches the start of `path`: `*` matches any run of
+/// characters and a trailing `$` requires the match to reach the end of `path`.
+fn robots_match(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        // The last part of an anchored pattern has to end the path; a `*` before it takes the rest
+        if anchored && i + 1 == parts.len() {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// `host` without a leading `www.`, so `www.example.com` and `example.com` count as one site.
+fn site_host(host: &str) -> String {
+    host.trim_start_matches("www.").to_lowercase()
+}
+
+/// Every `a[href]` on the page that leads to a web page, resolved against `base` into an
+/// absolute URL without its `#fragment`.
+fn resolve_links(document: &Html, base: &str) -> Vec<String> {
+    let base = url::Url::parse(base).ok();
+    let link_selector = Selector::parse("a[href]").unwrap();
+    document
+        .select(&link_selector)
+        .filter_map(|element| element.value().attr("href"))
+        .filter_map(|href| match &base {
+            Some(base) => base.join(href.trim()).ok(),
+            None => url::Url::parse(href.trim()).ok(),
+        })
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(|mut url| {
+            url.set_fragment(None);
+            url.to_string()
+        })
+        .collect()
+}
+
 impl WebCrawler {
     pub fn new() -> Result<Self, Box<dyn Error + Send + Sync>> {
         let client = Client::builder()
@@ -35,14 +213,58 @@ impl WebCrawler {
 
         Ok(Self {
             client,
-            last_visit: std::time::Instant::now(),
+            last_visit: Arc::new(Mutex::new(None)),
+            delay: Duration::from_secs(RATE_LIMIT_DELAY),
+            max_content_chars: crate::config::crawler_max_content(),
+            allow_non_html: false,
         })
     }
 
+    /// Keeps at most `max_content_chars` characters of each page's text.
+    pub fn with_max_content_chars(mut self, max_content_chars: usize) -> Self {
+        self.max_content_chars = max_content_chars.max(1);
+        self
+    }
+
+    /// Waits `delay` between requests instead of a second.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Reads responses of any content type instead of refusing those that aren't HTML.
+    pub fn allow_non_html(mut self, allow: bool) -> Self {
+        self.allow_non_html = allow;
+        self
+    }
+
+    /// Waits until `delay` has passed since the previous request, taking this request's slot
+    /// first so concurrent callers queue up instead of all going at once.
     async fn rate_limit(&self) {
-        let elapsed = self.last_visit.elapsed();
-        if elapsed < Duration::from_secs(RATE_LIMIT_DELAY) {
-            time::sleep(Duration::from_secs(RATE_LIMIT_DELAY) - elapsed).await;
+        let wait = {
+            let mut last_visit = self.last_visit.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let next = last_visit.map_or(now, |last| (last + self.delay).max(now));
+            *last_visit = Some(next);
+            next - now
+        };
+        if !wait.is_zero() {
+            time::sleep(wait).await;
+        }
+    }
+
+    /// The robots.txt rules of the site `url` is on; a site without a readable robots.txt
+    /// allows everything.
+    pub async fn robots(&self, url: &str) -> Robots {
+        let Some(robots_url) = url::Url::parse(url).ok().and_then(|u| u.join("/robots.txt").ok()) else {
+            return Robots::default();
+        };
+        self.rate_limit().await;
+        match self.client.get(robots_url).send().await {
+            Ok(response) if response.status().is_success() => {
+                Robots::parse(&response.text().await.unwrap_or_default())
+            }
+            _ => Robots::default(),
         }
     }
 
@@ -71,13 +293,29 @@ impl WebCrawler {
     pub async fn visit_page(&self, url: &str) -> Result<PageContent, Box<dyn Error + Send + Sync>> {
         self.rate_limit().await;
 
-        let response = self.client
+        let mut response = self.client
             .get(url)
             .send()
             .await?;
 
+        let header = |name| response.headers().get(name).and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok());
+        check_response(
+            header(reqwest::header::CONTENT_TYPE),
+            header(reqwest::header::CONTENT_LENGTH).and_then(|l| l.trim().parse().ok()),
+            self.allow_non_html,
+        )?;
+
         let final_url = response.url().to_string();
-        let html = response.text().await?;
+        // Read no more than the cap even when the server didn't say how much is coming
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_RESPONSE_BYTES {
+                body.truncate(MAX_RESPONSE_BYTES);
+                break;
+            }
+        }
+        let html = String::from_utf8_lossy(&body);
         let document = Html::parse_document(&html);
 
         // Extract title
@@ -87,24 +325,9 @@ impl WebCrawler {
             .next()
             .map(|title| title.text().collect::<String>());
 
-        // Extract main content
-        let content_selector = Selector::parse("p, h1, h2, h3, ul, ol, li").unwrap();
-        let mut text = String::new();
-        for element in document.select(&content_selector) {
-            let element_text = element.text().collect::<Vec<_>>().join(" ");
-            if !element_text.trim().is_empty() {
-                text.push_str(&format!("- {}\n", element_text));
-            }
-        }
+        let text = extract_text(&document, self.max_content_chars);
 
-        // Extract links
-        let link_selector = Selector::parse("a[href]").unwrap();
-        let links: Vec<String> = document
-            .select(&link_selector)
-            .filter_map(|element| element.value().attr("href"))
-            .filter(|href| href.starts_with("http"))
-            .map(|href| href.to_string())
-            .collect();
+        let links = resolve_links(&document, &final_url);
 
         Ok(PageContent {
             url: final_url,
@@ -114,3 +337,194 @@ impl WebCrawler {
         })
     }
 }
+
+/// Refuses a response whose headers show it isn't a page worth reading: a content type
+/// other than HTML or text unless `allow_non_html`, or a body over `MAX_RESPONSE_BYTES`.
+fn check_response(content_type: Option<&str>, content_length: Option<u64>, allow_non_html: bool) -> Result<(), String> {
+    if let Some(content_type) = content_type {
+        let mime = content_type.split(';').next().unwrap_or_default().trim().to_lowercase();
+        let readable = mime.starts_with("text/") || mime == "application/xhtml+xml" || mime.is_empty();
+        if !readable && !allow_non_html {
+            return Err(format!("Not an HTML page ({})", mime));
+        }
+    }
+    match content_length {
+        Some(length) if length > MAX_RESPONSE_BYTES as u64 => {
+            Err(format!("Page too large ({} bytes, limit {})", length, MAX_RESPONSE_BYTES))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The page's headings, paragraphs and list items, one per line, stopping once
+/// `max_chars` is reached and cutting back to the last full sentence.
+fn extract_text(document: &Html, max_chars: usize) -> String {
+    let content_selector = Selector::parse("p, h1, h2, h3, ul, ol, li").unwrap();
+    let mut text = String::new();
+    let mut chars = 0;
+    for element in document.select(&content_selector) {
+        let element_text = element.text().collect::<Vec<_>>().join(" ");
+        if !element_text.trim().is_empty() {
+            let line = format!("- {}\n", element_text);
+            chars += line.chars().count();
+            text.push_str(&line);
+            if chars > max_chars {
+                return truncate_at_sentence(&text, max_chars);
+            }
+        }
+    }
+    text
+}
+
+/// `text` cut to at most `max_chars` characters at the end of a sentence (or failing that,
+/// a word), followed by a note that the page went on.
+fn truncate_at_sentence(text: &str, max_chars: usize) -> String {
+    let end = text.char_indices().nth(max_chars).map_or(text.len(), |(i, _)| i);
+    let head = &text[..end];
+    let sentence_end = head.char_indices()
+        .filter(|&(i, c)| {
+            matches!(c, '.' | '!' | '?' | '\n')
+                && head[i + c.len_utf8()..].chars().next().is_none_or(char::is_whitespace)
+        })
+        .map(|(i, c)| i + c.len_utf8())
+        .next_back();
+    // A sentence ending early on would throw away most of the page
+    let cut = match sentence_end {
+        Some(i) if i >= end / 2 => i,
+        _ => head.rfind(char::is_whitespace).unwrap_or(end),
+    };
+    format!("{}\n[Truncated: page text continues past {} characters]\n", head[..cut].trim_end(), max_chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_long_page_is_cut_at_a_sentence() {
+        let paragraph = "Rust is fast. It is also safe! Is it fun? Very much so";
+        let html = format!("<h1>Rust</h1>{}", format!("<p>{}</p>", paragraph).repeat(50));
+        let document = Html::parse_document(&html);
+
+        let full = extract_text(&document, 100_000);
+        assert!(full.starts_with("- Rust\n- Rust is fast.") && !full.contains("[Truncated"));
+
+        let text = extract_text(&document, 60);
+        let (kept, note) = text.split_once("\n[Truncated").unwrap();
+        assert_eq!(kept, "- Rust\n- Rust is fast. It is also safe! Is it fun?");
+        assert_eq!(note, ": page text continues past 60 characters]\n");
+
+        // Multi-byte text is cut on a character boundary
+        assert!(truncate_at_sentence("żółw ".repeat(40).as_str(), 33).starts_with("żółw żółw"));
+    }
+
+    #[test]
+    fn test_non_html_and_oversized_responses_are_refused() {
+        assert!(check_response(Some("text/html; charset=utf-8"), Some(1_000), false).is_ok());
+        assert!(check_response(None, None, false).is_ok());
+        assert!(check_response(Some("application/pdf"), Some(1_000), false).unwrap_err().contains("application/pdf"));
+        assert!(check_response(Some("image/png"), None, false).is_err());
+        assert!(check_response(Some("application/pdf"), Some(1_000), true).is_ok());
+        assert!(check_response(Some("text/html"), Some(MAX_RESPONSE_BYTES as u64 + 1), false).unwrap_err().contains("too large"));
+    }
+
+    #[test]
+    fn test_relative_links_are_resolved() {
+        let document = Html::parse_document(r#"
+            <a href="/about">About</a>
+            <a href="guide.html#install">Guide</a>
+            <a href="../up">Up</a>
+            <a href="//cdn.example.net/lib.js">CDN</a>
+            <a href="https://Other.org:443/x">Other</a>
+            <a href="mailto:me@example.com">Mail</a>
+            <a href="javascript:void(0)">Nothing</a>
+        "#);
+        assert_eq!(resolve_links(&document, "https://example.com/docs/index.html"), vec![
+            "https://example.com/about",
+            "https://example.com/docs/guide.html",
+            "https://example.com/up",
+            "https://cdn.example.net/lib.js",
+            "https://other.org/x",
+        ]);
+    }
+
+    #[test]
+    fn test_links_are_filtered_by_domain_and_pattern() {
+        let links: Vec<String> = [
+            "https://example.com/a",
+            "https://www.example.com/b",
+            "https://example.com/a",
+            "https://tracker.io/pixel?id=1",
+            "https://twitter.com/share?url=x",
+            "https://example.com/share/c",
+        ].iter().map(|l| l.to_string()).collect();
+        let base = "https://example.com/start";
+
+        assert_eq!(LinkOptions::default().filter(base, &links).unwrap(), links);
+
+        let (url, options) = LinkOptions::parse("https://example.com/start --same-domain --dedupe --exclude /share/").unwrap();
+        assert_eq!(url, base);
+        assert_eq!(options.filter(base, &links).unwrap(), vec!["https://example.com/a", "https://www.example.com/b"]);
+
+        let options = LinkOptions { exclude_patterns: vec![r"tracker\.io|twitter\.com".to_string()], ..Default::default() };
+        assert_eq!(options.filter(base, &links).unwrap().len(), 4);
+
+        assert!(LinkOptions { exclude_patterns: vec!["(".to_string()], ..Default::default() }.filter(base, &links).is_err());
+        assert!(LinkOptions::parse("https://example.com --exclude").is_err());
+        assert!(LinkOptions::parse("--dedupe").is_err());
+    }
+
+    #[test]
+    fn test_robots_rules_for_this_crawler() {
+        let robots = Robots::parse("# site rules\nUser-agent: *\nDisallow: /private\nAllow: /private/open\nDisallow:\n\nUser-agent: BadBot\nDisallow: /\n");
+        assert!(robots.allows("https://example.com/docs"));
+        assert!(!robots.allows("https://example.com/private/notes"));
+        assert!(robots.allows("https://example.com/private/open/page"));
+
+        // Rules naming this crawler replace the ones for everyone
+        let named = Robots::parse("User-agent: *\nDisallow: /\n\nUser-agent: Googlebot\nUser-agent: AIAgent\nDisallow: /search?\n");
+        assert!(named.allows("https://example.com/"));
+        assert!(!named.allows("https://example.com/search?q=x"));
+        assert!(Robots::default().allows("https://example.com/anything"));
+
+        let wildcards = Robots::parse("User-agent: *\nDisallow: /*.pdf$\nDisallow: /*/drafts/\nAllow: /public/*.pdf$\n");
+        assert!(!wildcards.allows("https://example.com/files/report.pdf"));
+        assert!(wildcards.allows("https://example.com/files/report.pdf?download=1"));
+        assert!(wildcards.allows("https://example.com/public/report.pdf"));
+        assert!(!wildcards.allows("https://example.com/team/drafts/plan"));
+        assert!(wildcards.allows("https://example.com/drafts/plan"));
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_spaced_by_the_delay() {
+        let crawler = WebCrawler::new().unwrap().with_delay(Duration::from_millis(50));
+        let start = Instant::now();
+        for _ in 0..3 {
+            crawler.rate_limit().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_visit_page_refuses_a_pdf() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/report.pdf", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await;
+            let body = "%PDF-1.4 not a web page";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/pdf\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let crawler = WebCrawler::new().unwrap();
+        let error = crawler.visit_page(&url).await.unwrap_err();
+        assert_eq!(error.to_string(), "Not an HTML page (application/pdf)");
+    }
+}